@@ -0,0 +1,530 @@
+//! Shared command-line arguments.
+
+use crate::display::Format;
+use crate::summary::RunSummary;
+use anyhow::Context;
+use chrono_tz::Tz;
+use clap::Args;
+use paracas_daemon::{AggregationSettings, ReturnsMode};
+use paracas_lib::{
+    BasicAuth, CacheConfig, CandleStyle, Certificate, ClientConfig, DEFAULT_MAX_MEDIAN_RATIO,
+    DataSource, DateRange, ExpiryPolicy, FailureInjection, GapFill, HeaderMap, HeaderName,
+    HeaderValue, Indicator, Instrument, InstrumentAliases, InvalidTickAction, Jitter,
+    LateDataRetry, ParquetCodec, ParquetSettings, ParquetStatistics, PriceEncoding, PriceSource,
+    PriceValidation, RateLimit, RateLimiter, RetryPolicy, SlowRequestHook, StreamOptions,
+    Timeframe, TimestampPrecision, TlsBackend, load_certificates, url,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Environment variable holding basic auth credentials as "user:password".
+const BASIC_AUTH_ENV: &str = "PARACAS_BASIC_AUTH";
+
+/// Options controlling how data is fetched, shared by download commands.
+#[derive(Args, Clone, Debug)]
+pub(crate) struct FetchArgs {
+    /// Cache directory, set from the global `--cache-dir` flag or config file
+    #[arg(skip)]
+    pub(crate) cache_dir: Option<PathBuf>,
+
+    /// Read raw bi5 files from this directory (laid out like the cache) instead of downloading
+    #[arg(long)]
+    pub(crate) source_dir: Option<PathBuf>,
+
+    /// Re-download cached hours from the trailing N days, which may still be revised
+    #[arg(long, default_value = "2")]
+    pub(crate) revision_days: u32,
+
+    /// Request forex weekend hours too (skipped by default, as they never have data)
+    #[arg(long)]
+    pub(crate) include_weekends: bool,
+
+    /// Only request hours in which the instrument trades (e.g. the cash session for stocks)
+    #[arg(long)]
+    pub(crate) trading_hours_only: bool,
+
+    /// Check tick prices (zero, bid above ask, far off the hour median) and flag or drop bad
+    /// ticks
+    #[arg(long, value_name = "ACTION")]
+    pub(crate) validate_prices: Option<InvalidTickAction>,
+
+    /// Re-check empty market hours next to hours with data after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    pub(crate) retry_late: Option<u64>,
+
+    /// Cap the decompression window per in-flight hour (in MiB)
+    #[arg(long, value_name = "MIB")]
+    pub(crate) decompress_limit: Option<usize>,
+
+    /// Download up to N hours ahead of the writer, so a slow writer does not stall downloads
+    #[arg(long, value_name = "HOURS")]
+    pub(crate) prefetch: Option<usize>,
+
+    /// Negotiate HTTP/2 and multiplex requests over shared connections
+    #[arg(long)]
+    pub(crate) http2: bool,
+
+    /// Maximum concurrent requests per connection with --http2
+    #[arg(long, value_name = "N", default_value = "100")]
+    pub(crate) max_streams: usize,
+
+    /// Limit the number of TCP connections (requests wait for a free one)
+    #[arg(long, value_name = "N")]
+    pub(crate) max_connections: Option<usize>,
+
+    /// Extra request header as "Name: value" (repeatable)
+    #[arg(long = "header", value_name = "HEADER", value_parser = parse_header)]
+    pub(crate) headers: Vec<(HeaderName, HeaderValue)>,
+
+    /// Basic auth credentials as "user:password" [env: PARACAS_BASIC_AUTH]
+    #[arg(long, value_name = "USER:PASSWORD", value_parser = parse_basic_auth)]
+    pub(crate) basic_auth: Option<BasicAuth>,
+
+    /// Connect to fixed IPs instead of resolving a host, as "[HOST=]IP[,IP...]" (repeatable).
+    /// HOST defaults to the Dukascopy data feed
+    #[arg(long = "resolve", value_name = "[HOST=]IPS", value_parser = parse_resolve)]
+    pub(crate) dns_overrides: Vec<(String, Vec<IpAddr>)>,
+
+    /// TLS implementation: auto, rustls or native (as compiled in)
+    #[arg(long, value_name = "BACKEND", default_value = "auto")]
+    pub(crate) tls_backend: TlsBackend,
+
+    /// Also trust the CA certificates in this PEM or DER file, e.g. of a TLS-intercepting
+    /// proxy (repeatable)
+    #[arg(long = "ca-cert", value_name = "FILE", value_parser = parse_ca_cert)]
+    pub(crate) ca_certs: Vec<Vec<Certificate>>,
+
+    /// Trust only the --ca-cert certificates, not the TLS backend's built-in roots
+    #[arg(long, requires = "ca_certs")]
+    pub(crate) only_ca_certs: bool,
+
+    /// User agent to send; repeat to rotate through several, one per request
+    #[arg(long = "user-agent", value_name = "AGENT")]
+    pub(crate) user_agents: Vec<String>,
+
+    /// Use the computed backoff even when the server sends a Retry-After header
+    #[arg(long)]
+    pub(crate) ignore_retry_after: bool,
+
+    /// Limit requests per second across all concurrent downloads
+    #[arg(long, value_name = "N")]
+    pub(crate) max_rps: Option<f64>,
+
+    /// Limit download bandwidth across all concurrent downloads (in KiB/s)
+    #[arg(long, value_name = "KIB")]
+    pub(crate) max_kib_per_sec: Option<u64>,
+
+    /// Rate limiter shared by every client, built from the limits above
+    #[arg(skip)]
+    pub(crate) rate_limiter: Option<RateLimiter>,
+
+    /// Produce byte-identical output for identical inputs (ordered hours, no timestamps)
+    #[arg(long)]
+    pub(crate) deterministic: bool,
+
+    /// Write opaque aliases instead of instrument IDs, keeping the mapping in this key file
+    #[arg(long, value_name = "FILE")]
+    pub(crate) alias_file: Option<PathBuf>,
+
+    /// Sample process memory and in-flight downloads, and report the peaks
+    #[arg(long)]
+    pub(crate) report_memory: bool,
+
+    /// Warn about HTTP requests that take longer than this (in milliseconds)
+    #[arg(long, value_name = "MS")]
+    pub(crate) slow_request_ms: Option<u64>,
+
+    /// Write <output>.summary.json with parameters, timings, hour statistics and checksums
+    #[arg(long)]
+    pub(crate) summary: bool,
+
+    /// Development only: make a share of requests fail, e.g. "rate=0.05,kind=5xx"
+    /// (kinds: 5xx, 429, timeout, corrupt; optional seed=N)
+    #[arg(long, value_name = "SPEC", hide = true)]
+    pub(crate) inject_failures: Option<FailureInjection>,
+}
+
+impl FetchArgs {
+    /// Set the cache directory resolved from the global flags and config.
+    pub(crate) fn with_cache_dir(&self, cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            cache_dir,
+            ..self.clone()
+        }
+    }
+
+    /// Create the rate limiter shared by every client built from these args.
+    pub(crate) fn with_rate_limiter(mut self) -> Self {
+        let limit = RateLimit {
+            requests_per_second: self.max_rps,
+            bytes_per_second: self.max_kib_per_sec.map(|kib| kib * 1024),
+        };
+        self.rate_limiter = (!limit.is_unlimited()).then(|| RateLimiter::new(limit));
+        self
+    }
+
+    /// Start recording a run summary, or return `None` without `--summary`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn run_summary(
+        &self,
+        command: &'static str,
+        instrument: &Instrument,
+        alias: Option<&str>,
+        range: DateRange,
+        timeframe: Timeframe,
+        format: Format,
+        concurrency: usize,
+    ) -> Option<RunSummary> {
+        self.summary.then(|| {
+            RunSummary::start(command, instrument.id(), alias, range, timeframe, format)
+                .with_parameter("concurrency", concurrency)
+                .with_parameter("deterministic", self.deterministic)
+                .with_parameter("include_weekends", self.include_weekends)
+                .with_parameter("trading_hours_only", self.trading_hours_only)
+                .with_parameter("validate_prices", self.validate_prices.map(|a| a.as_str()))
+                .with_parameter("retry_late_secs", self.retry_late)
+                .with_parameter("cache", self.cache_dir.is_some())
+                .with_parameter("source_dir", &self.source_dir)
+        })
+    }
+
+    /// Load the alias key file, assign aliases to `instrument_ids` and save it.
+    ///
+    /// Returns `None` if aliasing is disabled.
+    pub(crate) fn aliases<'a>(
+        &self,
+        instrument_ids: impl IntoIterator<Item = &'a str>,
+    ) -> anyhow::Result<Option<InstrumentAliases>> {
+        let Some(path) = &self.alias_file else {
+            return Ok(None);
+        };
+        let mut aliases = InstrumentAliases::load(path)
+            .with_context(|| format!("Failed to read alias file {}", path.display()))?;
+        for id in instrument_ids {
+            aliases.alias(id);
+        }
+        aliases
+            .save(path)
+            .with_context(|| format!("Failed to write alias file {}", path.display()))?;
+        Ok(Some(aliases))
+    }
+
+    /// Build the download client configuration.
+    pub(crate) fn client_config(&self, concurrency: usize) -> ClientConfig {
+        let cache = self.cache_dir.as_ref().map(|dir| {
+            CacheConfig::new(dir)
+                .with_expiry(ExpiryPolicy::default().with_revision_days(self.revision_days))
+        });
+
+        let mut retry = RetryPolicy::default().with_retry_after(!self.ignore_retry_after);
+        if self.deterministic {
+            retry = retry.with_jitter(Jitter::Deterministic);
+        }
+
+        let source = self
+            .source_dir
+            .as_ref()
+            .map_or(DataSource::Http, DataSource::local);
+
+        let headers: HeaderMap = self.headers.iter().cloned().collect();
+
+        let mut dns_overrides: HashMap<String, Vec<IpAddr>> = HashMap::new();
+        for (host, ips) in &self.dns_overrides {
+            dns_overrides.entry(host.clone()).or_default().extend(ips);
+        }
+
+        ClientConfig {
+            concurrency,
+            retry,
+            headers,
+            rate_limiter: self.rate_limiter.clone(),
+            basic_auth: self.basic_auth.clone().or_else(|| {
+                std::env::var(BASIC_AUTH_ENV)
+                    .ok()
+                    .and_then(|value| parse_basic_auth(&value).ok())
+            }),
+            user_agents: self.user_agents.clone(),
+            dns_overrides,
+            tls_backend: self.tls_backend,
+            root_certificates: self.ca_certs.concat(),
+            tls_built_in_roots: !self.only_ca_certs,
+            cache,
+            source,
+            http2: self.http2,
+            max_streams_per_connection: self.max_streams,
+            max_connections: self.max_connections,
+            failure_injection: self.inject_failures.clone(),
+            slow_request_threshold: self.slow_request_ms.map(Duration::from_millis),
+            slow_request_hook: self.slow_request_ms.map(|_| {
+                SlowRequestHook::new(|request| eprintln!("Warning: slow request: {request}"))
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Build the stream options for an instrument.
+    pub(crate) const fn stream_options(&self, instrument: &Instrument) -> StreamOptions {
+        let mut options = StreamOptions::for_instrument(instrument)
+            .with_ordered(self.deterministic)
+            .with_skip_weekends(instrument.is_forex() && !self.include_weekends);
+        if self.trading_hours_only {
+            options = options.with_calendar(instrument.calendar());
+        }
+        if let Some(action) = self.validate_prices {
+            options = options.with_validation(PriceValidation {
+                action,
+                max_median_ratio: Some(DEFAULT_MAX_MEDIAN_RATIO),
+            });
+        }
+        options.prefetch = self.prefetch;
+        options.decompress_memory_limit = match self.decompress_limit {
+            Some(mib) => Some(mib * 1024 * 1024),
+            None => None,
+        };
+        match self.retry_late {
+            Some(secs) => {
+                options.with_late_data_retry(LateDataRetry::new(Duration::from_secs(secs), 1))
+            }
+            None => options,
+        }
+    }
+}
+
+/// Parse a "Name: value" request header.
+fn parse_header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected \"Name: value\", got \"{s}\""))?;
+    let name = HeaderName::try_from(name.trim()).map_err(|e| e.to_string())?;
+    let value = HeaderValue::try_from(value.trim()).map_err(|e| e.to_string())?;
+    Ok((name, value))
+}
+
+/// Parse a "[HOST=]IP[,IP...]" DNS override, defaulting to the data feed host.
+fn parse_resolve(s: &str) -> Result<(String, Vec<IpAddr>), String> {
+    let (host, ips) = s.split_once('=').unwrap_or((url::HOST, s));
+    if host.is_empty() {
+        return Err("host must not be empty".to_string());
+    }
+    let ips = ips
+        .split(',')
+        .map(|ip| {
+            ip.trim()
+                .parse()
+                .map_err(|_| format!("invalid IP address \"{ip}\""))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((host.to_ascii_lowercase(), ips))
+}
+
+/// Load the CA certificates of a "--ca-cert" file.
+fn parse_ca_cert(s: &str) -> Result<Vec<Certificate>, String> {
+    load_certificates(s).map_err(|e| e.to_string())
+}
+
+/// Parse "user:password" (or just "user") basic auth credentials.
+fn parse_basic_auth(s: &str) -> Result<BasicAuth, String> {
+    let (username, password) = match s.split_once(':') {
+        Some((username, password)) => (username, Some(password.to_string())),
+        None => (s, None),
+    };
+    if username.is_empty() {
+        return Err("user name must not be empty".to_string());
+    }
+    Ok(BasicAuth::new(username, password))
+}
+
+/// Parse a finite number greater than zero.
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("invalid number \"{s}\""))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err("must be a positive number".to_string());
+    }
+    Ok(value)
+}
+
+/// Options controlling how ticks are aggregated into bars.
+#[derive(Args, Clone, Debug)]
+pub(crate) struct AggregateArgs {
+    /// Tick price bars are built from (bid, ask, mid)
+    #[arg(long, value_name = "SOURCE", default_value = "mid")]
+    pub(crate) price: PriceSource,
+
+    /// Close bars once their ask+bid volume reaches VOLUME instead of at timeframe boundaries
+    #[arg(long, value_name = "VOLUME", conflicts_with = "timeframe")]
+    pub(crate) volume_bars: Option<f64>,
+
+    /// Close bars once their notional (price x volume) reaches NOTIONAL
+    #[arg(long, value_name = "NOTIONAL", conflicts_with_all = ["timeframe", "volume_bars"])]
+    pub(crate) notional_bars: Option<f64>,
+
+    /// Build Renko bricks of PIPS pips instead of time bars
+    #[arg(
+        long,
+        value_name = "PIPS",
+        value_parser = parse_positive,
+        conflicts_with_all = ["timeframe", "volume_bars", "notional_bars"]
+    )]
+    pub(crate) renko: Option<f64>,
+
+    /// Close bars once their high-low range reaches PIPS pips
+    #[arg(
+        long,
+        value_name = "PIPS",
+        value_parser = parse_positive,
+        conflicts_with_all = ["timeframe", "volume_bars", "notional_bars", "renko"]
+    )]
+    pub(crate) range_bars: Option<f64>,
+
+    /// Emit bars for periods without ticks: skip, flat (previous close, zero volume) or null
+    #[arg(long, value_name = "POLICY", default_value = "skip")]
+    pub(crate) fill_gaps: GapFill,
+
+    /// Candle style of the bars: standard or heikin-ashi
+    #[arg(long, value_name = "STYLE", default_value = "standard")]
+    pub(crate) candle_style: CandleStyle,
+
+    /// Keep ask and bid volumes per bar and add the imbalance (bid-ask)/(bid+ask)
+    #[arg(long)]
+    pub(crate) side_volumes: bool,
+
+    /// Add first/last tick timestamps and an is_partial flag to each bar
+    #[arg(long)]
+    pub(crate) tick_times: bool,
+
+    /// Add the VWAP and the mean and largest bid-ask spread to each bar
+    #[arg(long)]
+    pub(crate) price_stats: bool,
+
+    /// Add the tick rate, bid/ask change counts and time-weighted spread to each time bar
+    #[arg(
+        long,
+        conflicts_with_all = ["volume_bars", "notional_bars", "renko", "range_bars"]
+    )]
+    pub(crate) microstructure: bool,
+
+    /// Write log returns or price differences of bar closes instead of bars (log, diff)
+    #[arg(long, value_name = "MODE", requires = "timeframe")]
+    pub(crate) returns: Option<ReturnsMode>,
+
+    /// Drop ticks whose bid equals the ask
+    #[arg(long)]
+    pub(crate) drop_zero_spreads: bool,
+
+    /// Drop ticks with the bid above the ask or a zero or negative price
+    #[arg(long)]
+    pub(crate) drop_crossed: bool,
+
+    /// Drop ticks whose mid is more than N median absolute deviations off the recent median
+    #[arg(long, value_name = "N")]
+    pub(crate) spike_filter: Option<f64>,
+
+    /// Number of recent ticks the spike filter compares against [default: 100]
+    #[arg(long, value_name = "TICKS", requires = "spike_filter")]
+    pub(crate) spike_window: Option<usize>,
+
+    /// Add a volume-by-price histogram with bins of PIPS pips to each time bar (json, ndjson, parquet)
+    #[arg(
+        long,
+        value_name = "PIPS",
+        value_parser = parse_positive,
+        requires = "timeframe",
+        conflicts_with_all = ["returns", "indicators"]
+    )]
+    pub(crate) volume_profile: Option<f64>,
+
+    /// Add rolling indicator columns to each bar, e.g. sma20,ema50,atr14,vol20
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        requires = "timeframe",
+        conflicts_with = "returns"
+    )]
+    pub(crate) indicators: Vec<Indicator>,
+}
+
+impl AggregateArgs {
+    /// Convert to the settings stored with background jobs.
+    pub(crate) fn settings(&self) -> AggregationSettings {
+        AggregationSettings {
+            volume_bars: self.volume_bars,
+            notional_bars: self.notional_bars,
+            renko_pips: self.renko,
+            range_pips: self.range_bars,
+            price_source: self.price,
+            gap_fill: self.fill_gaps,
+            candle_style: self.candle_style,
+            side_volumes: self.side_volumes,
+            tick_times: self.tick_times,
+            price_stats: self.price_stats,
+            microstructure: self.microstructure,
+            returns: self.returns,
+            drop_zero_spreads: self.drop_zero_spreads,
+            drop_crossed: self.drop_crossed,
+            spike_mads: self.spike_filter,
+            spike_window: self.spike_window,
+            volume_profile_pips: self.volume_profile,
+            indicators: self.indicators.clone(),
+        }
+    }
+}
+
+/// Options tuning the Parquet writer.
+#[derive(Args, Clone, Debug)]
+pub(crate) struct ParquetArgs {
+    /// Parquet page compression: uncompressed, snappy, gzip, lz4, zstd or brotli (zstd is typically 2-3x smaller)
+    #[arg(long, value_name = "CODEC", default_value = "snappy")]
+    pub(crate) parquet_compression: ParquetCodec,
+
+    /// Parquet compression level (gzip 0-9, zstd 1-22, brotli 0-11) [default: codec default]
+    #[arg(long, value_name = "LEVEL")]
+    pub(crate) parquet_level: Option<u32>,
+
+    /// Write Parquet columns without dictionary encoding
+    #[arg(long)]
+    pub(crate) parquet_no_dictionary: bool,
+
+    /// Target Parquet data page size in bytes [default: 1048576]
+    #[arg(long, value_name = "BYTES")]
+    pub(crate) parquet_page_size: Option<usize>,
+
+    /// Parquet column statistics: none, chunk or page
+    #[arg(long, value_name = "LEVEL", default_value = "page")]
+    pub(crate) parquet_statistics: ParquetStatistics,
+
+    /// Declare the timestamp column as the Parquet sort order when rows are in timestamp order
+    #[arg(long)]
+    pub(crate) parquet_sorting_columns: bool,
+
+    /// Store Parquet prices as float, integer (raw feed integers) or decimal (exact, smaller files)
+    #[arg(long, value_name = "ENCODING", default_value = "float")]
+    pub(crate) parquet_prices: PriceEncoding,
+
+    /// Parquet timestamp unit: ms (older Spark), us or ns
+    #[arg(long, value_name = "UNIT", default_value = "us")]
+    pub(crate) parquet_timestamps: TimestampPrecision,
+
+    /// Time zone Parquet readers display timestamps in, e.g. Europe/London; values stay UTC instants
+    #[arg(long, value_name = "ZONE", default_value = "UTC")]
+    pub(crate) parquet_timezone: Tz,
+}
+
+impl ParquetArgs {
+    /// Convert to the settings stored with background jobs.
+    pub(crate) const fn settings(&self) -> ParquetSettings {
+        ParquetSettings {
+            codec: self.parquet_compression,
+            level: self.parquet_level,
+            dictionary: !self.parquet_no_dictionary,
+            data_page_size: self.parquet_page_size,
+            statistics: self.parquet_statistics,
+            sorting_columns: self.parquet_sorting_columns,
+            price_encoding: self.parquet_prices,
+            timestamp_precision: self.parquet_timestamps,
+            timezone: self.parquet_timezone,
+        }
+    }
+}
@@ -4,23 +4,28 @@
 //! with `--daemon-run <job_id>`. It loads the job from disk and executes
 //! the download tasks.
 
-use crate::display::{Format, aggregate_ticks, write_ohlcv, write_ticks};
+use crate::config::Locations;
+use crate::display::{
+    Format, WriteOptions, output_metadata, parse_range, tick_filter, write_output,
+};
+use crate::memory::MemorySampler;
+use crate::signal::shutdown_token;
+use crate::summary::RunSummary;
 use anyhow::{Context, Result, bail};
 use futures::StreamExt;
-use paracas_daemon::{DaemonProgress, JobId, JobStatus, StateManager};
+use paracas_daemon::{DaemonProgress, JobId, JobStatus};
 use paracas_lib::prelude::*;
-use std::path::PathBuf;
+use paracas_lib::{CacheConfig, CancellationToken, DerivedColumns, FailureInjection, SplitLimits};
 
 /// Execute a background download job.
 ///
 /// This is called when paracas is spawned with `--daemon-run <job_id>`.
 /// The function loads the job from disk, executes all pending tasks,
 /// and saves progress periodically.
-pub(crate) async fn daemon_run(job_id_str: &str) -> Result<()> {
+pub(crate) async fn daemon_run(job_id_str: &str, locations: &Locations) -> Result<()> {
     let job_id: JobId = job_id_str.parse().context("Invalid job ID")?;
 
-    let state_manager =
-        StateManager::with_default_path().context("Failed to initialize state manager")?;
+    let state_manager = locations.state_manager()?;
 
     let job = state_manager.load_job(job_id).context("Job not found")?;
 
@@ -38,13 +43,19 @@ pub(crate) async fn daemon_run(job_id_str: &str) -> Result<()> {
     }
 
     // Process each task
+    let cancel = shutdown_token();
     let job = progress.job().await;
     for (task_idx, task) in job.tasks.iter().enumerate() {
         if matches!(task.status, JobStatus::Completed) {
             continue; // Skip already completed tasks
         }
 
-        if let Err(e) = execute_task(&progress, task_idx).await {
+        if let Err(e) = execute_task(&progress, task_idx, &cancel).await {
+            if cancel.is_cancelled() {
+                // Keep the partial progress of the interrupted task
+                progress.mark_job_cancelled().await;
+                return Ok(());
+            }
             progress.mark_task_failed(task_idx, &e.to_string()).await;
         }
 
@@ -68,7 +79,13 @@ pub(crate) async fn daemon_run(job_id_str: &str) -> Result<()> {
 }
 
 /// Execute a single download task.
-async fn execute_task(progress: &DaemonProgress, task_idx: usize) -> Result<()> {
+///
+/// Fails without writing output if `cancel` fires during the download.
+async fn execute_task(
+    progress: &DaemonProgress,
+    task_idx: usize,
+    cancel: &CancellationToken,
+) -> Result<()> {
     progress.mark_task_running(task_idx).await;
 
     let job = progress.job().await;
@@ -81,24 +98,70 @@ async fn execute_task(progress: &DaemonProgress, task_idx: usize) -> Result<()>
         .context("Unknown instrument")?;
 
     // Parse date range
-    let start = chrono::NaiveDate::parse_from_str(&task.start_date, "%Y-%m-%d")?;
-    let end = chrono::NaiveDate::parse_from_str(&task.end_date, "%Y-%m-%d")?;
-    let range = DateRange::new(start, end)?;
+    let range = parse_range(&task.start_date, &task.end_date)?;
+
+    // Parse timeframe and format
+    let timeframe = task
+        .timeframe
+        .parse::<Timeframe>()
+        .map_err(|e| anyhow::anyhow!("{e}"))?;
+    let format = parse_format(&task.format)?;
 
     // Create client
+    let failure_injection = job
+        .inject_failures
+        .as_deref()
+        .map(str::parse::<FailureInjection>)
+        .transpose()
+        .map_err(anyhow::Error::msg)
+        .context("Invalid failure injection spec")?;
     let config = ClientConfig {
         concurrency: job.concurrency,
+        cache: job.cache_dir.as_ref().map(CacheConfig::new),
+        failure_injection,
         ..Default::default()
     };
     let client = DownloadClient::new(config)?;
+    let sampler = job.report_memory.then(MemorySampler::start);
+    if let Some(sampler) = &sampler {
+        sampler.watch(client.stats());
+    }
 
     // Download ticks
     let mut all_ticks: Vec<Tick> = Vec::new();
-    let mut stream = paracas_lib::tick_stream_resilient(&client, instrument, range);
+    let mut filter = tick_filter(&job.aggregation);
+    let options = StreamOptions::for_instrument(instrument)
+        .with_ordered(
+            job.deterministic
+                || filter.is_enabled()
+                || !timeframe.is_tick()
+                || job.aggregation.activity_bars(),
+        )
+        .with_cancellation(cancel.clone());
+    let mut stream =
+        paracas_lib::tick_stream_resilient_with_options(&client, instrument, range, options);
     let mut hours_completed = 0u64;
+    let mut summary = job.summary.then(|| {
+        RunSummary::start(
+            "daemon",
+            instrument.id(),
+            task.alias.as_deref(),
+            range,
+            timeframe,
+            format,
+        )
+        .with_parameter("concurrency", job.concurrency)
+        .with_parameter("deterministic", job.deterministic)
+        .with_parameter("cache", job.cache_dir.is_some())
+    });
 
     while let Some(batch) = stream.next().await {
-        all_ticks.extend(batch.ticks);
+        if let Some(summary) = &mut summary {
+            summary.record(&batch);
+        }
+        let mut ticks = batch.ticks;
+        filter.retain(&mut ticks);
+        all_ticks.extend(ticks);
         hours_completed += 1;
 
         // Update progress periodically (every 10 hours)
@@ -109,51 +172,102 @@ async fn execute_task(progress: &DaemonProgress, task_idx: usize) -> Result<()>
         }
     }
 
-    // Parse timeframe and aggregate if needed
-    let timeframe = task
-        .timeframe
-        .parse::<Timeframe>()
-        .map_err(|e| anyhow::anyhow!("{e}"))?;
-
-    // Parse format
-    let format = parse_format(&task.format)?;
+    if cancel.is_cancelled() {
+        progress
+            .update_task_progress(task_idx, hours_completed, all_ticks.len() as u64)
+            .await;
+        bail!("Cancelled after {hours_completed} hours");
+    }
 
     // Write output
     let output_path = task.output_path.clone();
-    write_output(&all_ticks, &output_path, format, timeframe)?;
+    let metadata = output_metadata(
+        instrument,
+        &range,
+        timeframe,
+        &job.aggregation,
+        format,
+        job.deterministic,
+        task.alias.as_deref(),
+    );
+    let outputs = write_output(
+        &all_ticks,
+        &output_path,
+        format,
+        timeframe,
+        &job.aggregation,
+        instrument.pip_size(),
+        &metadata,
+        &WriteOptions {
+            parquet: job.parquet,
+            symbol_column: job.symbol_column,
+            append: job.append,
+            server_time: job
+                .server_time
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default(),
+            metadata_header: job.metadata_header,
+            derived: DerivedColumns {
+                spread: job.spread_column,
+                mid: job.mid_column,
+            },
+            split: SplitLimits {
+                max_rows: job.max_file_rows,
+                max_bytes: job.max_file_bytes,
+            },
+        },
+    )?;
+
+    if let Some(summary) = summary {
+        summary.finish(&client.stats().snapshot(), &output_path, &outputs, format)?;
+    }
 
-    let bytes_written = std::fs::metadata(&output_path)
+    let bytes_written = outputs
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
         .map(|m| m.len())
-        .unwrap_or(0);
+        .sum();
 
+    if let Some(sampler) = sampler {
+        progress.record_memory(sampler.finish()).await;
+    }
     progress.mark_task_completed(task_idx, bytes_written).await;
 
     Ok(())
 }
 
-/// Parse a format string into a Format enum.
+/// Parse a format string into a Format enum, rejecting formats not compiled in.
 fn parse_format(format: &str) -> Result<Format> {
-    match format.to_lowercase().as_str() {
-        "csv" => Ok(Format::Csv),
-        "json" => Ok(Format::Json),
-        "ndjson" => Ok(Format::Ndjson),
-        "parquet" => Ok(Format::Parquet),
+    let format = match format.to_lowercase().as_str() {
+        "csv" => Format::Csv,
+        "json" => Format::Json,
+        "ndjson" => Format::Ndjson,
+        "parquet" => Format::Parquet,
+        "parquet-dataset" => Format::ParquetDataset,
+        "sqlite" => Format::Sqlite,
+        "duckdb" => Format::DuckDb,
+        "avro" => Format::Avro,
+        "msgpack" => Format::Msgpack,
+        "hdf5" => Format::Hdf5,
+        "xlsx" => Format::Xlsx,
+        "protobuf" => Format::Protobuf,
+        "orc" => Format::Orc,
+        "mt4" => Format::Mt4,
+        "mt5" => Format::Mt5,
+        "hst" => Format::Hst,
+        "ninjatrader" => Format::NinjaTrader,
+        "tradingview" => Format::TradingView,
+        "lean" => Format::Lean,
         _ => bail!("Unknown format: {}", format),
-    }
+    };
+    format.ensure_available()
 }
 
-/// Write ticks or OHLCV data to the output file.
-fn write_output(
-    ticks: &[Tick],
-    output: &PathBuf,
-    format: Format,
-    timeframe: Timeframe,
-) -> Result<()> {
-    if timeframe.is_tick() {
-        write_ticks(ticks, output, format)?;
-    } else {
-        let bars = aggregate_ticks(ticks, timeframe);
-        write_ohlcv(&bars, output, format)?;
-    }
-    Ok(())
+/// Format check for [`paracas_daemon::DaemonSpawner`], rejecting jobs the daemon could not write.
+pub(crate) fn check_format(format: &str) -> std::result::Result<(), String> {
+    parse_format(format)
+        .map(drop)
+        .map_err(|error| error.to_string())
 }
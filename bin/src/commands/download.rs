@@ -2,15 +2,31 @@
 //!
 //! This module handles downloading tick data from Dukascopy and writing it to various output formats.
 
-use crate::display::{Format, aggregate_ticks, write_ohlcv, write_ticks};
-use anyhow::{Context, Result};
+use crate::args::{AggregateArgs, FetchArgs};
+use crate::commands::daemon_run::check_format;
+use crate::config::Locations;
+use crate::display::{
+    Format, WriteOptions, confirm_plan, format_hour_errors, format_invalid_ticks, format_revisions,
+    format_stats, output_metadata, parse_date_hour, parse_range, resolve_timeframe,
+    tick_aggregator, tick_filter, with_compression, write_output, write_time_bars,
+    write_timeframes,
+};
+use crate::memory::{MemorySampler, format_memory};
+use crate::signal::shutdown_token;
+use anyhow::{Context, Result, bail};
 use chrono::NaiveDate;
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use paracas_daemon::{DaemonSpawner, DownloadJob, InstrumentTask, StateManager};
+use paracas_daemon::{DaemonSpawner, DownloadJob, InstrumentTask};
+use paracas_estimate::Estimator;
 use paracas_lib::prelude::*;
+use paracas_lib::{HourError, MtServerTime, OutputCompression};
+use std::io::IsTerminal;
 use std::path::PathBuf;
 
+/// Estimated download size above which a download asks for confirmation.
+const CONFIRM_MIN_BYTES: u64 = 100 * 1024 * 1024;
+
 /// Download tick data for an instrument.
 #[allow(clippy::too_many_arguments)]
 pub(crate) async fn download(
@@ -19,12 +35,37 @@ pub(crate) async fn download(
     end_str: Option<&str>,
     output: Option<PathBuf>,
     format: Format,
+    compression: OutputCompression,
+    write_options: WriteOptions,
     timeframe_str: Option<&str>,
+    timeframes: &[Timeframe],
     concurrency: usize,
     background: bool,
-    _yes: bool,
+    yes: bool,
+    fetch: &FetchArgs,
+    aggregate: &AggregateArgs,
+    locations: &Locations,
     quiet: bool,
 ) -> Result<()> {
+    let output = output.map(|output| with_compression(output, compression));
+    let compression = output
+        .as_deref()
+        .map_or(compression, OutputCompression::from_path);
+    format
+        .ensure_available()?
+        .ensure_supports(&aggregate.settings())?
+        .ensure_compression(compression)?
+        .ensure_parquet(&write_options.parquet)?
+        .ensure_append(write_options.append)?
+        .ensure_server_time(write_options.server_time)?
+        .ensure_metadata_header(write_options.metadata_header)?
+        .ensure_derived_columns(write_options.derived)?
+        .ensure_split(
+            write_options.split,
+            write_options.append,
+            &aggregate.settings(),
+        )?;
+
     // Handle background mode
     if background {
         return spawn_background_download(
@@ -33,8 +74,15 @@ pub(crate) async fn download(
             end_str,
             output,
             format,
+            compression,
+            write_options,
             timeframe_str,
             concurrency,
+            yes,
+            fetch,
+            aggregate,
+            locations,
+            quiet,
         );
     }
 
@@ -44,46 +92,90 @@ pub(crate) async fn download(
         .get(instrument_id)
         .with_context(|| format!("Unknown instrument: {instrument_id}"))?;
 
-    // Parse start date (default to instrument's earliest available data)
-    let start = match start_str {
-        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
-            .with_context(|| format!("Invalid start date: {s}"))?,
-        None => instrument
-            .start_tick_date()
-            .map(|dt| dt.date_naive())
-            .unwrap_or_else(|| NaiveDate::from_ymd_opt(2003, 5, 5).expect("valid date")),
+    // Parse start (default to instrument's earliest available data)
+    let (start, start_hour) = match start_str {
+        Some(s) => parse_date_hour(s).with_context(|| format!("Invalid start: {s}"))?,
+        None => (
+            instrument
+                .start_tick_date()
+                .map(|dt| dt.date_naive())
+                .unwrap_or_else(|| NaiveDate::from_ymd_opt(2003, 5, 5).expect("valid date")),
+            None,
+        ),
     };
 
-    // Parse end date (default to today)
-    let end = match end_str {
-        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
-            .with_context(|| format!("Invalid end date: {s}"))?,
-        None => chrono::Utc::now().date_naive(),
+    // Parse end (default to today)
+    let (end, end_hour) = match end_str {
+        Some(s) => parse_date_hour(s).with_context(|| format!("Invalid end: {s}"))?,
+        None => (chrono::Utc::now().date_naive(), None),
     };
 
-    let range = DateRange::new(start, end)?;
+    let range =
+        DateRange::new(start, end)?.with_hours(start_hour.unwrap_or(0), end_hour.unwrap_or(23))?;
 
-    // Determine output path (default to <instrument>.<format>)
-    let output = output
-        .unwrap_or_else(|| PathBuf::from(format!("{}.{}", instrument_id, format.extension())));
+    let aliases = fetch.aliases([instrument.id()])?;
+    let alias = aliases.as_ref().and_then(|a| a.get(instrument.id()));
 
-    // Parse timeframe
-    let timeframe = match timeframe_str {
-        Some(tf) => tf
-            .parse::<Timeframe>()
-            .map_err(|e| anyhow::anyhow!("{e}"))?,
-        None => Timeframe::Tick,
+    // Determine output path (default to <instrument>.<format>, or <alias>.<format>;
+    // a dataset goes into the instrument directory under the output root)
+    let file_name = format.file_name(alias.unwrap_or(instrument_id), compression);
+    let output = match output {
+        Some(root) if format.is_dataset() => root.join(file_name),
+        Some(output) => output,
+        None => file_name,
     };
+    if format.is_dataset() && !timeframes.is_empty() {
+        bail!("--timeframes cannot be written as a parquet-dataset");
+    }
+
+    let timeframe = match timeframes.first() {
+        Some(first) => *first,
+        None => resolve_timeframe(timeframe_str, &[instrument], &range, quiet)?,
+    };
+    if !yes && !quiet && !confirm_large_download(instrument, &range, timeframe)? {
+        return Ok(());
+    }
 
     // Create client
-    let config = ClientConfig {
-        concurrency,
-        ..Default::default()
+    let client = DownloadClient::new(fetch.client_config(concurrency))?;
+    let sampler = fetch.report_memory.then(MemorySampler::start);
+    if let Some(sampler) = &sampler {
+        sampler.watch(client.stats());
+    }
+    let settings = aggregate.settings();
+    let metadata = output_metadata(
+        instrument,
+        &range,
+        timeframe,
+        &settings,
+        format,
+        fetch.deterministic,
+        alias,
+    );
+    let cancel = shutdown_token();
+    // Time bars are aggregated as hours arrive (in order) instead of holding
+    // every tick in memory
+    let mut aggregator = (!timeframe.is_tick()
+        && !settings.activity_bars()
+        && settings.volume_profile_pips.is_none()
+        && timeframes.is_empty())
+    .then(|| tick_aggregator(timeframe, &settings));
+    let mut bars: Vec<Ohlcv> = Vec::new();
+    // The spike filter compares each tick against the ones before it
+    let mut filter = tick_filter(&settings);
+    let options = fetch
+        .stream_options(instrument)
+        .with_cancellation(cancel.clone());
+    // Every aggregator, like the spike filter, expects ticks in time order
+    let aggregates = !timeframe.is_tick() || settings.activity_bars() || !timeframes.is_empty();
+    let options = if aggregates || filter.is_enabled() {
+        options.with_ordered(true)
+    } else {
+        options
     };
-    let client = DownloadClient::new(config)?;
 
     // Setup progress bar
-    let total_hours = range.total_hours() as u64;
+    let total_hours = options.hour_count(range) as u64;
     let progress = if quiet {
         ProgressBar::hidden()
     } else {
@@ -94,47 +186,153 @@ pub(crate) async fn download(
                 .expect("Invalid progress template")
                 .progress_chars("=>-"),
         );
-        pb.set_message(format!("{} {} -> {}", instrument.id(), start, end));
+        pb.set_message(format!("{} {}", instrument.id(), range));
         pb
     };
 
     // Download and collect ticks using the resilient stream
     // This will retry on transient errors and skip hours that fail after retries
     let mut all_ticks: Vec<Tick> = Vec::new();
+    let mut tick_count = 0usize;
     let mut skipped_hours = 0u64;
-    let mut stream = paracas_lib::tick_stream_resilient(&client, instrument, range);
+    let mut invalid_ticks = 0usize;
+    let mut summary = fetch.run_summary(
+        "download",
+        instrument,
+        alias,
+        range,
+        timeframe,
+        format,
+        concurrency,
+    );
+    let (mut stream, errors) =
+        paracas_lib::tick_stream_resilient_split(&client, instrument, range, options);
 
     while let Some(batch) = stream.next().await {
         if batch.had_error() {
             skipped_hours += 1;
         }
-        all_ticks.extend(batch.ticks);
+        invalid_ticks += batch.invalid.len();
+        if let Some(summary) = &mut summary {
+            summary.record(&batch);
+        }
+        let mut ticks = batch.ticks;
+        filter.retain(&mut ticks);
+        tick_count += ticks.len();
+        match &mut aggregator {
+            Some(aggregator) => {
+                bars.extend(ticks.iter().filter_map(|tick| aggregator.process(*tick)));
+            }
+            None => all_ticks.extend(ticks),
+        }
         progress.inc(1);
     }
+    drop(stream);
+    let hour_errors: Vec<HourError> = errors.collect().await;
 
-    let finish_msg = if skipped_hours > 0 {
-        format!(
-            "Downloaded {} ticks ({} hours skipped due to errors)",
-            all_ticks.len(),
-            skipped_hours
-        )
-    } else {
-        format!("Downloaded {} ticks", all_ticks.len())
-    };
+    if cancel.is_cancelled() {
+        progress.abandon_with_message("cancelled");
+        bail!(
+            "Cancelled after {} of {} hours ({} ticks); no output written",
+            progress.position(),
+            total_hours,
+            tick_count
+        );
+    }
+
+    let mut finish_msg = format!("Downloaded {tick_count} ticks");
+    if skipped_hours > 0 {
+        finish_msg.push_str(&format!(" ({skipped_hours} hours skipped due to errors)"));
+    }
+    if filter.dropped() > 0 {
+        finish_msg.push_str(&format!(" ({} ticks filtered)", filter.dropped()));
+    }
+    if let Some(action) = fetch.validate_prices
+        && invalid_ticks > 0
+    {
+        finish_msg.push_str(&format!(
+            " ({})",
+            format_invalid_ticks(invalid_ticks, action)
+        ));
+    }
     progress.finish_with_message(finish_msg);
+    if !quiet {
+        println!("{}", format_stats(&client.stats().snapshot()));
+        if !hour_errors.is_empty() {
+            format_hour_errors(&hour_errors)
+                .iter()
+                .for_each(|line| println!("{line}"));
+        }
+        if let Some(cache) = client.cache() {
+            let revisions = cache.revisions();
+            if !revisions.is_empty() {
+                format_revisions(&revisions)
+                    .iter()
+                    .for_each(|line| println!("{line}"));
+            }
+        }
+    }
 
-    // Aggregate if needed
-    if timeframe.is_tick() {
-        // Write raw ticks
-        write_ticks(&all_ticks, &output, format)?;
+    // Write raw ticks, or aggregate to OHLCV (or returns)
+    let outputs = if let Some(aggregator) = aggregator {
+        bars.extend(aggregator.finish());
+        write_time_bars(
+            &bars,
+            timeframe,
+            &settings,
+            &output,
+            format,
+            &metadata,
+            &write_options,
+        )?
+    } else if timeframes.is_empty() {
+        write_output(
+            &all_ticks,
+            &output,
+            format,
+            timeframe,
+            &settings,
+            instrument.pip_size(),
+            &metadata,
+            &write_options,
+        )?
     } else {
-        // Aggregate to OHLCV
-        let bars = aggregate_ticks(&all_ticks, timeframe);
-        write_ohlcv(&bars, &output, format)?;
+        write_timeframes(
+            &all_ticks,
+            timeframes,
+            &settings,
+            &output,
+            format,
+            &metadata,
+            &write_options,
+        )?
+    };
+
+    if let Some(sampler) = sampler {
+        let report = sampler.finish();
+        if !quiet {
+            println!("{}", format_memory(&report));
+        }
     }
 
     if !quiet {
-        println!("Output written to: {}", output.display());
+        if format.is_dataset() {
+            println!(
+                "Dataset written to: {} ({} files)",
+                output.display(),
+                outputs.len()
+            );
+        } else {
+            for path in &outputs {
+                println!("Output written to: {}", path.display());
+            }
+        }
+    }
+    if let Some(summary) = summary {
+        let path = summary.finish(&client.stats().snapshot(), &output, &outputs, format)?;
+        if !quiet {
+            println!("Summary written to: {}", path.display());
+        }
     }
 
     Ok(())
@@ -148,15 +346,22 @@ fn spawn_background_download(
     end_str: Option<&str>,
     output: Option<PathBuf>,
     format: Format,
+    compression: OutputCompression,
+    write_options: WriteOptions,
     timeframe_str: Option<&str>,
     concurrency: usize,
+    yes: bool,
+    fetch: &FetchArgs,
+    aggregate: &AggregateArgs,
+    locations: &Locations,
+    quiet: bool,
 ) -> Result<()> {
     let registry = InstrumentRegistry::global();
     let instrument = registry
         .get(instrument_id)
         .with_context(|| format!("Unknown instrument: {instrument_id}"))?;
 
-    // Determine start date
+    // Determine start (default to instrument's earliest available data)
     let start = start_str
         .map(|s| s.to_string())
         .or_else(|| {
@@ -166,14 +371,24 @@ fn spawn_background_download(
         })
         .unwrap_or_else(|| "2003-05-05".to_string());
 
-    // Determine end date
+    // Determine end (default to today)
     let end = end_str
         .map(|s| s.to_string())
         .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
 
+    let aliases = fetch.aliases([instrument.id()])?;
+    let alias = aliases
+        .as_ref()
+        .and_then(|a| a.get(instrument.id()))
+        .map(String::from);
+
     // Determine output path
-    let output_path = output
-        .unwrap_or_else(|| PathBuf::from(format!("{}.{}", instrument_id, format.extension())));
+    let file_name = format.file_name(alias.as_deref().unwrap_or(instrument_id), compression);
+    let output_path = match output {
+        Some(root) if format.is_dataset() => root.join(file_name),
+        Some(output) => output,
+        None => file_name,
+    };
 
     // Make output path absolute
     let output_path = if output_path.is_absolute() {
@@ -184,15 +399,14 @@ fn spawn_background_download(
             .join(output_path)
     };
 
-    // Calculate total hours for progress tracking
-    let start_date = NaiveDate::parse_from_str(&start, "%Y-%m-%d")?;
-    let end_date = NaiveDate::parse_from_str(&end, "%Y-%m-%d")?;
-    let range = DateRange::new(start_date, end_date)?;
+    // Validate the range and calculate total hours for progress tracking
+    let range = parse_range(&start, &end)?;
 
-    // Determine timeframe string (default to "tick")
-    let timeframe = timeframe_str
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "tick".to_string());
+    let timeframe = resolve_timeframe(timeframe_str, &[instrument], &range, quiet)?;
+    if !yes && !quiet && !confirm_large_download(instrument, &range, timeframe)? {
+        return Ok(());
+    }
+    let timeframe = timeframe.to_string();
 
     let task = InstrumentTask::new(
         instrument_id.to_string(),
@@ -201,14 +415,32 @@ fn spawn_background_download(
         output_path,
         format.to_string(),
         timeframe,
-        range.total_hours() as u32,
-    );
+        fetch.stream_options(instrument).hour_count(range) as u32,
+    )
+    .with_alias(alias);
 
-    let mut job = DownloadJob::new(vec![task], concurrency);
+    let mut job = DownloadJob::new(vec![task], concurrency)
+        .with_aggregation(aggregate.settings())
+        .with_parquet(write_options.parquet)
+        .with_symbol_column(write_options.symbol_column)
+        .with_append(write_options.append)
+        .with_server_time(
+            (write_options.server_time != MtServerTime::Utc)
+                .then(|| write_options.server_time.to_string()),
+        )
+        .with_metadata_header(write_options.metadata_header)
+        .with_derived_columns(write_options.derived.spread, write_options.derived.mid)
+        .with_split(write_options.split.max_rows, write_options.split.max_bytes)
+        .with_deterministic(fetch.deterministic)
+        .with_report_memory(fetch.report_memory)
+        .with_cache_dir(fetch.cache_dir.clone())
+        .with_inject_failures(fetch.inject_failures.as_ref().map(ToString::to_string))
+        .with_summary(fetch.summary);
 
-    let state_manager =
-        StateManager::with_default_path().context("Failed to initialize state manager")?;
-    let spawner = DaemonSpawner::new(state_manager).context("Failed to create daemon spawner")?;
+    let state_manager = locations.state_manager()?;
+    let spawner = DaemonSpawner::new(state_manager)
+        .context("Failed to create daemon spawner")?
+        .with_format_check(check_format);
     let job_id = spawner
         .spawn(&mut job)
         .context("Failed to spawn background job")?;
@@ -219,3 +451,24 @@ fn spawn_background_download(
 
     Ok(())
 }
+
+/// Show the plan of a large download and ask whether to proceed.
+///
+/// Downloads estimated below [`CONFIRM_MIN_BYTES`] start right away, as do
+/// all downloads when stdin is not a terminal, so scripts are not blocked.
+fn confirm_large_download(
+    instrument: &Instrument,
+    range: &DateRange,
+    timeframe: Timeframe,
+) -> Result<bool> {
+    let estimate = Estimator::global().estimate_single(instrument, range);
+    if estimate.estimated_compressed_bytes < CONFIRM_MIN_BYTES || !std::io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    confirm_plan(
+        &format!("Instrument: {instrument}"),
+        range,
+        timeframe,
+        &estimate,
+    )
+}
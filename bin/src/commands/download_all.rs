@@ -3,15 +3,23 @@
 //! This module handles batch downloading of multiple instruments, with support for
 //! category filtering, parallel downloads, and download estimation.
 
-use crate::display::{Format, aggregate_ticks, parse_category, write_ohlcv, write_ticks};
+use crate::args::{AggregateArgs, FetchArgs};
+use crate::commands::daemon_run::check_format;
+use crate::config::Locations;
+use crate::display::{
+    Format, WriteOptions, confirm_plan, format_date_hour, format_invalid_ticks, format_revisions,
+    output_metadata, parse_category, parse_date_hour, resolve_timeframe, tick_filter, write_output,
+};
+use crate::memory::{MemorySampler, format_memory};
+use crate::signal::shutdown_token;
 use anyhow::{Context, Result};
 use chrono::NaiveDate;
 use futures::stream::{self, StreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use paracas_daemon::{DaemonSpawner, DownloadJob, InstrumentTask, StateManager};
+use paracas_daemon::{DaemonSpawner, DownloadJob, InstrumentTask};
 use paracas_estimate::Estimator;
 use paracas_lib::prelude::*;
-use std::io::Write as _;
+use paracas_lib::{InstrumentAliases, MtServerTime, OutputCompression};
 use std::path::PathBuf;
 
 /// Execute the download-all command.
@@ -22,13 +30,33 @@ pub(crate) async fn download_all(
     end_str: Option<&str>,
     output_dir: PathBuf,
     format: Format,
+    compression: OutputCompression,
+    write_options: WriteOptions,
     timeframe_str: Option<&str>,
     parallel_instruments: usize,
     concurrency: usize,
     background: bool,
     yes: bool,
+    fetch: &FetchArgs,
+    aggregate: &AggregateArgs,
+    locations: &Locations,
     quiet: bool,
 ) -> Result<()> {
+    format
+        .ensure_available()?
+        .ensure_supports(&aggregate.settings())?
+        .ensure_compression(compression)?
+        .ensure_parquet(&write_options.parquet)?
+        .ensure_append(write_options.append)?
+        .ensure_server_time(write_options.server_time)?
+        .ensure_metadata_header(write_options.metadata_header)?
+        .ensure_derived_columns(write_options.derived)?
+        .ensure_split(
+            write_options.split,
+            write_options.append,
+            &aggregate.settings(),
+        )?;
+
     // 1. Get instruments based on category filter (or all)
     let registry = InstrumentRegistry::global();
     let instruments: Vec<_> = match category {
@@ -43,92 +71,72 @@ pub(crate) async fn download_all(
         anyhow::bail!("No instruments found matching criteria");
     }
 
-    // Parse end date (default to today)
+    // Parse end (default to today)
     let today = chrono::Utc::now().date_naive();
-    let end = match end_str {
-        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
-            .with_context(|| format!("Invalid end date: {s}"))?,
-        None => today,
+    let (end, end_hour) = match end_str {
+        Some(s) => parse_date_hour(s).with_context(|| format!("Invalid end: {s}"))?,
+        None => (today, None),
     };
 
-    // Parse start date or use earliest instrument date
-    let start = match start_str {
-        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
-            .with_context(|| format!("Invalid start date: {s}"))?,
+    // Parse start or use earliest instrument date
+    let (start, start_hour) = match start_str {
+        Some(s) => parse_date_hour(s).with_context(|| format!("Invalid start: {s}"))?,
         None => {
             // Use the earliest start date among all selected instruments
-            instruments
+            let earliest = instruments
                 .iter()
                 .filter_map(|i| i.start_tick_date())
                 .map(|dt| dt.date_naive())
                 .min()
-                .unwrap_or_else(|| NaiveDate::from_ymd_opt(2003, 5, 5).expect("valid date"))
+                .unwrap_or_else(|| NaiveDate::from_ymd_opt(2003, 5, 5).expect("valid date"));
+            (earliest, None)
         }
     };
 
-    let range = DateRange::new(start, end)?;
+    let range =
+        DateRange::new(start, end)?.with_hours(start_hour.unwrap_or(0), end_hour.unwrap_or(23))?;
+
+    let timeframe = resolve_timeframe(timeframe_str, &instruments, &range, quiet)?;
 
     // 2. Show estimate and get confirmation
     let estimator = Estimator::global();
     let estimate = estimator.estimate_batch(&instruments, &range);
 
     if !yes && !quiet {
-        println!("Download plan:");
-        println!("  Instruments: {}", instruments.len());
-        println!("  Date range: {} to {}", start, end);
-        println!(
-            "  Estimated download size: {}",
-            Estimator::format_bytes(estimate.estimated_compressed_bytes)
-        );
-        println!(
-            "  Estimated output size: {}",
-            Estimator::format_bytes(estimate.estimated_output_bytes)
-        );
-        println!(
-            "  Estimated time: {}",
-            Estimator::format_duration(estimate.estimated_duration)
-        );
-        println!();
-
-        // Simple y/n confirmation
-        print!("Proceed with download? [y/N] ");
-        std::io::stdout().flush()?;
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-        if !input.trim().eq_ignore_ascii_case("y") {
-            println!("Cancelled.");
+        let subject = format!("Instruments: {}", instruments.len());
+        if !confirm_plan(&subject, &range, timeframe, &estimate)? {
             return Ok(());
         }
     }
 
+    let aliases = fetch.aliases(instruments.iter().map(|i| i.id()))?;
+
     // 3. If background mode, spawn daemon
     if background {
         return spawn_background_download_all(
             &instruments,
-            start,
-            end,
+            aliases.as_ref(),
+            range,
             &output_dir,
             format,
-            timeframe_str,
+            compression,
+            write_options,
+            timeframe,
             concurrency,
+            fetch,
+            aggregate,
+            locations,
         );
     }
 
     // 4. Create output directory if needed
     std::fs::create_dir_all(&output_dir)?;
 
-    // 5. Parse timeframe
-    let timeframe = match timeframe_str {
-        Some(tf) => tf
-            .parse::<Timeframe>()
-            .map_err(|e| anyhow::anyhow!("{e}"))?,
-        None => Timeframe::Tick,
-    };
-
-    // 6. Download instruments in parallel
+    // 5. Download instruments in parallel
     let multi_progress = MultiProgress::new();
+    let sampler = fetch.report_memory.then(MemorySampler::start);
 
-    let results: Vec<_> = stream::iter(instruments.into_iter())
+    let results: Vec<_> = stream::iter(instruments)
         .map(|instrument| {
             let pb = multi_progress.add(ProgressBar::new(100));
             pb.set_style(
@@ -141,12 +149,17 @@ pub(crate) async fn download_all(
 
             download_single_instrument(
                 instrument,
-                start,
-                end,
+                range,
                 output_dir.clone(),
                 format,
+                compression,
+                write_options,
                 timeframe,
                 concurrency,
+                fetch,
+                aggregate,
+                aliases.as_ref().and_then(|a| a.get(instrument.id())),
+                sampler.as_ref(),
                 pb,
                 quiet,
             )
@@ -155,12 +168,16 @@ pub(crate) async fn download_all(
         .collect()
         .await;
 
-    // 7. Report summary
+    // 6. Report summary
     let (successes, failures): (Vec<_>, Vec<_>) = results.iter().partition(|r| r.is_ok());
+    let memory = sampler.map(MemorySampler::finish);
 
     if !quiet {
         println!("\nDownload complete:");
         println!("  Successful: {}", successes.len());
+        if let Some(memory) = &memory {
+            println!("  {}", format_memory(memory));
+        }
         if !failures.is_empty() {
             println!("  Failed: {}", failures.len());
             for (i, err) in failures.iter().enumerate() {
@@ -183,83 +200,174 @@ pub(crate) async fn download_all(
     Ok(())
 }
 
+/// Clamp a range to the instrument's available data.
+///
+/// Returns `None` if the instrument has no data in the range.
+fn instrument_range(instrument: &Instrument, range: DateRange) -> Result<Option<DateRange>> {
+    let Some(instrument_start) = instrument.start_tick_date().map(|dt| dt.date_naive()) else {
+        return Ok(Some(range));
+    };
+    if range.start >= instrument_start {
+        return Ok(Some(range));
+    }
+    if instrument_start > range.end {
+        return Ok(None);
+    }
+    Ok(Some(
+        DateRange::new(instrument_start, range.end)?.with_hours(0, range.end_hour)?,
+    ))
+}
+
 /// Download a single instrument with progress tracking.
 #[allow(clippy::too_many_arguments)]
 async fn download_single_instrument(
     instrument: &Instrument,
-    start: NaiveDate,
-    end: NaiveDate,
+    range: DateRange,
     output_dir: PathBuf,
     format: Format,
+    compression: OutputCompression,
+    write_options: WriteOptions,
     timeframe: Timeframe,
     concurrency: usize,
+    fetch: &FetchArgs,
+    aggregate: &AggregateArgs,
+    alias: Option<&str>,
+    sampler: Option<&MemorySampler>,
     progress: ProgressBar,
     quiet: bool,
 ) -> Result<()> {
-    // Adjust start date based on instrument's available data
-    let effective_start = instrument
-        .start_tick_date()
-        .map_or(start, |instrument_start| {
-            let instrument_start_date = instrument_start.date_naive();
-            if start < instrument_start_date {
-                instrument_start_date
-            } else {
-                start
-            }
-        });
-
     // Skip if the instrument has no data in the requested range
-    if effective_start > end {
+    let Some(range) = instrument_range(instrument, range)? else {
         progress.finish_with_message("skipped (no data)");
         return Ok(());
-    }
-
-    let range = DateRange::new(effective_start, end)?;
-    let total_hours = range.total_hours() as u64;
-    progress.set_length(total_hours);
+    };
+    let cancel = shutdown_token();
+    let settings = aggregate.settings();
+    let mut filter = tick_filter(&settings);
+    let options = fetch
+        .stream_options(instrument)
+        .with_cancellation(cancel.clone());
+    // The spike filter compares each tick against the ones before it, and
+    // every aggregator expects ticks in time order
+    let aggregates = !timeframe.is_tick() || settings.activity_bars();
+    let options = if aggregates || filter.is_enabled() {
+        options.with_ordered(true)
+    } else {
+        options
+    };
+    progress.set_length(options.hour_count(range) as u64);
 
     // Create client
-    let config = ClientConfig {
-        concurrency,
-        ..Default::default()
-    };
-    let client = DownloadClient::new(config)?;
+    let client = DownloadClient::new(fetch.client_config(concurrency))?;
+    if let Some(sampler) = sampler {
+        sampler.watch(client.stats());
+    }
+    let metadata = output_metadata(
+        instrument,
+        &range,
+        timeframe,
+        &settings,
+        format,
+        fetch.deterministic,
+        alias,
+    );
 
     // Download and collect ticks
     let mut all_ticks: Vec<Tick> = Vec::new();
     let mut skipped_hours = 0u64;
-    let mut stream = paracas_lib::tick_stream_resilient(&client, instrument, range);
+    let mut invalid_ticks = 0usize;
+    let mut summary = fetch.run_summary(
+        "download-all",
+        instrument,
+        alias,
+        range,
+        timeframe,
+        format,
+        concurrency,
+    );
+    let mut stream =
+        paracas_lib::tick_stream_resilient_with_options(&client, instrument, range, options);
 
     while let Some(batch) = stream.next().await {
         if batch.had_error() {
             skipped_hours += 1;
         }
-        all_ticks.extend(batch.ticks);
+        invalid_ticks += batch.invalid.len();
+        if let Some(summary) = &mut summary {
+            summary.record(&batch);
+        }
+        let mut ticks = batch.ticks;
+        filter.retain(&mut ticks);
+        all_ticks.extend(ticks);
         progress.inc(1);
     }
 
+    if cancel.is_cancelled() {
+        progress.abandon_with_message(format!(
+            "cancelled after {}/{} hours",
+            progress.position(),
+            progress.length().unwrap_or_default()
+        ));
+        anyhow::bail!("{}: cancelled, no output written", instrument.id());
+    }
+
     let tick_count = all_ticks.len();
-    let finish_msg = if skipped_hours > 0 {
-        format!("{} ticks ({} hrs skipped)", tick_count, skipped_hours)
-    } else {
-        format!("{} ticks", tick_count)
-    };
+    let stats = client.stats().snapshot();
+    let throughput = format!(
+        "{} @ {}/s",
+        Estimator::format_bytes(stats.bytes_fetched),
+        Estimator::format_bytes(stats.bytes_per_second() as u64)
+    );
+    let mut finish_msg = format!("{} ticks, {}", tick_count, throughput);
+    if skipped_hours > 0 {
+        finish_msg.push_str(&format!(" ({skipped_hours} hrs skipped)"));
+    }
+    if filter.dropped() > 0 {
+        finish_msg.push_str(&format!(" ({} filtered)", filter.dropped()));
+    }
+    if let Some(action) = fetch.validate_prices
+        && invalid_ticks > 0
+    {
+        finish_msg.push_str(&format!(
+            " ({})",
+            format_invalid_ticks(invalid_ticks, action)
+        ));
+    }
     progress.finish_with_message(finish_msg);
 
     // Determine output path
-    let output_path = output_dir.join(format!("{}.{}", instrument.id(), format.extension()));
-
-    // Aggregate if needed
-    if timeframe.is_tick() {
-        write_ticks(&all_ticks, &output_path, format)?;
-    } else {
-        let bars = aggregate_ticks(&all_ticks, timeframe);
-        write_ohlcv(&bars, &output_path, format)?;
-    }
+    let name = alias.unwrap_or(instrument.id());
+    let output_path = output_dir.join(format.file_name(name, compression));
+
+    // Write raw ticks, or aggregate to OHLCV (or returns)
+    let outputs = write_output(
+        &all_ticks,
+        &output_path,
+        format,
+        timeframe,
+        &settings,
+        instrument.pip_size(),
+        &metadata,
+        &write_options,
+    )?;
 
     if !quiet {
+        if let Some(cache) = client.cache() {
+            let revisions = cache.revisions();
+            if !revisions.is_empty() {
+                for line in format_revisions(&revisions) {
+                    progress.println(format!("  {}: {line}", instrument.id()));
+                }
+            }
+        }
         progress.println(format!("  Written: {}", output_path.display()));
     }
+    if let Some(summary) = summary {
+        let path = summary.finish(&stats, &output_path, &outputs, format)?;
+        if !quiet {
+            progress.println(format!("  Summary: {}", path.display()));
+        }
+    }
 
     Ok(())
 }
@@ -268,12 +376,17 @@ async fn download_single_instrument(
 #[allow(clippy::too_many_arguments)]
 fn spawn_background_download_all(
     instruments: &[&Instrument],
-    start: NaiveDate,
-    end: NaiveDate,
+    aliases: Option<&InstrumentAliases>,
+    range: DateRange,
     output_dir: &PathBuf,
     format: Format,
-    timeframe_str: Option<&str>,
+    compression: OutputCompression,
+    write_options: WriteOptions,
+    timeframe: Timeframe,
     concurrency: usize,
+    fetch: &FetchArgs,
+    aggregate: &AggregateArgs,
+    locations: &Locations,
 ) -> Result<()> {
     // Make output directory absolute
     let output_dir = if output_dir.is_absolute() {
@@ -287,44 +400,30 @@ fn spawn_background_download_all(
     // Create output directory if needed
     std::fs::create_dir_all(&output_dir)?;
 
-    // Determine timeframe string (default to "tick")
-    let timeframe = timeframe_str
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "tick".to_string());
+    let timeframe = timeframe.to_string();
 
     // Create tasks for each instrument
     let mut tasks = Vec::with_capacity(instruments.len());
 
     for instrument in instruments {
-        // Adjust start date based on instrument's available data
-        let effective_start = instrument
-            .start_tick_date()
-            .map_or(start, |instrument_start| {
-                let instrument_start_date = instrument_start.date_naive();
-                if start < instrument_start_date {
-                    instrument_start_date
-                } else {
-                    start
-                }
-            });
-
         // Skip if the instrument has no data in the requested range
-        if effective_start > end {
+        let Some(range) = instrument_range(instrument, range)? else {
             continue;
-        }
-
-        let range = DateRange::new(effective_start, end)?;
-        let output_path = output_dir.join(format!("{}.{}", instrument.id(), format.extension()));
+        };
+        let alias = aliases.and_then(|a| a.get(instrument.id()));
+        let name = alias.unwrap_or(instrument.id());
+        let output_path = output_dir.join(format.file_name(name, compression));
 
         let task = InstrumentTask::new(
             instrument.id().to_string(),
-            effective_start.format("%Y-%m-%d").to_string(),
-            end.format("%Y-%m-%d").to_string(),
+            format_date_hour(range.start, range.start_hour, 0),
+            format_date_hour(range.end, range.end_hour, 23),
             output_path,
             format.to_string(),
             timeframe.clone(),
-            range.total_hours() as u32,
-        );
+            fetch.stream_options(instrument).hour_count(range) as u32,
+        )
+        .with_alias(alias.map(String::from));
 
         tasks.push(task);
     }
@@ -333,11 +432,28 @@ fn spawn_background_download_all(
         anyhow::bail!("No instruments with data in the specified date range");
     }
 
-    let mut job = DownloadJob::new(tasks, concurrency);
-
-    let state_manager =
-        StateManager::with_default_path().context("Failed to initialize state manager")?;
-    let spawner = DaemonSpawner::new(state_manager).context("Failed to create daemon spawner")?;
+    let mut job = DownloadJob::new(tasks, concurrency)
+        .with_aggregation(aggregate.settings())
+        .with_parquet(write_options.parquet)
+        .with_symbol_column(write_options.symbol_column)
+        .with_append(write_options.append)
+        .with_server_time(
+            (write_options.server_time != MtServerTime::Utc)
+                .then(|| write_options.server_time.to_string()),
+        )
+        .with_metadata_header(write_options.metadata_header)
+        .with_derived_columns(write_options.derived.spread, write_options.derived.mid)
+        .with_split(write_options.split.max_rows, write_options.split.max_bytes)
+        .with_deterministic(fetch.deterministic)
+        .with_report_memory(fetch.report_memory)
+        .with_cache_dir(fetch.cache_dir.clone())
+        .with_inject_failures(fetch.inject_failures.as_ref().map(ToString::to_string))
+        .with_summary(fetch.summary);
+
+    let state_manager = locations.state_manager()?;
+    let spawner = DaemonSpawner::new(state_manager)
+        .context("Failed to create daemon spawner")?
+        .with_format_check(check_format);
     let job_id = spawner
         .spawn(&mut job)
         .context("Failed to spawn background job")?;
@@ -0,0 +1,34 @@
+//! Formats and capabilities command implementations.
+//!
+//! This module lists the output formats and whether each one is compiled
+//! into this binary, and prints everything the build supports as JSON for
+//! frontends.
+
+use crate::display::Format;
+use anyhow::Result;
+use clap::ValueEnum;
+use paracas_lib::{OutputFormat, capabilities};
+
+/// List output formats with their availability.
+pub(crate) fn list_formats() -> Result<()> {
+    println!("{:<10} STATUS", "FORMAT");
+    println!("{}", "-".repeat(50));
+
+    for format in Format::value_variants() {
+        let output = OutputFormat::from(*format);
+        let status = match output.required_feature() {
+            Some(feature) if !output.is_available() => {
+                format!("not compiled in (build with --features {feature})")
+            }
+            _ => "available".to_string(),
+        };
+        println!("{:<10} {status}", format.to_string());
+    }
+    Ok(())
+}
+
+/// Print the capabilities of this build as JSON.
+pub(crate) fn print_capabilities() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&capabilities())?);
+    Ok(())
+}
@@ -1,14 +1,21 @@
 //! Info command implementation.
 //!
 //! This module handles displaying detailed information about a specific instrument,
-//! including size estimates for different time periods.
+//! including size estimates for different time periods and an optional live quote.
 
 use anyhow::{Context, Result};
+use chrono::{DurationRound, TimeDelta, Utc};
 use paracas_estimate::Estimator;
 use paracas_lib::prelude::*;
 
+/// How far back to search for the most recent hour with data (covers long weekends).
+const LIVE_LOOKBACK_HOURS: i64 = 24 * 5;
+
 /// Show detailed information about an instrument, including size estimates.
-pub(crate) fn show_info(instrument_id: &str) -> Result<()> {
+///
+/// With `live`, also downloads the most recent available hour and prints its
+/// last quote.
+pub(crate) async fn show_info(instrument_id: &str, live: bool) -> Result<()> {
     let registry = InstrumentRegistry::global();
     let instrument = registry
         .get(instrument_id)
@@ -100,5 +107,41 @@ pub(crate) fn show_info(instrument_id: &str) -> Result<()> {
         println!("\nNote: Estimates are based on historical averages and may vary.");
     }
 
+    if live {
+        show_live_quote(instrument).await?;
+    }
+
+    Ok(())
+}
+
+/// Download the most recent hour with data and print its last quote.
+async fn show_live_quote(instrument: &Instrument) -> Result<()> {
+    let client = DownloadClient::with_defaults()?;
+    let current_hour = Utc::now().duration_trunc(TimeDelta::hours(1))?;
+
+    println!("\nLive Quote:");
+    for offset in 0..LIVE_LOOKBACK_HOURS {
+        let hour = current_hour - TimeDelta::hours(offset);
+        let batch = paracas_lib::fetch_hour(&client, instrument, hour)
+            .await
+            .with_context(|| format!("Failed to fetch {}", hour.format("%Y-%m-%d %H:00")))?;
+
+        if let Some(tick) = batch.ticks.last() {
+            println!("  Hour:    {} UTC", hour.format("%Y-%m-%d %H:00"));
+            println!(
+                "  Time:    {}",
+                tick.timestamp.format("%Y-%m-%d %H:%M:%S%.3f UTC")
+            );
+            println!("  Bid:     {}", tick.bid);
+            println!("  Ask:     {}", tick.ask);
+            println!(
+                "  Spread:  {:.1} points",
+                tick.spread() * instrument.decimal_factor_f64()
+            );
+            return Ok(());
+        }
+    }
+
+    println!("  No data in the last {LIVE_LOOKBACK_HOURS} hours");
     Ok(())
 }
@@ -0,0 +1,88 @@
+//! Instruments command implementation.
+//!
+//! This module handles exporting the instrument registry, so downstream
+//! systems can use exactly the metadata paracas normalizes prices with.
+
+use crate::display::parse_category;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use paracas_lib::prelude::*;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Output format for the instrument export.
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Export the instrument registry, optionally filtered by category.
+///
+/// Instruments are sorted by ID. Writes to `output`, or stdout if omitted.
+pub(crate) fn export_instruments(
+    format: ExportFormat,
+    category: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let registry = InstrumentRegistry::global();
+    let mut instruments: Vec<_> = match category {
+        Some(cat) => registry.by_category(parse_category(cat)?).collect(),
+        None => registry.all().collect(),
+    };
+    instruments.sort_by(|a, b| a.id().cmp(b.id()));
+
+    let data = match format {
+        ExportFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&instruments)?;
+            json.push('\n');
+            json
+        }
+        ExportFormat::Csv => to_csv(&instruments),
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, data)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!(
+                "Exported {} instruments to {}",
+                instruments.len(),
+                path.display()
+            );
+        }
+        None => print!("{data}"),
+    }
+    Ok(())
+}
+
+/// Renders instruments as CSV with a header row.
+fn to_csv(instruments: &[&Instrument]) -> String {
+    let mut csv = String::from("id,name,description,category,decimal_factor,start_tick_date\n");
+    for instrument in instruments {
+        let start = instrument
+            .start_tick_date()
+            .map(|date| date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+            .unwrap_or_default();
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            csv_field(instrument.id()),
+            csv_field(instrument.name()),
+            csv_field(instrument.description()),
+            instrument.category(),
+            instrument.decimal_factor(),
+            start
+        );
+    }
+    csv
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -1,5 +1,7 @@
 //! Job management commands (pause, resume, kill, clean).
 
+use crate::commands::daemon_run::check_format;
+use crate::config::Locations;
 use anyhow::{Context, Result};
 use inquire::Select;
 use paracas_daemon::{DaemonSpawner, DownloadJob, JobStatus, StateManager};
@@ -96,7 +98,9 @@ pub(crate) fn resume_job(state: &StateManager, job_id: &str) -> Result<()> {
 
 /// Respawn a job that needs to be resumed but whose process is dead.
 fn respawn_job(state: &StateManager, job: &mut DownloadJob) -> Result<()> {
-    let spawner = DaemonSpawner::new(state.clone()).context("Failed to create daemon spawner")?;
+    let spawner = DaemonSpawner::new(state.clone())
+        .context("Failed to create daemon spawner")?
+        .with_format_check(check_format);
 
     // Reset job status to pending so it can be picked up
     job.status = JobStatus::Pending;
@@ -249,9 +253,13 @@ fn prompt_job_selection(
 }
 
 /// Execute the job management command.
-pub(crate) fn job_command(action: &str, job_id: Option<&str>, all: bool) -> Result<()> {
-    let state_manager =
-        StateManager::with_default_path().context("Failed to initialize state manager")?;
+pub(crate) fn job_command(
+    action: &str,
+    job_id: Option<&str>,
+    all: bool,
+    locations: &Locations,
+) -> Result<()> {
+    let state_manager = locations.state_manager()?;
 
     match action {
         "pause" => {
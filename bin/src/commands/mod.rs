@@ -3,7 +3,10 @@
 pub(crate) mod daemon_run;
 pub(crate) mod download;
 pub(crate) mod download_all;
+pub(crate) mod formats;
 pub(crate) mod info;
+pub(crate) mod instruments;
 pub(crate) mod job;
 pub(crate) mod list;
 pub(crate) mod status;
+pub(crate) mod verify;
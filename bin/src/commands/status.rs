@@ -1,5 +1,7 @@
 //! Background job status command.
 
+use crate::config::Locations;
+use crate::memory::format_memory;
 use anyhow::{Context, Result};
 use inquire::Select;
 use paracas_daemon::{DownloadJob, JobStatus, StateManager};
@@ -11,9 +13,9 @@ pub(crate) fn status(
     show_all: bool,
     follow: Option<u64>,
     cancel_id: Option<&str>,
+    locations: &Locations,
 ) -> Result<()> {
-    let state_manager =
-        StateManager::with_default_path().context("Failed to initialize state manager")?;
+    let state_manager = locations.state_manager()?;
 
     // Handle cancellation request
     // cancel_id is Some("") when --cancel is passed without a value
@@ -66,6 +68,9 @@ fn show_job_detail(state: &StateManager, job_id: &str) -> Result<()> {
             .map(|p| p.display().to_string())
             .unwrap_or_else(|| "N/A".into())
     );
+    if let Some(memory) = &job.memory {
+        println!("{}", format_memory(memory));
+    }
 
     println!("\nTasks:");
     for (i, task) in job.tasks.iter().enumerate() {
@@ -0,0 +1,179 @@
+//! Verify command implementation.
+//!
+//! This module checks the raw bi5 files in the cache against the coverage
+//! manifests recorded as they were downloaded, and reports which hours of a
+//! range are archived. Files are checked in parallel, and checksum runs can
+//! be interrupted with Ctrl-C and resumed.
+
+use crate::config::Locations;
+use crate::display::parse_range;
+use crate::signal::shutdown_token;
+use anyhow::{Context, Result, bail};
+use indicatif::{ProgressBar, ProgressStyle};
+use paracas_lib::prelude::*;
+use paracas_lib::{
+    CoverageManifest, IntegrityIssue, IntegrityProblem, MANIFEST_FILE, VerifyOptions,
+};
+use std::path::Path;
+
+/// Maximum number of failed hours listed per instrument.
+const MAX_LISTED_ISSUES: usize = 20;
+
+/// Verify cached files of `instruments`, or of every cached instrument.
+///
+/// Coverage is reported for the range between `start` and `end` when both
+/// are given. Fails if any stored file does not match its manifest entry.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify(
+    instruments: &[String],
+    start: Option<&str>,
+    end: Option<&str>,
+    checksums: bool,
+    threads: Option<usize>,
+    resume: bool,
+    locations: &Locations,
+    quiet: bool,
+) -> Result<()> {
+    let Some(cache_dir) = &locations.cache_dir else {
+        bail!("No cache directory configured (use --cache-dir or cache_dir in the config file)");
+    };
+    let ids = if instruments.is_empty() {
+        cached_instruments(cache_dir)?
+    } else {
+        instruments.to_vec()
+    };
+    if ids.is_empty() {
+        bail!("No coverage manifests found in {}", cache_dir.display());
+    }
+    let range = match (start, end) {
+        (Some(start), Some(end)) => Some(parse_range(start, end)?),
+        _ => None,
+    };
+
+    let mut options = VerifyOptions::default()
+        .with_checksums(checksums)
+        .with_resume(resume)
+        .with_cancellation(shutdown_token());
+    if let Some(threads) = threads {
+        options = options.with_threads(threads);
+    }
+
+    let mut failed = 0;
+    let mut skipped = 0;
+    for id in &ids {
+        let manifest = CoverageManifest::load(cache_dir, id)
+            .with_context(|| format!("Failed to read manifest of {id}"))?;
+        println!(
+            "{}: {} hours recorded",
+            manifest.instrument(),
+            manifest.len()
+        );
+
+        if let Some(range) = range {
+            let instrument = InstrumentRegistry::global()
+                .get(id)
+                .with_context(|| format!("Unknown instrument: {id}"))?;
+            let hours = StreamOptions::for_instrument(instrument).hours(range);
+            let coverage = manifest.coverage(hours);
+            println!(
+                "  Coverage of {range}: {}/{} hours ({} with data, {} empty)",
+                coverage.covered(),
+                coverage.covered() + coverage.missing.len(),
+                coverage.with_data,
+                coverage.empty
+            );
+            if let Some(first) = coverage.missing.first() {
+                println!(
+                    "  Missing: {} hours, first {}",
+                    coverage.missing.len(),
+                    first.format("%Y-%m-%d %H:00 UTC")
+                );
+            }
+        }
+
+        let progress = progress_bar(&manifest, quiet);
+        let report = manifest
+            .verify_with(&options, |entry| progress.inc(entry.len))
+            .with_context(|| format!("Failed to verify {id}"))?;
+        progress.finish_and_clear();
+
+        for issue in report.issues.iter().take(MAX_LISTED_ISSUES) {
+            println!("  {}", format_issue(issue));
+        }
+        if report.issues.len() > MAX_LISTED_ISSUES {
+            println!("  ... and {} more", report.issues.len() - MAX_LISTED_ISSUES);
+        }
+        failed += report.issues.len();
+        skipped += report.skipped;
+
+        if !report.completed {
+            let done = report.checked + report.skipped;
+            let hint = if checksums {
+                "; rerun with --resume to continue"
+            } else {
+                ""
+            };
+            bail!(
+                "Interrupted after {done} of {} hours of {id}{hint}",
+                manifest.len()
+            );
+        }
+    }
+
+    if failed > 0 {
+        bail!("{failed} cached files failed verification");
+    }
+    let checked = if checksums { "checksums" } else { "sizes" };
+    if skipped > 0 {
+        println!(
+            "All cached files match their manifests ({checked} checked, {skipped} verified earlier)"
+        );
+    } else {
+        println!("All cached files match their manifests ({checked} checked)");
+    }
+    Ok(())
+}
+
+/// Create a progress bar over the bytes recorded in a manifest.
+fn progress_bar(manifest: &CoverageManifest, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let total = manifest.entries().map(|entry| entry.len).sum();
+    let pb = ProgressBar::new(total);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .expect("Invalid progress template")
+            .progress_chars("=>-"),
+    );
+    pb
+}
+
+/// Returns the instruments with a coverage manifest in the cache, sorted.
+fn cached_instruments(cache_dir: &Path) -> Result<Vec<String>> {
+    let entries = std::fs::read_dir(cache_dir)
+        .with_context(|| format!("Failed to read cache directory {}", cache_dir.display()))?;
+    let mut ids: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(MANIFEST_FILE).is_file())
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_lowercase))
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Describe a failed hour on one line.
+fn format_issue(issue: &IntegrityIssue) -> String {
+    let problem = match &issue.problem {
+        IntegrityProblem::Missing => "file missing".to_string(),
+        IntegrityProblem::Size { expected, actual } => {
+            format!("size {actual} bytes, expected {expected}")
+        }
+        IntegrityProblem::Checksum { expected, actual } => {
+            format!("sha256 {actual:.12}, expected {expected:.12}")
+        }
+        IntegrityProblem::Unreadable(error) => format!("unreadable: {error}"),
+    };
+    format!("{}: {problem}", issue.hour.format("%Y-%m-%d %H:00 UTC"))
+}
@@ -0,0 +1,109 @@
+//! Configuration file and on-disk locations.
+//!
+//! Every location paracas writes to can be set in a JSON configuration file
+//! and overridden by the global `--config`, `--state-dir` and `--cache-dir`
+//! flags, so isolated contexts (per-project archives, CI sandboxes) can
+//! coexist on one machine.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use directories::ProjectDirs;
+use paracas_daemon::StateManager;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// File name of the configuration file in the default config directory.
+const CONFIG_FILE: &str = "config.json";
+
+/// Global flags overriding on-disk locations.
+#[derive(Args, Clone, Debug, Default)]
+pub(crate) struct LocationArgs {
+    /// Configuration file [default: <config dir>/paracas/config.json]
+    #[arg(long, global = true, value_name = "FILE")]
+    pub(crate) config: Option<PathBuf>,
+
+    /// Directory for background jobs, logs and the terms acknowledgement
+    #[arg(long, global = true, value_name = "DIR")]
+    pub(crate) state_dir: Option<PathBuf>,
+
+    /// Cache raw bi5 files in this directory and reuse them on later runs
+    #[arg(long, global = true, value_name = "DIR")]
+    pub(crate) cache_dir: Option<PathBuf>,
+}
+
+/// Contents of the configuration file.
+///
+/// Relative paths are resolved against the directory containing the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    /// Directory for background jobs, logs and the terms acknowledgement.
+    state_dir: Option<PathBuf>,
+    /// Directory for cached raw bi5 files.
+    cache_dir: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    /// Loads the file at `path`.
+    ///
+    /// A missing file is only an error when `required` is set.
+    fn load(path: &Path, required: bool) -> Result<Self> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Self::default());
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read config {}", path.display()));
+            }
+        };
+        let mut config: Self = serde_json::from_str(&data)
+            .with_context(|| format!("Invalid config {}", path.display()))?;
+
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+        config.state_dir = config.state_dir.map(|dir| base.join(dir));
+        config.cache_dir = config.cache_dir.map(|dir| base.join(dir));
+        Ok(config)
+    }
+}
+
+/// Resolved on-disk locations.
+#[derive(Debug, Clone)]
+pub(crate) struct Locations {
+    /// Directory for background jobs, logs and the terms acknowledgement.
+    pub(crate) state_dir: PathBuf,
+    /// Directory for cached raw bi5 files, if caching is enabled.
+    pub(crate) cache_dir: Option<PathBuf>,
+}
+
+impl Locations {
+    /// Resolves locations from flags, then the config file, then defaults.
+    pub(crate) fn resolve(args: &LocationArgs) -> Result<Self> {
+        let config = match &args.config {
+            Some(path) => ConfigFile::load(path, true)?,
+            None => match default_config_path() {
+                Some(path) => ConfigFile::load(&path, false)?,
+                None => ConfigFile::default(),
+            },
+        };
+
+        Ok(Self {
+            state_dir: args
+                .state_dir
+                .clone()
+                .or(config.state_dir)
+                .unwrap_or_else(StateManager::default_path),
+            cache_dir: args.cache_dir.clone().or(config.cache_dir),
+        })
+    }
+
+    /// Opens the state manager, creating its directories if needed.
+    pub(crate) fn state_manager(&self) -> Result<StateManager> {
+        StateManager::new(self.state_dir.clone()).context("Failed to initialize state manager")
+    }
+}
+
+/// Returns the default configuration file path for the platform.
+fn default_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "paracas").map(|dirs| dirs.config_dir().join(CONFIG_FILE))
+}
@@ -1,11 +1,27 @@
 //! Display utilities and output formatting for the paracas CLI.
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
+use chrono::NaiveDate;
 use clap::ValueEnum;
+use paracas_daemon::{AggregationSettings, ReturnsMode};
+use paracas_estimate::{DownloadEstimate, Estimator, TimeframeDefaults};
 use paracas_lib::prelude::*;
+use paracas_lib::{
+    DEFAULT_SPIKE_WINDOW, DerivedColumns, DynFormatter, FileFormatter, FormatError, GapFiller,
+    HeikinAshi, HourError, IndicatorColumn, IndicatorSet, InvalidTickAction, LeanFormatter,
+    LeanResolution, LeanTickType, MtCsvFormatter, MtLayout, MtServerTime, MultiTimeframeAggregator,
+    NinjaTraderFormatter, OutputCompression, ParallelAggregator, ParquetSettings,
+    PriceBarAggregator, PriceReturn, ReturnCalculator, ReturnKind, Revision, SkipReason,
+    SplitLimits, StatsSnapshot, TickFilter, TradingViewFormatter, VolumeBarAggregator,
+    VolumeProfile, VolumeProfileAggregator, read_first_line,
+};
+use std::borrow::Cow;
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::PathBuf;
+use std::io::{BufWriter, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Prefix of the instrument directory of a partitioned dataset.
+const DATASET_PREFIX: &str = "instrument=";
 
 /// Output format for downloaded data.
 #[derive(Clone, Copy, ValueEnum)]
@@ -14,6 +30,39 @@ pub(crate) enum Format {
     Json,
     Ndjson,
     Parquet,
+    /// Parquet files partitioned by instrument and date
+    ParquetDataset,
+    /// SQLite database with indexed tables
+    Sqlite,
+    /// DuckDB database
+    #[value(name = "duckdb")]
+    DuckDb,
+    /// Apache Avro container files
+    Avro,
+    /// Stream of MessagePack records
+    Msgpack,
+    /// HDF5 file with a group per instrument
+    Hdf5,
+    /// Excel workbook
+    Xlsx,
+    /// Length-delimited protobuf messages
+    Protobuf,
+    /// Apache ORC file
+    Orc,
+    /// MetaTrader 4 History Center CSV
+    Mt4,
+    /// MetaTrader 5 CSV
+    Mt5,
+    /// MetaTrader 4 history file
+    Hst,
+    /// NinjaTrader import text
+    #[value(name = "ninjatrader")]
+    NinjaTrader,
+    /// TradingView chart CSV
+    #[value(name = "tradingview")]
+    TradingView,
+    /// QuantConnect Lean CSV, one file per day below hourly bars
+    Lean,
 }
 
 impl Format {
@@ -23,101 +72,1420 @@ impl Format {
             Self::Csv => "csv",
             Self::Json => "json",
             Self::Ndjson => "ndjson",
-            Self::Parquet => "parquet",
+            Self::Parquet | Self::ParquetDataset => "parquet",
+            Self::Sqlite => "sqlite",
+            Self::DuckDb => "duckdb",
+            Self::Avro => "avro",
+            Self::Msgpack => "msgpack",
+            Self::Hdf5 => "h5",
+            Self::Xlsx => "xlsx",
+            Self::Protobuf => "pb",
+            Self::Orc => "orc",
+            Self::Mt4 | Self::Mt5 | Self::TradingView | Self::Lean => "csv",
+            Self::Hst => "hst",
+            Self::NinjaTrader => "txt",
+        }
+    }
+
+    /// Returns true if metadata is embedded in the file rather than written
+    /// as a JSON sidecar.
+    pub(crate) const fn embeds_metadata(self) -> bool {
+        matches!(
+            self,
+            Self::Parquet | Self::ParquetDataset | Self::Avro | Self::Hdf5 | Self::Xlsx
+        )
+    }
+
+    /// Returns true if output is a database whose tables records are
+    /// inserted into.
+    pub(crate) const fn is_database(self) -> bool {
+        matches!(self, Self::Sqlite | Self::DuckDb)
+    }
+
+    /// Returns true if output is a directory of partitions rather than a
+    /// single file.
+    pub(crate) const fn is_dataset(self) -> bool {
+        matches!(self, Self::ParquetDataset)
+    }
+
+    /// Returns true if output is for MetaTrader, whose timestamps are in
+    /// server time.
+    pub(crate) const fn is_metatrader(self) -> bool {
+        matches!(self, Self::Mt4 | Self::Mt5 | Self::Hst)
+    }
+
+    /// Returns true if output is for the import of a trading platform, which
+    /// holds ticks or bars without returns or indicators.
+    pub(crate) const fn is_platform(self) -> bool {
+        self.is_metatrader() || matches!(self, Self::NinjaTrader | Self::TradingView | Self::Lean)
+    }
+
+    /// Returns the format if it can hold the columns requested in
+    /// `settings`: volume profiles need a format with nested columns,
+    /// datasets hold only ticks and plain bars, and trading platform output
+    /// only ticks and bars without indicators.
+    pub(crate) fn ensure_supports(self, settings: &AggregationSettings) -> Result<Self> {
+        if settings.volume_profile_pips.is_some() && !OutputFormat::from(self).has_nested_columns()
+        {
+            bail!("--volume-profile needs json, ndjson or parquet output");
+        }
+        if self.is_platform() && (settings.returns.is_some() || !settings.indicators.is_empty()) {
+            bail!("{self} output holds ticks and bars, without returns or indicators");
+        }
+        if self.is_dataset()
+            && (settings.volume_profile_pips.is_some()
+                || settings.returns.is_some()
+                || !settings.indicators.is_empty())
+        {
+            bail!(
+                "parquet-dataset output holds ticks and bars, without returns, indicators or volume profiles"
+            );
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if `parquet` applies to it: non-default Parquet
+    /// settings need Parquet output and a valid codec level.
+    pub(crate) fn ensure_parquet(self, parquet: &ParquetSettings) -> Result<Self> {
+        if !matches!(self, Self::Parquet | Self::ParquetDataset) {
+            if *parquet != ParquetSettings::default() {
+                bail!("--parquet-* options apply to parquet output only");
+            }
+            return Ok(self);
+        }
+        #[cfg(feature = "parquet")]
+        ParquetFormatter::new().with_settings(parquet)?;
+        Ok(self)
+    }
+
+    /// Returns the format if `server_time` applies to it: timestamps are
+    /// converted to server time for MetaTrader output only.
+    pub(crate) fn ensure_server_time(self, server_time: MtServerTime) -> Result<Self> {
+        if server_time != MtServerTime::Utc && !self.is_metatrader() {
+            bail!("--server-time applies to mt4, mt5 and hst output only");
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if it can start with a metadata line: CSV, with
+    /// a comment line, or NDJSON, with a metadata record.
+    pub(crate) fn ensure_metadata_header(self, metadata_header: bool) -> Result<Self> {
+        if metadata_header && !matches!(self, Self::Csv | Self::Ndjson) {
+            bail!("--metadata-header needs csv or ndjson output");
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if it can hold `derived` tick columns: CSV or
+    /// Parquet.
+    pub(crate) fn ensure_derived_columns(self, derived: DerivedColumns) -> Result<Self> {
+        if !derived.is_empty() && !matches!(self, Self::Csv | Self::Parquet | Self::ParquetDataset)
+        {
+            bail!("--spread-column and --mid-column need csv, parquet or parquet-dataset output");
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if it can be appended to: CSV, NDJSON whose
+    /// records are appended line by line, or database tables.
+    pub(crate) fn ensure_append(self, append: bool) -> Result<Self> {
+        if append && !(matches!(self, Self::Csv | Self::Ndjson) || self.is_database()) {
+            bail!("--append needs csv, ndjson, sqlite or duckdb output");
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if its output can be split into files of
+    /// `split` limits: streamed formats of plain ticks and bars, written
+    /// afresh rather than appended to.
+    pub(crate) fn ensure_split(
+        self,
+        split: SplitLimits,
+        append: bool,
+        settings: &AggregationSettings,
+    ) -> Result<Self> {
+        if !split.is_split() {
+            return Ok(self);
+        }
+        if split.max_rows == Some(0) || split.max_bytes == Some(0) {
+            bail!("--max-file-rows and --max-file-bytes must be positive");
+        }
+        if !matches!(self, Self::Csv | Self::Json | Self::Ndjson | Self::Parquet) {
+            bail!("--max-file-rows and --max-file-bytes need csv, json, ndjson or parquet output");
+        }
+        if append {
+            bail!("split output cannot be appended to");
+        }
+        if settings.volume_profile_pips.is_some()
+            || settings.returns.is_some()
+            || !settings.indicators.is_empty()
+        {
+            bail!(
+                "split output holds ticks and bars, without returns, indicators or volume profiles"
+            );
+        }
+        Ok(self)
+    }
+
+    /// Returns the format if it can be written with `compression`: only
+    /// text formats are compressed as a whole, Parquet compresses its pages.
+    pub(crate) fn ensure_compression(self, compression: OutputCompression) -> Result<Self> {
+        if compression != OutputCompression::None
+            && matches!(self, Self::Parquet | Self::ParquetDataset)
+        {
+            bail!(
+                "{compression} compression applies to csv, json and ndjson output; parquet is already compressed"
+            );
+        }
+        if compression != OutputCompression::None
+            && !matches!(self, Self::Csv | Self::Json | Self::Ndjson)
+        {
+            bail!("{compression} compression applies to csv, json and ndjson output, not {self}");
+        }
+        if let Some(feature) = compression.required_feature()
+            && !compression.is_available()
+        {
+            return Err(FormatError::CompressionUnavailable {
+                compression,
+                feature,
+            }
+            .into());
+        }
+        Ok(self)
+    }
+
+    /// Returns the file name `<name>.<format>`, with the extension of
+    /// `compression` appended, or the instrument directory
+    /// `instrument=<name>` of a dataset.
+    pub(crate) fn file_name(self, name: &str, compression: OutputCompression) -> PathBuf {
+        if self.is_dataset() {
+            return PathBuf::from(format!("{DATASET_PREFIX}{name}"));
+        }
+        with_compression(
+            PathBuf::from(format!("{name}.{}", self.extension())),
+            compression,
+        )
+    }
+
+    /// Returns the format if it is compiled in, or an error naming the
+    /// cargo feature to build with.
+    pub(crate) fn ensure_available(self) -> Result<Self> {
+        OutputFormat::from(self).ensure_available()?;
+        Ok(self)
+    }
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Csv => Self::Csv,
+            Format::Json => Self::Json,
+            Format::Ndjson => Self::Ndjson,
+            Format::Parquet | Format::ParquetDataset => Self::Parquet,
+            Format::Sqlite => Self::Sqlite,
+            Format::DuckDb => Self::DuckDb,
+            Format::Avro => Self::Avro,
+            Format::Msgpack => Self::Msgpack,
+            Format::Hdf5 => Self::Hdf5,
+            Format::Xlsx => Self::Xlsx,
+            Format::Protobuf => Self::Protobuf,
+            Format::Orc => Self::Orc,
+            Format::Mt4 => Self::Mt4,
+            Format::Mt5 => Self::Mt5,
+            Format::Hst => Self::Hst,
+            Format::NinjaTrader => Self::NinjaTrader,
+            Format::TradingView => Self::TradingView,
+            Format::Lean => Self::Lean,
         }
     }
 }
 
 impl std::fmt::Display for Format {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.extension())
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{}", self.extension()),
+        }
+    }
+}
+
+/// Return `ticks` in time order, copying and sorting them only if needed.
+///
+/// Unordered streams deliver hours as they complete, while every aggregator
+/// expects ticks in time order.
+fn time_ordered(ticks: &[Tick]) -> Cow<'_, [Tick]> {
+    if ticks.is_sorted_by_key(|tick| tick.timestamp) {
+        Cow::Borrowed(ticks)
+    } else {
+        let mut ticks = ticks.to_vec();
+        ticks.sort_by_key(|tick| tick.timestamp);
+        Cow::Owned(ticks)
     }
 }
 
 /// Aggregate ticks into OHLCV bars using the given timeframe.
-pub(crate) fn aggregate_ticks(ticks: &[Tick], timeframe: Timeframe) -> Vec<Ohlcv> {
-    let mut aggregator = TickAggregator::new(timeframe);
-    let mut bars = Vec::new();
+///
+/// `pip_size` converts the pip sizes of Renko and range bars into prices.
+/// Ticks out of time order are sorted first.
+pub(crate) fn aggregate_ticks(
+    ticks: &[Tick],
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    pip_size: f64,
+) -> Vec<Ohlcv> {
+    let ticks = &*time_ordered(ticks);
+    let price_bars = match (settings.renko_pips, settings.range_pips) {
+        (Some(pips), _) => Some(PriceBarAggregator::renko(pips * pip_size)),
+        (None, Some(pips)) => Some(PriceBarAggregator::range(pips * pip_size)),
+        (None, None) => None,
+    };
+    if let Some(aggregator) = price_bars {
+        let mut aggregator = aggregator
+            .with_price_source(settings.price_source)
+            .with_side_volumes(settings.side_volumes)
+            .with_tick_times(settings.tick_times)
+            .with_price_stats(settings.price_stats);
+        let mut bars: Vec<_> = ticks.iter().flat_map(|t| aggregator.process(*t)).collect();
+        bars.extend(aggregator.finish());
+        return bars;
+    }
 
-    for tick in ticks {
-        if let Some(bar) = aggregator.process(*tick) {
-            bars.push(bar);
+    let activity = match (settings.volume_bars, settings.notional_bars) {
+        (Some(volume), _) => Some(VolumeBarAggregator::new(volume)),
+        (None, Some(notional)) => Some(VolumeBarAggregator::notional(notional)),
+        (None, None) => None,
+    };
+    if let Some(aggregator) = activity {
+        let mut aggregator = aggregator
+            .with_price_source(settings.price_source)
+            .with_side_volumes(settings.side_volumes)
+            .with_tick_times(settings.tick_times)
+            .with_price_stats(settings.price_stats);
+        let mut bars: Vec<_> = ticks
+            .iter()
+            .filter_map(|t| aggregator.process(*t))
+            .collect();
+        bars.extend(aggregator.finish());
+        return bars;
+    }
+
+    // Microstructure metrics need every tick's predecessor, so they are
+    // aggregated in a single pass
+    let bars = if settings.microstructure {
+        let mut aggregator = tick_aggregator(timeframe, settings);
+        let mut bars: Vec<_> = ticks
+            .iter()
+            .filter_map(|t| aggregator.process(*t))
+            .collect();
+        bars.extend(aggregator.finish());
+        bars
+    } else {
+        ParallelAggregator::new(timeframe)
+            .with_price_source(settings.price_source)
+            .with_side_volumes(settings.side_volumes)
+            .with_tick_times(settings.tick_times)
+            .with_price_stats(settings.price_stats)
+            .aggregate_ticks(ticks)
+    };
+    GapFiller::fill(timeframe, settings.gap_fill, &bars)
+}
+
+/// Create the filter for the tick checks enabled in `settings`.
+pub(crate) fn tick_filter(settings: &AggregationSettings) -> TickFilter {
+    let filter = TickFilter::new()
+        .with_drop_zero_spreads(settings.drop_zero_spreads)
+        .with_drop_crossed(settings.drop_crossed);
+    match settings.spike_mads {
+        Some(mads) => {
+            filter.with_spike_filter(mads, settings.spike_window.unwrap_or(DEFAULT_SPIKE_WINDOW))
         }
+        None => filter,
+    }
+}
+
+/// Create the aggregator for time bars of `timeframe`.
+pub(crate) const fn tick_aggregator(
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+) -> TickAggregator {
+    TickAggregator::new(timeframe)
+        .with_price_source(settings.price_source)
+        .with_side_volumes(settings.side_volumes)
+        .with_tick_times(settings.tick_times)
+        .with_price_stats(settings.price_stats)
+        .with_microstructure(settings.microstructure)
+}
+
+/// Fill the gaps of time bars aggregated as they were downloaded and write
+/// them, or their return series if requested.
+pub(crate) fn write_time_bars(
+    bars: &[Ohlcv],
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let bars = GapFiller::fill(timeframe, settings.gap_fill, bars);
+    write_bars(&bars, settings, output, format, metadata, options)
+}
+
+/// Aggregate ticks and write the bars, or their return series if requested.
+///
+/// `pip_size` is the instrument's, taken apart from `metadata`, which drops
+/// the decimal factor of aliased instruments.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_aggregated(
+    ticks: &[Tick],
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    pip_size: f64,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    if let Some(pips) = settings.volume_profile_pips {
+        let mut aggregator =
+            VolumeProfileAggregator::new(tick_aggregator(timeframe, settings), pips * pip_size);
+        let mut profiled: Vec<_> = time_ordered(ticks)
+            .iter()
+            .filter_map(|t| aggregator.process(*t))
+            .collect();
+        profiled.extend(aggregator.finish());
+        return write_profiled(
+            &profiled, timeframe, settings, output, format, metadata, options,
+        );
     }
+    let bars = aggregate_ticks(ticks, timeframe, settings, pip_size);
+    write_bars(&bars, settings, output, format, metadata, options)
+}
 
-    if let Some(bar) = aggregator.finish() {
-        bars.push(bar);
+/// Write time bars with the volume profile of each.
+fn write_profiled(
+    profiled: &[(Ohlcv, VolumeProfile)],
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let bars: Vec<_> = profiled.iter().map(|(bar, _)| *bar).collect();
+    let bars = GapFiller::fill(timeframe, settings.gap_fill, &bars);
+    // Bars filled into gaps have empty profiles
+    let mut profiled = profiled.iter().peekable();
+    let profiles: Vec<_> = bars
+        .iter()
+        .map(|bar| {
+            profiled
+                .next_if(|(next, _)| next.timestamp == bar.timestamp)
+                .map(|(_, profile)| profile.clone())
+                .unwrap_or_default()
+        })
+        .collect();
+    let bars = HeikinAshi::apply(settings.candle_style, &bars);
+    write_ohlcv(&bars, &[], &profiles, output, format, metadata, options)
+}
+
+/// Aggregate ticks into several timeframes in one pass and write one file
+/// per timeframe, named after the output with the timeframe appended.
+///
+/// Returns the paths written, in the order of `timeframes`.
+pub(crate) fn write_timeframes(
+    ticks: &[Tick],
+    timeframes: &[Timeframe],
+    settings: &AggregationSettings,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let series = MultiTimeframeAggregator::new(timeframes.iter().copied())
+        .with_price_source(settings.price_source)
+        .with_side_volumes(settings.side_volumes)
+        .with_tick_times(settings.tick_times)
+        .with_price_stats(settings.price_stats)
+        .with_microstructure(settings.microstructure)
+        .aggregate(time_ordered(ticks).iter().copied());
+    let files = series
+        .into_iter()
+        .map(|(timeframe, bars)| {
+            let bars = GapFiller::fill(timeframe, settings.gap_fill, &bars);
+            let path = timeframe_path(output, timeframe);
+            let metadata = metadata.clone().with_timeframe(timeframe);
+            write_bars(&bars, settings, &path, format, &metadata, options)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(files.concat())
+}
+
+/// Returns the output path for one of several timeframes, e.g.
+/// `eurusd_m5.csv` for `eurusd.csv` and `eurusd_m5.csv.gz` for
+/// `eurusd.csv.gz`.
+fn timeframe_path(output: &Path, timeframe: Timeframe) -> PathBuf {
+    let compression = OutputCompression::from_path(output);
+    if compression != OutputCompression::None {
+        let path = timeframe_path(&output.with_extension(""), timeframe);
+        return with_compression(path, compression);
+    }
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}_{timeframe}");
+    if let Some(extension) = output.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
     }
+    output.with_file_name(name)
+}
+
+/// Appends the extension of `compression` to `path` unless it already
+/// ends with it, e.g. `ticks.csv` to `ticks.csv.gz`.
+pub(crate) fn with_compression(path: PathBuf, compression: OutputCompression) -> PathBuf {
+    match compression.extension() {
+        Some(extension) if OutputCompression::from_path(&path) != compression => {
+            let mut path = path.into_os_string();
+            path.push(".");
+            path.push(extension);
+            PathBuf::from(path)
+        }
+        _ => path,
+    }
+}
+
+/// Write bars with any requested indicators, or their return series if
+/// requested.
+fn write_bars(
+    bars: &[Ohlcv],
+    settings: &AggregationSettings,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let bars = &HeikinAshi::apply(settings.candle_style, bars);
+    let Some(mode) = settings.returns else {
+        let indicators = IndicatorSet::compute(&settings.indicators, bars);
+        return write_ohlcv(bars, &indicators, &[], output, format, metadata, options);
+    };
+    let kind = match mode {
+        ReturnsMode::Log => ReturnKind::Log,
+        ReturnsMode::Diff => ReturnKind::Difference,
+    };
+    write_returns(
+        &ReturnCalculator::returns(kind, bars),
+        output,
+        format,
+        metadata,
+        options,
+    )
+}
 
-    bars
+/// Write ticks, or bars aggregated from them unless the output is raw ticks.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn write_output(
+    ticks: &[Tick],
+    output: &Path,
+    format: Format,
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    pip_size: f64,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    if timeframe.is_tick() && !settings.activity_bars() {
+        write_ticks(ticks, output, format, metadata, options)
+    } else {
+        write_aggregated(
+            ticks, timeframe, settings, pip_size, output, format, metadata, options,
+        )
+    }
 }
 
 /// Write ticks to a file in the specified format.
-pub(crate) fn write_ticks(ticks: &[Tick], output: &PathBuf, format: Format) -> Result<()> {
-    let file = File::create(output)?;
-    let writer = BufWriter::new(file);
+///
+/// Metadata is embedded in Parquet, Avro, HDF5 and Excel files and written as a
+/// JSON sidecar otherwise.
+pub(crate) fn write_ticks(
+    ticks: &[Tick],
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    if options.split.is_split() {
+        return write_split(Records::Ticks(ticks), output, format, metadata, options);
+    }
+    match format {
+        Format::Mt4 | Format::Hst | Format::TradingView => {
+            bail!("ticks cannot be written to {format}; aggregate them into bars")
+        }
+        Format::Lean => return write_lean(Records::Ticks(ticks), output, metadata, options),
+        #[cfg(feature = "parquet")]
+        Format::ParquetDataset => {
+            return write_dataset(output, metadata, options, |formatter, root, instrument| {
+                formatter.write_tick_dataset(ticks, root, instrument)
+            });
+        }
+        _ => match formatter(format, output, metadata, options)? {
+            Target::Stream(formatter) => {
+                formatter.write_ticks_dyn(ticks, &mut options.open(output)?)?;
+            }
+            Target::File(formatter) => formatter.write_ticks_to(ticks, output)?,
+        },
+    }
+
+    options.write_sidecar(output, format, metadata)?;
+
+    Ok(vec![output.to_path_buf()])
+}
 
+/// Write OHLCV bars to a file in the specified format, with indicator
+/// columns or, if any are given, the volume profile of each bar.
+///
+/// Metadata is embedded in Parquet, Avro, HDF5 and Excel files and written as a
+/// JSON sidecar otherwise.
+pub(crate) fn write_ohlcv(
+    bars: &[Ohlcv],
+    indicators: &[IndicatorColumn],
+    profiles: &[VolumeProfile],
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    if options.split.is_split() {
+        if !indicators.is_empty() || !profiles.is_empty() {
+            bail!("indicators and volume profiles cannot be written to split output");
+        }
+        return write_split(Records::Bars(bars), output, format, metadata, options);
+    }
     match format {
-        Format::Csv => {
-            let formatter = CsvFormatter::new();
-            formatter.write_ticks(ticks, writer)?;
+        Format::Lean => return write_lean(Records::Bars(bars), output, metadata, options),
+        Format::ParquetDataset if !indicators.is_empty() || !profiles.is_empty() => {
+            bail!("indicators and volume profiles cannot be written as a dataset")
         }
-        Format::Json => {
-            let formatter = JsonFormatter::new();
-            formatter.write_ticks(ticks, writer)?;
+        #[cfg(feature = "parquet")]
+        Format::ParquetDataset => {
+            return write_dataset(output, metadata, options, |formatter, root, instrument| {
+                formatter.write_ohlcv_dataset(bars, root, instrument)
+            });
         }
-        Format::Ndjson => {
-            let formatter = JsonFormatter::ndjson();
-            formatter.write_ticks(ticks, writer)?;
+        _ if !profiles.is_empty() && !OutputFormat::from(format).has_nested_columns() => {
+            bail!("volume profiles cannot be written to {format}")
         }
-        Format::Parquet => {
-            #[cfg(feature = "parquet")]
-            {
-                let formatter = ParquetFormatter::new();
-                formatter.write_ticks(ticks, writer)?;
+        _ => match formatter(format, output, metadata, options)? {
+            Target::Stream(formatter) => {
+                let writer = &mut options.open(output)?;
+                if profiles.is_empty() {
+                    formatter.write_ohlcv_dyn(bars, indicators, writer)?;
+                } else {
+                    formatter.write_ohlcv_with_profiles_dyn(bars, profiles, writer)?;
+                }
             }
-            #[cfg(not(feature = "parquet"))]
-            {
-                bail!("Parquet support not compiled in");
-            }
-        }
+            Target::File(formatter) => formatter.write_ohlcv_to(bars, indicators, output)?,
+        },
     }
 
-    Ok(())
+    options.write_sidecar(output, format, metadata)?;
+
+    Ok(vec![output.to_path_buf()])
 }
 
-/// Write OHLCV bars to a file in the specified format.
-pub(crate) fn write_ohlcv(bars: &[Ohlcv], output: &PathBuf, format: Format) -> Result<()> {
-    let file = File::create(output)?;
-    let writer = BufWriter::new(file);
+/// Ticks or bars written to split or per-day output.
+#[derive(Clone, Copy)]
+enum Records<'a> {
+    Ticks(&'a [Tick]),
+    Bars(&'a [Ohlcv]),
+}
 
-    match format {
+impl Records<'_> {
+    /// Write the records with `formatter` to numbered files of `output`.
+    fn write<F: StreamingFormatter>(
+        self,
+        formatter: &F,
+        split: SplitLimits,
+        output: &Path,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        match self {
+            Self::Ticks(ticks) => split.write_ticks(formatter, ticks, output),
+            Self::Bars(bars) => split.write_ohlcv(formatter, bars, output),
+        }
+    }
+
+    /// Return the records of each UTC day, with the day.
+    fn days(self) -> Vec<(NaiveDate, Self)> {
+        match self {
+            Self::Ticks(ticks) => ticks
+                .chunk_by(|a, b| a.timestamp.date_naive() == b.timestamp.date_naive())
+                .map(|day| (day[0].timestamp.date_naive(), Self::Ticks(day)))
+                .collect(),
+            Self::Bars(bars) => bars
+                .chunk_by(|a, b| a.timestamp.date_naive() == b.timestamp.date_naive())
+                .map(|day| (day[0].timestamp.date_naive(), Self::Bars(day)))
+                .collect(),
+        }
+    }
+}
+
+/// Write records to numbered files of `output`, e.g. `eurusd-0001.parquet`,
+/// each within the split limits of `options` and with its own sidecar.
+///
+/// Returns the paths written, in order.
+fn write_split(
+    records: Records<'_>,
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let files = match format {
         Format::Csv => {
-            let formatter = CsvFormatter::new();
-            formatter.write_ohlcv(bars, writer)?;
+            let formatter = csv_formatter(output, metadata, options)?;
+            records.write(&formatter, options.split, output)?
         }
         Format::Json => {
-            let formatter = JsonFormatter::new();
-            formatter.write_ohlcv(bars, writer)?;
+            let formatter = json_formatter(JsonFormatter::new(), output, metadata, options)?;
+            records.write(&formatter, options.split, output)?
         }
         Format::Ndjson => {
-            let formatter = JsonFormatter::ndjson();
-            formatter.write_ohlcv(bars, writer)?;
+            let formatter = json_formatter(JsonFormatter::ndjson(), output, metadata, options)?;
+            records.write(&formatter, options.split, output)?
         }
+        #[cfg(feature = "parquet")]
         Format::Parquet => {
-            #[cfg(feature = "parquet")]
-            {
-                let formatter = ParquetFormatter::new();
-                formatter.write_ohlcv(bars, writer)?;
+            let formatter = parquet_formatter(metadata, options)?;
+            records.write(&formatter, options.split, output)?
+        }
+        _ => bail!("{format} output cannot be split"),
+    };
+    for file in &files {
+        options.write_sidecar(file, format, metadata)?;
+    }
+    Ok(files)
+}
+
+/// Write records as QuantConnect Lean CSV: hour and daily bars to
+/// `output`, finer data to one file per UTC day beside it, named as Lean
+/// names the CSV in each day's zip archive, e.g.
+/// `20240102_eurusd_minute_quote.csv`. Lean reads rows in time order, so
+/// ticks, which arrive by hour in any order, are sorted first.
+fn write_lean(
+    records: Records<'_>,
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    let timeframe = metadata.timeframe.unwrap_or_default();
+    let Some(resolution) = LeanResolution::from_timeframe(timeframe) else {
+        bail!("lean output needs tick, s1, m1, h1 or d1 data, not {timeframe}");
+    };
+    let mut formatter = LeanFormatter::new().with_resolution(resolution);
+    if let Some(digits) = price_digits(metadata) {
+        formatter = formatter.with_digits(digits);
+    }
+    let sorted;
+    let records = match records {
+        Records::Ticks(ticks) => {
+            sorted = time_ordered(ticks);
+            Records::Ticks(&sorted)
+        }
+        records => records,
+    };
+    let write = |records, path: &Path| -> Result<()> {
+        match records {
+            Records::Ticks(ticks) => formatter.write_ticks(ticks, options.open(path)?)?,
+            Records::Bars(bars) => formatter.write_ohlcv(bars, options.open(path)?)?,
+        }
+        options.write_sidecar(path, Format::Lean, metadata)
+    };
+    if !resolution.is_daily_file() {
+        write(records, output)?;
+        return Ok(vec![output.to_path_buf()]);
+    }
+
+    let tick_type = match records {
+        Records::Ticks(_) => LeanTickType::Quote,
+        Records::Bars(_) => LeanTickType::Trade,
+    };
+    let symbol = metadata.instrument.clone().unwrap_or_else(|| {
+        output
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    });
+    let mut files = Vec::new();
+    for (date, day) in records.days() {
+        let path = output.with_file_name(resolution.entry_name(&symbol, date, tick_type));
+        write(day, &path)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Options of written files beyond their format and compression.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct WriteOptions {
+    /// Parquet writer tuning.
+    pub(crate) parquet: ParquetSettings,
+    /// Whether every row carries the instrument in a `symbol` column.
+    pub(crate) symbol_column: bool,
+    /// Whether to append to existing CSV or NDJSON files.
+    pub(crate) append: bool,
+    /// Time zone of MetaTrader timestamps.
+    pub(crate) server_time: MtServerTime,
+    /// Whether CSV and NDJSON files start with a metadata line.
+    pub(crate) metadata_header: bool,
+    /// Tick columns derived from the quotes in CSV and Parquet files.
+    pub(crate) derived: DerivedColumns,
+    /// Limits of each file of split output.
+    pub(crate) split: SplitLimits,
+}
+
+impl WriteOptions {
+    /// Return the value of the symbol column for `metadata`, if requested.
+    fn symbol(self, metadata: &OutputMetadata) -> Option<&str> {
+        metadata
+            .instrument
+            .as_deref()
+            .filter(|_| self.symbol_column)
+    }
+
+    /// Return the first line of `output` when appending to it, which
+    /// appended records are checked against.
+    fn existing_line(self, output: &Path) -> Result<Option<String>> {
+        if !self.append {
+            return Ok(None);
+        }
+        read_first_line(output).with_context(|| format!("Failed to read {}", output.display()))
+    }
+
+    /// Open `output` for writing, appending to it if requested.
+    fn open(self, output: &Path) -> std::io::Result<BufWriter<File>> {
+        File::options()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(output)
+            .map(BufWriter::new)
+    }
+
+    /// Write the metadata sidecar of a text `output`. When appending, an
+    /// existing sidecar describing the file's first write is kept.
+    fn write_sidecar(self, output: &Path, format: Format, metadata: &OutputMetadata) -> Result<()> {
+        let keep = self.append && OutputMetadata::sidecar_path(output).exists();
+        if format.is_metatrader() {
+            let mut metadata = metadata.clone();
+            metadata.timezone = self.server_time.to_string();
+            metadata.write_sidecar(output)?;
+        } else if !format.embeds_metadata() && !keep {
+            metadata.write_sidecar(output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Return the decimal places of the prices described by `metadata`: those
+/// of the instrument, plus one for mid prices, which may fall halfway
+/// between two quotes.
+fn price_digits(metadata: &OutputMetadata) -> Option<u32> {
+    let digits = Instrument::price_digits_for(metadata.decimal_factor?);
+    Some(digits + u32::from(metadata.price_source.as_deref() == Some("mid")))
+}
+
+/// Configured formatter of a format, by where it writes records.
+enum Target {
+    /// Formatter writing to the output file as a stream.
+    Stream(Box<dyn DynFormatter>),
+    /// Formatter writing to the output path itself: databases and HDF5.
+    #[cfg_attr(
+        not(any(feature = "sqlite", feature = "duckdb", feature = "hdf5")),
+        allow(dead_code)
+    )]
+    File(Box<dyn FileFormatter>),
+}
+
+/// Build the formatter of `format` for `metadata` and `options`, or an
+/// error naming the cargo feature to build with if it is not compiled in.
+///
+/// Lean and dataset output are written to several files, without a single
+/// formatter.
+fn formatter(
+    format: Format,
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Target> {
+    format.ensure_available()?;
+    Ok(match format {
+        Format::Csv => Target::Stream(Box::new(csv_formatter(output, metadata, options)?)),
+        Format::Json => Target::Stream(Box::new(json_formatter(
+            JsonFormatter::new(),
+            output,
+            metadata,
+            options,
+        )?)),
+        Format::Ndjson => Target::Stream(Box::new(json_formatter(
+            JsonFormatter::ndjson(),
+            output,
+            metadata,
+            options,
+        )?)),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => Target::Stream(Box::new(parquet_formatter(metadata, options)?)),
+        #[cfg(feature = "sqlite")]
+        Format::Sqlite => Target::File(Box::new(sqlite_formatter(output, metadata, options)?)),
+        #[cfg(feature = "duckdb")]
+        Format::DuckDb => Target::File(Box::new(duckdb_formatter(output, metadata, options)?)),
+        #[cfg(feature = "avro")]
+        Format::Avro => Target::Stream(Box::new(avro_formatter(metadata, options))),
+        #[cfg(feature = "msgpack")]
+        Format::Msgpack => Target::Stream(Box::new(msgpack_formatter(metadata, options))),
+        #[cfg(feature = "hdf5")]
+        Format::Hdf5 => Target::File(Box::new(hdf5_formatter(metadata))),
+        #[cfg(feature = "xlsx")]
+        Format::Xlsx => Target::Stream(Box::new(xlsx_formatter(metadata, options))),
+        #[cfg(feature = "protobuf")]
+        Format::Protobuf => Target::Stream(Box::new(protobuf_formatter(metadata, options))),
+        #[cfg(feature = "orc")]
+        Format::Orc => Target::Stream(Box::new(orc_formatter(metadata, options))),
+        Format::Mt4 | Format::Mt5 | Format::Hst => {
+            Target::Stream(Box::new(mt_formatter(format, metadata, options)))
+        }
+        Format::NinjaTrader => Target::Stream(Box::new(ninjatrader_formatter(metadata))),
+        Format::TradingView => Target::Stream(Box::new(tradingview_formatter(metadata))),
+        _ => bail!("{format} output is not written with a single formatter"),
+    })
+}
+
+/// Create a CSV formatter for `output`, writing prices with the decimal
+/// places of the instrument.
+fn csv_formatter(
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<CsvFormatter> {
+    let mut formatter = CsvFormatter::new()
+        .with_compression(OutputCompression::from_path(output))
+        .with_derived_columns(options.derived);
+    if let Some(digits) = price_digits(metadata) {
+        formatter = formatter.with_price_digits(digits);
+    }
+    if let Some(symbol) = options.symbol(metadata) {
+        formatter = formatter.with_symbol(symbol);
+    }
+    if options.metadata_header {
+        formatter = formatter.with_metadata(metadata.clone());
+    }
+    if let Some(header) = options.existing_line(output)? {
+        formatter = formatter.with_append(header);
+    }
+    Ok(formatter)
+}
+
+/// Configure a JSON or NDJSON formatter for `output`, rounding prices to
+/// the decimal places of the instrument.
+fn json_formatter(
+    formatter: JsonFormatter,
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<JsonFormatter> {
+    let mut formatter = formatter.with_compression(OutputCompression::from_path(output));
+    if let Some(digits) = price_digits(metadata) {
+        formatter = formatter.with_price_digits(digits);
+    }
+    if let Some(symbol) = options.symbol(metadata) {
+        formatter = formatter.with_symbol(symbol);
+    }
+    if options.metadata_header {
+        formatter = formatter.with_metadata(metadata.clone());
+    }
+    if let Some(record) = options.existing_line(output)? {
+        formatter = formatter.with_append(record);
+    }
+    Ok(formatter)
+}
+
+/// Create a Parquet formatter embedding `metadata`, tuned by `options`.
+#[cfg(feature = "parquet")]
+fn parquet_formatter(
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<ParquetFormatter> {
+    let mut formatter = ParquetFormatter::new()
+        .with_metadata(metadata.clone())
+        .with_settings(&options.parquet)?
+        .with_derived_columns(options.derived);
+    if let Some(symbol) = options.symbol(metadata) {
+        formatter = formatter.with_symbol(symbol);
+    }
+    Ok(formatter)
+}
+
+/// Create an Avro formatter embedding `metadata`.
+#[cfg(feature = "avro")]
+fn avro_formatter(metadata: &OutputMetadata, options: &WriteOptions) -> AvroFormatter {
+    let formatter = AvroFormatter::new().with_metadata(metadata.clone());
+    match options.symbol(metadata) {
+        Some(symbol) => formatter.with_symbol(symbol),
+        None => formatter,
+    }
+}
+
+/// Create a MessagePack formatter.
+#[cfg(feature = "msgpack")]
+fn msgpack_formatter(metadata: &OutputMetadata, options: &WriteOptions) -> MsgpackFormatter {
+    let formatter = MsgpackFormatter::new();
+    match options.symbol(metadata) {
+        Some(symbol) => formatter.with_symbol(symbol),
+        None => formatter,
+    }
+}
+
+/// Create a protobuf formatter.
+#[cfg(feature = "protobuf")]
+fn protobuf_formatter(metadata: &OutputMetadata, options: &WriteOptions) -> ProtobufFormatter {
+    let formatter = ProtobufFormatter::new();
+    match options.symbol(metadata) {
+        Some(symbol) => formatter.with_symbol(symbol),
+        None => formatter,
+    }
+}
+
+/// Create an ORC formatter.
+#[cfg(feature = "orc")]
+fn orc_formatter(metadata: &OutputMetadata, options: &WriteOptions) -> OrcFormatter {
+    let formatter = OrcFormatter::new();
+    match options.symbol(metadata) {
+        Some(symbol) => formatter.with_symbol(symbol),
+        None => formatter,
+    }
+}
+
+/// Create a MetaTrader formatter of the layout of `format`, with the digits,
+/// symbol and bar period of `metadata`.
+fn mt_formatter(
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> MtCsvFormatter {
+    let layout = match format {
+        Format::Mt5 => MtLayout::Mt5,
+        Format::Hst => MtLayout::Hst,
+        _ => MtLayout::Mt4,
+    };
+    let mut formatter = MtCsvFormatter::new()
+        .with_layout(layout)
+        .with_server_time(options.server_time);
+    if let Some(factor) = metadata.decimal_factor {
+        formatter = formatter.with_digits(Instrument::price_digits_for(factor));
+    }
+    if let Some(instrument) = &metadata.instrument {
+        formatter = formatter.with_symbol(instrument);
+    }
+    if let Some(minutes) = metadata
+        .timeframe
+        .and_then(|timeframe| timeframe.seconds())
+        .and_then(|seconds| u32::try_from(seconds / 60).ok())
+    {
+        formatter = formatter.with_period_minutes(minutes);
+    }
+    formatter
+}
+
+/// Create a NinjaTrader formatter with the digits and timeframe of
+/// `metadata`, stamping bars with their close time.
+const fn ninjatrader_formatter(metadata: &OutputMetadata) -> NinjaTraderFormatter {
+    let mut formatter = NinjaTraderFormatter::new();
+    if let Some(factor) = metadata.decimal_factor {
+        formatter = formatter.with_digits(Instrument::price_digits_for(factor));
+    }
+    if let Some(timeframe) = metadata.timeframe {
+        formatter = formatter.with_timeframe(timeframe);
+    }
+    formatter
+}
+
+/// Create a TradingView formatter with the digits of `metadata`.
+const fn tradingview_formatter(metadata: &OutputMetadata) -> TradingViewFormatter {
+    let formatter = TradingViewFormatter::new();
+    match metadata.decimal_factor {
+        Some(factor) => formatter.with_digits(Instrument::price_digits_for(factor)),
+        None => formatter,
+    }
+}
+
+/// Create an Excel formatter embedding `metadata` as document properties.
+#[cfg(feature = "xlsx")]
+fn xlsx_formatter(metadata: &OutputMetadata, options: &WriteOptions) -> XlsxFormatter {
+    let formatter = XlsxFormatter::new().with_metadata(metadata.clone());
+    match options.symbol(metadata) {
+        Some(symbol) => formatter.with_symbol(symbol),
+        None => formatter,
+    }
+}
+
+/// Create an HDF5 formatter writing into the group of the instrument of
+/// `metadata` and embedding `metadata` as attributes. Other instruments of
+/// an existing file at `output` are kept.
+#[cfg(feature = "hdf5")]
+fn hdf5_formatter(metadata: &OutputMetadata) -> Hdf5Formatter {
+    let formatter = Hdf5Formatter::new().with_metadata(metadata.clone());
+    match &metadata.instrument {
+        Some(instrument) => formatter.with_group(instrument),
+        None => formatter,
+    }
+}
+
+/// Remove an existing database at `output` unless appending to it.
+#[cfg(any(feature = "sqlite", feature = "duckdb"))]
+fn replace_database(output: &Path, options: &WriteOptions) -> Result<()> {
+    if !options.append && output.exists() {
+        std::fs::remove_file(output)
+            .with_context(|| format!("Failed to replace {}", output.display()))?;
+    }
+    Ok(())
+}
+
+/// Create a SQLite formatter storing the instrument of `metadata` in the
+/// symbol column. Unless appending, an existing database at `output` is
+/// replaced.
+#[cfg(feature = "sqlite")]
+fn sqlite_formatter(
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<SqliteFormatter> {
+    replace_database(output, options)?;
+    let mut formatter = SqliteFormatter::new();
+    if let Some(symbol) = &metadata.instrument {
+        formatter = formatter.with_symbol(symbol);
+    }
+    Ok(formatter)
+}
+
+/// Create a DuckDB formatter storing the instrument of `metadata` in the
+/// symbol column. Unless appending, an existing database at `output` is
+/// replaced.
+#[cfg(feature = "duckdb")]
+fn duckdb_formatter(
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<DuckDbFormatter> {
+    replace_database(output, options)?;
+    let mut formatter = DuckDbFormatter::new();
+    if let Some(symbol) = &metadata.instrument {
+        formatter = formatter.with_symbol(symbol);
+    }
+    Ok(formatter)
+}
+
+/// Write a partitioned Parquet dataset into the instrument directory
+/// `output` (`<root>/instrument=<name>`), returning the files written.
+#[cfg(feature = "parquet")]
+fn write_dataset(
+    output: &Path,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+    write: impl FnOnce(&ParquetFormatter, &Path, &str) -> Result<Vec<PathBuf>, FormatError>,
+) -> Result<Vec<PathBuf>> {
+    let root = output.parent().unwrap_or(Path::new(""));
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    let instrument = name.strip_prefix(DATASET_PREFIX).unwrap_or(&name);
+    let formatter = parquet_formatter(metadata, options)?;
+    std::fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    Ok(write(&formatter, root, instrument)?)
+}
+
+/// Write a return series to a file in the specified format.
+///
+/// Metadata is embedded in Parquet, Avro, HDF5 and Excel files and written as a
+/// JSON sidecar otherwise.
+pub(crate) fn write_returns(
+    returns: &[PriceReturn],
+    output: &Path,
+    format: Format,
+    metadata: &OutputMetadata,
+    options: &WriteOptions,
+) -> Result<Vec<PathBuf>> {
+    match format {
+        Format::ParquetDataset => bail!("returns cannot be written as a dataset"),
+        _ if format.is_platform() => bail!("returns cannot be written to {format}"),
+        _ => match formatter(format, output, metadata, options)? {
+            Target::Stream(formatter) => {
+                formatter.write_returns_dyn(returns, &mut options.open(output)?)?;
             }
-            #[cfg(not(feature = "parquet"))]
-            {
-                bail!("Parquet support not compiled in");
+            Target::File(formatter) => formatter.write_returns_to(returns, output)?,
+        },
+    }
+
+    options.write_sidecar(output, format, metadata)?;
+
+    Ok(vec![output.to_path_buf()])
+}
+
+/// Build the metadata describing a downloaded dataset.
+///
+/// Bars are labelled with the price `settings` builds them from.
+pub(crate) fn output_metadata(
+    instrument: &Instrument,
+    range: &DateRange,
+    timeframe: Timeframe,
+    settings: &AggregationSettings,
+    format: Format,
+    deterministic: bool,
+    alias: Option<&str>,
+) -> OutputMetadata {
+    let price_source = if timeframe.is_tick() && !settings.activity_bars() {
+        "bid/ask"
+    } else {
+        settings.price_source.as_str()
+    };
+    let mut metadata = OutputMetadata::for_instrument(instrument);
+    if let Some(alias) = alias {
+        metadata = metadata.with_instrument_alias(alias);
+    }
+    let metadata = metadata
+        .with_price_source(price_source)
+        .with_timeframe(timeframe)
+        .with_parameter("start", range.start)
+        .with_parameter("end", range.end)
+        .with_parameter("format", format);
+    if deterministic {
+        metadata.without_generated_at()
+    } else {
+        metadata
+    }
+}
+
+/// Summarize download statistics as a single human-readable line.
+pub(crate) fn format_stats(stats: &StatsSnapshot) -> String {
+    let mut line = format!(
+        "Fetched {} in {:.1}s ({}/s), {} requests",
+        Estimator::format_bytes(stats.bytes_fetched),
+        stats.wall_time.as_secs_f64(),
+        Estimator::format_bytes(stats.bytes_per_second() as u64),
+        stats.requests,
+    );
+    if stats.retries > 0 {
+        line.push_str(&format!(", {} retries", stats.retries));
+    }
+    if stats.not_found > 0 {
+        line.push_str(&format!(", {} empty hours", stats.not_found));
+    }
+    if stats.cache_hits > 0 {
+        line.push_str(&format!(", {} cache hits", stats.cache_hits));
+    }
+    if stats.revisions > 0 {
+        line.push_str(&format!(", {} hours revised upstream", stats.revisions));
+    }
+    if stats.refetches > 0 {
+        line.push_str(&format!(", {} corrupt hours re-fetched", stats.refetches));
+    }
+    if stats.late_retries > 0 {
+        line.push_str(&format!(", {} empty hours re-checked", stats.late_retries));
+    }
+    if stats.server_delays > 0 {
+        line.push_str(&format!(
+            ", {} server-requested waits ({:.1}s)",
+            stats.server_delays,
+            stats.server_delay_time.as_secs_f64()
+        ));
+    }
+    if stats.requests > 0 {
+        line.push_str(&format!(
+            "; request latency avg {} ms, max {} ms; decompression {:.1}s",
+            stats.mean_request_time().as_millis(),
+            stats.max_request_time.as_millis(),
+            stats.decode_time.as_secs_f64(),
+        ));
+    }
+    if stats.slow_requests > 0 {
+        line.push_str(&format!(", {} slow requests", stats.slow_requests));
+    }
+    line
+}
+
+/// Maximum number of revised hours listed individually.
+const MAX_LISTED_REVISIONS: usize = 10;
+
+/// Describe hours whose cached content changed upstream, one line per hour.
+pub(crate) fn format_revisions(revisions: &[Revision]) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{} cached hours changed upstream and were re-downloaded:",
+        revisions.len()
+    )];
+    for revision in revisions.iter().take(MAX_LISTED_REVISIONS) {
+        let hour = revision.hour.map_or_else(
+            || revision.url.clone(),
+            |h| h.format("%Y-%m-%d %H:00 UTC").to_string(),
+        );
+        lines.push(format!(
+            "  {hour}: {} -> {} bytes (sha256 {:.12} -> {:.12})",
+            revision.previous_len,
+            revision.current_len,
+            revision.previous_sha256,
+            revision.current_sha256
+        ));
+    }
+    if revisions.len() > MAX_LISTED_REVISIONS {
+        lines.push(format!(
+            "  ... and {} more",
+            revisions.len() - MAX_LISTED_REVISIONS
+        ));
+    }
+    lines
+}
+
+/// Maximum number of skipped hours listed individually.
+const MAX_LISTED_HOUR_ERRORS: usize = 10;
+
+/// Describe hours skipped due to errors, one line per hour.
+pub(crate) fn format_hour_errors(errors: &[HourError]) -> Vec<String> {
+    let mut lines = vec![format!("{} hours skipped due to errors:", errors.len())];
+    for error in errors.iter().take(MAX_LISTED_HOUR_ERRORS) {
+        let hour = error.hour.format("%Y-%m-%d %H:00 UTC");
+        match error.reason {
+            SkipReason::Decompress { .. } | SkipReason::Parse { .. } => {
+                lines.push(format!("  {hour}: {} ({})", error.reason, error.error));
             }
+            _ => lines.push(format!("  {hour}: {}", error.reason)),
         }
     }
+    let transient = errors.iter().filter(|e| e.reason.is_transient()).count();
+    if transient > 0 {
+        lines.push(format!(
+            "  {transient} of these failed on the server side and may succeed if retried later"
+        ));
+    }
+    if errors.len() > MAX_LISTED_HOUR_ERRORS {
+        lines.push(format!(
+            "  ... and {} more",
+            errors.len() - MAX_LISTED_HOUR_ERRORS
+        ));
+    }
+    lines
+}
 
-    Ok(())
+/// Describe how many ticks failed price validation.
+pub(crate) fn format_invalid_ticks(count: usize, action: InvalidTickAction) -> String {
+    let verb = match action {
+        InvalidTickAction::Flag => "flagged",
+        InvalidTickAction::Drop => "dropped",
+    };
+    format!("{count} ticks with bad prices {verb}")
+}
+
+/// Parse a range boundary: `YYYY-MM-DD`, optionally followed by `THH` or `THH:00`.
+pub(crate) fn parse_date_hour(s: &str) -> Result<(NaiveDate, Option<u32>)> {
+    let (date_part, hour_part) = match s.split_once(['T', ' ']) {
+        Some((date, hour)) => (date, Some(hour)),
+        None => (s, None),
+    };
+
+    let date = NaiveDate::parse_from_str(date_part, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date: {s}"))?;
+    let hour = hour_part
+        .map(|h| {
+            let h = h.strip_suffix(":00").unwrap_or(h);
+            h.parse::<u32>()
+                .ok()
+                .filter(|h| *h < 24)
+                .with_context(|| format!("Invalid hour in {s} (expected HH or HH:00)"))
+        })
+        .transpose()?;
+
+    Ok((date, hour))
+}
+
+/// Format a range boundary, including the hour only if it is not `whole_day_hour`.
+pub(crate) fn format_date_hour(date: NaiveDate, hour: u32, whole_day_hour: u32) -> String {
+    if hour == whole_day_hour {
+        date.format("%Y-%m-%d").to_string()
+    } else {
+        format!("{}T{:02}", date.format("%Y-%m-%d"), hour)
+    }
+}
+
+/// Parse stored start/end boundaries (as written by [`format_date_hour`]) into a range.
+pub(crate) fn parse_range(start: &str, end: &str) -> Result<DateRange> {
+    let (start_date, start_hour) = parse_date_hour(start)?;
+    let (end_date, end_hour) = parse_date_hour(end)?;
+    Ok(DateRange::new(start_date, end_date)?
+        .with_hours(start_hour.unwrap_or(0), end_hour.unwrap_or(23))?)
+}
+
+/// Parse the requested timeframe, or pick one for `instruments` over `range`.
+///
+/// Without a timeframe, raw ticks are downloaded unless their estimated size
+/// is too large, in which case the categories' default bars are used and a
+/// warning is printed.
+pub(crate) fn resolve_timeframe(
+    timeframe: Option<&str>,
+    instruments: &[&Instrument],
+    range: &DateRange,
+    quiet: bool,
+) -> Result<Timeframe> {
+    if let Some(timeframe) = timeframe {
+        return timeframe
+            .parse::<Timeframe>()
+            .map_err(|e| anyhow::anyhow!("{e}"));
+    }
+    let choice = TimeframeDefaults::default().choose_batch(Estimator::global(), instruments, range);
+    if choice.is_aggregated() && !quiet {
+        eprintln!(
+            "Warning: raw ticks for {range} would take about {} as output; \
+             downloading {} bars instead (pass --timeframe tick to keep ticks)",
+            Estimator::format_bytes(choice.tick_estimate.estimated_output_bytes),
+            choice.timeframe
+        );
+    }
+    Ok(choice.timeframe)
+}
+
+/// Print a download plan and ask whether to proceed.
+///
+/// `subject` names what is downloaded, e.g. "Instruments: 28".
+pub(crate) fn confirm_plan(
+    subject: &str,
+    range: &DateRange,
+    timeframe: Timeframe,
+    estimate: &DownloadEstimate,
+) -> Result<bool> {
+    println!("Download plan:");
+    println!("  {subject}");
+    println!("  Date range: {range}");
+    println!("  Timeframe: {timeframe}");
+    println!(
+        "  Estimated download size: {}",
+        Estimator::format_bytes(estimate.estimated_compressed_bytes)
+    );
+    if timeframe.is_tick() {
+        println!(
+            "  Estimated output size: {}",
+            Estimator::format_bytes(estimate.estimated_output_bytes)
+        );
+    }
+    println!(
+        "  Estimated time: {}",
+        Estimator::format_duration(estimate.estimated_duration)
+    );
+    println!();
+
+    // Simple y/n confirmation
+    print!("Proceed with download? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    if !input.trim().eq_ignore_ascii_case("y") {
+        println!("Cancelled.");
+        return Ok(false);
+    }
+    Ok(true)
 }
 
 /// Parse a category string into a Category enum.
@@ -136,3 +1504,116 @@ pub(crate) fn parse_category(s: &str) -> Result<Category> {
         ),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use paracas_lib::PriceSource;
+
+    fn tick(second: u32, bid: f64) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, second).unwrap();
+        Tick::new(timestamp, bid + 0.0002, bid, 1.0, 1.0)
+    }
+
+    #[test]
+    fn test_aggregate_ticks_sorts_unordered_ticks() {
+        let ticks: Vec<_> = (0..6)
+            .map(|s| tick(s, 1.1 + f64::from(s) * 0.001))
+            .collect();
+        // Later hours can complete before earlier ones
+        let mut unordered = ticks[3..].to_vec();
+        unordered.extend_from_slice(&ticks[..3]);
+        let settings = AggregationSettings {
+            volume_bars: Some(4.0),
+            ..Default::default()
+        };
+
+        let bars = aggregate_ticks(&unordered, Timeframe::Tick, &settings, 0.0001);
+        assert_eq!(
+            bars,
+            aggregate_ticks(&ticks, Timeframe::Tick, &settings, 0.0001)
+        );
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[0].timestamp, ticks[0].timestamp);
+        assert!(bars.is_sorted_by_key(|bar| bar.timestamp));
+    }
+
+    #[test]
+    fn test_output_metadata_price_source() {
+        let instrument = Instrument::new(
+            "eurusd",
+            "EUR/USD",
+            "Euro vs US Dollar",
+            Category::Forex,
+            100_000,
+            None,
+        );
+        let range = DateRange::single_day(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        let metadata = |timeframe, price_source| {
+            let settings = AggregationSettings {
+                price_source,
+                ..Default::default()
+            };
+            output_metadata(
+                &instrument,
+                &range,
+                timeframe,
+                &settings,
+                Format::Csv,
+                true,
+                None,
+            )
+        };
+
+        let bid = metadata(Timeframe::Minute1, PriceSource::Bid);
+        assert_eq!(bid.price_source.as_deref(), Some("bid"));
+        assert_eq!(price_digits(&bid), Some(instrument.price_digits()));
+
+        let mid = metadata(Timeframe::Minute1, PriceSource::Mid);
+        assert_eq!(price_digits(&mid), Some(instrument.price_digits() + 1));
+
+        let ticks = metadata(Timeframe::Tick, PriceSource::Bid);
+        assert_eq!(ticks.price_source.as_deref(), Some("bid/ask"));
+    }
+
+    #[test]
+    fn test_write_rejects_unsupported_records() {
+        let instrument = Instrument::new(
+            "eurusd",
+            "EUR/USD",
+            "Euro vs US Dollar",
+            Category::Forex,
+            100_000,
+            None,
+        );
+        let range = DateRange::single_day(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        let settings = AggregationSettings::default();
+        let metadata = output_metadata(
+            &instrument,
+            &range,
+            Timeframe::Tick,
+            &settings,
+            Format::Hst,
+            true,
+            None,
+        );
+        let options = WriteOptions::default();
+        // Rejected before the output is opened
+        let output = Path::new("missing/eurusd");
+
+        let err = write_ticks(&[tick(0, 1.1)], output, Format::Hst, &metadata, &options);
+        assert!(
+            err.unwrap_err()
+                .to_string()
+                .contains("aggregate them into bars")
+        );
+        for format in [Format::Mt4, Format::Lean, Format::ParquetDataset] {
+            assert!(write_returns(&[], output, format, &metadata, &options).is_err());
+        }
+        assert_eq!(
+            Format::Avro.ensure_available().is_ok(),
+            OutputFormat::Avro.is_available()
+        );
+    }
+}
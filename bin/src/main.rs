@@ -2,12 +2,22 @@
 
 use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
+use paracas_lib::{DerivedColumns, MtServerTime, OutputCompression, SplitLimits, Timeframe};
 use std::path::PathBuf;
 
+mod args;
 mod commands;
+mod config;
 mod display;
+mod memory;
+mod signal;
+mod summary;
+mod terms;
 
-use display::Format;
+use args::{AggregateArgs, FetchArgs, ParquetArgs};
+use commands::instruments::ExportFormat;
+use config::{LocationArgs, Locations};
+use display::{Format, WriteOptions};
 
 #[derive(Parser)]
 #[command(name = "paracas")]
@@ -25,6 +35,13 @@ struct Cli {
     #[arg(short, long, global = true)]
     quiet: bool,
 
+    /// Acknowledge the data source terms non-interactively
+    #[arg(long, global = true)]
+    ack_terms: bool,
+
+    #[command(flatten)]
+    locations: LocationArgs,
+
     /// Hidden: Run as daemon with job ID (internal use only)
     #[arg(long, hide = true)]
     daemon_run: Option<String>,
@@ -37,11 +54,11 @@ enum Commands {
         /// Instrument identifier (e.g., eurusd, btcusd)
         instrument: String,
 
-        /// Start date (YYYY-MM-DD). Defaults to instrument's earliest available data.
+        /// Start date (YYYY-MM-DD, or YYYY-MM-DDTHH to start at a UTC hour). Defaults to instrument's earliest available data.
         #[arg(short, long)]
         start: Option<String>,
 
-        /// End date (YYYY-MM-DD). Defaults to today.
+        /// End date (YYYY-MM-DD, or YYYY-MM-DDTHH to stop after that UTC hour). Defaults to today.
         #[arg(short, long)]
         end: Option<String>,
 
@@ -53,10 +70,64 @@ enum Commands {
         #[arg(short, long, value_enum, default_value = "csv")]
         format: Format,
 
-        /// OHLCV aggregation timeframe (omit for raw ticks)
+        /// Compress CSV or JSON output with gzip or zstd, appending .gz or .zst
+        /// to the file name
+        #[arg(long, value_name = "CODEC")]
+        compress: Option<OutputCompression>,
+
+        /// Add a leading symbol column with the instrument (or its alias) to every row
+        #[arg(long)]
+        symbol_column: bool,
+
+        /// Append to existing csv or ndjson output, checking that its columns match
+        #[arg(long)]
+        append: bool,
+
+        /// Time zone of mt4, mt5 and hst timestamps: utc, ny-close (UTC+2, or
+        /// UTC+3 in US summer, as on most forex servers) or a tz database name
+        #[arg(long, value_name = "ZONE", default_value = "utc")]
+        server_time: MtServerTime,
+
+        /// Write the metadata (instrument, date range, timeframe, version,
+        /// generation time) as a leading line of csv or ndjson output
+        #[arg(long)]
+        metadata_header: bool,
+
+        /// Add a spread (ask minus bid) column to csv and parquet tick output
+        #[arg(long)]
+        spread_column: bool,
+
+        /// Add a mid price column to csv and parquet tick output
+        #[arg(long)]
+        mid_column: bool,
+
+        /// Split csv, json, ndjson or parquet output into numbered files
+        /// (eurusd-0001.csv, ...) of at most this many rows
+        #[arg(long, value_name = "ROWS")]
+        max_file_rows: Option<usize>,
+
+        /// Split csv, json, ndjson or parquet output into numbered files of
+        /// about this many bytes, exceeded by at most a batch of rows
+        #[arg(long, value_name = "BYTES")]
+        max_file_bytes: Option<u64>,
+
+        /// OHLCV aggregation timeframe, e.g. m1, h4 or 90s (omit for raw ticks)
         #[arg(short, long)]
         timeframe: Option<String>,
 
+        /// Aggregate several timeframes in one pass (e.g. m1,m5,h1,d1), writing
+        /// <output>_<timeframe> files
+        #[arg(
+            long,
+            value_name = "LIST",
+            value_delimiter = ',',
+            conflicts_with_all = [
+                "timeframe", "background", "volume_bars", "notional_bars", "renko", "range_bars",
+                "volume_profile",
+            ]
+        )]
+        timeframes: Vec<Timeframe>,
+
         /// Maximum concurrent downloads
         #[arg(long, default_value = "32")]
         concurrency: usize,
@@ -65,9 +136,18 @@ enum Commands {
         #[arg(long)]
         background: bool,
 
-        /// Skip confirmation prompt (for background mode)
-        #[arg(long)]
+        /// Skip the confirmation prompt shown for large downloads
+        #[arg(short, long)]
         yes: bool,
+
+        #[command(flatten)]
+        fetch: FetchArgs,
+
+        #[command(flatten)]
+        aggregate: AggregateArgs,
+
+        #[command(flatten)]
+        parquet: ParquetArgs,
     },
 
     /// List available instruments
@@ -81,10 +161,20 @@ enum Commands {
         search: Option<String>,
     },
 
+    /// Export instrument metadata
+    Instruments {
+        #[command(subcommand)]
+        action: InstrumentsAction,
+    },
+
     /// Show instrument details
     Info {
         /// Instrument identifier
         instrument: String,
+
+        /// Fetch the most recent available hour and show the last quote
+        #[arg(long)]
+        live: bool,
     },
 
     /// Check background job status
@@ -115,11 +205,11 @@ enum Commands {
         #[arg(short, long)]
         category: Option<String>,
 
-        /// Start date (YYYY-MM-DD). Defaults to each instrument's earliest data.
+        /// Start date (YYYY-MM-DD, or YYYY-MM-DDTHH to start at a UTC hour). Defaults to each instrument's earliest data.
         #[arg(short, long)]
         start: Option<String>,
 
-        /// End date (YYYY-MM-DD). Defaults to today.
+        /// End date (YYYY-MM-DD, or YYYY-MM-DDTHH to stop after that UTC hour). Defaults to today.
         #[arg(short, long)]
         end: Option<String>,
 
@@ -131,7 +221,48 @@ enum Commands {
         #[arg(short, long, value_enum, default_value = "csv")]
         format: Format,
 
-        /// OHLCV aggregation timeframe (omit for raw ticks)
+        /// Compress CSV or JSON output with gzip or zstd, appending .gz or .zst
+        /// to the file names
+        #[arg(long, value_name = "CODEC")]
+        compress: Option<OutputCompression>,
+
+        /// Add a leading symbol column with the instrument (or its alias) to every row
+        #[arg(long)]
+        symbol_column: bool,
+
+        /// Append to existing csv or ndjson output, checking that its columns match
+        #[arg(long)]
+        append: bool,
+
+        /// Time zone of mt4, mt5 and hst timestamps: utc, ny-close (UTC+2, or
+        /// UTC+3 in US summer, as on most forex servers) or a tz database name
+        #[arg(long, value_name = "ZONE", default_value = "utc")]
+        server_time: MtServerTime,
+
+        /// Write the metadata (instrument, date range, timeframe, version,
+        /// generation time) as a leading line of csv or ndjson output
+        #[arg(long)]
+        metadata_header: bool,
+
+        /// Add a spread (ask minus bid) column to csv and parquet tick output
+        #[arg(long)]
+        spread_column: bool,
+
+        /// Add a mid price column to csv and parquet tick output
+        #[arg(long)]
+        mid_column: bool,
+
+        /// Split csv, json, ndjson or parquet output into numbered files
+        /// (eurusd-0001.csv, ...) of at most this many rows
+        #[arg(long, value_name = "ROWS")]
+        max_file_rows: Option<usize>,
+
+        /// Split csv, json, ndjson or parquet output into numbered files of
+        /// about this many bytes, exceeded by at most a batch of rows
+        #[arg(long, value_name = "BYTES")]
+        max_file_bytes: Option<u64>,
+
+        /// OHLCV aggregation timeframe, e.g. m1, h4 or 90s (omit for raw ticks)
         #[arg(short, long)]
         timeframe: Option<String>,
 
@@ -148,8 +279,49 @@ enum Commands {
         background: bool,
 
         /// Skip confirmation prompt
-        #[arg(long)]
+        #[arg(short, long)]
         yes: bool,
+
+        #[command(flatten)]
+        fetch: FetchArgs,
+
+        #[command(flatten)]
+        aggregate: AggregateArgs,
+
+        #[command(flatten)]
+        parquet: ParquetArgs,
+    },
+
+    /// List output formats and whether each is compiled in
+    Formats,
+
+    /// Print supported formats, timeframes, categories and features as JSON
+    Capabilities,
+
+    /// Verify cached raw files against their coverage manifests
+    Verify {
+        /// Instruments to verify. Defaults to every instrument in the cache
+        instruments: Vec<String>,
+
+        /// Report coverage from this date (YYYY-MM-DD or YYYY-MM-DDTHH)
+        #[arg(short, long, requires = "end")]
+        start: Option<String>,
+
+        /// Report coverage up to this date (YYYY-MM-DD or YYYY-MM-DDTHH)
+        #[arg(short, long, requires = "start")]
+        end: Option<String>,
+
+        /// Hash file contents instead of only comparing sizes
+        #[arg(long)]
+        checksums: bool,
+
+        /// Number of files checked in parallel [default: number of CPUs]
+        #[arg(long)]
+        threads: Option<usize>,
+
+        /// Skip hours verified by an interrupted --checksums run
+        #[arg(long, requires = "checksums")]
+        resume: bool,
     },
 
     /// Manage background jobs (pause, resume, kill, clean)
@@ -159,6 +331,25 @@ enum Commands {
     },
 }
 
+/// Actions on the instrument registry.
+#[derive(Subcommand)]
+enum InstrumentsAction {
+    /// Export all instruments with their decimal factors and start dates
+    Export {
+        /// Export format
+        #[arg(short, long, value_enum, default_value = "json")]
+        format: ExportFormat,
+
+        /// Filter by category (forex, crypto, index, stock, commodity, etf, bond)
+        #[arg(short, long)]
+        category: Option<String>,
+
+        /// Output file path. Defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
 /// Actions for managing background jobs.
 #[derive(Subcommand)]
 enum JobAction {
@@ -191,10 +382,11 @@ enum JobAction {
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let locations = Locations::resolve(&cli.locations)?;
 
     // Check for daemon mode first (internal use)
     if let Some(job_id) = cli.daemon_run {
-        return commands::daemon_run::daemon_run(&job_id).await;
+        return commands::daemon_run::daemon_run(&job_id, &locations).await;
     }
 
     // Show help if no command provided
@@ -203,6 +395,13 @@ async fn main() -> Result<()> {
         return Ok(());
     };
 
+    if matches!(
+        command,
+        Commands::Download { .. } | Commands::DownloadAll { .. }
+    ) {
+        terms::ensure_acknowledged(cli.ack_terms, cli.quiet, &locations)?;
+    }
+
     match command {
         Commands::Download {
             instrument,
@@ -210,10 +409,23 @@ async fn main() -> Result<()> {
             end,
             output,
             format,
+            compress,
+            symbol_column,
+            append,
+            server_time,
+            metadata_header,
+            spread_column,
+            mid_column,
+            max_file_rows,
+            max_file_bytes,
             timeframe,
+            timeframes,
             concurrency,
             background,
             yes,
+            fetch,
+            aggregate,
+            parquet,
         } => {
             commands::download::download(
                 &instrument,
@@ -221,10 +433,32 @@ async fn main() -> Result<()> {
                 end.as_deref(),
                 output,
                 format,
+                compress.unwrap_or_default(),
+                WriteOptions {
+                    parquet: parquet.settings(),
+                    symbol_column,
+                    append,
+                    server_time,
+                    metadata_header,
+                    derived: DerivedColumns {
+                        spread: spread_column,
+                        mid: mid_column,
+                    },
+                    split: SplitLimits {
+                        max_rows: max_file_rows,
+                        max_bytes: max_file_bytes,
+                    },
+                },
                 timeframe.as_deref(),
+                &timeframes,
                 concurrency,
                 background,
                 yes,
+                &fetch
+                    .with_cache_dir(locations.cache_dir.clone())
+                    .with_rate_limiter(),
+                &aggregate,
+                &locations,
                 cli.quiet,
             )
             .await
@@ -232,25 +466,57 @@ async fn main() -> Result<()> {
         Commands::List { category, search } => {
             commands::list::list_instruments(category.as_deref(), search.as_deref())
         }
-        Commands::Info { instrument } => commands::info::show_info(&instrument),
+        Commands::Instruments { action } => match action {
+            InstrumentsAction::Export {
+                format,
+                category,
+                output,
+            } => commands::instruments::export_instruments(
+                format,
+                category.as_deref(),
+                output.as_deref(),
+            ),
+        },
+        Commands::Formats => commands::formats::list_formats(),
+        Commands::Capabilities => commands::formats::print_capabilities(),
+        Commands::Info { instrument, live } => commands::info::show_info(&instrument, live).await,
         Commands::Status {
             job_id,
             running,
             all,
             follow,
             cancel,
-        } => commands::status::status(job_id.as_deref(), running, all, follow, cancel.as_deref()),
+        } => commands::status::status(
+            job_id.as_deref(),
+            running,
+            all,
+            follow,
+            cancel.as_deref(),
+            &locations,
+        ),
         Commands::DownloadAll {
             category,
             start,
             end,
             output_dir,
             format,
+            compress,
+            symbol_column,
+            append,
+            server_time,
+            metadata_header,
+            spread_column,
+            mid_column,
+            max_file_rows,
+            max_file_bytes,
             timeframe,
             parallel_instruments,
             concurrency,
             background,
             yes,
+            fetch,
+            aggregate,
+            parquet,
         } => {
             commands::download_all::download_all(
                 category.as_deref(),
@@ -258,26 +524,64 @@ async fn main() -> Result<()> {
                 end.as_deref(),
                 output_dir,
                 format,
+                compress.unwrap_or_default(),
+                WriteOptions {
+                    parquet: parquet.settings(),
+                    symbol_column,
+                    append,
+                    server_time,
+                    metadata_header,
+                    derived: DerivedColumns {
+                        spread: spread_column,
+                        mid: mid_column,
+                    },
+                    split: SplitLimits {
+                        max_rows: max_file_rows,
+                        max_bytes: max_file_bytes,
+                    },
+                },
                 timeframe.as_deref(),
                 parallel_instruments,
                 concurrency,
                 background,
                 yes,
+                &fetch
+                    .with_cache_dir(locations.cache_dir.clone())
+                    .with_rate_limiter(),
+                &aggregate,
+                &locations,
                 cli.quiet,
             )
             .await
         }
+        Commands::Verify {
+            instruments,
+            start,
+            end,
+            checksums,
+            threads,
+            resume,
+        } => commands::verify::verify(
+            &instruments,
+            start.as_deref(),
+            end.as_deref(),
+            checksums,
+            threads,
+            resume,
+            &locations,
+            cli.quiet,
+        ),
         Commands::Job { action } => match action {
             JobAction::Pause { job_id } => {
-                commands::job::job_command("pause", job_id.as_deref(), false)
+                commands::job::job_command("pause", job_id.as_deref(), false, &locations)
             }
             JobAction::Resume { job_id } => {
-                commands::job::job_command("resume", job_id.as_deref(), false)
+                commands::job::job_command("resume", job_id.as_deref(), false, &locations)
             }
             JobAction::Kill { job_id } => {
-                commands::job::job_command("kill", job_id.as_deref(), false)
+                commands::job::job_command("kill", job_id.as_deref(), false, &locations)
             }
-            JobAction::Clean { all } => commands::job::job_command("clean", None, all),
+            JobAction::Clean { all } => commands::job::job_command("clean", None, all, &locations),
         },
     }
 }
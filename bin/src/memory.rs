@@ -0,0 +1,105 @@
+//! Memory usage sampling for `--report-memory`.
+//!
+//! A background task periodically samples the process resident set size and
+//! the number of downloads in flight, keeping the peaks for the final summary.
+
+use paracas_daemon::MemoryReport;
+use paracas_estimate::Estimator;
+use paracas_lib::DownloadStats;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Interval between samples.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sentinel for "RSS not available on this platform".
+const NO_RSS: u64 = u64::MAX;
+
+#[derive(Debug, Default)]
+struct Peaks {
+    rss: AtomicU64,
+    in_flight: AtomicU64,
+    watched: Mutex<Vec<DownloadStats>>,
+}
+
+impl Peaks {
+    fn sample(&self) {
+        self.rss
+            .fetch_max(current_rss().unwrap_or(NO_RSS), Ordering::Relaxed);
+        let in_flight: u64 = self
+            .watched
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .iter()
+            .map(|stats| stats.snapshot().in_flight)
+            .sum();
+        self.in_flight.fetch_max(in_flight, Ordering::Relaxed);
+    }
+}
+
+/// Samples memory usage in the background until finished.
+#[derive(Debug)]
+pub(crate) struct MemorySampler {
+    peaks: Arc<Peaks>,
+    handle: JoinHandle<()>,
+}
+
+impl MemorySampler {
+    /// Start sampling.
+    pub(crate) fn start() -> Self {
+        let peaks = Arc::new(Peaks::default());
+        let task_peaks = Arc::clone(&peaks);
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                task_peaks.sample();
+            }
+        });
+        Self { peaks, handle }
+    }
+
+    /// Include a client's in-flight downloads in the samples.
+    pub(crate) fn watch(&self, stats: &DownloadStats) {
+        self.peaks
+            .watched
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(stats.clone());
+    }
+
+    /// Stop sampling and return the observed peaks.
+    pub(crate) fn finish(self) -> MemoryReport {
+        self.handle.abort();
+        self.peaks.sample();
+
+        let rss = self.peaks.rss.load(Ordering::Relaxed);
+        MemoryReport {
+            peak_rss_bytes: (rss != NO_RSS).then_some(rss),
+            peak_in_flight: self.peaks.in_flight.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Returns the current resident set size of this process in bytes.
+///
+/// Only supported on Linux; returns `None` elsewhere.
+pub(crate) fn current_rss() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Format a memory report for the download summary.
+pub(crate) fn format_memory(report: &MemoryReport) -> String {
+    let rss = report
+        .peak_rss_bytes
+        .map_or_else(|| "n/a".to_string(), Estimator::format_bytes);
+    format!(
+        "Peak memory: {rss} RSS, {} downloads in flight",
+        report.peak_in_flight
+    )
+}
@@ -0,0 +1,43 @@
+//! Shutdown signal handling.
+//!
+//! Ctrl-C (and SIGTERM on Unix, which `job kill` sends) cancels a
+//! process-wide token instead of terminating the process, so downloads can
+//! stop their streams, drop in-flight requests and report partial progress.
+
+use paracas_lib::CancellationToken;
+use std::sync::OnceLock;
+
+/// Returns the process-wide shutdown token.
+///
+/// The signal listener is installed on first use and must be called from
+/// within the Tokio runtime.
+pub(crate) fn shutdown_token() -> CancellationToken {
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+    TOKEN
+        .get_or_init(|| {
+            let token = CancellationToken::new();
+            let trigger = token.clone();
+            tokio::spawn(async move {
+                wait_for_signal().await;
+                trigger.cancel();
+            });
+            token
+        })
+        .clone()
+}
+
+/// Waits for Ctrl-C or, on Unix, SIGTERM.
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = term.recv() => {}
+            }
+            return;
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
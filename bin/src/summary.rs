@@ -0,0 +1,272 @@
+//! Run summaries for `--summary`.
+//!
+//! A summary is a machine-readable record of one download run, written as
+//! `<output>.summary.json` next to the output: the parameters, timings, hour
+//! statistics with classified skips, download counters, the paracas version
+//! and SHA-256 checksums of the files written. Data-lineage tooling can rely
+//! on the same shape for every run.
+
+use crate::display::Format;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use paracas_lib::{
+    DateRange, OutputMetadata, StatsSnapshot, TickBatch, Timeframe, sha256_file_hex,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Suffix appended to the output file name for the summary.
+const SUMMARY_SUFFIX: &str = ".summary.json";
+
+/// Record of a download run, filled in while the run progresses.
+#[derive(Debug, Serialize)]
+pub(crate) struct RunSummary {
+    /// Version of paracas that ran the download.
+    paracas_version: &'static str,
+    /// Command that ran the download.
+    command: &'static str,
+    /// Instrument downloaded.
+    instrument: String,
+    /// Alias written instead of the instrument ID, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    /// Start of the first hour requested.
+    start: DateTime<Utc>,
+    /// Start of the last hour requested.
+    end: DateTime<Utc>,
+    /// Output timeframe.
+    timeframe: String,
+    /// Output format.
+    format: String,
+    /// Other options the run was started with.
+    parameters: BTreeMap<&'static str, serde_json::Value>,
+    /// Start of the run.
+    started_at: DateTime<Utc>,
+    /// End of the run, set by [`Self::finish`].
+    finished_at: Option<DateTime<Utc>>,
+    /// Duration of the run in seconds.
+    duration_secs: f64,
+    /// Hour statistics.
+    hours: HourSummary,
+    /// Ticks kept.
+    ticks: u64,
+    /// Ticks that failed price validation.
+    invalid_ticks: u64,
+    /// Download counters of the client.
+    download: DownloadSummary,
+    /// Files written, with checksums.
+    files: Vec<FileChecksum>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+/// Hours requested and what became of them.
+#[derive(Debug, Default, Serialize)]
+struct HourSummary {
+    /// Hours requested.
+    requested: u64,
+    /// Hours with ticks.
+    with_data: u64,
+    /// Hours without ticks and without errors.
+    empty: u64,
+    /// Hours skipped after errors.
+    skipped: u64,
+    /// Number of skipped hours by class of failure.
+    skip_reasons: BTreeMap<&'static str, u64>,
+    /// Every skipped hour.
+    skipped_hours: Vec<SkippedHour>,
+}
+
+/// An hour skipped after errors.
+#[derive(Debug, Serialize)]
+struct SkippedHour {
+    /// Start of the hour.
+    hour: DateTime<Utc>,
+    /// Class of failure, as counted in `skip_reasons`.
+    kind: &'static str,
+    /// Description of the failure.
+    reason: String,
+    /// Whether a later retry is likely to succeed.
+    transient: bool,
+}
+
+/// Download counters of a run.
+#[derive(Debug, Default, Serialize)]
+struct DownloadSummary {
+    /// Compressed bytes received from the server.
+    bytes_fetched: u64,
+    /// HTTP requests sent, including retries.
+    requests: u64,
+    /// Attempts that were retried.
+    retries: u64,
+    /// Files served from a cache.
+    cache_hits: u64,
+    /// Hours with no data.
+    not_found: u64,
+    /// Downloads that failed after exhausting retries.
+    failures: u64,
+    /// Hours downloaded again after their data was found corrupt.
+    refetches: u64,
+    /// Empty hours downloaded again in case their data was published late.
+    late_retries: u64,
+    /// Retries that waited the delay requested with `Retry-After`.
+    server_delays: u64,
+    /// Time spent waiting for server-requested delays, in seconds.
+    server_delay_secs: f64,
+    /// Mean time per HTTP request in seconds.
+    mean_request_secs: f64,
+    /// Longest HTTP request in seconds.
+    max_request_secs: f64,
+    /// Requests slower than the slow-request threshold.
+    slow_requests: u64,
+    /// Time spent decompressing and decoding hours, in seconds.
+    decode_secs: f64,
+}
+
+impl From<&StatsSnapshot> for DownloadSummary {
+    fn from(stats: &StatsSnapshot) -> Self {
+        Self {
+            bytes_fetched: stats.bytes_fetched,
+            requests: stats.requests,
+            retries: stats.retries,
+            cache_hits: stats.cache_hits,
+            not_found: stats.not_found,
+            failures: stats.failures,
+            refetches: stats.refetches,
+            late_retries: stats.late_retries,
+            server_delays: stats.server_delays,
+            server_delay_secs: stats.server_delay_time.as_secs_f64(),
+            mean_request_secs: stats.mean_request_time().as_secs_f64(),
+            max_request_secs: stats.max_request_time.as_secs_f64(),
+            slow_requests: stats.slow_requests,
+            decode_secs: stats.decode_time.as_secs_f64(),
+        }
+    }
+}
+
+/// A file written by the run.
+#[derive(Debug, Serialize)]
+struct FileChecksum {
+    /// Path of the file.
+    path: PathBuf,
+    /// Size of the file.
+    bytes: u64,
+    /// SHA-256 (hex) of the file.
+    sha256: String,
+}
+
+impl RunSummary {
+    /// Start recording a run of `command`.
+    pub(crate) fn start(
+        command: &'static str,
+        instrument: &str,
+        alias: Option<&str>,
+        range: DateRange,
+        timeframe: Timeframe,
+        format: Format,
+    ) -> Self {
+        Self {
+            paracas_version: env!("CARGO_PKG_VERSION"),
+            command,
+            instrument: instrument.to_string(),
+            alias: alias.map(String::from),
+            start: range.start_datetime(),
+            end: range.end_datetime(),
+            timeframe: timeframe.to_string(),
+            format: format.to_string(),
+            parameters: BTreeMap::new(),
+            started_at: Utc::now(),
+            finished_at: None,
+            duration_secs: 0.0,
+            hours: HourSummary::default(),
+            ticks: 0,
+            invalid_ticks: 0,
+            download: DownloadSummary::default(),
+            files: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Record an option the run was started with.
+    pub(crate) fn with_parameter(mut self, name: &'static str, value: impl Serialize) -> Self {
+        self.parameters
+            .insert(name, serde_json::to_value(value).unwrap_or_default());
+        self
+    }
+
+    /// Record a yielded hour.
+    pub(crate) fn record(&mut self, batch: &TickBatch) {
+        let hours = &mut self.hours;
+        hours.requested += 1;
+        if let Some(reason) = batch.skipped {
+            hours.skipped += 1;
+            *hours.skip_reasons.entry(reason.as_str()).or_default() += 1;
+            hours.skipped_hours.push(SkippedHour {
+                hour: batch.hour,
+                kind: reason.as_str(),
+                reason: reason.to_string(),
+                transient: reason.is_transient(),
+            });
+        } else if batch.is_empty() {
+            hours.empty += 1;
+        } else {
+            hours.with_data += 1;
+        }
+        self.ticks += batch.len() as u64;
+        self.invalid_ticks += batch.invalid.len() as u64;
+    }
+
+    /// Finish the run after `outputs` were written for `output`, recording
+    /// the download counters and the checksum of every file written, and
+    /// write the summary next to `output`.
+    ///
+    /// Returns the path of the summary.
+    pub(crate) fn finish(
+        mut self,
+        stats: &StatsSnapshot,
+        output: &Path,
+        outputs: &[PathBuf],
+        format: Format,
+    ) -> Result<PathBuf> {
+        self.finished_at = Some(Utc::now());
+        self.duration_secs = self.started.elapsed().as_secs_f64();
+        self.download = DownloadSummary::from(stats);
+        self.hours.skipped_hours.sort_by_key(|skipped| skipped.hour);
+
+        let mut files = Vec::new();
+        for output in outputs {
+            files.push(output.clone());
+            if !format.embeds_metadata() {
+                files.push(OutputMetadata::sidecar_path(output));
+            }
+        }
+        self.files = files
+            .into_iter()
+            .map(|path| {
+                let bytes = std::fs::metadata(&path)?.len();
+                let sha256 = sha256_file_hex(&path)?;
+                Ok(FileChecksum {
+                    path,
+                    bytes,
+                    sha256,
+                })
+            })
+            .collect::<std::io::Result<_>>()
+            .context("Failed to checksum output")?;
+
+        let path = summary_path(output);
+        let json = serde_json::to_string_pretty(&self)?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// Returns the summary path for an output file (`<file>.summary.json`).
+fn summary_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(SUMMARY_SUFFIX);
+    output.with_file_name(name)
+}
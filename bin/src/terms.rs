@@ -0,0 +1,53 @@
+//! Data terms acknowledgement.
+//!
+//! Downloads require a one-time acknowledgement of the data source terms.
+//! The acknowledgement is persisted in the state directory and must be
+//! renewed whenever the terms text changes.
+
+use crate::config::Locations;
+use anyhow::{Context, Result, bail};
+use inquire::Confirm;
+use paracas_lib::DATA_LICENSE;
+use std::io::IsTerminal;
+
+/// Ensure the data terms have been acknowledged before downloading.
+///
+/// `ack` acknowledges non-interactively (for scripts and CI). Otherwise the
+/// user is prompted when stdin is a terminal.
+pub(crate) fn ensure_acknowledged(ack: bool, quiet: bool, locations: &Locations) -> Result<()> {
+    let state = locations.state_manager()?;
+
+    if state.has_acknowledged_terms(DATA_LICENSE) {
+        return Ok(());
+    }
+
+    if ack {
+        state
+            .acknowledge_terms(DATA_LICENSE)
+            .context("Failed to record terms acknowledgement")?;
+        if !quiet {
+            eprintln!("Data terms acknowledged: {DATA_LICENSE}");
+        }
+        return Ok(());
+    }
+
+    if !std::io::stdin().is_terminal() {
+        bail!(
+            "The data terms have not been acknowledged.\n{DATA_LICENSE}\n\
+             Re-run with --ack-terms to accept them non-interactively."
+        );
+    }
+
+    println!("{DATA_LICENSE}\n");
+    let accepted = Confirm::new("Do you accept these terms?")
+        .with_default(false)
+        .prompt()?;
+    if !accepted {
+        bail!("Data terms not accepted");
+    }
+
+    state
+        .acknowledge_terms(DATA_LICENSE)
+        .context("Failed to record terms acknowledgement")?;
+    Ok(())
+}
@@ -1,16 +1,22 @@
 //! Streaming tick-to-OHLCV aggregation.
 
-use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
-use paracas_types::{Tick, Timeframe};
+use chrono::{DateTime, Datelike, TimeDelta, TimeZone, Timelike, Utc};
+use paracas_types::{PriceSource, Tick, Timeframe};
 
 use crate::Ohlcv;
 
 /// Streaming tick aggregator.
 ///
 /// Aggregates ticks into OHLCV bars based on the configured timeframe.
+/// Bars are priced from mid prices unless another [`PriceSource`] is set.
+///
+/// Periods without ticks produce no bar; pass the bars through a
+/// [`GapFiller`](crate::GapFiller) for a regular index.
 #[derive(Debug)]
 pub struct TickAggregator {
     timeframe: Timeframe,
+    price_source: PriceSource,
+    fields: BarFields,
     current_bar: Option<OhlcvBuilder>,
 }
 
@@ -20,107 +26,277 @@ impl TickAggregator {
     pub const fn new(timeframe: Timeframe) -> Self {
         Self {
             timeframe,
+            price_source: PriceSource::Mid,
+            fields: BarFields::new(),
             current_bar: None,
         }
     }
 
+    /// Sets which tick price the bars are built from.
+    #[must_use]
+    pub const fn with_price_source(mut self, source: PriceSource) -> Self {
+        self.price_source = source;
+        self
+    }
+
+    /// Preserves ask and bid volumes separately on each bar, along with the
+    /// volume imbalance, instead of only the total volume.
+    #[must_use]
+    pub const fn with_side_volumes(mut self, enabled: bool) -> Self {
+        self.fields.side_volumes = enabled;
+        self
+    }
+
+    /// Records the timestamps of the first and last tick on each bar.
+    #[must_use]
+    pub const fn with_tick_times(mut self, enabled: bool) -> Self {
+        self.fields.tick_times = enabled;
+        self
+    }
+
+    /// Adds the volume-weighted average price and the mean and largest
+    /// bid-ask spread to each bar.
+    #[must_use]
+    pub const fn with_price_stats(mut self, enabled: bool) -> Self {
+        self.fields.price_stats = enabled;
+        self
+    }
+
+    /// Adds microstructure metrics to each bar: the tick arrival rate, the
+    /// number of ticks changing the bid and the ask, and the spread weighted
+    /// by how long each quote stood until the next tick or the bar's end.
+    ///
+    /// Quote changes of a bar's first tick are counted against the last
+    /// tick of the previous bar. Has no effect on tick bars.
+    #[must_use]
+    pub const fn with_microstructure(mut self, enabled: bool) -> Self {
+        self.fields.microstructure = enabled && !self.timeframe.is_tick();
+        self
+    }
+
     /// Returns the timeframe being aggregated to.
     #[must_use]
     pub const fn timeframe(&self) -> Timeframe {
         self.timeframe
     }
 
+    /// Returns the tick price the bars are built from.
+    #[must_use]
+    pub const fn price_source(&self) -> PriceSource {
+        self.price_source
+    }
+
     /// Processes a tick, potentially emitting a completed bar.
     ///
     /// Returns `Some(bar)` when a bar is completed by this tick,
     /// `None` otherwise.
     pub fn process(&mut self, tick: Tick) -> Option<Ohlcv> {
-        let bar_start = self.bar_start_for(tick.timestamp);
+        let bar_start = bar_start(self.timeframe, tick.timestamp);
+        let price = tick.price(self.price_source);
 
         match self.current_bar.take() {
             Some(mut builder) if builder.timestamp == bar_start => {
                 // Same bar, update it
-                builder.update(&tick);
+                builder.update(&tick, price);
                 self.current_bar = Some(builder);
                 None
             }
             Some(builder) => {
                 // New bar started, finish the old one
-                let completed = builder.finish();
-                self.current_bar = Some(OhlcvBuilder::new(bar_start, &tick));
-                Some(completed)
+                let mut next = OhlcvBuilder::new(bar_start, &tick, price);
+                next.continue_from(&builder);
+                self.current_bar = Some(next);
+                Some(self.complete(builder, false))
             }
             None => {
                 // First tick
-                self.current_bar = Some(OhlcvBuilder::new(bar_start, &tick));
+                self.current_bar = Some(OhlcvBuilder::new(bar_start, &tick, price));
                 None
             }
         }
     }
 
     /// Finishes aggregation, returning any remaining partial bar.
+    ///
+    /// The returned bar is flagged with [`Ohlcv::is_partial`], since later
+    /// ticks may still fall into its period.
     #[must_use]
-    pub fn finish(self) -> Option<Ohlcv> {
-        self.current_bar.map(|b| b.finish())
-    }
-
-    /// Calculates the bar start time for a given timestamp.
-    fn bar_start_for(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
-        match self.timeframe {
-            Timeframe::Tick => timestamp,
-            Timeframe::Second1 => truncate_to_seconds(timestamp, 1),
-            Timeframe::Minute1 => truncate_to_minutes(timestamp, 1),
-            Timeframe::Minute5 => truncate_to_minutes(timestamp, 5),
-            Timeframe::Minute15 => truncate_to_minutes(timestamp, 15),
-            Timeframe::Minute30 => truncate_to_minutes(timestamp, 30),
-            Timeframe::Hour1 => truncate_to_hours(timestamp, 1),
-            Timeframe::Hour4 => truncate_to_hours(timestamp, 4),
-            Timeframe::Day1 => truncate_to_day(timestamp),
+    pub fn finish(mut self) -> Option<Ohlcv> {
+        self.current_bar.take().map(|b| self.complete(b, true))
+    }
+
+    /// Builds a bar, its last quote standing until the end of its period.
+    fn complete(&self, mut builder: OhlcvBuilder, partial: bool) -> Ohlcv {
+        if let Some(duration) = self
+            .timeframe
+            .duration()
+            .and_then(|d| TimeDelta::from_std(d).ok())
+        {
+            builder.hold_until(builder.timestamp + duration);
+        }
+        builder.build(self.fields, partial)
+    }
+}
+
+/// Calculates the start of the `timeframe` bar containing `timestamp`.
+pub(crate) fn bar_start(timeframe: Timeframe, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+    match timeframe {
+        Timeframe::Tick => timestamp,
+        Timeframe::Second1 => truncate_to_seconds(timestamp, 1),
+        Timeframe::Minute1 => truncate_to_minutes(timestamp, 1),
+        Timeframe::Minute5 => truncate_to_minutes(timestamp, 5),
+        Timeframe::Minute15 => truncate_to_minutes(timestamp, 15),
+        Timeframe::Minute30 => truncate_to_minutes(timestamp, 30),
+        Timeframe::Hour1 => truncate_to_hours(timestamp, 1),
+        Timeframe::Hour4 => truncate_to_hours(timestamp, 4),
+        Timeframe::Day1 => truncate_to_day(timestamp),
+        Timeframe::Custom(duration) => truncate_to_multiple(timestamp, duration.seconds()),
+    }
+}
+
+/// Optional fields set on built bars.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BarFields {
+    /// Per-side volumes and the imbalance.
+    pub(crate) side_volumes: bool,
+    /// First and last tick timestamps.
+    pub(crate) tick_times: bool,
+    /// VWAP and spread statistics.
+    pub(crate) price_stats: bool,
+    /// Tick rate, quote changes and time-weighted spread.
+    pub(crate) microstructure: bool,
+}
+
+impl BarFields {
+    /// Returns fields with every option disabled.
+    pub(crate) const fn new() -> Self {
+        Self {
+            side_volumes: false,
+            tick_times: false,
+            price_stats: false,
+            microstructure: false,
         }
     }
 }
 
 /// Builder for OHLCV bars.
 #[derive(Debug)]
-struct OhlcvBuilder {
+pub(crate) struct OhlcvBuilder {
     timestamp: DateTime<Utc>,
     open: f64,
     high: f64,
     low: f64,
     close: f64,
     volume: f64,
+    ask_volume: f64,
+    bid_volume: f64,
+    first_tick_ts: DateTime<Utc>,
+    last_tick_ts: DateTime<Utc>,
     tick_count: u32,
+    /// Sum of price times volume, for the VWAP.
+    price_volume: f64,
+    /// Sum of prices, for the mean price of bars without volume.
+    price_sum: f64,
+    spread_sum: f64,
+    max_spread: f64,
+    /// Quote of the last tick.
+    last_ask: f64,
+    last_bid: f64,
+    bid_changes: u32,
+    ask_changes: u32,
+    /// Sum of spreads times the seconds they stood, up to the last tick.
+    spread_seconds: f64,
+    /// When the last quote stops counting towards the time-weighted spread,
+    /// if later than the last tick.
+    end: Option<DateTime<Utc>>,
 }
 
 impl OhlcvBuilder {
-    /// Creates a new builder from the first tick.
-    fn new(timestamp: DateTime<Utc>, tick: &Tick) -> Self {
-        let mid = tick.mid();
+    /// Creates a new builder from the first tick and its price.
+    pub(crate) fn new(timestamp: DateTime<Utc>, tick: &Tick, price: f64) -> Self {
         let volume = f64::from(tick.total_volume());
         Self {
             timestamp,
-            open: mid,
-            high: mid,
-            low: mid,
-            close: mid,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
             volume,
+            ask_volume: f64::from(tick.ask_volume),
+            bid_volume: f64::from(tick.bid_volume),
+            first_tick_ts: tick.timestamp,
+            last_tick_ts: tick.timestamp,
             tick_count: 1,
+            price_volume: price * volume,
+            price_sum: price,
+            spread_sum: tick.spread(),
+            max_spread: tick.spread(),
+            last_ask: tick.ask,
+            last_bid: tick.bid,
+            bid_changes: 0,
+            ask_changes: 0,
+            spread_seconds: 0.0,
+            end: None,
+        }
+    }
+
+    /// Counts the quote changes of the bar's first tick against the last
+    /// tick of the previous bar.
+    pub(crate) fn continue_from(&mut self, previous: &Self) {
+        self.count_changes(previous.last_ask, previous.last_bid);
+    }
+
+    /// Lets the last quote stand until `end` in the time-weighted spread.
+    pub(crate) const fn hold_until(&mut self, end: DateTime<Utc>) {
+        self.end = Some(end);
+    }
+
+    /// Counts a change from the given quote to the last one.
+    fn count_changes(&mut self, previous_ask: f64, previous_bid: f64) {
+        if self.last_ask != previous_ask {
+            self.ask_changes += 1;
+        }
+        if self.last_bid != previous_bid {
+            self.bid_changes += 1;
         }
     }
 
-    /// Updates the builder with a new tick.
-    fn update(&mut self, tick: &Tick) {
-        let mid = tick.mid();
-        self.high = self.high.max(mid);
-        self.low = self.low.min(mid);
-        self.close = mid;
-        self.volume += f64::from(tick.total_volume());
+    /// Updates the builder with a new tick and its price.
+    pub(crate) fn update(&mut self, tick: &Tick, price: f64) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        let volume = f64::from(tick.total_volume());
+        self.volume += volume;
+        self.price_volume += price * volume;
+        self.price_sum += price;
+        self.spread_sum += tick.spread();
+        self.max_spread = self.max_spread.max(tick.spread());
+        self.ask_volume += f64::from(tick.ask_volume);
+        self.bid_volume += f64::from(tick.bid_volume);
+        let held = (tick.timestamp - self.last_tick_ts).as_seconds_f64();
+        self.spread_seconds += (self.last_ask - self.last_bid) * held;
+        let (previous_ask, previous_bid) = (self.last_ask, self.last_bid);
+        self.last_ask = tick.ask;
+        self.last_bid = tick.bid;
+        self.count_changes(previous_ask, previous_bid);
+        self.last_tick_ts = tick.timestamp;
         self.tick_count += 1;
     }
 
-    /// Finishes building and returns the OHLCV bar.
-    const fn finish(self) -> Ohlcv {
-        Ohlcv::new(
+    /// Returns the distance between the high and low so far.
+    pub(crate) fn range(&self) -> f64 {
+        self.high - self.low
+    }
+
+    /// Finishes building and returns the OHLCV bar, with the optional
+    /// `fields` enabled.
+    ///
+    /// The VWAP of a bar without volume is its mean tick price, and the
+    /// time-weighted spread of a bar whose quotes stood no time is its mean
+    /// spread.
+    pub(crate) fn build(self, fields: BarFields, partial: bool) -> Ohlcv {
+        let mut bar = Ohlcv::new(
             self.timestamp,
             self.open,
             self.high,
@@ -129,6 +305,39 @@ impl OhlcvBuilder {
             self.volume,
             self.tick_count,
         )
+        .with_partial(partial);
+        if fields.side_volumes {
+            bar = bar.with_side_volumes(self.ask_volume, self.bid_volume);
+        }
+        if fields.tick_times {
+            bar = bar.with_tick_times(self.first_tick_ts, self.last_tick_ts);
+        }
+        if fields.price_stats {
+            let ticks = f64::from(self.tick_count);
+            let vwap = if self.volume > 0.0 {
+                self.price_volume / self.volume
+            } else {
+                self.price_sum / ticks
+            };
+            bar = bar.with_price_stats(vwap, self.spread_sum / ticks, self.max_spread);
+        }
+        if fields.microstructure {
+            let end = self.end.unwrap_or(self.last_tick_ts).max(self.last_tick_ts);
+            let spread_seconds = self.spread_seconds
+                + (self.last_ask - self.last_bid) * (end - self.last_tick_ts).as_seconds_f64();
+            let quoted = (end - self.first_tick_ts).as_seconds_f64();
+            let ticks = f64::from(self.tick_count);
+            let twa_spread = if quoted > 0.0 {
+                spread_seconds / quoted
+            } else {
+                self.spread_sum / ticks
+            };
+            let period = (end - self.timestamp).as_seconds_f64();
+            let tick_rate = if period > 0.0 { ticks / period } else { 0.0 };
+            bar =
+                bar.with_microstructure(tick_rate, self.bid_changes, self.ask_changes, twa_spread);
+        }
+        bar
     }
 }
 
@@ -160,6 +369,14 @@ fn truncate_to_hours(dt: DateTime<Utc>, interval: u32) -> DateTime<Utc> {
         .unwrap()
 }
 
+/// Truncates a timestamp to a multiple of `interval` seconds since the Unix
+/// epoch.
+fn truncate_to_multiple(dt: DateTime<Utc>, interval: u64) -> DateTime<Utc> {
+    let interval = i64::try_from(interval).unwrap_or(i64::MAX).max(1);
+    let seconds = dt.timestamp();
+    DateTime::from_timestamp(seconds - seconds.rem_euclid(interval), 0).unwrap_or(dt)
+}
+
 /// Truncates a timestamp to the start of the day.
 fn truncate_to_day(dt: DateTime<Utc>) -> DateTime<Utc> {
     Utc.with_ymd_and_hms(dt.year(), dt.month(), dt.day(), 0, 0, 0)
@@ -228,6 +445,136 @@ mod tests {
         assert_eq!(bar.tick_count, 1);
     }
 
+    #[test]
+    fn test_side_volumes() {
+        let mut agg = TickAggregator::new(Timeframe::Hour1).with_side_volumes(true);
+
+        let mut tick1 = make_tick(12, 0, 0, 0, 1.1001, 1.1000);
+        tick1.ask_volume = 1.0;
+        tick1.bid_volume = 3.0;
+        let mut tick2 = make_tick(12, 30, 0, 0, 1.1002, 1.1001);
+        tick2.ask_volume = 1.0;
+        tick2.bid_volume = 1.0;
+        agg.process(tick1);
+        agg.process(tick2);
+
+        let bar = agg.finish().unwrap();
+        assert_eq!(bar.ask_volume, Some(2.0));
+        assert_eq!(bar.bid_volume, Some(4.0));
+        assert!((bar.imbalance.unwrap() - 2.0 / 6.0).abs() < 1e-10);
+        assert!((bar.volume - 6.0).abs() < 1e-10);
+
+        // Disabled by default
+        let mut agg = TickAggregator::new(Timeframe::Hour1);
+        agg.process(tick1);
+        assert!(!agg.finish().unwrap().has_side_volumes());
+    }
+
+    #[test]
+    fn test_price_stats() {
+        let mut agg = TickAggregator::new(Timeframe::Hour1)
+            .with_price_source(PriceSource::Bid)
+            .with_price_stats(true);
+        let mut tick1 = make_tick(12, 0, 0, 0, 1.0002, 1.0000);
+        tick1.ask_volume = 1.0;
+        tick1.bid_volume = 0.0;
+        let mut tick2 = make_tick(12, 30, 0, 0, 1.0008, 1.0004);
+        tick2.ask_volume = 2.0;
+        tick2.bid_volume = 1.0;
+        agg.process(tick1);
+        agg.process(tick2);
+
+        let bar = agg.finish().unwrap();
+        assert!((bar.vwap.unwrap() - 1.0003).abs() < 1e-12);
+        assert!((bar.avg_spread.unwrap() - 0.0003).abs() < 1e-12);
+        assert!((bar.max_spread.unwrap() - 0.0004).abs() < 1e-12);
+
+        // Without volume, the VWAP is the mean price
+        tick1.ask_volume = 0.0;
+        tick2.ask_volume = 0.0;
+        tick2.bid_volume = 0.0;
+        let mut agg = TickAggregator::new(Timeframe::Hour1)
+            .with_price_source(PriceSource::Bid)
+            .with_price_stats(true);
+        agg.process(tick1);
+        agg.process(tick2);
+        assert!((agg.finish().unwrap().vwap.unwrap() - 1.0002).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_microstructure() {
+        let mut agg = TickAggregator::new(Timeframe::Hour1).with_microstructure(true);
+        agg.process(make_tick(12, 0, 0, 0, 1.0002, 1.0000));
+        agg.process(make_tick(12, 15, 0, 0, 1.0002, 1.0001));
+        agg.process(make_tick(12, 30, 0, 0, 1.0005, 1.0001));
+
+        let bar = agg.process(make_tick(13, 0, 0, 0, 1.0005, 1.0002)).unwrap();
+        assert!((bar.tick_rate.unwrap() - 3.0 / 3600.0).abs() < 1e-12);
+        assert_eq!((bar.bid_changes, bar.ask_changes), (Some(1), Some(1)));
+        // Spreads of 2, 1 and 4 pips standing 15, 15 and 30 minutes
+        assert!((bar.twa_spread.unwrap() - 0.000_275).abs() < 1e-12);
+
+        // The first tick's bid changed from the previous bar's last tick
+        let last = agg.finish().unwrap();
+        assert_eq!((last.bid_changes, last.ask_changes), (Some(1), Some(0)));
+        assert!((last.twa_spread.unwrap() - 0.0003).abs() < 1e-12);
+
+        let tick_bars = TickAggregator::new(Timeframe::Tick).with_microstructure(true);
+        assert!(!tick_bars.fields.microstructure);
+    }
+
+    #[test]
+    fn test_tick_times_and_partial() {
+        let mut agg = TickAggregator::new(Timeframe::Hour1).with_tick_times(true);
+
+        let tick1 = make_tick(12, 5, 0, 0, 1.1001, 1.1000);
+        let tick2 = make_tick(12, 40, 0, 250, 1.1002, 1.1001);
+        let tick3 = make_tick(13, 1, 0, 0, 1.1003, 1.1002);
+        agg.process(tick1);
+        agg.process(tick2);
+
+        let bar = agg.process(tick3).unwrap();
+        assert_eq!(bar.first_tick_ts, Some(tick1.timestamp));
+        assert_eq!(bar.last_tick_ts, Some(tick2.timestamp));
+        assert!(!bar.is_partial);
+
+        let last = agg.finish().unwrap();
+        assert!(last.is_partial);
+        assert_eq!(last.first_tick_ts, Some(tick3.timestamp));
+
+        // Tick times are opt-in, the partial flag is not
+        let mut agg = TickAggregator::new(Timeframe::Hour1);
+        agg.process(tick1);
+        let last = agg.finish().unwrap();
+        assert!(!last.has_tick_times());
+        assert!(last.is_partial);
+    }
+
+    #[test]
+    fn test_price_source() {
+        let ticks = [
+            make_tick(12, 0, 0, 0, 1.1002, 1.1000),
+            make_tick(12, 10, 0, 0, 1.1012, 1.1008),
+            make_tick(12, 20, 0, 0, 1.0996, 1.0990),
+        ];
+        let bar = |source| {
+            let mut agg = TickAggregator::new(Timeframe::Hour1).with_price_source(source);
+            assert_eq!(agg.price_source(), source);
+            for tick in ticks {
+                agg.process(tick);
+            }
+            let bar = agg.finish().unwrap();
+            [bar.open, bar.high, bar.low, bar.close]
+        };
+
+        assert_eq!(bar(PriceSource::Bid), [1.1000, 1.1008, 1.0990, 1.0990]);
+        assert_eq!(bar(PriceSource::Ask), [1.1002, 1.1012, 1.0996, 1.0996]);
+        // Mid prices by default
+        let mid = bar(PriceSource::Mid);
+        assert!((mid[0] - 1.1001).abs() < 1e-10);
+        assert!((mid[1] - 1.1010).abs() < 1e-10);
+    }
+
     #[test]
     fn test_truncate_functions() {
         let dt = Utc.with_ymd_and_hms(2024, 1, 15, 14, 37, 45).unwrap();
@@ -237,4 +584,134 @@ mod tests {
         assert_eq!(truncate_to_hours(dt, 4).hour(), 12);
         assert_eq!(truncate_to_day(dt).hour(), 0);
     }
+
+    #[test]
+    fn test_custom_timeframe_buckets() {
+        let custom =
+            |seconds| Timeframe::from_duration(std::time::Duration::from_secs(seconds)).unwrap();
+        let dt = Utc.with_ymd_and_hms(2024, 1, 15, 14, 37, 45).unwrap();
+        let at = |h, m, s| Utc.with_ymd_and_hms(2024, 1, 15, h, m, s).unwrap();
+        assert_eq!(bar_start(custom(90), dt), at(14, 37, 30));
+        assert_eq!(bar_start(custom(7200), dt), at(14, 0, 0));
+        assert_eq!(bar_start(custom(21_600), dt), at(12, 0, 0));
+        assert_eq!(bar_start(custom(3600), dt), bar_start(Timeframe::Hour1, dt));
+
+        let mut agg = TickAggregator::new(custom(90));
+        assert!(agg.process(make_tick(10, 0, 10, 0, 1.1, 1.0)).is_none());
+        assert!(agg.process(make_tick(10, 1, 29, 0, 1.1, 1.0)).is_none());
+        let bar = agg.process(make_tick(10, 1, 30, 0, 1.1, 1.0)).unwrap();
+        assert_eq!(
+            bar.timestamp,
+            Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap()
+        );
+        assert_eq!(bar.tick_count, 2);
+    }
+
+    mod properties {
+        use super::*;
+        use crate::BarResampler;
+        use proptest::prelude::*;
+
+        /// Timeframe pairs where the coarse one is a whole multiple of the fine one.
+        const NESTED: &[(Timeframe, Timeframe)] = &[
+            (Timeframe::Second1, Timeframe::Minute1),
+            (Timeframe::Minute1, Timeframe::Minute5),
+            (Timeframe::Minute1, Timeframe::Hour1),
+            (Timeframe::Minute5, Timeframe::Minute15),
+            (Timeframe::Minute15, Timeframe::Hour4),
+            (Timeframe::Minute30, Timeframe::Hour1),
+            (Timeframe::Hour1, Timeframe::Hour4),
+            (Timeframe::Hour4, Timeframe::Day1),
+        ];
+
+        /// Ordered ticks spread over up to a few days.
+        ///
+        /// Volumes are multiples of 0.25 so that sums are exact regardless of
+        /// the order they are added in.
+        fn ticks() -> impl Strategy<Value = Vec<Tick>> {
+            let tick = (0..600_000i64, 1.0..2.0f64, 0.0..0.001f64, 0..40u8, 0..40u8);
+            (0..3 * 86_400_000i64, prop::collection::vec(tick, 1..300)).prop_map(
+                |(offset, steps)| {
+                    let mut timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+                        + TimeDelta::milliseconds(offset);
+                    steps
+                        .into_iter()
+                        .map(|(step, bid, spread, ask_volume, bid_volume)| {
+                            timestamp += TimeDelta::milliseconds(step);
+                            Tick::new(
+                                timestamp,
+                                bid + spread,
+                                bid,
+                                f32::from(ask_volume) / 4.0,
+                                f32::from(bid_volume) / 4.0,
+                            )
+                        })
+                        .collect()
+                },
+            )
+        }
+
+        fn aggregate(ticks: &[Tick], timeframe: Timeframe) -> Vec<Ohlcv> {
+            let mut aggregator = TickAggregator::new(timeframe)
+                .with_side_volumes(true)
+                .with_tick_times(true);
+            let mut bars: Vec<_> = ticks
+                .iter()
+                .filter_map(|tick| aggregator.process(*tick))
+                .collect();
+            bars.extend(aggregator.finish());
+            bars
+        }
+
+        proptest! {
+            #[test]
+            fn prop_prices_within_range(ticks in ticks()) {
+                for timeframe in Timeframe::all() {
+                    for bar in aggregate(&ticks, *timeframe) {
+                        prop_assert!(bar.low <= bar.open && bar.open <= bar.high);
+                        prop_assert!(bar.low <= bar.close && bar.close <= bar.high);
+                    }
+                }
+            }
+
+            #[test]
+            fn prop_volume_and_ticks_conserved(ticks in ticks()) {
+                let volume: f64 = ticks.iter().map(|t| f64::from(t.total_volume())).sum();
+                for timeframe in Timeframe::all() {
+                    let bars = aggregate(&ticks, *timeframe);
+                    let bar_volume: f64 = bars.iter().map(|bar| bar.volume).sum();
+                    let tick_count: u32 = bars.iter().map(|bar| bar.tick_count).sum();
+                    prop_assert_eq!(bar_volume, volume);
+                    prop_assert_eq!(tick_count as usize, ticks.len());
+                    prop_assert!(bars.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+                    // Only the trailing bar is partial
+                    prop_assert!(bars.last().unwrap().is_partial);
+                    prop_assert!(bars.iter().rev().skip(1).all(|bar| !bar.is_partial));
+                }
+            }
+
+            #[test]
+            fn prop_bar_counts_nest(ticks in ticks()) {
+                for (fine, coarse) in NESTED {
+                    let fine_bars = aggregate(&ticks, *fine);
+                    let coarse_bars = aggregate(&ticks, *coarse);
+                    let mut starts: Vec<_> = fine_bars
+                        .iter()
+                        .map(|bar| bar_start(*coarse, bar.timestamp))
+                        .collect();
+                    starts.dedup();
+                    prop_assert_eq!(starts.len(), coarse_bars.len());
+                    prop_assert!(fine_bars.len() >= coarse_bars.len());
+                }
+            }
+
+            #[test]
+            fn prop_resample_matches_direct_aggregation(ticks in ticks()) {
+                for (fine, coarse) in NESTED {
+                    let resampled = BarResampler::resample(*coarse, &aggregate(&ticks, *fine));
+                    prop_assert_eq!(resampled, aggregate(&ticks, *coarse));
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,237 @@
+//! Bars carrying both sides of the quote.
+
+use chrono::{DateTime, Utc};
+use paracas_types::{Tick, Timeframe};
+use serde::{Deserialize, Serialize};
+
+use crate::Ohlcv;
+use crate::aggregator::bar_start;
+
+/// OHLC bar with separate bid and ask prices.
+///
+/// Unlike [`Ohlcv`], which is priced from one side or the mid, this keeps
+/// the open, high, low and close of both sides, so spread-sensitive
+/// strategies see the quotes actually traded against.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OhlcvBidAsk {
+    /// Bar open time (start of the period).
+    pub timestamp: DateTime<Utc>,
+    /// First bid price.
+    pub bid_open: f64,
+    /// Highest bid price.
+    pub bid_high: f64,
+    /// Lowest bid price.
+    pub bid_low: f64,
+    /// Last bid price.
+    pub bid_close: f64,
+    /// First ask price.
+    pub ask_open: f64,
+    /// Highest ask price.
+    pub ask_high: f64,
+    /// Lowest ask price.
+    pub ask_low: f64,
+    /// Last ask price.
+    pub ask_close: f64,
+    /// Summed bid-side volume.
+    pub bid_volume: f64,
+    /// Summed ask-side volume.
+    pub ask_volume: f64,
+    /// Number of ticks in the bar.
+    pub tick_count: u32,
+    /// Whether the bar may be incomplete, as [`Ohlcv::is_partial`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_partial: bool,
+}
+
+impl OhlcvBidAsk {
+    /// Creates a bar from its first tick.
+    fn open(timestamp: DateTime<Utc>, tick: &Tick) -> Self {
+        Self {
+            timestamp,
+            bid_open: tick.bid,
+            bid_high: tick.bid,
+            bid_low: tick.bid,
+            bid_close: tick.bid,
+            ask_open: tick.ask,
+            ask_high: tick.ask,
+            ask_low: tick.ask,
+            ask_close: tick.ask,
+            bid_volume: f64::from(tick.bid_volume),
+            ask_volume: f64::from(tick.ask_volume),
+            tick_count: 1,
+            is_partial: false,
+        }
+    }
+
+    /// Updates the bar with a later tick.
+    fn update(&mut self, tick: &Tick) {
+        self.bid_high = self.bid_high.max(tick.bid);
+        self.bid_low = self.bid_low.min(tick.bid);
+        self.bid_close = tick.bid;
+        self.ask_high = self.ask_high.max(tick.ask);
+        self.ask_low = self.ask_low.min(tick.ask);
+        self.ask_close = tick.ask;
+        self.bid_volume += f64::from(tick.bid_volume);
+        self.ask_volume += f64::from(tick.ask_volume);
+        self.tick_count += 1;
+    }
+
+    /// Returns the total volume (ask + bid).
+    #[must_use]
+    pub fn volume(&self) -> f64 {
+        self.ask_volume + self.bid_volume
+    }
+
+    /// Returns the spread at the open.
+    #[must_use]
+    pub fn open_spread(&self) -> f64 {
+        self.ask_open - self.bid_open
+    }
+
+    /// Returns the spread at the close.
+    #[must_use]
+    pub fn close_spread(&self) -> f64 {
+        self.ask_close - self.bid_close
+    }
+
+    /// Returns the bid side as an [`Ohlcv`] bar with side volumes.
+    #[must_use]
+    pub fn bid(&self) -> Ohlcv {
+        self.side(self.bid_open, self.bid_high, self.bid_low, self.bid_close)
+    }
+
+    /// Returns the ask side as an [`Ohlcv`] bar with side volumes.
+    #[must_use]
+    pub fn ask(&self) -> Ohlcv {
+        self.side(self.ask_open, self.ask_high, self.ask_low, self.ask_close)
+    }
+
+    /// Builds an [`Ohlcv`] bar from one side's prices.
+    fn side(&self, open: f64, high: f64, low: f64, close: f64) -> Ohlcv {
+        Ohlcv::new(
+            self.timestamp,
+            open,
+            high,
+            low,
+            close,
+            self.volume(),
+            self.tick_count,
+        )
+        .with_side_volumes(self.ask_volume, self.bid_volume)
+        .with_partial(self.is_partial)
+    }
+}
+
+/// Streaming aggregator producing [`OhlcvBidAsk`] bars.
+///
+/// Works like [`TickAggregator`](crate::TickAggregator), but keeps bid and
+/// ask prices apart instead of pricing bars from one side.
+#[derive(Debug)]
+pub struct BidAskAggregator {
+    timeframe: Timeframe,
+    current: Option<OhlcvBidAsk>,
+}
+
+impl BidAskAggregator {
+    /// Creates a new aggregator for the given timeframe.
+    #[must_use]
+    pub const fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            current: None,
+        }
+    }
+
+    /// Returns the timeframe being aggregated to.
+    #[must_use]
+    pub const fn timeframe(&self) -> Timeframe {
+        self.timeframe
+    }
+
+    /// Processes a tick, potentially emitting a completed bar.
+    ///
+    /// Returns `Some(bar)` when a bar is completed by this tick, `None`
+    /// otherwise.
+    pub fn process(&mut self, tick: Tick) -> Option<OhlcvBidAsk> {
+        let start = bar_start(self.timeframe, tick.timestamp);
+        match self.current.as_mut() {
+            Some(bar) if bar.timestamp == start => {
+                bar.update(&tick);
+                None
+            }
+            _ => self.current.replace(OhlcvBidAsk::open(start, &tick)),
+        }
+    }
+
+    /// Finishes aggregation, returning any remaining bar flagged as partial.
+    #[must_use]
+    pub fn finish(mut self) -> Option<OhlcvBidAsk> {
+        self.current.take().map(|bar| OhlcvBidAsk {
+            is_partial: true,
+            ..bar
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TickAggregator;
+    use chrono::TimeZone;
+    use paracas_types::PriceSource;
+
+    fn tick(minute: u32, ask: f64, bid: f64) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Tick::new(timestamp, ask, bid, 1.0, 2.0)
+    }
+
+    #[test]
+    fn test_bid_ask_bars() {
+        let mut agg = BidAskAggregator::new(Timeframe::Minute30);
+        assert!(agg.process(tick(0, 1.1002, 1.1000)).is_none());
+        assert!(agg.process(tick(10, 1.1010, 1.1006)).is_none());
+        assert!(agg.process(tick(20, 1.0995, 1.0990)).is_none());
+
+        let bar = agg.process(tick(30, 1.1001, 1.0999)).unwrap();
+        assert_eq!(
+            [bar.bid_open, bar.bid_high, bar.bid_low, bar.bid_close],
+            [1.1000, 1.1006, 1.0990, 1.0990]
+        );
+        assert_eq!(
+            [bar.ask_open, bar.ask_high, bar.ask_low, bar.ask_close],
+            [1.1002, 1.1010, 1.0995, 1.0995]
+        );
+        assert_eq!(bar.tick_count, 3);
+        assert!((bar.volume() - 9.0).abs() < 1e-10);
+        assert!((bar.close_spread() - 0.0005).abs() < 1e-10);
+        assert!(!bar.is_partial);
+
+        let last = agg.finish().unwrap();
+        assert!(last.is_partial);
+        assert_eq!(last.tick_count, 1);
+    }
+
+    #[test]
+    fn test_sides_match_priced_aggregation() {
+        let ticks = [
+            tick(0, 1.1002, 1.1000),
+            tick(10, 1.1010, 1.1006),
+            tick(20, 1.0995, 1.0990),
+        ];
+        let mut agg = BidAskAggregator::new(Timeframe::Hour1);
+        for tick in ticks {
+            agg.process(tick);
+        }
+        let bar = agg.finish().unwrap();
+
+        for (source, side) in [(PriceSource::Bid, bar.bid()), (PriceSource::Ask, bar.ask())] {
+            let mut priced = TickAggregator::new(Timeframe::Hour1)
+                .with_price_source(source)
+                .with_side_volumes(true);
+            for tick in ticks {
+                priced.process(tick);
+            }
+            assert_eq!(priced.finish().unwrap(), side);
+        }
+    }
+}
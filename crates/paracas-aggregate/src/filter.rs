@@ -0,0 +1,244 @@
+//! Tick filtering ahead of aggregation or formatting.
+
+use paracas_types::Tick;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Default number of recent mid prices the spike check compares against.
+pub const DEFAULT_SPIKE_WINDOW: usize = 100;
+
+/// Why a [`TickFilter`] rejected a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterReason {
+    /// The ask or bid is zero, negative or not a number.
+    NonPositive,
+    /// The bid equals the ask.
+    ZeroSpread,
+    /// The bid is above the ask.
+    Crossed,
+    /// The mid price is too many MADs off the recent median.
+    Spike,
+}
+
+impl FilterReason {
+    /// Returns a short description of the reason.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::NonPositive => "non-positive price",
+            Self::ZeroSpread => "zero spread",
+            Self::Crossed => "bid above ask",
+            Self::Spike => "price spike",
+        }
+    }
+}
+
+impl fmt::Display for FilterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Streaming filter removing bad ticks before they corrupt bar highs and
+/// lows.
+///
+/// Each check is opt-in. The spike check keeps a rolling window of recent
+/// mid prices and rejects a tick whose mid is more than `max_mads` median
+/// absolute deviations (MAD) off the window median. Every tick with sane
+/// quotes enters the window, spikes included: the median and MAD shrug off
+/// isolated spikes, while a genuine jump in the market becomes the new
+/// normal once it has lasted half a window. Nothing is rejected as a spike
+/// until the window is full, or while its prices are all equal.
+///
+/// Ticks must be fed in time order; keep one filter per instrument.
+#[derive(Debug, Clone, Default)]
+pub struct TickFilter {
+    drop_zero_spreads: bool,
+    drop_crossed: bool,
+    max_mads: Option<f64>,
+    window_size: usize,
+    /// Recent mid prices, oldest first.
+    window: VecDeque<f64>,
+    dropped: u64,
+}
+
+impl TickFilter {
+    /// Creates a filter with every check disabled.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            drop_zero_spreads: false,
+            drop_crossed: false,
+            max_mads: None,
+            window_size: DEFAULT_SPIKE_WINDOW,
+            window: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Drops ticks whose bid equals the ask.
+    #[must_use]
+    pub const fn with_drop_zero_spreads(mut self, enabled: bool) -> Self {
+        self.drop_zero_spreads = enabled;
+        self
+    }
+
+    /// Drops ticks whose bid is above the ask, or with a zero, negative or
+    /// NaN price.
+    #[must_use]
+    pub const fn with_drop_crossed(mut self, enabled: bool) -> Self {
+        self.drop_crossed = enabled;
+        self
+    }
+
+    /// Drops ticks whose mid price is more than `max_mads` MADs off the
+    /// median of the last `window` mid prices.
+    #[must_use]
+    pub const fn with_spike_filter(mut self, max_mads: f64, window: usize) -> Self {
+        self.max_mads = Some(max_mads);
+        self.window_size = window;
+        self
+    }
+
+    /// Returns true if any check is enabled.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.drop_zero_spreads || self.drop_crossed || self.max_mads.is_some()
+    }
+
+    /// Returns the number of ticks rejected so far.
+    #[must_use]
+    pub const fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Checks the next tick, returning why it should be dropped, if it
+    /// should.
+    pub fn check(&mut self, tick: &Tick) -> Option<FilterReason> {
+        let reason = self.quote_problem(tick).or_else(|| {
+            let spike = self.is_spike(tick.mid());
+            self.push(tick.mid());
+            spike.then_some(FilterReason::Spike)
+        });
+        if reason.is_some() {
+            self.dropped += 1;
+        }
+        reason
+    }
+
+    /// Removes the rejected ticks from a batch, returning how many were
+    /// removed.
+    pub fn retain(&mut self, ticks: &mut Vec<Tick>) -> usize {
+        if !self.is_enabled() {
+            return 0;
+        }
+        let before = ticks.len();
+        ticks.retain(|tick| self.check(tick).is_none());
+        before - ticks.len()
+    }
+
+    /// Returns the problem with a tick's quotes that an enabled check
+    /// rejects, if any.
+    fn quote_problem(&self, tick: &Tick) -> Option<FilterReason> {
+        // Written to also catch NaN
+        if !(tick.ask > 0.0 && tick.bid > 0.0) {
+            return (self.drop_crossed || self.max_mads.is_some())
+                .then_some(FilterReason::NonPositive);
+        }
+        if self.drop_crossed && tick.bid > tick.ask {
+            Some(FilterReason::Crossed)
+        } else if self.drop_zero_spreads && tick.bid == tick.ask {
+            Some(FilterReason::ZeroSpread)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if `mid` is too far off the window median.
+    fn is_spike(&self, mid: f64) -> bool {
+        let Some(max_mads) = self.max_mads else {
+            return false;
+        };
+        if self.window.len() < self.window_size.max(1) {
+            return false;
+        }
+        let mut values: Vec<f64> = self.window.iter().copied().collect();
+        let center = median(&mut values);
+        values
+            .iter_mut()
+            .for_each(|value| *value = (*value - center).abs());
+        let mad = median(&mut values);
+        mad > 0.0 && (mid - center).abs() > max_mads * mad
+    }
+
+    /// Adds a mid price to the window, evicting the oldest if it is full.
+    fn push(&mut self, mid: f64) {
+        if self.max_mads.is_none() {
+            return;
+        }
+        if self.window.len() >= self.window_size.max(1) {
+            self.window.pop_front();
+        }
+        self.window.push_back(mid);
+    }
+}
+
+/// Returns the median of a non-empty slice, reordering it.
+fn median(values: &mut [f64]) -> f64 {
+    let middle = values.len() / 2;
+    let (_, median, _) = values.select_nth_unstable_by(middle, f64::total_cmp);
+    *median
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    fn tick(second: i64, ask: f64, bid: f64) -> Tick {
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        Tick::new(start + TimeDelta::seconds(second), ask, bid, 1.0, 1.0)
+    }
+
+    #[test]
+    fn test_quote_checks() {
+        let mut ticks = vec![
+            tick(0, 1.1002, 1.1000),
+            tick(1, 1.1001, 1.1001),
+            tick(2, 1.1000, 1.1003),
+            tick(3, 0.0, 1.1000),
+            tick(4, 1.1003, 1.1001),
+        ];
+        let mut filter = TickFilter::new();
+        assert_eq!(filter.retain(&mut ticks), 0);
+
+        let mut filter = TickFilter::new()
+            .with_drop_zero_spreads(true)
+            .with_drop_crossed(true);
+        assert_eq!(filter.check(&ticks[1]), Some(FilterReason::ZeroSpread));
+        assert_eq!(filter.check(&ticks[2]), Some(FilterReason::Crossed));
+        assert_eq!(filter.check(&ticks[3]), Some(FilterReason::NonPositive));
+        assert_eq!(filter.retain(&mut ticks), 3);
+        assert_eq!(ticks.len(), 2);
+        assert_eq!(filter.dropped(), 6);
+    }
+
+    #[test]
+    fn test_spike_filter() {
+        let mut filter = TickFilter::new().with_spike_filter(5.0, 10);
+        let mut ticks: Vec<_> = (0..20)
+            .map(|i| {
+                let bid = 1.1 + f64::from(i % 3) * 0.0001;
+                tick(i.into(), bid + 0.0002, bid)
+            })
+            .collect();
+        // A lone spike, then a lasting jump that becomes the new level
+        ticks[15] = tick(15, 1.2002, 1.2);
+        ticks.extend((20..40).map(|i| tick(i, 1.1502, 1.15)));
+
+        // The spike and the first four ticks at the new level are dropped
+        assert_eq!(filter.retain(&mut ticks), 5);
+        assert!(ticks.iter().all(|t| t.bid != 1.2));
+        assert_eq!(ticks.iter().filter(|t| t.bid == 1.15).count(), 16);
+    }
+}
@@ -0,0 +1,136 @@
+//! Filling bar periods without ticks.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use paracas_types::{GapFill, Timeframe};
+
+use crate::Ohlcv;
+
+/// Streaming adapter inserting bars for empty periods between bars.
+///
+/// [`TickAggregator`](crate::TickAggregator) only emits bars for periods
+/// with ticks. Feeding its bars through a filler yields one bar per period
+/// from the first bar to the last, with the gaps filled according to a
+/// [`GapFill`] policy. Filled bars have zero volume and a zero tick count,
+/// and carry zero side volumes when the preceding bar has side volumes.
+#[derive(Debug)]
+pub struct GapFiller {
+    timeframe: Timeframe,
+    policy: GapFill,
+    previous: Option<Ohlcv>,
+}
+
+impl GapFiller {
+    /// Creates a filler for bars of `timeframe`.
+    #[must_use]
+    pub const fn new(timeframe: Timeframe, policy: GapFill) -> Self {
+        Self {
+            timeframe,
+            policy,
+            previous: None,
+        }
+    }
+
+    /// Returns the gap-filling policy.
+    #[must_use]
+    pub const fn policy(&self) -> GapFill {
+        self.policy
+    }
+
+    /// Processes a bar, returning the bars for the empty periods since the
+    /// previous bar followed by `bar` itself.
+    ///
+    /// Bars must arrive in order. Tick data and the [`GapFill::Skip`] policy
+    /// pass bars through unchanged.
+    pub fn process(&mut self, bar: Ohlcv) -> Vec<Ohlcv> {
+        let mut bars = Vec::new();
+        if let (Some(previous), Some(step)) = (self.previous, self.step())
+            && self.policy.fills()
+        {
+            let mut timestamp = previous.timestamp + step;
+            while timestamp < bar.timestamp {
+                bars.push(self.gap_bar(&previous, timestamp));
+                timestamp += step;
+            }
+        }
+        self.previous = Some(bar);
+        bars.push(bar);
+        bars
+    }
+
+    /// Fills the gaps of a complete series of bars.
+    #[must_use]
+    pub fn fill(timeframe: Timeframe, policy: GapFill, bars: &[Ohlcv]) -> Vec<Ohlcv> {
+        let mut filler = Self::new(timeframe, policy);
+        bars.iter().flat_map(|bar| filler.process(*bar)).collect()
+    }
+
+    /// Returns the length of a bar period, or `None` for tick data.
+    fn step(&self) -> Option<TimeDelta> {
+        let seconds = self.timeframe.seconds()?;
+        TimeDelta::try_seconds(i64::try_from(seconds).ok()?)
+    }
+
+    /// Builds the bar for an empty period following `previous`.
+    fn gap_bar(&self, previous: &Ohlcv, timestamp: DateTime<Utc>) -> Ohlcv {
+        let price = match self.policy {
+            GapFill::Flat => previous.close,
+            GapFill::Skip | GapFill::Null => f64::NAN,
+        };
+        let bar = Ohlcv::new(timestamp, price, price, price, price, 0.0, 0);
+        if previous.has_side_volumes() {
+            bar.with_side_volumes(0.0, 0.0)
+        } else {
+            bar
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike};
+
+    fn bar(minute: u32, close: f64) -> Ohlcv {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Ohlcv::new(timestamp, 1.0, 1.5, 0.5, close, 2.0, 3)
+    }
+
+    #[test]
+    fn test_flat_fill() {
+        let bars = [bar(0, 1.1), bar(1, 1.2), bar(4, 1.3)];
+        let filled = GapFiller::fill(Timeframe::Minute1, GapFill::Flat, &bars);
+
+        let minutes: Vec<_> = filled.iter().map(|bar| bar.timestamp.minute()).collect();
+        assert_eq!(minutes, [0, 1, 2, 3, 4]);
+        for gap in &filled[2..4] {
+            assert_eq!(
+                (gap.open, gap.high, gap.low, gap.close),
+                (1.2, 1.2, 1.2, 1.2)
+            );
+            assert_eq!((gap.volume, gap.tick_count), (0.0, 0));
+            assert!(!gap.has_side_volumes());
+        }
+        assert_eq!(filled[4], bars[2]);
+    }
+
+    #[test]
+    fn test_null_fill_and_skip() {
+        let bars = [bar(0, 1.1).with_side_volumes(1.0, 1.0), bar(10, 1.3)];
+        let filled = GapFiller::fill(Timeframe::Minute5, GapFill::Null, &bars);
+        assert_eq!(filled.len(), 3);
+        assert!(filled[1].open.is_nan() && filled[1].close.is_nan());
+        assert_eq!(filled[1].ask_volume, Some(0.0));
+
+        let skipped = GapFiller::fill(Timeframe::Minute5, GapFill::Skip, &bars);
+        assert_eq!(skipped, bars);
+        let ticks = GapFiller::fill(Timeframe::Tick, GapFill::Flat, &bars);
+        assert_eq!(ticks, bars);
+    }
+
+    #[test]
+    fn test_policy_from_str() {
+        assert_eq!("FLAT".parse(), Ok(GapFill::Flat));
+        assert_eq!("nan".parse(), Ok(GapFill::Null));
+        assert!("zero".parse::<GapFill>().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Heikin-Ashi candle transform.
+
+use paracas_types::CandleStyle;
+
+use crate::Ohlcv;
+
+/// Streaming transform of bars into Heikin-Ashi candles.
+///
+/// Each candle closes at the mean of its bar's open, high, low and close,
+/// and opens at the midpoint of the previous candle's body (the first
+/// candle opens at the midpoint of its bar's body). Highs and lows are
+/// widened to cover the new open and close. Volume and every other field
+/// are kept.
+///
+/// Bars with missing (NaN) prices, such as null gap fills, pass through
+/// unchanged and do not affect later candles.
+#[derive(Debug, Default)]
+pub struct HeikinAshi {
+    /// Open and close of the previous candle.
+    previous: Option<(f64, f64)>,
+}
+
+impl HeikinAshi {
+    /// Creates a transform starting a new series.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { previous: None }
+    }
+
+    /// Converts the next bar of the series into a Heikin-Ashi candle.
+    pub fn process(&mut self, bar: Ohlcv) -> Ohlcv {
+        if bar.open.is_nan() || bar.close.is_nan() {
+            return bar;
+        }
+        let close = (bar.open + bar.high + bar.low + bar.close) / 4.0;
+        let open = self.previous.map_or_else(
+            || (bar.open + bar.close) / 2.0,
+            |(open, close)| (open + close) / 2.0,
+        );
+        self.previous = Some((open, close));
+        Ohlcv {
+            open,
+            high: bar.high.max(open).max(close),
+            low: bar.low.min(open).min(close),
+            close,
+            ..bar
+        }
+    }
+
+    /// Converts a complete series of bars.
+    #[must_use]
+    pub fn transform(bars: &[Ohlcv]) -> Vec<Ohlcv> {
+        let mut heikin_ashi = Self::new();
+        bars.iter().map(|bar| heikin_ashi.process(*bar)).collect()
+    }
+
+    /// Applies a candle style to a complete series of bars.
+    #[must_use]
+    pub fn apply(style: CandleStyle, bars: &[Ohlcv]) -> Vec<Ohlcv> {
+        match style {
+            CandleStyle::Standard => bars.to_vec(),
+            CandleStyle::HeikinAshi => Self::transform(bars),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn bar(minute: u32, open: f64, high: f64, low: f64, close: f64) -> Ohlcv {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Ohlcv::new(timestamp, open, high, low, close, 2.0, 3)
+    }
+
+    #[test]
+    fn test_transform() {
+        let bars = [
+            bar(0, 10.0, 12.0, 9.0, 11.0),
+            bar(1, 11.0, 11.0, 7.0, 8.0),
+            bar(2, f64::NAN, f64::NAN, f64::NAN, f64::NAN),
+            bar(3, 8.0, 10.0, 8.0, 10.0),
+        ];
+        let candles = HeikinAshi::transform(&bars);
+
+        let ohlc = |c: &Ohlcv| (c.open, c.high, c.low, c.close);
+        assert_eq!(ohlc(&candles[0]), (10.5, 12.0, 9.0, 10.5));
+        assert_eq!(ohlc(&candles[1]), (10.5, 11.0, 7.0, 9.25));
+        assert!(candles[2].close.is_nan());
+        assert_eq!(ohlc(&candles[3]), (9.875, 10.0, 8.0, 9.0));
+        assert_eq!((candles[3].volume, candles[3].tick_count), (2.0, 3));
+
+        assert_eq!(HeikinAshi::apply(CandleStyle::Standard, &bars)[1], bars[1]);
+    }
+}
@@ -0,0 +1,197 @@
+//! Rolling indicators decorating a bar series.
+
+use paracas_types::Indicator;
+use std::collections::VecDeque;
+
+use crate::Ohlcv;
+
+/// Values of one indicator for each bar of a series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorColumn {
+    /// Column name, e.g. `sma_20`.
+    pub name: String,
+    /// Value for each bar; `None` until the indicator has seen enough bars,
+    /// and for bars with missing prices.
+    pub values: Vec<Option<f64>>,
+}
+
+/// Streaming calculator of a set of rolling indicators.
+///
+/// Bars with missing (NaN) prices, such as null gap fills, get no values
+/// and do not affect later ones.
+#[derive(Debug, Clone)]
+pub struct IndicatorSet {
+    states: Vec<(Indicator, State)>,
+}
+
+impl IndicatorSet {
+    /// Creates a calculator for the given indicators, in order.
+    #[must_use]
+    pub fn new(indicators: &[Indicator]) -> Self {
+        Self {
+            states: indicators
+                .iter()
+                .map(|indicator| (*indicator, State::default()))
+                .collect(),
+        }
+    }
+
+    /// Returns the indicators being calculated.
+    pub fn indicators(&self) -> impl Iterator<Item = Indicator> + '_ {
+        self.states.iter().map(|(indicator, _)| *indicator)
+    }
+
+    /// Processes the next bar, returning the value of each indicator.
+    pub fn process(&mut self, bar: &Ohlcv) -> Vec<Option<f64>> {
+        let missing = [bar.high, bar.low, bar.close].iter().any(|v| v.is_nan());
+        self.states
+            .iter_mut()
+            .map(|(indicator, state)| {
+                if missing {
+                    None
+                } else {
+                    state.update(*indicator, bar)
+                }
+            })
+            .collect()
+    }
+
+    /// Calculates indicators over a complete series of bars.
+    #[must_use]
+    pub fn compute(indicators: &[Indicator], bars: &[Ohlcv]) -> Vec<IndicatorColumn> {
+        let mut set = Self::new(indicators);
+        let mut columns: Vec<_> = indicators
+            .iter()
+            .map(|indicator| IndicatorColumn {
+                name: indicator.to_string(),
+                values: Vec::with_capacity(bars.len()),
+            })
+            .collect();
+        for bar in bars {
+            for (column, value) in columns.iter_mut().zip(set.process(bar)) {
+                column.values.push(value);
+            }
+        }
+        columns
+    }
+}
+
+/// Running state of one indicator.
+#[derive(Debug, Clone, Default)]
+struct State {
+    /// Recent inputs: closes for SMA, log returns for volatility, true
+    /// ranges while an EMA or ATR warms up.
+    window: VecDeque<f64>,
+    /// Sum of the window.
+    sum: f64,
+    /// Current EMA or ATR once warmed up.
+    average: Option<f64>,
+    /// Close of the previous bar.
+    previous_close: Option<f64>,
+}
+
+impl State {
+    /// Feeds a bar, returning the indicator value if warmed up.
+    fn update(&mut self, indicator: Indicator, bar: &Ohlcv) -> Option<f64> {
+        let period = indicator.period();
+        let previous_close = self.previous_close.replace(bar.close);
+        match indicator {
+            Indicator::Sma(_) => {
+                self.push(bar.close, period);
+                (self.window.len() == period).then(|| self.sum / period as f64)
+            }
+            Indicator::Ema(_) => {
+                let alpha = 2.0 / (period as f64 + 1.0);
+                self.smooth(bar.close, period, |ema, close| ema + alpha * (close - ema))
+            }
+            Indicator::Atr(_) => {
+                let true_range = previous_close.map_or(bar.high - bar.low, |close| {
+                    (bar.high - bar.low)
+                        .max((bar.high - close).abs())
+                        .max((bar.low - close).abs())
+                });
+                self.smooth(true_range, period, |atr, tr| {
+                    (atr * (period as f64 - 1.0) + tr) / period as f64
+                })
+            }
+            Indicator::Volatility(_) => {
+                self.push((bar.close / previous_close?).ln(), period);
+                (self.window.len() == period && period > 1).then(|| {
+                    let mean = self.sum / period as f64;
+                    let squares: f64 = self.window.iter().map(|r| (r - mean).powi(2)).sum();
+                    (squares / (period - 1) as f64).sqrt()
+                })
+            }
+        }
+    }
+
+    /// Adds a value to the rolling window of `period` values.
+    fn push(&mut self, value: f64, period: usize) {
+        if self.window.len() == period {
+            self.sum -= self.window.pop_front().unwrap_or_default();
+        }
+        self.window.push_back(value);
+        self.sum += value;
+    }
+
+    /// Updates a recursive average seeded with the simple average of the
+    /// first `period` values.
+    fn smooth(&mut self, value: f64, period: usize, step: impl Fn(f64, f64) -> f64) -> Option<f64> {
+        self.average = match self.average {
+            Some(average) => Some(step(average, value)),
+            None => {
+                self.push(value, period);
+                (self.window.len() == period).then(|| self.sum / period as f64)
+            }
+        };
+        self.average
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn bar(minute: u32, high: f64, low: f64, close: f64) -> Ohlcv {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Ohlcv::new(timestamp, close, high, low, close, 1.0, 1)
+    }
+
+    #[test]
+    fn test_compute() {
+        let bars = [
+            bar(0, 11.0, 9.0, 10.0),
+            bar(1, 12.0, 10.0, 11.0),
+            bar(2, f64::NAN, f64::NAN, f64::NAN),
+            bar(3, 13.0, 11.0, 12.0),
+            bar(4, 13.0, 9.0, 10.0),
+        ];
+        let indicators = [
+            Indicator::Sma(2),
+            Indicator::Ema(2),
+            Indicator::Atr(2),
+            Indicator::Volatility(2),
+        ];
+        let columns = IndicatorSet::compute(&indicators, &bars);
+
+        assert_eq!(columns[0].name, "sma_2");
+        assert_eq!(
+            columns[0].values,
+            [None, Some(10.5), None, Some(11.5), Some(11.0)]
+        );
+        // Seeded with the SMA, then alpha = 2/3
+        assert_eq!(columns[1].values[3], Some(10.5 + 2.0 / 3.0 * 1.5));
+        // True ranges 2, 2, 2, 4
+        assert_eq!(
+            columns[2].values,
+            [None, Some(2.0), None, Some(2.0), Some(3.0)]
+        );
+        let returns = [(12.0f64 / 11.0).ln(), (10.0f64 / 12.0).ln()];
+        let mean = (returns[0] + returns[1]) / 2.0;
+        let expected = ((returns[0] - mean).powi(2) + (returns[1] - mean).powi(2)).sqrt();
+        assert_eq!(columns[3].values[..3], [None, None, None]);
+        assert!(columns[3].values[3].is_some());
+        assert!((columns[3].values[4].unwrap() - expected).abs() < 1e-12);
+    }
+}
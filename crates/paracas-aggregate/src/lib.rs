@@ -4,6 +4,20 @@
 //!
 //! - [`Ohlcv`] - OHLCV bar data structure
 //! - [`TickAggregator`] - Streaming tick aggregator
+//! - [`VolumeBarAggregator`] - Bars closing at a volume or notional threshold instead of a
+//!   time boundary
+//! - [`PriceBarAggregator`] - Renko bricks and range bars closing on price movement
+//! - [`MultiTimeframeAggregator`] - Bars of several timeframes from one pass over the ticks
+//! - `ParallelAggregator` - Time bars from chunks of ticks aggregated on a thread pool
+//!   (`parallel` feature)
+//! - [`VolumeProfileAggregator`] - Time bars with a volume-by-price histogram of each bar
+//! - [`BidAskAggregator`] - Streaming aggregation into [`OhlcvBidAsk`] bars with both sides
+//! - [`TickFilter`] - Removal of zero-spread, crossed and spiking ticks before aggregation
+//! - [`GapFiller`] - Bars for empty periods, for a regular time index
+//! - [`HeikinAshi`] - Heikin-Ashi candles from a bar series
+//! - [`IndicatorSet`] - SMA, EMA, ATR and rolling volatility columns for a bar series
+//! - [`BarResampler`] - Streaming resampling of bars into a coarser timeframe
+//! - [`ReturnCalculator`] - Log-return or price-difference series from bar closes
 
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/factordynamics/paracas/issues/")]
@@ -12,7 +26,33 @@
 #![forbid(unsafe_code)]
 
 mod aggregator;
+mod bid_ask;
+mod filter;
+mod gap;
+mod heikin_ashi;
+mod indicators;
+mod multi;
 mod ohlcv;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod price_bars;
+mod profile;
+mod resample;
+mod returns;
+mod volume;
 
 pub use aggregator::TickAggregator;
+pub use bid_ask::{BidAskAggregator, OhlcvBidAsk};
+pub use filter::{DEFAULT_SPIKE_WINDOW, FilterReason, TickFilter};
+pub use gap::GapFiller;
+pub use heikin_ashi::HeikinAshi;
+pub use indicators::{IndicatorColumn, IndicatorSet};
+pub use multi::MultiTimeframeAggregator;
 pub use ohlcv::Ohlcv;
+#[cfg(feature = "parallel")]
+pub use parallel::ParallelAggregator;
+pub use price_bars::{PriceBarAggregator, PriceBarKind};
+pub use profile::{PriceLevel, VolumeProfile, VolumeProfileAggregator};
+pub use resample::BarResampler;
+pub use returns::{PriceReturn, ReturnCalculator, ReturnKind};
+pub use volume::{BarMeasure, VolumeBarAggregator};
@@ -0,0 +1,160 @@
+//! Aggregation into several timeframes in one pass.
+
+use paracas_types::{PriceSource, Tick, Timeframe};
+
+use crate::{Ohlcv, TickAggregator};
+
+/// Streaming aggregator building bars of several timeframes at once.
+///
+/// Each tick is read once and fed to one [`TickAggregator`] per timeframe,
+/// so producing m1, m5, h1 and d1 bars costs a single pass over the ticks
+/// instead of one per timeframe. Duplicate timeframes and
+/// [`Timeframe::Tick`] are ignored.
+#[derive(Debug)]
+pub struct MultiTimeframeAggregator {
+    aggregators: Vec<TickAggregator>,
+}
+
+impl MultiTimeframeAggregator {
+    /// Creates an aggregator for the given timeframes, kept in the order
+    /// given.
+    #[must_use]
+    pub fn new(timeframes: impl IntoIterator<Item = Timeframe>) -> Self {
+        let mut aggregators: Vec<TickAggregator> = Vec::new();
+        for timeframe in timeframes {
+            if !timeframe.is_tick() && !aggregators.iter().any(|a| a.timeframe() == timeframe) {
+                aggregators.push(TickAggregator::new(timeframe));
+            }
+        }
+        Self { aggregators }
+    }
+
+    /// Sets which tick price the bars are built from.
+    #[must_use]
+    pub fn with_price_source(self, source: PriceSource) -> Self {
+        self.map(|aggregator| aggregator.with_price_source(source))
+    }
+
+    /// Preserves ask and bid volumes separately on each bar.
+    #[must_use]
+    pub fn with_side_volumes(self, enabled: bool) -> Self {
+        self.map(|aggregator| aggregator.with_side_volumes(enabled))
+    }
+
+    /// Records the timestamps of the first and last tick on each bar.
+    #[must_use]
+    pub fn with_tick_times(self, enabled: bool) -> Self {
+        self.map(|aggregator| aggregator.with_tick_times(enabled))
+    }
+
+    /// Adds the VWAP and the mean and largest bid-ask spread to each bar.
+    #[must_use]
+    pub fn with_price_stats(self, enabled: bool) -> Self {
+        self.map(|aggregator| aggregator.with_price_stats(enabled))
+    }
+
+    /// Adds the tick arrival rate, bid and ask change counts and the
+    /// time-weighted spread to each bar.
+    #[must_use]
+    pub fn with_microstructure(self, enabled: bool) -> Self {
+        self.map(|aggregator| aggregator.with_microstructure(enabled))
+    }
+
+    /// Returns the timeframes being aggregated to.
+    pub fn timeframes(&self) -> impl Iterator<Item = Timeframe> + '_ {
+        self.aggregators.iter().map(TickAggregator::timeframe)
+    }
+
+    /// Processes a tick, returning the bars it completes, tagged with their
+    /// timeframe.
+    pub fn process(&mut self, tick: Tick) -> Vec<(Timeframe, Ohlcv)> {
+        self.aggregators
+            .iter_mut()
+            .filter_map(|aggregator| {
+                let timeframe = aggregator.timeframe();
+                aggregator.process(tick).map(|bar| (timeframe, bar))
+            })
+            .collect()
+    }
+
+    /// Finishes aggregation, returning the partial bar of every timeframe
+    /// that has one.
+    #[must_use]
+    pub fn finish(self) -> Vec<(Timeframe, Ohlcv)> {
+        self.aggregators
+            .into_iter()
+            .filter_map(|aggregator| {
+                let timeframe = aggregator.timeframe();
+                aggregator.finish().map(|bar| (timeframe, bar))
+            })
+            .collect()
+    }
+
+    /// Aggregates a complete series of ticks, returning the bars of each
+    /// timeframe in the order the timeframes were given.
+    #[must_use]
+    pub fn aggregate(
+        mut self,
+        ticks: impl IntoIterator<Item = Tick>,
+    ) -> Vec<(Timeframe, Vec<Ohlcv>)> {
+        let mut series: Vec<_> = self.timeframes().map(|tf| (tf, Vec::new())).collect();
+        for tick in ticks {
+            for (aggregator, (_, bars)) in self.aggregators.iter_mut().zip(&mut series) {
+                bars.extend(aggregator.process(tick));
+            }
+        }
+        for (aggregator, (_, bars)) in self.aggregators.into_iter().zip(&mut series) {
+            bars.extend(aggregator.finish());
+        }
+        series
+    }
+
+    /// Applies `f` to every per-timeframe aggregator.
+    fn map(self, f: impl Fn(TickAggregator) -> TickAggregator) -> Self {
+        Self {
+            aggregators: self.aggregators.into_iter().map(f).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    #[test]
+    fn test_matches_separate_aggregators() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let ticks: Vec<_> = (0..500)
+            .map(|i| {
+                let bid = 1.1 + f64::from(i % 17) * 0.0001;
+                Tick::new(
+                    start + TimeDelta::seconds(i64::from(i) * 37),
+                    bid + 0.0002,
+                    bid,
+                    1.0,
+                    0.5,
+                )
+            })
+            .collect();
+        let timeframes = [
+            Timeframe::Minute1,
+            Timeframe::Minute5,
+            Timeframe::Tick,
+            Timeframe::Hour1,
+            Timeframe::Minute1,
+        ];
+
+        let multi = MultiTimeframeAggregator::new(timeframes).with_side_volumes(true);
+        assert_eq!(
+            multi.timeframes().collect::<Vec<_>>(),
+            [Timeframe::Minute1, Timeframe::Minute5, Timeframe::Hour1]
+        );
+        for (timeframe, bars) in multi.aggregate(ticks.iter().copied()) {
+            let mut single = TickAggregator::new(timeframe).with_side_volumes(true);
+            let mut expected: Vec<_> = ticks.iter().filter_map(|t| single.process(*t)).collect();
+            expected.extend(single.finish());
+            assert_eq!(bars, expected, "{timeframe}");
+        }
+    }
+}
@@ -8,18 +8,67 @@ use serde::{Deserialize, Serialize};
 pub struct Ohlcv {
     /// Bar open time (start of the period).
     pub timestamp: DateTime<Utc>,
-    /// Opening price (first tick's mid price).
+    /// Opening price (first tick's price, mid by default).
     pub open: f64,
     /// Highest price during the period.
     pub high: f64,
     /// Lowest price during the period.
     pub low: f64,
-    /// Closing price (last tick's mid price).
+    /// Closing price (last tick's price, mid by default).
     pub close: f64,
     /// Total volume (sum of ask + bid volumes).
     pub volume: f64,
     /// Number of ticks in the bar.
     pub tick_count: u32,
+    /// Summed ask-side volume, when side volumes are preserved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask_volume: Option<f64>,
+    /// Summed bid-side volume, when side volumes are preserved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid_volume: Option<f64>,
+    /// Volume imbalance `(bid - ask) / (bid + ask)`, when side volumes are
+    /// preserved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imbalance: Option<f64>,
+    /// Timestamp of the first tick in the bar, when tick times are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_tick_ts: Option<DateTime<Utc>>,
+    /// Timestamp of the last tick in the bar, when tick times are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_tick_ts: Option<DateTime<Utc>>,
+    /// Volume-weighted average price, when price statistics are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vwap: Option<f64>,
+    /// Mean bid-ask spread over the bar's ticks, when price statistics are
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_spread: Option<f64>,
+    /// Largest bid-ask spread in the bar, when price statistics are
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_spread: Option<f64>,
+    /// Ticks per second over the bar's period, when microstructure metrics
+    /// are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tick_rate: Option<f64>,
+    /// Number of ticks changing the bid, when microstructure metrics are
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid_changes: Option<u32>,
+    /// Number of ticks changing the ask, when microstructure metrics are
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask_changes: Option<u32>,
+    /// Bid-ask spread weighted by how long each quote stood, when
+    /// microstructure metrics are recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twa_spread: Option<f64>,
+    /// Whether the bar may be incomplete.
+    ///
+    /// Set on the trailing bar emitted when aggregation finishes, since more
+    /// ticks for its period may follow in later data.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_partial: bool,
 }
 
 impl Ohlcv {
@@ -42,9 +91,106 @@ impl Ohlcv {
             close,
             volume,
             tick_count,
+            ask_volume: None,
+            bid_volume: None,
+            imbalance: None,
+            first_tick_ts: None,
+            last_tick_ts: None,
+            vwap: None,
+            avg_spread: None,
+            max_spread: None,
+            tick_rate: None,
+            bid_changes: None,
+            ask_changes: None,
+            twa_spread: None,
+            is_partial: false,
         }
     }
 
+    /// Sets the per-side volumes and derives the imbalance.
+    ///
+    /// The imbalance is zero when both sides are empty.
+    #[must_use]
+    pub fn with_side_volumes(mut self, ask_volume: f64, bid_volume: f64) -> Self {
+        let total = ask_volume + bid_volume;
+        self.ask_volume = Some(ask_volume);
+        self.bid_volume = Some(bid_volume);
+        self.imbalance = Some(if total > 0.0 {
+            (bid_volume - ask_volume) / total
+        } else {
+            0.0
+        });
+        self
+    }
+
+    /// Returns true if the bar carries per-side volumes.
+    #[must_use]
+    pub const fn has_side_volumes(&self) -> bool {
+        self.ask_volume.is_some() && self.bid_volume.is_some()
+    }
+
+    /// Sets the timestamps of the first and last tick in the bar.
+    #[must_use]
+    pub const fn with_tick_times(mut self, first: DateTime<Utc>, last: DateTime<Utc>) -> Self {
+        self.first_tick_ts = Some(first);
+        self.last_tick_ts = Some(last);
+        self
+    }
+
+    /// Returns true if the bar carries first and last tick timestamps.
+    #[must_use]
+    pub const fn has_tick_times(&self) -> bool {
+        self.first_tick_ts.is_some() && self.last_tick_ts.is_some()
+    }
+
+    /// Sets the VWAP and the mean and largest bid-ask spread.
+    #[must_use]
+    pub const fn with_price_stats(mut self, vwap: f64, avg_spread: f64, max_spread: f64) -> Self {
+        self.vwap = Some(vwap);
+        self.avg_spread = Some(avg_spread);
+        self.max_spread = Some(max_spread);
+        self
+    }
+
+    /// Returns true if the bar carries VWAP and spread statistics.
+    #[must_use]
+    pub const fn has_price_stats(&self) -> bool {
+        self.vwap.is_some() && self.avg_spread.is_some() && self.max_spread.is_some()
+    }
+
+    /// Sets the tick arrival rate, the number of bid and ask changes and the
+    /// time-weighted spread.
+    #[must_use]
+    pub const fn with_microstructure(
+        mut self,
+        tick_rate: f64,
+        bid_changes: u32,
+        ask_changes: u32,
+        twa_spread: f64,
+    ) -> Self {
+        self.tick_rate = Some(tick_rate);
+        self.bid_changes = Some(bid_changes);
+        self.ask_changes = Some(ask_changes);
+        self.twa_spread = Some(twa_spread);
+        self
+    }
+
+    /// Returns true if the bar carries microstructure metrics.
+    #[must_use]
+    pub const fn has_microstructure(&self) -> bool {
+        self.tick_rate.is_some()
+            && self.bid_changes.is_some()
+            && self.ask_changes.is_some()
+            && self.twa_spread.is_some()
+    }
+
+    /// Marks the bar as possibly incomplete.
+    #[must_use]
+    pub const fn with_partial(mut self, partial: bool) -> Self {
+        self.is_partial = partial;
+        self
+    }
+
     /// Returns the price range (high - low).
     #[must_use]
     pub fn range(&self) -> f64 {
@@ -119,6 +265,32 @@ mod tests {
         assert!(bar.is_bearish());
     }
 
+    #[test]
+    fn test_side_volumes() {
+        let bar = create_test_bar();
+        assert!(!bar.has_side_volumes());
+
+        let bar = bar.with_side_volumes(300.0, 700.0);
+        assert!(bar.has_side_volumes());
+        assert_eq!(bar.ask_volume, Some(300.0));
+        assert!((bar.imbalance.unwrap() - 0.4).abs() < 1e-10);
+
+        let empty = create_test_bar().with_side_volumes(0.0, 0.0);
+        assert_eq!(empty.imbalance, Some(0.0));
+    }
+
+    #[test]
+    fn test_partial_serialization() {
+        let bar = create_test_bar();
+        let json = serde_json::to_string(&bar).unwrap();
+        assert!(!json.contains("is_partial"));
+
+        let json = serde_json::to_string(&bar.with_partial(true)).unwrap();
+        assert!(json.contains("\"is_partial\":true"));
+        let parsed: Ohlcv = serde_json::from_str(&json).unwrap();
+        assert!(parsed.is_partial);
+    }
+
     #[test]
     fn test_typical_price() {
         let bar = create_test_bar();
@@ -0,0 +1,175 @@
+//! Parallel aggregation over independent chunks of ticks.
+
+use paracas_types::{PriceSource, Tick, Timeframe};
+use rayon::prelude::*;
+
+use crate::aggregator::BarFields;
+use crate::resample::merge;
+use crate::{Ohlcv, TickAggregator};
+
+/// Aggregator building time bars from chunks of ticks on the rayon thread
+/// pool.
+///
+/// Each chunk, such as the ticks of one downloaded hour, is aggregated on its
+/// own, then the bars straddling two chunks are merged. The result is the
+/// same as feeding every tick through one [`TickAggregator`], up to the
+/// rounding of merged price statistics, so CPU-bound aggregation of long
+/// ranges scales with the number of cores.
+///
+/// Chunks must be in time order, with ticks in time order within each chunk.
+/// Microstructure metrics are not available, since they depend on the tick
+/// before each chunk; use a [`TickAggregator`] for those.
+#[derive(Debug, Clone, Copy)]
+pub struct ParallelAggregator {
+    timeframe: Timeframe,
+    price_source: PriceSource,
+    fields: BarFields,
+}
+
+impl ParallelAggregator {
+    /// Creates an aggregator for the given timeframe.
+    #[must_use]
+    pub const fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            price_source: PriceSource::Mid,
+            fields: BarFields::new(),
+        }
+    }
+
+    /// Sets which tick price the bars are built from.
+    #[must_use]
+    pub const fn with_price_source(mut self, source: PriceSource) -> Self {
+        self.price_source = source;
+        self
+    }
+
+    /// Preserves ask and bid volumes separately on each bar.
+    #[must_use]
+    pub const fn with_side_volumes(mut self, enabled: bool) -> Self {
+        self.fields.side_volumes = enabled;
+        self
+    }
+
+    /// Records the timestamps of the first and last tick on each bar.
+    #[must_use]
+    pub const fn with_tick_times(mut self, enabled: bool) -> Self {
+        self.fields.tick_times = enabled;
+        self
+    }
+
+    /// Adds the volume-weighted average price and the mean and largest
+    /// bid-ask spread to each bar.
+    #[must_use]
+    pub const fn with_price_stats(mut self, enabled: bool) -> Self {
+        self.fields.price_stats = enabled;
+        self
+    }
+
+    /// Returns the timeframe being aggregated to.
+    #[must_use]
+    pub const fn timeframe(&self) -> Timeframe {
+        self.timeframe
+    }
+
+    /// Aggregates chunks of ticks in parallel, returning the bars in order.
+    ///
+    /// As with [`TickAggregator::finish`], the last bar is flagged as
+    /// partial.
+    #[must_use]
+    pub fn aggregate<T: AsRef<[Tick]> + Sync>(&self, chunks: &[T]) -> Vec<Ohlcv> {
+        let series: Vec<Vec<Ohlcv>> = chunks
+            .par_iter()
+            .map(|chunk| self.aggregate_chunk(chunk.as_ref()))
+            .collect();
+
+        let mut bars: Vec<Ohlcv> = Vec::with_capacity(series.iter().map(Vec::len).sum());
+        for chunk_bars in series {
+            let mut chunk_bars = chunk_bars.into_iter();
+            let Some(first) = chunk_bars.next() else {
+                continue;
+            };
+            // The previous chunk's trailing bar continues into this chunk,
+            // or was complete after all
+            match bars.last_mut() {
+                Some(last) if last.timestamp == first.timestamp => {
+                    *last = merge(*last, &first).with_partial(first.is_partial);
+                }
+                Some(last) => {
+                    last.is_partial = false;
+                    bars.push(first);
+                }
+                None => bars.push(first),
+            }
+            bars.extend(chunk_bars);
+        }
+        bars
+    }
+
+    /// Aggregates a complete series of ticks, split into one chunk per
+    /// thread of the pool.
+    #[must_use]
+    pub fn aggregate_ticks(&self, ticks: &[Tick]) -> Vec<Ohlcv> {
+        let chunk_size = ticks.len().div_ceil(rayon::current_num_threads()).max(1);
+        let chunks: Vec<&[Tick]> = ticks.chunks(chunk_size).collect();
+        self.aggregate(&chunks)
+    }
+
+    /// Aggregates one chunk, ending with its trailing bar flagged partial.
+    fn aggregate_chunk(&self, ticks: &[Tick]) -> Vec<Ohlcv> {
+        let mut aggregator = TickAggregator::new(self.timeframe)
+            .with_price_source(self.price_source)
+            .with_side_volumes(self.fields.side_volumes)
+            .with_tick_times(self.fields.tick_times)
+            .with_price_stats(self.fields.price_stats);
+        let mut bars: Vec<_> = ticks
+            .iter()
+            .filter_map(|t| aggregator.process(*t))
+            .collect();
+        bars.extend(aggregator.finish());
+        bars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    fn ticks() -> Vec<Tick> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..2000)
+            .map(|i| {
+                let bid = 1.1 + f64::from(i % 23) * 0.0001;
+                Tick::new(
+                    start + TimeDelta::seconds(i64::from(i) * 13),
+                    bid + 0.0002,
+                    bid,
+                    1.0,
+                    0.5,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_sequential_aggregation() {
+        let ticks = ticks();
+        let mut single = TickAggregator::new(Timeframe::Minute5).with_side_volumes(true);
+        let mut expected: Vec<_> = ticks.iter().filter_map(|t| single.process(*t)).collect();
+        expected.extend(single.finish());
+
+        let parallel = ParallelAggregator::new(Timeframe::Minute5).with_side_volumes(true);
+        // Chunk boundaries inside bars, on bar boundaries, and empty chunks
+        let chunks = [
+            &ticks[..7],
+            &ticks[7..7],
+            &ticks[7..500],
+            &ticks[500..1385],
+            &ticks[1385..],
+        ];
+        assert_eq!(parallel.aggregate(&chunks), expected);
+        assert_eq!(parallel.aggregate_ticks(&ticks), expected);
+        assert!(parallel.aggregate_ticks(&[]).is_empty());
+    }
+}
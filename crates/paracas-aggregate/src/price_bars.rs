@@ -0,0 +1,292 @@
+//! Price-movement bar construction: Renko bricks and range bars.
+
+use chrono::{DateTime, Utc};
+use paracas_types::{PriceSource, Tick};
+
+use crate::Ohlcv;
+use crate::aggregator::{BarFields, OhlcvBuilder};
+
+/// Kind of bar built by a [`PriceBarAggregator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceBarKind {
+    /// Bricks of exactly the box size, each opening where the previous one
+    /// closed. Continuing a trend takes one box of movement; reversing it
+    /// takes two.
+    Renko,
+    /// Bars closing once their high-low range reaches the box size.
+    Range,
+}
+
+/// Direction of the last Renko brick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trend {
+    Up,
+    Down,
+}
+
+/// Streaming aggregator for bars that close on price movement instead of
+/// time.
+///
+/// Neither kind can be derived from time bars after the fact, since the
+/// order of ticks within a bar decides where bars close. The box size is in
+/// price units; multiply a pip count by
+/// [`Instrument::pip_size`](paracas_types::Instrument::pip_size) to size it
+/// in pips.
+///
+/// Range bars take ticks until their high-low range is at least the box
+/// size; the tick crossing it belongs to the bar it closes. Renko bricks are
+/// anchored at the first tick's price and have the box size as body and no
+/// wicks. A tick moving several boxes at once emits several bricks; its
+/// volume and ticks go to the first, the others are empty. Bars of both
+/// kinds are stamped with the time of their first tick.
+#[derive(Debug)]
+pub struct PriceBarAggregator {
+    kind: PriceBarKind,
+    size: f64,
+    price_source: PriceSource,
+    fields: BarFields,
+    current_bar: Option<OhlcvBuilder>,
+    /// Close of the last Renko brick, or the first price before any brick.
+    anchor: Option<f64>,
+    trend: Option<Trend>,
+}
+
+impl PriceBarAggregator {
+    /// Creates an aggregator for `kind` bars with a box of `size` in price
+    /// units.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a finite positive number.
+    #[must_use]
+    pub const fn new(kind: PriceBarKind, size: f64) -> Self {
+        assert!(
+            size.is_finite() && size > 0.0,
+            "box size must be a finite positive number"
+        );
+        Self {
+            kind,
+            size,
+            price_source: PriceSource::Mid,
+            fields: BarFields::new(),
+            current_bar: None,
+            anchor: None,
+            trend: None,
+        }
+    }
+
+    /// Creates an aggregator for Renko bricks of `size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a finite positive number.
+    #[must_use]
+    pub const fn renko(size: f64) -> Self {
+        Self::new(PriceBarKind::Renko, size)
+    }
+
+    /// Creates an aggregator for range bars of `size`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not a finite positive number.
+    #[must_use]
+    pub const fn range(size: f64) -> Self {
+        Self::new(PriceBarKind::Range, size)
+    }
+
+    /// Sets which tick price the bars are built from.
+    #[must_use]
+    pub const fn with_price_source(mut self, source: PriceSource) -> Self {
+        self.price_source = source;
+        self
+    }
+
+    /// Preserves ask and bid volumes separately on each bar.
+    #[must_use]
+    pub const fn with_side_volumes(mut self, enabled: bool) -> Self {
+        self.fields.side_volumes = enabled;
+        self
+    }
+
+    /// Records the timestamps of the first and last tick on each bar.
+    #[must_use]
+    pub const fn with_tick_times(mut self, enabled: bool) -> Self {
+        self.fields.tick_times = enabled;
+        self
+    }
+
+    /// Adds the volume-weighted average price and the mean and largest
+    /// bid-ask spread to each bar.
+    #[must_use]
+    pub const fn with_price_stats(mut self, enabled: bool) -> Self {
+        self.fields.price_stats = enabled;
+        self
+    }
+
+    /// Returns the kind of bar built.
+    #[must_use]
+    pub const fn kind(&self) -> PriceBarKind {
+        self.kind
+    }
+
+    /// Returns the box size in price units.
+    #[must_use]
+    pub const fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// Processes a tick, returning the bars it completes.
+    ///
+    /// Range bars complete at most one bar per tick; a large move can
+    /// complete several Renko bricks.
+    pub fn process(&mut self, tick: Tick) -> Vec<Ohlcv> {
+        let price = tick.price(self.price_source);
+        match &mut self.current_bar {
+            Some(builder) => builder.update(&tick, price),
+            None => self.current_bar = Some(OhlcvBuilder::new(tick.timestamp, &tick, price)),
+        }
+        match self.kind {
+            PriceBarKind::Range => self.close_range_bar().into_iter().collect(),
+            PriceBarKind::Renko => self.close_bricks(tick.timestamp, price),
+        }
+    }
+
+    /// Finishes aggregation, returning the bar or brick still forming,
+    /// flagged as partial, with the prices of its ticks.
+    #[must_use]
+    pub fn finish(mut self) -> Option<Ohlcv> {
+        self.current_bar.take().map(|b| b.build(self.fields, true))
+    }
+
+    /// Closes the current range bar if its range reached the box size.
+    fn close_range_bar(&mut self) -> Option<Ohlcv> {
+        if self.current_bar.as_ref()?.range() < self.size {
+            return None;
+        }
+        self.current_bar.take().map(|b| b.build(self.fields, false))
+    }
+
+    /// Emits every Renko brick that `price` completes.
+    fn close_bricks(&mut self, timestamp: DateTime<Utc>, price: f64) -> Vec<Ohlcv> {
+        let size = self.size;
+        let mut anchor = *self.anchor.get_or_insert(price);
+        let mut bricks = Vec::new();
+        loop {
+            // After a brick, the opposite brick opens one box beyond its open
+            let (up_open, down_open) = match self.trend {
+                Some(Trend::Up) => (anchor, anchor - size),
+                Some(Trend::Down) => (anchor + size, anchor),
+                None => (anchor, anchor),
+            };
+            let (open, trend) = if price >= up_open + size {
+                (up_open, Trend::Up)
+            } else if price <= down_open - size {
+                (down_open, Trend::Down)
+            } else {
+                break;
+            };
+            let close = match trend {
+                Trend::Up => open + size,
+                Trend::Down => open - size,
+            };
+            bricks.push(self.brick(timestamp, open, close));
+            anchor = close;
+            self.trend = Some(trend);
+        }
+        self.anchor = Some(anchor);
+        bricks
+    }
+
+    /// Builds a brick from `open` to `close` out of the ticks collected so
+    /// far, or an empty brick if they went into an earlier one.
+    fn brick(&mut self, timestamp: DateTime<Utc>, open: f64, close: f64) -> Ohlcv {
+        let mut brick = match self.current_bar.take() {
+            Some(builder) => builder.build(self.fields, false),
+            None => {
+                let mut brick = Ohlcv::new(timestamp, open, open, open, open, 0.0, 0);
+                if self.fields.side_volumes {
+                    brick = brick.with_side_volumes(0.0, 0.0);
+                }
+                if self.fields.tick_times {
+                    brick = brick.with_tick_times(timestamp, timestamp);
+                }
+                brick
+            }
+        };
+        brick.open = open;
+        brick.close = close;
+        brick.high = open.max(close);
+        brick.low = open.min(close);
+        brick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn tick(second: u32, bid: f64) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, second).unwrap();
+        Tick::new(timestamp, bid + 0.0002, bid, 1.0, 1.0)
+    }
+
+    fn run(mut agg: PriceBarAggregator, bids: &[f64]) -> Vec<Ohlcv> {
+        let mut bars = Vec::new();
+        for (second, bid) in (0..).zip(bids) {
+            bars.extend(agg.process(tick(second, *bid)));
+        }
+        bars
+    }
+
+    #[test]
+    fn test_range_bars() {
+        let agg = PriceBarAggregator::range(0.5).with_price_source(PriceSource::Bid);
+        let bars = run(agg, &[10.0, 10.2, 9.8, 9.6, 9.7, 10.3]);
+
+        assert_eq!(bars.len(), 2);
+        assert_eq!(
+            (bars[0].open, bars[0].high, bars[0].low, bars[0].close),
+            (10.0, 10.2, 9.6, 9.6)
+        );
+        assert_eq!(bars[0].tick_count, 4);
+        assert_eq!(bars[1].timestamp, tick(4, 0.0).timestamp);
+        assert_eq!((bars[1].low, bars[1].high), (9.7, 10.3));
+    }
+
+    #[test]
+    fn test_renko_bricks() {
+        let agg = PriceBarAggregator::renko(1.0).with_price_source(PriceSource::Bid);
+        // Up two boxes at once, a pullback of one box that is no reversal,
+        // then a reversal two boxes below the last close
+        let bricks = run(agg, &[10.0, 10.5, 12.2, 11.1, 10.0, 9.0]);
+
+        let bodies: Vec<_> = bricks.iter().map(|b| (b.open, b.close)).collect();
+        assert_eq!(
+            bodies,
+            [(10.0, 11.0), (11.0, 12.0), (11.0, 10.0), (10.0, 9.0)]
+        );
+        assert_eq!(bricks[0].tick_count, 3);
+        assert_eq!(bricks[1].tick_count, 0);
+        assert_eq!(bricks[1].volume, 0.0);
+        assert_eq!(bricks[2].tick_count, 2);
+        assert!(bricks.iter().all(|b| b.high == b.open.max(b.close)));
+    }
+
+    #[test]
+    fn test_finish_returns_forming_bar() {
+        let mut agg = PriceBarAggregator::renko(1.0).with_price_source(PriceSource::Bid);
+        assert!(agg.process(tick(0, 10.0)).is_empty());
+        assert!(agg.process(tick(1, 10.4)).is_empty());
+        let bar = agg.finish().unwrap();
+        assert!(bar.is_partial);
+        assert_eq!((bar.open, bar.close, bar.tick_count), (10.0, 10.4, 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "box size")]
+    fn test_zero_box_size_panics() {
+        let _ = PriceBarAggregator::renko(0.0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Per-bar volume profiles.
+
+use paracas_types::Tick;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{Ohlcv, TickAggregator};
+
+/// Volume and ticks traded within one price bin of a bar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    /// Lower edge of the bin.
+    pub price: f64,
+    /// Summed volume of the ticks in the bin.
+    pub volume: f64,
+    /// Number of ticks in the bin.
+    pub tick_count: u32,
+}
+
+/// Histogram of a bar's ticks by price, lowest bin first.
+///
+/// Only bins with ticks are listed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VolumeProfile {
+    /// The price bins with ticks.
+    pub levels: Vec<PriceLevel>,
+}
+
+impl VolumeProfile {
+    /// Returns the bin with the most volume (the point of control), the
+    /// lowest one on ties.
+    #[must_use]
+    pub fn point_of_control(&self) -> Option<&PriceLevel> {
+        self.levels.iter().reduce(|best, level| {
+            if level.volume > best.volume {
+                level
+            } else {
+                best
+            }
+        })
+    }
+}
+
+/// Time-bar aggregator that also builds the volume profile of each bar.
+///
+/// Wraps a configured [`TickAggregator`], binning the price it builds bars
+/// from into bins of `bin_width`. Ticks with a NaN price count towards the
+/// bar but not its profile.
+#[derive(Debug)]
+pub struct VolumeProfileAggregator {
+    aggregator: TickAggregator,
+    bin_width: f64,
+    /// Bins of the current bar, by index of their lower edge.
+    bins: BTreeMap<i64, PriceLevel>,
+}
+
+impl VolumeProfileAggregator {
+    /// Creates an aggregator building profiles with bins of `bin_width`
+    /// alongside the bars of `aggregator`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bin_width` is not a finite positive number.
+    #[must_use]
+    pub const fn new(aggregator: TickAggregator, bin_width: f64) -> Self {
+        assert!(
+            bin_width.is_finite() && bin_width > 0.0,
+            "bin width must be a finite positive number"
+        );
+        Self {
+            aggregator,
+            bin_width,
+            bins: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the width of the price bins.
+    #[must_use]
+    pub const fn bin_width(&self) -> f64 {
+        self.bin_width
+    }
+
+    /// Processes a tick, returning the bar it completes with its profile.
+    pub fn process(&mut self, tick: Tick) -> Option<(Ohlcv, VolumeProfile)> {
+        let price = tick.price(self.aggregator.price_source());
+        let completed = self
+            .aggregator
+            .process(tick)
+            .map(|bar| (bar, self.take_profile()));
+        if !price.is_nan() {
+            let index = (price / self.bin_width).floor() as i64;
+            let width = self.bin_width;
+            let level = self.bins.entry(index).or_insert_with(|| PriceLevel {
+                price: index as f64 * width,
+                volume: 0.0,
+                tick_count: 0,
+            });
+            level.volume += f64::from(tick.total_volume());
+            level.tick_count += 1;
+        }
+        completed
+    }
+
+    /// Finishes aggregation, returning the partial last bar with its
+    /// profile.
+    #[must_use]
+    pub fn finish(mut self) -> Option<(Ohlcv, VolumeProfile)> {
+        let profile = self.take_profile();
+        self.aggregator.finish().map(|bar| (bar, profile))
+    }
+
+    /// Takes the profile of the current bar, starting an empty one.
+    fn take_profile(&mut self) -> VolumeProfile {
+        VolumeProfile {
+            levels: std::mem::take(&mut self.bins).into_values().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use paracas_types::Timeframe;
+
+    fn tick(minute: u32, bid: f64, volume: f32) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Tick::new(timestamp, bid, bid, volume, 0.0)
+    }
+
+    #[test]
+    fn test_profiles_per_bar() {
+        let mut aggregator =
+            VolumeProfileAggregator::new(TickAggregator::new(Timeframe::Minute5), 0.001);
+        assert!(aggregator.process(tick(0, 1.1004, 1.0)).is_none());
+        aggregator.process(tick(1, 1.1016, 2.0));
+        aggregator.process(tick(2, 1.1012, 4.0));
+
+        let (bar, profile) = aggregator.process(tick(5, 1.2, 1.0)).unwrap();
+        assert_eq!(bar.tick_count, 3);
+        assert_eq!(profile.levels.len(), 2);
+        assert!((profile.levels[0].price - 1.1).abs() < 1e-9);
+        assert_eq!(profile.levels[0].tick_count, 1);
+        let control = profile.point_of_control().unwrap();
+        assert!((control.price - 1.101).abs() < 1e-9);
+        assert_eq!((control.volume, control.tick_count), (6.0, 2));
+
+        let (last, profile) = aggregator.finish().unwrap();
+        assert!(last.is_partial);
+        assert_eq!(profile.levels.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "bin width")]
+    fn test_zero_bin_width_panics() {
+        let _ = VolumeProfileAggregator::new(TickAggregator::new(Timeframe::Minute5), 0.0);
+    }
+}
@@ -0,0 +1,202 @@
+//! Streaming bar-to-bar resampling.
+
+use chrono::{DateTime, Utc};
+use paracas_types::Timeframe;
+
+use crate::Ohlcv;
+use crate::aggregator::bar_start;
+
+/// Streaming resampler merging bars into a coarser timeframe.
+///
+/// Feeding the bars of one timeframe in order yields the same bars as
+/// aggregating the underlying ticks directly into the target timeframe, which
+/// must be at least as coarse as the source one (e.g. `m1` into `h1`).
+/// Per-side volumes, tick times and price statistics are carried over when
+/// every merged bar has them. Microstructure metrics, which depend on the
+/// timing of individual ticks, are dropped.
+#[derive(Debug)]
+pub struct BarResampler {
+    timeframe: Timeframe,
+    current: Option<Ohlcv>,
+}
+
+impl BarResampler {
+    /// Creates a resampler producing bars of `timeframe`.
+    #[must_use]
+    pub const fn new(timeframe: Timeframe) -> Self {
+        Self {
+            timeframe,
+            current: None,
+        }
+    }
+
+    /// Returns the timeframe being resampled to.
+    #[must_use]
+    pub const fn timeframe(&self) -> Timeframe {
+        self.timeframe
+    }
+
+    /// Processes a bar, potentially emitting a completed coarser bar.
+    ///
+    /// Returns `Some(bar)` when a bar is completed by this one, `None`
+    /// otherwise.
+    pub fn process(&mut self, bar: Ohlcv) -> Option<Ohlcv> {
+        let start = bar_start(self.timeframe, bar.timestamp);
+        match self.current.take() {
+            Some(current) if current.timestamp == start => {
+                self.current = Some(merge(current, &bar));
+                None
+            }
+            completed => {
+                self.current = Some(Ohlcv {
+                    timestamp: start,
+                    ..bar
+                });
+                completed
+            }
+        }
+    }
+
+    /// Finishes resampling, returning any remaining bar flagged as partial.
+    #[must_use]
+    pub fn finish(mut self) -> Option<Ohlcv> {
+        self.current.take().map(|bar| bar.with_partial(true))
+    }
+
+    /// Resamples a complete series of bars.
+    #[must_use]
+    pub fn resample(timeframe: Timeframe, bars: &[Ohlcv]) -> Vec<Ohlcv> {
+        let mut resampler = Self::new(timeframe);
+        let mut resampled: Vec<_> = bars
+            .iter()
+            .filter_map(|bar| resampler.process(*bar))
+            .collect();
+        resampled.extend(resampler.finish());
+        resampled
+    }
+}
+
+/// Merges `next` into `bar`, which covers the earlier part of its period.
+pub(crate) fn merge(bar: Ohlcv, next: &Ohlcv) -> Ohlcv {
+    let mut merged = Ohlcv::new(
+        bar.timestamp,
+        bar.open,
+        bar.high.max(next.high),
+        bar.low.min(next.low),
+        next.close,
+        bar.volume + next.volume,
+        bar.tick_count + next.tick_count,
+    )
+    .with_partial(bar.is_partial || next.is_partial);
+    if let (Some(ask), Some(bid), Some(next_ask), Some(next_bid)) = (
+        bar.ask_volume,
+        bar.bid_volume,
+        next.ask_volume,
+        next.bid_volume,
+    ) {
+        merged = merged.with_side_volumes(ask + next_ask, bid + next_bid);
+    }
+    if let (Some(first), Some(last)) = (bar.first_tick_ts, last_tick(next)) {
+        merged = merged.with_tick_times(first, last);
+    }
+    if let (
+        Some(vwap),
+        Some(avg_spread),
+        Some(max_spread),
+        Some(next_vwap),
+        Some(next_avg),
+        Some(next_max),
+    ) = (
+        bar.vwap,
+        bar.avg_spread,
+        bar.max_spread,
+        next.vwap,
+        next.avg_spread,
+        next.max_spread,
+    ) {
+        // Bars without volume weigh their VWAP (mean price) by tick count
+        let (weight, next_weight) = if merged.volume > 0.0 {
+            (bar.volume, next.volume)
+        } else {
+            (f64::from(bar.tick_count), f64::from(next.tick_count))
+        };
+        let ticks = f64::from(bar.tick_count);
+        let next_ticks = f64::from(next.tick_count);
+        merged = merged.with_price_stats(
+            (vwap * weight + next_vwap * next_weight) / (weight + next_weight),
+            (avg_spread * ticks + next_avg * next_ticks) / (ticks + next_ticks),
+            max_spread.max(next_max),
+        );
+    }
+    merged
+}
+
+/// Returns the last tick time of a bar that records tick times.
+const fn last_tick(bar: &Ohlcv) -> Option<DateTime<Utc>> {
+    match (bar.first_tick_ts, bar.last_tick_ts) {
+        (Some(_), Some(last)) => Some(last),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Timelike};
+
+    fn bar(minute: u32, open: f64, high: f64, low: f64, close: f64) -> Ohlcv {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, minute, 0).unwrap();
+        Ohlcv::new(timestamp, open, high, low, close, 2.0, 3)
+    }
+
+    #[test]
+    fn test_resample_merges_period() {
+        let bars = [
+            bar(0, 1.0, 1.5, 0.9, 1.2),
+            bar(7, 1.2, 1.3, 0.8, 1.1),
+            bar(15, 1.1, 1.4, 1.0, 1.3),
+        ];
+        let resampled = BarResampler::resample(Timeframe::Minute15, &bars);
+
+        assert_eq!(resampled.len(), 2);
+        let first = resampled[0];
+        assert_eq!(
+            (first.open, first.high, first.low, first.close),
+            (1.0, 1.5, 0.8, 1.1)
+        );
+        assert_eq!(first.volume, 4.0);
+        assert_eq!(first.tick_count, 6);
+        assert!(!first.is_partial);
+        assert_eq!(resampled[1].timestamp.minute(), 15);
+        assert!(resampled[1].is_partial);
+    }
+
+    #[test]
+    fn test_resample_keeps_side_volumes_only_if_complete() {
+        let with_sides = bar(0, 1.0, 1.0, 1.0, 1.0).with_side_volumes(1.0, 3.0);
+        let merged = merge(
+            with_sides,
+            &bar(1, 1.0, 1.0, 1.0, 1.0).with_side_volumes(1.0, 1.0),
+        );
+        assert_eq!(merged.ask_volume, Some(2.0));
+        assert_eq!(merged.imbalance, Some(2.0 / 6.0));
+
+        let merged = merge(with_sides, &bar(1, 1.0, 1.0, 1.0, 1.0));
+        assert!(!merged.has_side_volumes());
+    }
+
+    #[test]
+    fn test_resample_weighs_price_stats() {
+        let first = bar(0, 1.0, 1.0, 1.0, 1.0).with_price_stats(1.0, 0.1, 0.2);
+        let mut second = bar(1, 2.0, 2.0, 2.0, 2.0).with_price_stats(2.0, 0.3, 0.4);
+        second.volume = 6.0;
+        second.tick_count = 1;
+        let merged = merge(first, &second);
+        // VWAP by volume (2 and 6), mean spread by ticks (3 and 1)
+        assert_eq!(merged.vwap, Some(1.75));
+        assert!((merged.avg_spread.unwrap() - 0.15).abs() < 1e-12);
+        assert_eq!(merged.max_spread, Some(0.4));
+
+        assert!(!merge(first, &bar(1, 1.0, 1.0, 1.0, 1.0)).has_price_stats());
+    }
+}
@@ -0,0 +1,139 @@
+//! Return series computed from bar closes.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Ohlcv;
+
+/// How the change between consecutive closes is expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReturnKind {
+    /// Natural log return, `ln(close / previous_close)`.
+    #[default]
+    Log,
+    /// Price difference, `close - previous_close`.
+    Difference,
+}
+
+impl ReturnKind {
+    /// Computes the change from `previous` to `close`.
+    #[must_use]
+    pub fn compute(self, previous: f64, close: f64) -> f64 {
+        match self {
+            Self::Log => (close / previous).ln(),
+            Self::Difference => close - previous,
+        }
+    }
+}
+
+/// Change in close price over one bar.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceReturn {
+    /// Bar open time (start of the period).
+    pub timestamp: DateTime<Utc>,
+    /// Change from the previous bar's close to this bar's close.
+    #[serde(rename = "return")]
+    pub value: f64,
+    /// Number of ticks in the bar.
+    pub tick_count: u32,
+}
+
+/// Streaming converter from bars to a return series.
+///
+/// Each bar is compared with the close of the bar before it, so the first
+/// bar only seeds the series and produces no return.
+#[derive(Debug, Clone, Copy)]
+pub struct ReturnCalculator {
+    kind: ReturnKind,
+    previous_close: Option<f64>,
+}
+
+impl ReturnCalculator {
+    /// Creates a calculator producing returns of the given kind.
+    #[must_use]
+    pub const fn new(kind: ReturnKind) -> Self {
+        Self {
+            kind,
+            previous_close: None,
+        }
+    }
+
+    /// Returns the kind of return being computed.
+    #[must_use]
+    pub const fn kind(&self) -> ReturnKind {
+        self.kind
+    }
+
+    /// Processes a bar, returning its change from the previous close.
+    pub fn process(&mut self, bar: &Ohlcv) -> Option<PriceReturn> {
+        let previous = self.previous_close.replace(bar.close)?;
+        Some(PriceReturn {
+            timestamp: bar.timestamp,
+            value: self.kind.compute(previous, bar.close),
+            tick_count: bar.tick_count,
+        })
+    }
+
+    /// Converts a complete series of bars into returns.
+    #[must_use]
+    pub fn returns(kind: ReturnKind, bars: &[Ohlcv]) -> Vec<PriceReturn> {
+        let mut calculator = Self::new(kind);
+        bars.iter()
+            .filter_map(|bar| calculator.process(bar))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone};
+
+    fn bars(closes: &[f64]) -> Vec<Ohlcv> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        closes
+            .iter()
+            .zip(0..)
+            .map(|(&close, i)| {
+                Ohlcv::new(
+                    start + TimeDelta::hours(i),
+                    close,
+                    close,
+                    close,
+                    close,
+                    1.0,
+                    3,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_log_returns() {
+        let bars = bars(&[1.0, 2.0, 1.0]);
+        let returns = ReturnCalculator::returns(ReturnKind::Log, &bars);
+
+        assert_eq!(returns.len(), 2);
+        assert_eq!(returns[0].timestamp, bars[1].timestamp);
+        assert!((returns[0].value - 2f64.ln()).abs() < 1e-12);
+        assert!((returns[1].value + 2f64.ln()).abs() < 1e-12);
+        assert_eq!(returns[1].tick_count, 3);
+    }
+
+    #[test]
+    fn test_difference_returns() {
+        let returns = ReturnCalculator::returns(ReturnKind::Difference, &bars(&[1.5, 1.25]));
+        assert_eq!(returns.len(), 1);
+        assert!((returns[0].value + 0.25).abs() < 1e-12);
+
+        assert!(ReturnCalculator::returns(ReturnKind::Difference, &bars(&[1.0])).is_empty());
+    }
+
+    #[test]
+    fn test_serialized_field_names() {
+        let returns = ReturnCalculator::returns(ReturnKind::Difference, &bars(&[1.0, 1.5]));
+        let json = serde_json::to_string(&returns[0]).unwrap();
+        assert!(json.contains("\"return\":0.5"));
+        assert!(json.contains("\"tick_count\":3"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Volume- and notional-based bar construction.
+
+use paracas_types::{PriceSource, Tick};
+
+use crate::Ohlcv;
+use crate::aggregator::{BarFields, OhlcvBuilder};
+
+/// What a [`VolumeBarAggregator`] sums up to decide when a bar closes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BarMeasure {
+    /// Ask plus bid volume.
+    #[default]
+    Volume,
+    /// Price times volume, in quote currency per volume unit. The price is
+    /// the one bars are built from.
+    Notional,
+}
+
+impl BarMeasure {
+    /// Returns how much `tick` adds to the bar, given its bar price.
+    fn of(self, tick: &Tick, price: f64) -> f64 {
+        let volume = f64::from(tick.total_volume());
+        match self {
+            Self::Volume => volume,
+            Self::Notional => volume * price,
+        }
+    }
+}
+
+/// Streaming aggregator closing a bar once its volume reaches a threshold.
+///
+/// A bar takes ticks until their summed ask and bid volume is at least the
+/// threshold, so bars form faster in active markets and slower in quiet
+/// ones. With [`BarMeasure::Notional`], bars close on traded notional
+/// (price × volume) instead, which keeps bar sizes comparable when prices
+/// move a lot. The tick crossing the threshold belongs to the bar it closes;
+/// volume is not split between bars. Each bar is stamped with the time of
+/// its first tick.
+#[derive(Debug)]
+pub struct VolumeBarAggregator {
+    threshold: f64,
+    measure: BarMeasure,
+    accumulated: f64,
+    price_source: PriceSource,
+    fields: BarFields,
+    current_bar: Option<OhlcvBuilder>,
+}
+
+impl VolumeBarAggregator {
+    /// Creates an aggregator closing bars at `threshold` volume.
+    #[must_use]
+    pub const fn new(threshold: f64) -> Self {
+        Self {
+            threshold,
+            measure: BarMeasure::Volume,
+            accumulated: 0.0,
+            price_source: PriceSource::Mid,
+            fields: BarFields::new(),
+            current_bar: None,
+        }
+    }
+
+    /// Creates an aggregator closing bars at `threshold` notional.
+    #[must_use]
+    pub const fn notional(threshold: f64) -> Self {
+        Self::new(threshold).with_measure(BarMeasure::Notional)
+    }
+
+    /// Sets what is summed up towards the threshold.
+    #[must_use]
+    pub const fn with_measure(mut self, measure: BarMeasure) -> Self {
+        self.measure = measure;
+        self
+    }
+
+    /// Sets which tick price the bars are built from.
+    #[must_use]
+    pub const fn with_price_source(mut self, source: PriceSource) -> Self {
+        self.price_source = source;
+        self
+    }
+
+    /// Preserves ask and bid volumes separately on each bar.
+    #[must_use]
+    pub const fn with_side_volumes(mut self, enabled: bool) -> Self {
+        self.fields.side_volumes = enabled;
+        self
+    }
+
+    /// Records the timestamps of the first and last tick on each bar.
+    #[must_use]
+    pub const fn with_tick_times(mut self, enabled: bool) -> Self {
+        self.fields.tick_times = enabled;
+        self
+    }
+
+    /// Adds the volume-weighted average price and the mean and largest
+    /// bid-ask spread to each bar.
+    #[must_use]
+    pub const fn with_price_stats(mut self, enabled: bool) -> Self {
+        self.fields.price_stats = enabled;
+        self
+    }
+
+    /// Returns the volume or notional at which bars close.
+    #[must_use]
+    pub const fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Returns what is summed up towards the threshold.
+    #[must_use]
+    pub const fn measure(&self) -> BarMeasure {
+        self.measure
+    }
+
+    /// Processes a tick, potentially emitting a completed bar.
+    ///
+    /// Returns `Some(bar)` when this tick brings the bar's volume or
+    /// notional to the threshold, `None` otherwise.
+    pub fn process(&mut self, tick: Tick) -> Option<Ohlcv> {
+        let price = tick.price(self.price_source);
+        match &mut self.current_bar {
+            Some(builder) => builder.update(&tick, price),
+            None => self.current_bar = Some(OhlcvBuilder::new(tick.timestamp, &tick, price)),
+        }
+        self.accumulated += self.measure.of(&tick, price);
+        if self.accumulated < self.threshold {
+            return None;
+        }
+        self.accumulated = 0.0;
+        self.current_bar.take().map(|b| b.build(self.fields, false))
+    }
+
+    /// Finishes aggregation, returning the remaining bar below the threshold
+    /// flagged as partial.
+    #[must_use]
+    pub fn finish(mut self) -> Option<Ohlcv> {
+        self.current_bar.take().map(|b| b.build(self.fields, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn tick(second: u32, bid: f64, volume: f32) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, second).unwrap();
+        Tick::new(timestamp, bid + 0.0002, bid, volume, volume)
+    }
+
+    #[test]
+    fn test_bars_close_at_threshold() {
+        let mut agg = VolumeBarAggregator::new(10.0)
+            .with_price_source(PriceSource::Bid)
+            .with_tick_times(true);
+        let ticks = [
+            tick(0, 1.10, 2.0),
+            tick(1, 1.12, 2.0),
+            tick(2, 1.09, 1.5),
+            tick(3, 1.11, 6.0),
+            tick(4, 1.13, 1.0),
+        ];
+        let bars: Vec<_> = ticks.iter().filter_map(|t| agg.process(*t)).collect();
+
+        // 4 + 4 + 3 = 11 crosses 10 on the third tick; 12 alone crosses it again
+        assert_eq!(bars.len(), 2);
+        let first = bars[0];
+        assert_eq!(first.timestamp, ticks[0].timestamp);
+        assert_eq!(first.last_tick_ts, Some(ticks[2].timestamp));
+        assert_eq!(
+            (first.open, first.high, first.low, first.close),
+            (1.10, 1.12, 1.09, 1.09)
+        );
+        assert!((first.volume - 11.0).abs() < 1e-10);
+        assert_eq!(first.tick_count, 3);
+        assert_eq!(bars[1].tick_count, 1);
+
+        let last = agg.finish().unwrap();
+        assert!(last.is_partial);
+        assert!((last.volume - 2.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_notional_bars() {
+        let mut agg = VolumeBarAggregator::notional(20.0).with_price_source(PriceSource::Bid);
+        assert_eq!(agg.measure(), BarMeasure::Notional);
+        // Notional 2 * 2 * 2.0 = 8 per tick at a price of 2, 16 at a price of 4
+        let ticks = [tick(0, 2.0, 2.0), tick(1, 2.0, 2.0), tick(2, 4.0, 2.0)];
+        let bars: Vec<_> = ticks.iter().filter_map(|t| agg.process(*t)).collect();
+        assert_eq!(bars.len(), 1);
+        assert_eq!(bars[0].tick_count, 3);
+        assert!(agg.finish().is_none());
+
+        // The same ticks by volume: 4 per tick
+        let mut agg = VolumeBarAggregator::new(8.0);
+        let counts: Vec<_> = ticks
+            .iter()
+            .filter_map(|t| agg.process(*t))
+            .map(|bar| bar.tick_count)
+            .collect();
+        assert_eq!(counts, [2]);
+    }
+}
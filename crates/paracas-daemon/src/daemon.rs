@@ -17,6 +17,14 @@ pub const DAEMON_JOB_ID_ENV: &str = "PARACAS_DAEMON_JOB_ID";
 /// Command line argument for daemon mode.
 pub const DAEMON_RUN_ARG: &str = "--daemon-run";
 
+/// Command line argument passing the state directory to the daemon.
+pub const STATE_DIR_ARG: &str = "--state-dir";
+
+/// Checks whether the daemon can write an output format.
+///
+/// Returns a human-readable reason when the format is unsupported.
+pub type FormatCheck = fn(&str) -> std::result::Result<(), String>;
+
 /// Spawns detached daemon processes for background downloads.
 ///
 /// The spawner handles all the platform-specific details of creating
@@ -26,6 +34,7 @@ pub const DAEMON_RUN_ARG: &str = "--daemon-run";
 pub struct DaemonSpawner {
     state_manager: StateManager,
     executable_path: PathBuf,
+    format_check: Option<FormatCheck>,
 }
 
 impl DaemonSpawner {
@@ -39,6 +48,7 @@ impl DaemonSpawner {
         Ok(Self {
             state_manager,
             executable_path,
+            format_check: None,
         })
     }
 
@@ -50,9 +60,20 @@ impl DaemonSpawner {
         Self {
             state_manager,
             executable_path,
+            format_check: None,
         }
     }
 
+    /// Validate task formats with `check` before spawning.
+    ///
+    /// Jobs with a task whose format is rejected fail in [`Self::spawn`]
+    /// instead of hours later inside the daemon.
+    #[must_use]
+    pub const fn with_format_check(mut self, check: FormatCheck) -> Self {
+        self.format_check = Some(check);
+        self
+    }
+
     /// Spawn a background download job.
     ///
     /// Returns the job ID for tracking. The job's PID and log file path
@@ -60,8 +81,10 @@ impl DaemonSpawner {
     ///
     /// # Errors
     ///
-    /// Returns an error if the daemon process cannot be spawned.
+    /// Returns an error if a task's format fails the format check, or if
+    /// the daemon process cannot be spawned.
     pub fn spawn(&self, job: &mut DownloadJob) -> Result<JobId> {
+        self.check_formats(job)?;
         let job_id = job.id;
 
         // Set up log file path
@@ -98,6 +121,21 @@ impl DaemonSpawner {
         Ok(job_id)
     }
 
+    /// Reject the job if any task's format fails the format check.
+    fn check_formats(&self, job: &DownloadJob) -> Result<()> {
+        let Some(check) = self.format_check else {
+            return Ok(());
+        };
+        for task in &job.tasks {
+            check(&task.format).map_err(|reason| StateError::UnsupportedFormat {
+                instrument: task.instrument_id.clone(),
+                format: task.format.clone(),
+                reason,
+            })?;
+        }
+        Ok(())
+    }
+
     /// Spawn a detached child process.
     #[cfg(unix)]
     fn spawn_detached(
@@ -110,6 +148,8 @@ impl DaemonSpawner {
 
         let child = Command::new(&self.executable_path)
             .args([DAEMON_RUN_ARG, &job_id.to_string()])
+            .arg(STATE_DIR_ARG)
+            .arg(self.state_manager.base_path())
             .env(DAEMON_JOB_ID_ENV, job_id.to_string())
             .stdin(Stdio::null())
             .stdout(stdout)
@@ -140,6 +180,8 @@ impl DaemonSpawner {
 
         let child = Command::new(&self.executable_path)
             .args([DAEMON_RUN_ARG, &job_id.to_string()])
+            .arg(STATE_DIR_ARG)
+            .arg(self.state_manager.base_path())
             .env(DAEMON_JOB_ID_ENV, job_id.to_string())
             .stdin(Stdio::null())
             .stdout(stdout)
@@ -164,6 +206,8 @@ impl DaemonSpawner {
     ) -> Result<std::process::Child> {
         let child = Command::new(&self.executable_path)
             .args([DAEMON_RUN_ARG, &job_id.to_string()])
+            .arg(STATE_DIR_ARG)
+            .arg(self.state_manager.base_path())
             .env(DAEMON_JOB_ID_ENV, job_id.to_string())
             .stdin(Stdio::null())
             .stdout(stdout)
@@ -270,4 +314,23 @@ mod tests {
             assert_eq!(job.log_file.as_ref().unwrap(), &expected_log_path);
         }
     }
+
+    #[test]
+    fn test_spawn_rejects_unsupported_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let state_manager = StateManager::new(temp_dir.path().to_path_buf()).unwrap();
+        let spawner =
+            DaemonSpawner::with_executable(state_manager.clone(), PathBuf::from("/bin/true"))
+                .with_format_check(|format| match format {
+                    "csv" => Err("not compiled in".to_string()),
+                    _ => Ok(()),
+                });
+
+        let mut job = create_test_job();
+        let result = spawner.spawn(&mut job);
+
+        assert!(matches!(result, Err(StateError::UnsupportedFormat { .. })));
+        assert!(job.pid.is_none());
+        assert!(state_manager.load_job(job.id).is_err());
+    }
 }
@@ -1,6 +1,7 @@
 //! Download job definitions and types.
 
 use chrono::{DateTime, Utc};
+use paracas_types::{CandleStyle, GapFill, Indicator, ParquetSettings, PriceSource};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
@@ -81,6 +82,9 @@ pub struct InstrumentTask {
     pub bytes_written: u64,
     /// Error message if the task failed.
     pub error_message: Option<String>,
+    /// Opaque alias written to outputs in place of the instrument ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
 }
 
 impl InstrumentTask {
@@ -108,9 +112,17 @@ impl InstrumentTask {
             ticks_downloaded: 0,
             bytes_written: 0,
             error_message: None,
+            alias: None,
         }
     }
 
+    /// Sets the alias written to outputs in place of the instrument ID.
+    #[must_use]
+    pub fn with_alias(mut self, alias: Option<String>) -> Self {
+        self.alias = alias;
+        self
+    }
+
     /// Returns the progress percentage for this task.
     #[must_use]
     pub fn progress_percent(&self) -> f64 {
@@ -121,6 +133,127 @@ impl InstrumentTask {
     }
 }
 
+/// Options applied when aggregating ticks into bars.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregationSettings {
+    /// Close bars once their volume reaches this threshold instead of at
+    /// timeframe boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_bars: Option<f64>,
+    /// Close bars once their notional (price × volume) reaches this
+    /// threshold instead of at timeframe boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notional_bars: Option<f64>,
+    /// Build Renko bricks of this many pips instead of time bars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renko_pips: Option<f64>,
+    /// Close bars once their range reaches this many pips instead of at
+    /// timeframe boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range_pips: Option<f64>,
+    /// Tick price the bars are built from.
+    #[serde(default)]
+    pub price_source: PriceSource,
+    /// What to emit for periods without ticks.
+    #[serde(default)]
+    pub gap_fill: GapFill,
+    /// Plain or Heikin-Ashi candles.
+    #[serde(default)]
+    pub candle_style: CandleStyle,
+    /// Keep ask and bid volumes separate and add the volume imbalance.
+    #[serde(default)]
+    pub side_volumes: bool,
+    /// Record the timestamps of the first and last tick on each bar.
+    #[serde(default)]
+    pub tick_times: bool,
+    /// Record the VWAP and the mean and largest spread of each bar.
+    #[serde(default)]
+    pub price_stats: bool,
+    /// Record the tick rate, bid and ask changes and time-weighted spread of
+    /// each bar.
+    #[serde(default)]
+    pub microstructure: bool,
+    /// Write a return series computed from bar closes instead of the bars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<ReturnsMode>,
+    /// Drop ticks whose bid equals the ask.
+    #[serde(default)]
+    pub drop_zero_spreads: bool,
+    /// Drop ticks with the bid above the ask or a non-positive price.
+    #[serde(default)]
+    pub drop_crossed: bool,
+    /// Drop ticks whose mid price is more than this many median absolute
+    /// deviations off the median of recent ticks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spike_mads: Option<f64>,
+    /// Number of recent ticks the spike check compares against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spike_window: Option<usize>,
+    /// Bin width in pips of the volume profile written with each time bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_profile_pips: Option<f64>,
+    /// Rolling indicators written as extra columns next to each bar.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indicators: Vec<Indicator>,
+}
+
+impl AggregationSettings {
+    /// Returns true if bars close on traded activity or price movement
+    /// rather than at timeframe boundaries, so ticks are aggregated whatever
+    /// the timeframe.
+    #[must_use]
+    pub const fn activity_bars(&self) -> bool {
+        self.volume_bars.is_some()
+            || self.notional_bars.is_some()
+            || self.renko_pips.is_some()
+            || self.range_pips.is_some()
+    }
+}
+
+/// Kind of return series written in place of bars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReturnsMode {
+    /// Natural log returns of consecutive closes.
+    Log,
+    /// Price differences of consecutive closes.
+    Diff,
+}
+
+impl std::str::FromStr for ReturnsMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "log" => Ok(Self::Log),
+            "diff" | "difference" => Ok(Self::Diff),
+            _ => Err(format!(
+                "invalid returns mode '{s}', expected one of: log, diff"
+            )),
+        }
+    }
+}
+
+/// Peak memory usage observed while a job ran.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryReport {
+    /// Highest resident set size in bytes, if the platform reports it.
+    pub peak_rss_bytes: Option<u64>,
+    /// Highest number of downloads in flight at once.
+    pub peak_in_flight: u64,
+}
+
+impl MemoryReport {
+    /// Combines two reports, keeping the higher value of each peak.
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            peak_rss_bytes: self.peak_rss_bytes.max(other.peak_rss_bytes),
+            peak_in_flight: self.peak_in_flight.max(other.peak_in_flight),
+        }
+    }
+}
+
 /// A complete download job containing one or more instrument tasks.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadJob {
@@ -142,6 +275,56 @@ pub struct DownloadJob {
     pub pid: Option<u32>,
     /// Path to the log file for this job.
     pub log_file: Option<PathBuf>,
+    /// Options applied when aggregating ticks into bars.
+    #[serde(default)]
+    pub aggregation: AggregationSettings,
+    /// Writer options for Parquet output.
+    #[serde(default)]
+    pub parquet: ParquetSettings,
+    /// Whether every row carries the instrument in a `symbol` column.
+    #[serde(default)]
+    pub symbol_column: bool,
+    /// Whether output is appended to existing CSV or NDJSON files.
+    #[serde(default)]
+    pub append: bool,
+    /// Time zone of MetaTrader timestamps (`utc`, `ny-close` or a tz
+    /// database name), if not UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_time: Option<String>,
+    /// Whether CSV and NDJSON output starts with a metadata line.
+    #[serde(default)]
+    pub metadata_header: bool,
+    /// Whether tick output carries a `spread` column.
+    #[serde(default)]
+    pub spread_column: bool,
+    /// Whether tick output carries a `mid` column.
+    #[serde(default)]
+    pub mid_column: bool,
+    /// Maximum rows per output file, splitting output into numbered files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_rows: Option<usize>,
+    /// Maximum bytes per output file, splitting output into numbered files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_bytes: Option<u64>,
+    /// Whether to produce byte-identical output for identical inputs.
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Directory for cached raw bi5 files, if caching is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_dir: Option<PathBuf>,
+    /// Whether to sample memory usage while the job runs.
+    #[serde(default)]
+    pub report_memory: bool,
+    /// Peak memory usage, recorded when `report_memory` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryReport>,
+    /// Failure injection spec (e.g. `rate=0.05,kind=5xx`) for testing
+    /// recovery during development.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inject_failures: Option<String>,
+    /// Whether to write a run summary next to each output file.
+    #[serde(default)]
+    pub summary: bool,
 }
 
 impl DownloadJob {
@@ -158,9 +341,124 @@ impl DownloadJob {
             concurrency,
             pid: None,
             log_file: None,
+            aggregation: AggregationSettings::default(),
+            parquet: ParquetSettings::default(),
+            symbol_column: false,
+            append: false,
+            server_time: None,
+            metadata_header: false,
+            spread_column: false,
+            mid_column: false,
+            max_file_rows: None,
+            max_file_bytes: None,
+            deterministic: false,
+            cache_dir: None,
+            report_memory: false,
+            memory: None,
+            inject_failures: None,
+            summary: false,
         }
     }
 
+    /// Sets the aggregation options for this job.
+    #[must_use]
+    pub fn with_aggregation(mut self, aggregation: AggregationSettings) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Sets the Parquet writer options for this job.
+    #[must_use]
+    pub const fn with_parquet(mut self, parquet: ParquetSettings) -> Self {
+        self.parquet = parquet;
+        self
+    }
+
+    /// Adds the instrument as a `symbol` column of every output row.
+    #[must_use]
+    pub const fn with_symbol_column(mut self, symbol_column: bool) -> Self {
+        self.symbol_column = symbol_column;
+        self
+    }
+
+    /// Appends output to existing files instead of replacing them.
+    #[must_use]
+    pub const fn with_append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    /// Sets the time zone of MetaTrader timestamps.
+    #[must_use]
+    pub fn with_server_time(mut self, server_time: Option<String>) -> Self {
+        self.server_time = server_time;
+        self
+    }
+
+    /// Starts CSV and NDJSON output with a metadata line.
+    #[must_use]
+    pub const fn with_metadata_header(mut self, metadata_header: bool) -> Self {
+        self.metadata_header = metadata_header;
+        self
+    }
+
+    /// Adds `spread` and `mid` columns to tick output.
+    #[must_use]
+    pub const fn with_derived_columns(mut self, spread: bool, mid: bool) -> Self {
+        self.spread_column = spread;
+        self.mid_column = mid;
+        self
+    }
+
+    /// Splits output into numbered files of at most `max_rows` rows or
+    /// about `max_bytes` bytes.
+    #[must_use]
+    pub const fn with_split(mut self, max_rows: Option<usize>, max_bytes: Option<u64>) -> Self {
+        self.max_file_rows = max_rows;
+        self.max_file_bytes = max_bytes;
+        self
+    }
+
+    /// Enables deterministic output for this job.
+    #[must_use]
+    pub const fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Sets the directory for cached raw bi5 files.
+    #[must_use]
+    pub fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
+        self.cache_dir = cache_dir;
+        self
+    }
+
+    /// Enables memory usage sampling for this job.
+    #[must_use]
+    pub const fn with_report_memory(mut self, report_memory: bool) -> Self {
+        self.report_memory = report_memory;
+        self
+    }
+
+    /// Sets the failure injection spec for this job.
+    #[must_use]
+    pub fn with_inject_failures(mut self, spec: Option<String>) -> Self {
+        self.inject_failures = spec;
+        self
+    }
+
+    /// Enables writing a run summary next to each output file.
+    #[must_use]
+    pub const fn with_summary(mut self, summary: bool) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    /// Records peak memory usage, merging with any earlier report.
+    pub fn record_memory(&mut self, report: MemoryReport) {
+        self.memory = Some(self.memory.unwrap_or_default().merge(report));
+    }
+
     /// Returns the overall progress percentage across all tasks.
     #[must_use]
     pub fn progress_percent(&self) -> f64 {
@@ -342,4 +640,27 @@ mod tests {
         assert!(job.completed_at.is_some());
         assert!(job.is_finished());
     }
+
+    #[test]
+    fn test_record_memory_keeps_peaks() {
+        let mut job = DownloadJob::new(Vec::new(), 4).with_report_memory(true);
+        assert!(job.memory.is_none());
+
+        job.record_memory(MemoryReport {
+            peak_rss_bytes: Some(200),
+            peak_in_flight: 3,
+        });
+        job.record_memory(MemoryReport {
+            peak_rss_bytes: Some(100),
+            peak_in_flight: 8,
+        });
+
+        assert_eq!(
+            job.memory,
+            Some(MemoryReport {
+                peak_rss_bytes: Some(200),
+                peak_in_flight: 8,
+            })
+        );
+    }
 }
@@ -22,7 +22,9 @@ mod job;
 mod progress;
 mod state;
 
-pub use daemon::{DAEMON_JOB_ID_ENV, DAEMON_RUN_ARG, DaemonSpawner};
-pub use job::{DownloadJob, InstrumentTask, JobId, JobStatus};
+pub use daemon::{DAEMON_JOB_ID_ENV, DAEMON_RUN_ARG, DaemonSpawner, FormatCheck, STATE_DIR_ARG};
+pub use job::{
+    AggregationSettings, DownloadJob, InstrumentTask, JobId, JobStatus, MemoryReport, ReturnsMode,
+};
 pub use progress::DaemonProgress;
-pub use state::{Result, StateError, StateManager};
+pub use state::{Result, StateError, StateManager, TermsAcknowledgement};
@@ -3,7 +3,7 @@
 //! This module provides thread-safe progress tracking for daemon jobs,
 //! including periodic checkpointing to disk for crash recovery.
 
-use crate::{DownloadJob, JobStatus, StateError, StateManager};
+use crate::{DownloadJob, JobStatus, MemoryReport, StateError, StateManager};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -148,6 +148,15 @@ impl DaemonProgress {
         let _ = self.save_checkpoint().await;
     }
 
+    /// Record peak memory usage for the job.
+    ///
+    /// Peaks are merged with any earlier report, so this can be called once
+    /// per task.
+    pub async fn record_memory(&self, report: MemoryReport) {
+        self.job.write().await.record_memory(report);
+        self.maybe_save_checkpoint().await;
+    }
+
     /// Mark the entire job as completed.
     ///
     /// Call this when all tasks have finished successfully.
@@ -178,6 +187,19 @@ impl DaemonProgress {
         let _ = self.save_checkpoint().await;
     }
 
+    /// Mark the entire job as cancelled.
+    ///
+    /// Call this when the job is stopped before all tasks finish.
+    pub async fn mark_job_cancelled(&self) {
+        {
+            let mut job = self.job.write().await;
+            job.mark_cancelled();
+        }
+
+        // Always save on job cancellation
+        let _ = self.save_checkpoint().await;
+    }
+
     /// Save current progress to disk (called periodically).
     ///
     /// This forces a checkpoint save regardless of the save interval.
@@ -1,7 +1,9 @@
 //! State management for persistent job storage.
 
 use crate::{DownloadJob, JobId, JobStatus};
+use chrono::{DateTime, Utc};
 use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -90,11 +92,34 @@ pub enum StateError {
         /// The underlying I/O error.
         source: std::io::Error,
     },
+
+    /// A task requested an output format the daemon cannot write.
+    #[error("Unsupported format '{format}' for {instrument}: {reason}")]
+    UnsupportedFormat {
+        /// The instrument of the rejected task.
+        instrument: String,
+        /// The requested format.
+        format: String,
+        /// Why the format is unsupported.
+        reason: String,
+    },
 }
 
 /// Result type for state operations.
 pub type Result<T> = std::result::Result<T, StateError>;
 
+/// File name of the persisted data terms acknowledgement.
+const TERMS_ACK_FILE: &str = "terms_ack.json";
+
+/// A persisted acknowledgement of the data terms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermsAcknowledgement {
+    /// The exact terms text that was acknowledged.
+    pub terms: String,
+    /// When the terms were acknowledged.
+    pub acknowledged_at: DateTime<Utc>,
+}
+
 /// Manages persistent state for download jobs.
 ///
 /// Jobs are stored as JSON files in `~/.paracas/jobs/` with log files
@@ -252,7 +277,7 @@ impl StateManager {
         }
 
         // Sort by creation time, newest first
-        jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        jobs.sort_by_key(|b| std::cmp::Reverse(b.created_at));
 
         Ok(jobs)
     }
@@ -331,6 +356,40 @@ impl StateManager {
         }
     }
 
+    /// Returns the path of the data terms acknowledgement file.
+    #[must_use]
+    pub fn terms_ack_path(&self) -> PathBuf {
+        self.base_path.join(TERMS_ACK_FILE)
+    }
+
+    /// Returns true if exactly these terms have been acknowledged.
+    ///
+    /// A change to the terms text invalidates any earlier acknowledgement.
+    #[must_use]
+    pub fn has_acknowledged_terms(&self, terms: &str) -> bool {
+        fs::read_to_string(self.terms_ack_path())
+            .ok()
+            .and_then(|content| serde_json::from_str::<TermsAcknowledgement>(&content).ok())
+            .is_some_and(|ack| ack.terms == terms)
+    }
+
+    /// Persists an acknowledgement of the given terms.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the acknowledgement cannot be written to disk.
+    pub fn acknowledge_terms(&self, terms: &str) -> Result<TermsAcknowledgement> {
+        let ack = TermsAcknowledgement {
+            terms: terms.to_string(),
+            acknowledged_at: Utc::now(),
+        };
+        let path = self.terms_ack_path();
+        let json = serde_json::to_string_pretty(&ack)?;
+
+        fs::write(&path, json).map_err(|e| StateError::WriteFile { path, source: e })?;
+        Ok(ack)
+    }
+
     /// Cleans up stale jobs where the process is no longer running.
     ///
     /// Marks running jobs as failed if their daemon process has died.
@@ -395,6 +454,17 @@ mod tests {
         assert!(temp_dir.path().join("logs").exists());
     }
 
+    #[test]
+    fn test_terms_acknowledgement() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = StateManager::new(temp_dir.path().to_path_buf()).unwrap();
+
+        assert!(!manager.has_acknowledged_terms("terms v1"));
+        manager.acknowledge_terms("terms v1").unwrap();
+        assert!(manager.has_acknowledged_terms("terms v1"));
+        assert!(!manager.has_acknowledged_terms("terms v2"));
+    }
+
     #[test]
     fn test_save_and_load_job() {
         let temp_dir = TempDir::new().unwrap();
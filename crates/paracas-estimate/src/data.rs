@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::sync::OnceLock;
 
+use paracas_types::Timeframe;
 use serde::{Deserialize, Serialize};
 
 /// Embedded JSON data with historical size estimates.
@@ -22,9 +23,15 @@ pub struct CategoryEstimate {
     pub avg_ticks_per_hour: u64,
     /// Multiplier for peak trading hours.
     pub peak_multiplier: f64,
+    /// Bar timeframe used instead of raw ticks when they would be too large
+    /// (see [`TimeframeDefaults`](crate::TimeframeDefaults)).
+    pub default_timeframe: Timeframe,
 }
 
 impl CategoryEstimate {
+    /// Default bar timeframe of categories that do not set one.
+    pub const DEFAULT_TIMEFRAME: Timeframe = Timeframe::Minute1;
+
     /// Creates a new category estimate.
     #[must_use]
     pub fn new(
@@ -38,9 +45,17 @@ impl CategoryEstimate {
             avg_compressed_bytes_per_hour,
             avg_ticks_per_hour,
             peak_multiplier,
+            default_timeframe: Self::DEFAULT_TIMEFRAME,
         }
     }
 
+    /// Sets the bar timeframe used when raw ticks would be too large.
+    #[must_use]
+    pub const fn with_default_timeframe(mut self, timeframe: Timeframe) -> Self {
+        self.default_timeframe = timeframe;
+        self
+    }
+
     /// Returns the maximum compressed bytes per hour (at peak).
     #[must_use]
     pub fn max_compressed_bytes_per_hour(&self) -> u64 {
@@ -66,6 +81,8 @@ struct RawCategoryEstimate {
     avg_compressed_bytes_per_hour: u64,
     avg_ticks_per_hour: u64,
     peak_multiplier: f64,
+    #[serde(default)]
+    default_timeframe: Option<Timeframe>,
 }
 
 /// Database of historical size estimates per instrument category.
@@ -97,12 +114,15 @@ impl EstimateDatabase {
             .categories
             .into_iter()
             .map(|(name, raw_est)| {
-                let estimate = CategoryEstimate::new(
+                let mut estimate = CategoryEstimate::new(
                     name.clone(),
                     raw_est.avg_compressed_bytes_per_hour,
                     raw_est.avg_ticks_per_hour,
                     raw_est.peak_multiplier,
                 );
+                if let Some(timeframe) = raw_est.default_timeframe {
+                    estimate = estimate.with_default_timeframe(timeframe);
+                }
                 (name, estimate)
             })
             .collect();
@@ -167,6 +187,10 @@ mod tests {
         let crypto = db.get("crypto").expect("crypto should exist");
         assert_eq!(crypto.avg_compressed_bytes_per_hour, 150000);
         assert_eq!(crypto.avg_ticks_per_hour, 10000);
+        assert_eq!(crypto.default_timeframe, Timeframe::Minute1);
+
+        let bond = db.get("bond").expect("bond should exist");
+        assert_eq!(bond.default_timeframe, Timeframe::Minute15);
     }
 
     #[test]
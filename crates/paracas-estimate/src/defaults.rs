@@ -0,0 +1,156 @@
+//! Timeframe defaults for requests that do not name one.
+//!
+//! Raw ticks are the default output, but over long ranges of busy
+//! instruments they add up to hundreds of gigabytes. [`TimeframeDefaults`]
+//! keeps ticks while their estimated output stays under a size budget and
+//! otherwise falls back to the category's default bar timeframe (see
+//! [`CategoryEstimate::default_timeframe`]).
+
+use paracas_types::{DateRange, Instrument, Timeframe};
+
+use crate::data::{CategoryEstimate, EstimateDatabase};
+use crate::estimator::{DownloadEstimate, Estimator};
+
+/// Default output budget for raw ticks, in bytes (10 GiB).
+pub const DEFAULT_MAX_TICK_OUTPUT_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Picks the timeframe for requests without an explicit one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeframeDefaults {
+    /// Estimated output size above which raw ticks are replaced by bars.
+    pub max_tick_output_bytes: u64,
+}
+
+impl Default for TimeframeDefaults {
+    fn default() -> Self {
+        Self {
+            max_tick_output_bytes: DEFAULT_MAX_TICK_OUTPUT_BYTES,
+        }
+    }
+}
+
+impl TimeframeDefaults {
+    /// Sets the estimated output size above which ticks are replaced by bars.
+    #[must_use]
+    pub const fn with_max_tick_output_bytes(mut self, bytes: u64) -> Self {
+        self.max_tick_output_bytes = bytes;
+        self
+    }
+
+    /// Chooses the timeframe for downloading `instrument` over `range`.
+    #[must_use]
+    pub fn choose(
+        &self,
+        estimator: &Estimator,
+        instrument: &Instrument,
+        range: &DateRange,
+    ) -> TimeframeChoice {
+        self.choose_batch(estimator, &[instrument], range)
+    }
+
+    /// Chooses one timeframe for downloading all `instruments` over `range`.
+    ///
+    /// The budget applies to the combined output. When ticks exceed it, the
+    /// coarsest default timeframe among the instruments' categories is used.
+    #[must_use]
+    pub fn choose_batch(
+        &self,
+        estimator: &Estimator,
+        instruments: &[&Instrument],
+        range: &DateRange,
+    ) -> TimeframeChoice {
+        let tick_estimate = estimator.estimate_batch(instruments, range);
+        let timeframe = if tick_estimate.estimated_output_bytes > self.max_tick_output_bytes {
+            instruments
+                .iter()
+                .map(|instrument| category_default(instrument))
+                .max_by_key(|timeframe| timeframe.seconds())
+                .unwrap_or(Timeframe::Tick)
+        } else {
+            Timeframe::Tick
+        };
+        TimeframeChoice {
+            timeframe,
+            tick_estimate,
+        }
+    }
+}
+
+/// Returns the default bar timeframe of the instrument's category.
+fn category_default(instrument: &Instrument) -> Timeframe {
+    EstimateDatabase::global()
+        .get(instrument.category().as_str())
+        .map_or(CategoryEstimate::DEFAULT_TIMEFRAME, |estimate| {
+            estimate.default_timeframe
+        })
+}
+
+/// Timeframe picked by [`TimeframeDefaults`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeframeChoice {
+    /// The timeframe to download.
+    pub timeframe: Timeframe,
+    /// Estimate for downloading raw ticks, which drove the choice.
+    pub tick_estimate: DownloadEstimate,
+}
+
+impl TimeframeChoice {
+    /// Returns true if raw ticks were replaced by bars.
+    #[must_use]
+    pub const fn is_aggregated(&self) -> bool {
+        !self.timeframe.is_tick()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use paracas_types::Category;
+
+    fn instrument(category: Category) -> Instrument {
+        Instrument::new("test", "Test", "Test instrument", category, 100_000, None)
+    }
+
+    fn years(count: i32) -> DateRange {
+        DateRange::new(
+            NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2020 + count, 1, 1).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_short_ranges_keep_ticks() {
+        let defaults = TimeframeDefaults::default();
+        let crypto = instrument(Category::Crypto);
+        let range = DateRange::single_day(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        let choice = defaults.choose(Estimator::global(), &crypto, &range);
+        assert_eq!(choice.timeframe, Timeframe::Tick);
+        assert!(!choice.is_aggregated());
+    }
+
+    #[test]
+    fn test_long_crypto_range_uses_minute_bars() {
+        let defaults = TimeframeDefaults::default();
+        let crypto = instrument(Category::Crypto);
+        let forex = instrument(Category::Forex);
+
+        let choice = defaults.choose(Estimator::global(), &crypto, &years(1));
+        assert_eq!(choice.timeframe, Timeframe::Minute1);
+        assert!(choice.tick_estimate.estimated_output_bytes > DEFAULT_MAX_TICK_OUTPUT_BYTES);
+
+        // Forex trades less per hour and not on weekends
+        let choice = defaults.choose(Estimator::global(), &forex, &years(1));
+        assert_eq!(choice.timeframe, Timeframe::Tick);
+    }
+
+    #[test]
+    fn test_batch_uses_coarsest_category_default() {
+        let defaults = TimeframeDefaults::default().with_max_tick_output_bytes(0);
+        let crypto = instrument(Category::Crypto);
+        let stock = instrument(Category::Stock);
+        let choice = defaults.choose_batch(Estimator::global(), &[&crypto, &stock], &years(1));
+        assert_eq!(choice.timeframe, Timeframe::Minute5);
+    }
+}
@@ -132,13 +132,16 @@ impl Estimator {
     }
 
     /// Estimates download metrics for a single instrument and date range.
+    ///
+    /// Only hours in which the instrument trades, per its
+    /// [`calendar`](Instrument::calendar), are counted.
     #[must_use]
     pub fn estimate_single(
         &self,
         instrument: &Instrument,
         date_range: &DateRange,
     ) -> DownloadEstimate {
-        let total_hours = date_range.total_hours();
+        let total_hours = date_range.total_trading_hours(instrument.calendar());
         let category = instrument.category().as_str();
 
         let db = EstimateDatabase::global();
@@ -166,14 +169,16 @@ impl Estimator {
             return DownloadEstimate::empty();
         }
 
-        let total_hours = date_range.total_hours();
         let db = EstimateDatabase::global();
 
+        let mut total_hours = 0;
         let mut total_compressed_bytes: u64 = 0;
         let mut total_ticks: u64 = 0;
         let mut min_confidence = EstimateConfidence::High;
 
         for instrument in instruments {
+            let hours = date_range.total_trading_hours(instrument.calendar());
+            total_hours += hours;
             let category = instrument.category().as_str();
             let (cat_estimate, confidence) = db.get(category).map_or_else(
                 || {
@@ -185,9 +190,8 @@ impl Estimator {
                 |est| (est.clone(), EstimateConfidence::High),
             );
 
-            total_compressed_bytes +=
-                cat_estimate.avg_compressed_bytes_per_hour * total_hours as u64;
-            total_ticks += cat_estimate.avg_ticks_per_hour * total_hours as u64;
+            total_compressed_bytes += cat_estimate.avg_compressed_bytes_per_hour * hours as u64;
+            total_ticks += cat_estimate.avg_ticks_per_hour * hours as u64;
 
             // Use the lowest confidence among all instruments
             if matches!(confidence, EstimateConfidence::Low) {
@@ -205,7 +209,7 @@ impl Estimator {
         let estimated_duration = self.calculate_duration(total_compressed_bytes);
 
         DownloadEstimate::new(
-            total_hours * instruments.len(),
+            total_hours,
             total_compressed_bytes,
             estimated_uncompressed_bytes,
             estimated_output_bytes,
@@ -381,6 +385,31 @@ mod tests {
         assert_eq!(estimate.estimated_compressed_bytes, (75000 + 150000) * 24);
     }
 
+    #[test]
+    fn test_estimate_skips_closed_hours() {
+        let estimator = Estimator::default();
+        let forex = create_test_instrument(Category::Forex);
+        let crypto = create_test_instrument(Category::Crypto);
+        let stock = create_test_instrument(Category::Stock);
+        // Saturday 2024-01-06 through Sunday 2024-01-07
+        let start = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+        let date_range = DateRange::new(start, end).unwrap();
+
+        // Forex reopens at 20:00 on Sunday
+        let estimate = estimator.estimate_single(&forex, &date_range);
+        assert_eq!(estimate.total_hours, 4);
+        assert_eq!(
+            estimator.estimate_single(&stock, &date_range).total_hours,
+            0
+        );
+
+        let instruments: Vec<&Instrument> = vec![&forex, &crypto];
+        let estimate = estimator.estimate_batch(&instruments, &date_range);
+        assert_eq!(estimate.total_hours, 4 + 48);
+        assert_eq!(estimate.estimated_compressed_bytes, 75000 * 4 + 150000 * 48);
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(Estimator::format_bytes(500), "500 B");
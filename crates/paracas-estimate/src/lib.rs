@@ -8,6 +8,7 @@
 //! - [`Estimator`] - Computes download estimates for instruments and date ranges
 //! - [`DownloadEstimate`] - Estimated download metrics
 //! - [`EstimateConfidence`] - Confidence level of the estimate
+//! - [`TimeframeDefaults`] - Falls back to bars when raw ticks would be too large
 
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/factordynamics/paracas/issues/")]
@@ -16,7 +17,9 @@
 #![forbid(unsafe_code)]
 
 mod data;
+mod defaults;
 mod estimator;
 
 pub use data::{CategoryEstimate, EstimateDatabase};
+pub use defaults::{DEFAULT_MAX_TICK_OUTPUT_BYTES, TimeframeChoice, TimeframeDefaults};
 pub use estimator::{DownloadEstimate, EstimateConfidence, Estimator};
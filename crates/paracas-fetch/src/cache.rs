@@ -0,0 +1,372 @@
+//! On-disk cache of raw bi5 files.
+//!
+//! Entries mirror the Dukascopy URL layout under the cache root
+//! (`<root>/EURUSD/2024/00/15/12h_ticks.bi5`) and store the compressed bytes
+//! exactly as downloaded. Hours without data are stored as empty files.
+//!
+//! When an expired entry is downloaded again and its content differs from the
+//! stored copy, the cache records a [`Revision`] so upstream corrections do not
+//! go unnoticed.
+//!
+//! Each stored hour is also recorded in the instrument's
+//! [`CoverageManifest`], which tracks coverage and checksums.
+
+use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
+use paracas_types::hour_from_url;
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::manifest::{CoverageManifest, ManifestEntry};
+use crate::url::relative_path;
+
+/// Policy deciding when a cached entry must be downloaded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    /// Maximum age of any entry, or `None` to keep entries indefinitely.
+    pub ttl: Option<Duration>,
+    /// Trailing window (in days) during which Dukascopy may still revise data.
+    ///
+    /// An entry fetched while its hour was inside this window is provisional:
+    /// it is re-downloaded on every run until a copy fetched after the window
+    /// closed is stored.
+    pub revision_days: u32,
+}
+
+impl Default for ExpiryPolicy {
+    fn default() -> Self {
+        Self {
+            ttl: None,
+            revision_days: 2,
+        }
+    }
+}
+
+impl ExpiryPolicy {
+    /// Sets the maximum entry age.
+    #[must_use]
+    pub const fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the revision window in days.
+    #[must_use]
+    pub const fn with_revision_days(mut self, days: u32) -> Self {
+        self.revision_days = days;
+        self
+    }
+
+    /// Returns true if an entry for `hour` fetched at `fetched_at` is still
+    /// usable at `now`.
+    #[must_use]
+    pub fn is_fresh(
+        &self,
+        hour: DateTime<Utc>,
+        fetched_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> bool {
+        if let Some(ttl) = self.ttl {
+            let age = (now - fetched_at).to_std().unwrap_or_default();
+            if age > ttl {
+                return false;
+            }
+        }
+
+        let settled_at =
+            hour + TimeDelta::hours(1) + TimeDelta::days(i64::from(self.revision_days));
+        fetched_at >= settled_at
+    }
+}
+
+/// Configuration for the on-disk cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Root directory of the cache.
+    pub dir: PathBuf,
+    /// Expiry policy for cached entries.
+    pub expiry: ExpiryPolicy,
+}
+
+impl CacheConfig {
+    /// Creates a cache configuration rooted at `dir` with the default policy.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            expiry: ExpiryPolicy::default(),
+        }
+    }
+
+    /// Sets the expiry policy.
+    #[must_use]
+    pub const fn with_expiry(mut self, expiry: ExpiryPolicy) -> Self {
+        self.expiry = expiry;
+        self
+    }
+}
+
+/// An hour whose upstream content changed since it was cached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// The URL that was re-downloaded.
+    pub url: String,
+    /// Start of the revised hour, if it could be derived from the URL.
+    pub hour: Option<DateTime<Utc>>,
+    /// SHA-256 (hex) of the previously cached content.
+    pub previous_sha256: String,
+    /// SHA-256 (hex) of the newly downloaded content.
+    pub current_sha256: String,
+    /// Size of the previously cached content in bytes.
+    pub previous_len: u64,
+    /// Size of the newly downloaded content in bytes.
+    pub current_len: u64,
+}
+
+/// Returns the hex-encoded SHA-256 digest of `data`.
+#[must_use]
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Returns the hex-encoded SHA-256 digest of the file at `path`, reading it
+/// in chunks.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn sha256_file_hex(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// On-disk cache of compressed bi5 files keyed by URL.
+///
+/// Clones share the log of detected revisions.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    config: CacheConfig,
+    revisions: Arc<Mutex<Vec<Revision>>>,
+}
+
+impl DiskCache {
+    /// Creates a cache with the given configuration.
+    ///
+    /// The root directory is created lazily on first write.
+    #[must_use]
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            revisions: Arc::default(),
+        }
+    }
+
+    /// Returns the cache configuration.
+    #[must_use]
+    pub const fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Returns the cache root directory.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.config.dir
+    }
+
+    /// Returns the on-disk path for a Dukascopy URL, or `None` if the URL is
+    /// not under [`BASE_URL`](crate::url::BASE_URL).
+    #[must_use]
+    pub fn path_for_url(&self, url: &str) -> Option<PathBuf> {
+        relative_path(url).map(|relative| self.config.dir.join(relative))
+    }
+
+    /// Loads the coverage manifest of `instrument`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest exists but cannot be read.
+    pub fn manifest(&self, instrument: &str) -> io::Result<CoverageManifest> {
+        CoverageManifest::load(&self.config.dir, instrument)
+    }
+
+    /// Looks up a fresh entry for `url`.
+    ///
+    /// Returns `Some(None)` for a cached empty hour, `Some(Some(bytes))` for
+    /// cached data, and `None` if the entry is missing or expired.
+    pub async fn get(&self, url: &str) -> Option<Option<Bytes>> {
+        let path = self.path_for_url(url)?;
+        let hour = hour_from_url(url)?;
+        let metadata = tokio::fs::metadata(&path).await.ok()?;
+        let fetched_at: DateTime<Utc> = metadata.modified().ok()?.into();
+
+        if !self.config.expiry.is_fresh(hour, fetched_at, Utc::now()) {
+            return None;
+        }
+
+        let data = tokio::fs::read(&path).await.ok()?;
+        Some((!data.is_empty()).then(|| Bytes::from(data)))
+    }
+
+    /// Returns all revisions detected so far.
+    #[must_use]
+    pub fn revisions(&self) -> Vec<Revision> {
+        self.revisions
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Stores the result of downloading `url`; `None` records an empty hour.
+    ///
+    /// If a previous copy exists with different content, the change is
+    /// recorded and returned as a [`Revision`]. The hour is added to the
+    /// instrument's coverage manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry cannot be written.
+    pub async fn put(&self, url: &str, data: Option<&Bytes>) -> io::Result<Option<Revision>> {
+        let Some(path) = self.path_for_url(url) else {
+            return Ok(None);
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let content = data.map_or(&[][..], |b| b.as_ref());
+        let revision = match tokio::fs::read(&path).await {
+            Ok(previous) if previous != content => Some(Revision {
+                url: url.to_string(),
+                hour: hour_from_url(url),
+                previous_sha256: sha256_hex(&previous),
+                current_sha256: sha256_hex(content),
+                previous_len: previous.len() as u64,
+                current_len: content.len() as u64,
+            }),
+            _ => None,
+        };
+
+        // Write to a temporary file and rename so readers never see partial data.
+        let tmp = path.with_extension("bi5.tmp");
+        tokio::fs::write(&tmp, content).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+
+        if let (Some(hour), Some(instrument)) = (
+            hour_from_url(url),
+            relative_path(url).and_then(|relative| relative.split('/').next()),
+        ) {
+            let entry = ManifestEntry::new(hour, content);
+            CoverageManifest::append(&self.config.dir, instrument, &entry).await?;
+        }
+
+        if let Some(revision) = &revision {
+            self.revisions
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .push(revision.clone());
+        }
+        Ok(revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::tick_url;
+    use chrono::TimeZone;
+
+    fn hour() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_path_for_url() {
+        let cache = DiskCache::new(CacheConfig::new("/cache"));
+        let path = cache.path_for_url(&tick_url("eurusd", hour())).unwrap();
+        assert_eq!(
+            path,
+            PathBuf::from("/cache/EURUSD/2024/00/15/12h_ticks.bi5")
+        );
+        assert!(cache.path_for_url("https://example.com/x.bi5").is_none());
+    }
+
+    #[test]
+    fn test_revision_window() {
+        let policy = ExpiryPolicy::default().with_revision_days(2);
+
+        // Fetched an hour after the hour ended: still provisional.
+        let early = hour() + TimeDelta::hours(2);
+        assert!(!policy.is_fresh(hour(), early, early));
+
+        // Fetched after the window closed: fresh.
+        let settled = hour() + TimeDelta::days(3);
+        assert!(policy.is_fresh(hour(), settled, settled + TimeDelta::days(30)));
+    }
+
+    #[test]
+    fn test_ttl() {
+        let policy = ExpiryPolicy::default()
+            .with_revision_days(0)
+            .with_ttl(Duration::from_secs(3600));
+        let fetched = hour() + TimeDelta::days(1);
+        assert!(policy.is_fresh(hour(), fetched, fetched + TimeDelta::minutes(30)));
+        assert!(!policy.is_fresh(hour(), fetched, fetched + TimeDelta::hours(2)));
+    }
+
+    #[tokio::test]
+    async fn test_put_and_get() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let policy = ExpiryPolicy::default().with_revision_days(0);
+        let cache = DiskCache::new(CacheConfig::new(dir.path()).with_expiry(policy));
+
+        let url = tick_url("eurusd", hour());
+        assert!(cache.get(&url).await.is_none());
+
+        cache
+            .put(&url, Some(&Bytes::from_static(b"abc")))
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get(&url).await,
+            Some(Some(Bytes::from_static(b"abc")))
+        );
+
+        assert!(cache.revisions().is_empty());
+
+        let empty_url = tick_url("eurusd", hour() + TimeDelta::hours(1));
+        cache.put(&empty_url, None).await.unwrap();
+        assert_eq!(cache.get(&empty_url).await, Some(None));
+    }
+
+    #[tokio::test]
+    async fn test_revision_detection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cache = DiskCache::new(CacheConfig::new(dir.path()));
+        let url = tick_url("eurusd", hour());
+
+        let first = Bytes::from_static(b"original");
+        assert!(cache.put(&url, Some(&first)).await.unwrap().is_none());
+        // Storing identical content is not a revision.
+        assert!(cache.put(&url, Some(&first)).await.unwrap().is_none());
+
+        let corrected = Bytes::from_static(b"corrected!");
+        let revision = cache.put(&url, Some(&corrected)).await.unwrap().unwrap();
+        assert_eq!(revision.hour, Some(hour()));
+        assert_eq!(revision.previous_sha256, sha256_hex(b"original"));
+        assert_eq!(revision.current_len, 10);
+        assert_eq!(cache.revisions(), vec![revision]);
+    }
+
+    #[test]
+    fn test_sha256_file_hex() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, b"original").unwrap();
+        assert_eq!(sha256_file_hex(&path).unwrap(), sha256_hex(b"original"));
+        assert!(sha256_file_hex(dir.path().join("missing")).is_err());
+    }
+}
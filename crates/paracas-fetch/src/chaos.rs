@@ -0,0 +1,291 @@
+//! Failure injection for exercising recovery paths during development.
+//!
+//! With [`ClientConfig::failure_injection`](crate::ClientConfig::failure_injection)
+//! set, a share of HTTP request attempts fail on purpose before anything is
+//! sent, so retries, the circuit breaker, corrupt-file refetches, resume and
+//! repair can be tested without a misbehaving server. Never enable this for
+//! real downloads.
+
+use bytes::Bytes;
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::client::DownloadError;
+use crate::retry::FailureKind;
+
+/// Body returned for [`InjectedFailure::Corrupt`], which is not a valid bi5
+/// archive.
+const CORRUPT_BODY: &[u8] = b"paracas injected corrupt body";
+
+/// A failure that can be injected into a request attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InjectedFailure {
+    /// The server answers 503 Service Unavailable.
+    ServerError,
+    /// The server answers 429 Too Many Requests.
+    RateLimited,
+    /// The request times out.
+    Timeout,
+    /// The request succeeds with a body that fails to decompress.
+    Corrupt,
+}
+
+impl InjectedFailure {
+    /// Returns the failure name as accepted by [`FromStr`].
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ServerError => "5xx",
+            Self::RateLimited => "429",
+            Self::Timeout => "timeout",
+            Self::Corrupt => "corrupt",
+        }
+    }
+
+    /// Returns how the retry policy classifies the failed attempt.
+    ///
+    /// Corrupt bodies are not failed attempts; they are reported as generic
+    /// request errors for completeness.
+    pub(crate) const fn failure_kind(self) -> FailureKind {
+        match self {
+            Self::ServerError => FailureKind::Status(503),
+            Self::RateLimited => FailureKind::Status(429),
+            Self::Timeout => FailureKind::Timeout,
+            Self::Corrupt => FailureKind::Request,
+        }
+    }
+
+    /// Returns the error reported once the retry budget is spent.
+    pub(crate) const fn error(self, attempts: u32) -> DownloadError {
+        match self {
+            Self::Timeout => DownloadError::Timeout(attempts),
+            Self::RateLimited => DownloadError::ServerError {
+                status: 429,
+                attempts,
+            },
+            Self::ServerError | Self::Corrupt => DownloadError::ServerError {
+                status: 503,
+                attempts,
+            },
+        }
+    }
+
+    /// Returns the body served for an injected corrupt download.
+    pub(crate) const fn corrupt_body() -> Bytes {
+        Bytes::from_static(CORRUPT_BODY)
+    }
+}
+
+impl fmt::Display for InjectedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for InjectedFailure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "5xx" | "503" => Ok(Self::ServerError),
+            "429" => Ok(Self::RateLimited),
+            "timeout" => Ok(Self::Timeout),
+            "corrupt" => Ok(Self::Corrupt),
+            _ => Err(format!(
+                "unknown failure kind \"{s}\" (expected 5xx, 429, timeout or corrupt)"
+            )),
+        }
+    }
+}
+
+/// Settings for injecting failures into HTTP request attempts.
+///
+/// Parses from a comma-separated list of `key=value` pairs, e.g.
+/// `rate=0.05,kind=5xx`. `kind` may be repeated to mix failures, and
+/// `seed=N` makes the sequence of failures reproducible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureInjection {
+    /// Share of request attempts that fail, between 0 and 1.
+    pub rate: f64,
+    /// Failures to inject, picked uniformly for each failing attempt.
+    pub kinds: Vec<InjectedFailure>,
+    /// Seed for a reproducible sequence of failures, or `None` for a random
+    /// one.
+    pub seed: Option<u64>,
+}
+
+impl Default for FailureInjection {
+    fn default() -> Self {
+        Self {
+            rate: 0.05,
+            kinds: vec![InjectedFailure::ServerError],
+            seed: None,
+        }
+    }
+}
+
+impl FailureInjection {
+    /// Sets the share of request attempts that fail.
+    #[must_use]
+    pub const fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Sets the failures to inject.
+    #[must_use]
+    pub fn with_kinds(mut self, kinds: impl IntoIterator<Item = InjectedFailure>) -> Self {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+
+    /// Sets the seed for a reproducible sequence of failures.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl fmt::Display for FailureInjection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rate={}", self.rate)?;
+        for kind in &self.kinds {
+            write!(f, ",kind={kind}")?;
+        }
+        if let Some(seed) = self.seed {
+            write!(f, ",seed={seed}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for FailureInjection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut injection = Self::default();
+        let mut kinds = Vec::new();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got \"{pair}\""))?;
+            match key.trim() {
+                "rate" => {
+                    injection.rate = value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|rate| (0.0..=1.0).contains(rate))
+                        .ok_or_else(|| format!("invalid rate \"{value}\" (expected 0 to 1)"))?;
+                }
+                "kind" => kinds.push(value.trim().parse()?),
+                "seed" => {
+                    injection.seed = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .map_err(|_| format!("invalid seed \"{value}\""))?,
+                    );
+                }
+                other => {
+                    return Err(format!(
+                        "unknown key \"{other}\" (expected rate, kind or seed)"
+                    ));
+                }
+            }
+        }
+        if !kinds.is_empty() {
+            injection.kinds = kinds;
+        }
+        Ok(injection)
+    }
+}
+
+/// Decides which request attempts fail, shared by clones of a client.
+#[derive(Debug, Clone)]
+pub(crate) struct FailureInjector {
+    config: FailureInjection,
+    attempts: Arc<AtomicU64>,
+    random: RandomState,
+}
+
+impl FailureInjector {
+    /// Creates an injector, or `None` if no failures would be injected.
+    pub(crate) fn new(config: &FailureInjection) -> Option<Self> {
+        (config.rate > 0.0 && !config.kinds.is_empty()).then(|| Self {
+            config: config.clone(),
+            attempts: Arc::new(AtomicU64::new(0)),
+            random: RandomState::new(),
+        })
+    }
+
+    /// Returns the failure to inject into the next attempt, if any.
+    pub(crate) fn next(&self) -> Option<InjectedFailure> {
+        let attempt = self.attempts.fetch_add(1, Ordering::Relaxed);
+        let roll = self.config.seed.map_or_else(
+            || self.random.hash_one(attempt),
+            |seed| {
+                // DefaultHasher::new uses fixed keys, so seeded runs repeat
+                let mut hasher = DefaultHasher::new();
+                (seed, attempt).hash(&mut hasher);
+                hasher.finish()
+            },
+        );
+        // High bits decide whether to fail, low bits pick the kind
+        let share = (roll >> 11) as f64 / (1u64 << 53) as f64;
+        if share >= self.config.rate {
+            return None;
+        }
+        let index = (roll % self.config.kinds.len() as u64) as usize;
+        Some(self.config.kinds[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let injection: FailureInjection = "rate=0.05,kind=5xx".parse().unwrap();
+        assert_eq!(injection, FailureInjection::default());
+
+        let injection: FailureInjection = "kind=timeout, kind=corrupt, seed=7".parse().unwrap();
+        assert_eq!(
+            injection.kinds,
+            [InjectedFailure::Timeout, InjectedFailure::Corrupt]
+        );
+        assert_eq!(injection.seed, Some(7));
+        assert_eq!(injection.to_string().parse(), Ok(injection));
+
+        assert!("rate=2".parse::<FailureInjection>().is_err());
+        assert!("kind=dns".parse::<FailureInjection>().is_err());
+        assert!("often".parse::<FailureInjection>().is_err());
+    }
+
+    #[test]
+    fn test_injector_rate_and_seed() {
+        let config = FailureInjection::default()
+            .with_rate(0.25)
+            .with_kinds([InjectedFailure::ServerError, InjectedFailure::Timeout])
+            .with_seed(42);
+        let injector = FailureInjector::new(&config).unwrap();
+        let rolls: Vec<_> = (0..4000).map(|_| injector.next()).collect();
+
+        let failures = rolls.iter().flatten().count();
+        assert!((800..1200).contains(&failures), "{failures} failures");
+        assert!(rolls.contains(&Some(InjectedFailure::Timeout)));
+        assert!(rolls.contains(&Some(InjectedFailure::ServerError)));
+
+        // The same seed repeats the same sequence
+        let again = FailureInjector::new(&config).unwrap();
+        assert!(rolls.iter().all(|roll| *roll == again.next()));
+
+        assert!(FailureInjector::new(&config.with_rate(0.0)).is_none());
+    }
+}
@@ -0,0 +1,284 @@
+//! Circuit breaker shared across concurrent downloads.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Interval at which waiting requests re-check a half-open breaker.
+const PROBE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Configuration for the download circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive server errors required to open the circuit.
+    pub failure_threshold: u32,
+    /// How long all requests are paused once the circuit opens.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Sets the number of consecutive failures that opens the circuit.
+    #[must_use]
+    pub const fn with_failure_threshold(mut self, threshold: u32) -> Self {
+        self.failure_threshold = threshold;
+        self
+    }
+
+    /// Sets the cooldown window.
+    #[must_use]
+    pub const fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+/// Observable state of a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests are paused until the cooldown elapses.
+    Open,
+    /// The cooldown elapsed and a single probe request is allowed through.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    probing: bool,
+    /// Number of probes released so far, identifying the current one.
+    probes: u64,
+}
+
+/// Circuit breaker that pauses all requests after repeated server errors.
+///
+/// Cloning is cheap and clones share state, so every clone of a
+/// [`DownloadClient`](crate::DownloadClient) observes the same circuit.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl CircuitBreaker {
+    /// Creates a new closed circuit breaker.
+    #[must_use]
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Returns the breaker configuration.
+    #[must_use]
+    pub const fn config(&self) -> &CircuitBreakerConfig {
+        &self.config
+    }
+
+    /// Returns the current state of the circuit.
+    #[must_use]
+    pub fn state(&self) -> CircuitState {
+        let inner = self.lock();
+        match inner.open_until {
+            None => CircuitState::Closed,
+            Some(until) if Instant::now() < until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Waits until a request is allowed through the circuit.
+    ///
+    /// While the circuit is open, callers sleep until the cooldown elapses.
+    /// Once it has elapsed, exactly one caller is released as a probe; the
+    /// others keep waiting until the probe succeeds or re-opens the circuit.
+    /// Dropping the probe's permit without recording an outcome, e.g. when
+    /// its download is cancelled, releases the next caller as a probe.
+    pub async fn acquire(&self) -> CircuitPermit<'_> {
+        loop {
+            let wait = {
+                let mut inner = self.lock();
+                match inner.open_until {
+                    None => return CircuitPermit::new(self, None),
+                    Some(until) => {
+                        let now = Instant::now();
+                        if now < until {
+                            until - now
+                        } else if inner.probing {
+                            PROBE_POLL_INTERVAL
+                        } else {
+                            inner.probing = true;
+                            inner.probes += 1;
+                            return CircuitPermit::new(self, Some(inner.probes));
+                        }
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Records a successful request, closing the circuit.
+    pub fn record_success(&self) {
+        let mut inner = self.lock();
+        inner.consecutive_failures = 0;
+        inner.open_until = None;
+        inner.probing = false;
+    }
+
+    /// Records a failed request, opening the circuit if the threshold is hit
+    /// or if the failed request was a half-open probe.
+    pub fn record_failure(&self) {
+        let mut inner = self.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        if inner.probing || inner.consecutive_failures >= self.config.failure_threshold {
+            inner.open_until = Some(Instant::now() + self.config.cooldown);
+            inner.probing = false;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // State is always left consistent, so a poisoned lock is still usable.
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Permission to send one request, returned by [`CircuitBreaker::acquire`].
+///
+/// The outcome of the request is recorded through the permit. A half-open
+/// probe's permit dropped without an outcome gives up the probe, so that
+/// another request can probe the circuit instead.
+#[derive(Debug)]
+#[must_use = "a dropped probe permit gives up the probe"]
+pub struct CircuitPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    /// Number of the probe this permit was released as, if any.
+    probe: Option<u64>,
+}
+
+impl<'a> CircuitPermit<'a> {
+    const fn new(breaker: &'a CircuitBreaker, probe: Option<u64>) -> Self {
+        Self { breaker, probe }
+    }
+
+    /// Returns whether the request is the half-open probe.
+    #[must_use]
+    pub const fn is_probe(&self) -> bool {
+        self.probe.is_some()
+    }
+
+    /// Records that the request succeeded, closing the circuit.
+    pub fn record_success(mut self) {
+        self.probe = None;
+        self.breaker.record_success();
+    }
+
+    /// Records that the request failed, see
+    /// [`CircuitBreaker::record_failure`].
+    pub fn record_failure(mut self) {
+        self.probe = None;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(probe) = self.probe {
+            let mut inner = self.breaker.lock();
+            // A later probe may have been released in the meantime
+            if inner.probes == probe {
+                inner.probing = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(threshold: u32, cooldown_ms: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            CircuitBreakerConfig::default()
+                .with_failure_threshold(threshold)
+                .with_cooldown(Duration::from_millis(cooldown_ms)),
+        )
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let cb = breaker(3, 60_000);
+        cb.record_failure();
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let cb = breaker(2, 60_000);
+        cb.record_failure();
+        cb.record_success();
+        cb.record_failure();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let cb = breaker(1, 60_000);
+        let clone = cb.clone();
+        clone.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe() {
+        let cb = breaker(1, 20);
+        cb.record_failure();
+
+        let start = Instant::now();
+        let probe = cb.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(probe.is_probe());
+        assert_eq!(cb.state(), CircuitState::HalfOpen);
+
+        // A failed probe re-opens the circuit immediately.
+        probe.record_failure();
+        assert_eq!(cb.state(), CircuitState::Open);
+
+        cb.acquire().await.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+        assert!(!cb.acquire().await.is_probe());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_is_released() {
+        let cb = breaker(1, 20);
+        cb.record_failure();
+
+        let probe = cb.acquire().await;
+        assert!(probe.is_probe());
+        drop(probe);
+
+        // Without releasing the probe, this would wait forever
+        let probe = tokio::time::timeout(Duration::from_millis(500), cb.acquire())
+            .await
+            .expect("dropped probe was not released");
+        assert!(probe.is_probe());
+        probe.record_success();
+        assert_eq!(cb.state(), CircuitState::Closed);
+    }
+}
@@ -1,9 +1,28 @@
 //! HTTP client for downloading bi5 files.
 
+use crate::cache::{CacheConfig, DiskCache};
+use crate::chaos::{FailureInjection, FailureInjector, InjectedFailure};
+use crate::circuit::{CircuitBreaker, CircuitBreakerConfig, CircuitPermit};
+use crate::conditional::{ConditionalDownload, Validators};
+use crate::dns::{DnsResolver, override_addrs};
+use crate::headers::{BasicAuth, HeaderHook, UserAgentPool};
+use crate::latency::{SlowRequest, SlowRequestHook};
+use crate::rate::RateLimiter;
+use crate::retry::{FailureKind, RetryPolicy, parse_retry_after};
+use crate::source::{DataSource, read_local};
+use crate::stats::DownloadStats;
+use crate::tls::{self, TlsBackend};
+use crate::trace;
 use bytes::Bytes;
-use reqwest::Client;
-use std::time::Duration;
+use reqwest::header::{HeaderMap, RETRY_AFTER, USER_AGENT};
+use reqwest::{Certificate, Client, StatusCode, Version};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::sync::Semaphore;
 
 /// Configuration for the download client.
 #[derive(Debug, Clone)]
@@ -12,14 +31,98 @@ pub struct ClientConfig {
     pub concurrency: usize,
     /// Request timeout.
     pub timeout: Duration,
-    /// Maximum retry attempts for failed requests.
-    pub max_retries: u32,
-    /// Base delay for exponential backoff (in milliseconds).
-    pub base_delay_ms: u64,
-    /// Maximum delay between retries (in milliseconds).
-    pub max_delay_ms: u64,
+    /// Retry policy for failed requests.
+    pub retry: RetryPolicy,
     /// User agent string.
     pub user_agent: String,
+    /// User agents to rotate through, one per request.
+    ///
+    /// When non-empty, replaces [`Self::user_agent`].
+    pub user_agents: Vec<String>,
+    /// Headers sent with every HTTP request.
+    pub headers: HeaderMap,
+    /// Basic authentication sent with every HTTP request.
+    ///
+    /// Replaces an `Authorization` header in [`Self::headers`].
+    pub basic_auth: Option<BasicAuth>,
+    /// Callback adding headers to each request, run before every attempt.
+    pub header_hook: Option<HeaderHook>,
+    /// Fixed addresses for host names, bypassing DNS.
+    ///
+    /// Keys are host names such as [`url::HOST`](crate::url::HOST); requests
+    /// to a host connect to its addresses in order.
+    pub dns_overrides: HashMap<String, Vec<IpAddr>>,
+    /// Resolver for host names without an override, or `None` for the
+    /// system resolver.
+    pub dns_resolver: Option<DnsResolver>,
+    /// TLS implementation for HTTPS connections.
+    pub tls_backend: TlsBackend,
+    /// Extra CA certificates to trust, e.g. that of a TLS-intercepting proxy.
+    ///
+    /// See [`load_certificates`](crate::load_certificates).
+    pub root_certificates: Vec<Certificate>,
+    /// Trust the backend's built-in root certificates.
+    ///
+    /// Disable to trust only [`Self::root_certificates`].
+    pub tls_built_in_roots: bool,
+    /// Rate limiter to respect, or `None` for no limit.
+    ///
+    /// Clients given clones of the same limiter share one budget.
+    pub rate_limiter: Option<RateLimiter>,
+    /// Circuit breaker settings, or `None` to disable the breaker.
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// On-disk cache of raw bi5 files, or `None` to always download.
+    pub cache: Option<CacheConfig>,
+    /// Where bi5 files are read from.
+    ///
+    /// With [`DataSource::Local`] no HTTP requests are made and the cache,
+    /// retry policy and circuit breaker are not used.
+    pub source: DataSource,
+    /// Negotiate HTTP/2 with the server, multiplexing requests as streams
+    /// over shared connections.
+    ///
+    /// When disabled, HTTP/1.1 is used and every in-flight request occupies
+    /// its own connection.
+    pub http2: bool,
+    /// Maximum in-flight requests per connection when HTTP/2 is used.
+    pub max_streams_per_connection: usize,
+    /// Maximum number of TCP connections, or `None` for no limit.
+    ///
+    /// Over HTTP/1.1 every in-flight request holds its own connection, so
+    /// at most this many requests are in flight, and at most this many
+    /// connections are kept idle. With [`Self::http2`], the limit is raised
+    /// to [`Self::max_in_flight`] once a response shows the server speaks
+    /// HTTP/2, which multiplexes requests over a shared connection; a server
+    /// answering over HTTP/1.1 keeps the connection limit. Requests beyond
+    /// the limit wait for a free slot.
+    pub max_connections: Option<usize>,
+    /// Failures injected into HTTP request attempts, for testing recovery
+    /// paths during development. Leave `None` for real downloads.
+    pub failure_injection: Option<FailureInjection>,
+    /// HTTP attempts taking at least this long are counted as slow and
+    /// reported, or `None` to not look for slow requests.
+    pub slow_request_threshold: Option<Duration>,
+    /// Callback notified of every slow attempt.
+    pub slow_request_hook: Option<SlowRequestHook>,
+}
+
+impl ClientConfig {
+    /// Returns the maximum number of requests in flight at once, if limited.
+    ///
+    /// This is the connection limit times the streams each connection can
+    /// carry: one for HTTP/1.1, [`Self::max_streams_per_connection`] for
+    /// HTTP/2. Until the server is seen to speak HTTP/2, only
+    /// [`Self::max_connections`] requests are let through.
+    #[must_use]
+    pub const fn max_in_flight(&self) -> Option<usize> {
+        match self.max_connections {
+            Some(connections) if self.http2 => {
+                Some(connections.saturating_mul(self.max_streams_per_connection))
+            }
+            Some(connections) => Some(connections),
+            None => None,
+        }
+    }
 }
 
 impl Default for ClientConfig {
@@ -27,10 +130,27 @@ impl Default for ClientConfig {
         Self {
             concurrency: 10, // Lower concurrency to avoid overwhelming the server
             timeout: Duration::from_secs(60),
-            max_retries: 10,      // More retries for transient failures
-            base_delay_ms: 500,   // Start with 500ms delay
-            max_delay_ms: 30_000, // Max 30 seconds between retries
+            retry: RetryPolicy::default(),
             user_agent: format!("paracas/{}", env!("CARGO_PKG_VERSION")),
+            user_agents: Vec::new(),
+            headers: HeaderMap::new(),
+            basic_auth: None,
+            header_hook: None,
+            dns_overrides: HashMap::new(),
+            dns_resolver: None,
+            tls_backend: TlsBackend::Auto,
+            root_certificates: Vec::new(),
+            tls_built_in_roots: true,
+            rate_limiter: None,
+            circuit_breaker: Some(CircuitBreakerConfig::default()),
+            cache: None,
+            source: DataSource::Http,
+            http2: false,
+            max_streams_per_connection: 100,
+            max_connections: None,
+            failure_injection: None,
+            slow_request_threshold: None,
+            slow_request_hook: None,
         }
     }
 }
@@ -47,18 +167,57 @@ pub enum DownloadError {
     Timeout(u32),
 
     /// Server returned an error status.
-    #[error("Server error: {status}")]
+    #[error("Server error: {status} after {attempts} attempts")]
     ServerError {
         /// HTTP status code.
         status: u16,
+        /// Number of requests sent.
+        attempts: u32,
     },
+
+    /// The connection failed or the response could not be read.
+    #[error("Connection failed after {attempts} attempts: {source}")]
+    Connection {
+        /// Number of requests sent.
+        attempts: u32,
+        /// The error of the last attempt.
+        source: reqwest::Error,
+    },
+
+    /// Reading from a local data source failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl DownloadError {
+    /// Returns the number of requests sent before giving up.
+    ///
+    /// Errors raised without a retry loop, such as local I/O failures, count
+    /// as a single attempt.
+    #[must_use]
+    pub const fn attempts(&self) -> u32 {
+        match self {
+            Self::Timeout(attempts)
+            | Self::ServerError { attempts, .. }
+            | Self::Connection { attempts, .. } => *attempts,
+            Self::Http(_) | Self::Io(_) => 1,
+        }
+    }
 }
 
 /// HTTP client with connection pooling and retry logic.
+///
+/// Clones share the connection pool, circuit breaker and statistics.
 #[derive(Debug, Clone)]
 pub struct DownloadClient {
     client: Client,
     config: ClientConfig,
+    breaker: Option<CircuitBreaker>,
+    stats: DownloadStats,
+    cache: Option<DiskCache>,
+    slots: Option<Arc<RequestSlots>>,
+    user_agents: Option<UserAgentPool>,
+    failures: Option<FailureInjector>,
 }
 
 impl DownloadClient {
@@ -68,9 +227,9 @@ impl DownloadClient {
     ///
     /// Returns an error if the HTTP client cannot be created.
     pub fn new(config: ClientConfig) -> Result<Self, reqwest::Error> {
-        let client = Client::builder()
-            // Connection pooling - maintain up to concurrency idle connections per host
-            .pool_max_idle_per_host(config.concurrency)
+        let mut builder = Client::builder()
+            // Connection pooling - keep every allowed connection idle for reuse
+            .pool_max_idle_per_host(config.max_connections.unwrap_or(config.concurrency))
             // Keep connections alive for reuse (Dukascopy supports persistent connections)
             .pool_idle_timeout(Duration::from_secs(90))
             // Disable Nagle's algorithm for lower latency
@@ -82,9 +241,48 @@ impl DownloadClient {
             // Connection timeout (separate from request timeout)
             .connect_timeout(Duration::from_secs(10))
             .user_agent(&config.user_agent)
-            .gzip(true)
-            .build()?;
-        Ok(Self { client, config })
+            .default_headers(config.headers.clone())
+            .gzip(true);
+        builder = if config.http2 {
+            // Larger flow-control windows keep multiplexed downloads from stalling
+            builder.http2_adaptive_window(true)
+        } else {
+            builder.http1_only()
+        };
+        for (host, ips) in &config.dns_overrides {
+            builder = builder.resolve_to_addrs(host, &override_addrs(ips));
+        }
+        if let Some(resolver) = &config.dns_resolver {
+            builder = builder.dns_resolver2(resolver.resolver());
+        }
+        builder = tls::configure(
+            builder,
+            config.tls_backend,
+            &config.root_certificates,
+            config.tls_built_in_roots,
+        );
+        let client = builder.build()?;
+        let breaker = config.circuit_breaker.map(CircuitBreaker::new);
+        let cache = config.cache.clone().map(DiskCache::new);
+        let slots = config
+            .max_connections
+            .zip(config.max_in_flight())
+            .map(|(connections, in_flight)| Arc::new(RequestSlots::new(connections, in_flight)));
+        let user_agents = UserAgentPool::new(&config.user_agents);
+        let failures = config
+            .failure_injection
+            .as_ref()
+            .and_then(FailureInjector::new);
+        Ok(Self {
+            client,
+            config,
+            breaker,
+            stats: DownloadStats::new(),
+            cache,
+            slots,
+            user_agents,
+            failures,
+        })
     }
 
     /// Creates a client with default configuration.
@@ -102,89 +300,454 @@ impl DownloadClient {
         &self.config
     }
 
+    /// Returns the statistics accumulator shared by all clones of this client.
+    #[must_use]
+    pub const fn stats(&self) -> &DownloadStats {
+        &self.stats
+    }
+
+    /// Returns the on-disk cache, if enabled.
+    #[must_use]
+    pub const fn cache(&self) -> Option<&DiskCache> {
+        self.cache.as_ref()
+    }
+
+    /// Returns the shared circuit breaker, if enabled.
+    #[must_use]
+    pub const fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.breaker.as_ref()
+    }
+
     /// Downloads a single bi5 file, returning the compressed bytes.
     ///
     /// Returns `Ok(None)` if the file does not exist (404).
     ///
+    /// When a cache is configured, fresh entries are served from disk and
+    /// successful downloads are stored.
+    ///
+    /// While the circuit breaker is open, attempts wait for the cooldown
+    /// instead of consuming the retry budget.
+    ///
+    /// With a [`DataSource::Local`] source the file is read from disk instead.
+    ///
     /// # Errors
     ///
     /// Returns an error if the download fails after all retries.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "download",
+            level = "debug",
+            skip(self),
+            fields(
+                bytes = tracing::field::Empty,
+                retries = tracing::field::Empty,
+                cache_hit = tracing::field::Empty
+            )
+        )
+    )]
     pub async fn download(&self, url: &str) -> Result<Option<Bytes>, DownloadError> {
-        let mut attempts = 0;
+        self.fetch(url, false).await
+    }
+
+    /// Downloads a single bi5 file again, bypassing every cache.
+    ///
+    /// The disk cache is not consulted and the request carries a
+    /// cache-busting query parameter so intermediate CDN caches cannot serve
+    /// the previous copy. The result replaces any cached entry. Used to
+    /// recover from corrupt downloads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the download fails after all retries.
+    pub async fn refetch(&self, url: &str) -> Result<Option<Bytes>, DownloadError> {
+        self.fetch(url, true).await
+    }
+
+    /// Serves `url` from the configured source, optionally bypassing caches.
+    async fn fetch(&self, url: &str, bust_cache: bool) -> Result<Option<Bytes>, DownloadError> {
+        if let DataSource::Local(dir) = &self.config.source {
+            return self.read_local(dir, url).await;
+        }
+
+        if !bust_cache
+            && let Some(cache) = &self.cache
+            && let Some(cached) = cache.get(url).await
+        {
+            self.stats.record_cache_hit();
+            trace::record("cache_hit", 1);
+            return Ok(cached);
+        }
+
+        let request_url = if bust_cache {
+            cache_busting_url(url)
+        } else {
+            url.to_string()
+        };
+        self.stats.record_start();
+        let result = self
+            .download_with_retries(&request_url, None)
+            .await
+            .map(ConditionalDownload::into_data);
+        self.stats.record_end();
+
+        match &result {
+            Ok(Some(bytes)) => {
+                self.stats.record_bytes(bytes.len());
+                trace::record("bytes", bytes.len() as u64);
+            }
+            Ok(None) => self.stats.record_not_found(),
+            Err(_) => self.stats.record_failure(),
+        }
+
+        if let (Some(cache), Ok(data)) = (&self.cache, &result) {
+            // A failed cache write only costs a re-download next time.
+            if let Ok(Some(_)) = cache.put(url, data.as_ref()).await {
+                self.stats.record_revision();
+            }
+        }
+        result
+    }
+
+    /// Downloads a single bi5 file unless it is unchanged since `validators`
+    /// were issued.
+    ///
+    /// Sends `If-None-Match` and `If-Modified-Since` from `validators`, so an
+    /// unchanged file costs a `304 Not Modified`. Meant for polling the
+    /// newest hour while it is being published: keep the validators of each
+    /// [`ConditionalDownload::Modified`] result for the next poll. Empty
+    /// validators always download the file.
+    ///
+    /// The disk cache is neither consulted nor updated. A
+    /// [`DataSource::Local`] source has no validators, so its files are
+    /// always reported as modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the download fails after all retries.
+    pub async fn download_if_changed(
+        &self,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<ConditionalDownload, DownloadError> {
+        if let DataSource::Local(dir) = &self.config.source {
+            let data = self.read_local(dir, url).await?;
+            return Ok(data.map_or(ConditionalDownload::NotFound, |data| {
+                ConditionalDownload::Modified {
+                    data,
+                    validators: Validators::default(),
+                }
+            }));
+        }
+
+        self.stats.record_start();
+        let result = self.download_with_retries(url, Some(validators)).await;
+        self.stats.record_end();
+
+        match &result {
+            Ok(ConditionalDownload::Modified { data, .. }) => {
+                self.stats.record_bytes(data.len());
+                trace::record("bytes", data.len() as u64);
+            }
+            Ok(ConditionalDownload::NotFound) => self.stats.record_not_found(),
+            Ok(ConditionalDownload::NotModified) => {}
+            Err(_) => self.stats.record_failure(),
+        }
+        result
+    }
+
+    /// Reads a single file from a local data source.
+    async fn read_local(
+        &self,
+        dir: &std::path::Path,
+        url: &str,
+    ) -> Result<Option<Bytes>, DownloadError> {
+        self.stats.record_start();
+        let result = read_local(dir, url).await;
+        self.stats.record_end();
+
+        match &result {
+            Ok(Some(bytes)) => {
+                self.stats.record_bytes(bytes.len());
+                trace::record("bytes", bytes.len() as u64);
+            }
+            Ok(None) => self.stats.record_not_found(),
+            Err(_) => self.stats.record_failure(),
+        }
+        Ok(result?)
+    }
+
+    /// Runs the request/retry loop for a single URL, conditionally if
+    /// `validators` are given.
+    async fn download_with_retries(
+        &self,
+        url: &str,
+        validators: Option<&Validators>,
+    ) -> Result<ConditionalDownload, DownloadError> {
+        let policy = &self.config.retry;
+        let mut retries = 0;
 
         loop {
-            match self.client.get(url).send().await {
-                Ok(response) => {
-                    if response.status() == reqwest::StatusCode::NOT_FOUND {
-                        return Ok(None); // No data for this hour
+            let permit = match &self.breaker {
+                Some(breaker) => Some(breaker.acquire().await),
+                None => None,
+            };
+            if let Some(limiter) = &self.config.rate_limiter {
+                limiter.acquire().await;
+            }
+
+            // Held until the body is read or the attempt fails
+            let slot = match &self.slots {
+                Some(slots) => slots.semaphore.acquire().await.ok(),
+                None => None,
+            };
+            self.stats.record_request();
+            let mut retry_after = None;
+            let injected = self.failures.as_ref().and_then(FailureInjector::next);
+            let kind = match injected {
+                Some(InjectedFailure::Corrupt) => {
+                    return Ok(ConditionalDownload::Modified {
+                        data: InjectedFailure::corrupt_body(),
+                        validators: Validators::default(),
+                    });
+                }
+                Some(failure) => {
+                    record_failure(permit);
+                    let kind = failure.failure_kind();
+                    if !policy.should_retry(&kind, retries) {
+                        return Err(failure.error(retries + 1));
                     }
+                    kind
+                }
+                None => {
+                    let started = Instant::now();
+                    let attempt = self.send(url, validators).await;
+                    let status = attempt.as_ref().ok().map(|reply| reply.status.as_u16());
+                    self.observe_latency(url, retries + 1, started.elapsed(), status);
+                    match attempt {
+                        Ok(reply) => {
+                            let status = reply.status;
+                            // Only server errors (5xx) and rate limiting (429) indicate an unhealthy server
+                            let server_failure = status.is_server_error()
+                                || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                            if server_failure {
+                                record_failure(permit);
+                            } else {
+                                record_success(permit);
+                            }
+
+                            if status == reqwest::StatusCode::NOT_FOUND {
+                                return Ok(ConditionalDownload::NotFound); // No data for this hour
+                            }
+                            if status == reqwest::StatusCode::NOT_MODIFIED {
+                                return Ok(ConditionalDownload::NotModified);
+                            }
+                            if let Some(body) = reply.body {
+                                let bytes = body.map_err(|source| DownloadError::Connection {
+                                    attempts: retries + 1,
+                                    source,
+                                })?;
+                                if let Some(limiter) = &self.config.rate_limiter {
+                                    limiter.record_bytes(bytes.len());
+                                }
+                                return Ok(ConditionalDownload::Modified {
+                                    data: bytes,
+                                    validators: reply.validators,
+                                });
+                            }
 
-                    // Retry on server errors (5xx) and rate limiting (429)
-                    if response.status().is_server_error()
-                        || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
-                    {
-                        if attempts < self.config.max_retries {
-                            attempts += 1;
-                            let delay = self.calculate_backoff_delay(attempts);
-                            tokio::time::sleep(delay).await;
-                            continue;
+                            let kind = FailureKind::Status(status.as_u16());
+                            if !policy.should_retry(&kind, retries) {
+                                return Err(DownloadError::ServerError {
+                                    status: status.as_u16(),
+                                    attempts: retries + 1,
+                                });
+                            }
+                            retry_after = reply.retry_after;
+                            kind
+                        }
+                        Err(e) => {
+                            record_failure(permit);
+                            match FailureKind::from_error(&e) {
+                                Some(kind) if policy.should_retry(&kind, retries) => kind,
+                                Some(FailureKind::Timeout) => {
+                                    return Err(DownloadError::Timeout(retries + 1));
+                                }
+                                _ => {
+                                    return Err(DownloadError::Connection {
+                                        attempts: retries + 1,
+                                        source: e,
+                                    });
+                                }
+                            }
                         }
-                        return Err(DownloadError::ServerError {
-                            status: response.status().as_u16(),
-                        });
                     }
-
-                    response.error_for_status_ref()?;
-                    return Ok(Some(response.bytes().await?));
-                }
-                Err(e) if self.is_retryable_error(&e) && attempts < self.config.max_retries => {
-                    attempts += 1;
-                    let delay = self.calculate_backoff_delay(attempts);
-                    tokio::time::sleep(delay).await;
                 }
-                Err(e) => return Err(e.into()),
+            };
+
+            drop(slot);
+            retries += 1;
+            self.stats.record_retry();
+            trace::record("retries", u64::from(retries));
+            let server_delay = policy.server_delay(retry_after);
+            if let Some(delay) = server_delay {
+                self.stats.record_server_delay(delay);
             }
+            let delay = server_delay.unwrap_or_else(|| policy.delay(retries));
+            trace::retry(&kind, retries, delay);
+            tokio::time::sleep(delay).await;
         }
     }
 
-    /// Calculates the backoff delay with exponential backoff and jitter.
-    fn calculate_backoff_delay(&self, attempt: u32) -> Duration {
-        // Exponential backoff: base_delay * 2^attempt
-        let exp_delay = self
-            .config
-            .base_delay_ms
-            .saturating_mul(1u64 << attempt.min(10));
-
-        // Cap at max delay
-        let capped_delay = exp_delay.min(self.config.max_delay_ms);
-
-        // Add jitter (±25%)
-        let jitter_range = capped_delay / 4;
-        let jitter = if jitter_range > 0 {
-            // Simple deterministic jitter based on attempt number
-            // This avoids needing a random number generator
-            let jitter_offset = (attempt as u64 * 17) % (jitter_range * 2);
-            jitter_offset.saturating_sub(jitter_range)
+    /// Sends a request to `url`, reading the body unless the status is an
+    /// error.
+    async fn send(
+        &self,
+        url: &str,
+        validators: Option<&Validators>,
+    ) -> Result<Reply, reqwest::Error> {
+        let mut request = self.request(url);
+        if let Some(validators) = validators {
+            request = validators.apply(request);
+        }
+        let response = request.send().await?;
+        if response.version() == Version::HTTP_2
+            && let Some(slots) = &self.slots
+        {
+            slots.multiplexed();
+        }
+        let status = response.status();
+        let validators = Validators::from_headers(response.headers());
+        let retry_after = if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, chrono::Utc::now()))
         } else {
-            0
+            None
         };
+        let body = if status.is_client_error() || status.is_server_error() {
+            None
+        } else {
+            Some(response.bytes().await)
+        };
+        Ok(Reply {
+            status,
+            retry_after,
+            validators,
+            body,
+        })
+    }
+
+    /// Records the latency of an attempt and reports it if it was slow.
+    fn observe_latency(&self, url: &str, attempt: u32, latency: Duration, status: Option<u16>) {
+        self.stats.record_latency(latency);
+        let Some(threshold) = self.config.slow_request_threshold else {
+            return;
+        };
+        if latency < threshold {
+            return;
+        }
+        self.stats.record_slow_request();
+        let request = SlowRequest {
+            url: url.to_string(),
+            attempt,
+            latency,
+            status,
+        };
+        trace::slow_request(&request);
+        if let Some(hook) = &self.config.slow_request_hook {
+            hook.notify(&request);
+        }
+    }
+
+    /// Builds a GET request with credentials, the rotated user agent and
+    /// hook headers.
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client.get(url);
+        if let Some(auth) = &self.config.basic_auth {
+            request = request.basic_auth(&auth.username, auth.password.as_ref());
+        }
+        if let Some(pool) = &self.user_agents {
+            request = request.header(USER_AGENT, pool.next());
+        }
+        if let Some(hook) = &self.config.header_hook {
+            request = request.headers(hook.headers(url));
+        }
+        request
+    }
+}
+
+/// Records a successful attempt with the circuit breaker, if enabled.
+fn record_success(permit: Option<CircuitPermit<'_>>) {
+    if let Some(permit) = permit {
+        permit.record_success();
+    }
+}
 
-        let final_delay = (capped_delay as i64 + jitter as i64).max(100) as u64;
-        Duration::from_millis(final_delay)
+/// Records a failed attempt with the circuit breaker, if enabled.
+fn record_failure(permit: Option<CircuitPermit<'_>>) {
+    if let Some(permit) = permit {
+        permit.record_failure();
     }
+}
 
-    /// Determines if an error is retryable.
-    fn is_retryable_error(&self, error: &reqwest::Error) -> bool {
-        // Don't retry builder errors (configuration issues)
-        if error.is_builder() {
-            return false;
+/// A response to an HTTP attempt.
+struct Reply {
+    /// Response status.
+    status: StatusCode,
+    /// Delay requested with `Retry-After` on a 429 or 503 response.
+    retry_after: Option<Duration>,
+    /// Cache validators of the response.
+    validators: Validators,
+    /// The body, read unless the status is an error.
+    body: Option<Result<Bytes, reqwest::Error>>,
+}
+
+/// Slots limiting the requests in flight to what the allowed connections
+/// carry.
+#[derive(Debug)]
+struct RequestSlots {
+    semaphore: Semaphore,
+    /// Slots added once the server is seen to multiplex requests over
+    /// HTTP/2, until then held back so HTTP/1.1 cannot exceed the
+    /// connection limit.
+    streams: AtomicUsize,
+}
+
+impl RequestSlots {
+    /// Creates one slot per connection, growing to `in_flight` slots over
+    /// HTTP/2.
+    fn new(connections: usize, in_flight: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(connections),
+            streams: AtomicUsize::new(in_flight.saturating_sub(connections)),
         }
+    }
 
-        // Retry on timeouts, connection errors, and request errors
-        error.is_timeout() || error.is_connect() || error.is_request()
+    /// Releases the slots of HTTP/2 streams, on the first HTTP/2 response.
+    fn multiplexed(&self) {
+        let streams = self.streams.swap(0, Ordering::Relaxed);
+        if streams > 0 {
+            self.semaphore.add_permits(streams);
+        }
     }
 }
 
+/// Appends a unique query parameter to `url`.
+fn cache_busting_url(url: &str) -> String {
+    let nonce = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}nocache={nonce}")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,32 +756,286 @@ mod tests {
     fn test_client_config_default() {
         let config = ClientConfig::default();
         assert_eq!(config.concurrency, 10);
-        assert_eq!(config.max_retries, 10);
+        assert_eq!(config.retry.max_retries, 10);
         assert_eq!(config.timeout, Duration::from_secs(60));
-        assert_eq!(config.base_delay_ms, 500);
-        assert_eq!(config.max_delay_ms, 30_000);
+        assert_eq!(config.retry.base_delay, Duration::from_millis(500));
+        assert_eq!(config.retry.max_delay, Duration::from_secs(30));
+        assert_eq!(
+            config.circuit_breaker,
+            Some(CircuitBreakerConfig::default())
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker_disabled() {
+        let config = ClientConfig {
+            circuit_breaker: None,
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        assert!(client.circuit_breaker().is_none());
+    }
+
+    #[test]
+    fn test_max_in_flight() {
+        let mut config = ClientConfig::default();
+        assert_eq!(config.max_in_flight(), None);
+
+        config.max_connections = Some(4);
+        assert_eq!(config.max_in_flight(), Some(4));
+
+        config.http2 = true;
+        config.max_streams_per_connection = 50;
+        assert_eq!(config.max_in_flight(), Some(200));
+
+        // HTTP/1.1 keeps the connection limit until HTTP/2 is seen
+        let slots = DownloadClient::new(config).unwrap().slots.unwrap();
+        assert_eq!(slots.semaphore.available_permits(), 4);
+        slots.multiplexed();
+        slots.multiplexed();
+        assert_eq!(slots.semaphore.available_permits(), 200);
+    }
+
+    #[test]
+    fn test_request_headers() {
+        let config = ClientConfig {
+            user_agents: vec!["agent-a".to_string(), "agent-b".to_string()],
+            header_hook: Some(HeaderHook::new(|_, headers| {
+                headers.insert("x-token", reqwest::header::HeaderValue::from_static("t"));
+            })),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+
+        let agents: Vec<_> = (0..3)
+            .map(|_| {
+                let request = client.request("https://example.com").build().unwrap();
+                assert_eq!(request.headers()["x-token"], "t");
+                request.headers()[USER_AGENT].clone()
+            })
+            .collect();
+        assert_eq!(agents, ["agent-a", "agent-b", "agent-a"]);
+    }
+
+    #[test]
+    fn test_basic_auth_header() {
+        let config = ClientConfig {
+            basic_auth: Some(BasicAuth::new("user", Some("pass".to_string()))),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let request = client.request("https://example.com").build().unwrap();
+        // base64("user:pass")
+        assert_eq!(
+            request.headers()[reqwest::header::AUTHORIZATION],
+            "Basic dXNlcjpwYXNz"
+        );
     }
 
     #[tokio::test]
-    async fn test_client_creation() {
-        let client = DownloadClient::with_defaults();
-        assert!(client.is_ok());
+    async fn test_local_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("EURUSD/2024/00/15/12h_ticks.bi5");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"raw").unwrap();
+
+        let config = ClientConfig {
+            source: DataSource::local(dir.path()),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let base = crate::url::BASE_URL;
+
+        let data = client
+            .download(&format!("{base}/EURUSD/2024/00/15/12h_ticks.bi5"))
+            .await
+            .unwrap();
+        assert_eq!(data.as_deref(), Some(&b"raw"[..]));
+        let missing = client
+            .download(&format!("{base}/EURUSD/2024/00/15/13h_ticks.bi5"))
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        let stats = client.stats().snapshot();
+        assert_eq!(stats.requests, 0);
+        assert_eq!(stats.bytes_fetched, 3);
+    }
+
+    #[tokio::test]
+    async fn test_dns_overrides_and_resolver() {
+        use reqwest::dns::{Name, Resolving};
+        use std::net::{Ipv4Addr, SocketAddr};
+        use std::sync::Mutex;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl reqwest::dns::Resolve for Recorder {
+            fn resolve(&self, name: Name) -> Resolving {
+                self.0.lock().unwrap().push(name.as_str().to_string());
+                let addrs = vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 0))];
+                Box::pin(async move { Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs) })
+            }
+        }
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+
+        let config = ClientConfig {
+            dns_overrides: HashMap::from([(
+                "pinned.paracas.test".to_string(),
+                vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            )]),
+            dns_resolver: Some(DnsResolver::new(Recorder(Arc::clone(&resolved)))),
+            circuit_breaker: None,
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+
+        for host in ["pinned.paracas.test", "resolved.paracas.test"] {
+            let url = format!("http://{host}:{port}/a.bi5");
+            assert!(client.download(&url).await.unwrap().is_none());
+        }
+        assert_eq!(*resolved.lock().unwrap(), ["resolved.paracas.test"]);
     }
 
     #[test]
-    fn test_backoff_delay_calculation() {
-        let client = DownloadClient::with_defaults().unwrap();
+    fn test_cache_busting_url() {
+        let url = cache_busting_url("https://example.com/a.bi5");
+        assert!(url.starts_with("https://example.com/a.bi5?nocache="));
+        let url = cache_busting_url("https://example.com/a.bi5?x=1");
+        assert!(url.contains("?x=1&nocache="));
+    }
+
+    #[tokio::test]
+    async fn test_injected_failures_are_retried() {
+        use crate::chaos::{FailureInjection, InjectedFailure};
+
+        let injection = FailureInjection::default().with_rate(1.0);
+        let config = ClientConfig {
+            retry: RetryPolicy::default()
+                .with_max_retries(2)
+                .with_delays(Duration::from_millis(1), Duration::from_millis(1)),
+            circuit_breaker: None,
+            failure_injection: Some(injection.clone()),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+
+        // Nothing is sent, so the unroutable host is never contacted
+        let url = "http://paracas.invalid/a.bi5";
+        let error = client.download(url).await.unwrap_err();
+        assert!(matches!(
+            error,
+            DownloadError::ServerError {
+                status: 503,
+                attempts: 3
+            }
+        ));
+        assert_eq!(client.stats().snapshot().retries, 2);
+
+        let config = ClientConfig {
+            failure_injection: Some(injection.with_kinds([InjectedFailure::Corrupt])),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let body = client.download(url).await.unwrap().unwrap();
+        assert!(crate::decompress_bi5(&body).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_replaces_backoff() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket
+                    .write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nretry-after: 120\r\n\
+                          content-length: 0\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+
+        let config = ClientConfig {
+            retry: RetryPolicy::default()
+                .with_max_retries(2)
+                .with_max_retry_after(Duration::from_millis(5)),
+            circuit_breaker: None,
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let url = format!("http://127.0.0.1:{port}/a.bi5");
+        let error = client.download(&url).await.unwrap_err();
+        assert!(matches!(
+            error,
+            DownloadError::ServerError {
+                status: 503,
+                attempts: 3
+            }
+        ));
+
+        // Both retries waited the capped server delay, not the 1s+ backoff
+        let stats = client.stats().snapshot();
+        assert_eq!(stats.server_delays, 2);
+        assert_eq!(stats.server_delay_time, Duration::from_millis(10));
+    }
+
+    #[tokio::test]
+    async fn test_download_if_changed() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-        // First attempt: base_delay * 2 = 1000ms (plus jitter)
-        let delay1 = client.calculate_backoff_delay(1);
-        assert!(delay1.as_millis() >= 750 && delay1.as_millis() <= 1250);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0; 1024];
+                let read = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..read]).to_lowercase();
+                let response: &[u8] = if request.contains("if-none-match: \"v1\"") {
+                    b"HTTP/1.1 304 Not Modified\r\netag: \"v1\"\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 3\r\n\r\nabc"
+                };
+                let _ = socket.write_all(response).await;
+            }
+        });
+
+        let client = DownloadClient::with_defaults().unwrap();
+        let url = format!("http://127.0.0.1:{port}/a.bi5");
+        let first = client
+            .download_if_changed(&url, &Validators::default())
+            .await
+            .unwrap();
+        let ConditionalDownload::Modified { data, validators } = first else {
+            panic!("expected a download, got {first:?}");
+        };
+        assert_eq!(&data[..], b"abc");
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
 
-        // Second attempt: base_delay * 4 = 2000ms (plus jitter)
-        let delay2 = client.calculate_backoff_delay(2);
-        assert!(delay2.as_millis() >= 1500 && delay2.as_millis() <= 2500);
+        let second = client.download_if_changed(&url, &validators).await.unwrap();
+        assert_eq!(second, ConditionalDownload::NotModified);
+        assert_eq!(client.stats().snapshot().bytes_fetched, 3);
+    }
 
-        // High attempt should be capped at max_delay
-        let delay_high = client.calculate_backoff_delay(20);
-        assert!(delay_high.as_millis() <= 37500); // max_delay + 25% jitter
+    #[tokio::test]
+    async fn test_client_creation() {
+        let client = DownloadClient::with_defaults();
+        assert!(client.is_ok());
     }
 }
@@ -0,0 +1,103 @@
+//! Conditional requests for polling files that may still grow.
+//!
+//! The newest hour of an instrument is published while it fills up. A
+//! poller keeps the [`Validators`] of the last copy it downloaded and passes
+//! them to [`DownloadClient::download_if_changed`](crate::DownloadClient::download_if_changed),
+//! which sends `If-None-Match` and `If-Modified-Since` so an unchanged file
+//! costs a `304 Not Modified` instead of a full download.
+
+use bytes::Bytes;
+use reqwest::header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+/// Cache validators identifying a downloaded copy of a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// The `ETag` header of the response.
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the response.
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Returns true if neither validator is known, in which case a
+    /// conditional request always downloads the file.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Reads the validators of a response.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Adds the conditional request headers for these validators.
+    pub(crate) fn apply(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// Result of a conditional download.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionalDownload {
+    /// The file has not changed since the validators were issued.
+    NotModified,
+    /// The file does not exist (404).
+    NotFound,
+    /// The file is new or changed.
+    Modified {
+        /// The compressed bytes of the file.
+        data: Bytes,
+        /// Validators of this copy, for the next poll.
+        validators: Validators,
+    },
+}
+
+impl ConditionalDownload {
+    /// Returns the downloaded bytes, or `None` if nothing was downloaded.
+    #[must_use]
+    pub fn into_data(self) -> Option<Bytes> {
+        match self {
+            Self::Modified { data, .. } => Some(data),
+            Self::NotModified | Self::NotFound => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_validators_round_trip() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+        let validators = Validators::from_headers(&headers);
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert!(validators.last_modified.is_none());
+        assert!(Validators::default().is_empty());
+
+        let request = validators
+            .apply(reqwest::Client::new().get("https://example.com"))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[IF_NONE_MATCH], "\"v1\"");
+        assert!(!request.headers().contains_key(IF_MODIFIED_SINCE));
+    }
+}
@@ -1,9 +1,13 @@
 //! LZMA decompression for bi5 files.
 
-use lzma_rs::lzma_decompress;
-use std::io::{BufReader, Cursor};
+use lzma_rs::decompress::Options;
+use lzma_rs::{lzma_decompress, lzma_decompress_with_options};
+use paracas_types::RawTick;
+use std::io::{self, BufReader, Cursor, Write};
 use thiserror::Error;
 
+use crate::parse::{ParseError, parse_single_tick};
+
 /// Errors that can occur during decompression.
 #[derive(Error, Debug)]
 pub enum DecompressError {
@@ -14,6 +18,10 @@ pub enum DecompressError {
     /// Empty input data.
     #[error("Empty input data")]
     EmptyInput,
+
+    /// The decompressed data is not a whole number of tick records.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
 }
 
 /// Decompresses LZMA-compressed bi5 data.
@@ -46,6 +54,106 @@ pub fn decompress_bi5(compressed: &[u8]) -> Result<Vec<u8>, DecompressError> {
     Ok(decompressed)
 }
 
+/// Decompresses bi5 data and parses it into ticks as it is decoded.
+///
+/// Unlike [`decompress_bi5`] followed by [`parse_ticks`](crate::parse_ticks),
+/// the decompressed bytes are never held in a separate buffer: records are
+/// handed to `on_tick` as soon as the decoder flushes them. Memory is bounded
+/// by the LZMA dictionary window, which `memory_limit` caps in bytes; data
+/// needing a larger window fails to decompress.
+///
+/// Returns the number of ticks decoded.
+///
+/// # Errors
+///
+/// Returns an error if decompression fails, the memory limit is exceeded, or
+/// the output ends with a partial record.
+pub fn decompress_ticks(
+    compressed: &[u8],
+    memory_limit: Option<usize>,
+    on_tick: impl FnMut(RawTick),
+) -> Result<usize, DecompressError> {
+    if compressed.is_empty() {
+        return Err(DecompressError::EmptyInput);
+    }
+
+    let options = Options {
+        memlimit: memory_limit,
+        ..Options::default()
+    };
+    let mut reader = BufReader::new(Cursor::new(compressed));
+    let mut sink = TickSink::new(on_tick);
+
+    lzma_decompress_with_options(&mut reader, &mut sink, &options)
+        .map_err(|e| DecompressError::LzmaError(e.to_string()))?;
+    sink.finish()
+}
+
+/// Writer that splits decompressed bytes into tick records.
+struct TickSink<F> {
+    on_tick: F,
+    partial: [u8; RawTick::SIZE],
+    partial_len: usize,
+    ticks: usize,
+}
+
+impl<F: FnMut(RawTick)> TickSink<F> {
+    const fn new(on_tick: F) -> Self {
+        Self {
+            on_tick,
+            partial: [0; RawTick::SIZE],
+            partial_len: 0,
+            ticks: 0,
+        }
+    }
+
+    fn emit(&mut self, record: &[u8]) {
+        (self.on_tick)(parse_single_tick(record));
+        self.ticks += 1;
+    }
+
+    fn finish(self) -> Result<usize, DecompressError> {
+        if self.partial_len > 0 {
+            let len = self.ticks * RawTick::SIZE + self.partial_len;
+            return Err(ParseError::InvalidLength(len, RawTick::SIZE).into());
+        }
+        Ok(self.ticks)
+    }
+}
+
+impl<F: FnMut(RawTick)> Write for TickSink<F> {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let written = buf.len();
+
+        if self.partial_len > 0 {
+            let take = (RawTick::SIZE - self.partial_len).min(buf.len());
+            self.partial[self.partial_len..self.partial_len + take].copy_from_slice(&buf[..take]);
+            self.partial_len += take;
+            buf = &buf[take..];
+            if self.partial_len < RawTick::SIZE {
+                return Ok(written);
+            }
+            let record = self.partial;
+            self.emit(&record);
+            self.partial_len = 0;
+        }
+
+        let mut records = buf.chunks_exact(RawTick::SIZE);
+        for record in &mut records {
+            self.emit(record);
+        }
+        let rest = records.remainder();
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -61,4 +169,63 @@ mod tests {
         let result = decompress_bi5(&[0x00, 0x01, 0x02, 0x03]);
         assert!(matches!(result, Err(DecompressError::LzmaError(_))));
     }
+
+    #[test]
+    fn test_tick_sink_reassembles_records() {
+        let mut record = vec![0u8; RawTick::SIZE];
+        record[3] = 7; // ms_offset = 7
+        let data = [record.clone(), record].concat();
+
+        let mut offsets = Vec::new();
+        let mut sink = TickSink::new(|tick: RawTick| offsets.push(tick.ms_offset));
+        // Split across writes at an offset that is not a record boundary
+        sink.write_all(&data[..13]).unwrap();
+        sink.write_all(&data[13..30]).unwrap();
+        sink.write_all(&data[30..]).unwrap();
+        assert_eq!(sink.finish().unwrap(), 2);
+        assert_eq!(offsets, vec![7, 7]);
+    }
+
+    #[test]
+    fn test_tick_sink_partial_record() {
+        let mut sink = TickSink::new(|_| {});
+        sink.write_all(&[0u8; 25]).unwrap();
+        assert!(matches!(
+            sink.finish(),
+            Err(DecompressError::Parse(ParseError::InvalidLength(25, 20)))
+        ));
+    }
+
+    #[test]
+    fn test_decompress_ticks_round_trip() {
+        let mut raw = Vec::new();
+        for ms in 0..1000u32 {
+            let mut record = [0u8; RawTick::SIZE];
+            record[..4].copy_from_slice(&ms.to_be_bytes());
+            raw.extend_from_slice(&record);
+        }
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress(&mut Cursor::new(&raw), &mut compressed).unwrap();
+
+        let mut offsets = Vec::new();
+        let count = decompress_ticks(&compressed, None, |t| offsets.push(t.ms_offset)).unwrap();
+        assert_eq!(count, 1000);
+        assert_eq!(offsets, (0..1000).collect::<Vec<_>>());
+        assert_eq!(decompress_bi5(&compressed).unwrap(), raw);
+
+        let limited = decompress_ticks(&compressed, Some(64), |_| {});
+        assert!(matches!(limited, Err(DecompressError::LzmaError(_))));
+    }
+
+    #[test]
+    fn test_decompress_ticks_invalid() {
+        assert!(matches!(
+            decompress_ticks(&[], None, |_| {}),
+            Err(DecompressError::EmptyInput)
+        ));
+        assert!(matches!(
+            decompress_ticks(&[0x00, 0x01, 0x02, 0x03], None, |_| {}),
+            Err(DecompressError::LzmaError(_))
+        ));
+    }
 }
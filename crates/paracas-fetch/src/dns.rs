@@ -0,0 +1,47 @@
+//! DNS resolution overrides.
+//!
+//! [`ClientConfig::dns_overrides`](crate::ClientConfig::dns_overrides) pins
+//! host names to fixed addresses, for networks with broken DNS or to stick to
+//! a known-good edge node of the data feed. Other host names are resolved by
+//! the system resolver, or by a custom [`DnsResolver`].
+
+use reqwest::dns::Resolve;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+/// Custom DNS resolver for host names without an override.
+///
+/// Wraps any [`Resolve`] implementation. Clones share the same resolver.
+#[derive(Clone)]
+pub struct DnsResolver {
+    resolver: Arc<dyn Resolve>,
+}
+
+impl DnsResolver {
+    /// Creates a resolver from a [`Resolve`] implementation.
+    pub fn new(resolver: impl Resolve + 'static) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+        }
+    }
+
+    /// Returns the wrapped resolver.
+    pub(crate) fn resolver(&self) -> Arc<dyn Resolve> {
+        Arc::clone(&self.resolver)
+    }
+}
+
+impl fmt::Debug for DnsResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsResolver").finish_non_exhaustive()
+    }
+}
+
+/// Returns socket addresses for override IPs.
+///
+/// The port is left at zero so requests keep the port of their URL, or the
+/// scheme's default.
+pub(crate) fn override_addrs(ips: &[IpAddr]) -> Vec<SocketAddr> {
+    ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect()
+}
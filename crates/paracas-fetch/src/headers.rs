@@ -0,0 +1,143 @@
+//! Request header customization.
+//!
+//! Static headers, [`BasicAuth`] credentials and a user-agent pool are set
+//! on [`ClientConfig`](crate::ClientConfig). For values that change between
+//! requests, such as short-lived tokens issued by an authenticating gateway,
+//! register a [`HeaderHook`].
+
+use reqwest::header::HeaderMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Callback signature of a [`HeaderHook`].
+type HeaderCallback = dyn Fn(&str, &mut HeaderMap) + Send + Sync;
+
+/// Callback adding headers to every HTTP request.
+///
+/// The hook runs before each attempt, including retries, and receives the
+/// request URL. Headers it inserts replace configured headers of the same
+/// name. Clones share the same callback.
+#[derive(Clone)]
+pub struct HeaderHook {
+    callback: Arc<HeaderCallback>,
+}
+
+impl HeaderHook {
+    /// Creates a hook from a callback.
+    ///
+    /// The callback runs on the task issuing the request and should return
+    /// quickly.
+    pub fn new(callback: impl Fn(&str, &mut HeaderMap) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Returns the headers the hook adds for a request to `url`.
+    #[must_use]
+    pub fn headers(&self, url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        (self.callback)(url, &mut headers);
+        headers
+    }
+}
+
+impl fmt::Debug for HeaderHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderHook").finish_non_exhaustive()
+    }
+}
+
+/// HTTP basic authentication credentials.
+///
+/// The password is redacted from `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    /// User name.
+    pub username: String,
+    /// Password, if any.
+    pub password: Option<String>,
+}
+
+impl BasicAuth {
+    /// Creates credentials from a user name and optional password.
+    pub fn new(username: impl Into<String>, password: Option<String>) -> Self {
+        Self {
+            username: username.into(),
+            password,
+        }
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Round-robin rotation over a list of user agents.
+///
+/// Clones share the rotation position.
+#[derive(Debug, Clone)]
+pub(crate) struct UserAgentPool {
+    agents: Arc<[String]>,
+    next: Arc<AtomicUsize>,
+}
+
+impl UserAgentPool {
+    /// Creates a pool, or `None` if `agents` is empty.
+    pub(crate) fn new(agents: &[String]) -> Option<Self> {
+        if agents.is_empty() {
+            return None;
+        }
+        Some(Self {
+            agents: agents.into(),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Returns the user agent for the next request.
+    pub(crate) fn next(&self) -> &str {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.agents.len();
+        &self.agents[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_user_agent_pool_rotates() {
+        assert!(UserAgentPool::new(&[]).is_none());
+
+        let pool = UserAgentPool::new(&["a".to_string(), "b".to_string()]).unwrap();
+        let shared = pool.clone();
+        assert_eq!(pool.next(), "a");
+        assert_eq!(shared.next(), "b");
+        assert_eq!(pool.next(), "a");
+    }
+
+    #[test]
+    fn test_basic_auth_debug_redacts_password() {
+        let auth = BasicAuth::new("user", Some("secret".to_string()));
+        let debug = format!("{auth:?}");
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[test]
+    fn test_header_hook_sees_url() {
+        let hook = HeaderHook::new(|url, headers| {
+            let value = HeaderValue::from_str(&url.len().to_string()).unwrap();
+            headers.insert("x-url-length", value);
+        });
+        let headers = hook.headers("https://example.com");
+        assert_eq!(headers["x-url-length"], "19");
+    }
+}
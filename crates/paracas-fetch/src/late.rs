@@ -0,0 +1,177 @@
+//! Re-attempting hours whose data may have been published late.
+//!
+//! Dukascopy sometimes publishes an hour after it was first requested, so a
+//! 404 for an hour the market was open is not always a real gap. With
+//! [`LateDataRetry`] enabled on [`StreamOptions`](crate::StreamOptions), a
+//! stream waits and downloads such hours again when an adjacent hour has data.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use paracas_types::{Instrument, is_forex_weekend};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::TickBatch;
+
+/// Settings for re-attempting empty hours that should contain data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LateDataRetry {
+    /// Wait before each re-attempt.
+    pub delay: Duration,
+    /// Maximum number of re-attempts per hour.
+    pub attempts: u32,
+}
+
+impl Default for LateDataRetry {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_secs(60),
+            attempts: 1,
+        }
+    }
+}
+
+impl LateDataRetry {
+    /// Creates settings with the given delay and number of re-attempts.
+    #[must_use]
+    pub const fn new(delay: Duration, attempts: u32) -> Self {
+        Self { delay, attempts }
+    }
+}
+
+/// Tracks which hours of a stream had data and re-attempts suspicious gaps.
+///
+/// Clones share the same record of hours.
+#[derive(Debug, Clone)]
+pub(crate) struct LateDataTracker {
+    retry: LateDataRetry,
+    forex: bool,
+    hours: Arc<Mutex<HashMap<DateTime<Utc>, bool>>>,
+}
+
+impl LateDataTracker {
+    pub(crate) fn new(retry: LateDataRetry, instrument: &Instrument) -> Self {
+        Self {
+            retry,
+            forex: instrument.is_forex(),
+            hours: Arc::default(),
+        }
+    }
+
+    /// Returns true if the market was open during `hour`.
+    fn market_open(&self, hour: DateTime<Utc>) -> bool {
+        !(self.forex && is_forex_weekend(hour))
+    }
+
+    fn record(&self, hour: DateTime<Utc>, has_data: bool) {
+        self.hours
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(hour, has_data);
+    }
+
+    /// Returns true if the previous or next hour is known to have data.
+    fn neighbor_has_data(&self, hour: DateTime<Utc>) -> bool {
+        let hours = self.hours.lock().unwrap_or_else(PoisonError::into_inner);
+        [hour - TimeDelta::hours(1), hour + TimeDelta::hours(1)]
+            .iter()
+            .any(|neighbor| hours.get(neighbor) == Some(&true))
+    }
+
+    /// Records `batch` and, if it is an unexpected gap, re-attempts it.
+    ///
+    /// An empty hour during market hours is re-attempted after the configured
+    /// delay, as long as an adjacent hour has data by then. Failed
+    /// re-attempts keep the original empty batch.
+    pub(crate) async fn recheck<F, E>(
+        &self,
+        batch: TickBatch,
+        mut refetch: impl FnMut() -> F,
+    ) -> TickBatch
+    where
+        F: Future<Output = Result<TickBatch, E>>,
+    {
+        let hour = batch.hour;
+        self.record(hour, !batch.is_empty());
+        if !batch.is_empty() || !self.market_open(hour) {
+            return batch;
+        }
+
+        for _ in 0..self.retry.attempts {
+            tokio::time::sleep(self.retry.delay).await;
+            if !self.neighbor_has_data(hour) {
+                break;
+            }
+            if let Ok(retried) = refetch().await
+                && !retried.is_empty()
+            {
+                self.record(hour, true);
+                return retried;
+            }
+        }
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use paracas_types::{Category, Tick};
+    use std::future;
+
+    fn tracker() -> LateDataTracker {
+        let instrument = Instrument::new("eurusd", "EUR/USD", "", Category::Forex, 100_000, None);
+        LateDataTracker::new(LateDataRetry::new(Duration::ZERO, 2), &instrument)
+    }
+
+    fn batch_with_data(hour: DateTime<Utc>) -> TickBatch {
+        TickBatch::new(hour, vec![Tick::new(hour, 1.1001, 1.1000, 1.0, 1.0)])
+    }
+
+    #[tokio::test]
+    async fn test_retries_gap_next_to_data() {
+        let tracker = tracker();
+        // Monday 2024-01-15
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        tracker.record(hour - TimeDelta::hours(1), true);
+
+        let mut calls = 0;
+        let batch = tracker
+            .recheck(TickBatch::new(hour, Vec::new()), || {
+                calls += 1;
+                future::ready(Ok::<_, ()>(batch_with_data(hour)))
+            })
+            .await;
+        assert_eq!(batch.len(), 1);
+        assert_eq!(calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_skips_isolated_and_closed_hours() {
+        let tracker = tracker();
+        let mut calls = 0;
+
+        // No neighbor with data
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let batch = tracker
+            .recheck(TickBatch::new(hour, Vec::new()), || {
+                calls += 1;
+                future::ready(Ok::<_, ()>(batch_with_data(hour)))
+            })
+            .await;
+        assert!(batch.is_empty());
+
+        // Saturday: market closed
+        let saturday = Utc.with_ymd_and_hms(2024, 1, 13, 12, 0, 0).unwrap();
+        tracker.record(saturday - TimeDelta::hours(1), true);
+        let batch = tracker
+            .recheck(TickBatch::new(saturday, Vec::new()), || {
+                calls += 1;
+                future::ready(Ok::<_, ()>(batch_with_data(saturday)))
+            })
+            .await;
+        assert!(batch.is_empty());
+        assert_eq!(calls, 0);
+    }
+}
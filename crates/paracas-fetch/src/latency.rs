@@ -0,0 +1,112 @@
+//! Slow-request detection.
+//!
+//! Every HTTP attempt is timed from sending the request until its body is
+//! read, and the totals are kept in [`DownloadStats`](crate::DownloadStats)
+//! next to the time spent decompressing. Attempts slower than
+//! [`ClientConfig::slow_request_threshold`](crate::ClientConfig::slow_request_threshold)
+//! are reported to a [`SlowRequestHook`] and, with the `tracing` feature, as
+//! warning events. Comparing the two tells network-bound downloads from
+//! CPU-bound ones.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An HTTP attempt that took longer than the configured threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowRequest {
+    /// The requested URL.
+    pub url: String,
+    /// Attempt number, starting at 1.
+    pub attempt: u32,
+    /// Time from sending the request until the body was read or the attempt
+    /// failed.
+    pub latency: Duration,
+    /// Response status, or `None` if no response arrived.
+    pub status: Option<u16>,
+}
+
+impl fmt::Display for SlowRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (attempt {}) took {} ms",
+            self.url,
+            self.attempt,
+            self.latency.as_millis()
+        )?;
+        match self.status {
+            Some(status) => write!(f, ", HTTP {status}"),
+            None => f.write_str(", no response"),
+        }
+    }
+}
+
+/// Callback signature of a [`SlowRequestHook`].
+type SlowRequestCallback = dyn Fn(&SlowRequest) + Send + Sync;
+
+/// Callback notified of every slow HTTP attempt.
+///
+/// Clones share the same callback.
+#[derive(Clone)]
+pub struct SlowRequestHook {
+    callback: Arc<SlowRequestCallback>,
+}
+
+impl SlowRequestHook {
+    /// Creates a hook from a callback.
+    ///
+    /// The callback runs on the task issuing the request and should return
+    /// quickly.
+    pub fn new(callback: impl Fn(&SlowRequest) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Notifies the hook of a slow attempt.
+    pub fn notify(&self, request: &SlowRequest) {
+        (self.callback)(request);
+    }
+}
+
+impl fmt::Debug for SlowRequestHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowRequestHook").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_hook_and_display() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let hook = SlowRequestHook::new(move |request| {
+            recorder.lock().unwrap().push(request.to_string());
+        });
+
+        let request = SlowRequest {
+            url: "https://example.com/a.bi5".to_string(),
+            attempt: 2,
+            latency: Duration::from_millis(1500),
+            status: Some(200),
+        };
+        hook.notify(&request);
+        hook.notify(&SlowRequest {
+            status: None,
+            ..request
+        });
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                "https://example.com/a.bi5 (attempt 2) took 1500 ms, HTTP 200",
+                "https://example.com/a.bi5 (attempt 2) took 1500 ms, no response",
+            ]
+        );
+    }
+}
@@ -4,9 +4,28 @@
 //!
 //! - [`url::tick_url`] - Constructs Dukascopy data URLs
 //! - [`DownloadClient`] - HTTP client with connection pooling and retries
+//! - [`RetryPolicy`] - Per-failure retry budgets, backoff curve and jitter
+//! - [`RateLimiter`] - Request and bandwidth budget shared across clients
+//! - [`CircuitBreaker`] - Pauses all requests during server outages
+//! - [`HeaderHook`] - Per-request headers for authenticating gateways
+//! - [`TlsBackend`] - TLS implementation and extra trusted CA certificates
+//! - [`Validators`] - Conditional requests for polling the newest hour
+//! - [`DataSource`] - HTTP feed or a local directory of archived bi5 files
+//! - [`DiskCache`] - On-disk bi5 cache with TTL and revision-window expiry
+//! - [`CoverageManifest`] - Per-instrument record of cached hours with checksums
+//! - [`DownloadStats`] - Bytes, requests, retries and wall time for a client
+//! - [`SlowRequestHook`] - Reports HTTP attempts slower than a threshold
 //! - [`decompress::decompress_bi5`] - LZMA decompression
+//! - [`decompress_ticks`] - Streaming decompression straight into tick records
 //! - [`parse::parse_ticks`] - Binary tick data parsing
+//! - [`parse_ticks_columnar`] - Bulk parsing into struct-of-arrays columns
 //! - [`tick_stream`] - Async streaming tick download
+//! - [`tick_stream_chunks`] - Ticks regrouped into fixed-size chunks
+//! - [`multi_tick_stream`] - Several instruments through one concurrency pool
+//! - [`LateDataRetry`] - Re-attempts empty hours whose data may be published late
+//! - [`ProgressObserver`] - Progress callbacks or watch channel for streams
+//! - [`PriceValidation`] - Flags or drops ticks with implausible prices
+//! - [`FailureInjection`] - Deliberately failing requests for testing recovery
 
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/factordynamics/paracas/issues/")]
@@ -14,13 +33,65 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod cache;
+mod chaos;
+mod circuit;
 mod client;
+mod conditional;
 mod decompress;
+mod dns;
+mod headers;
+mod late;
+mod latency;
+mod manifest;
+mod multi;
 mod parse;
+mod progress;
+mod rate;
+mod retry;
+mod source;
+mod stats;
 mod stream;
+mod tls;
+mod trace;
 pub mod url;
+mod validate;
 
+pub use cache::{CacheConfig, DiskCache, ExpiryPolicy, Revision, sha256_file_hex, sha256_hex};
+pub use chaos::{FailureInjection, InjectedFailure};
+pub use circuit::{CircuitBreaker, CircuitBreakerConfig, CircuitPermit, CircuitState};
 pub use client::{ClientConfig, DownloadClient, DownloadError};
-pub use decompress::{DecompressError, decompress_bi5};
-pub use parse::{ParseError, parse_ticks, tick_count};
-pub use stream::{TickBatch, flatten_ticks, tick_stream, tick_stream_resilient};
+pub use conditional::{ConditionalDownload, Validators};
+pub use decompress::{DecompressError, decompress_bi5, decompress_ticks};
+pub use dns::DnsResolver;
+pub use headers::{BasicAuth, HeaderHook};
+pub use late::LateDataRetry;
+pub use latency::{SlowRequest, SlowRequestHook};
+pub use manifest::{
+    Coverage, CoverageManifest, IntegrityIssue, IntegrityProblem, MANIFEST_FILE, ManifestEntry,
+    VERIFIED_FILE, VerifyOptions, VerifyReport,
+};
+pub use multi::{multi_tick_stream, multi_tick_stream_with_options};
+pub use parse::{
+    ParseError, TickColumns, parse_ticks, parse_ticks_columnar, parse_ticks_into, tick_count,
+};
+pub use progress::{ProgressObserver, StreamProgress};
+pub use rate::{RateLimit, RateLimiter};
+pub use reqwest::Certificate;
+pub use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+pub use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+pub use retry::{
+    Backoff, DEFAULT_MAX_RETRY_AFTER, FailureKind, Jitter, RetryPolicy, default_retryable,
+};
+pub use source::DataSource;
+pub use stats::{DownloadStats, StatsSnapshot};
+pub use stream::{
+    HourError, SkipReason, StreamOptions, TickBatch, chunk_ticks, fetch_hour, flatten_ticks,
+    tick_stream, tick_stream_chunks, tick_stream_resilient, tick_stream_resilient_split,
+    tick_stream_resilient_with_options, tick_stream_with_options,
+};
+pub use tls::{CertificateError, TlsBackend, load_certificates};
+pub use tokio_util::sync::CancellationToken;
+pub use validate::{
+    DEFAULT_MAX_MEDIAN_RATIO, InvalidTick, InvalidTickAction, PriceProblem, PriceValidation,
+};
@@ -0,0 +1,598 @@
+//! Per-instrument coverage manifests for the disk cache.
+//!
+//! Every hour stored in the cache is recorded in
+//! `<root>/<INSTRUMENT>/manifest.jsonl` with its size and SHA-256 digest.
+//! The manifest is append-only, one JSON object per line, so it is updated
+//! cheaply as hours are downloaded and survives interrupted runs; when an
+//! hour appears more than once, the last line wins.
+//!
+//! Manifests answer which hours of a range are already archived and allow
+//! auditing the stored files against their recorded checksums. Audits of
+//! large archives hash files on several threads and record verified hours in
+//! `verified.jsonl`, so an interrupted run can resume where it stopped.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::sha256_hex;
+use crate::url::tick_url;
+
+/// File name of the manifest inside an instrument's cache directory.
+pub const MANIFEST_FILE: &str = "manifest.jsonl";
+
+/// File name of the log of hours whose checksums were verified.
+pub const VERIFIED_FILE: &str = "verified.jsonl";
+
+/// A cached hour recorded in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Start of the hour.
+    pub hour: DateTime<Utc>,
+    /// Size of the stored bi5 file in bytes (0 for an hour without data).
+    pub len: u64,
+    /// SHA-256 (hex) of the stored file.
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// Creates the entry for an hour stored with `data`.
+    #[must_use]
+    pub fn new(hour: DateTime<Utc>, data: &[u8]) -> Self {
+        Self {
+            hour,
+            len: data.len() as u64,
+            sha256: sha256_hex(data),
+        }
+    }
+}
+
+/// Coverage of a set of hours by a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Hours stored with data.
+    pub with_data: usize,
+    /// Hours stored as empty (no data published).
+    pub empty: usize,
+    /// Hours not in the manifest, in ascending order.
+    pub missing: Vec<DateTime<Utc>>,
+}
+
+impl Coverage {
+    /// Returns the number of hours in the manifest.
+    #[must_use]
+    pub const fn covered(&self) -> usize {
+        self.with_data + self.empty
+    }
+
+    /// Returns true if every hour is in the manifest.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Problem found when checking a stored file against its manifest entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityProblem {
+    /// The file no longer exists.
+    Missing,
+    /// The file size differs from the recorded size.
+    Size {
+        /// Recorded size in bytes.
+        expected: u64,
+        /// Size on disk in bytes.
+        actual: u64,
+    },
+    /// The file content differs from the recorded digest.
+    Checksum {
+        /// Recorded SHA-256 (hex).
+        expected: String,
+        /// SHA-256 (hex) of the file on disk.
+        actual: String,
+    },
+    /// The file could not be read.
+    Unreadable(String),
+}
+
+/// A stored hour that failed verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityIssue {
+    /// Start of the affected hour.
+    pub hour: DateTime<Utc>,
+    /// What is wrong with the stored file.
+    pub problem: IntegrityProblem,
+}
+
+/// Settings for [`CoverageManifest::verify_with`].
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    /// Hash file contents instead of only comparing sizes.
+    pub checksums: bool,
+    /// Number of files checked in parallel.
+    pub threads: usize,
+    /// Skip hours recorded as verified by an earlier checksum run, if their
+    /// manifest entry is unchanged.
+    ///
+    /// Without resume, the verified log is started afresh.
+    pub resume: bool,
+    /// Token that stops the verification when cancelled.
+    pub cancel: Option<CancellationToken>,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            checksums: false,
+            threads: std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            resume: false,
+            cancel: None,
+        }
+    }
+}
+
+impl VerifyOptions {
+    /// Sets whether file contents are hashed.
+    #[must_use]
+    pub const fn with_checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Sets the number of files checked in parallel.
+    #[must_use]
+    pub const fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets whether hours verified by an earlier run are skipped.
+    #[must_use]
+    pub const fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Stops the verification when `token` is cancelled.
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+}
+
+/// Outcome of [`CoverageManifest::verify_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Hours that failed verification, in hour order.
+    pub issues: Vec<IntegrityIssue>,
+    /// Number of hours checked in this run.
+    pub checked: usize,
+    /// Number of hours skipped because an earlier run verified them.
+    pub skipped: usize,
+    /// True unless the run was cancelled before every hour was handled.
+    pub completed: bool,
+}
+
+/// Coverage manifest of one instrument in a cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageManifest {
+    root: PathBuf,
+    instrument: String,
+    entries: BTreeMap<DateTime<Utc>, ManifestEntry>,
+}
+
+impl CoverageManifest {
+    /// Returns the manifest path of `instrument` under the cache `root`.
+    #[must_use]
+    pub fn path_for(root: &Path, instrument: &str) -> PathBuf {
+        root.join(instrument.to_uppercase()).join(MANIFEST_FILE)
+    }
+
+    /// Loads the manifest of `instrument`, returning an empty manifest if
+    /// none exists yet.
+    ///
+    /// A truncated last line, left by an interrupted write, is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or an earlier line is
+    /// malformed.
+    pub fn load(root: &Path, instrument: &str) -> io::Result<Self> {
+        let mut manifest = Self {
+            root: root.to_path_buf(),
+            instrument: instrument.to_uppercase(),
+            entries: BTreeMap::new(),
+        };
+        for entry in read_entries(&Self::path_for(root, instrument))? {
+            manifest.entries.insert(entry.hour, entry);
+        }
+        Ok(manifest)
+    }
+
+    /// Appends an entry to the manifest file of `instrument` under `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub async fn append(root: &Path, instrument: &str, entry: &ManifestEntry) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let path = Self::path_for(root, instrument);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(entry).map_err(io::Error::other)?;
+        line.push('\n');
+
+        // A single appended write per entry keeps concurrent writers' lines intact.
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await
+    }
+
+    /// Rewrites the manifest file with one line per hour.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn compact(&self) -> io::Result<()> {
+        let path = Self::path_for(&self.root, &self.instrument);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut data = String::new();
+        for entry in self.entries.values() {
+            data.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+            data.push('\n');
+        }
+        let tmp = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    /// Returns the instrument ID (upper case, as in the cache layout).
+    #[must_use]
+    pub fn instrument(&self) -> &str {
+        &self.instrument
+    }
+
+    /// Returns the entry for `hour`, if recorded.
+    #[must_use]
+    pub fn get(&self, hour: DateTime<Utc>) -> Option<&ManifestEntry> {
+        self.entries.get(&hour)
+    }
+
+    /// Returns all entries in hour order.
+    pub fn entries(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.entries.values()
+    }
+
+    /// Returns the number of recorded hours.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no hour is recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Records an entry in memory, replacing any entry for the same hour.
+    pub fn insert(&mut self, entry: ManifestEntry) {
+        self.entries.insert(entry.hour, entry);
+    }
+
+    /// Returns how many of `hours` are recorded, and which are missing.
+    pub fn coverage(&self, hours: impl IntoIterator<Item = DateTime<Utc>>) -> Coverage {
+        let mut coverage = Coverage::default();
+        for hour in hours {
+            match self.entries.get(&hour) {
+                Some(entry) if entry.len == 0 => coverage.empty += 1,
+                Some(_) => coverage.with_data += 1,
+                None => coverage.missing.push(hour),
+            }
+        }
+        coverage.missing.sort_unstable();
+        coverage
+    }
+
+    /// Returns the on-disk path of the file for `hour`.
+    #[must_use]
+    pub fn file_for(&self, hour: DateTime<Utc>) -> PathBuf {
+        let url = tick_url(&self.instrument, hour);
+        let relative = crate::url::relative_path(&url).unwrap_or_default();
+        self.root.join(relative)
+    }
+
+    /// Checks one recorded hour against the file on disk.
+    ///
+    /// Sizes are always compared; content is hashed only when `checksums`
+    /// is set. Returns `None` if the file matches.
+    #[must_use]
+    pub fn verify_entry(&self, entry: &ManifestEntry, checksums: bool) -> Option<IntegrityIssue> {
+        let path = self.file_for(entry.hour);
+        let problem = match std::fs::metadata(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Some(IntegrityProblem::Missing),
+            Err(e) => Some(IntegrityProblem::Unreadable(e.to_string())),
+            Ok(metadata) if metadata.len() != entry.len => Some(IntegrityProblem::Size {
+                expected: entry.len,
+                actual: metadata.len(),
+            }),
+            Ok(_) if !checksums => None,
+            Ok(_) => match std::fs::read(&path) {
+                Err(e) => Some(IntegrityProblem::Unreadable(e.to_string())),
+                Ok(data) => {
+                    let actual = sha256_hex(&data);
+                    (actual != entry.sha256).then(|| IntegrityProblem::Checksum {
+                        expected: entry.sha256.clone(),
+                        actual,
+                    })
+                }
+            },
+        };
+        problem.map(|problem| IntegrityIssue {
+            hour: entry.hour,
+            problem,
+        })
+    }
+
+    /// Checks every recorded hour against the files on disk.
+    ///
+    /// See [`Self::verify_entry`] for what is compared.
+    #[must_use]
+    pub fn verify(&self, checksums: bool) -> Vec<IntegrityIssue> {
+        self.entries
+            .values()
+            .filter_map(|entry| self.verify_entry(entry, checksums))
+            .collect()
+    }
+
+    /// Checks every recorded hour on several threads.
+    ///
+    /// `on_entry` is called once for every hour handled, checked or skipped,
+    /// e.g. to advance a progress bar by the entry's size. With checksums,
+    /// hours that pass are appended to [`VERIFIED_FILE`] as they complete so
+    /// a cancelled run can be resumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the verified log cannot be read or created.
+    pub fn verify_with(
+        &self,
+        options: &VerifyOptions,
+        on_entry: impl Fn(&ManifestEntry) + Sync,
+    ) -> io::Result<VerifyReport> {
+        let log_path = self.root.join(&self.instrument).join(VERIFIED_FILE);
+        let verified: HashMap<_, _> = if options.checksums && options.resume {
+            read_entries(&log_path)?
+                .into_iter()
+                .map(|entry| (entry.hour, entry.sha256))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let log = if options.checksums {
+            std::fs::create_dir_all(self.root.join(&self.instrument))?;
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&log_path)?;
+            if !options.resume {
+                file.set_len(0)?;
+            }
+            Some(Mutex::new(file))
+        } else {
+            None
+        };
+
+        let entries: Vec<&ManifestEntry> = self.entries.values().collect();
+        let next = AtomicUsize::new(0);
+        let report = Mutex::new(VerifyReport::default());
+        let cancelled = || options.cancel.as_ref().is_some_and(|t| t.is_cancelled());
+
+        std::thread::scope(|scope| {
+            for _ in 0..options.threads.clamp(1, entries.len().max(1)) {
+                scope.spawn(|| {
+                    while !cancelled() {
+                        let Some(entry) = entries.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                            break;
+                        };
+                        if verified.get(&entry.hour) == Some(&entry.sha256) {
+                            lock(&report).skipped += 1;
+                        } else {
+                            let issue = self.verify_entry(entry, options.checksums);
+                            if issue.is_none()
+                                && let Some(log) = &log
+                            {
+                                // A failed log write only costs re-hashing on resume.
+                                let _ = append_line(&mut lock(log), entry);
+                            }
+                            let mut report = lock(&report);
+                            report.checked += 1;
+                            report.issues.extend(issue);
+                        }
+                        on_entry(entry);
+                    }
+                });
+            }
+        });
+
+        let mut report = report.into_inner().unwrap_or_else(PoisonError::into_inner);
+        report.completed = report.checked + report.skipped == entries.len();
+        report.issues.sort_by_key(|issue| issue.hour);
+        Ok(report)
+    }
+}
+
+/// Reads the entries of a JSON lines file, in file order.
+///
+/// A missing file has no entries, and a truncated last line is ignored.
+fn read_entries(path: &Path) -> io::Result<Vec<ManifestEntry>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut entries = Vec::new();
+    let mut lines = io::BufReader::new(file).lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<ManifestEntry>(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if lines.peek().is_none() => break,
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+    Ok(entries)
+}
+
+/// Writes an entry as one JSON line.
+fn append_line(file: &mut std::fs::File, entry: &ManifestEntry) -> io::Result<()> {
+    let mut line = serde_json::to_string(entry).map_err(io::Error::other)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+}
+
+/// Locks a mutex, ignoring poisoning.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{CacheConfig, DiskCache};
+    use bytes::Bytes;
+    use chrono::{TimeDelta, TimeZone};
+
+    fn hour(h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 15, h, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cache_updates_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(CacheConfig::new(dir.path()));
+        let data = Bytes::from_static(b"ticks");
+        cache
+            .put(&tick_url("eurusd", hour(0)), Some(&data))
+            .await
+            .unwrap();
+        cache.put(&tick_url("eurusd", hour(1)), None).await.unwrap();
+
+        let manifest = CoverageManifest::load(dir.path(), "eurusd").unwrap();
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest.get(hour(0)).unwrap().sha256, sha256_hex(b"ticks"));
+
+        let hours = (0..3).map(|h| hour(0) + TimeDelta::hours(h));
+        let coverage = manifest.coverage(hours);
+        assert_eq!((coverage.with_data, coverage.empty), (1, 1));
+        assert_eq!(coverage.missing, vec![hour(2)]);
+        assert!(manifest.verify(true).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verify_detects_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(CacheConfig::new(dir.path()));
+        let data = Bytes::from_static(b"ticks");
+        for h in 0..2 {
+            cache
+                .put(&tick_url("eurusd", hour(h)), Some(&data))
+                .await
+                .unwrap();
+        }
+        let manifest = CoverageManifest::load(dir.path(), "EURUSD").unwrap();
+
+        std::fs::write(manifest.file_for(hour(0)), b"TICKS").unwrap();
+        std::fs::remove_file(manifest.file_for(hour(1))).unwrap();
+
+        assert_eq!(manifest.verify(false).len(), 1);
+        let issues = manifest.verify(true);
+        assert!(matches!(
+            issues[0].problem,
+            IntegrityProblem::Checksum { .. }
+        ));
+        assert_eq!(issues[1].problem, IntegrityProblem::Missing);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_verify_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(CacheConfig::new(dir.path()));
+        let data = Bytes::from_static(b"ticks");
+        for h in 0..6 {
+            cache
+                .put(&tick_url("eurusd", hour(h)), Some(&data))
+                .await
+                .unwrap();
+        }
+        let manifest = CoverageManifest::load(dir.path(), "eurusd").unwrap();
+        std::fs::write(manifest.file_for(hour(4)), b"TICKS").unwrap();
+
+        let options = VerifyOptions::default()
+            .with_checksums(true)
+            .with_threads(3);
+        let handled = AtomicUsize::new(0);
+        let report = manifest
+            .verify_with(&options, |_| {
+                handled.fetch_add(1, Ordering::Relaxed);
+            })
+            .unwrap();
+        assert!(report.completed);
+        assert_eq!((report.checked, report.skipped), (6, 0));
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].hour, hour(4));
+        assert_eq!(handled.load(Ordering::Relaxed), 6);
+
+        let report = manifest
+            .verify_with(&options.clone().with_resume(true), |_| {})
+            .unwrap();
+        assert_eq!((report.checked, report.skipped), (1, 5));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let report = manifest
+            .verify_with(&options.with_cancellation(token), |_| {})
+            .unwrap();
+        assert!(!report.completed);
+        assert_eq!(report.checked, 0);
+    }
+
+    #[test]
+    fn test_load_replays_and_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CoverageManifest::path_for(dir.path(), "eurusd");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let first = serde_json::to_string(&ManifestEntry::new(hour(0), b"a")).unwrap();
+        let second = serde_json::to_string(&ManifestEntry::new(hour(0), b"bb")).unwrap();
+        std::fs::write(&path, format!("{first}\n{second}\n{{\"hour\":")).unwrap();
+
+        let manifest = CoverageManifest::load(dir.path(), "eurusd").unwrap();
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(manifest.get(hour(0)).unwrap().len, 2);
+
+        manifest.compact().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{second}\n")
+        );
+    }
+}
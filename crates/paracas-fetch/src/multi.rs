@@ -0,0 +1,151 @@
+//! Tick streams over several instruments sharing one concurrency pool.
+
+use futures::stream::{self, Stream, StreamExt};
+use paracas_types::{DateRange, Instrument};
+use std::convert::Infallible;
+
+use crate::client::DownloadClient;
+use crate::late::LateDataTracker;
+use crate::stream::{
+    Decoder, StreamOptions, TickBatch, fetch_hour_with_refetch, recheck_late, run_downloads,
+};
+use crate::trace;
+use crate::url::tick_url;
+
+/// Creates a stream of tick batches for several instruments over one range.
+///
+/// Every hour of every instrument is downloaded through a single pool of
+/// [`ClientConfig::concurrency`](crate::ClientConfig::concurrency) requests,
+/// so adding instruments does not multiply the load on the server. Items are
+/// `(instrument_id, batch)` pairs.
+///
+/// Hours are requested hour by hour across all instruments, so datasets
+/// spanning several pairs fill in chronologically. Like
+/// [`tick_stream_resilient`](crate::tick_stream_resilient), failed hours are
+/// yielded as empty batches with a [`TickBatch::skipped`] reason instead of
+/// ending the stream.
+///
+/// Download statistics accumulate on [`DownloadClient::stats`].
+pub fn multi_tick_stream<'a>(
+    client: &'a DownloadClient,
+    instruments: &'a [&'a Instrument],
+    range: DateRange,
+) -> impl Stream<Item = (String, TickBatch)> + 'a {
+    multi_tick_stream_with_options(client, instruments, range, StreamOptions::default())
+}
+
+/// Creates a multi-instrument stream, as [`multi_tick_stream`], using the
+/// given stream options for every instrument.
+///
+/// With [`StreamOptions::ordered`] set, batches are yielded by hour and, within
+/// an hour, in the order of `instruments`. Progress counts the hours of all
+/// instruments.
+pub fn multi_tick_stream_with_options<'a>(
+    client: &'a DownloadClient,
+    instruments: &'a [&'a Instrument],
+    range: DateRange,
+    options: StreamOptions,
+) -> impl Stream<Item = (String, TickBatch)> + 'a {
+    let sources: Vec<_> = instruments
+        .iter()
+        .map(|instrument| {
+            let late = options
+                .late_data
+                .map(|retry| LateDataTracker::new(retry, instrument));
+            (
+                instrument.id().to_string(),
+                Decoder::new(instrument, &options),
+                late,
+            )
+        })
+        .collect();
+
+    let downloads = stream::iter(options.hours(range))
+        .flat_map(move |hour| stream::iter(sources.clone()).map(move |source| (hour, source)))
+        .map(move |(hour, (instrument_id, decoder, late))| {
+            let url = tick_url(&instrument_id, hour);
+            let client = client.clone();
+            let span_id = instrument_id.clone();
+            let fetch = async move {
+                let batch = match fetch_hour_with_refetch(&client, &url, hour, decoder).await {
+                    Ok(batch) => recheck_late(late.as_ref(), &client, &url, batch, decoder).await,
+                    Err(e) => TickBatch::skipped(e.hour, e.reason),
+                };
+                Ok::<_, Infallible>((instrument_id, batch))
+            };
+            trace::hour(&span_id, hour, fetch)
+        });
+
+    let total_hours = options.hour_count(range) * instruments.len();
+    run_downloads(downloads, client, options, total_hours)
+        .map(|result| result.unwrap_or_else(|never| match never {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::relative_path;
+    use crate::{ClientConfig, DataSource, SkipReason};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use paracas_types::Category;
+
+    fn instrument(id: &str) -> Instrument {
+        Instrument::new(id, id, id, Category::Forex, 100_000, None)
+    }
+
+    #[tokio::test]
+    async fn test_multi_stream_yields_every_instrument_hour() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ClientConfig {
+            concurrency: 3,
+            source: DataSource::local(dir.path()),
+            ..Default::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let eurusd = instrument("eurusd");
+        let gbpusd = instrument("gbpusd");
+        let instruments = [&eurusd, &gbpusd];
+        let range = DateRange::single_day(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap())
+            .with_hours(10, 12)
+            .unwrap();
+        let hour = |h| Utc.with_ymd_and_hms(2024, 1, 2, h, 0, 0).unwrap();
+
+        // A corrupt file for one hour of one instrument
+        let url = tick_url("gbpusd", hour(11));
+        let corrupt = dir.path().join(relative_path(&url).unwrap());
+        std::fs::create_dir_all(corrupt.parent().unwrap()).unwrap();
+        std::fs::write(&corrupt, b"not lzma").unwrap();
+
+        let options = StreamOptions::default().with_ordered(true);
+        let items: Vec<_> = multi_tick_stream_with_options(&client, &instruments, range, options)
+            .collect()
+            .await;
+
+        let order: Vec<_> = items
+            .iter()
+            .map(|(id, batch)| (id.as_str(), batch.hour))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("eurusd", hour(10)),
+                ("gbpusd", hour(10)),
+                ("eurusd", hour(11)),
+                ("gbpusd", hour(11)),
+                ("eurusd", hour(12)),
+                ("gbpusd", hour(12)),
+            ]
+        );
+        assert!(items.iter().all(|(_, batch)| batch.is_empty()));
+
+        // Only the corrupt hour is skipped; missing files are empty hours
+        let skipped: Vec<_> = items
+            .iter()
+            .filter_map(|(id, batch)| batch.skipped.map(|reason| (id.as_str(), reason)))
+            .collect();
+        assert_eq!(
+            skipped,
+            [("gbpusd", SkipReason::Decompress { attempts: 2 })]
+        );
+    }
+}
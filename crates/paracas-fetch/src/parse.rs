@@ -1,7 +1,8 @@
 //! Binary tick parsing from bi5 format.
 
 use byteorder::{BigEndian, ByteOrder};
-use paracas_types::RawTick;
+use chrono::{DateTime, Utc};
+use paracas_types::{RawTick, Tick};
 use thiserror::Error;
 
 /// Errors that can occur during tick parsing.
@@ -44,9 +45,31 @@ pub fn parse_ticks(data: &[u8]) -> Result<impl Iterator<Item = RawTick> + '_, Pa
     Ok(data.chunks_exact(RawTick::SIZE).map(parse_single_tick))
 }
 
+/// Parses and normalizes decompressed bi5 data for one hour into `out`.
+///
+/// Ticks are appended to `out`, so one buffer can be cleared and reused
+/// across hours instead of allocating a new vector per hour. Returns the
+/// number of ticks appended; on error `out` is left unchanged.
+///
+/// # Errors
+///
+/// Returns an error if the data length is not a multiple of the record size.
+pub fn parse_ticks_into(
+    data: &[u8],
+    hour_start: DateTime<Utc>,
+    decimal_factor: f64,
+    out: &mut Vec<Tick>,
+) -> Result<usize, ParseError> {
+    let raw = parse_ticks(data)?;
+    let count = tick_count(data.len());
+    out.reserve(count);
+    out.extend(raw.map(|tick| tick.normalize(hour_start, decimal_factor)));
+    Ok(count)
+}
+
 /// Parses a single tick from a 20-byte chunk.
 #[inline]
-fn parse_single_tick(data: &[u8]) -> RawTick {
+pub(crate) fn parse_single_tick(data: &[u8]) -> RawTick {
     RawTick::new(
         BigEndian::read_u32(&data[0..4]),
         BigEndian::read_u32(&data[4..8]),
@@ -56,6 +79,80 @@ fn parse_single_tick(data: &[u8]) -> RawTick {
     )
 }
 
+/// Ticks of one hour decoded into struct-of-arrays form.
+///
+/// Each column holds one value per tick, in file order. The layout maps
+/// directly onto Arrow arrays, so writers can build record batches without
+/// materializing a [`RawTick`] or [`Tick`](paracas_types::Tick) per row.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TickColumns {
+    /// Tick timestamps as milliseconds since the Unix epoch.
+    pub timestamps: Vec<i64>,
+    /// Ask prices.
+    pub asks: Vec<f64>,
+    /// Bid prices.
+    pub bids: Vec<f64>,
+    /// Ask volumes.
+    pub ask_volumes: Vec<f32>,
+    /// Bid volumes.
+    pub bid_volumes: Vec<f32>,
+}
+
+impl TickColumns {
+    /// Returns the number of ticks.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    /// Returns true if there are no ticks.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+}
+
+/// Parses decompressed bi5 data for one hour directly into columns.
+///
+/// Timestamps are offset from `hour_start` and prices are divided by
+/// `decimal_factor`, matching [`RawTick::normalize`]. Each column is filled
+/// by its own pass over the fixed-size records, which keeps the loops free
+/// of branches and lets the compiler vectorize the big-endian reads.
+///
+/// # Errors
+///
+/// Returns an error if the data length is not a multiple of the record size.
+pub fn parse_ticks_columnar(
+    data: &[u8],
+    hour_start: DateTime<Utc>,
+    decimal_factor: f64,
+) -> Result<TickColumns, ParseError> {
+    if !data.len().is_multiple_of(RawTick::SIZE) {
+        return Err(ParseError::InvalidLength(data.len(), RawTick::SIZE));
+    }
+
+    let base = hour_start.timestamp_millis();
+    Ok(TickColumns {
+        timestamps: column(data, 0, |b| base + i64::from(BigEndian::read_u32(b))),
+        asks: column(data, 4, |b| {
+            f64::from(BigEndian::read_u32(b)) / decimal_factor
+        }),
+        bids: column(data, 8, |b| {
+            f64::from(BigEndian::read_u32(b)) / decimal_factor
+        }),
+        ask_volumes: column(data, 12, BigEndian::read_f32),
+        bid_volumes: column(data, 16, BigEndian::read_f32),
+    })
+}
+
+/// Reads the 4-byte field at `offset` of every record into a column.
+#[inline]
+fn column<T>(data: &[u8], offset: usize, read: impl Fn(&[u8]) -> T) -> Vec<T> {
+    data.chunks_exact(RawTick::SIZE)
+        .map(|record| read(&record[offset..offset + 4]))
+        .collect()
+}
+
 /// Returns the number of ticks in the given data.
 #[must_use]
 pub const fn tick_count(data_len: usize) -> usize {
@@ -99,6 +196,64 @@ mod tests {
         assert_eq!(ticks[1].ms_offset, 1000);
     }
 
+    #[test]
+    fn test_parse_columnar_matches_normalize() {
+        use chrono::TimeZone;
+
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let mut data = create_test_tick_bytes(0, 110_000, 109_990, 1.5, 2.5);
+        data.extend(create_test_tick_bytes(
+            3_599_999, 110_010, 110_000, 0.5, 0.25,
+        ));
+
+        let columns = parse_ticks_columnar(&data, hour, 100_000.0).unwrap();
+        assert_eq!(columns.len(), 2);
+
+        for (i, raw) in parse_ticks(&data).unwrap().enumerate() {
+            let tick = raw.normalize(hour, 100_000.0);
+            assert_eq!(columns.timestamps[i], tick.timestamp.timestamp_millis());
+            assert_eq!(columns.asks[i], tick.ask);
+            assert_eq!(columns.bids[i], tick.bid);
+            assert_eq!(columns.ask_volumes[i], tick.ask_volume);
+            assert_eq!(columns.bid_volumes[i], tick.bid_volume);
+        }
+
+        assert!(
+            parse_ticks_columnar(&[], hour, 100_000.0)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            parse_ticks_columnar(&data[..25], hour, 100_000.0),
+            Err(ParseError::InvalidLength(25, 20))
+        ));
+    }
+
+    #[test]
+    fn test_parse_ticks_into_reuses_buffer() {
+        use chrono::TimeZone;
+
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let mut data = create_test_tick_bytes(0, 110_000, 109_990, 1.0, 2.0);
+        data.extend(create_test_tick_bytes(1000, 110_010, 110_000, 1.0, 2.0));
+
+        let mut buf = Vec::new();
+        assert_eq!(parse_ticks_into(&data, hour, 100_000.0, &mut buf), Ok(2));
+        assert_eq!(
+            parse_ticks_into(&data[..20], hour, 100_000.0, &mut buf),
+            Ok(1)
+        );
+        assert_eq!(buf.len(), 3);
+        assert!((buf[1].ask - 1.1001).abs() < 1e-10);
+
+        let capacity = buf.capacity();
+        buf.clear();
+        assert!(parse_ticks_into(&data[..25], hour, 100_000.0, &mut buf).is_err());
+        assert!(buf.is_empty());
+        assert_eq!(parse_ticks_into(&data, hour, 100_000.0, &mut buf), Ok(2));
+        assert_eq!(buf.capacity(), capacity);
+    }
+
     #[test]
     fn test_invalid_length() {
         let data = vec![0u8; 25]; // Not a multiple of 20
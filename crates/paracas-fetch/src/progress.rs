@@ -0,0 +1,190 @@
+//! Progress reporting for tick streams.
+//!
+//! Register a [`ProgressObserver`] on [`StreamOptions`](crate::StreamOptions)
+//! to be notified after every hour a stream yields, either through a callback
+//! or a [`tokio::sync::watch`] channel.
+
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::DownloadStats;
+
+/// Progress of a tick stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamProgress {
+    /// Hours yielded so far.
+    pub hours_completed: u64,
+    /// Hours the stream will request in total.
+    pub hours_total: u64,
+    /// Ticks yielded so far.
+    pub ticks: u64,
+    /// Compressed bytes downloaded since the stream started.
+    ///
+    /// Taken from the client's [`DownloadStats`], so it also includes other
+    /// streams sharing the same client.
+    pub bytes: u64,
+    /// Hours that failed (errors or skipped batches).
+    pub errors: u64,
+    /// Whether the stream was cancelled before yielding every hour.
+    ///
+    /// Set on the final update of a cancelled stream; the other fields then
+    /// describe the partial download.
+    pub cancelled: bool,
+}
+
+impl StreamProgress {
+    /// Returns the completed fraction in `0.0..=1.0`.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        if self.hours_total == 0 {
+            1.0
+        } else {
+            self.hours_completed as f64 / self.hours_total as f64
+        }
+    }
+
+    /// Returns true once every hour has been yielded.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.hours_completed >= self.hours_total
+    }
+}
+
+/// Receives [`StreamProgress`] updates from a tick stream.
+///
+/// Clones share the same callback.
+#[derive(Clone)]
+pub struct ProgressObserver {
+    callback: Arc<dyn Fn(&StreamProgress) + Send + Sync>,
+}
+
+impl ProgressObserver {
+    /// Creates an observer that invokes `callback` after every hour.
+    ///
+    /// The callback runs on the task polling the stream and should return
+    /// quickly.
+    pub fn new(callback: impl Fn(&StreamProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Creates an observer that publishes updates to a watch channel.
+    #[must_use]
+    pub fn channel() -> (Self, watch::Receiver<StreamProgress>) {
+        let (tx, rx) = watch::channel(StreamProgress::default());
+        let observer = Self::new(move |progress| {
+            tx.send_replace(*progress);
+        });
+        (observer, rx)
+    }
+
+    /// Delivers a progress update.
+    pub fn notify(&self, progress: &StreamProgress) {
+        (self.callback)(progress);
+    }
+}
+
+impl fmt::Debug for ProgressObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressObserver").finish_non_exhaustive()
+    }
+}
+
+/// Accumulates progress for a single stream and notifies the observer.
+#[derive(Debug)]
+pub(crate) struct ProgressTracker {
+    observer: Option<ProgressObserver>,
+    stats: DownloadStats,
+    bytes_at_start: u64,
+    progress: StreamProgress,
+}
+
+impl ProgressTracker {
+    pub(crate) fn new(
+        observer: Option<ProgressObserver>,
+        stats: &DownloadStats,
+        hours_total: usize,
+    ) -> Self {
+        let progress = StreamProgress {
+            hours_total: hours_total as u64,
+            ..StreamProgress::default()
+        };
+        if let Some(observer) = &observer {
+            observer.notify(&progress);
+        }
+        Self {
+            observer,
+            bytes_at_start: stats.snapshot().bytes_fetched,
+            stats: stats.clone(),
+            progress,
+        }
+    }
+
+    /// Records a yielded hour.
+    pub(crate) fn record(&mut self, ticks: usize, failed: bool) {
+        let Some(observer) = &self.observer else {
+            return;
+        };
+        self.progress.hours_completed += 1;
+        self.progress.ticks += ticks as u64;
+        self.progress.errors += u64::from(failed);
+        self.progress.bytes = self
+            .stats
+            .snapshot()
+            .bytes_fetched
+            .saturating_sub(self.bytes_at_start);
+        observer.notify(&self.progress);
+    }
+
+    /// Records that the stream was cancelled.
+    pub(crate) fn cancel(&mut self) {
+        self.progress.cancelled = true;
+        if let Some(observer) = &self.observer {
+            observer.notify(&self.progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_tracker_notifies_callback() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let observer = ProgressObserver::new(move |p| sink.lock().unwrap().push(*p));
+
+        let mut tracker = ProgressTracker::new(Some(observer), &DownloadStats::new(), 2);
+        tracker.record(10, false);
+        tracker.record(0, true);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].hours_total, 2);
+        assert_eq!(seen[2].hours_completed, 2);
+        assert_eq!(seen[2].ticks, 10);
+        assert_eq!(seen[2].errors, 1);
+        assert!(seen[2].is_complete());
+    }
+
+    #[test]
+    fn test_watch_channel() {
+        let (observer, rx) = ProgressObserver::channel();
+        let mut tracker = ProgressTracker::new(Some(observer), &DownloadStats::new(), 4);
+        tracker.record(5, false);
+
+        let progress = *rx.borrow();
+        assert_eq!(progress.hours_completed, 1);
+        assert_eq!(progress.ticks, 5);
+        assert!((progress.fraction() - 0.25).abs() < f64::EPSILON);
+
+        tracker.cancel();
+        let progress = *rx.borrow();
+        assert!(progress.cancelled);
+        assert_eq!(progress.hours_completed, 1);
+    }
+}
@@ -0,0 +1,154 @@
+//! Request and bandwidth rate limiting shared across clients.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Limits enforced by a [`RateLimiter`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimit {
+    /// Maximum requests started per second, or `None` for no limit.
+    pub requests_per_second: Option<f64>,
+    /// Maximum bytes downloaded per second, or `None` for no limit.
+    pub bytes_per_second: Option<u64>,
+}
+
+impl RateLimit {
+    /// Sets the maximum number of requests started per second.
+    #[must_use]
+    pub const fn with_requests_per_second(mut self, requests: f64) -> Self {
+        self.requests_per_second = Some(requests);
+        self
+    }
+
+    /// Sets the maximum number of bytes downloaded per second.
+    #[must_use]
+    pub const fn with_bytes_per_second(mut self, bytes: u64) -> Self {
+        self.bytes_per_second = Some(bytes);
+        self
+    }
+
+    /// Returns true if neither limit is set.
+    #[must_use]
+    pub const fn is_unlimited(&self) -> bool {
+        self.requests_per_second.is_none() && self.bytes_per_second.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Earliest start of the next request under the request limit.
+    next_request: Option<Instant>,
+    /// Time at which bytes already downloaded are paid off.
+    bandwidth_free: Option<Instant>,
+}
+
+/// Rate limiter shared by any number of download clients.
+///
+/// Cloning is cheap and clones share state: give the same limiter to every
+/// [`DownloadClient`](crate::DownloadClient) that should respect one budget,
+/// e.g. the per-instrument clients of a batch download.
+///
+/// Requests are spaced evenly to honor the request limit. Downloaded bytes
+/// are charged after each response, delaying the following requests until
+/// the bandwidth budget has caught up.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter enforcing `limit`.
+    #[must_use]
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Returns the enforced limits.
+    #[must_use]
+    pub const fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    /// Waits until a request may start.
+    pub async fn acquire(&self) {
+        let start = {
+            let mut inner = self.lock();
+            let now = Instant::now();
+            let mut start = now;
+            if let Some(free) = inner.bandwidth_free {
+                start = start.max(free);
+            }
+            if let Some(rps) = self.limit.requests_per_second.filter(|rps| *rps > 0.0) {
+                start = start.max(inner.next_request.unwrap_or(now));
+                inner.next_request = Some(start + Duration::from_secs_f64(1.0 / rps));
+            }
+            start
+        };
+        if start > Instant::now() {
+            tokio::time::sleep_until(start.into()).await;
+        }
+    }
+
+    /// Charges `bytes` downloaded against the bandwidth limit.
+    pub fn record_bytes(&self, bytes: usize) {
+        let Some(rate) = self.limit.bytes_per_second.filter(|rate| *rate > 0) else {
+            return;
+        };
+        let cost = Duration::from_secs_f64(bytes as f64 / rate as f64);
+        let mut inner = self.lock();
+        let now = Instant::now();
+        let free = inner.bandwidth_free.map_or(now, |free| free.max(now));
+        inner.bandwidth_free = Some(free + cost);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_spaced_across_clones() {
+        let limiter = RateLimiter::new(RateLimit::default().with_requests_per_second(50.0));
+        let other = limiter.clone();
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+            other.acquire().await;
+        }
+        // Six requests at 50/s: the last starts 100ms after the first
+        assert!(start.elapsed() >= Duration::from_millis(95));
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_delays_next_request() {
+        let limiter = RateLimiter::new(RateLimit::default().with_bytes_per_second(1000));
+        limiter.acquire().await;
+        let start = Instant::now();
+        limiter.record_bytes(50);
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_does_not_wait() {
+        let limit = RateLimit::default();
+        assert!(limit.is_unlimited());
+        let limiter = RateLimiter::new(limit);
+        let start = Instant::now();
+        for _ in 0..100 {
+            limiter.acquire().await;
+            limiter.record_bytes(1 << 20);
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
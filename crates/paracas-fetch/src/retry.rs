@@ -0,0 +1,382 @@
+//! Retry policy for failed requests.
+//!
+//! A [`RetryPolicy`] decides whether a failed request is retried, how many
+//! times, and how long to wait between attempts. Retry budgets can differ by
+//! failure kind, so rate limiting (429) can be retried more patiently than a
+//! server error (503) or a connect timeout. When the server says how long to
+//! wait with a `Retry-After` header, that delay replaces the computed backoff.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Classification of a failed request attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureKind {
+    /// The server answered with an HTTP status.
+    Status(u16),
+    /// The request timed out.
+    Timeout,
+    /// The connection could not be established.
+    Connect,
+    /// Any other transport error while sending the request.
+    Request,
+}
+
+impl FailureKind {
+    /// Classifies a transport error, or returns `None` for errors that are
+    /// never worth retrying (e.g. an invalid request).
+    #[must_use]
+    pub fn from_error(error: &reqwest::Error) -> Option<Self> {
+        if error.is_builder() {
+            None
+        } else if error.is_timeout() {
+            Some(Self::Timeout)
+        } else if error.is_connect() {
+            Some(Self::Connect)
+        } else if error.is_request() {
+            Some(Self::Request)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns true for server errors (5xx), rate limiting (429) and all
+/// transport failures.
+#[must_use]
+pub const fn default_retryable(kind: &FailureKind) -> bool {
+    match *kind {
+        FailureKind::Status(status) => status == 429 || (status >= 500 && status < 600),
+        FailureKind::Timeout | FailureKind::Connect | FailureKind::Request => true,
+    }
+}
+
+/// Shape of the delay growth between attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// The base delay before every retry.
+    Constant,
+    /// The base delay multiplied by the attempt number.
+    Linear,
+    /// The base delay doubled on every attempt.
+    #[default]
+    Exponential,
+}
+
+/// Default cap on delays requested by the server with `Retry-After`.
+pub const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Randomization applied to backoff delays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Use the computed delay as is.
+    None,
+    /// A reproducible ±25% offset derived from the attempt number.
+    #[default]
+    Deterministic,
+    /// A random delay between zero and the computed delay.
+    Full,
+    /// Half the computed delay plus a random share of the other half.
+    Equal,
+}
+
+/// Policy controlling retries of failed requests.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retry budget for failures without a specific limit.
+    pub max_retries: u32,
+    /// Retry budgets for specific failure kinds, overriding `max_retries`.
+    pub limits: BTreeMap<FailureKind, u32>,
+    /// Delay growth between attempts.
+    pub backoff: Backoff,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+    /// Maximum delay between retries.
+    pub max_delay: Duration,
+    /// Randomization applied to delays.
+    pub jitter: Jitter,
+    /// Decides whether a failure kind is retried at all.
+    pub retryable: fn(&FailureKind) -> bool,
+    /// Whether a `Retry-After` header on 429 and 503 responses replaces the
+    /// computed backoff.
+    pub honor_retry_after: bool,
+    /// Longest `Retry-After` delay waited for; longer ones are shortened.
+    pub max_retry_after: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 10, // More retries for transient failures
+            limits: BTreeMap::new(),
+            backoff: Backoff::Exponential,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: Jitter::Deterministic,
+            retryable: default_retryable,
+            honor_retry_after: true,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a policy that never retries.
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the default retry budget.
+    #[must_use]
+    pub const fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the retry budget for a failure kind.
+    #[must_use]
+    pub fn with_limit(mut self, kind: FailureKind, retries: u32) -> Self {
+        self.limits.insert(kind, retries);
+        self
+    }
+
+    /// Sets the retry budget for an HTTP status.
+    #[must_use]
+    pub fn with_status_limit(self, status: u16, retries: u32) -> Self {
+        self.with_limit(FailureKind::Status(status), retries)
+    }
+
+    /// Sets the backoff curve.
+    #[must_use]
+    pub const fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the base and maximum delays.
+    #[must_use]
+    pub const fn with_delays(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the jitter mode.
+    #[must_use]
+    pub const fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the predicate deciding which failures are retried.
+    #[must_use]
+    pub const fn with_retryable(mut self, retryable: fn(&FailureKind) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    /// Sets whether `Retry-After` headers replace the computed backoff.
+    #[must_use]
+    pub const fn with_retry_after(mut self, honor: bool) -> Self {
+        self.honor_retry_after = honor;
+        self
+    }
+
+    /// Sets the longest `Retry-After` delay waited for.
+    #[must_use]
+    pub const fn with_max_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Returns the retry budget for a failure kind.
+    #[must_use]
+    pub fn limit_for(&self, kind: &FailureKind) -> u32 {
+        self.limits.get(kind).copied().unwrap_or(self.max_retries)
+    }
+
+    /// Returns true if a request that failed with `kind` after `retries`
+    /// retries should be attempted again.
+    #[must_use]
+    pub fn should_retry(&self, kind: &FailureKind, retries: u32) -> bool {
+        (self.retryable)(kind) && retries < self.limit_for(kind)
+    }
+
+    /// Returns the delay before retry number `attempt` (starting at 1).
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let base = self.base_delay.as_millis() as u64;
+        let raw = match self.backoff {
+            Backoff::Constant => base,
+            Backoff::Linear => base.saturating_mul(u64::from(attempt)),
+            Backoff::Exponential => base.saturating_mul(1u64 << attempt.min(10)),
+        };
+        let capped = raw.min(self.max_delay.as_millis() as u64);
+
+        let millis = match self.jitter {
+            Jitter::None => capped,
+            Jitter::Deterministic => {
+                // Simple deterministic jitter (±25%) based on the attempt number
+                let jitter_range = capped / 4;
+                let jitter = if jitter_range > 0 {
+                    let jitter_offset = (u64::from(attempt) * 17) % (jitter_range * 2);
+                    jitter_offset.saturating_sub(jitter_range)
+                } else {
+                    0
+                };
+                (capped as i64 + jitter as i64).max(100) as u64
+            }
+            Jitter::Full => random_below(capped + 1),
+            Jitter::Equal => capped / 2 + random_below(capped / 2 + 1),
+        };
+        Duration::from_millis(millis)
+    }
+
+    /// Returns the delay to wait instead of [`Self::delay`] when the server
+    /// asked for `retry_after`, or `None` to use the computed backoff.
+    #[must_use]
+    pub fn server_delay(&self, retry_after: Option<Duration>) -> Option<Duration> {
+        retry_after
+            .filter(|_| self.honor_retry_after)
+            .map(|delay| delay.min(self.max_retry_after))
+    }
+}
+
+/// Parses a `Retry-After` header value, either a number of seconds or an
+/// HTTP date, into the delay from `now`.
+///
+/// Dates in the past give a zero delay.
+pub(crate) fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Returns a random value in `0..bound` (or 0 if `bound` is 0).
+fn random_below(bound: u64) -> u64 {
+    if bound == 0 {
+        return 0;
+    }
+    // RandomState is seeded randomly per instance, avoiding a RNG dependency.
+    RandomState::new().hash_one(0u8) % bound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_delays() {
+        let policy = RetryPolicy::default();
+
+        // First attempt: base_delay * 2 = 1000ms (plus jitter)
+        let delay1 = policy.delay(1);
+        assert!(delay1.as_millis() >= 750 && delay1.as_millis() <= 1250);
+
+        // Second attempt: base_delay * 4 = 2000ms (plus jitter)
+        let delay2 = policy.delay(2);
+        assert!(delay2.as_millis() >= 1500 && delay2.as_millis() <= 2500);
+
+        // High attempt should be capped at max_delay
+        let delay_high = policy.delay(20);
+        assert!(delay_high.as_millis() <= 37500); // max_delay + 25% jitter
+    }
+
+    #[test]
+    fn test_backoff_curves() {
+        let policy = RetryPolicy::default()
+            .with_jitter(Jitter::None)
+            .with_delays(Duration::from_millis(100), Duration::from_secs(1));
+
+        let constant = policy.clone().with_backoff(Backoff::Constant);
+        assert_eq!(constant.delay(5), Duration::from_millis(100));
+
+        let linear = policy.clone().with_backoff(Backoff::Linear);
+        assert_eq!(linear.delay(3), Duration::from_millis(300));
+
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+        assert_eq!(policy.delay(4), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_random_jitter_bounds() {
+        let policy =
+            RetryPolicy::default().with_delays(Duration::from_secs(1), Duration::from_secs(1));
+
+        for _ in 0..50 {
+            let full = policy.clone().with_jitter(Jitter::Full).delay(3);
+            assert!(full <= Duration::from_secs(1));
+
+            let equal = policy.clone().with_jitter(Jitter::Equal).delay(3);
+            assert!(equal >= Duration::from_millis(500) && equal <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_per_kind_limits() {
+        let policy = RetryPolicy::default()
+            .with_max_retries(3)
+            .with_status_limit(429, 20)
+            .with_limit(FailureKind::Connect, 0);
+
+        assert!(policy.should_retry(&FailureKind::Status(429), 10));
+        assert!(!policy.should_retry(&FailureKind::Status(503), 3));
+        assert!(policy.should_retry(&FailureKind::Status(503), 2));
+        assert!(!policy.should_retry(&FailureKind::Connect, 0));
+        assert!(policy.should_retry(&FailureKind::Timeout, 0));
+
+        // Client errors are not retryable by default.
+        assert!(!policy.should_retry(&FailureKind::Status(403), 0));
+    }
+
+    #[test]
+    fn test_custom_predicate() {
+        let policy =
+            RetryPolicy::default().with_retryable(|kind| matches!(kind, FailureKind::Status(503)));
+        assert!(policy.should_retry(&FailureKind::Status(503), 0));
+        assert!(!policy.should_retry(&FailureKind::Timeout, 0));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+
+        let policy = RetryPolicy::default().with_max_retry_after(Duration::from_secs(60));
+        let requested = Some(Duration::from_secs(90));
+        assert_eq!(
+            policy.server_delay(requested),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(policy.server_delay(None), None);
+        assert_eq!(policy.with_retry_after(false).server_delay(requested), None);
+    }
+}
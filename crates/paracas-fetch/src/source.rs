@@ -0,0 +1,97 @@
+//! Where the download client reads bi5 files from.
+//!
+//! Besides the Dukascopy HTTP feed, a client can read archived raw files from
+//! a local directory laid out like the feed URLs
+//! (`<root>/EURUSD/2024/00/15/12h_ticks.bi5`), e.g. a copy of the disk cache.
+//! This allows re-processing data without network access.
+
+use bytes::Bytes;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::url::relative_path;
+
+/// Source of raw bi5 files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DataSource {
+    /// Download from the Dukascopy HTTP feed.
+    #[default]
+    Http,
+    /// Read files from a local directory tree mirroring the feed URLs.
+    ///
+    /// Missing and empty files are treated as hours without data.
+    Local(PathBuf),
+}
+
+impl DataSource {
+    /// Creates a local source rooted at `dir`.
+    #[must_use]
+    pub fn local(dir: impl Into<PathBuf>) -> Self {
+        Self::Local(dir.into())
+    }
+
+    /// Returns the root directory of a local source.
+    #[must_use]
+    pub fn local_dir(&self) -> Option<&Path> {
+        match self {
+            Self::Http => None,
+            Self::Local(dir) => Some(dir),
+        }
+    }
+
+    /// Returns true if files are downloaded over HTTP.
+    #[must_use]
+    pub const fn is_http(&self) -> bool {
+        matches!(self, Self::Http)
+    }
+}
+
+/// Reads the file for `url` from a local source rooted at `dir`.
+///
+/// Returns `Ok(None)` if the file is missing or empty, or if the URL does not
+/// map to a path under `dir`.
+pub(crate) async fn read_local(dir: &Path, url: &str) -> io::Result<Option<Bytes>> {
+    let Some(relative) = relative_path(url) else {
+        return Ok(None);
+    };
+    match tokio::fs::read(dir.join(relative)).await {
+        Ok(data) if data.is_empty() => Ok(None),
+        Ok(data) => Ok(Some(Bytes::from(data))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::tick_url;
+    use chrono::{TimeZone, Utc};
+
+    #[tokio::test]
+    async fn test_read_local() {
+        let dir = tempfile::tempdir().unwrap();
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let url = tick_url("eurusd", hour);
+
+        assert_eq!(read_local(dir.path(), &url).await.unwrap(), None);
+
+        let path = dir.path().join("EURUSD/2024/00/15/12h_ticks.bi5");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(read_local(dir.path(), &url).await.unwrap(), None);
+
+        std::fs::write(&path, b"raw").unwrap();
+        assert_eq!(
+            read_local(dir.path(), &url).await.unwrap(),
+            Some(Bytes::from_static(b"raw"))
+        );
+    }
+
+    #[test]
+    fn test_data_source_default() {
+        assert!(DataSource::default().is_http());
+        let source = DataSource::local("/data");
+        assert_eq!(source.local_dir(), Some(Path::new("/data")));
+    }
+}
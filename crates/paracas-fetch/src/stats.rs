@@ -0,0 +1,362 @@
+//! Download statistics shared across client clones.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Sentinel for "no request started yet".
+const UNSET: u64 = u64::MAX;
+
+/// Converts a duration to nanoseconds, saturating at `u64::MAX`.
+fn duration_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+#[derive(Debug)]
+struct Counters {
+    origin: Instant,
+    bytes_fetched: AtomicU64,
+    requests: AtomicU64,
+    retries: AtomicU64,
+    not_found: AtomicU64,
+    cache_hits: AtomicU64,
+    failures: AtomicU64,
+    revisions: AtomicU64,
+    refetches: AtomicU64,
+    late_retries: AtomicU64,
+    in_flight: AtomicU64,
+    peak_in_flight: AtomicU64,
+    request_nanos: AtomicU64,
+    max_request_nanos: AtomicU64,
+    slow_requests: AtomicU64,
+    decode_nanos: AtomicU64,
+    server_delays: AtomicU64,
+    server_delay_nanos: AtomicU64,
+    first_start_nanos: AtomicU64,
+    last_end_nanos: AtomicU64,
+}
+
+/// Accumulator for download statistics.
+///
+/// Every [`DownloadClient`](crate::DownloadClient) owns one, and clones of the
+/// client share it, so the counters cover all hours fetched by
+/// [`tick_stream`](crate::tick_stream) and
+/// [`tick_stream_resilient`](crate::tick_stream_resilient). Call
+/// [`snapshot`](Self::snapshot) to read a consistent-enough view at any time.
+#[derive(Debug, Clone)]
+pub struct DownloadStats {
+    counters: Arc<Counters>,
+}
+
+impl Default for DownloadStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadStats {
+    /// Creates an empty accumulator.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            counters: Arc::new(Counters {
+                origin: Instant::now(),
+                bytes_fetched: AtomicU64::new(0),
+                requests: AtomicU64::new(0),
+                retries: AtomicU64::new(0),
+                not_found: AtomicU64::new(0),
+                cache_hits: AtomicU64::new(0),
+                failures: AtomicU64::new(0),
+                revisions: AtomicU64::new(0),
+                refetches: AtomicU64::new(0),
+                late_retries: AtomicU64::new(0),
+                in_flight: AtomicU64::new(0),
+                peak_in_flight: AtomicU64::new(0),
+                request_nanos: AtomicU64::new(0),
+                max_request_nanos: AtomicU64::new(0),
+                slow_requests: AtomicU64::new(0),
+                decode_nanos: AtomicU64::new(0),
+                server_delays: AtomicU64::new(0),
+                server_delay_nanos: AtomicU64::new(0),
+                first_start_nanos: AtomicU64::new(UNSET),
+                last_end_nanos: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns a point-in-time copy of the counters.
+    #[must_use]
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let c = &self.counters;
+        let first = c.first_start_nanos.load(Ordering::Relaxed);
+        let last = c.last_end_nanos.load(Ordering::Relaxed);
+        let wall_time = if first == UNSET {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos(last.saturating_sub(first))
+        };
+
+        StatsSnapshot {
+            bytes_fetched: c.bytes_fetched.load(Ordering::Relaxed),
+            requests: c.requests.load(Ordering::Relaxed),
+            retries: c.retries.load(Ordering::Relaxed),
+            not_found: c.not_found.load(Ordering::Relaxed),
+            cache_hits: c.cache_hits.load(Ordering::Relaxed),
+            failures: c.failures.load(Ordering::Relaxed),
+            revisions: c.revisions.load(Ordering::Relaxed),
+            refetches: c.refetches.load(Ordering::Relaxed),
+            late_retries: c.late_retries.load(Ordering::Relaxed),
+            in_flight: c.in_flight.load(Ordering::Relaxed),
+            peak_in_flight: c.peak_in_flight.load(Ordering::Relaxed),
+            request_time: Duration::from_nanos(c.request_nanos.load(Ordering::Relaxed)),
+            max_request_time: Duration::from_nanos(c.max_request_nanos.load(Ordering::Relaxed)),
+            slow_requests: c.slow_requests.load(Ordering::Relaxed),
+            decode_time: Duration::from_nanos(c.decode_nanos.load(Ordering::Relaxed)),
+            server_delays: c.server_delays.load(Ordering::Relaxed),
+            server_delay_time: Duration::from_nanos(c.server_delay_nanos.load(Ordering::Relaxed)),
+            wall_time,
+        }
+    }
+
+    fn now_nanos(&self) -> u64 {
+        u64::try_from(self.counters.origin.elapsed().as_nanos()).unwrap_or(UNSET - 1)
+    }
+
+    /// Marks the start of a download.
+    pub(crate) fn record_start(&self) {
+        let now = self.now_nanos();
+        self.counters
+            .first_start_nanos
+            .fetch_min(now, Ordering::Relaxed);
+        let in_flight = self.counters.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.counters
+            .peak_in_flight
+            .fetch_max(in_flight, Ordering::Relaxed);
+    }
+
+    /// Marks the end of a download.
+    pub(crate) fn record_end(&self) {
+        let now = self.now_nanos();
+        self.counters
+            .last_end_nanos
+            .fetch_max(now, Ordering::Relaxed);
+        self.counters.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Records an HTTP request attempt.
+    pub(crate) fn record_request(&self) {
+        self.counters.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the latency of an HTTP attempt.
+    pub(crate) fn record_latency(&self, latency: Duration) {
+        let nanos = duration_nanos(latency);
+        self.counters
+            .request_nanos
+            .fetch_add(nanos, Ordering::Relaxed);
+        self.counters
+            .max_request_nanos
+            .fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Records an HTTP attempt slower than the configured threshold.
+    pub(crate) fn record_slow_request(&self) {
+        self.counters.slow_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records time spent decompressing and parsing an hour.
+    pub(crate) fn record_decode(&self, elapsed: Duration) {
+        self.counters
+            .decode_nanos
+            .fetch_add(duration_nanos(elapsed), Ordering::Relaxed);
+    }
+
+    /// Records a retry delay imposed by the server with `Retry-After`.
+    pub(crate) fn record_server_delay(&self, delay: Duration) {
+        self.counters.server_delays.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .server_delay_nanos
+            .fetch_add(duration_nanos(delay), Ordering::Relaxed);
+    }
+
+    /// Records a retried attempt.
+    pub(crate) fn record_retry(&self) {
+        self.counters.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a 404 response.
+    pub(crate) fn record_not_found(&self) {
+        self.counters.not_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records fetched payload bytes.
+    pub(crate) fn record_bytes(&self, bytes: usize) {
+        self.counters
+            .bytes_fetched
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records a file served from a cache instead of the network.
+    pub fn record_cache_hit(&self) {
+        self.counters.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a download that failed after exhausting retries.
+    pub(crate) fn record_failure(&self) {
+        self.counters.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a re-downloaded hour whose content changed upstream.
+    pub(crate) fn record_revision(&self) {
+        self.counters.revisions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an hour downloaded again after its data was found corrupt.
+    pub(crate) fn record_refetch(&self) {
+        self.counters.refetches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an empty hour downloaded again in case its data was late.
+    pub(crate) fn record_late_retry(&self) {
+        self.counters.late_retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time view of [`DownloadStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// Compressed bytes received from the server.
+    pub bytes_fetched: u64,
+    /// HTTP requests sent, including retries.
+    pub requests: u64,
+    /// Attempts that were retried.
+    pub retries: u64,
+    /// Hours with no data (404 responses).
+    pub not_found: u64,
+    /// Files served from a cache.
+    pub cache_hits: u64,
+    /// Downloads that failed after exhausting retries.
+    pub failures: u64,
+    /// Cached hours whose content changed when re-downloaded.
+    pub revisions: u64,
+    /// Hours downloaded again after their data was found corrupt.
+    pub refetches: u64,
+    /// Empty hours downloaded again in case their data was published late.
+    pub late_retries: u64,
+    /// Downloads currently in progress.
+    pub in_flight: u64,
+    /// Highest number of downloads in progress at once.
+    pub peak_in_flight: u64,
+    /// Summed latency of all HTTP attempts, from sending the request until
+    /// the body was read.
+    pub request_time: Duration,
+    /// Latency of the slowest HTTP attempt.
+    pub max_request_time: Duration,
+    /// HTTP attempts slower than
+    /// [`ClientConfig::slow_request_threshold`](crate::ClientConfig::slow_request_threshold).
+    pub slow_requests: u64,
+    /// CPU time spent decompressing and parsing hours, summed across
+    /// threads.
+    pub decode_time: Duration,
+    /// Retries that waited for a delay requested by the server with
+    /// `Retry-After` instead of the computed backoff.
+    pub server_delays: u64,
+    /// Total time spent waiting for server-requested delays.
+    pub server_delay_time: Duration,
+    /// Time between the first request starting and the last one finishing.
+    pub wall_time: Duration,
+}
+
+impl StatsSnapshot {
+    /// Returns the mean latency of HTTP attempts.
+    #[must_use]
+    pub fn mean_request_time(&self) -> Duration {
+        duration_nanos(self.request_time)
+            .checked_div(self.requests)
+            .map_or(Duration::ZERO, Duration::from_nanos)
+    }
+
+    /// Returns the average download throughput in bytes per second.
+    #[must_use]
+    pub fn bytes_per_second(&self) -> f64 {
+        let secs = self.wall_time.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes_fetched as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_snapshot() {
+        let stats = DownloadStats::new();
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot, StatsSnapshot::default());
+        assert!(snapshot.bytes_per_second().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_clones_share_counters() {
+        let stats = DownloadStats::new();
+        let clone = stats.clone();
+
+        clone.record_start();
+        clone.record_request();
+        clone.record_retry();
+        clone.record_bytes(1024);
+        clone.record_not_found();
+        clone.record_cache_hit();
+        std::thread::sleep(Duration::from_millis(1));
+        clone.record_end();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.retries, 1);
+        assert_eq!(snapshot.bytes_fetched, 1024);
+        assert_eq!(snapshot.not_found, 1);
+        assert_eq!(snapshot.cache_hits, 1);
+        assert_eq!(snapshot.in_flight, 0);
+        assert_eq!(snapshot.peak_in_flight, 1);
+        assert!(snapshot.wall_time >= Duration::from_millis(1));
+        assert!(snapshot.bytes_per_second() > 0.0);
+    }
+
+    #[test]
+    fn test_peak_in_flight() {
+        let stats = DownloadStats::new();
+        stats.record_start();
+        stats.record_start();
+        assert_eq!(stats.snapshot().in_flight, 2);
+        stats.record_end();
+        stats.record_start();
+        stats.record_end();
+        stats.record_end();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.in_flight, 0);
+        assert_eq!(snapshot.peak_in_flight, 2);
+    }
+
+    #[test]
+    fn test_latency_and_decode_time() {
+        let stats = DownloadStats::new();
+        for millis in [10, 30] {
+            stats.record_request();
+            stats.record_latency(Duration::from_millis(millis));
+        }
+        stats.record_slow_request();
+        stats.record_decode(Duration::from_millis(5));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.request_time, Duration::from_millis(40));
+        assert_eq!(snapshot.max_request_time, Duration::from_millis(30));
+        assert_eq!(snapshot.mean_request_time(), Duration::from_millis(20));
+        assert_eq!(snapshot.slow_requests, 1);
+        assert_eq!(snapshot.decode_time, Duration::from_millis(5));
+    }
+}
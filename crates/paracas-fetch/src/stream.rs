@@ -1,10 +1,27 @@
 //! Streaming tick download pipeline.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::channel::mpsc;
+use futures::future;
 use futures::stream::{self, Stream, StreamExt};
-use paracas_types::{DateRange, Instrument, ParacasError, Tick};
+use paracas_types::{DateRange, Instrument, ParacasError, Tick, TradingCalendar, is_forex_weekend};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use thiserror::Error;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
-use crate::{DownloadClient, decompress_bi5, parse_ticks, url::tick_url};
+use crate::late::{LateDataRetry, LateDataTracker};
+use crate::progress::{ProgressObserver, ProgressTracker};
+use crate::validate::{InvalidTick, PriceValidation};
+use crate::{
+    DecompressError, DownloadClient, DownloadError, DownloadStats, decompress_ticks, trace,
+    url::tick_url,
+};
 
 /// A batch of ticks from a single hour.
 #[derive(Debug, Clone)]
@@ -13,8 +30,13 @@ pub struct TickBatch {
     pub hour: DateTime<Utc>,
     /// The ticks in this batch.
     pub ticks: Vec<Tick>,
-    /// Whether this batch had an error that was skipped.
-    pub had_error: bool,
+    /// Why the hour was skipped, if it failed.
+    pub skipped: Option<SkipReason>,
+    /// Ticks that failed [`StreamOptions::validation`].
+    ///
+    /// With [`InvalidTickAction::Drop`](crate::InvalidTickAction::Drop) they are no longer in
+    /// [`Self::ticks`]; indices refer to the hour's decoded ticks.
+    pub invalid: Vec<InvalidTick>,
 }
 
 impl TickBatch {
@@ -24,17 +46,19 @@ impl TickBatch {
         Self {
             hour,
             ticks,
-            had_error: false,
+            skipped: None,
+            invalid: Vec::new(),
         }
     }
 
-    /// Creates a new tick batch that represents a skipped error.
+    /// Creates an empty batch for an hour skipped because of `reason`.
     #[must_use]
-    pub const fn skipped_error(hour: DateTime<Utc>) -> Self {
+    pub const fn skipped(hour: DateTime<Utc>, reason: SkipReason) -> Self {
         Self {
             hour,
             ticks: Vec::new(),
-            had_error: true,
+            skipped: Some(reason),
+            invalid: Vec::new(),
         }
     }
 
@@ -51,9 +75,290 @@ impl TickBatch {
     }
 
     /// Returns true if this batch had an error that was skipped.
+    ///
+    /// An empty batch without an error means no data was published for the
+    /// hour.
     #[must_use]
     pub const fn had_error(&self) -> bool {
-        self.had_error
+        self.skipped.is_some()
+    }
+}
+
+/// Classified cause of an hour skipped by a resilient stream.
+///
+/// Distinguishes a server that kept failing, which is worth retrying later,
+/// from data that was downloaded but is corrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SkipReason {
+    /// The server kept answering with an error status.
+    #[error("HTTP {status} after {attempts} attempts")]
+    HttpStatus {
+        /// The status of the last response.
+        status: u16,
+        /// Number of requests sent.
+        attempts: u32,
+    },
+
+    /// The requests kept timing out.
+    #[error("timed out after {attempts} attempts")]
+    Timeout {
+        /// Number of requests sent.
+        attempts: u32,
+    },
+
+    /// The connection kept failing, or a local source could not be read.
+    #[error("connection failed after {attempts} attempts")]
+    Network {
+        /// Number of requests sent.
+        attempts: u32,
+    },
+
+    /// The data could not be decompressed.
+    #[error("decompression failed after {attempts} downloads")]
+    Decompress {
+        /// Number of times the hour was downloaded.
+        attempts: u32,
+    },
+
+    /// The decompressed data was not valid tick data.
+    #[error("parse failed after {attempts} downloads")]
+    Parse {
+        /// Number of times the hour was downloaded.
+        attempts: u32,
+    },
+}
+
+impl SkipReason {
+    /// Returns the number of requests or downloads used before skipping.
+    #[must_use]
+    pub const fn attempts(&self) -> u32 {
+        match self {
+            Self::HttpStatus { attempts, .. }
+            | Self::Timeout { attempts }
+            | Self::Network { attempts }
+            | Self::Decompress { attempts }
+            | Self::Parse { attempts } => *attempts,
+        }
+    }
+
+    /// Returns a short name for the class of failure, e.g. `"timeout"`.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::HttpStatus { .. } => "http_status",
+            Self::Timeout { .. } => "timeout",
+            Self::Network { .. } => "network",
+            Self::Decompress { .. } => "decompress",
+            Self::Parse { .. } => "parse",
+        }
+    }
+
+    /// Returns true if the failure is likely to clear up on a later retry.
+    ///
+    /// Timeouts, connection failures, rate limiting and server errors are
+    /// transient; other statuses and corrupt data usually are not.
+    #[must_use]
+    pub const fn is_transient(&self) -> bool {
+        match self {
+            Self::HttpStatus { status, .. } => *status == 429 || *status >= 500,
+            Self::Timeout { .. } | Self::Network { .. } => true,
+            Self::Decompress { .. } | Self::Parse { .. } => false,
+        }
+    }
+
+    /// Classifies a failed download.
+    const fn from_download(error: &DownloadError) -> Self {
+        let attempts = error.attempts();
+        match error {
+            DownloadError::ServerError { status, .. } => Self::HttpStatus {
+                status: *status,
+                attempts,
+            },
+            DownloadError::Timeout(_) => Self::Timeout { attempts },
+            DownloadError::Http(_) | DownloadError::Connection { .. } | DownloadError::Io(_) => {
+                Self::Network { attempts }
+            }
+        }
+    }
+
+    /// Classifies data that failed to decode after `attempts` downloads.
+    const fn from_decode(error: &ParacasError, attempts: u32) -> Self {
+        match error {
+            ParacasError::Parse(_) => Self::Parse { attempts },
+            _ => Self::Decompress { attempts },
+        }
+    }
+}
+
+/// Why a resilient stream skipped an hour.
+#[derive(Debug, Error)]
+#[error("{hour}: {error}")]
+pub struct HourError {
+    /// The hour start timestamp.
+    pub hour: DateTime<Utc>,
+    /// The error that caused the hour to be skipped.
+    pub error: ParacasError,
+    /// The classified cause of the failure.
+    pub reason: SkipReason,
+    /// Whether the hour was downloaded again after its data was found corrupt.
+    pub refetched: bool,
+}
+
+/// Options controlling which hours a tick stream requests and how it reports.
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// Skip Saturday and early-Sunday hours, for which Dukascopy never has
+    /// forex data (see [`is_forex_weekend`]).
+    pub skip_weekends: bool,
+    /// Only request hours in which this calendar trades, or `None` to
+    /// request every hour not skipped otherwise.
+    ///
+    /// Use the instrument's [`calendar`](Instrument::calendar) to skip
+    /// closed sessions, e.g. nights for stocks.
+    pub calendar: Option<TradingCalendar>,
+    /// Yield batches in hour order instead of completion order.
+    ///
+    /// Downloads still run concurrently; completed hours are held back until
+    /// all earlier hours have been yielded.
+    pub ordered: bool,
+    /// Number of hours scheduled ahead of the consumer, or `None` to only
+    /// download while the stream is polled.
+    ///
+    /// By default at most [`ClientConfig::concurrency`](crate::ClientConfig::concurrency)
+    /// hours are in progress and they only advance while the consumer polls
+    /// the stream, so a slow consumer stalls the downloads. With a prefetch
+    /// window, scheduled hours download in background tasks, still at most
+    /// `concurrency` at once, and keep going while the consumer is busy.
+    /// Once the window is full of hours the consumer has not taken, no more
+    /// are scheduled, which bounds memory.
+    ///
+    /// Requires a Tokio runtime.
+    pub prefetch: Option<usize>,
+    /// Observer notified after every yielded hour.
+    pub progress: Option<ProgressObserver>,
+    /// Re-attempt empty hours during market hours whose neighbors have data,
+    /// or `None` to accept every 404 as a gap.
+    pub late_data: Option<LateDataRetry>,
+    /// Cap in bytes on the LZMA dictionary window used while decompressing
+    /// an hour, or `None` for no limit.
+    ///
+    /// Hours are decompressed straight into ticks, so this bounds the
+    /// memory used per in-flight hour besides the ticks themselves. Hours
+    /// needing a larger window fail to decompress.
+    pub decompress_memory_limit: Option<usize>,
+    /// Price sanity checks applied to every decoded hour, or `None` to keep
+    /// all ticks unchecked.
+    pub validation: Option<PriceValidation>,
+    /// Token that stops the stream when cancelled.
+    ///
+    /// On cancellation the stream ends immediately: in-flight downloads are
+    /// dropped, which aborts their requests, and the progress observer
+    /// receives a final update with [`StreamProgress::cancelled`] set.
+    ///
+    /// [`StreamProgress::cancelled`]: crate::StreamProgress::cancelled
+    pub cancel: Option<CancellationToken>,
+}
+
+impl StreamOptions {
+    /// Creates options suited to the instrument.
+    ///
+    /// Weekend hours are skipped for forex instruments.
+    #[must_use]
+    pub const fn for_instrument(instrument: &Instrument) -> Self {
+        Self {
+            skip_weekends: instrument.is_forex(),
+            calendar: None,
+            ordered: false,
+            prefetch: None,
+            progress: None,
+            late_data: None,
+            decompress_memory_limit: None,
+            validation: None,
+            cancel: None,
+        }
+    }
+
+    /// Sets whether weekend hours are skipped.
+    #[must_use]
+    pub const fn with_skip_weekends(mut self, skip: bool) -> Self {
+        self.skip_weekends = skip;
+        self
+    }
+
+    /// Restricts requests to hours in which `calendar` trades.
+    #[must_use]
+    pub const fn with_calendar(mut self, calendar: TradingCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Sets whether batches are yielded in hour order.
+    #[must_use]
+    pub const fn with_ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Sets the number of hours scheduled ahead of the consumer.
+    #[must_use]
+    pub const fn with_prefetch(mut self, hours: usize) -> Self {
+        self.prefetch = Some(hours);
+        self
+    }
+
+    /// Enables re-attempting empty hours whose data may be published late.
+    #[must_use]
+    pub const fn with_late_data_retry(mut self, retry: LateDataRetry) -> Self {
+        self.late_data = Some(retry);
+        self
+    }
+
+    /// Caps the LZMA dictionary window used while decompressing an hour.
+    #[must_use]
+    pub const fn with_decompress_memory_limit(mut self, bytes: usize) -> Self {
+        self.decompress_memory_limit = Some(bytes);
+        self
+    }
+
+    /// Checks the prices of every decoded hour.
+    #[must_use]
+    pub const fn with_validation(mut self, validation: PriceValidation) -> Self {
+        self.validation = Some(validation);
+        self
+    }
+
+    /// Stops the stream when `token` is cancelled.
+    #[must_use]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Sets the progress observer.
+    #[must_use]
+    pub fn with_progress(mut self, observer: ProgressObserver) -> Self {
+        self.progress = Some(observer);
+        self
+    }
+
+    /// Returns the hours of `range` that will be requested.
+    pub fn hours(&self, range: DateRange) -> impl Iterator<Item = DateTime<Utc>> + use<> {
+        let skip_weekends = self.skip_weekends;
+        let calendar = self.calendar;
+        range.hours().filter(move |hour| {
+            !(skip_weekends && is_forex_weekend(*hour))
+                && calendar.is_none_or(|calendar| calendar.is_trading_hour(*hour))
+        })
+    }
+
+    /// Returns the number of hours of `range` that will be requested.
+    #[must_use]
+    pub fn hour_count(&self, range: DateRange) -> usize {
+        if self.skip_weekends || self.calendar.is_some() {
+            self.hours(range).count()
+        } else {
+            range.total_hours()
+        }
     }
 }
 
@@ -71,58 +376,273 @@ impl TickBatch {
 /// # Returns
 ///
 /// An async stream of tick batches, one per hour.
+///
+/// Download statistics accumulate on [`DownloadClient::stats`].
 pub fn tick_stream<'a>(
     client: &'a DownloadClient,
     instrument: &'a Instrument,
     range: DateRange,
 ) -> impl Stream<Item = Result<TickBatch, ParacasError>> + 'a {
-    let decimal_factor = instrument.decimal_factor_f64();
+    tick_stream_with_options(client, instrument, range, StreamOptions::default())
+}
+
+/// Creates an async stream of tick batches, as [`tick_stream`], using the
+/// given stream options.
+pub fn tick_stream_with_options<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    options: StreamOptions,
+) -> impl Stream<Item = Result<TickBatch, ParacasError>> + 'a {
+    let decoder = Decoder::new(instrument, &options);
     let instrument_id = instrument.id().to_string();
+
+    let late = options
+        .late_data
+        .map(|retry| LateDataTracker::new(retry, instrument));
+
+    let downloads = stream::iter(options.hours(range)).map(move |hour| {
+        let url = tick_url(&instrument_id, hour);
+        let client = client.clone();
+        let late = late.clone();
+        let fetch = async move {
+            let result = client.download(&url).await;
+            // Process immediately after download (decompression is offloaded to spawn_blocking)
+            let batch = process_download_result(hour, result, decoder, client.stats()).await?;
+            Ok(recheck_late(late.as_ref(), &client, &url, batch, decoder).await)
+        };
+        trace::hour(&instrument_id, hour, fetch)
+    });
+
+    let total_hours = options.hour_count(range);
+    run_downloads(downloads, client, options, total_hours)
+}
+
+/// Output of an hour's download, counted by the progress tracker.
+pub(crate) trait HourOutput {
+    /// Returns the number of ticks downloaded.
+    fn tick_count(&self) -> usize;
+}
+
+impl HourOutput for TickBatch {
+    fn tick_count(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K> HourOutput for (K, TickBatch) {
+    fn tick_count(&self) -> usize {
+        self.1.len()
+    }
+}
+
+/// Runs per-hour downloads with the client's concurrency.
+///
+/// Every yielded hour is reported to the progress observer. If the options'
+/// cancellation token fires, the stream ends at once, dropping in-flight
+/// downloads, and a final cancelled update is reported.
+///
+/// With [`StreamOptions::prefetch`] set, downloads are spawned as tasks
+/// limited by a semaphore; dropping the stream aborts them.
+pub(crate) fn run_downloads<'a, T, E>(
+    downloads: impl Stream<Item = impl Future<Output = Result<T, E>> + Send + 'static> + 'a,
+    client: &DownloadClient,
+    options: StreamOptions,
+    total_hours: usize,
+) -> impl Stream<Item = Result<T, E>> + 'a
+where
+    T: HourOutput + Send + 'static,
+    E: Send + 'static,
+{
     let concurrency = client.config().concurrency;
+    let tracker = Arc::new(Mutex::new(ProgressTracker::new(
+        options.progress.clone(),
+        client.stats(),
+        total_hours,
+    )));
+
+    let results = match options.prefetch {
+        Some(window) => {
+            let permits = Arc::new(Semaphore::new(concurrency.max(1)));
+            let spawned = downloads.map(move |download| {
+                let permits = Arc::clone(&permits);
+                Prefetched(tokio::spawn(async move {
+                    // The semaphore is never closed
+                    let _permit = permits.acquire_owned().await;
+                    download.await
+                }))
+            });
+            let window = window.max(1);
+            if options.ordered {
+                spawned.buffered(window).left_stream().left_stream()
+            } else {
+                spawned
+                    .buffer_unordered(window)
+                    .right_stream()
+                    .left_stream()
+            }
+        }
+        None if options.ordered => downloads.buffered(concurrency).left_stream().right_stream(),
+        None => downloads
+            .buffer_unordered(concurrency)
+            .right_stream()
+            .right_stream(),
+    };
+    let recorder = Arc::clone(&tracker);
+    let results = results.inspect(move |result| {
+        let mut tracker = recorder.lock().unwrap_or_else(PoisonError::into_inner);
+        match result {
+            Ok(output) => tracker.record(output.tick_count(), false),
+            Err(_) => tracker.record(0, true),
+        }
+    });
 
-    stream::iter(range.hours())
-        .map(move |hour| {
-            let url = tick_url(&instrument_id, hour);
-            let client = client.clone();
-            async move {
-                let result = client.download(&url).await;
-                // Process immediately after download (decompression is offloaded to spawn_blocking)
-                process_download_result(hour, result, decimal_factor).await
+    // Boxed so the stream stays `Unpin` like the uncancellable pipeline.
+    let cancel = options.cancel;
+    results.take_until(Box::pin(async move {
+        match cancel {
+            Some(token) => {
+                token.cancelled_owned().await;
+                tracker
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .cancel();
             }
+            None => future::pending().await,
+        }
+    }))
+}
+
+/// A download spawned ahead of the consumer, aborted when dropped.
+struct Prefetched<T>(JoinHandle<T>);
+
+impl<T> Future for Prefetched<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        Pin::new(&mut self.0).poll(cx).map(|result| {
+            // Tasks are only aborted on drop, so this is a panic to forward
+            result.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
         })
-        .buffer_unordered(concurrency)
+    }
 }
 
-/// Processes a download result into a tick batch.
+impl<T> Drop for Prefetched<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Downloads and parses the ticks for a single hour.
+///
+/// Returns an empty batch if the hour has no data.
 ///
-/// Decompression is offloaded to a blocking thread pool to avoid blocking
-/// the async executor.
+/// # Errors
+///
+/// Returns an error if the download, decompression, or parsing fails.
+pub async fn fetch_hour(
+    client: &DownloadClient,
+    instrument: &Instrument,
+    hour: DateTime<Utc>,
+) -> Result<TickBatch, ParacasError> {
+    let url = tick_url(instrument.id(), hour);
+    let fetch = async {
+        let result = client.download(&url).await;
+        let decoder = Decoder::new(instrument, &StreamOptions::default());
+        process_download_result(hour, result, decoder, client.stats()).await
+    };
+    trace::hour(instrument.id(), hour, fetch).await
+}
+
+/// Processes a download result into a tick batch.
 async fn process_download_result(
     hour: DateTime<Utc>,
     result: Result<Option<bytes::Bytes>, crate::DownloadError>,
-    decimal_factor: f64,
+    decoder: Decoder,
+    stats: &DownloadStats,
 ) -> Result<TickBatch, ParacasError> {
-    match result {
-        Ok(Some(compressed)) => {
-            // Offload CPU-intensive LZMA decompression to blocking thread pool
-            let decompressed = tokio::task::spawn_blocking(move || decompress_bi5(&compressed))
-                .await
-                .map_err(|e| ParacasError::Decompress(format!("spawn_blocking failed: {e}")))?
-                .map_err(|e| ParacasError::Decompress(e.to_string()))?;
+    let data = result.map_err(|e| ParacasError::Http(e.to_string()))?;
+    decode_hour(hour, data, decoder, stats).await
+}
 
-            let ticks: Vec<Tick> = parse_ticks(&decompressed)
-                .map_err(|e| ParacasError::Parse(e.to_string()))?
-                .map(|raw| raw.normalize(hour, decimal_factor))
-                .collect();
+/// How downloaded hours are decoded into ticks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Decoder {
+    decimal_factor: f64,
+    memory_limit: Option<usize>,
+    validation: Option<PriceValidation>,
+}
 
-            Ok(TickBatch::new(hour, ticks))
-        }
-        Ok(None) => {
-            // No data for this hour
-            Ok(TickBatch::new(hour, Vec::new()))
+impl Decoder {
+    pub(crate) fn new(instrument: &Instrument, options: &StreamOptions) -> Self {
+        Self {
+            decimal_factor: instrument.decimal_factor_f64(),
+            memory_limit: options.decompress_memory_limit,
+            validation: options.validation,
         }
-        Err(e) => Err(ParacasError::Http(e.to_string())),
     }
+
+    /// Decompresses an hour's data straight into normalized ticks.
+    fn decode(self, hour: DateTime<Utc>, compressed: &[u8]) -> Result<Vec<Tick>, DecompressError> {
+        let mut ticks = Vec::new();
+        decompress_ticks(compressed, self.memory_limit, |raw| {
+            ticks.push(raw.normalize(hour, self.decimal_factor));
+        })?;
+        Ok(ticks)
+    }
+}
+
+/// Decompresses and parses the downloaded data for an hour.
+///
+/// `None` (no data for this hour) yields an empty batch. Decompression is
+/// offloaded to a blocking thread pool to avoid blocking the async executor.
+///
+/// Data that fails to decompress or parse, or that contains ticks outside the
+/// hour, is reported as corrupt.
+async fn decode_hour(
+    hour: DateTime<Utc>,
+    data: Option<bytes::Bytes>,
+    decoder: Decoder,
+    stats: &DownloadStats,
+) -> Result<TickBatch, ParacasError> {
+    let Some(compressed) = data else {
+        return Ok(TickBatch::new(hour, Vec::new()));
+    };
+
+    // Offload CPU-intensive LZMA decompression to blocking thread pool
+    let stats = stats.clone();
+    let mut ticks = tokio::task::spawn_blocking(trace::propagate(move || {
+        let started = Instant::now();
+        let ticks = trace::decompress(compressed.len(), || decoder.decode(hour, &compressed));
+        stats.record_decode(started.elapsed());
+        ticks
+    }))
+    .await
+    .map_err(|e| ParacasError::Decompress(format!("spawn_blocking failed: {e}")))?
+    .map_err(|e| match e {
+        DecompressError::Parse(e) => ParacasError::Parse(e.to_string()),
+        e => ParacasError::Decompress(e.to_string()),
+    })?;
+
+    let hour_end = hour + TimeDelta::hours(1);
+    if let Some(tick) = ticks
+        .iter()
+        .find(|tick| tick.timestamp < hour || tick.timestamp >= hour_end)
+    {
+        return Err(ParacasError::Parse(format!(
+            "tick at {} is outside hour {hour}",
+            tick.timestamp
+        )));
+    }
+
+    let invalid = decoder
+        .validation
+        .map(|validation| validation.apply(&mut ticks))
+        .unwrap_or_default();
+    Ok(TickBatch {
+        invalid,
+        ..TickBatch::new(hour, ticks)
+    })
 }
 
 /// Creates a resilient async stream that skips failed hours instead of failing entirely.
@@ -139,69 +659,150 @@ async fn process_download_result(
 /// # Returns
 ///
 /// An async stream of tick batches. Failed hours are returned as empty batches
-/// with `had_error` set to true.
+/// whose [`TickBatch::skipped`] reason tells a server that kept failing apart
+/// from corrupt data; hours without published data are empty batches with
+/// no reason. Hours whose data is corrupt are downloaded once more, bypassing
+/// caches, before being skipped.
+///
+/// Download statistics accumulate on [`DownloadClient::stats`].
 pub fn tick_stream_resilient<'a>(
     client: &'a DownloadClient,
     instrument: &'a Instrument,
     range: DateRange,
 ) -> impl Stream<Item = TickBatch> + 'a {
-    let decimal_factor = instrument.decimal_factor_f64();
-    let instrument_id = instrument.id().to_string();
-    let concurrency = client.config().concurrency;
+    tick_stream_resilient_with_options(client, instrument, range, StreamOptions::default())
+}
 
-    stream::iter(range.hours())
-        .map(move |hour| {
-            let url = tick_url(&instrument_id, hour);
-            let client = client.clone();
-            async move {
-                let result = client.download(&url).await;
-                // Process immediately after download (decompression is offloaded to spawn_blocking)
-                process_download_result_resilient(hour, result, decimal_factor).await
-            }
+/// Creates a resilient async stream, as [`tick_stream_resilient`], using the
+/// given stream options.
+pub fn tick_stream_resilient_with_options<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    options: StreamOptions,
+) -> impl Stream<Item = TickBatch> + 'a {
+    hour_results(client, instrument, range, options)
+        .map(|result| result.unwrap_or_else(|e| TickBatch::skipped(e.hour, e.reason)))
+}
+
+/// Creates a resilient async stream, as
+/// [`tick_stream_resilient_with_options`], that also reports why hours were
+/// skipped.
+///
+/// Returns the batch stream and a stream of [`HourError`]s, one for every
+/// batch yielded with a skip reason. The error stream ends once the batch
+/// stream has finished or been dropped; poll it alongside or after the batch
+/// stream, as errors are buffered until read.
+pub fn tick_stream_resilient_split<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    options: StreamOptions,
+) -> (
+    impl Stream<Item = TickBatch> + 'a,
+    impl Stream<Item = HourError> + use<>,
+) {
+    let (errors_tx, errors_rx) = mpsc::unbounded();
+    let batches = hour_results(client, instrument, range, options).map(move |result| {
+        result.unwrap_or_else(|e| {
+            let batch = TickBatch::skipped(e.hour, e.reason);
+            // The receiver may have been dropped; the batch still reports the error.
+            let _ = errors_tx.unbounded_send(e);
+            batch
         })
-        .buffer_unordered(concurrency)
+    });
+    (batches, errors_rx)
+}
+
+/// Downloads every hour of `range`, keeping the error for failed hours.
+fn hour_results<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    options: StreamOptions,
+) -> impl Stream<Item = Result<TickBatch, HourError>> + 'a {
+    let decoder = Decoder::new(instrument, &options);
+    let instrument_id = instrument.id().to_string();
+
+    let late = options
+        .late_data
+        .map(|retry| LateDataTracker::new(retry, instrument));
+
+    let downloads = stream::iter(options.hours(range)).map(move |hour| {
+        let url = tick_url(&instrument_id, hour);
+        let client = client.clone();
+        let late = late.clone();
+        let fetch = async move {
+            let batch = fetch_hour_with_refetch(&client, &url, hour, decoder).await?;
+            Ok(recheck_late(late.as_ref(), &client, &url, batch, decoder).await)
+        };
+        trace::hour(&instrument_id, hour, fetch)
+    });
+
+    let total_hours = options.hour_count(range);
+    run_downloads(downloads, client, options, total_hours)
 }
 
-/// Processes a download result into a tick batch, skipping errors.
+/// Downloads and decodes an hour.
 ///
-/// Decompression is offloaded to a blocking thread pool to avoid blocking
-/// the async executor.
-async fn process_download_result_resilient(
+/// If the data turns out to be corrupt, the hour is downloaded once more with
+/// [`DownloadClient::refetch`] before giving up, since corrupt responses are
+/// usually transient.
+pub(crate) async fn fetch_hour_with_refetch(
+    client: &DownloadClient,
+    url: &str,
     hour: DateTime<Utc>,
-    result: Result<Option<bytes::Bytes>, crate::DownloadError>,
-    decimal_factor: f64,
+    decoder: Decoder,
+) -> Result<TickBatch, HourError> {
+    let download_failed = |error: DownloadError, refetched| HourError {
+        hour,
+        reason: SkipReason::from_download(&error),
+        error: ParacasError::Http(error.to_string()),
+        refetched,
+    };
+    let data = client
+        .download(url)
+        .await
+        .map_err(|e| download_failed(e, false))?;
+    if let Ok(batch) = decode_hour(hour, data, decoder, client.stats()).await {
+        return Ok(batch);
+    }
+
+    client.stats().record_refetch();
+    let data = client
+        .refetch(url)
+        .await
+        .map_err(|e| download_failed(e, true))?;
+    decode_hour(hour, data, decoder, client.stats())
+        .await
+        .map_err(|error| HourError {
+            hour,
+            reason: SkipReason::from_decode(&error, 2),
+            error,
+            refetched: true,
+        })
+}
+
+/// Re-attempts an empty hour when late-data retries are enabled.
+pub(crate) async fn recheck_late(
+    late: Option<&LateDataTracker>,
+    client: &DownloadClient,
+    url: &str,
+    batch: TickBatch,
+    decoder: Decoder,
 ) -> TickBatch {
-    match result {
-        Ok(Some(compressed)) => {
-            // Offload CPU-intensive LZMA decompression to blocking thread pool
-            let decompress_result =
-                tokio::task::spawn_blocking(move || decompress_bi5(&compressed)).await;
-
-            match decompress_result {
-                Ok(Ok(decompressed)) => parse_ticks(&decompressed).map_or_else(
-                    |_| TickBatch::skipped_error(hour),
-                    |raw_ticks| {
-                        let ticks: Vec<Tick> = raw_ticks
-                            .map(|raw| raw.normalize(hour, decimal_factor))
-                            .collect();
-                        TickBatch::new(hour, ticks)
-                    },
-                ),
-                _ => {
-                    // Decompression error or spawn_blocking failed - return empty batch with error flag
-                    TickBatch::skipped_error(hour)
-                }
-            }
+    let Some(late) = late else {
+        return batch;
+    };
+    let hour = batch.hour;
+    late.recheck(batch, || {
+        client.stats().record_late_retry();
+        async move {
+            let result = client.refetch(url).await;
+            process_download_result(hour, result, decoder, client.stats()).await
         }
-        Ok(None) => {
-            // No data for this hour
-            TickBatch::new(hour, Vec::new())
-        }
-        Err(_) => {
-            // HTTP error - return empty batch with error flag
-            TickBatch::skipped_error(hour)
-        }
-    }
+    })
+    .await
 }
 
 /// Flattens a tick batch stream into individual ticks.
@@ -217,6 +818,66 @@ pub fn flatten_ticks(
     })
 }
 
+/// Creates a stream of fixed-size tick chunks for the given instrument and
+/// date range.
+///
+/// Hours are downloaded as by [`tick_stream`] but yielded in order, and their
+/// ticks are regrouped into vectors of exactly `chunk_size` ticks across hour
+/// boundaries; only the last chunk may be shorter. This suits writers that
+/// want uniform batches, such as Parquet row groups or database inserts.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+pub fn tick_stream_chunks<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    chunk_size: usize,
+) -> impl Stream<Item = Result<Vec<Tick>, ParacasError>> + 'a {
+    let options = StreamOptions::default().with_ordered(true);
+    chunk_ticks(
+        tick_stream_with_options(client, instrument, range, options),
+        chunk_size,
+    )
+}
+
+/// Regroups a tick batch stream into chunks of `chunk_size` ticks.
+///
+/// Every chunk but the last holds exactly `chunk_size` ticks. Errors are
+/// passed through without discarding buffered ticks.
+///
+/// # Panics
+///
+/// Panics if `chunk_size` is zero.
+pub fn chunk_ticks<'a>(
+    batch_stream: impl Stream<Item = Result<TickBatch, ParacasError>> + 'a,
+    chunk_size: usize,
+) -> impl Stream<Item = Result<Vec<Tick>, ParacasError>> + 'a {
+    assert!(chunk_size > 0, "chunk size must be positive");
+    let state = (Box::pin(batch_stream), VecDeque::new(), false);
+    Box::pin(stream::unfold(
+        state,
+        move |(mut batches, mut pending, mut done)| async move {
+            loop {
+                if pending.len() >= chunk_size || (done && !pending.is_empty()) {
+                    let take = chunk_size.min(pending.len());
+                    let chunk = pending.drain(..take).collect();
+                    return Some((Ok(chunk), (batches, pending, done)));
+                }
+                if done {
+                    return None;
+                }
+                match batches.next().await {
+                    Some(Ok(batch)) => pending.extend(batch.ticks),
+                    Some(Err(e)) => return Some((Err(e), (batches, pending, done))),
+                    None => done = true,
+                }
+            }
+        },
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -231,10 +892,273 @@ mod tests {
     }
 
     #[test]
-    fn test_tick_batch_skipped_error() {
+    fn test_stream_options_skip_weekends() {
+        use chrono::NaiveDate;
+
+        // Friday 2024-01-12 through Monday 2024-01-15.
+        let range = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+        )
+        .unwrap();
+
+        let all = StreamOptions::default();
+        assert_eq!(all.hour_count(range), 96);
+
+        let trading = StreamOptions::default().with_skip_weekends(true);
+        assert_eq!(trading.hour_count(range), 96 - 24 - 20);
+        assert!(trading.hours(range).all(|hour| !is_forex_weekend(hour)));
+    }
+
+    #[test]
+    fn test_stream_options_calendar() {
+        use chrono::NaiveDate;
+
+        // Friday 2024-01-12 through Monday 2024-01-15.
+        let range = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+        )
+        .unwrap();
+
+        let session = TradingCalendar::Session {
+            open: 13,
+            close: 20,
+        };
+        let options = StreamOptions::default().with_calendar(session);
+        assert_eq!(options.hour_count(range), 2 * 8);
+        assert_eq!(
+            options.hour_count(range),
+            range.total_trading_hours(session)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_hour() {
+        let hour = Utc::now();
+        let decoder = Decoder {
+            decimal_factor: 100_000.0,
+            memory_limit: None,
+            validation: None,
+        };
+        let batch = decode_hour(hour, None, decoder, &DownloadStats::new())
+            .await
+            .unwrap();
+        assert!(batch.is_empty());
+        assert!(!batch.had_error());
+
+        let corrupt = bytes::Bytes::from_static(b"not lzma");
+        assert!(
+            decode_hour(hour, Some(corrupt), decoder, &DownloadStats::new())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_hour_validates_prices() {
+        use crate::InvalidTickAction;
+        use chrono::TimeZone;
+        use std::io::Cursor;
+
+        let mut raw = Vec::new();
+        for (ms, ask, bid) in [
+            (0u32, 110_002u32, 110_000u32),
+            (1, 0, 110_000),
+            (2, 110_003, 110_001),
+        ] {
+            for field in [ms, ask, bid] {
+                raw.extend_from_slice(&field.to_be_bytes());
+            }
+            raw.extend_from_slice(&[0; 8]);
+        }
+        let mut compressed = Vec::new();
+        lzma_rs::lzma_compress(&mut Cursor::new(&raw), &mut compressed).unwrap();
+        let data = Some(bytes::Bytes::from(compressed));
+
+        let hour = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        let validation = PriceValidation::default().with_action(InvalidTickAction::Drop);
+        let decoder = Decoder {
+            decimal_factor: 100_000.0,
+            memory_limit: None,
+            validation: Some(validation),
+        };
+        let batch = decode_hour(hour, data, decoder, &DownloadStats::new())
+            .await
+            .unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.invalid.len(), 1);
+        assert_eq!(batch.invalid[0].index, 1);
+    }
+
+    #[tokio::test]
+    async fn test_resilient_split_reports_corrupt_hour() {
+        use crate::{ClientConfig, DataSource};
+        use chrono::{NaiveDate, Timelike};
+        use paracas_types::Category;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("EURUSD/2024/00/15/03h_ticks.bi5");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"not lzma").unwrap();
+
+        let config = ClientConfig {
+            source: DataSource::local(dir.path()),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let instrument = &Instrument::new("eurusd", "EUR/USD", "", Category::Forex, 100_000, None);
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let range = DateRange::new(day, day).unwrap();
+
+        let (batches, errors) =
+            tick_stream_resilient_split(&client, instrument, range, StreamOptions::default());
+        let batches: Vec<_> = batches.collect().await;
+        let errors: Vec<_> = errors.collect().await;
+
+        assert_eq!(batches.len(), 24);
+        assert_eq!(batches.iter().filter(|b| b.had_error()).count(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].hour.hour(), 3);
+        assert_eq!(errors[0].reason, SkipReason::Decompress { attempts: 2 });
+        let skipped = batches.iter().find(|b| b.had_error()).unwrap();
+        assert_eq!(skipped.skipped, Some(errors[0].reason));
+        assert!(errors[0].refetched);
+        assert_eq!(client.stats().snapshot().refetches, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_stream_ends_and_reports() {
+        use crate::{ClientConfig, DataSource, ProgressObserver};
+        use chrono::NaiveDate;
+        use paracas_types::Category;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ClientConfig {
+            source: DataSource::local(dir.path()),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let instrument = &Instrument::new("eurusd", "EUR/USD", "", Category::Forex, 100_000, None);
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let range = DateRange::new(day, day).unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let (observer, rx) = ProgressObserver::channel();
+        let options = StreamOptions::default()
+            .with_progress(observer)
+            .with_cancellation(token);
+
+        let batches: Vec<_> =
+            tick_stream_resilient_with_options(&client, instrument, range, options)
+                .collect()
+                .await;
+
+        assert!(batches.is_empty());
+        let progress = *rx.borrow();
+        assert!(progress.cancelled);
+        assert_eq!(progress.hours_total, 24);
+        assert_eq!(progress.hours_completed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_downloads_while_consumer_is_busy() {
+        use crate::{ClientConfig, DataSource};
+        use chrono::NaiveDate;
+        use paracas_types::Category;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ClientConfig {
+            concurrency: 1,
+            source: DataSource::local(dir.path()),
+            ..ClientConfig::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let instrument = &Instrument::new("eurusd", "EUR/USD", "", Category::Forex, 100_000, None);
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let range = DateRange::single_day(day).with_hours(0, 5).unwrap();
+
+        let mut hours_fetched = Vec::new();
+        for options in [
+            StreamOptions::default().with_ordered(true),
+            StreamOptions::default().with_ordered(true).with_prefetch(3),
+        ] {
+            let before = client.stats().snapshot().not_found;
+            let mut stream = tick_stream_with_options(&client, instrument, range, options);
+            let first = stream.next().await.unwrap().unwrap();
+            assert_eq!(first.hour, range.start_datetime());
+
+            // A slow consumer: only prefetched hours progress meanwhile
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            hours_fetched.push(client.stats().snapshot().not_found - before);
+
+            let rest: Vec<_> = stream.map(|batch| batch.unwrap().hour).collect().await;
+            assert_eq!(rest.len(), 5);
+            assert!(rest.is_sorted());
+        }
+        assert_eq!(hours_fetched, [1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_ticks_across_hours() {
+        let hour = Utc::now();
+        let batch = |n: usize| {
+            let ticks = (0..n)
+                .map(|i| Tick::new(hour, i as f64, 0.0, 1.0, 1.0))
+                .collect();
+            Ok(TickBatch::new(hour, ticks))
+        };
+        let batches = stream::iter(vec![batch(3), batch(0), batch(6), batch(1)]);
+
+        let chunks: Vec<_> = chunk_ticks(batches, 4)
+            .map(|chunk| chunk.unwrap().len())
+            .collect()
+            .await;
+        assert_eq!(chunks, [4, 4, 2]);
+    }
+
+    #[test]
+    fn test_tick_batch_skipped() {
         let hour = Utc::now();
-        let batch = TickBatch::skipped_error(hour);
+        let reason = SkipReason::Timeout { attempts: 3 };
+        let batch = TickBatch::skipped(hour, reason);
         assert!(batch.is_empty());
         assert!(batch.had_error());
+        assert_eq!(batch.skipped, Some(reason));
+    }
+
+    #[test]
+    fn test_skip_reason_classification() {
+        let server = DownloadError::ServerError {
+            status: 503,
+            attempts: 4,
+        };
+        let reason = SkipReason::from_download(&server);
+        assert_eq!(
+            reason,
+            SkipReason::HttpStatus {
+                status: 503,
+                attempts: 4
+            }
+        );
+        assert!(reason.is_transient());
+        assert_eq!(reason.to_string(), "HTTP 503 after 4 attempts");
+
+        let forbidden = DownloadError::ServerError {
+            status: 403,
+            attempts: 1,
+        };
+        assert!(!SkipReason::from_download(&forbidden).is_transient());
+
+        let timeout = SkipReason::from_download(&DownloadError::Timeout(3));
+        assert_eq!(timeout, SkipReason::Timeout { attempts: 3 });
+        assert_eq!(timeout.attempts(), 3);
+        assert_eq!(timeout.as_str(), "timeout");
+
+        let parse = SkipReason::from_decode(&ParacasError::Parse("bad".into()), 2);
+        assert_eq!(parse, SkipReason::Parse { attempts: 2 });
+        assert!(!parse.is_transient());
     }
 }
@@ -0,0 +1,241 @@
+//! TLS backend selection and trust-store configuration.
+//!
+//! The backends compiled in are chosen with the crate features `rustls-tls`
+//! (rustls with the bundled Mozilla roots, the default),
+//! `rustls-tls-native-roots` (rustls with the operating system's roots) and
+//! `native-tls` (the platform TLS library and its trust store). At least one
+//! must be enabled.
+//!
+//! Networks behind a TLS-intercepting proxy need the proxy's CA: load it with
+//! [`load_certificates`] and add it to
+//! [`ClientConfig::root_certificates`](crate::ClientConfig::root_certificates).
+
+use reqwest::{Certificate, ClientBuilder};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[cfg(not(any(
+    feature = "rustls-tls",
+    feature = "rustls-tls-native-roots",
+    feature = "native-tls"
+)))]
+compile_error!(
+    "paracas-fetch needs a TLS backend: enable `rustls-tls`, `rustls-tls-native-roots` or `native-tls`"
+);
+
+/// TLS implementation used for HTTPS connections.
+///
+/// Only the backends whose crate feature is enabled exist; see
+/// [`TlsBackend::available`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TlsBackend {
+    /// The default backend of the enabled features, preferring native TLS.
+    #[default]
+    Auto,
+    /// rustls.
+    #[cfg(any(feature = "rustls-tls", feature = "rustls-tls-native-roots"))]
+    Rustls,
+    /// The platform TLS library (OpenSSL, Secure Transport or SChannel).
+    #[cfg(feature = "native-tls")]
+    Native,
+}
+
+impl TlsBackend {
+    /// Returns the backends compiled into this build.
+    #[must_use]
+    pub const fn available() -> &'static [Self] {
+        &[
+            Self::Auto,
+            #[cfg(any(feature = "rustls-tls", feature = "rustls-tls-native-roots"))]
+            Self::Rustls,
+            #[cfg(feature = "native-tls")]
+            Self::Native,
+        ]
+    }
+
+    /// Returns the backend name as accepted by [`FromStr`].
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            #[cfg(any(feature = "rustls-tls", feature = "rustls-tls-native-roots"))]
+            Self::Rustls => "rustls",
+            #[cfg(feature = "native-tls")]
+            Self::Native => "native",
+        }
+    }
+}
+
+impl fmt::Display for TlsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TlsBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+        if let Some(backend) = Self::available().iter().find(|b| b.as_str() == name) {
+            return Ok(*backend);
+        }
+        match name.as_str() {
+            "rustls" => Err(
+                "rustls support is not compiled in (enable the `rustls-tls` feature)".to_string(),
+            ),
+            "native" => Err(
+                "native TLS support is not compiled in (enable the `native-tls` feature)"
+                    .to_string(),
+            ),
+            _ => Err(format!(
+                "unknown TLS backend \"{s}\" (expected auto, rustls or native)"
+            )),
+        }
+    }
+}
+
+/// Errors loading CA certificates.
+#[derive(Error, Debug)]
+pub enum CertificateError {
+    /// The certificate file could not be read.
+    #[error("Failed to read {}: {source}", path.display())]
+    Io {
+        /// Path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// The file does not hold valid certificates.
+    #[error("Invalid certificate in {}: {source}", path.display())]
+    Invalid {
+        /// Path of the file.
+        path: PathBuf,
+        /// The underlying error.
+        source: reqwest::Error,
+    },
+
+    /// The file holds no certificates.
+    #[error("No certificates found in {}", path.display())]
+    Empty {
+        /// Path of the file.
+        path: PathBuf,
+    },
+}
+
+/// Loads CA certificates from a PEM bundle or a single DER certificate.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or holds no valid
+/// certificates.
+pub fn load_certificates(path: impl AsRef<Path>) -> Result<Vec<Certificate>, CertificateError> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).map_err(|source| CertificateError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    // DER certificates start with an ASN.1 SEQUENCE tag
+    let certificates = if bytes.first() == Some(&0x30) {
+        Certificate::from_der(&bytes).map(|certificate| vec![certificate])
+    } else {
+        Certificate::from_pem_bundle(&bytes)
+    }
+    .map_err(|source| CertificateError::Invalid {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if certificates.is_empty() {
+        return Err(CertificateError::Empty {
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(certificates)
+}
+
+/// Applies the backend and trust-store settings to a client builder.
+pub(crate) fn configure(
+    mut builder: ClientBuilder,
+    backend: TlsBackend,
+    root_certificates: &[Certificate],
+    built_in_roots: bool,
+) -> ClientBuilder {
+    builder = match backend {
+        TlsBackend::Auto => builder,
+        #[cfg(any(feature = "rustls-tls", feature = "rustls-tls-native-roots"))]
+        TlsBackend::Rustls => builder.use_rustls_tls(),
+        #[cfg(feature = "native-tls")]
+        TlsBackend::Native => builder.use_native_tls(),
+    };
+    for certificate in root_certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    builder.tls_built_in_root_certs(built_in_roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed CA used to exercise certificate loading.
+    const TEST_CA: &str = "\
+-----BEGIN CERTIFICATE-----\n\
+MIIBizCCATGgAwIBAgIUJp+b+SF64flTNhLfEZQMD+67tLEwCgYIKoZIzj0EAwIw\n\
+GjEYMBYGA1UEAwwPcGFyYWNhcyB0ZXN0IENBMCAXDTI2MTAxNjE0MTEzNloYDzIx\n\
+MjYwOTIyMTQxMTM2WjAaMRgwFgYDVQQDDA9wYXJhY2FzIHRlc3QgQ0EwWTATBgcq\n\
+hkjOPQIBBggqhkjOPQMBBwNCAARzpnyufrpIybYYiMBVk5Wd5oDZrdh5N5Hxcgo3\n\
+fHbYe3jSFcNdyqgHGJhWBHuCrzHzJdFm4kKS+XRIsnL2C/IWo1MwUTAdBgNVHQ4E\n\
+FgQUUhN9wigP7RCTD04HsUUCiAwI7zYwHwYDVR0jBBgwFoAUUhN9wigP7RCTD04H\n\
+sUUCiAwI7zYwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEA+ZOT\n\
+d6Dm0wniz8XxUU20sZU4nKqTJWqR5+99Kh/k4eMCICMeQoqm7Kqt/IJazM8XONBd\n\
+fI9f/WuTFRuRkYXktYlg\n\
+-----END CERTIFICATE-----\n\
+";
+
+    #[test]
+    fn test_backend_round_trip() {
+        for backend in TlsBackend::available() {
+            assert_eq!(backend.as_str().parse::<TlsBackend>(), Ok(*backend));
+        }
+        assert_eq!("AUTO".parse::<TlsBackend>(), Ok(TlsBackend::Auto));
+        assert!("schannel".parse::<TlsBackend>().is_err());
+    }
+
+    #[test]
+    fn test_load_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("bundle.pem");
+        std::fs::write(&bundle, format!("{TEST_CA}{TEST_CA}")).unwrap();
+        let certificates = load_certificates(&bundle).unwrap();
+        assert_eq!(certificates.len(), 2);
+
+        let builder = configure(
+            reqwest::Client::builder(),
+            TlsBackend::Auto,
+            &certificates,
+            false,
+        );
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn test_load_certificates_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.pem");
+        assert!(matches!(
+            load_certificates(&missing),
+            Err(CertificateError::Io { .. })
+        ));
+
+        let empty = dir.path().join("empty.pem");
+        std::fs::write(&empty, "# no certificates here\n").unwrap();
+        assert!(matches!(
+            load_certificates(&empty),
+            Err(CertificateError::Empty { .. })
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+//! Optional `tracing` instrumentation of the fetch pipeline.
+//!
+//! With the `tracing` feature enabled, every hour fetched by a tick stream
+//! runs inside an `hour` span containing `download` and `decompress` spans
+//! that record byte, retry and tick counts. Without the feature these
+//! helpers compile to plain calls.
+
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::time::Duration;
+
+use crate::latency::SlowRequest;
+use crate::retry::FailureKind;
+
+/// Runs the future for one hour inside an `hour` span.
+#[cfg(feature = "tracing")]
+pub(crate) fn hour<F: Future>(
+    instrument: &str,
+    hour: DateTime<Utc>,
+    future: F,
+) -> impl Future<Output = F::Output> + use<F> {
+    use tracing::Instrument;
+    future.instrument(tracing::info_span!("hour", instrument, hour = %hour))
+}
+
+/// Runs the future for one hour inside an `hour` span.
+#[cfg(not(feature = "tracing"))]
+pub(crate) const fn hour<F: Future>(_instrument: &str, _hour: DateTime<Utc>, future: F) -> F {
+    future
+}
+
+/// Wraps a closure so it runs inside the caller's span on another thread
+/// (e.g. under `spawn_blocking`).
+#[cfg(feature = "tracing")]
+pub(crate) fn propagate<R>(f: impl FnOnce() -> R + Send) -> impl FnOnce() -> R + Send {
+    let span = tracing::Span::current();
+    move || span.in_scope(f)
+}
+
+/// Wraps a closure so it runs inside the caller's span on another thread
+/// (e.g. under `spawn_blocking`).
+#[cfg(not(feature = "tracing"))]
+pub(crate) const fn propagate<F>(f: F) -> F {
+    f
+}
+
+/// Runs the decompression and parsing of an hour inside a `decompress` span.
+#[cfg(feature = "tracing")]
+pub(crate) fn decompress<T, E>(
+    compressed_bytes: usize,
+    f: impl FnOnce() -> Result<Vec<T>, E>,
+) -> Result<Vec<T>, E> {
+    let span = tracing::debug_span!(
+        "decompress",
+        compressed_bytes,
+        decompressed_bytes = tracing::field::Empty,
+        ticks = tracing::field::Empty
+    );
+    span.in_scope(|| {
+        let result = f();
+        if let Ok(ticks) = &result {
+            span.record(
+                "decompressed_bytes",
+                ticks.len() * paracas_types::RawTick::SIZE,
+            );
+            span.record("ticks", ticks.len());
+        }
+        result
+    })
+}
+
+/// Runs the decompression and parsing of an hour inside a `decompress` span.
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn decompress<T, E>(
+    _compressed_bytes: usize,
+    f: impl FnOnce() -> Result<Vec<T>, E>,
+) -> Result<Vec<T>, E> {
+    f()
+}
+
+/// Records a value on a field of the current span.
+#[cfg(feature = "tracing")]
+pub(crate) fn record(field: &str, value: u64) {
+    tracing::Span::current().record(field, value);
+}
+
+/// Records a value on a field of the current span.
+#[cfg(not(feature = "tracing"))]
+pub(crate) const fn record(_field: &str, _value: u64) {}
+
+/// Emits an event for a retried request.
+#[cfg(feature = "tracing")]
+pub(crate) fn retry(kind: &FailureKind, attempt: u32, delay: Duration) {
+    tracing::debug!(
+        ?kind,
+        attempt,
+        delay_ms = delay.as_millis() as u64,
+        "retrying request"
+    );
+}
+
+/// Emits an event for a retried request.
+#[cfg(not(feature = "tracing"))]
+pub(crate) const fn retry(_kind: &FailureKind, _attempt: u32, _delay: Duration) {}
+
+/// Emits an event for a request slower than the configured threshold.
+#[cfg(feature = "tracing")]
+pub(crate) fn slow_request(request: &SlowRequest) {
+    tracing::warn!(
+        url = %request.url,
+        attempt = request.attempt,
+        latency_ms = request.latency.as_millis() as u64,
+        status = request.status,
+        "slow request"
+    );
+}
+
+/// Emits an event for a request slower than the configured threshold.
+#[cfg(not(feature = "tracing"))]
+pub(crate) const fn slow_request(_request: &SlowRequest) {}
@@ -2,6 +2,9 @@
 
 use chrono::{DateTime, Datelike, Timelike, Utc};
 
+/// Host name of the Dukascopy data feed.
+pub const HOST: &str = "datafeed.dukascopy.com";
+
 /// Base URL for Dukascopy data feed.
 pub const BASE_URL: &str = "https://datafeed.dukascopy.com/datafeed";
 
@@ -34,6 +37,20 @@ pub fn tick_url(instrument: &str, hour: DateTime<Utc>) -> String {
     )
 }
 
+/// Returns the path of `url` relative to [`BASE_URL`].
+///
+/// This is the layout used by the disk cache and local data sources, e.g.
+/// `EURUSD/2024/00/15/12h_ticks.bi5`. Returns `None` for URLs outside the
+/// data feed or containing `..` components.
+#[must_use]
+pub fn relative_path(url: &str) -> Option<&str> {
+    let relative = url.strip_prefix(BASE_URL)?.trim_start_matches('/');
+    if relative.is_empty() || relative.split('/').any(|part| part == "..") {
+        return None;
+    }
+    Some(relative)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,4 +82,15 @@ mod tests {
         let url = tick_url("GBPJPY", hour);
         assert!(url.contains("GBPJPY"));
     }
+
+    #[test]
+    fn test_relative_path() {
+        let hour = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        assert_eq!(
+            relative_path(&tick_url("eurusd", hour)),
+            Some("EURUSD/2024/00/15/12h_ticks.bi5")
+        );
+        assert_eq!(relative_path("https://example.com/EURUSD"), None);
+        assert_eq!(relative_path(&format!("{BASE_URL}/../etc")), None);
+    }
 }
@@ -0,0 +1,265 @@
+//! Price sanity checks for decoded ticks.
+//!
+//! Raw hours occasionally contain junk records, such as zero prices or a
+//! single tick a thousand times off the market, that poison aggregated bars.
+//! [`PriceValidation`] finds them and either flags or drops them.
+
+use paracas_types::Tick;
+use std::fmt;
+use std::str::FromStr;
+
+/// Default limit on how far a tick's mid price may be from the hour median,
+/// as a ratio in either direction.
+pub const DEFAULT_MAX_MEDIAN_RATIO: f64 = 10.0;
+
+/// What makes a tick's prices implausible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PriceProblem {
+    /// The ask or bid is zero, negative or not a number.
+    NonPositive,
+    /// The bid is above the ask.
+    Crossed,
+    /// The mid price is far off the median of the hour.
+    Outlier,
+}
+
+impl PriceProblem {
+    /// Returns a short description of the problem.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::NonPositive => "non-positive price",
+            Self::Crossed => "bid above ask",
+            Self::Outlier => "far off the hour median",
+        }
+    }
+}
+
+impl fmt::Display for PriceProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A tick that failed price validation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidTick {
+    /// Position of the tick among the hour's decoded ticks.
+    pub index: usize,
+    /// The tick.
+    pub tick: Tick,
+    /// Why the tick is implausible.
+    pub problem: PriceProblem,
+}
+
+/// What happens to ticks that fail validation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidTickAction {
+    /// Keep the ticks and report them.
+    #[default]
+    Flag,
+    /// Remove the ticks and report them.
+    Drop,
+}
+
+impl InvalidTickAction {
+    /// Returns the action name as accepted by [`FromStr`].
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flag => "flag",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+impl fmt::Display for InvalidTickAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for InvalidTickAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flag" => Ok(Self::Flag),
+            "drop" => Ok(Self::Drop),
+            _ => Err(format!("unknown action \"{s}\" (expected flag or drop)")),
+        }
+    }
+}
+
+/// Price sanity checks applied to each decoded hour.
+///
+/// A tick is invalid if a price is not positive, if its bid is above its
+/// ask, or if its mid price is more than [`Self::max_median_ratio`] times
+/// above or below the median mid price of the hour's other valid ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceValidation {
+    /// Whether invalid ticks are kept or removed.
+    pub action: InvalidTickAction,
+    /// Largest ratio between a tick's mid price and the hour median, or
+    /// `None` to skip the outlier check.
+    pub max_median_ratio: Option<f64>,
+}
+
+impl Default for PriceValidation {
+    fn default() -> Self {
+        Self {
+            action: InvalidTickAction::Flag,
+            max_median_ratio: Some(DEFAULT_MAX_MEDIAN_RATIO),
+        }
+    }
+}
+
+impl PriceValidation {
+    /// Sets what happens to invalid ticks.
+    #[must_use]
+    pub const fn with_action(mut self, action: InvalidTickAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Sets the largest allowed ratio to the hour median, or `None` to skip
+    /// the outlier check.
+    #[must_use]
+    pub const fn with_max_median_ratio(mut self, ratio: Option<f64>) -> Self {
+        self.max_median_ratio = ratio;
+        self
+    }
+
+    /// Returns the invalid ticks of an hour, in order.
+    #[must_use]
+    pub fn check(&self, ticks: &[Tick]) -> Vec<InvalidTick> {
+        let problems: Vec<_> = ticks.iter().map(basic_problem).collect();
+        let median = self.max_median_ratio.and_then(|_| {
+            let mids: Vec<f64> = ticks
+                .iter()
+                .zip(&problems)
+                .filter(|(_, problem)| problem.is_none())
+                .map(|(tick, _)| tick.mid())
+                .collect();
+            median(mids)
+        });
+
+        ticks
+            .iter()
+            .zip(problems)
+            .enumerate()
+            .filter_map(|(index, (tick, problem))| {
+                let problem = problem.or_else(|| {
+                    let (ratio, median) = self.max_median_ratio.zip(median)?;
+                    let mid = tick.mid();
+                    (mid > median * ratio || mid * ratio < median).then_some(PriceProblem::Outlier)
+                })?;
+                Some(InvalidTick {
+                    index,
+                    tick: *tick,
+                    problem,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks an hour's ticks, removing the invalid ones if the action is
+    /// [`InvalidTickAction::Drop`].
+    ///
+    /// Returns the invalid ticks, with indices into the ticks as passed in.
+    pub fn apply(&self, ticks: &mut Vec<Tick>) -> Vec<InvalidTick> {
+        let invalid = self.check(ticks);
+        if self.action == InvalidTickAction::Drop && !invalid.is_empty() {
+            let mut next = invalid.iter().map(|invalid| invalid.index).peekable();
+            let mut index = 0;
+            ticks.retain(|_| {
+                let keep = next.next_if_eq(&index).is_none();
+                index += 1;
+                keep
+            });
+        }
+        invalid
+    }
+}
+
+/// Returns the problem found without looking at other ticks, if any.
+fn basic_problem(tick: &Tick) -> Option<PriceProblem> {
+    // Written to also catch NaN
+    if !(tick.ask > 0.0 && tick.bid > 0.0) {
+        Some(PriceProblem::NonPositive)
+    } else if tick.bid > tick.ask {
+        Some(PriceProblem::Crossed)
+    } else {
+        None
+    }
+}
+
+/// Returns the median of `values`, or `None` if there are none.
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let middle = values.len() / 2;
+    let (_, median, _) = values.select_nth_unstable_by(middle, f64::total_cmp);
+    Some(*median)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn tick(ask: f64, bid: f64) -> Tick {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        Tick::new(timestamp, ask, bid, 1.0, 1.0)
+    }
+
+    fn hour() -> Vec<Tick> {
+        vec![
+            tick(1.1002, 1.1000),
+            tick(0.0, 1.1000),
+            tick(1.1003, 1.1001),
+            tick(1.1000, 1.1004),
+            tick(110.03, 110.01),
+            tick(1.1004, 1.1002),
+        ]
+    }
+
+    #[test]
+    fn test_check_finds_each_problem() {
+        let invalid = PriceValidation::default().check(&hour());
+        let found: Vec<_> = invalid.iter().map(|i| (i.index, i.problem)).collect();
+        assert_eq!(
+            found,
+            [
+                (1, PriceProblem::NonPositive),
+                (3, PriceProblem::Crossed),
+                (4, PriceProblem::Outlier),
+            ]
+        );
+
+        let without_outliers = PriceValidation::default().with_max_median_ratio(None);
+        assert_eq!(without_outliers.check(&hour()).len(), 2);
+    }
+
+    #[test]
+    fn test_apply_flags_or_drops() {
+        let mut ticks = hour();
+        let invalid = PriceValidation::default().apply(&mut ticks);
+        assert_eq!(invalid.len(), 3);
+        assert_eq!(ticks, hour());
+
+        let drop = PriceValidation::default().with_action(InvalidTickAction::Drop);
+        let invalid = drop.apply(&mut ticks);
+        assert_eq!(invalid.len(), 3);
+        assert_eq!(invalid[2].tick, hour()[4]);
+        let expected: Vec<_> = [0, 2, 5].iter().map(|&i| hour()[i]).collect();
+        assert_eq!(ticks, expected);
+    }
+
+    #[test]
+    fn test_action_from_str() {
+        assert_eq!("DROP".parse(), Ok(InvalidTickAction::Drop));
+        assert!("ignore".parse::<InvalidTickAction>().is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Opaque instrument aliases for sharing datasets.
+//!
+//! Outputs written with aliases carry a random identifier in place of the
+//! instrument ID. The mapping is kept in a separate key file that stays with
+//! the data owner, so aliases are stable across runs while shared files do
+//! not reveal which instruments they contain.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::FormatError;
+
+/// Prefix of generated aliases.
+const ALIAS_PREFIX: &str = "inst_";
+
+/// Number of random hex digits in a generated alias.
+const ALIAS_DIGITS: usize = 12;
+
+/// Mapping from instrument IDs to opaque aliases.
+///
+/// Serialized as a JSON object of `instrument id -> alias`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InstrumentAliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl InstrumentAliases {
+    /// Creates an empty mapping.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a key file, returning an empty mapping if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the mapping to a key file as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or the file write fails.
+    pub fn save(&self, path: &Path) -> Result<(), FormatError> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Returns the alias for an instrument, generating one if it has none.
+    pub fn alias(&mut self, instrument_id: &str) -> &str {
+        if !self.aliases.contains_key(instrument_id) {
+            let alias = self.generate();
+            self.aliases.insert(instrument_id.to_string(), alias);
+        }
+        &self.aliases[instrument_id]
+    }
+
+    /// Returns the existing alias for an instrument.
+    #[must_use]
+    pub fn get(&self, instrument_id: &str) -> Option<&str> {
+        self.aliases.get(instrument_id).map(String::as_str)
+    }
+
+    /// Resolves an alias back to its instrument ID.
+    #[must_use]
+    pub fn instrument_for(&self, alias: &str) -> Option<&str> {
+        self.aliases
+            .iter()
+            .find(|(_, a)| a.as_str() == alias)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// Returns the number of aliased instruments.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// Returns true if no instrument has an alias.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Generates a random alias not used by any other instrument.
+    fn generate(&self) -> String {
+        loop {
+            let random = Uuid::new_v4().simple().to_string();
+            let alias = format!("{ALIAS_PREFIX}{}", &random[..ALIAS_DIGITS]);
+            if self.instrument_for(&alias).is_none() {
+                return alias;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_is_stable_and_opaque() {
+        let mut aliases = InstrumentAliases::new();
+        let eurusd = aliases.alias("eurusd").to_string();
+        let btcusd = aliases.alias("btcusd").to_string();
+
+        assert!(eurusd.starts_with(ALIAS_PREFIX));
+        assert!(!eurusd.contains("eur"));
+        assert_ne!(eurusd, btcusd);
+        assert_eq!(aliases.alias("eurusd"), eurusd);
+        assert_eq!(aliases.instrument_for(&btcusd), Some("btcusd"));
+        assert_eq!(aliases.len(), 2);
+    }
+
+    #[test]
+    fn test_key_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aliases.json");
+        assert!(InstrumentAliases::load(&path).unwrap().is_empty());
+
+        let mut aliases = InstrumentAliases::new();
+        aliases.alias("eurusd");
+        aliases.save(&path).unwrap();
+
+        let loaded = InstrumentAliases::load(&path).unwrap();
+        assert_eq!(loaded, aliases);
+        assert!(loaded.get("eurusd").is_some());
+    }
+}
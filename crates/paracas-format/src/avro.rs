@@ -0,0 +1,320 @@
+//! Apache Avro output.
+//!
+//! Files are Avro object container files: the record schema is embedded in
+//! the header, so Kafka, Hadoop and Spark readers need no schema registry
+//! to decode them.
+
+use apache_avro::types::Value;
+use apache_avro::{Schema, Writer};
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use serde_json::json;
+use std::io::Write;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{FormatError, Formatter, OutputMetadata};
+
+/// Namespace of the record schemas.
+pub const AVRO_NAMESPACE: &str = "paracas";
+
+/// Apache Avro formatter.
+///
+/// Ticks, bars and returns are written as `Tick`, `Bar` and `Return`
+/// records in the [`AVRO_NAMESPACE`] namespace. Timestamps use the
+/// `timestamp-millis` logical type; optional bar columns and indicators
+/// are `["null", ...]` unions.
+#[derive(Debug, Clone, Default)]
+pub struct AvroFormatter {
+    /// Instrument written as the `symbol` field of every record, if any.
+    symbol: Option<String>,
+    /// Dataset metadata embedded in the file header.
+    metadata: Option<OutputMetadata>,
+}
+
+/// A field of a record schema: its name and Avro type.
+type FieldDef = (String, serde_json::Value);
+
+impl AvroFormatter {
+    /// Creates a new Avro formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            symbol: None,
+            metadata: None,
+        }
+    }
+
+    /// Adds a leading `symbol` field holding `symbol` to every record.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Embeds dataset metadata in the file header, under keys prefixed with
+    /// [`METADATA_KEY_PREFIX`](crate::METADATA_KEY_PREFIX).
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Writes a container file of `name` records with `fields` after the
+    /// symbol and timestamp fields, one per row of values.
+    fn write_records<W: Write>(
+        &self,
+        name: &str,
+        fields: Vec<FieldDef>,
+        rows: impl Iterator<Item = Vec<Value>>,
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let fields: Vec<_> = self
+            .symbol
+            .iter()
+            .map(|_| ("symbol".to_string(), json!("string")))
+            .chain([(
+                "timestamp".to_string(),
+                json!({"type": "long", "logicalType": "timestamp-millis"}),
+            )])
+            .chain(fields)
+            .collect();
+        let schema = Schema::parse(&json!({
+            "type": "record",
+            "name": name,
+            "namespace": AVRO_NAMESPACE,
+            "fields": fields
+                .iter()
+                .map(|(name, kind)| match kind {
+                    serde_json::Value::Array(_) => {
+                        json!({"name": name, "type": kind, "default": null})
+                    }
+                    _ => json!({"name": name, "type": kind}),
+                })
+                .collect::<Vec<_>>(),
+        }))
+        .map_err(avro)?;
+
+        let mut writer = Writer::new(&schema, writer).map_err(avro)?;
+        if let Some(metadata) = &self.metadata {
+            for (key, value) in metadata.key_value_pairs() {
+                writer.add_user_metadata(key, value).map_err(avro)?;
+            }
+        }
+        let symbol = self.symbol.clone().map(Value::String);
+        for row in rows {
+            let values = symbol.iter().cloned().chain(row);
+            let record = fields
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(values)
+                .collect();
+            writer.append_value(Value::Record(record)).map_err(avro)?;
+        }
+        writer.into_inner().map_err(avro)?.flush()?;
+        Ok(())
+    }
+}
+
+impl Formatter for AvroFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let fields = vec![
+            ("ask".to_string(), json!("double")),
+            ("bid".to_string(), json!("double")),
+            ("ask_volume".to_string(), json!("float")),
+            ("bid_volume".to_string(), json!("float")),
+        ];
+        let rows = ticks.iter().map(|tick| {
+            vec![
+                timestamp(tick.timestamp),
+                Value::Double(tick.ask),
+                Value::Double(tick.bid),
+                Value::Float(tick.ask_volume),
+                Value::Float(tick.bid_volume),
+            ]
+        });
+        self.write_records("Tick", fields, rows, writer)
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let columns = OhlcvColumns::of(bars);
+        let timestamp_type = json!({"type": "long", "logicalType": "timestamp-millis"});
+        let optional = |kind: serde_json::Value| json!(["null", kind]);
+        let mut fields: Vec<FieldDef> = ["open", "high", "low", "close", "volume"]
+            .into_iter()
+            .map(|name| (name.to_string(), json!("double")))
+            .chain([("tick_count".to_string(), json!("int"))])
+            .collect();
+        let mut add = |names: &[&str], kind: serde_json::Value| {
+            fields.extend(names.iter().map(|name| (name.to_string(), kind.clone())));
+        };
+        if columns.side_volumes {
+            add(
+                &["ask_volume", "bid_volume", "imbalance"],
+                optional(json!("double")),
+            );
+        }
+        if columns.tick_times {
+            add(&["first_tick_ts", "last_tick_ts"], optional(timestamp_type));
+            add(&["is_partial"], json!("boolean"));
+        }
+        if columns.price_stats {
+            add(
+                &["vwap", "avg_spread", "max_spread"],
+                optional(json!("double")),
+            );
+        }
+        if columns.microstructure {
+            add(&["tick_rate"], optional(json!("double")));
+            add(&["bid_changes", "ask_changes"], optional(json!("int")));
+            add(&["twa_spread"], optional(json!("double")));
+        }
+        fields.extend(
+            indicators
+                .iter()
+                .map(|c| (c.name.clone(), optional(json!("double")))),
+        );
+
+        let some = |value: Value| Value::Union(1, Box::new(value));
+        let null = || Value::Union(0, Box::new(Value::Null));
+        let double = |value: Option<f64>| value.map_or_else(null, |v| some(Value::Double(v)));
+        let int = |value: Option<u32>| {
+            value.map_or_else(null, |v| some(Value::Int(v.try_into().unwrap_or(i32::MAX))))
+        };
+        let time = |value: Option<DateTime<Utc>>| value.map_or_else(null, |ts| some(timestamp(ts)));
+        let rows = bars.iter().enumerate().map(|(row, bar)| {
+            let mut values = vec![
+                timestamp(bar.timestamp),
+                Value::Double(bar.open),
+                Value::Double(bar.high),
+                Value::Double(bar.low),
+                Value::Double(bar.close),
+                Value::Double(bar.volume),
+                Value::Int(bar.tick_count.try_into().unwrap_or(i32::MAX)),
+            ];
+            if columns.side_volumes {
+                values.extend([
+                    double(bar.ask_volume),
+                    double(bar.bid_volume),
+                    double(bar.imbalance),
+                ]);
+            }
+            if columns.tick_times {
+                values.extend([
+                    time(bar.first_tick_ts),
+                    time(bar.last_tick_ts),
+                    Value::Boolean(bar.is_partial),
+                ]);
+            }
+            if columns.price_stats {
+                values.extend([
+                    double(bar.vwap),
+                    double(bar.avg_spread),
+                    double(bar.max_spread),
+                ]);
+            }
+            if columns.microstructure {
+                values.extend([
+                    double(bar.tick_rate),
+                    int(bar.bid_changes),
+                    int(bar.ask_changes),
+                    double(bar.twa_spread),
+                ]);
+            }
+            values.extend(indicators.iter().map(|c| double(c.values[row])));
+            values
+        });
+        self.write_records("Bar", fields, rows, writer)
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let fields = vec![
+            ("return".to_string(), json!("double")),
+            ("tick_count".to_string(), json!("int")),
+        ];
+        let rows = returns.iter().map(|r| {
+            vec![
+                timestamp(r.timestamp),
+                Value::Double(r.value),
+                Value::Int(r.tick_count.try_into().unwrap_or(i32::MAX)),
+            ]
+        });
+        self.write_records("Return", fields, rows, writer)
+    }
+
+    fn extension(&self) -> &str {
+        "avro"
+    }
+}
+
+/// Returns a `timestamp-millis` value.
+const fn timestamp(timestamp: DateTime<Utc>) -> Value {
+    Value::TimestampMillis(timestamp.timestamp_millis())
+}
+
+/// Converts an Avro error.
+fn avro(e: apache_avro::Error) -> FormatError {
+    FormatError::Avro(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::METADATA_KEY_PREFIX;
+    use apache_avro::Reader;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_avro_round_trip() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0);
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None],
+        };
+        let formatter = AvroFormatter::new()
+            .with_symbol("eurusd")
+            .with_metadata(OutputMetadata::new().with_price_source("mid"));
+
+        let mut output = Vec::new();
+        formatter
+            .write_ohlcv_with_indicators(&[bar], &[sma], &mut output)
+            .unwrap();
+
+        let reader = Reader::new(output.as_slice()).unwrap();
+        assert_eq!(
+            reader.user_metadata()[&format!("{METADATA_KEY_PREFIX}price_source")],
+            b"mid"
+        );
+        let records: Vec<_> = reader.map(Result::unwrap).collect();
+        let Value::Record(fields) = &records[0] else {
+            panic!("expected a record");
+        };
+        let field = |name: &str| &fields.iter().find(|(n, _)| n == name).unwrap().1;
+        assert_eq!(fields[0].0, "symbol");
+        assert_eq!(field("symbol"), &Value::String("eurusd".to_string()));
+        assert_eq!(
+            field("timestamp"),
+            &Value::TimestampMillis(timestamp.timestamp_millis())
+        );
+        assert_eq!(
+            field("bid_volume"),
+            &Value::Union(1, Box::new(Value::Double(3.0)))
+        );
+        assert_eq!(field("sma_2"), &Value::Union(0, Box::new(Value::Null)));
+    }
+}
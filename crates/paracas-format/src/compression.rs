@@ -0,0 +1,316 @@
+//! Transparent compression of text output.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::{FormatError, OutputMetadata};
+
+/// Compression applied to a whole text output file (CSV or JSON).
+///
+/// Parquet compresses its pages internally instead; see
+/// `ParquetFormatter::with_compression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OutputCompression {
+    /// Uncompressed output.
+    #[default]
+    None,
+    /// Gzip (`.gz`), readable by any tool.
+    Gzip,
+    /// Zstandard (`.zst`), faster and smaller than gzip.
+    Zstd,
+}
+
+impl OutputCompression {
+    /// Returns the file extension appended to the format extension, e.g.
+    /// `gz` for `ticks.csv.gz`.
+    #[must_use]
+    pub const fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+        }
+    }
+
+    /// Detects the compression from the last extension of `path`.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+
+    /// Returns the cargo feature that provides this compression, if any.
+    #[must_use]
+    pub const fn required_feature(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gzip"),
+            Self::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Returns true if this compression is compiled in.
+    #[must_use]
+    pub const fn is_available(&self) -> bool {
+        match self {
+            Self::None => true,
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Wraps `writer` in an encoder for this compression.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::CompressionUnavailable`] if the compression
+    /// was not compiled in, or an error if the encoder cannot be created.
+    pub fn wrap<W: Write>(self, writer: W) -> Result<CompressedWriter<W>, FormatError> {
+        let encoder = match self {
+            Self::None => Encoder::Plain(writer),
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                Encoder::Zstd(zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)?)
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(FormatError::CompressionUnavailable {
+                    compression: self,
+                    feature: self.required_feature().unwrap_or_default(),
+                });
+            }
+        };
+        Ok(CompressedWriter { encoder })
+    }
+}
+
+/// Reads the first line of the file at `path`, without its line ending,
+/// decompressing it according to the file's extension.
+///
+/// This is the header of a CSV file or the first record of an NDJSON file,
+/// which [`CsvFormatter::with_append`](crate::CsvFormatter::with_append) and
+/// [`JsonFormatter::with_append`](crate::JsonFormatter::with_append) check
+/// appended records against; a leading metadata line (see
+/// [`OutputMetadata::is_header_line`](crate::OutputMetadata::is_header_line))
+/// is skipped. Returns `None` if the file is missing or empty.
+///
+/// # Errors
+///
+/// Returns [`FormatError::CompressionUnavailable`] if the file's compression
+/// was not compiled in, or an error if reading fails.
+pub fn read_first_line(path: &Path) -> Result<Option<String>, FormatError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let reader: Box<dyn Read> = match OutputCompression::from_path(path) {
+        OutputCompression::None => Box::new(file),
+        #[cfg(feature = "gzip")]
+        OutputCompression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        #[cfg(feature = "zstd")]
+        OutputCompression::Zstd => Box::new(zstd::Decoder::new(file)?),
+        #[allow(unreachable_patterns)]
+        compression => {
+            return Err(FormatError::CompressionUnavailable {
+                compression,
+                feature: compression.required_feature().unwrap_or_default(),
+            });
+        }
+    };
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let trimmed = line.trim_end_matches(['\n', '\r']);
+        if !OutputMetadata::is_header_line(trimmed) {
+            return Ok((!trimmed.is_empty()).then(|| trimmed.to_string()));
+        }
+    }
+}
+
+impl std::fmt::Display for OutputCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Gzip => write!(f, "gzip"),
+            Self::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl std::str::FromStr for OutputCompression {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "zstd" | "zst" => Ok(Self::Zstd),
+            _ => Err(FormatError::UnknownCompression(s.to_string())),
+        }
+    }
+}
+
+/// Writer compressing everything written to it.
+///
+/// Call [`finish`](Self::finish) after the last write to complete the
+/// compressed stream; dropping the writer may leave it truncated.
+pub struct CompressedWriter<W: Write> {
+    encoder: Encoder<W>,
+}
+
+/// Encoder behind a [`CompressedWriter`].
+enum Encoder<W: Write> {
+    /// No compression.
+    Plain(W),
+    /// Gzip encoder.
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    /// Zstandard encoder.
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> CompressedWriter<W> {
+    /// Returns the compression being applied.
+    #[must_use]
+    pub const fn compression(&self) -> OutputCompression {
+        match self.encoder {
+            Encoder::Plain(_) => OutputCompression::None,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(_) => OutputCompression::Gzip,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(_) => OutputCompression::Zstd,
+        }
+    }
+
+    /// Completes the compressed stream, flushes it and returns the
+    /// underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    // Only the plain variant exists without compression features
+    #[allow(clippy::infallible_destructuring_match)]
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self.encoder {
+            Encoder::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for CompressedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl<W: Write> std::fmt::Debug for CompressedWriter<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompressedWriter")
+            .field("compression", &self.compression())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            OutputCompression::from_path(Path::new("eurusd.csv.gz")),
+            OutputCompression::Gzip
+        );
+        assert_eq!(
+            OutputCompression::from_path(Path::new("eurusd.ndjson.zst")),
+            OutputCompression::Zstd
+        );
+        assert_eq!(
+            OutputCompression::from_path(Path::new("eurusd.csv")),
+            OutputCompression::None
+        );
+        assert_eq!("gz".parse().ok(), Some(OutputCompression::Gzip));
+        assert!("lz4".parse::<OutputCompression>().is_err());
+    }
+
+    #[cfg(all(feature = "gzip", feature = "zstd"))]
+    #[test]
+    fn test_round_trip() {
+        use std::io::Read;
+
+        let text = "timestamp,ask,bid\n".repeat(100);
+
+        let mut writer = OutputCompression::Gzip.wrap(Vec::new()).unwrap();
+        writer.write_all(text.as_bytes()).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert!(compressed.len() < text.len());
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+
+        let mut writer = OutputCompression::Zstd.wrap(Vec::new()).unwrap();
+        writer.write_all(text.as_bytes()).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert_eq!(
+            zstd::decode_all(compressed.as_slice()).unwrap(),
+            text.as_bytes()
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_read_first_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ticks.csv.gz");
+        assert_eq!(read_first_line(&path).unwrap(), None);
+
+        let mut writer = OutputCompression::Gzip
+            .wrap(File::create(&path).unwrap())
+            .unwrap();
+        writer
+            .write_all(b"# paracas {}\ntimestamp,ask,bid\r\n1,2,3\n")
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(
+            read_first_line(&path).unwrap().as_deref(),
+            Some("timestamp,ask,bid")
+        );
+    }
+}
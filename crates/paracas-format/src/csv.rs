@@ -1,18 +1,49 @@
 //! CSV output format.
 
-use paracas_aggregate::Ohlcv;
-use paracas_types::Tick;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, OhlcvBidAsk, PriceReturn};
+use paracas_types::{Tick, TimestampPrecision};
+use std::borrow::Cow;
 use std::io::Write;
+use std::marker::PhantomData;
 
-use crate::{FormatError, Formatter};
+use crate::formatter::{OhlcvColumns, check_indicators, format_price};
+use crate::{
+    BatchWriter, CompressedWriter, DerivedColumns, FormatError, Formatter, OutputCompression,
+    OutputMetadata, StreamingFormatter,
+};
 
 /// CSV formatter.
+///
+/// Fields holding the delimiter, a double quote or a line break are quoted
+/// as RFC 4180 specifies, with inner quotes doubled.
 #[derive(Debug, Clone, Default)]
 pub struct CsvFormatter {
     /// Field delimiter (default: comma).
     delimiter: char,
     /// Whether to include header row.
     include_header: bool,
+    /// Fractional-second precision for tick timestamps.
+    timestamp_precision: TimestampPrecision,
+    /// Compression of the whole output.
+    compression: OutputCompression,
+    /// Names of the columns to write, in order, or all if unset.
+    columns: Option<Vec<String>>,
+    /// How timestamps are written.
+    timestamp_style: TimestampStyle,
+    /// Instrument written in a leading `symbol` column, if any.
+    symbol: Option<String>,
+    /// Header row of the output being appended to, if any.
+    append: Option<String>,
+    /// Decimal places of prices, if fixed.
+    price_digits: Option<u32>,
+    /// Decimal separator of numbers (default: period).
+    decimal_separator: char,
+    /// Metadata written as a comment line above the header, if any.
+    metadata: Option<OutputMetadata>,
+    /// Tick columns derived from the quotes.
+    derived: DerivedColumns,
 }
 
 impl CsvFormatter {
@@ -22,6 +53,16 @@ impl CsvFormatter {
         Self {
             delimiter: ',',
             include_header: true,
+            timestamp_precision: TimestampPrecision::Millisecond,
+            compression: OutputCompression::None,
+            columns: None,
+            timestamp_style: TimestampStyle::Iso,
+            symbol: None,
+            append: None,
+            price_digits: None,
+            decimal_separator: '.',
+            metadata: None,
+            derived: DerivedColumns::new(),
         }
     }
 
@@ -39,76 +80,732 @@ impl CsvFormatter {
         self
     }
 
+    /// Sets the fractional-second precision for tick timestamps.
+    ///
+    /// Defaults to milliseconds; use nanoseconds for sub-millisecond sources.
+    #[must_use]
+    pub const fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Compresses the output, e.g. into a `.csv.gz` file.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: OutputCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Writes only the named columns, in the given order, to match a fixed
+    /// downstream schema (default: all columns).
+    ///
+    /// Names are those of the header, e.g. `timestamp`, `bid` and `ask` for
+    /// ticks, or `close` and indicator names for bars. Writing records
+    /// without a named column fails with [`FormatError::UnknownColumn`].
+    #[must_use]
+    pub fn with_columns<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.columns = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sets how timestamps are written (default: ISO 8601).
+    ///
+    /// An invalid `strftime` pattern fails the write with
+    /// [`FormatError::InvalidTimestampFormat`].
+    #[must_use]
+    pub fn with_timestamp_style(mut self, style: TimestampStyle) -> Self {
+        self.timestamp_style = style;
+        self
+    }
+
+    /// Adds a leading `symbol` column holding `symbol` on every row, so
+    /// files of several instruments can be concatenated or loaded into one
+    /// table.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Appends to output whose header row is `header` (see
+    /// [`read_first_line`](crate::read_first_line)), e.g. for incremental
+    /// daily updates of a growing file.
+    ///
+    /// The header is not written again; instead, writing fails with
+    /// [`FormatError::SchemaMismatch`] if the records' header differs. The
+    /// writer must be opened in append mode.
+    #[must_use]
+    pub fn with_append(mut self, header: impl Into<String>) -> Self {
+        self.append = Some(header.into());
+        self
+    }
+
+    /// Writes prices and spreads with exactly `digits` decimal places,
+    /// e.g. [`Instrument::price_digits`](paracas_types::Instrument::price_digits),
+    /// so every row has the same width (default: the shortest representation
+    /// of each value, which may differ from row to row).
+    #[must_use]
+    pub const fn with_price_digits(mut self, digits: u32) -> Self {
+        self.price_digits = Some(digits);
+        self
+    }
+
+    /// Sets the decimal separator of numbers (default: `.`), e.g. `,` for
+    /// locales writing `1,0945`. Timestamps are not affected.
+    ///
+    /// Numbers containing the delimiter are quoted, so a comma separator
+    /// with the comma delimiter still yields valid CSV; prefer
+    /// [`european`](Self::european) for spreadsheets.
+    #[must_use]
+    pub const fn with_decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Writes `metadata` as a comment line above the header row (see
+    /// [`OutputMetadata::comment_line`]), so the file records its own
+    /// provenance. Most CSV readers skip it given `#` as comment character,
+    /// e.g. `comment='#'` in pandas. Nothing is written when appending or
+    /// without a header.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Adds tick columns derived from the quotes after the volumes: the
+    /// spread with the digits of prices, and the mid with one more, as it
+    /// may fall halfway between two quotes.
+    #[must_use]
+    pub const fn with_derived_columns(mut self, derived: DerivedColumns) -> Self {
+        self.derived = derived;
+        self
+    }
+
+    /// Creates a formatter of the European Excel convention: semicolon
+    /// delimiters and decimal commas.
+    #[must_use]
+    pub const fn european() -> Self {
+        Self::new().with_delimiter(';').with_decimal_separator(',')
+    }
+
     /// Creates a tab-separated values (TSV) formatter.
     #[must_use]
     pub const fn tsv() -> Self {
         Self {
             delimiter: '\t',
             include_header: true,
+            timestamp_precision: TimestampPrecision::Millisecond,
+            compression: OutputCompression::None,
+            columns: None,
+            timestamp_style: TimestampStyle::Iso,
+            symbol: None,
+            append: None,
+            price_digits: None,
+            decimal_separator: '.',
+            metadata: None,
+            derived: DerivedColumns::new(),
         }
     }
 }
 
-impl Formatter for CsvFormatter {
-    fn write_ticks<W: Write + Send>(
+/// Formats an optional value, leaving the field empty when absent.
+fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// ISO 8601 pattern of time bar and return timestamps, whole seconds.
+const SECONDS_ISO_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+/// How CSV and JSON timestamps are written.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TimestampStyle {
+    /// ISO 8601 UTC: `2024-01-15T12:30:45.000Z` for ticks (at the
+    /// configured precision) and `2024-01-15T12:00:00Z` for bars.
+    #[default]
+    Iso,
+    /// Milliseconds since the Unix epoch.
+    EpochMillis,
+    /// Microseconds since the Unix epoch.
+    EpochMicros,
+    /// A custom `strftime` pattern in UTC, e.g. `%Y-%m-%d %H:%M:%S%.3f`.
+    Strftime(String),
+}
+
+/// A column of CSV output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Symbol,
+    Timestamp,
+    Ask,
+    Bid,
+    AskVolume,
+    BidVolume,
+    Open,
+    High,
+    Low,
+    Close,
+    BidOpen,
+    BidHigh,
+    BidLow,
+    BidClose,
+    AskOpen,
+    AskHigh,
+    AskLow,
+    AskClose,
+    Volume,
+    TickCount,
+    Imbalance,
+    FirstTickTs,
+    LastTickTs,
+    IsPartial,
+    Vwap,
+    AvgSpread,
+    MaxSpread,
+    TickRate,
+    BidChanges,
+    AskChanges,
+    TwaSpread,
+    Spread,
+    Mid,
+    Return,
+    /// Indicator column at this index.
+    Indicator(usize),
+}
+
+impl Column {
+    /// Returns whether the column holds numbers, written with the
+    /// configured decimal separator.
+    const fn is_numeric(self) -> bool {
+        !matches!(
+            self,
+            Self::Symbol | Self::Timestamp | Self::FirstTickTs | Self::LastTickTs | Self::IsPartial
+        )
+    }
+}
+
+/// Columns written, with their header names.
+type Layout = Vec<(String, Column)>;
+
+/// Names the given columns.
+fn named(columns: &[(&str, Column)]) -> Layout {
+    columns
+        .iter()
+        .map(|&(name, column)| (name.to_string(), column))
+        .collect()
+}
+
+impl CsvFormatter {
+    /// Selects the columns to write from the `available` ones, after
+    /// checking the timestamp style.
+    fn layout(&self, mut available: Layout) -> Result<Layout, FormatError> {
+        if self.symbol.is_some() {
+            available.insert(0, ("symbol".to_string(), Column::Symbol));
+        }
+        if let TimestampStyle::Strftime(pattern) = &self.timestamp_style
+            && StrftimeItems::new(pattern).any(|item| item == Item::Error)
+        {
+            return Err(FormatError::InvalidTimestampFormat(pattern.clone()));
+        }
+        let Some(names) = &self.columns else {
+            return Ok(available);
+        };
+        names
+            .iter()
+            .map(|name| {
+                available
+                    .iter()
+                    .find(|(available, _)| available == name)
+                    .cloned()
+                    .ok_or_else(|| FormatError::UnknownColumn(name.clone()))
+            })
+            .collect()
+    }
+
+    /// Returns the value of the `symbol` column.
+    fn symbol(&self) -> String {
+        self.symbol.clone().unwrap_or_default()
+    }
+
+    /// Quotes `field` if it holds the delimiter, a double quote or a line
+    /// break, doubling inner quotes.
+    fn quote<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        if field.contains([self.delimiter, '"', '\n', '\r']) {
+            Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+        } else {
+            Cow::Borrowed(field)
+        }
+    }
+
+    /// Writes the field of `column` holding `value`, preceded by the
+    /// delimiter unless it is the first of its row.
+    fn write_field<W: Write>(
         &self,
-        ticks: &[Tick],
-        mut writer: W,
+        first: bool,
+        column: Column,
+        value: &str,
+        writer: &mut W,
     ) -> Result<(), FormatError> {
-        let d = self.delimiter;
+        if !first {
+            write!(writer, "{}", self.delimiter)?;
+        }
+        let value = if column.is_numeric() && self.decimal_separator != '.' {
+            Cow::Owned(value.replace('.', &self.decimal_separator.to_string()))
+        } else {
+            Cow::Borrowed(value)
+        };
+        writer.write_all(self.quote(&value).as_bytes())?;
+        Ok(())
+    }
 
-        if self.include_header {
-            writeln!(writer, "timestamp{d}ask{d}bid{d}ask_volume{d}bid_volume")?;
+    /// Formats a price with the configured digits.
+    fn price(&self, price: f64) -> String {
+        format_price(price, self.price_digits)
+    }
+
+    /// Formats an optional price, leaving the field empty when absent.
+    fn optional_price(&self, price: Option<f64>) -> String {
+        price.map(|p| self.price(p)).unwrap_or_default()
+    }
+
+    /// Returns the tick columns to write.
+    fn tick_layout(&self) -> Result<Layout, FormatError> {
+        let mut available = named(&[
+            ("timestamp", Column::Timestamp),
+            ("ask", Column::Ask),
+            ("bid", Column::Bid),
+            ("ask_volume", Column::AskVolume),
+            ("bid_volume", Column::BidVolume),
+        ]);
+        if self.derived.spread {
+            available.extend(named(&[("spread", Column::Spread)]));
+        }
+        if self.derived.mid {
+            available.extend(named(&[("mid", Column::Mid)]));
+        }
+        self.layout(available)
+    }
+
+    /// Returns the bar columns to write for the given optional columns and
+    /// indicators.
+    fn ohlcv_layout(
+        &self,
+        columns: OhlcvColumns,
+        indicators: &[IndicatorColumn],
+    ) -> Result<Layout, FormatError> {
+        let mut available = named(&[
+            ("timestamp", Column::Timestamp),
+            ("open", Column::Open),
+            ("high", Column::High),
+            ("low", Column::Low),
+            ("close", Column::Close),
+            ("volume", Column::Volume),
+            ("tick_count", Column::TickCount),
+        ]);
+        if columns.side_volumes {
+            available.extend(named(&[
+                ("ask_volume", Column::AskVolume),
+                ("bid_volume", Column::BidVolume),
+                ("imbalance", Column::Imbalance),
+            ]));
+        }
+        if columns.tick_times {
+            available.extend(named(&[
+                ("first_tick_ts", Column::FirstTickTs),
+                ("last_tick_ts", Column::LastTickTs),
+                ("is_partial", Column::IsPartial),
+            ]));
+        }
+        if columns.price_stats {
+            available.extend(named(&[
+                ("vwap", Column::Vwap),
+                ("avg_spread", Column::AvgSpread),
+                ("max_spread", Column::MaxSpread),
+            ]));
+        }
+        if columns.microstructure {
+            available.extend(named(&[
+                ("tick_rate", Column::TickRate),
+                ("bid_changes", Column::BidChanges),
+                ("ask_changes", Column::AskChanges),
+                ("twa_spread", Column::TwaSpread),
+            ]));
         }
+        available.extend(
+            indicators
+                .iter()
+                .enumerate()
+                .map(|(i, column)| (column.name.clone(), Column::Indicator(i))),
+        );
+        self.layout(available)
+    }
+
+    /// Returns the bid/ask bar columns to write, with `is_partial` if
+    /// `partial`.
+    fn bid_ask_layout(&self, partial: bool) -> Result<Layout, FormatError> {
+        let mut available = named(&[
+            ("timestamp", Column::Timestamp),
+            ("bid_open", Column::BidOpen),
+            ("bid_high", Column::BidHigh),
+            ("bid_low", Column::BidLow),
+            ("bid_close", Column::BidClose),
+            ("ask_open", Column::AskOpen),
+            ("ask_high", Column::AskHigh),
+            ("ask_low", Column::AskLow),
+            ("ask_close", Column::AskClose),
+            ("bid_volume", Column::BidVolume),
+            ("ask_volume", Column::AskVolume),
+            ("tick_count", Column::TickCount),
+        ]);
+        if partial {
+            available.extend(named(&[("is_partial", Column::IsPartial)]));
+        }
+        self.layout(available)
+    }
+
+    /// Returns the return series columns to write.
+    fn returns_layout(&self) -> Result<Layout, FormatError> {
+        self.layout(named(&[
+            ("timestamp", Column::Timestamp),
+            ("return", Column::Return),
+            ("tick_count", Column::TickCount),
+        ]))
+    }
+
+    /// Writes the header row of `layout`, or checks it against the header
+    /// of the output being appended to.
+    fn write_header<W: Write>(&self, layout: &Layout, writer: &mut W) -> Result<(), FormatError> {
+        let names: Vec<_> = layout.iter().map(|(name, _)| self.quote(name)).collect();
+        let header = names.join(&self.delimiter.to_string());
+        match &self.append {
+            Some(existing) if *existing != header => Err(FormatError::SchemaMismatch {
+                existing: existing.clone(),
+                appended: header,
+            }),
+            Some(_) => Ok(()),
+            None => {
+                if let Some(metadata) = &self.metadata {
+                    writeln!(writer, "{}", metadata.comment_line()?)?;
+                }
+                writeln!(writer, "{header}")?;
+                Ok(())
+            }
+        }
+    }
 
+    /// Formats a timestamp in the configured style, using `iso_format` for
+    /// ISO 8601.
+    fn timestamp(&self, timestamp: DateTime<Utc>, iso_format: &str) -> String {
+        match &self.timestamp_style {
+            TimestampStyle::Iso => timestamp.format(iso_format).to_string(),
+            TimestampStyle::EpochMillis => timestamp.timestamp_millis().to_string(),
+            TimestampStyle::EpochMicros => timestamp.timestamp_micros().to_string(),
+            TimestampStyle::Strftime(pattern) => timestamp.format(pattern).to_string(),
+        }
+    }
+
+    /// Writes the tick header row.
+    fn write_tick_header<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        self.write_header(&self.tick_layout()?, writer)
+    }
+
+    /// Writes one row per tick.
+    fn write_tick_rows<W: Write>(&self, ticks: &[Tick], writer: &mut W) -> Result<(), FormatError> {
+        let layout = self.tick_layout()?;
+        let ts_format = self.timestamp_precision.iso_format();
         for tick in ticks {
-            writeln!(
-                writer,
-                "{}{d}{}{d}{}{d}{}{d}{}",
-                tick.timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ"),
-                tick.ask,
-                tick.bid,
-                tick.ask_volume,
-                tick.bid_volume
-            )?;
+            for (i, &(_, column)) in layout.iter().enumerate() {
+                let value = match column {
+                    Column::Symbol => self.symbol(),
+                    Column::Timestamp => self.timestamp(tick.timestamp, ts_format),
+                    Column::Ask => self.price(tick.ask),
+                    Column::Bid => self.price(tick.bid),
+                    Column::AskVolume => tick.ask_volume.to_string(),
+                    Column::BidVolume => tick.bid_volume.to_string(),
+                    Column::Spread => self.price(tick.spread()),
+                    Column::Mid => format_price(tick.mid(), self.price_digits.map(|d| d + 1)),
+                    _ => String::new(),
+                };
+                self.write_field(i == 0, column, &value, writer)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the bar header row for the given columns.
+    fn write_ohlcv_header<W: Write>(
+        &self,
+        columns: OhlcvColumns,
+        indicators: &[IndicatorColumn],
+        writer: &mut W,
+    ) -> Result<(), FormatError> {
+        self.write_header(&self.ohlcv_layout(columns, indicators)?, writer)
+    }
+
+    /// Writes one row per bar with the given columns.
+    fn write_ohlcv_rows<W: Write>(
+        &self,
+        bars: &[Ohlcv],
+        columns: OhlcvColumns,
+        indicators: &[IndicatorColumn],
+        writer: &mut W,
+    ) -> Result<(), FormatError> {
+        let layout = self.ohlcv_layout(columns, indicators)?;
+        let ts_format = self.timestamp_precision.iso_format();
+        // Activity and price bars start at their first tick, within a second
+        let bar_format = if columns.subsecond {
+            ts_format
+        } else {
+            SECONDS_ISO_FORMAT
+        };
+        for (row, bar) in bars.iter().enumerate() {
+            for (i, &(_, column)) in layout.iter().enumerate() {
+                let value = match column {
+                    Column::Symbol => self.symbol(),
+                    Column::Timestamp => self.timestamp(bar.timestamp, bar_format),
+                    Column::Open => self.price(bar.open),
+                    Column::High => self.price(bar.high),
+                    Column::Low => self.price(bar.low),
+                    Column::Close => self.price(bar.close),
+                    Column::Volume => bar.volume.to_string(),
+                    Column::TickCount => bar.tick_count.to_string(),
+                    Column::AskVolume => optional(bar.ask_volume),
+                    Column::BidVolume => optional(bar.bid_volume),
+                    Column::Imbalance => optional(bar.imbalance),
+                    Column::FirstTickTs => bar
+                        .first_tick_ts
+                        .map(|ts| self.timestamp(ts, ts_format))
+                        .unwrap_or_default(),
+                    Column::LastTickTs => bar
+                        .last_tick_ts
+                        .map(|ts| self.timestamp(ts, ts_format))
+                        .unwrap_or_default(),
+                    Column::IsPartial => bar.is_partial.to_string(),
+                    Column::Vwap => self.optional_price(bar.vwap),
+                    Column::AvgSpread => self.optional_price(bar.avg_spread),
+                    Column::MaxSpread => self.optional_price(bar.max_spread),
+                    Column::TickRate => optional(bar.tick_rate),
+                    Column::BidChanges => optional(bar.bid_changes),
+                    Column::AskChanges => optional(bar.ask_changes),
+                    Column::TwaSpread => self.optional_price(bar.twa_spread),
+                    Column::Indicator(index) => optional(indicators[index].values[row]),
+                    _ => String::new(),
+                };
+                self.write_field(i == 0, column, &value, writer)?;
+            }
+            writeln!(writer)?;
         }
 
         Ok(())
     }
+}
 
-    fn write_ohlcv<W: Write + Send>(
+impl Formatter for CsvFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let mut writer = self.compression.wrap(writer)?;
+        if self.include_header {
+            self.write_tick_header(&mut writer)?;
+        }
+        self.write_tick_rows(ticks, &mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
         &self,
         bars: &[Ohlcv],
-        mut writer: W,
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let mut writer = self.compression.wrap(writer)?;
+        let columns = OhlcvColumns::of(bars);
+        if self.include_header {
+            self.write_ohlcv_header(columns, indicators, &mut writer)?;
+        }
+        self.write_ohlcv_rows(bars, columns, indicators, &mut writer)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn write_bid_ask_bars<W: Write + Send>(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: W,
     ) -> Result<(), FormatError> {
-        let d = self.delimiter;
+        let layout = self.bid_ask_layout(bars.iter().any(|bar| bar.is_partial))?;
+        let mut writer = self.compression.wrap(writer)?;
 
         if self.include_header {
-            writeln!(
-                writer,
-                "timestamp{d}open{d}high{d}low{d}close{d}volume{d}tick_count"
-            )?;
+            self.write_header(&layout, &mut writer)?;
         }
 
         for bar in bars {
-            writeln!(
-                writer,
-                "{}{d}{}{d}{}{d}{}{d}{}{d}{}{d}{}",
-                bar.timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
-                bar.open,
-                bar.high,
-                bar.low,
-                bar.close,
-                bar.volume,
-                bar.tick_count
-            )?;
+            for (i, &(_, column)) in layout.iter().enumerate() {
+                let value = match column {
+                    Column::Symbol => self.symbol(),
+                    Column::Timestamp => self.timestamp(bar.timestamp, SECONDS_ISO_FORMAT),
+                    Column::BidOpen => self.price(bar.bid_open),
+                    Column::BidHigh => self.price(bar.bid_high),
+                    Column::BidLow => self.price(bar.bid_low),
+                    Column::BidClose => self.price(bar.bid_close),
+                    Column::AskOpen => self.price(bar.ask_open),
+                    Column::AskHigh => self.price(bar.ask_high),
+                    Column::AskLow => self.price(bar.ask_low),
+                    Column::AskClose => self.price(bar.ask_close),
+                    Column::BidVolume => bar.bid_volume.to_string(),
+                    Column::AskVolume => bar.ask_volume.to_string(),
+                    Column::TickCount => bar.tick_count.to_string(),
+                    Column::IsPartial => bar.is_partial.to_string(),
+                    _ => String::new(),
+                };
+                self.write_field(i == 0, column, &value, &mut writer)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.finish()?;
+        Ok(())
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let layout = self.returns_layout()?;
+        let mut writer = self.compression.wrap(writer)?;
+
+        if self.include_header {
+            self.write_header(&layout, &mut writer)?;
         }
 
+        for r in returns {
+            for (i, &(_, column)) in layout.iter().enumerate() {
+                let value = match column {
+                    Column::Symbol => self.symbol(),
+                    Column::Timestamp => self.timestamp(r.timestamp, SECONDS_ISO_FORMAT),
+                    Column::Return => r.value.to_string(),
+                    Column::TickCount => r.tick_count.to_string(),
+                    _ => String::new(),
+                };
+                self.write_field(i == 0, column, &value, &mut writer)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.finish()?;
         Ok(())
     }
 
     fn extension(&self) -> &str {
-        "csv"
+        match self.compression {
+            OutputCompression::None => "csv",
+            OutputCompression::Gzip => "csv.gz",
+            OutputCompression::Zstd => "csv.zst",
+        }
+    }
+}
+
+impl StreamingFormatter for CsvFormatter {
+    type TickWriter<W: Write + Send> = CsvBatchWriter<W, Tick>;
+    type OhlcvWriter<W: Write + Send> = CsvBatchWriter<W, Ohlcv>;
+
+    fn tick_writer<W: Write + Send>(&self, writer: W) -> Result<Self::TickWriter<W>, FormatError> {
+        let mut writer = self.compression.wrap(writer)?;
+        if self.include_header {
+            self.write_tick_header(&mut writer)?;
+        }
+        Ok(CsvBatchWriter::new(self.clone(), writer))
+    }
+
+    fn ohlcv_writer<W: Write + Send>(
+        &self,
+        writer: W,
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        let writer = self.compression.wrap(writer)?;
+        Ok(CsvBatchWriter::new(self.clone(), writer))
+    }
+
+    fn ohlcv_writer_for<W: Write + Send>(
+        &self,
+        writer: W,
+        bars: &[Ohlcv],
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        let mut batch_writer = self.ohlcv_writer(writer)?;
+        batch_writer.columns(bars)?;
+        Ok(batch_writer)
+    }
+}
+
+/// Incremental CSV writer created by [`CsvFormatter`].
+#[derive(Debug)]
+pub struct CsvBatchWriter<W: Write, T> {
+    formatter: CsvFormatter,
+    writer: CompressedWriter<W>,
+    /// Bar columns, fixed by the first non-empty batch of bars.
+    columns: Option<OhlcvColumns>,
+    records: PhantomData<fn(&T)>,
+}
+
+impl<W: Write, T> CsvBatchWriter<W, T> {
+    /// Wraps `writer`, after any tick header.
+    const fn new(formatter: CsvFormatter, writer: CompressedWriter<W>) -> Self {
+        Self {
+            formatter,
+            writer,
+            columns: None,
+            records: PhantomData,
+        }
+    }
+}
+
+impl<W: Write> CsvBatchWriter<W, Ohlcv> {
+    /// Returns the bar columns, writing the header on first use.
+    fn columns(&mut self, bars: &[Ohlcv]) -> Result<OhlcvColumns, FormatError> {
+        if let Some(columns) = self.columns {
+            return Ok(columns);
+        }
+        let columns = OhlcvColumns::of(bars);
+        if self.formatter.include_header {
+            self.formatter
+                .write_ohlcv_header(columns, &[], &mut self.writer)?;
+        }
+        self.columns = Some(columns);
+        Ok(columns)
+    }
+}
+
+impl<W: Write> BatchWriter<Tick> for CsvBatchWriter<W, Tick> {
+    fn write_batch(&mut self, ticks: &[Tick]) -> Result<(), FormatError> {
+        self.formatter.write_tick_rows(ticks, &mut self.writer)
+    }
+
+    fn finish(self) -> Result<(), FormatError> {
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> BatchWriter<Ohlcv> for CsvBatchWriter<W, Ohlcv> {
+    fn write_batch(&mut self, bars: &[Ohlcv]) -> Result<(), FormatError> {
+        if bars.is_empty() {
+            return Ok(());
+        }
+        let columns = self.columns(bars)?;
+        self.formatter
+            .write_ohlcv_rows(bars, columns, &[], &mut self.writer)
+    }
+
+    fn finish(mut self) -> Result<(), FormatError> {
+        self.columns(&[])?;
+        self.writer.finish()?;
+        Ok(())
     }
 }
 
@@ -149,6 +846,377 @@ mod tests {
         assert!(!result.contains("timestamp,ask"));
     }
 
+    #[test]
+    fn test_csv_price_digits() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![
+            Ohlcv::new(timestamp, 1.1, 1.100_099_999_999_99, 1.0, 1.15, 4.0, 2)
+                .with_price_stats(1.12, 0.000_015, 0.00002),
+        ];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new()
+            .with_header(false)
+            .with_price_digits(5)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(
+            result,
+            "2024-01-15T12:00:00Z,1.10000,1.10010,1.00000,1.15000,4,2,1.12000,0.00002,0.00002\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_nanosecond_timestamps() {
+        let formatter = CsvFormatter::new()
+            .with_header(false)
+            .with_timestamp_precision(TimestampPrecision::Nanosecond);
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap()
+            + chrono::TimeDelta::nanoseconds(123_456_789);
+        let ticks = vec![Tick::new(timestamp, 1.1001, 1.1000, 100.0, 200.0)];
+        let mut output = Cursor::new(Vec::new());
+
+        formatter.write_ticks(&ticks, &mut output).unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        assert!(result.starts_with("2024-01-15T12:30:45.123456789Z"));
+    }
+
+    #[test]
+    fn test_csv_subsecond_bar_timestamps() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2),
+            Ohlcv::new(
+                timestamp + chrono::TimeDelta::milliseconds(250),
+                1.15,
+                1.2,
+                1.1,
+                1.2,
+                4.0,
+                2,
+            ),
+        ];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new()
+            .with_header(false)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let mut lines = result.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("2024-01-15T12:00:00.000Z,")
+        );
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("2024-01-15T12:00:00.250Z,")
+        );
+    }
+
+    #[test]
+    fn test_csv_side_volumes() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars =
+            vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0)];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new().write_ohlcv(&bars, &mut output).unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let mut lines = result.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .ends_with("tick_count,ask_volume,bid_volume,imbalance")
+        );
+        assert!(lines.next().unwrap().ends_with(",2,1,3,0.5"));
+    }
+
+    #[test]
+    fn test_csv_price_stats() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_price_stats(1.125, 0.5, 0.75),
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2),
+        ];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new().write_ohlcv(&bars, &mut output).unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let lines: Vec<_> = result.lines().collect();
+        assert!(lines[0].ends_with("tick_count,vwap,avg_spread,max_spread"));
+        assert!(lines[1].ends_with(",2,1.125,0.5,0.75"));
+        assert!(lines[2].ends_with(",2,,,"));
+    }
+
+    #[test]
+    fn test_csv_indicators() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2); 2];
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None, Some(1.15)],
+        };
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new()
+            .write_ohlcv_with_indicators(&bars, std::slice::from_ref(&sma), &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let lines: Vec<_> = result.lines().collect();
+        assert!(lines[0].ends_with("tick_count,sma_2"));
+        assert!(lines[1].ends_with(",2,"));
+        assert!(lines[2].ends_with(",2,1.15"));
+
+        let short = IndicatorColumn {
+            values: vec![None],
+            ..sma
+        };
+        let err = CsvFormatter::new()
+            .write_ohlcv_with_indicators(&bars, &[short], Cursor::new(Vec::new()))
+            .unwrap_err();
+        assert!(matches!(err, FormatError::ColumnLength { rows: 2, .. }));
+    }
+
+    #[test]
+    fn test_csv_microstructure() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_microstructure(0.5, 3, 1, 0.25),
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2),
+        ];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new().write_ohlcv(&bars, &mut output).unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let lines: Vec<_> = result.lines().collect();
+        assert!(lines[0].ends_with("tick_count,tick_rate,bid_changes,ask_changes,twa_spread"));
+        assert!(lines[1].ends_with(",2,0.5,3,1,0.25"));
+        assert!(lines[2].ends_with(",2,,,,"));
+    }
+
+    #[test]
+    fn test_csv_tick_times() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let first = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 5).unwrap();
+        let last = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 50).unwrap();
+        let bars = vec![
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)
+                .with_tick_times(first, last)
+                .with_partial(true),
+        ];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new().write_ohlcv(&bars, &mut output).unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let mut lines = result.lines();
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .ends_with("tick_count,first_tick_ts,last_tick_ts,is_partial")
+        );
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .ends_with(",2024-01-15T12:00:05.000Z,2024-01-15T12:00:50.000Z,true")
+        );
+    }
+
+    #[test]
+    fn test_csv_returns() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap();
+        let returns = vec![PriceReturn {
+            timestamp,
+            value: -0.25,
+            tick_count: 42,
+        }];
+        let mut output = Cursor::new(Vec::new());
+
+        CsvFormatter::new()
+            .write_returns(&returns, &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(
+            result,
+            "timestamp,return,tick_count\n2024-01-15T13:00:00Z,-0.25,42\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_derived_columns() {
+        let mut output = Vec::new();
+        CsvFormatter::new()
+            .with_price_digits(4)
+            .with_derived_columns(DerivedColumns::new().with_spread().with_mid())
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "timestamp,ask,bid,ask_volume,bid_volume,spread,mid\n\
+             2024-01-15T12:30:45.000Z,1.1001,1.1000,100,200,0.0001,1.10005\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_bid_ask_bars() {
+        let bar = OhlcvBidAsk {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap(),
+            bid_open: 1.1,
+            bid_high: 1.2,
+            bid_low: 1.0,
+            bid_close: 1.15,
+            ask_open: 1.1001,
+            ask_high: 1.2001,
+            ask_low: 1.0001,
+            ask_close: 1.1501,
+            bid_volume: 3.0,
+            ask_volume: 1.5,
+            tick_count: 2,
+            is_partial: false,
+        };
+        let mut output = Vec::new();
+
+        CsvFormatter::new()
+            .with_price_digits(4)
+            .write_bid_ask_bars(&[bar], &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "timestamp,bid_open,bid_high,bid_low,bid_close,ask_open,ask_high,ask_low,ask_close,\
+             bid_volume,ask_volume,tick_count\n\
+             2024-01-15T12:00:00Z,1.1000,1.2000,1.0000,1.1500,1.1001,1.2001,1.0001,1.1501,3,1.5,2\n"
+        );
+
+        // A partial bar adds the is_partial column
+        let mut output = Vec::new();
+        CsvFormatter::new()
+            .write_bid_ask_bars(
+                &[OhlcvBidAsk {
+                    is_partial: true,
+                    ..bar
+                }],
+                &mut output,
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output
+                .lines()
+                .next()
+                .unwrap()
+                .ends_with(",tick_count,is_partial")
+        );
+        assert!(output.ends_with(",2,true\n"));
+    }
+
+    #[test]
+    fn test_csv_symbol() {
+        let mut output = Vec::new();
+
+        CsvFormatter::new()
+            .with_symbol("eurusd")
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        assert_eq!(
+            result,
+            "symbol,timestamp,ask,bid,ask_volume,bid_volume\n\
+             eurusd,2024-01-15T12:30:45.000Z,1.1001,1.1,100,200\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_append() {
+        let formatter = CsvFormatter::new().with_append("timestamp,ask,bid,ask_volume,bid_volume");
+        let mut output = Vec::new();
+
+        formatter
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        assert_eq!(result, "2024-01-15T12:30:45.000Z,1.1001,1.1,100,200\n");
+
+        let err = formatter
+            .with_symbol("eurusd")
+            .write_ticks(&[create_test_tick()], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::SchemaMismatch { .. }));
+    }
+
+    #[test]
+    fn test_csv_column_selection() {
+        let formatter = CsvFormatter::new().with_columns(["bid", "timestamp", "ask"]);
+        let mut output = Vec::new();
+
+        formatter
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output).unwrap();
+        assert_eq!(
+            result,
+            "bid,timestamp,ask\n1.1,2024-01-15T12:30:45.000Z,1.1001\n"
+        );
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+        let err = CsvFormatter::new()
+            .with_columns(["close", "vwap"])
+            .write_ohlcv(&bars, Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::UnknownColumn(name) if name == "vwap"));
+    }
+
+    #[test]
+    fn test_csv_timestamp_styles() {
+        let write = |style| {
+            let mut output = Vec::new();
+            CsvFormatter::new()
+                .with_header(false)
+                .with_columns(["timestamp"])
+                .with_timestamp_style(style)
+                .write_ticks(&[create_test_tick()], &mut output)
+                .map(|()| String::from_utf8(output).unwrap())
+        };
+
+        assert_eq!(
+            write(TimestampStyle::EpochMillis).unwrap(),
+            "1705321845000\n"
+        );
+        assert_eq!(
+            write(TimestampStyle::EpochMicros).unwrap(),
+            "1705321845000000\n"
+        );
+        assert_eq!(
+            write(TimestampStyle::Strftime("%Y%m%d %H%M%S".to_string())).unwrap(),
+            "20240115 123045\n"
+        );
+        assert!(matches!(
+            write(TimestampStyle::Strftime("%Q".to_string())),
+            Err(FormatError::InvalidTimestampFormat(_))
+        ));
+    }
+
     #[test]
     fn test_tsv() {
         let formatter = CsvFormatter::tsv();
@@ -160,4 +1228,118 @@ mod tests {
         let result = String::from_utf8(output.into_inner()).unwrap();
         assert!(result.contains("timestamp\task\tbid"));
     }
+
+    #[test]
+    fn test_csv_quoting_and_decimal_comma() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+        let indicators = [IndicatorColumn {
+            name: "band;\"upper\"".to_string(),
+            values: vec![Some(1.25)],
+        }];
+
+        let mut output = Vec::new();
+        CsvFormatter::european()
+            .with_symbol("eur;usd")
+            .write_ohlcv_with_indicators(&bars, &indicators, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "symbol;timestamp;open;high;low;close;volume;tick_count;\"band;\"\"upper\"\"\"\n\
+             \"eur;usd\";2024-01-15T12:00:00Z;1,1;1,2;1;1,15;4;2;1,25\n"
+        );
+
+        // A decimal comma with the comma delimiter quotes the numbers
+        let mut output = Vec::new();
+        CsvFormatter::new()
+            .with_decimal_separator(',')
+            .with_header(false)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2024-01-15T12:00:00Z,\"1,1\",\"1,2\",1,\"1,15\",4,2\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_metadata_comment() {
+        let metadata = OutputMetadata::new()
+            .with_parameter("start", "2024-01-15")
+            .without_generated_at();
+        let formatter = CsvFormatter::new().with_metadata(metadata.clone());
+
+        let mut output = Vec::new();
+        formatter
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+        let result = String::from_utf8(output).unwrap();
+        let mut lines = result.lines();
+        assert_eq!(
+            OutputMetadata::from_header_line(lines.next().unwrap()),
+            Some(metadata)
+        );
+        assert_eq!(
+            lines.next(),
+            Some("timestamp,ask,bid,ask_volume,bid_volume")
+        );
+
+        let mut output = Vec::new();
+        formatter
+            .with_append("timestamp,ask,bid,ask_volume,bid_volume")
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+        assert!(!output.starts_with(b"#"));
+    }
+
+    #[test]
+    fn test_csv_streaming_matches_batch() {
+        let formatter = CsvFormatter::new();
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars: Vec<_> = (0..3)
+            .map(|i| Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, i).with_side_volumes(1.0, 3.0))
+            .collect();
+        let mut expected = Vec::new();
+        formatter.write_ohlcv(&bars, &mut expected).unwrap();
+
+        let mut output = Vec::new();
+        let mut writer = formatter.ohlcv_writer(&mut output).unwrap();
+        writer.write_batch(&[]).unwrap();
+        writer.write_batch(&bars[..1]).unwrap();
+        writer.write_batch(&bars[1..]).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(output, expected);
+
+        let mut output = Vec::new();
+        let mut writer = formatter.tick_writer(&mut output).unwrap();
+        writer.write_batch(&[create_test_tick()]).unwrap();
+        writer.finish().unwrap();
+        let mut expected = Vec::new();
+        formatter
+            .write_ticks(&[create_test_tick()], &mut expected)
+            .unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_csv_gzip() {
+        use std::io::Read;
+
+        let formatter = CsvFormatter::new().with_compression(OutputCompression::Gzip);
+        assert_eq!(formatter.extension(), "csv.gz");
+        let ticks = vec![create_test_tick(); 10];
+        let mut output = Vec::new();
+        formatter.write_ticks(&ticks, &mut output).unwrap();
+
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(output.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        let mut expected = Vec::new();
+        CsvFormatter::new()
+            .write_ticks(&ticks, &mut expected)
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
 }
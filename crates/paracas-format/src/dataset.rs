@@ -0,0 +1,179 @@
+//! Hive-partitioned Parquet datasets.
+//!
+//! Spark, DuckDB and Polars prune partitions by the `key=value` directory
+//! names, so queries over one instrument or a few days of a large archive
+//! only open the files they need.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use paracas_aggregate::Ohlcv;
+use paracas_types::Tick;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use crate::formatter::OhlcvColumns;
+use crate::{BatchWriter, FormatError, ParquetFormatter, StreamingFormatter};
+
+/// File name of the single file in each partition.
+pub const PARTITION_FILE: &str = "part-0.parquet";
+
+impl ParquetFormatter {
+    /// Returns the file of the partition holding `date` for `instrument`:
+    /// `<root>/instrument=<instrument>/date=<YYYY-MM-DD>/part-0.parquet`.
+    #[must_use]
+    pub fn partition_path(root: &Path, instrument: &str, date: NaiveDate) -> PathBuf {
+        root.join(format!("instrument={instrument}"))
+            .join(format!("date={}", date.format("%Y-%m-%d")))
+            .join(PARTITION_FILE)
+    }
+
+    /// Writes ticks as a dataset under `root`, one file per UTC date (see
+    /// [`partition_path`](Self::partition_path)).
+    ///
+    /// Existing files of the same partitions are replaced. Returns the
+    /// files written, by date.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory or file cannot be created or
+    /// writing fails.
+    pub fn write_tick_dataset(
+        &self,
+        ticks: &[Tick],
+        root: &Path,
+        instrument: &str,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        write_partitions(
+            ticks,
+            |tick| tick.timestamp,
+            root,
+            instrument,
+            |file| self.tick_writer(file),
+        )
+    }
+
+    /// Writes bars as a dataset under `root`, one file per UTC date of the
+    /// bar timestamps (see [`partition_path`](Self::partition_path)).
+    ///
+    /// Every file has the optional columns carried by any of the bars, so
+    /// the partitions share one schema. Existing files of the same
+    /// partitions are replaced. Returns the files written, by date.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory or file cannot be created or
+    /// writing fails.
+    pub fn write_ohlcv_dataset(
+        &self,
+        bars: &[Ohlcv],
+        root: &Path,
+        instrument: &str,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        let columns = OhlcvColumns::of(bars);
+        write_partitions(
+            bars,
+            |bar| bar.timestamp,
+            root,
+            instrument,
+            |file| Ok(self.ohlcv_writer(file)?.with_columns(columns)),
+        )
+    }
+}
+
+/// Writes `records` into one partition file per UTC date.
+///
+/// Records need not be sorted; each partition receives its records in
+/// input order.
+fn write_partitions<T, B: BatchWriter<T>>(
+    records: &[T],
+    timestamp: impl Fn(&T) -> DateTime<Utc>,
+    root: &Path,
+    instrument: &str,
+    open: impl Fn(BufWriter<File>) -> Result<B, FormatError>,
+) -> Result<Vec<PathBuf>, FormatError> {
+    let date = |record: &T| timestamp(record).date_naive();
+    let mut partitions: BTreeMap<NaiveDate, Vec<&[T]>> = BTreeMap::new();
+    for run in records.chunk_by(|a, b| date(a) == date(b)) {
+        partitions.entry(date(&run[0])).or_default().push(run);
+    }
+
+    partitions
+        .into_iter()
+        .map(|(date, runs)| {
+            let path = ParquetFormatter::partition_path(root, instrument, date);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let mut writer = open(BufWriter::new(File::create(&path)?))?;
+            for run in runs {
+                writer.write_batch(run)?;
+            }
+            writer.finish()?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn row_count(path: &Path) -> i64 {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap();
+        builder.metadata().file_metadata().num_rows()
+    }
+
+    #[test]
+    fn test_tick_dataset() {
+        let dir = tempfile::tempdir().unwrap();
+        let tick = |day: u32, hour: u32| {
+            let timestamp = Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap();
+            Tick::new(timestamp, 1.1001, 1.1000, 1.0, 1.0)
+        };
+        let ticks = [tick(2, 10), tick(2, 23), tick(3, 0), tick(2, 12)];
+
+        let files = ParquetFormatter::new()
+            .write_tick_dataset(&ticks, dir.path(), "EURUSD")
+            .unwrap();
+
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        assert_eq!(
+            files,
+            [
+                ParquetFormatter::partition_path(dir.path(), "EURUSD", day(2)),
+                ParquetFormatter::partition_path(dir.path(), "EURUSD", day(3)),
+            ]
+        );
+        assert!(files[0].ends_with("instrument=EURUSD/date=2024-01-02/part-0.parquet"));
+        assert_eq!(row_count(&files[0]), 3);
+        assert_eq!(row_count(&files[1]), 1);
+    }
+
+    #[test]
+    fn test_ohlcv_dataset_shares_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let bar = |day: u32| {
+            let timestamp = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)
+        };
+        let bars = [bar(2), bar(3).with_side_volumes(1.0, 3.0)];
+
+        let files = ParquetFormatter::new()
+            .write_ohlcv_dataset(&bars, dir.path(), "eurusd")
+            .unwrap();
+
+        let schemas: Vec<_> = files
+            .iter()
+            .map(|path| {
+                let file = File::open(path).unwrap();
+                let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+                builder.schema().clone()
+            })
+            .collect();
+        assert_eq!(schemas[0], schemas[1]);
+        assert!(schemas[0].field_with_name("ask_volume").is_ok());
+    }
+}
@@ -0,0 +1,338 @@
+//! DuckDB output.
+//!
+//! Records are appended straight into a table of a DuckDB database file
+//! through DuckDB's bulk appender, so downloads can be queried without an
+//! intermediate CSV import.
+
+use ::duckdb::Connection;
+use ::duckdb::types::{TimeUnit, Value};
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use std::path::Path;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{BARS_TABLE, FileFormatter, FormatError, RETURNS_TABLE, TICKS_TABLE};
+
+/// DuckDB formatter.
+///
+/// Tables are created on first write with a `symbol` column and a
+/// `TIMESTAMP` (microsecond) `timestamp` column, and appended to
+/// afterwards. DuckDB prunes row groups by their min/max timestamps, so no
+/// index is created.
+#[derive(Debug, Clone, Default)]
+pub struct DuckDbFormatter {
+    /// Table written, or the default table of the records if unset.
+    table: Option<String>,
+    /// Instrument stored in the `symbol` column, if any.
+    symbol: Option<String>,
+}
+
+/// A column of a table: its name and SQL definition.
+type ColumnDef = (String, &'static str);
+
+impl DuckDbFormatter {
+    /// Creates a new DuckDB formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            table: None,
+            symbol: None,
+        }
+    }
+
+    /// Writes into `table` instead of [`TICKS_TABLE`], [`BARS_TABLE`] or
+    /// [`RETURNS_TABLE`].
+    #[must_use]
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// Stores `symbol` in the `symbol` column of every row (default: NULL).
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Appends ticks to the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table lacks a column, or appending fails.
+    pub fn write_ticks(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        let columns = [
+            ("ask", "DOUBLE NOT NULL"),
+            ("bid", "DOUBLE NOT NULL"),
+            ("ask_volume", "FLOAT NOT NULL"),
+            ("bid_volume", "FLOAT NOT NULL"),
+        ];
+        let rows = ticks.iter().map(|tick| {
+            vec![
+                timestamp(tick.timestamp),
+                Value::Double(tick.ask),
+                Value::Double(tick.bid),
+                Value::Float(tick.ask_volume),
+                Value::Float(tick.bid_volume),
+            ]
+        });
+        self.append(path, TICKS_TABLE, &named(&columns), rows)
+    }
+
+    /// Appends bars to the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table lacks a column, or appending fails.
+    pub fn write_ohlcv(&self, bars: &[Ohlcv], path: &Path) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], path)
+    }
+
+    /// Appends bars with a nullable column per indicator to the database
+    /// at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, or [`FormatError::Database`] if the database cannot
+    /// be opened, the table lacks a column, or appending fails.
+    pub fn write_ohlcv_with_indicators(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let optional = OhlcvColumns::of(bars);
+        let mut columns = named(&[
+            ("open", "DOUBLE NOT NULL"),
+            ("high", "DOUBLE NOT NULL"),
+            ("low", "DOUBLE NOT NULL"),
+            ("close", "DOUBLE NOT NULL"),
+            ("volume", "DOUBLE NOT NULL"),
+            ("tick_count", "UINTEGER NOT NULL"),
+        ]);
+        if optional.side_volumes {
+            columns.extend(named(&[
+                ("ask_volume", "DOUBLE"),
+                ("bid_volume", "DOUBLE"),
+                ("imbalance", "DOUBLE"),
+            ]));
+        }
+        if optional.tick_times {
+            columns.extend(named(&[
+                ("first_tick_ts", "TIMESTAMP"),
+                ("last_tick_ts", "TIMESTAMP"),
+                ("is_partial", "BOOLEAN NOT NULL"),
+            ]));
+        }
+        if optional.price_stats {
+            columns.extend(named(&[
+                ("vwap", "DOUBLE"),
+                ("avg_spread", "DOUBLE"),
+                ("max_spread", "DOUBLE"),
+            ]));
+        }
+        if optional.microstructure {
+            columns.extend(named(&[
+                ("tick_rate", "DOUBLE"),
+                ("bid_changes", "UINTEGER"),
+                ("ask_changes", "UINTEGER"),
+                ("twa_spread", "DOUBLE"),
+            ]));
+        }
+        columns.extend(indicators.iter().map(|c| (c.name.clone(), "DOUBLE")));
+
+        let double = |value: Option<f64>| value.map_or(Value::Null, Value::Double);
+        let uint = |value: Option<u32>| value.map_or(Value::Null, Value::UInt);
+        let rows = bars.iter().enumerate().map(|(row, bar)| {
+            let mut values = vec![
+                timestamp(bar.timestamp),
+                Value::Double(bar.open),
+                Value::Double(bar.high),
+                Value::Double(bar.low),
+                Value::Double(bar.close),
+                Value::Double(bar.volume),
+                Value::UInt(bar.tick_count),
+            ];
+            if optional.side_volumes {
+                values.extend([
+                    double(bar.ask_volume),
+                    double(bar.bid_volume),
+                    double(bar.imbalance),
+                ]);
+            }
+            if optional.tick_times {
+                values.extend([
+                    bar.first_tick_ts.map_or(Value::Null, timestamp),
+                    bar.last_tick_ts.map_or(Value::Null, timestamp),
+                    Value::Boolean(bar.is_partial),
+                ]);
+            }
+            if optional.price_stats {
+                values.extend([
+                    double(bar.vwap),
+                    double(bar.avg_spread),
+                    double(bar.max_spread),
+                ]);
+            }
+            if optional.microstructure {
+                values.extend([
+                    double(bar.tick_rate),
+                    uint(bar.bid_changes),
+                    uint(bar.ask_changes),
+                    double(bar.twa_spread),
+                ]);
+            }
+            values.extend(indicators.iter().map(|c| double(c.values[row])));
+            values
+        });
+        self.append(path, BARS_TABLE, &columns, rows)
+    }
+
+    /// Appends a return series to the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table lacks a column, or appending fails.
+    pub fn write_returns(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        let columns = [
+            ("return", "DOUBLE NOT NULL"),
+            ("tick_count", "UINTEGER NOT NULL"),
+        ];
+        let rows = returns.iter().map(|r| {
+            vec![
+                timestamp(r.timestamp),
+                Value::Double(r.value),
+                Value::UInt(r.tick_count),
+            ]
+        });
+        self.append(path, RETURNS_TABLE, &named(&columns), rows)
+    }
+
+    /// Creates the table if missing and appends `rows` of the timestamp and
+    /// `columns`.
+    ///
+    /// The appender names its columns, so appending to a table without one
+    /// of them fails instead of shifting values into other columns.
+    fn append(
+        &self,
+        path: &Path,
+        default_table: &str,
+        columns: &[ColumnDef],
+        rows: impl Iterator<Item = Vec<Value>>,
+    ) -> Result<(), FormatError> {
+        let table = self.table.as_deref().unwrap_or(default_table);
+        let definitions: Vec<_> = [("symbol".to_string(), "VARCHAR")]
+            .into_iter()
+            .chain([("timestamp".to_string(), "TIMESTAMP NOT NULL")])
+            .chain(columns.iter().cloned())
+            .collect();
+        let names: Vec<_> = definitions.iter().map(|(name, _)| name.as_str()).collect();
+
+        let conn = Connection::open(path).map_err(database)?;
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {} ({});",
+            quote(table),
+            definitions
+                .iter()
+                .map(|(name, definition)| format!("{} {definition}", quote(name)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ))
+        .map_err(database)?;
+
+        let mut appender = conn
+            .appender_with_columns(table, &names)
+            .map_err(database)?;
+        let symbol = self.symbol.clone().map_or(Value::Null, Value::Text);
+        for row in rows {
+            let values = std::iter::once(symbol.clone()).chain(row);
+            appender
+                .append_row(::duckdb::appender_params_from_iter(values))
+                .map_err(database)?;
+        }
+        appender.flush().map_err(database)
+    }
+}
+
+impl FileFormatter for DuckDbFormatter {
+    fn write_ticks_to(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        self.write_ticks(ticks, path)
+    }
+
+    fn write_ohlcv_to(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, indicators, path)
+    }
+
+    fn write_returns_to(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        self.write_returns(returns, path)
+    }
+}
+
+/// Returns a `TIMESTAMP` value.
+const fn timestamp(timestamp: DateTime<Utc>) -> Value {
+    Value::Timestamp(TimeUnit::Microsecond, timestamp.timestamp_micros())
+}
+
+/// Names the given column definitions.
+fn named(columns: &[(&str, &'static str)]) -> Vec<ColumnDef> {
+    columns
+        .iter()
+        .map(|&(name, definition)| (name.to_string(), definition))
+        .collect()
+}
+
+/// Quotes an SQL identifier.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Converts a DuckDB error.
+fn database(e: ::duckdb::Error) -> FormatError {
+    FormatError::Database(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_duckdb_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("eurusd.duckdb");
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+
+        let formatter = DuckDbFormatter::new().with_symbol("eurusd");
+        formatter.write_ohlcv(&[bar], &path).unwrap();
+        formatter.write_ohlcv(&[bar], &path).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let (count, epoch): (i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), MIN(epoch(timestamp))::BIGINT FROM bars WHERE symbol = 'eurusd'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(epoch, timestamp.timestamp());
+        drop(conn);
+
+        let err = formatter
+            .write_ohlcv(&[bar.with_side_volumes(1.0, 3.0)], &path)
+            .unwrap_err();
+        assert!(matches!(err, FormatError::Database(_)));
+    }
+}
@@ -1,10 +1,21 @@
 //! Output format abstraction.
 
-use paracas_aggregate::Ohlcv;
-use paracas_types::Tick;
+use paracas_aggregate::{IndicatorColumn, Ohlcv, OhlcvBidAsk, PriceReturn, VolumeProfile};
+use paracas_types::{PriceEncoding, Tick};
 use std::io::Write;
 use thiserror::Error;
 
+use crate::OutputCompression;
+
+/// Default table of ticks in database output.
+pub const TICKS_TABLE: &str = "ticks";
+
+/// Default table of bars in database output.
+pub const BARS_TABLE: &str = "bars";
+
+/// Default table of return series in database output.
+pub const RETURNS_TABLE: &str = "returns";
+
 /// Output format identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
 pub enum OutputFormat {
@@ -17,6 +28,34 @@ pub enum OutputFormat {
     Ndjson,
     /// Apache Parquet format.
     Parquet,
+    /// SQLite database.
+    Sqlite,
+    /// DuckDB database.
+    DuckDb,
+    /// Apache Avro object container format.
+    Avro,
+    /// Stream of MessagePack maps.
+    Msgpack,
+    /// HDF5 file of typed datasets.
+    Hdf5,
+    /// Excel workbook.
+    Xlsx,
+    /// Length-delimited protobuf messages.
+    Protobuf,
+    /// Apache ORC file.
+    Orc,
+    /// MetaTrader 4 History Center CSV.
+    Mt4,
+    /// MetaTrader 5 CSV.
+    Mt5,
+    /// MetaTrader 4 `.hst` history file.
+    Hst,
+    /// NinjaTrader import text.
+    NinjaTrader,
+    /// TradingView chart CSV.
+    TradingView,
+    /// QuantConnect Lean CSV.
+    Lean,
 }
 
 impl OutputFormat {
@@ -28,19 +67,136 @@ impl OutputFormat {
             Self::Json => "json",
             Self::Ndjson => "ndjson",
             Self::Parquet => "parquet",
+            Self::Sqlite => "sqlite",
+            Self::DuckDb => "duckdb",
+            Self::Avro => "avro",
+            Self::Msgpack => "msgpack",
+            Self::Hdf5 => "h5",
+            Self::Xlsx => "xlsx",
+            Self::Protobuf => "pb",
+            Self::Orc => "orc",
+            Self::Mt4 | Self::Mt5 => "csv",
+            Self::Hst => "hst",
+            Self::NinjaTrader => "txt",
+            Self::TradingView | Self::Lean => "csv",
         }
     }
 
-    /// Returns all available formats.
+    /// Returns the name of this format, which parses back to it.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Mt4 => "mt4",
+            Self::Mt5 => "mt5",
+            Self::NinjaTrader => "ninjatrader",
+            Self::TradingView => "tradingview",
+            Self::Lean => "lean",
+            _ => self.extension(),
+        }
+    }
+
+    /// Returns true if the format has nested columns, which hold the volume
+    /// profile of each bar.
+    #[must_use]
+    pub const fn has_nested_columns(&self) -> bool {
+        matches!(self, Self::Json | Self::Ndjson | Self::Parquet)
+    }
+
+    /// Returns all formats, including those not compiled in.
     #[must_use]
     pub const fn all() -> &'static [Self] {
-        &[Self::Csv, Self::Json, Self::Ndjson, Self::Parquet]
+        &[
+            Self::Csv,
+            Self::Json,
+            Self::Ndjson,
+            Self::Parquet,
+            Self::Sqlite,
+            Self::DuckDb,
+            Self::Avro,
+            Self::Msgpack,
+            Self::Hdf5,
+            Self::Xlsx,
+            Self::Protobuf,
+            Self::Orc,
+            Self::Mt4,
+            Self::Mt5,
+            Self::Hst,
+            Self::NinjaTrader,
+            Self::TradingView,
+            Self::Lean,
+        ]
+    }
+
+    /// Returns the cargo feature that provides this format, if it is optional.
+    #[must_use]
+    pub const fn required_feature(&self) -> Option<&'static str> {
+        match self {
+            Self::Csv
+            | Self::Json
+            | Self::Ndjson
+            | Self::Mt4
+            | Self::Mt5
+            | Self::Hst
+            | Self::NinjaTrader
+            | Self::TradingView
+            | Self::Lean => None,
+            Self::Parquet => Some("parquet"),
+            Self::Sqlite => Some("sqlite"),
+            Self::DuckDb => Some("duckdb"),
+            Self::Avro => Some("avro"),
+            Self::Msgpack => Some("msgpack"),
+            Self::Hdf5 => Some("hdf5"),
+            Self::Xlsx => Some("xlsx"),
+            Self::Protobuf => Some("protobuf"),
+            Self::Orc => Some("orc"),
+        }
+    }
+
+    /// Returns true if this format is compiled in.
+    #[must_use]
+    pub const fn is_available(&self) -> bool {
+        match self {
+            Self::Csv
+            | Self::Json
+            | Self::Ndjson
+            | Self::Mt4
+            | Self::Mt5
+            | Self::Hst
+            | Self::NinjaTrader
+            | Self::TradingView
+            | Self::Lean => true,
+            Self::Parquet => cfg!(feature = "parquet"),
+            Self::Sqlite => cfg!(feature = "sqlite"),
+            Self::DuckDb => cfg!(feature = "duckdb"),
+            Self::Avro => cfg!(feature = "avro"),
+            Self::Msgpack => cfg!(feature = "msgpack"),
+            Self::Hdf5 => cfg!(feature = "hdf5"),
+            Self::Xlsx => cfg!(feature = "xlsx"),
+            Self::Protobuf => cfg!(feature = "protobuf"),
+            Self::Orc => cfg!(feature = "orc"),
+        }
+    }
+
+    /// Returns the format if it is compiled in.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Unavailable`] naming the cargo feature to
+    /// enable otherwise.
+    pub const fn ensure_available(self) -> Result<Self, FormatError> {
+        match self.required_feature() {
+            Some(feature) if !self.is_available() => Err(FormatError::Unavailable {
+                format: self,
+                feature,
+            }),
+            _ => Ok(self),
+        }
     }
 }
 
 impl std::fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.extension())
+        write!(f, "{}", self.name())
     }
 }
 
@@ -53,11 +209,191 @@ impl std::str::FromStr for OutputFormat {
             "json" => Ok(Self::Json),
             "ndjson" | "jsonl" => Ok(Self::Ndjson),
             "parquet" | "pq" => Ok(Self::Parquet),
+            "sqlite" | "db" => Ok(Self::Sqlite),
+            "duckdb" | "ddb" => Ok(Self::DuckDb),
+            "avro" => Ok(Self::Avro),
+            "msgpack" | "mp" => Ok(Self::Msgpack),
+            "hdf5" | "h5" => Ok(Self::Hdf5),
+            "xlsx" | "excel" => Ok(Self::Xlsx),
+            "protobuf" | "pb" => Ok(Self::Protobuf),
+            "orc" => Ok(Self::Orc),
+            "mt4" => Ok(Self::Mt4),
+            "mt5" => Ok(Self::Mt5),
+            "hst" => Ok(Self::Hst),
+            "ninjatrader" | "nt" => Ok(Self::NinjaTrader),
+            "tradingview" | "tv" => Ok(Self::TradingView),
+            "lean" | "quantconnect" => Ok(Self::Lean),
             _ => Err(FormatError::UnknownFormat(s.to_string())),
         }
     }
 }
 
+/// Tick columns derived from the quotes, written after the volumes.
+///
+/// Most consumers compute at least one of them; writing them saves a
+/// post-processing step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DerivedColumns {
+    /// `spread`, the ask minus the bid.
+    pub spread: bool,
+    /// `mid`, halfway between the bid and the ask.
+    pub mid: bool,
+}
+
+impl DerivedColumns {
+    /// Creates a set of no derived columns.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            spread: false,
+            mid: false,
+        }
+    }
+
+    /// Adds the `spread` column.
+    #[must_use]
+    pub const fn with_spread(mut self) -> Self {
+        self.spread = true;
+        self
+    }
+
+    /// Adds the `mid` column.
+    #[must_use]
+    pub const fn with_mid(mut self) -> Self {
+        self.mid = true;
+        self
+    }
+
+    /// Returns whether no column is derived.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        !self.spread && !self.mid
+    }
+}
+
+/// Optional OHLCV column groups present in a set of bars.
+///
+/// Each group is written when at least one bar carries it, so every row of a
+/// file has the same columns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct OhlcvColumns {
+    /// `ask_volume`, `bid_volume` and `imbalance`.
+    pub(crate) side_volumes: bool,
+    /// `first_tick_ts`, `last_tick_ts` and `is_partial`.
+    pub(crate) tick_times: bool,
+    /// `vwap`, `avg_spread` and `max_spread`.
+    pub(crate) price_stats: bool,
+    /// `tick_rate`, `bid_changes`, `ask_changes` and `twa_spread`.
+    pub(crate) microstructure: bool,
+    /// Whether any bar starts within a second, as activity and price bars
+    /// do, instead of on a whole second.
+    pub(crate) subsecond: bool,
+}
+
+impl OhlcvColumns {
+    /// Detects the column groups carried by `bars`.
+    pub(crate) fn of(bars: &[Ohlcv]) -> Self {
+        Self {
+            side_volumes: bars.iter().any(Ohlcv::has_side_volumes),
+            tick_times: bars.iter().any(Ohlcv::has_tick_times),
+            price_stats: bars.iter().any(Ohlcv::has_price_stats),
+            microstructure: bars.iter().any(Ohlcv::has_microstructure),
+            subsecond: bars
+                .iter()
+                .any(|bar| bar.timestamp.timestamp_subsec_nanos() != 0),
+        }
+    }
+}
+
+/// Checks that every indicator column has one value per bar.
+pub(crate) fn check_indicators(
+    bars: &[Ohlcv],
+    indicators: &[IndicatorColumn],
+) -> Result<(), FormatError> {
+    indicators
+        .iter()
+        .find(|c| c.values.len() != bars.len())
+        .map_or(Ok(()), |column| {
+            Err(FormatError::ColumnLength {
+                name: column.name.clone(),
+                values: column.values.len(),
+                rows: bars.len(),
+            })
+        })
+}
+
+/// Rounds `price` to `digits` decimal places.
+pub(crate) fn round_price(price: f64, digits: u32) -> f64 {
+    let scale = 10f64.powi(digits as i32);
+    (price * scale).round() / scale
+}
+
+/// Returns `tick` with its prices rounded to `digits` decimal places.
+pub(crate) fn round_tick(tick: &Tick, digits: u32) -> Tick {
+    Tick {
+        ask: round_price(tick.ask, digits),
+        bid: round_price(tick.bid, digits),
+        ..*tick
+    }
+}
+
+/// Returns `bar` with its prices and spreads rounded to `digits` decimal
+/// places.
+pub(crate) fn round_bar(bar: &Ohlcv, digits: u32) -> Ohlcv {
+    let round = |price: Option<f64>| price.map(|p| round_price(p, digits));
+    Ohlcv {
+        open: round_price(bar.open, digits),
+        high: round_price(bar.high, digits),
+        low: round_price(bar.low, digits),
+        close: round_price(bar.close, digits),
+        vwap: round(bar.vwap),
+        avg_spread: round(bar.avg_spread),
+        max_spread: round(bar.max_spread),
+        twa_spread: round(bar.twa_spread),
+        ..*bar
+    }
+}
+
+/// Returns `bar` with the prices of both sides rounded to `digits` decimal
+/// places.
+pub(crate) fn round_bid_ask_bar(bar: &OhlcvBidAsk, digits: u32) -> OhlcvBidAsk {
+    OhlcvBidAsk {
+        bid_open: round_price(bar.bid_open, digits),
+        bid_high: round_price(bar.bid_high, digits),
+        bid_low: round_price(bar.bid_low, digits),
+        bid_close: round_price(bar.bid_close, digits),
+        ask_open: round_price(bar.ask_open, digits),
+        ask_high: round_price(bar.ask_high, digits),
+        ask_low: round_price(bar.ask_low, digits),
+        ask_close: round_price(bar.ask_close, digits),
+        ..*bar
+    }
+}
+
+/// Formats `price` with `digits` decimal places, or in full without.
+pub(crate) fn format_price(price: f64, digits: Option<u32>) -> String {
+    digits.map_or_else(
+        || price.to_string(),
+        |digits| format!("{price:.*}", digits as usize),
+    )
+}
+
+/// Checks that there is one volume profile per bar.
+pub(crate) fn check_profiles(
+    bars: &[Ohlcv],
+    profiles: &[VolumeProfile],
+) -> Result<(), FormatError> {
+    if profiles.len() == bars.len() {
+        Ok(())
+    } else {
+        Err(FormatError::ColumnLength {
+            name: "volume_profile".to_string(),
+            values: profiles.len(),
+            rows: bars.len(),
+        })
+    }
+}
+
 /// Errors that can occur during formatting.
 #[derive(Error, Debug)]
 pub enum FormatError {
@@ -65,6 +401,90 @@ pub enum FormatError {
     #[error("Unknown format: {0}")]
     UnknownFormat(String),
 
+    /// The format is known but was not compiled in.
+    #[error("{format} support not compiled in; rebuild with `--features {feature}`")]
+    Unavailable {
+        /// The requested format.
+        format: OutputFormat,
+        /// The cargo feature providing the format.
+        feature: &'static str,
+    },
+
+    /// Unknown output compression.
+    #[error("Unknown compression: {0}")]
+    UnknownCompression(String),
+
+    /// The compression is known but was not compiled in.
+    #[error("{compression} compression not compiled in; rebuild with `--features {feature}`")]
+    CompressionUnavailable {
+        /// The requested compression.
+        compression: OutputCompression,
+        /// The cargo feature providing the compression.
+        feature: &'static str,
+    },
+
+    /// Exact price encodings need the decimal factor of the instrument.
+    #[error("{0} prices need the instrument's decimal factor in the output metadata")]
+    MissingDecimalFactor(PriceEncoding),
+
+    /// A selected CSV column is not among the columns of the records.
+    #[error("Unknown column: {0}")]
+    UnknownColumn(String),
+
+    /// A custom timestamp format is not a valid `strftime` pattern.
+    #[error("Invalid timestamp format: {0}")]
+    InvalidTimestampFormat(String),
+
+    /// Appended records do not have the columns of the existing output.
+    #[error("Cannot append records with columns [{appended}] to output with columns [{existing}]")]
+    SchemaMismatch {
+        /// Columns of the existing output.
+        existing: String,
+        /// Columns of the appended records.
+        appended: String,
+    },
+
+    /// The output style cannot be appended to.
+    #[error("{0} output cannot be appended to")]
+    AppendUnsupported(String),
+
+    /// An extra column does not have one value per row.
+    #[error("Column {name} has {values} values for {rows} rows")]
+    ColumnLength {
+        /// Name of the column.
+        name: String,
+        /// Number of values in the column.
+        values: usize,
+        /// Number of rows written.
+        rows: usize,
+    },
+
+    /// There are more records than the output holds.
+    #[error("{rows} rows exceed the limit of {limit} rows")]
+    TooManyRows {
+        /// Number of records written.
+        rows: usize,
+        /// Most records the output holds.
+        limit: usize,
+    },
+
+    /// Unknown time zone.
+    #[error("Unknown time zone: {0}")]
+    UnknownTimezone(String),
+
+    /// The format cannot hold records of this kind.
+    #[error("{format} output cannot hold {records}")]
+    UnsupportedRecords {
+        /// The output format.
+        format: String,
+        /// The kind of records.
+        records: &'static str,
+    },
+
+    /// The format cannot hold nested columns such as volume profiles.
+    #[error("{0} output cannot hold nested columns such as volume profiles")]
+    NestedColumns(String),
+
     /// I/O error.
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
@@ -76,6 +496,26 @@ pub enum FormatError {
     /// Arrow/Parquet error.
     #[error("Parquet error: {0}")]
     Parquet(String),
+
+    /// SQLite or DuckDB error.
+    #[error("Database error: {0}")]
+    Database(String),
+
+    /// Avro schema or encoding error.
+    #[error("Avro error: {0}")]
+    Avro(String),
+
+    /// HDF5 library error.
+    #[error("HDF5 error: {0}")]
+    Hdf5(String),
+
+    /// Excel workbook error.
+    #[error("XLSX error: {0}")]
+    Xlsx(String),
+
+    /// ORC encoding error.
+    #[error("ORC error: {0}")]
+    Orc(String),
 }
 
 /// Trait for output formatters.
@@ -94,6 +534,107 @@ pub trait Formatter: Send + Sync {
     /// Returns an error if writing fails.
     fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError>;
 
+    /// Writes OHLCV data with indicator columns after the bar columns.
+    ///
+    /// Without indicator columns this is [`write_ohlcv`](Self::write_ohlcv).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::UnsupportedRecords`] if the format has no
+    /// indicator columns, [`FormatError::ColumnLength`] if a column does not
+    /// have one value per bar, or an error if writing fails.
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        if indicators.is_empty() {
+            return self.write_ohlcv(bars, writer);
+        }
+        Err(FormatError::UnsupportedRecords {
+            format: self.extension().to_string(),
+            records: "indicator columns",
+        })
+    }
+
+    /// Writes OHLCV data with the volume profile of each bar as a nested
+    /// `volume_profile` column of price levels.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::NestedColumns`] if the format has no nested
+    /// columns, [`FormatError::ColumnLength`] if there is not one profile
+    /// per bar, or an error if writing fails.
+    fn write_ohlcv_with_profiles<W: Write + Send>(
+        &self,
+        _bars: &[Ohlcv],
+        _profiles: &[VolumeProfile],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(FormatError::NestedColumns(self.extension().to_string()))
+    }
+
+    /// Writes bars carrying both sides of the quote, with the bid and ask
+    /// open, high, low and close side by side.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::UnsupportedRecords`] if the format has no
+    /// layout for them, or an error if writing fails.
+    fn write_bid_ask_bars<W: Write + Send>(
+        &self,
+        _bars: &[OhlcvBidAsk],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(FormatError::UnsupportedRecords {
+            format: self.extension().to_string(),
+            records: "bid/ask bars",
+        })
+    }
+
+    /// Writes a return series to the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::UnsupportedRecords`] if the format has no
+    /// layout for returns, or an error if writing fails.
+    fn write_returns<W: Write + Send>(
+        &self,
+        _returns: &[PriceReturn],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(FormatError::UnsupportedRecords {
+            format: self.extension().to_string(),
+            records: "returns",
+        })
+    }
+
     /// Returns the file extension for this format.
     fn extension(&self) -> &str;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_availability() {
+        assert!(OutputFormat::Csv.is_available());
+        assert_eq!(OutputFormat::Csv.required_feature(), None);
+        assert_eq!(OutputFormat::Parquet.required_feature(), Some("parquet"));
+        assert_eq!(
+            OutputFormat::Parquet.is_available(),
+            cfg!(feature = "parquet")
+        );
+
+        let err = FormatError::Unavailable {
+            format: OutputFormat::Parquet,
+            feature: "parquet",
+        };
+        assert_eq!(
+            err.to_string(),
+            "parquet support not compiled in; rebuild with `--features parquet`"
+        );
+    }
+}
@@ -0,0 +1,392 @@
+//! HDF5 output.
+//!
+//! Records are stored column by column as typed one-dimensional datasets,
+//! in a group per instrument, so files open directly in pandas (through
+//! h5py), MATLAB and the many HDF5-only tools of legacy quant stacks.
+
+use chrono::{DateTime, Utc};
+use hdf5_metno::types::VarLenUnicode;
+use hdf5_metno::{File, Group, Location};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::{Tick, TimestampPrecision};
+use std::path::Path;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{BARS_TABLE, FileFormatter, FormatError, OutputMetadata, RETURNS_TABLE, TICKS_TABLE};
+
+/// Value of missing optional timestamps.
+pub const HDF5_MISSING_TIMESTAMP: i64 = i64::MIN;
+
+/// HDF5 formatter.
+///
+/// Records are written to a `ticks`, `bars` or `returns` group, holding one
+/// dataset per column, inside the instrument group. Writing records again
+/// replaces that group and leaves other instruments of the file intact.
+/// Timestamps are `int64` since the Unix epoch, with their resolution in a
+/// `unit` attribute. Missing values of optional columns are NaN, which
+/// makes optional counts `float64`, or [`HDF5_MISSING_TIMESTAMP`] for
+/// timestamps.
+#[derive(Debug, Clone, Default)]
+pub struct Hdf5Formatter {
+    /// Instrument group written into, or the root group if unset.
+    group: Option<String>,
+    /// Dataset metadata stored as attributes of the records group.
+    metadata: Option<OutputMetadata>,
+    /// Resolution of the timestamp datasets.
+    timestamp_precision: TimestampPrecision,
+}
+
+/// The values of a column, by dataset type.
+#[derive(Debug, Clone)]
+enum Column {
+    /// Timestamps since the Unix epoch.
+    Timestamp(Vec<i64>),
+    /// 64-bit floats.
+    Double(Vec<f64>),
+    /// 32-bit floats.
+    Float(Vec<f32>),
+    /// Unsigned 32-bit integers.
+    Uint(Vec<u32>),
+    /// Booleans, stored as an HDF5 enum.
+    Bool(Vec<bool>),
+}
+
+impl Hdf5Formatter {
+    /// Creates a new HDF5 formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            group: None,
+            metadata: None,
+            timestamp_precision: TimestampPrecision::Millisecond,
+        }
+    }
+
+    /// Writes records into the group `instrument` of the file.
+    #[must_use]
+    pub fn with_group(mut self, instrument: impl Into<String>) -> Self {
+        self.group = Some(instrument.into());
+        self
+    }
+
+    /// Stores dataset metadata as string attributes of the records group,
+    /// named with the [`METADATA_KEY_PREFIX`](crate::METADATA_KEY_PREFIX).
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Sets the resolution of the timestamp datasets (default:
+    /// milliseconds).
+    #[must_use]
+    pub const fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Writes ticks to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Hdf5`] if the file cannot be opened or a
+    /// dataset cannot be written.
+    pub fn write_ticks(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        let column = |f: fn(&Tick) -> f64| ticks.iter().map(f).collect();
+        let columns = vec![
+            (
+                "timestamp".to_string(),
+                self.timestamps(ticks.iter().map(|t| t.timestamp)),
+            ),
+            ("ask".to_string(), Column::Double(column(|t| t.ask))),
+            ("bid".to_string(), Column::Double(column(|t| t.bid))),
+            (
+                "ask_volume".to_string(),
+                Column::Float(ticks.iter().map(|t| t.ask_volume).collect()),
+            ),
+            (
+                "bid_volume".to_string(),
+                Column::Float(ticks.iter().map(|t| t.bid_volume).collect()),
+            ),
+        ];
+        self.write(path, TICKS_TABLE, columns)
+    }
+
+    /// Writes bars to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Hdf5`] if the file cannot be opened or a
+    /// dataset cannot be written.
+    pub fn write_ohlcv(&self, bars: &[Ohlcv], path: &Path) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], path)
+    }
+
+    /// Writes bars with a dataset per indicator to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, or [`FormatError::Hdf5`] if the file cannot be opened
+    /// or a dataset cannot be written.
+    pub fn write_ohlcv_with_indicators(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let optional = OhlcvColumns::of(bars);
+        let double = |f: fn(&Ohlcv) -> f64| Column::Double(bars.iter().map(f).collect());
+        let nullable = |f: fn(&Ohlcv) -> Option<f64>| {
+            Column::Double(bars.iter().map(|bar| f(bar).unwrap_or(f64::NAN)).collect())
+        };
+        let count = |f: fn(&Ohlcv) -> Option<u32>| {
+            Column::Double(
+                bars.iter()
+                    .map(|bar| f(bar).map_or(f64::NAN, f64::from))
+                    .collect(),
+            )
+        };
+        let time = |f: fn(&Ohlcv) -> Option<DateTime<Utc>>| {
+            Column::Timestamp(
+                bars.iter()
+                    .map(|bar| {
+                        f(bar).map_or(HDF5_MISSING_TIMESTAMP, |ts| {
+                            self.timestamp_precision.to_epoch(ts)
+                        })
+                    })
+                    .collect(),
+            )
+        };
+
+        let mut columns = vec![
+            (
+                "timestamp",
+                self.timestamps(bars.iter().map(|b| b.timestamp)),
+            ),
+            ("open", double(|b| b.open)),
+            ("high", double(|b| b.high)),
+            ("low", double(|b| b.low)),
+            ("close", double(|b| b.close)),
+            ("volume", double(|b| b.volume)),
+            (
+                "tick_count",
+                Column::Uint(bars.iter().map(|b| b.tick_count).collect()),
+            ),
+        ];
+        if optional.side_volumes {
+            columns.extend([
+                ("ask_volume", nullable(|b| b.ask_volume)),
+                ("bid_volume", nullable(|b| b.bid_volume)),
+                ("imbalance", nullable(|b| b.imbalance)),
+            ]);
+        }
+        if optional.tick_times {
+            columns.extend([
+                ("first_tick_ts", time(|b| b.first_tick_ts)),
+                ("last_tick_ts", time(|b| b.last_tick_ts)),
+                (
+                    "is_partial",
+                    Column::Bool(bars.iter().map(|b| b.is_partial).collect()),
+                ),
+            ]);
+        }
+        if optional.price_stats {
+            columns.extend([
+                ("vwap", nullable(|b| b.vwap)),
+                ("avg_spread", nullable(|b| b.avg_spread)),
+                ("max_spread", nullable(|b| b.max_spread)),
+            ]);
+        }
+        if optional.microstructure {
+            columns.extend([
+                ("tick_rate", nullable(|b| b.tick_rate)),
+                ("bid_changes", count(|b| b.bid_changes)),
+                ("ask_changes", count(|b| b.ask_changes)),
+                ("twa_spread", nullable(|b| b.twa_spread)),
+            ]);
+        }
+        let mut columns: Vec<_> = columns
+            .into_iter()
+            .map(|(name, column)| (name.to_string(), column))
+            .collect();
+        columns.extend(indicators.iter().map(|c| {
+            let values = c.values.iter().map(|v| v.unwrap_or(f64::NAN)).collect();
+            (c.name.clone(), Column::Double(values))
+        }));
+        self.write(path, BARS_TABLE, columns)
+    }
+
+    /// Writes a return series to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Hdf5`] if the file cannot be opened or a
+    /// dataset cannot be written.
+    pub fn write_returns(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        let columns = vec![
+            (
+                "timestamp".to_string(),
+                self.timestamps(returns.iter().map(|r| r.timestamp)),
+            ),
+            (
+                "return".to_string(),
+                Column::Double(returns.iter().map(|r| r.value).collect()),
+            ),
+            (
+                "tick_count".to_string(),
+                Column::Uint(returns.iter().map(|r| r.tick_count).collect()),
+            ),
+        ];
+        self.write(path, RETURNS_TABLE, columns)
+    }
+
+    /// Returns a timestamp column at the configured precision.
+    fn timestamps(&self, timestamps: impl Iterator<Item = DateTime<Utc>>) -> Column {
+        Column::Timestamp(
+            timestamps
+                .map(|ts| self.timestamp_precision.to_epoch(ts))
+                .collect(),
+        )
+    }
+
+    /// Replaces the `records` group of the instrument with a dataset per
+    /// column.
+    fn write(
+        &self,
+        path: &Path,
+        records: &str,
+        columns: Vec<(String, Column)>,
+    ) -> Result<(), FormatError> {
+        let file = File::append(path).map_err(hdf5)?;
+        let instrument;
+        let parent: &Group = match &self.group {
+            Some(name) if file.link_exists(name) => {
+                instrument = file.group(name).map_err(hdf5)?;
+                &instrument
+            }
+            Some(name) => {
+                instrument = file.create_group(name).map_err(hdf5)?;
+                &instrument
+            }
+            None => &file,
+        };
+        if parent.link_exists(records) {
+            parent.unlink(records).map_err(hdf5)?;
+        }
+        let group = parent.create_group(records).map_err(hdf5)?;
+        if let Some(metadata) = &self.metadata {
+            for (key, value) in metadata.key_value_pairs() {
+                write_attr(&group, &key, &value)?;
+            }
+        }
+
+        let unit = unit(self.timestamp_precision);
+        for (name, column) in columns {
+            let builder = group.new_dataset_builder();
+            let name = name.as_str();
+            let dataset = match &column {
+                Column::Timestamp(values) => builder.with_data(values.as_slice()).create(name),
+                Column::Double(values) => builder.with_data(values.as_slice()).create(name),
+                Column::Float(values) => builder.with_data(values.as_slice()).create(name),
+                Column::Uint(values) => builder.with_data(values.as_slice()).create(name),
+                Column::Bool(values) => builder.with_data(values.as_slice()).create(name),
+            }
+            .map_err(hdf5)?;
+            if matches!(column, Column::Timestamp(_)) {
+                write_attr(&dataset, "unit", unit)?;
+            }
+        }
+        file.flush().map_err(hdf5)
+    }
+}
+
+impl FileFormatter for Hdf5Formatter {
+    fn write_ticks_to(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        self.write_ticks(ticks, path)
+    }
+
+    fn write_ohlcv_to(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, indicators, path)
+    }
+
+    fn write_returns_to(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        self.write_returns(returns, path)
+    }
+}
+
+/// Returns the unit name of timestamps at `precision`.
+const fn unit(precision: TimestampPrecision) -> &'static str {
+    match precision {
+        TimestampPrecision::Millisecond => "ms",
+        TimestampPrecision::Microsecond => "us",
+        TimestampPrecision::Nanosecond => "ns",
+    }
+}
+
+/// Writes a scalar string attribute.
+fn write_attr(location: &Location, name: &str, value: &str) -> Result<(), FormatError> {
+    let value: VarLenUnicode = value.parse().map_err(hdf5)?;
+    location
+        .new_attr::<VarLenUnicode>()
+        .shape(())
+        .create(name)
+        .and_then(|attr| attr.write_scalar(&value))
+        .map_err(hdf5)
+}
+
+/// Converts an HDF5 error.
+fn hdf5(e: impl std::fmt::Display) -> FormatError {
+    FormatError::Hdf5(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_hdf5_instrument_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fx.h5");
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let ticks = [Tick::new(timestamp, 1.5, 1.25, 100.0, 200.0)];
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0);
+
+        let eurusd = Hdf5Formatter::new().with_group("eurusd");
+        eurusd.write_ticks(&ticks, &path).unwrap();
+        eurusd.write_ticks(&ticks, &path).unwrap();
+        eurusd.write_ohlcv(&[bar], &path).unwrap();
+        Hdf5Formatter::new()
+            .with_group("gbpusd")
+            .with_metadata(OutputMetadata::new().with_price_source("mid"))
+            .write_ticks(&ticks, &path)
+            .unwrap();
+
+        let file = File::open(&path).unwrap();
+        assert_eq!(file.member_names().unwrap(), ["eurusd", "gbpusd"]);
+        let timestamps = file.dataset("eurusd/ticks/timestamp").unwrap();
+        assert_eq!(
+            timestamps.read_raw::<i64>().unwrap(),
+            [timestamp.timestamp_millis()]
+        );
+        let unit = timestamps.attr("unit").unwrap();
+        assert_eq!(unit.read_scalar::<VarLenUnicode>().unwrap().as_str(), "ms");
+        let volumes = file.dataset("eurusd/bars/bid_volume").unwrap();
+        assert_eq!(volumes.read_raw::<f64>().unwrap(), [3.0]);
+        assert!(!file.link_exists("eurusd/bars/vwap"));
+
+        let attr = format!("{}price_source", crate::METADATA_KEY_PREFIX);
+        let source = file.group("gbpusd/ticks").unwrap().attr(&attr).unwrap();
+        assert_eq!(
+            source.read_scalar::<VarLenUnicode>().unwrap().as_str(),
+            "mid"
+        );
+    }
+}
@@ -1,10 +1,23 @@
 //! JSON output format.
 
-use paracas_aggregate::Ohlcv;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, OhlcvBidAsk, PriceReturn, VolumeProfile};
 use paracas_types::Tick;
+use serde::ser::{Error as _, SerializeMap};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
 use std::io::Write;
+use std::marker::PhantomData;
 
-use crate::{FormatError, Formatter};
+use crate::formatter::{
+    check_indicators, check_profiles, round_bar, round_bid_ask_bar, round_tick,
+};
+use crate::{
+    BatchWriter, CompressedWriter, FormatError, Formatter, OutputCompression, OutputMetadata,
+    StreamingFormatter, TimestampStyle,
+};
 
 /// JSON output style.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -16,6 +29,39 @@ pub enum JsonStyle {
     Ndjson,
 }
 
+/// Naming of JSON fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum JsonFieldNaming {
+    /// `ask_volume`, as the record types name their fields.
+    #[default]
+    SnakeCase,
+    /// `askVolume`, as JavaScript consumers expect. Indicator names are
+    /// converted too (`sma_20` becomes `sma20`).
+    CamelCase,
+}
+
+impl JsonFieldNaming {
+    /// Returns the name of the field named `name` in snake case.
+    fn apply<'a>(self, name: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::SnakeCase => Cow::Borrowed(name),
+            Self::CamelCase if !name.contains('_') => Cow::Borrowed(name),
+            Self::CamelCase => {
+                let mut parts = name.split('_');
+                let mut camel = parts.next().unwrap_or_default().to_string();
+                for part in parts {
+                    let mut chars = part.chars();
+                    if let Some(first) = chars.next() {
+                        camel.extend(first.to_uppercase());
+                        camel.push_str(chars.as_str());
+                    }
+                }
+                Cow::Owned(camel)
+            }
+        }
+    }
+}
+
 /// JSON formatter.
 #[derive(Debug, Clone, Default)]
 pub struct JsonFormatter {
@@ -23,8 +69,43 @@ pub struct JsonFormatter {
     style: JsonStyle,
     /// Whether to pretty-print (only for array style).
     pretty: bool,
+    /// Compression of the whole output.
+    compression: OutputCompression,
+    /// Instrument written as the `symbol` field of every record, if any.
+    symbol: Option<String>,
+    /// First record of the NDJSON output being appended to, if any.
+    append: Option<String>,
+    /// Decimal places prices are rounded to, if any.
+    price_digits: Option<u32>,
+    /// Naming of fields.
+    field_naming: JsonFieldNaming,
+    /// How timestamps are written.
+    timestamp_style: TimestampStyle,
+    /// Metadata written as the leading NDJSON record, if any.
+    metadata: Option<OutputMetadata>,
 }
 
+/// Bar fields omitted from records when empty, which may be missing from
+/// either side when checking appended records.
+const OPTIONAL_FIELDS: &[&str] = &[
+    "ask_volume",
+    "bid_volume",
+    "imbalance",
+    "first_tick_ts",
+    "last_tick_ts",
+    "vwap",
+    "avg_spread",
+    "max_spread",
+    "tick_rate",
+    "bid_changes",
+    "ask_changes",
+    "twa_spread",
+    "is_partial",
+];
+
+/// Fields holding timestamps, written in the configured style.
+const TIMESTAMP_FIELDS: &[&str] = &["timestamp", "first_tick_ts", "last_tick_ts"];
+
 impl JsonFormatter {
     /// Creates a new JSON formatter with default settings (array style).
     #[must_use]
@@ -32,6 +113,13 @@ impl JsonFormatter {
         Self {
             style: JsonStyle::Array,
             pretty: false,
+            compression: OutputCompression::None,
+            symbol: None,
+            append: None,
+            price_digits: None,
+            field_naming: JsonFieldNaming::SnakeCase,
+            timestamp_style: TimestampStyle::Iso,
+            metadata: None,
         }
     }
 
@@ -41,6 +129,13 @@ impl JsonFormatter {
         Self {
             style: JsonStyle::Ndjson,
             pretty: false,
+            compression: OutputCompression::None,
+            symbol: None,
+            append: None,
+            price_digits: None,
+            field_naming: JsonFieldNaming::SnakeCase,
+            timestamp_style: TimestampStyle::Iso,
+            metadata: None,
         }
     }
 
@@ -57,62 +152,505 @@ impl JsonFormatter {
         self.style = style;
         self
     }
-}
 
-impl Formatter for JsonFormatter {
-    fn write_ticks<W: Write + Send>(
+    /// Compresses the output, e.g. into a `.ndjson.zst` file.
+    #[must_use]
+    pub const fn with_compression(mut self, compression: OutputCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Adds a leading `symbol` field holding `symbol` to every record, so
+    /// files of several instruments can be concatenated or loaded into one
+    /// table.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Appends to NDJSON output whose first record is `first_record` (see
+    /// [`read_first_line`](crate::read_first_line)), e.g. for incremental
+    /// daily updates of a growing file.
+    ///
+    /// Writing fails with [`FormatError::SchemaMismatch`] if the records'
+    /// fields differ from those of `first_record`, apart from optional bar
+    /// fields omitted when empty, and with
+    /// [`FormatError::AppendUnsupported`] for the array style. The writer
+    /// must be opened in append mode.
+    #[must_use]
+    pub fn with_append(mut self, first_record: impl Into<String>) -> Self {
+        self.append = Some(first_record.into());
+        self
+    }
+
+    /// Rounds prices and spreads to `digits` decimal places, e.g.
+    /// [`Instrument::price_digits`](paracas_types::Instrument::price_digits),
+    /// so they are written as quoted (`1.1001`) rather than with the
+    /// floating-point error of arithmetic on them (`1.10009999999999`).
+    #[must_use]
+    pub const fn with_price_digits(mut self, digits: u32) -> Self {
+        self.price_digits = Some(digits);
+        self
+    }
+
+    /// Sets the naming of fields (default: [`JsonFieldNaming::SnakeCase`]).
+    #[must_use]
+    pub const fn with_field_naming(mut self, naming: JsonFieldNaming) -> Self {
+        self.field_naming = naming;
+        self
+    }
+
+    /// Sets how timestamps are written (default: ISO 8601 strings). Epoch
+    /// styles write numbers, e.g. `1705321845000` for
+    /// [`TimestampStyle::EpochMillis`].
+    ///
+    /// An invalid `strftime` pattern fails the write with
+    /// [`FormatError::InvalidTimestampFormat`].
+    #[must_use]
+    pub fn with_timestamp_style(mut self, style: TimestampStyle) -> Self {
+        self.timestamp_style = style;
+        self
+    }
+
+    /// Writes `metadata` as a leading NDJSON record (see
+    /// [`OutputMetadata::header_record`]), so the file records its own
+    /// provenance. Nothing is written when appending.
+    ///
+    /// A JSON array has no room for it: writing fails with
+    /// [`FormatError::UnsupportedRecords`] for the array style.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Returns `ticks` with prices rounded to the configured digits.
+    fn ticks<'a>(&self, ticks: &'a [Tick]) -> Cow<'a, [Tick]> {
+        self.price_digits.map_or(Cow::Borrowed(ticks), |digits| {
+            ticks.iter().map(|tick| round_tick(tick, digits)).collect()
+        })
+    }
+
+    /// Returns `bars` with prices rounded to the configured digits.
+    fn bars<'a>(&self, bars: &'a [Ohlcv]) -> Cow<'a, [Ohlcv]> {
+        self.price_digits.map_or(Cow::Borrowed(bars), |digits| {
+            bars.iter().map(|bar| round_bar(bar, digits)).collect()
+        })
+    }
+
+    /// Returns bid/ask `bars` with prices rounded to the configured digits.
+    fn bid_ask_bars<'a>(&self, bars: &'a [OhlcvBidAsk]) -> Cow<'a, [OhlcvBidAsk]> {
+        self.price_digits.map_or(Cow::Borrowed(bars), |digits| {
+            bars.iter()
+                .map(|bar| round_bid_ask_bar(bar, digits))
+                .collect()
+        })
+    }
+
+    /// Checks that the output can be appended to, if appending, that it
+    /// can hold the metadata record, if any, and the timestamp style.
+    fn check_appendable(&self) -> Result<(), FormatError> {
+        if self.append.is_some() && self.style == JsonStyle::Array {
+            return Err(FormatError::AppendUnsupported("JSON array".to_string()));
+        }
+        if self.metadata.is_some() && self.style == JsonStyle::Array {
+            return Err(FormatError::UnsupportedRecords {
+                format: "JSON array".to_string(),
+                records: "a metadata record",
+            });
+        }
+        if let TimestampStyle::Strftime(pattern) = &self.timestamp_style
+            && StrftimeItems::new(pattern).any(|item| item == Item::Error)
+        {
+            return Err(FormatError::InvalidTimestampFormat(pattern.clone()));
+        }
+        Ok(())
+    }
+
+    /// Writes the metadata record, if any, unless appending.
+    fn write_metadata<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        if let Some(metadata) = self.metadata.as_ref().filter(|_| self.append.is_none()) {
+            writeln!(writer, "{}", metadata.header_record()?)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `record` has the fields of the output being appended to.
+    fn check_append<T: Serialize>(&self, record: &T) -> Result<(), FormatError> {
+        let Some(existing) = &self.append else {
+            return Ok(());
+        };
+        let existing: Map<String, Value> = serde_json::from_str(existing)?;
+        let Value::Object(appended) = serde_json::to_value(record)? else {
+            return Ok(());
+        };
+        let missing = |a: &Map<String, Value>, b: &Map<String, Value>| {
+            a.keys().any(|key| {
+                !b.contains_key(key)
+                    && !OPTIONAL_FIELDS
+                        .iter()
+                        .any(|field| self.field_naming.apply(field) == key.as_str())
+            })
+        };
+        if missing(&existing, &appended) || missing(&appended, &existing) {
+            let fields = |map: &Map<String, Value>| {
+                map.keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            return Err(FormatError::SchemaMismatch {
+                existing: fields(&existing),
+                appended: fields(&appended),
+            });
+        }
+        Ok(())
+    }
+
+    /// Wraps `record` to carry the configured symbol, field naming and
+    /// timestamp style.
+    fn record<'a, T>(&'a self, record: &'a T) -> Record<'a, T> {
+        Record {
+            fields: WithSymbol {
+                symbol: self.symbol.as_deref(),
+                record,
+            },
+            field_naming: self.field_naming,
+            timestamp_style: &self.timestamp_style,
+        }
+    }
+
+    /// Writes records in the configured style.
+    fn write_records<T: Serialize, W: Write>(
         &self,
-        ticks: &[Tick],
-        mut writer: W,
+        records: &[T],
+        writer: W,
     ) -> Result<(), FormatError> {
+        self.check_appendable()?;
+        let records: Vec<_> = records.iter().map(|record| self.record(record)).collect();
+        if let Some(first) = records.first() {
+            self.check_append(first)?;
+        }
+        let mut writer = self.compression.wrap(writer)?;
+        self.write_metadata(&mut writer)?;
         match self.style {
             JsonStyle::Array => {
                 if self.pretty {
-                    serde_json::to_writer_pretty(&mut writer, ticks)?;
+                    serde_json::to_writer_pretty(&mut writer, &records)?;
                 } else {
-                    serde_json::to_writer(&mut writer, ticks)?;
+                    serde_json::to_writer(&mut writer, &records)?;
                 }
                 writeln!(writer)?;
             }
             JsonStyle::Ndjson => {
-                for tick in ticks {
-                    serde_json::to_writer(&mut writer, tick)?;
+                for record in &records {
+                    serde_json::to_writer(&mut writer, record)?;
                     writeln!(writer)?;
                 }
             }
         }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// A record serialized in the configured field naming and timestamp style.
+struct Record<'a, T> {
+    fields: WithSymbol<'a, T>,
+    field_naming: JsonFieldNaming,
+    timestamp_style: &'a TimestampStyle,
+}
+
+impl<T: Serialize> Serialize for Record<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.field_naming == JsonFieldNaming::SnakeCase
+            && *self.timestamp_style == TimestampStyle::Iso
+        {
+            return self.fields.serialize(serializer);
+        }
+        let mut value = serde_json::to_value(&self.fields).map_err(S::Error::custom)?;
+        self.restyle(&mut value).map_err(S::Error::custom)?;
+        value.serialize(serializer)
+    }
+}
+
+impl<T> Record<'_, T> {
+    /// Renames the fields of `value` and rewrites its timestamps, in place.
+    fn restyle(&self, value: &mut Value) -> Result<(), String> {
+        match value {
+            Value::Object(map) => {
+                let fields = std::mem::take(map);
+                for (name, mut field) in fields {
+                    if TIMESTAMP_FIELDS.contains(&name.as_str()) {
+                        self.restyle_timestamp(&mut field)?;
+                    } else {
+                        self.restyle(&mut field)?;
+                    }
+                    map.insert(self.field_naming.apply(&name).into_owned(), field);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.restyle(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Rewrites an ISO 8601 timestamp in the configured style.
+    fn restyle_timestamp(&self, value: &mut Value) -> Result<(), String> {
+        let Value::String(iso) = value else {
+            return Ok(());
+        };
+        let timestamp = DateTime::parse_from_rfc3339(iso)
+            .map_err(|e| e.to_string())?
+            .with_timezone(&Utc);
+        *value = match self.timestamp_style {
+            TimestampStyle::Iso => return Ok(()),
+            TimestampStyle::EpochMillis => timestamp.timestamp_millis().into(),
+            TimestampStyle::EpochMicros => timestamp.timestamp_micros().into(),
+            TimestampStyle::Strftime(pattern) => timestamp.format(pattern).to_string().into(),
+        };
         Ok(())
     }
+}
+
+/// A record serialized with the instrument as an extra leading field.
+#[derive(Serialize)]
+struct WithSymbol<'a, T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<&'a str>,
+    #[serde(flatten)]
+    record: &'a T,
+}
+
+/// A bar serialized with its indicator values as extra fields.
+#[derive(Serialize)]
+struct BarWithIndicators<'a> {
+    #[serde(flatten)]
+    bar: &'a Ohlcv,
+    #[serde(flatten)]
+    indicators: IndicatorValues<'a>,
+}
+
+/// A bar serialized with its volume profile as an extra field.
+#[derive(Serialize)]
+struct BarWithProfile<'a> {
+    #[serde(flatten)]
+    bar: &'a Ohlcv,
+    volume_profile: &'a VolumeProfile,
+}
+
+/// The indicator values of one row, serialized as a map.
+struct IndicatorValues<'a> {
+    columns: &'a [IndicatorColumn],
+    row: usize,
+}
+
+impl Serialize for IndicatorValues<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for column in self.columns {
+            map.serialize_entry(&column.name, &column.values[self.row])?;
+        }
+        map.end()
+    }
+}
 
-    fn write_ohlcv<W: Write + Send>(
+impl Formatter for JsonFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        self.write_records(&self.ticks(ticks), writer)
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
         &self,
         bars: &[Ohlcv],
-        mut writer: W,
+        indicators: &[IndicatorColumn],
+        writer: W,
     ) -> Result<(), FormatError> {
-        match self.style {
-            JsonStyle::Array => {
-                if self.pretty {
-                    serde_json::to_writer_pretty(&mut writer, bars)?;
-                } else {
-                    serde_json::to_writer(&mut writer, bars)?;
-                }
-                writeln!(writer)?;
+        check_indicators(bars, indicators)?;
+        let bars = self.bars(bars);
+        if indicators.is_empty() {
+            return self.write_records(&bars, writer);
+        }
+        let records: Vec<_> = bars
+            .iter()
+            .enumerate()
+            .map(|(row, bar)| BarWithIndicators {
+                bar,
+                indicators: IndicatorValues {
+                    columns: indicators,
+                    row,
+                },
+            })
+            .collect();
+        self.write_records(&records, writer)
+    }
+
+    fn write_ohlcv_with_profiles<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        profiles: &[VolumeProfile],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_profiles(bars, profiles)?;
+        let bars = self.bars(bars);
+        let records: Vec<_> = bars
+            .iter()
+            .zip(profiles)
+            .map(|(bar, volume_profile)| BarWithProfile {
+                bar,
+                volume_profile,
+            })
+            .collect();
+        self.write_records(&records, writer)
+    }
+
+    fn write_bid_ask_bars<W: Write + Send>(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        self.write_records(&self.bid_ask_bars(bars), writer)
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        self.write_records(returns, writer)
+    }
+
+    fn extension(&self) -> &str {
+        match (self.style, self.compression) {
+            (JsonStyle::Array, OutputCompression::None) => "json",
+            (JsonStyle::Array, OutputCompression::Gzip) => "json.gz",
+            (JsonStyle::Array, OutputCompression::Zstd) => "json.zst",
+            (JsonStyle::Ndjson, OutputCompression::None) => "ndjson",
+            (JsonStyle::Ndjson, OutputCompression::Gzip) => "ndjson.gz",
+            (JsonStyle::Ndjson, OutputCompression::Zstd) => "ndjson.zst",
+        }
+    }
+}
+
+impl StreamingFormatter for JsonFormatter {
+    type TickWriter<W: Write + Send> = JsonBatchWriter<W, Tick>;
+    type OhlcvWriter<W: Write + Send> = JsonBatchWriter<W, Ohlcv>;
+
+    fn tick_writer<W: Write + Send>(&self, writer: W) -> Result<Self::TickWriter<W>, FormatError> {
+        JsonBatchWriter::begin(self.clone(), writer)
+    }
+
+    fn ohlcv_writer<W: Write + Send>(
+        &self,
+        writer: W,
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        JsonBatchWriter::begin(self.clone(), writer)
+    }
+}
+
+/// Incremental JSON writer created by [`JsonFormatter`].
+///
+/// Produces the same array or NDJSON output as writing all records at once.
+#[derive(Debug)]
+pub struct JsonBatchWriter<W: Write, T> {
+    formatter: JsonFormatter,
+    writer: CompressedWriter<W>,
+    /// Number of records written so far.
+    written: usize,
+    records: PhantomData<fn(&T)>,
+}
+
+impl<W: Write, T> JsonBatchWriter<W, T> {
+    /// Opens the array, if any.
+    fn begin(formatter: JsonFormatter, writer: W) -> Result<Self, FormatError> {
+        formatter.check_appendable()?;
+        let mut writer = formatter.compression.wrap(writer)?;
+        formatter.write_metadata(&mut writer)?;
+        if formatter.style == JsonStyle::Array {
+            write!(writer, "[")?;
+        }
+        Ok(Self {
+            formatter,
+            writer,
+            written: 0,
+            records: PhantomData,
+        })
+    }
+}
+
+impl<W: Write, T: Serialize> JsonBatchWriter<W, T> {
+    /// Writes records after those written so far.
+    fn write_records(&mut self, records: &[T]) -> Result<(), FormatError> {
+        for record in records {
+            let record = self.formatter.record(record);
+            if self.written == 0 {
+                self.formatter.check_append(&record)?;
             }
-            JsonStyle::Ndjson => {
-                for bar in bars {
-                    serde_json::to_writer(&mut writer, bar)?;
-                    writeln!(writer)?;
+            match self.formatter.style {
+                JsonStyle::Array => {
+                    if self.written > 0 {
+                        write!(self.writer, ",")?;
+                    }
+                    if self.formatter.pretty {
+                        // Indent the record one level, as an element of the
+                        // pretty-printed array
+                        let json = serde_json::to_string_pretty(&record)?;
+                        write!(self.writer, "\n  {}", json.replace('\n', "\n  "))?;
+                    } else {
+                        serde_json::to_writer(&mut self.writer, &record)?;
+                    }
+                }
+                JsonStyle::Ndjson => {
+                    serde_json::to_writer(&mut self.writer, &record)?;
+                    writeln!(self.writer)?;
                 }
             }
+            self.written += 1;
         }
         Ok(())
     }
 
-    fn extension(&self) -> &str {
-        match self.style {
-            JsonStyle::Array => "json",
-            JsonStyle::Ndjson => "ndjson",
+    /// Closes the array, if any, and finishes the output.
+    fn close(mut self) -> Result<(), FormatError> {
+        if self.formatter.style == JsonStyle::Array {
+            if self.formatter.pretty && self.written > 0 {
+                writeln!(self.writer)?;
+            }
+            writeln!(self.writer, "]")?;
         }
+        self.writer.finish()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> BatchWriter<Tick> for JsonBatchWriter<W, Tick> {
+    fn write_batch(&mut self, ticks: &[Tick]) -> Result<(), FormatError> {
+        let ticks = self.formatter.ticks(ticks);
+        self.write_records(&ticks)
+    }
+
+    fn finish(self) -> Result<(), FormatError> {
+        self.close()
+    }
+}
+
+impl<W: Write> BatchWriter<Ohlcv> for JsonBatchWriter<W, Ohlcv> {
+    fn write_batch(&mut self, bars: &[Ohlcv]) -> Result<(), FormatError> {
+        let bars = self.formatter.bars(bars);
+        self.write_records(&bars)
+    }
+
+    fn finish(self) -> Result<(), FormatError> {
+        self.close()
     }
 }
 
@@ -127,6 +665,99 @@ mod tests {
         Tick::new(timestamp, 1.1001, 1.1000, 100.0, 200.0)
     }
 
+    #[test]
+    fn test_json_price_digits() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(
+            timestamp,
+            1.1,
+            1.100_099_999_999_99,
+            1.0,
+            1.15,
+            4.0,
+            2,
+        )];
+
+        let mut output = Vec::new();
+        JsonFormatter::ndjson()
+            .with_price_digits(5)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        let record: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(record["high"], 1.1001);
+
+        let mut output = Vec::new();
+        let mut writer = JsonFormatter::ndjson()
+            .with_price_digits(5)
+            .ohlcv_writer(&mut output)
+            .unwrap();
+        writer.write_batch(&bars).unwrap();
+        writer.finish().unwrap();
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("\"high\":1.1001,")
+        );
+    }
+
+    #[test]
+    fn test_json_camel_case_epoch_millis() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)
+            .with_side_volumes(1.0, 3.0)
+            .with_tick_times(timestamp, timestamp);
+        let formatter = JsonFormatter::ndjson()
+            .with_symbol("eurusd")
+            .with_field_naming(JsonFieldNaming::CamelCase)
+            .with_timestamp_style(TimestampStyle::EpochMillis);
+
+        let mut output = Vec::new();
+        formatter.write_ohlcv(&[bar], &mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        assert!(
+            result.starts_with("{\"symbol\":\"eurusd\",\"timestamp\":1705320000000,\"open\":1.1,")
+        );
+        assert!(result.contains("\"tickCount\":2,\"askVolume\":1.0,"));
+        assert!(result.contains("\"firstTickTs\":1705320000000,"));
+
+        // Appending checks fields by their written names
+        let bare = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+        let mut output = Vec::new();
+        formatter
+            .with_append(result.trim_end())
+            .write_ohlcv(&[bare], &mut output)
+            .unwrap();
+        assert!(!output.is_empty());
+
+        let err = JsonFormatter::new()
+            .with_timestamp_style(TimestampStyle::Strftime("%Q".to_string()))
+            .write_ohlcv(&[bar], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::InvalidTimestampFormat(_)));
+    }
+
+    #[test]
+    fn test_ndjson_metadata_record() {
+        let metadata = OutputMetadata::new().without_generated_at();
+        let formatter = JsonFormatter::ndjson().with_metadata(metadata.clone());
+
+        let mut output = Vec::new();
+        let mut writer = formatter.tick_writer(&mut output).unwrap();
+        writer.write_batch(&[create_test_tick()]).unwrap();
+        writer.finish().unwrap();
+        let result = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = result.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(OutputMetadata::from_header_line(lines[0]), Some(metadata));
+        assert!(lines[1].starts_with("{\"timestamp\":"));
+
+        let err = formatter
+            .with_style(JsonStyle::Array)
+            .write_ticks(&[create_test_tick()], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::UnsupportedRecords { .. }));
+    }
+
     #[test]
     fn test_json_array() {
         let formatter = JsonFormatter::new();
@@ -154,6 +785,150 @@ mod tests {
         assert!(lines[0].starts_with('{'));
     }
 
+    #[test]
+    fn test_ndjson_symbol() {
+        let mut output = Cursor::new(Vec::new());
+
+        JsonFormatter::ndjson()
+            .with_symbol("eurusd")
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        assert!(result.starts_with("{\"symbol\":\"eurusd\",\"timestamp\":"));
+    }
+
+    #[test]
+    fn test_ndjson_append() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+        let existing = serde_json::to_string(&bar.with_partial(true)).unwrap();
+        let formatter = JsonFormatter::ndjson().with_append(existing);
+
+        let mut output = Cursor::new(Vec::new());
+        formatter.write_ohlcv(&[bar], &mut output).unwrap();
+        assert_eq!(
+            output.into_inner().iter().filter(|&&b| b == b'\n').count(),
+            1
+        );
+
+        let err = formatter
+            .write_ticks(&[create_test_tick()], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::SchemaMismatch { .. }));
+
+        let err = formatter
+            .with_style(JsonStyle::Array)
+            .write_ohlcv(&[bar], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::AppendUnsupported(_)));
+
+        // The optional fields are exactly those a bare bar omits
+        let full = bar
+            .with_side_volumes(1.0, 3.0)
+            .with_tick_times(timestamp, timestamp)
+            .with_price_stats(1.1, 0.1, 0.2)
+            .with_microstructure(0.5, 3, 1, 0.25)
+            .with_partial(true);
+        let Value::Object(full) = serde_json::to_value(full).unwrap() else {
+            unreachable!()
+        };
+        let Value::Object(bare) = serde_json::to_value(bar).unwrap() else {
+            unreachable!()
+        };
+        let optional: Vec<_> = full.keys().filter(|key| !bare.contains_key(*key)).collect();
+        assert_eq!(optional.len(), OPTIONAL_FIELDS.len());
+        assert!(
+            optional
+                .iter()
+                .all(|key| OPTIONAL_FIELDS.contains(&key.as_str()))
+        );
+    }
+
+    #[test]
+    fn test_ndjson_indicators() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2); 2];
+        let ema = IndicatorColumn {
+            name: "ema_2".to_string(),
+            values: vec![None, Some(1.15)],
+        };
+        let mut output = Cursor::new(Vec::new());
+
+        JsonFormatter::ndjson()
+            .write_ohlcv_with_indicators(&bars, &[ema], &mut output)
+            .unwrap();
+
+        let result = String::from_utf8(output.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = result
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["ema_2"], serde_json::Value::Null);
+        assert_eq!(lines[1]["ema_2"], 1.15);
+        assert_eq!(lines[1]["close"], 1.15);
+    }
+
+    #[test]
+    fn test_ndjson_bid_ask_bars() {
+        let bars = [OhlcvBidAsk {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap(),
+            bid_open: 1.100_004,
+            bid_high: 1.2,
+            bid_low: 1.0,
+            bid_close: 1.15,
+            ask_open: 1.1001,
+            ask_high: 1.2001,
+            ask_low: 1.0001,
+            ask_close: 1.1501,
+            bid_volume: 3.0,
+            ask_volume: 1.0,
+            tick_count: 2,
+            is_partial: false,
+        }];
+        let mut output = Vec::new();
+
+        JsonFormatter::ndjson()
+            .with_price_digits(5)
+            .write_bid_ask_bars(&bars, &mut output)
+            .unwrap();
+
+        let record: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(record["bid_open"], 1.1);
+        assert_eq!(record["ask_close"], 1.1501);
+        assert_eq!(record["tick_count"], 2);
+        assert!(record.get("is_partial").is_none());
+    }
+
+    #[test]
+    fn test_json_volume_profiles() {
+        use paracas_aggregate::PriceLevel;
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+        let profiles = vec![VolumeProfile {
+            levels: vec![PriceLevel {
+                price: 1.1,
+                volume: 4.0,
+                tick_count: 2,
+            }],
+        }];
+        let mut output = Cursor::new(Vec::new());
+
+        JsonFormatter::new()
+            .write_ohlcv_with_profiles(&bars, &profiles, &mut output)
+            .unwrap();
+
+        let result: serde_json::Value = serde_json::from_slice(&output.into_inner()).unwrap();
+        assert_eq!(result[0]["close"], 1.15);
+        assert_eq!(result[0]["volume_profile"][0]["tick_count"], 2);
+
+        let err = crate::CsvFormatter::new()
+            .write_ohlcv_with_profiles(&bars, &profiles, Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::NestedColumns(_)));
+    }
+
     #[test]
     fn test_pretty_json() {
         let formatter = JsonFormatter::new().with_pretty(true);
@@ -166,4 +941,32 @@ mod tests {
         assert!(result.contains('\n'));
         assert!(result.contains("  ")); // Indentation
     }
+
+    #[test]
+    fn test_json_streaming_matches_batch() {
+        let ticks = [create_test_tick(); 3];
+        for formatter in [
+            JsonFormatter::new(),
+            JsonFormatter::new().with_pretty(true),
+            JsonFormatter::ndjson(),
+        ] {
+            for count in [0, 3] {
+                let mut expected = Vec::new();
+                formatter
+                    .write_ticks(&ticks[..count], &mut expected)
+                    .unwrap();
+
+                let mut output = Vec::new();
+                let mut writer = formatter.tick_writer(&mut output).unwrap();
+                for tick in &ticks[..count] {
+                    writer.write_batch(std::slice::from_ref(tick)).unwrap();
+                }
+                writer.finish().unwrap();
+                assert_eq!(
+                    String::from_utf8(output).unwrap(),
+                    String::from_utf8(expected).unwrap()
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,376 @@
+//! QuantConnect Lean data format.
+//!
+//! Lean reads headerless CSV files from zip archives laid out by security
+//! type, market, resolution and symbol, e.g.
+//! `forex/oanda/minute/eurusd/20240102_quote.zip`. Tick, second and minute
+//! data is stored one archive per day, with times in milliseconds since
+//! midnight of that day; hour and daily data is stored in one archive per
+//! symbol, with `yyyyMMdd HH:mm` times.
+
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use paracas_aggregate::{Ohlcv, OhlcvBidAsk, PriceReturn};
+use paracas_types::{Tick, Timeframe};
+use std::io::{self, Write};
+
+use crate::formatter::format_price;
+use crate::{FormatError, Formatter};
+
+/// Factor Lean scales equity prices by, storing them as integers.
+const PRICE_SCALE: f64 = 10_000.0;
+
+/// Resolution of Lean data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LeanResolution {
+    /// Tick data.
+    Tick,
+    /// 1-second bars.
+    Second,
+    /// 1-minute bars.
+    #[default]
+    Minute,
+    /// 1-hour bars.
+    Hour,
+    /// Daily bars.
+    Daily,
+}
+
+impl LeanResolution {
+    /// Returns the resolution of `timeframe`, or `None` if Lean has no such
+    /// resolution.
+    #[must_use]
+    pub const fn from_timeframe(timeframe: Timeframe) -> Option<Self> {
+        match timeframe {
+            Timeframe::Tick => Some(Self::Tick),
+            Timeframe::Second1 => Some(Self::Second),
+            Timeframe::Minute1 => Some(Self::Minute),
+            Timeframe::Hour1 => Some(Self::Hour),
+            Timeframe::Day1 => Some(Self::Daily),
+            _ => None,
+        }
+    }
+
+    /// Returns the name Lean uses in directory and file names.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Tick => "tick",
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Daily => "daily",
+        }
+    }
+
+    /// Returns whether data of this resolution is stored one file per day,
+    /// with times relative to midnight.
+    #[must_use]
+    pub const fn is_daily_file(&self) -> bool {
+        matches!(self, Self::Tick | Self::Second | Self::Minute)
+    }
+
+    /// Returns the name of the zip archive holding `symbol`'s data of
+    /// `date`, e.g. `20240102_quote.zip`, or `eurusd.zip` for hour and daily
+    /// data, which ignores `date`.
+    #[must_use]
+    pub fn zip_name(&self, symbol: &str, date: NaiveDate, tick_type: LeanTickType) -> String {
+        if self.is_daily_file() {
+            format!("{}_{}.zip", date.format("%Y%m%d"), tick_type.name())
+        } else {
+            format!("{}.zip", symbol.to_lowercase())
+        }
+    }
+
+    /// Returns the name of the CSV file inside the archive of
+    /// [`zip_name`](Self::zip_name), e.g. `20240102_eurusd_minute_quote.csv`,
+    /// or `eurusd.csv` for hour and daily data.
+    #[must_use]
+    pub fn entry_name(&self, symbol: &str, date: NaiveDate, tick_type: LeanTickType) -> String {
+        let symbol = symbol.to_lowercase();
+        if self.is_daily_file() {
+            format!(
+                "{}_{symbol}_{}_{}.csv",
+                date.format("%Y%m%d"),
+                self.name(),
+                tick_type.name()
+            )
+        } else {
+            format!("{symbol}.csv")
+        }
+    }
+}
+
+/// Kind of Lean data, which names its files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LeanTickType {
+    /// Trade ticks and bars.
+    Trade,
+    /// Quote ticks and bars.
+    Quote,
+}
+
+impl LeanTickType {
+    /// Returns the name Lean uses in file names.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Trade => "trade",
+            Self::Quote => "quote",
+        }
+    }
+}
+
+/// QuantConnect Lean formatter.
+///
+/// Ticks are written as quote ticks, `time,bid,ask`, and bars as trade bars,
+/// `time,open,high,low,close,volume`, with tick volumes. Quote bars of both
+/// sides, as Lean reads forex and CFD data, are written by
+/// [`write_bid_ask_bars`](Formatter::write_bid_ask_bars). Times are milliseconds since
+/// midnight UTC for tick, second and minute data, so each file should hold
+/// one day, and `yyyyMMdd HH:mm` for hour and daily data. Prices are written
+/// as decimals, or scaled by 10,000 to integers as Lean stores equities.
+#[derive(Debug, Clone, Default)]
+pub struct LeanFormatter {
+    /// Resolution of the data, which sets the time format.
+    resolution: LeanResolution,
+    /// Whether prices are scaled to integers.
+    scaled: bool,
+    /// Decimal places of unscaled prices, if fixed.
+    digits: Option<u32>,
+}
+
+impl LeanFormatter {
+    /// Creates a new Lean formatter of minute resolution.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            resolution: LeanResolution::Minute,
+            scaled: false,
+            digits: None,
+        }
+    }
+
+    /// Sets the resolution of the data (default: [`LeanResolution::Minute`]).
+    #[must_use]
+    pub const fn with_resolution(mut self, resolution: LeanResolution) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// Writes prices scaled by 10,000 to integers, as Lean stores equity
+    /// data, instead of as decimals.
+    #[must_use]
+    pub const fn with_scaled_prices(mut self) -> Self {
+        self.scaled = true;
+        self
+    }
+
+    /// Writes unscaled prices with `digits` decimal places.
+    #[must_use]
+    pub const fn with_digits(mut self, digits: u32) -> Self {
+        self.digits = Some(digits);
+        self
+    }
+
+    /// Returns the resolution of the data.
+    #[must_use]
+    pub const fn resolution(&self) -> LeanResolution {
+        self.resolution
+    }
+
+    /// Formats a price, scaled or with the configured digits.
+    fn price(&self, price: f64) -> String {
+        if self.scaled {
+            format!("{:.0}", price * PRICE_SCALE)
+        } else {
+            format_price(price, self.digits)
+        }
+    }
+
+    /// Formats a bar time for the configured resolution.
+    fn time(&self, timestamp: DateTime<Utc>) -> String {
+        if self.resolution.is_daily_file() {
+            millis_of_day(timestamp).to_string()
+        } else {
+            timestamp.format("%Y%m%d %H:%M").to_string()
+        }
+    }
+}
+
+/// Returns the milliseconds since midnight of `timestamp`.
+fn millis_of_day(timestamp: DateTime<Utc>) -> u32 {
+    timestamp.num_seconds_from_midnight() * 1000 + timestamp.timestamp_subsec_millis()
+}
+
+impl Formatter for LeanFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        for tick in ticks {
+            writeln!(
+                writer,
+                "{},{},{}",
+                millis_of_day(tick.timestamp),
+                self.price(tick.bid),
+                self.price(tick.ask),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        for bar in bars {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                self.time(bar.timestamp),
+                self.price(bar.open),
+                self.price(bar.high),
+                self.price(bar.low),
+                self.price(bar.close),
+                bar.tick_count,
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes bars as Lean quote bars,
+    /// `time,bid open,bid high,bid low,bid close,ask open,ask high,ask low,ask close`,
+    /// the layout of Lean's forex and CFD data.
+    fn write_bid_ask_bars<W: Write + Send>(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        for bar in bars {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                self.time(bar.timestamp),
+                self.price(bar.bid_open),
+                self.price(bar.bid_high),
+                self.price(bar.bid_low),
+                self.price(bar.bid_close),
+                self.price(bar.ask_open),
+                self.price(bar.ask_high),
+                self.price(bar.ask_low),
+                self.price(bar.ask_close),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        _returns: &[PriceReturn],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(FormatError::UnsupportedRecords {
+            format: "lean".to_string(),
+            records: "returns",
+        })
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone};
+    use paracas_aggregate::BidAskAggregator;
+
+    #[test]
+    fn test_lean_ticks() {
+        let timestamp =
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 1, 2).unwrap() + TimeDelta::microseconds(345_678);
+        let ticks = [Tick::new(timestamp, 1.10002, 1.1, 1.0, 2.0)];
+
+        let mut output = Vec::new();
+        LeanFormatter::new()
+            .with_resolution(LeanResolution::Tick)
+            .with_digits(5)
+            .write_ticks(&ticks, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "62345,1.10000,1.10002\n"
+        );
+    }
+
+    #[test]
+    fn test_lean_bars() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 13, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+
+        let mut output = Vec::new();
+        LeanFormatter::new()
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "46800000,1.1,1.2,1,1.15,2\n"
+        );
+
+        let mut output = Vec::new();
+        LeanFormatter::new()
+            .with_resolution(LeanResolution::Hour)
+            .with_scaled_prices()
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "20240102 13:00,11000,12000,10000,11500,2\n"
+        );
+    }
+
+    #[test]
+    fn test_lean_quote_bars() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 0, 1, 0).unwrap();
+        let mut aggregator = BidAskAggregator::new(Timeframe::Minute1);
+        assert!(
+            aggregator
+                .process(Tick::new(timestamp, 1.1002, 1.1, 1.0, 1.0))
+                .is_none()
+        );
+        let bars: Vec<_> = aggregator.finish().into_iter().collect();
+
+        let mut output = Vec::new();
+        LeanFormatter::new()
+            .write_bid_ask_bars(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "60000,1.1,1.1,1.1,1.1,1.1002,1.1002,1.1002,1.1002\n"
+        );
+    }
+
+    #[test]
+    fn test_lean_file_names() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let minute = LeanResolution::Minute;
+        assert_eq!(
+            minute.zip_name("EURUSD", date, LeanTickType::Quote),
+            "20240102_quote.zip"
+        );
+        assert_eq!(
+            minute.entry_name("EURUSD", date, LeanTickType::Quote),
+            "20240102_eurusd_minute_quote.csv"
+        );
+        let daily = LeanResolution::Daily;
+        assert_eq!(
+            daily.zip_name("EURUSD", date, LeanTickType::Trade),
+            "eurusd.zip"
+        );
+        assert_eq!(
+            daily.entry_name("EURUSD", date, LeanTickType::Trade),
+            "eurusd.csv"
+        );
+        assert_eq!(LeanResolution::from_timeframe(Timeframe::Hour4), None);
+    }
+}
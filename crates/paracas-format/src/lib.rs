@@ -5,7 +5,43 @@
 //!
 //! - [`CsvFormatter`] - CSV format
 //! - [`JsonFormatter`] - JSON array or NDJSON format
-//! - [`ParquetFormatter`] - Apache Parquet columnar format
+//! - [`ParquetFormatter`] - Apache Parquet columnar format, as single files
+//!   or Hive-partitioned datasets
+//! - [`SqliteFormatter`] - indexed tables of a SQLite database
+//! - [`DuckDbFormatter`] - tables of a DuckDB database
+//! - [`AvroFormatter`] - Apache Avro container files with embedded schema
+//! - [`MsgpackFormatter`] - compact stream of MessagePack records
+//! - [`Hdf5Formatter`] - typed HDF5 datasets, one group per instrument
+//! - [`XlsxFormatter`] - Excel workbooks with typed cells
+//! - [`ProtobufFormatter`] - length-delimited protobuf messages of the
+//!   published [`proto`] schema
+//! - [`OrcFormatter`] - Apache ORC files for Hive and Trino warehouses
+//! - [`MtCsvFormatter`] - MetaTrader 4 and 5 importable CSV and MetaTrader 4
+//!   `.hst` history files
+//! - [`NinjaTraderFormatter`] - NinjaTrader tick and bar import text
+//! - [`TradingViewFormatter`] - TradingView chart and Pine Seeds CSV
+//! - [`LeanFormatter`] - QuantConnect Lean tick and bar CSV, with the names
+//!   of Lean's per-day zip archives
+//!
+//! [`ParquetFormatter`] also converts records to in-memory Arrow record
+//! batches of the same schema, re-exported as [`arrow`].
+//!
+//! [`FormatterRegistry`] maps format names to boxed [`DynFormatter`]s, the
+//! object-safe form of [`Formatter`], for applications that add their own
+//! formats and choose one by name at runtime.
+//!
+//! Formatters implementing [`StreamingFormatter`] also write records batch
+//! by batch as they arrive, without holding the whole dataset in memory.
+//! [`SplitLimits`] uses them to split large outputs into numbered files of
+//! bounded rows or bytes.
+//!
+//! CSV and JSON output can be compressed with gzip or zstd through
+//! [`OutputCompression`].
+//!
+//! [`OutputMetadata`] describes how a file was produced and is embedded in
+//! Parquet key-value metadata or written as a JSON sidecar.
+//! [`InstrumentAliases`] replaces instrument IDs with opaque aliases in
+//! file names and metadata, for datasets shared outside a team.
 
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/factordynamics/paracas/issues/")]
@@ -13,16 +49,81 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod alias;
+mod compression;
 mod csv;
 mod formatter;
 mod json;
+mod lean;
+mod metadata;
+mod metatrader;
+mod ninjatrader;
+mod registry;
+mod split;
+mod streaming;
+mod tradingview;
 
+#[cfg(feature = "avro")]
+mod avro;
+#[cfg(feature = "parquet")]
+mod dataset;
+#[cfg(feature = "duckdb")]
+mod duckdb;
+#[cfg(feature = "hdf5")]
+mod hdf5;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "orc")]
+mod orc;
 #[cfg(feature = "parquet")]
 mod parquet;
+#[cfg(feature = "protobuf")]
+pub mod proto;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "xlsx")]
+mod xlsx;
 
-pub use crate::csv::CsvFormatter;
-pub use formatter::{FormatError, Formatter, OutputFormat};
-pub use json::{JsonFormatter, JsonStyle};
+pub use crate::csv::{CsvBatchWriter, CsvFormatter, TimestampStyle};
+pub use alias::InstrumentAliases;
+pub use compression::{CompressedWriter, OutputCompression, read_first_line};
+pub use formatter::{
+    BARS_TABLE, DerivedColumns, FormatError, Formatter, OutputFormat, RETURNS_TABLE, TICKS_TABLE,
+};
+pub use json::{JsonBatchWriter, JsonFieldNaming, JsonFormatter, JsonStyle};
+pub use lean::{LeanFormatter, LeanResolution, LeanTickType};
+pub use metadata::{
+    DATA_LICENSE, METADATA_COMMENT_PREFIX, METADATA_KEY_PREFIX, METADATA_RECORD_FIELD,
+    OutputMetadata, SIDECAR_SUFFIX,
+};
+pub use metatrader::{HST_VERSION, MtCsvFormatter, MtLayout, MtServerTime};
+pub use ninjatrader::NinjaTraderFormatter;
+pub use registry::{DynFormatter, FileFormatter, FormatterRegistry};
+pub use split::SplitLimits;
+pub use streaming::{BatchWriter, StreamingFormatter};
+pub use tradingview::{TradingViewFormatter, TradingViewStyle};
 
+#[cfg(feature = "duckdb")]
+pub use crate::duckdb::DuckDbFormatter;
+#[cfg(feature = "parquet")]
+pub use crate::parquet::{ParquetBatchWriter, ParquetFormatter};
+#[cfg(feature = "parquet")]
+pub use arrow;
+#[cfg(feature = "avro")]
+pub use avro::{AVRO_NAMESPACE, AvroFormatter};
 #[cfg(feature = "parquet")]
-pub use crate::parquet::ParquetFormatter;
+pub use dataset::PARTITION_FILE;
+#[cfg(feature = "hdf5")]
+pub use hdf5::{HDF5_MISSING_TIMESTAMP, Hdf5Formatter};
+#[cfg(feature = "msgpack")]
+pub use msgpack::MsgpackFormatter;
+#[cfg(feature = "orc")]
+pub use orc::{OrcCompression, OrcFormatter};
+#[cfg(feature = "protobuf")]
+pub use protobuf::ProtobufFormatter;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteFormatter;
+#[cfg(feature = "xlsx")]
+pub use xlsx::{XLSX_MAX_ROWS, XlsxFormatter};
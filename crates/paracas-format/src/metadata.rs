@@ -0,0 +1,360 @@
+//! Self-describing dataset metadata.
+//!
+//! Parquet output embeds this metadata in the file's key-value metadata;
+//! other formats carry it in a JSON sidecar next to the data file, and CSV
+//! and NDJSON optionally in a leading line of the file itself.
+
+use chrono::{DateTime, Utc};
+use paracas_types::{Instrument, Timeframe};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::FormatError;
+
+/// Prefix applied to every Parquet key-value metadata key.
+pub const METADATA_KEY_PREFIX: &str = "paracas.";
+
+/// Suffix appended to a data file name to form its sidecar path.
+pub const SIDECAR_SUFFIX: &str = ".meta.json";
+
+/// Prefix of the comment line carrying metadata above the header of CSV
+/// output.
+pub const METADATA_COMMENT_PREFIX: &str = "# paracas ";
+
+/// Field of the leading NDJSON record carrying metadata.
+pub const METADATA_RECORD_FIELD: &str = "paracas_metadata";
+
+/// Data source and terms notice embedded in outputs of Dukascopy data.
+pub const DATA_LICENSE: &str = "Source: Dukascopy Bank SA historical data feed \
+(https://www.dukascopy.com). Data is provided for personal, non-commercial use \
+under the Dukascopy terms of use; redistribution may be restricted.";
+
+/// Metadata describing how an output file was produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputMetadata {
+    /// Instrument identifier (e.g. `eurusd`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument: Option<String>,
+    /// Decimal factor used to convert raw integer prices.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimal_factor: Option<u32>,
+    /// Which price the data represents (e.g. `bid/ask` for ticks, `mid` for bars).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_source: Option<String>,
+    /// Timeframe of the data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeframe: Option<Timeframe>,
+    /// Data source and terms-of-use notice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Timezone of all timestamps in the file.
+    pub timezone: String,
+    /// Version of paracas that wrote the file.
+    pub paracas_version: String,
+    /// When the file was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<DateTime<Utc>>,
+    /// Additional generation parameters (date range, format, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl Default for OutputMetadata {
+    fn default() -> Self {
+        Self {
+            instrument: None,
+            decimal_factor: None,
+            price_source: None,
+            timeframe: None,
+            license: None,
+            timezone: "UTC".to_string(),
+            paracas_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: Some(Utc::now()),
+            parameters: BTreeMap::new(),
+        }
+    }
+}
+
+impl OutputMetadata {
+    /// Creates metadata stamped with the current time and paracas version.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates metadata describing data for the given instrument.
+    ///
+    /// The license defaults to [`DATA_LICENSE`].
+    #[must_use]
+    pub fn for_instrument(instrument: &Instrument) -> Self {
+        Self {
+            instrument: Some(instrument.id().to_string()),
+            decimal_factor: Some(instrument.decimal_factor()),
+            license: Some(DATA_LICENSE.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the instrument ID with an opaque alias.
+    ///
+    /// The decimal factor is dropped as well, since it hints at the
+    /// instrument's asset class.
+    #[must_use]
+    pub fn with_instrument_alias(mut self, alias: impl Into<String>) -> Self {
+        self.instrument = Some(alias.into());
+        self.decimal_factor = None;
+        self
+    }
+
+    /// Sets the price source.
+    #[must_use]
+    pub fn with_price_source(mut self, source: impl Into<String>) -> Self {
+        self.price_source = Some(source.into());
+        self
+    }
+
+    /// Sets the timeframe.
+    #[must_use]
+    pub const fn with_timeframe(mut self, timeframe: Timeframe) -> Self {
+        self.timeframe = Some(timeframe);
+        self
+    }
+
+    /// Sets the data source and terms notice.
+    #[must_use]
+    pub fn with_license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Removes the generation timestamp, so identical inputs produce
+    /// byte-identical metadata.
+    #[must_use]
+    pub const fn without_generated_at(mut self) -> Self {
+        self.generated_at = None;
+        self
+    }
+
+    /// Adds a generation parameter.
+    #[must_use]
+    pub fn with_parameter(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.parameters.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Returns the metadata as flat key-value pairs, prefixed with
+    /// [`METADATA_KEY_PREFIX`].
+    ///
+    /// Generation parameters are nested under `paracas.param.<key>`.
+    #[must_use]
+    pub fn key_value_pairs(&self) -> Vec<(String, String)> {
+        let key = |name: &str| format!("{METADATA_KEY_PREFIX}{name}");
+        let mut pairs = Vec::new();
+
+        if let Some(instrument) = &self.instrument {
+            pairs.push((key("instrument"), instrument.clone()));
+        }
+        if let Some(factor) = self.decimal_factor {
+            pairs.push((key("decimal_factor"), factor.to_string()));
+        }
+        if let Some(source) = &self.price_source {
+            pairs.push((key("price_source"), source.clone()));
+        }
+        if let Some(timeframe) = self.timeframe {
+            pairs.push((key("timeframe"), timeframe.to_string()));
+        }
+        if let Some(license) = &self.license {
+            pairs.push((key("license"), license.clone()));
+        }
+        pairs.push((key("timezone"), self.timezone.clone()));
+        pairs.push((key("version"), self.paracas_version.clone()));
+        if let Some(generated_at) = self.generated_at {
+            pairs.push((key("generated_at"), generated_at.to_rfc3339()));
+        }
+        for (name, value) in &self.parameters {
+            pairs.push((key(&format!("param.{name}")), value.clone()));
+        }
+
+        pairs
+    }
+
+    /// Returns the metadata as a CSV comment line, `# paracas {...}`, with
+    /// the metadata as compact JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn comment_line(&self) -> Result<String, FormatError> {
+        Ok(format!(
+            "{METADATA_COMMENT_PREFIX}{}",
+            serde_json::to_string(self)?
+        ))
+    }
+
+    /// Returns the metadata as an NDJSON record, `{"paracas_metadata":{...}}`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn header_record(&self) -> Result<String, FormatError> {
+        let mut record = serde_json::Map::new();
+        record.insert(
+            METADATA_RECORD_FIELD.to_string(),
+            serde_json::to_value(self)?,
+        );
+        Ok(serde_json::to_string(&record)?)
+    }
+
+    /// Parses the metadata of a line written by
+    /// [`comment_line`](Self::comment_line) or
+    /// [`header_record`](Self::header_record), returning `None` for any
+    /// other line.
+    #[must_use]
+    pub fn from_header_line(line: &str) -> Option<Self> {
+        if let Some(json) = line.strip_prefix(METADATA_COMMENT_PREFIX) {
+            return serde_json::from_str(json).ok();
+        }
+        let mut record: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(line).ok()?;
+        if record.len() != 1 {
+            return None;
+        }
+        serde_json::from_value(record.remove(METADATA_RECORD_FIELD)?).ok()
+    }
+
+    /// Returns whether `line` is a metadata line written by
+    /// [`comment_line`](Self::comment_line) or
+    /// [`header_record`](Self::header_record).
+    #[must_use]
+    pub fn is_header_line(line: &str) -> bool {
+        line.starts_with(METADATA_COMMENT_PREFIX)
+            || line.starts_with(&format!("{{\"{METADATA_RECORD_FIELD}\":"))
+    }
+
+    /// Returns the sidecar path for a data file (`<file>.meta.json`).
+    #[must_use]
+    pub fn sidecar_path(output: &Path) -> PathBuf {
+        let mut name = output.file_name().unwrap_or_default().to_os_string();
+        name.push(SIDECAR_SUFFIX);
+        output.with_file_name(name)
+    }
+
+    /// Writes the metadata as a pretty-printed JSON sidecar next to `output`.
+    ///
+    /// Returns the path of the sidecar file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or the file write fails.
+    pub fn write_sidecar(&self, output: &Path) -> Result<PathBuf, FormatError> {
+        let path = Self::sidecar_path(output);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paracas_types::Category;
+
+    fn sample() -> OutputMetadata {
+        let instrument = Instrument::new(
+            "eurusd",
+            "EUR/USD",
+            "Euro vs US Dollar",
+            Category::Forex,
+            100_000,
+            None,
+        );
+        OutputMetadata::for_instrument(&instrument)
+            .with_price_source("mid")
+            .with_timeframe(Timeframe::Hour1)
+            .with_parameter("start", "2024-01-01")
+    }
+
+    #[test]
+    fn test_key_value_pairs() {
+        let pairs = sample().key_value_pairs();
+        let get = |k: &str| {
+            pairs
+                .iter()
+                .find(|(key, _)| key == k)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("paracas.instrument"), Some("eurusd"));
+        assert_eq!(get("paracas.decimal_factor"), Some("100000"));
+        assert_eq!(get("paracas.timeframe"), Some("h1"));
+        assert_eq!(get("paracas.timezone"), Some("UTC"));
+        assert_eq!(get("paracas.license"), Some(DATA_LICENSE));
+        assert_eq!(get("paracas.param.start"), Some("2024-01-01"));
+    }
+
+    #[test]
+    fn test_instrument_alias() {
+        let metadata = sample().with_instrument_alias("inst_0123456789ab");
+        let pairs = metadata.key_value_pairs();
+        assert!(pairs.contains(&(
+            "paracas.instrument".to_string(),
+            "inst_0123456789ab".to_string()
+        )));
+        assert!(!pairs.iter().any(|(_, v)| v.contains("eurusd")));
+        assert!(metadata.decimal_factor.is_none());
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            OutputMetadata::sidecar_path(Path::new("out/eurusd.csv")),
+            PathBuf::from("out/eurusd.csv.meta.json")
+        );
+    }
+
+    #[test]
+    fn test_without_generated_at() {
+        let metadata = sample().without_generated_at();
+        assert!(metadata.generated_at.is_none());
+        assert!(
+            !metadata
+                .key_value_pairs()
+                .iter()
+                .any(|(key, _)| key == "paracas.generated_at")
+        );
+    }
+
+    #[test]
+    fn test_header_lines() {
+        let metadata = sample();
+
+        let comment = metadata.comment_line().unwrap();
+        assert!(comment.starts_with("# paracas {\"instrument\":\"eurusd\","));
+        assert!(!comment.contains('\n'));
+        assert!(OutputMetadata::is_header_line(&comment));
+        assert_eq!(
+            OutputMetadata::from_header_line(&comment),
+            Some(metadata.clone())
+        );
+
+        let record = metadata.header_record().unwrap();
+        assert!(OutputMetadata::is_header_line(&record));
+        assert_eq!(OutputMetadata::from_header_line(&record), Some(metadata));
+
+        for line in [
+            "timestamp,ask,bid",
+            "{\"timestamp\":\"2024-01-15T12:00:00Z\"}",
+        ] {
+            assert!(!OutputMetadata::is_header_line(line));
+            assert_eq!(OutputMetadata::from_header_line(line), None);
+        }
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let metadata = sample();
+        let json = serde_json::to_string(&metadata).unwrap();
+        let decoded: OutputMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, metadata);
+    }
+}
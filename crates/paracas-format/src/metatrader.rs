@@ -0,0 +1,437 @@
+//! MetaTrader output.
+//!
+//! Bars are written in the layouts the MetaTrader 4 History Center and the
+//! MetaTrader 5 custom symbol import expect, or as MetaTrader 4 `.hst`
+//! history files. MetaTrader shows times in the server time of the broker
+//! rather than UTC, so timestamps are converted to a [`MtServerTime`].
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use paracas_aggregate::{Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use std::io::{self, Write};
+
+use crate::formatter::format_price;
+use crate::{FormatError, Formatter};
+
+/// Version of the `.hst` history files written.
+pub const HST_VERSION: i32 = 401;
+
+/// Copyright notice MetaTrader 4 writes into `.hst` headers.
+const HST_COPYRIGHT: &str = "(C)opyright 2003, MetaQuotes Software Corp.";
+
+/// Layout of MetaTrader output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MtLayout {
+    /// MetaTrader 4 History Center CSV: no header, comma-separated
+    /// `date,time,open,high,low,close,volume` with times to the minute.
+    #[default]
+    Mt4,
+    /// MetaTrader 5 CSV: tab-separated with a `<DATE>`, `<TIME>`, ... header
+    /// and times to the second, as exported and imported by MetaTrader 5.
+    /// Ticks are written with milliseconds.
+    Mt5,
+    /// MetaTrader 4 binary `.hst` history file (version 401).
+    Hst,
+}
+
+/// Time zone of MetaTrader timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MtServerTime {
+    /// UTC.
+    #[default]
+    Utc,
+    /// Local time of a time zone of the tz database, e.g. `Europe/London`.
+    Zone(Tz),
+    /// New York time plus seven hours, so that days start at the 17:00 New
+    /// York close: UTC+2, or UTC+3 while the US observes daylight saving
+    /// time. Most forex brokers run their servers on this time, which gives
+    /// five daily bars a week.
+    NewYorkClose,
+}
+
+impl MtServerTime {
+    /// Returns the server time of `timestamp`.
+    #[must_use]
+    pub fn convert(self, timestamp: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Self::Utc => timestamp.naive_utc(),
+            Self::Zone(tz) => timestamp.with_timezone(&tz).naive_local(),
+            Self::NewYorkClose => {
+                timestamp
+                    .with_timezone(&chrono_tz::America::New_York)
+                    .naive_local()
+                    + TimeDelta::hours(7)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for MtServerTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Utc => write!(f, "UTC"),
+            Self::Zone(tz) => write!(f, "{}", tz.name()),
+            Self::NewYorkClose => write!(f, "ny-close"),
+        }
+    }
+}
+
+impl std::str::FromStr for MtServerTime {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utc" => Ok(Self::Utc),
+            "ny-close" | "new-york-close" => Ok(Self::NewYorkClose),
+            _ => s
+                .parse()
+                .map(Self::Zone)
+                .map_err(|_| FormatError::UnknownTimezone(s.to_string())),
+        }
+    }
+}
+
+/// MetaTrader formatter.
+///
+/// Writes bars in an [`MtLayout`], with timestamps in an [`MtServerTime`].
+/// Volumes are tick volumes, the tick count of each bar, as MetaTrader
+/// keeps for forex symbols. Indicator columns and returns have no place in
+/// these layouts and are rejected; ticks are written in the MetaTrader 5
+/// layout only.
+#[derive(Debug, Clone, Default)]
+pub struct MtCsvFormatter {
+    /// Layout of the output.
+    layout: MtLayout,
+    /// Time zone of timestamps.
+    server_time: MtServerTime,
+    /// Decimal places of prices, if fixed.
+    digits: Option<u32>,
+    /// Symbol written into `.hst` headers.
+    symbol: Option<String>,
+    /// Bar period in minutes written into `.hst` headers.
+    period_minutes: Option<u32>,
+}
+
+impl MtCsvFormatter {
+    /// Creates a new formatter of the MetaTrader 4 CSV layout in UTC.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            layout: MtLayout::Mt4,
+            server_time: MtServerTime::Utc,
+            digits: None,
+            symbol: None,
+            period_minutes: None,
+        }
+    }
+
+    /// Sets the layout of the output (default: [`MtLayout::Mt4`]).
+    #[must_use]
+    pub const fn with_layout(mut self, layout: MtLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets the time zone of timestamps (default: UTC).
+    #[must_use]
+    pub const fn with_server_time(mut self, server_time: MtServerTime) -> Self {
+        self.server_time = server_time;
+        self
+    }
+
+    /// Writes prices with `digits` decimal places, the digits of the
+    /// MetaTrader symbol, and spreads in points of `10^-digits`.
+    ///
+    /// Without digits, prices are written in full and spreads as zero.
+    #[must_use]
+    pub const fn with_digits(mut self, digits: u32) -> Self {
+        self.digits = Some(digits);
+        self
+    }
+
+    /// Sets the symbol of `.hst` headers, written in upper case.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Sets the bar period of `.hst` headers in minutes, e.g. 60 for hourly
+    /// bars. Without a period, it is taken from the first two bars.
+    #[must_use]
+    pub const fn with_period_minutes(mut self, minutes: u32) -> Self {
+        self.period_minutes = Some(minutes);
+        self
+    }
+
+    /// Formats a price with the configured digits.
+    fn price(&self, price: f64) -> String {
+        format_price(price, self.digits)
+    }
+
+    /// Returns the spread of `bar` in points, or zero without digits or an
+    /// average spread.
+    fn spread_points(&self, bar: &Ohlcv) -> i32 {
+        match (self.digits, bar.avg_spread) {
+            (Some(digits), Some(spread)) => (spread * 10f64.powi(digits as i32)).round() as i32,
+            _ => 0,
+        }
+    }
+
+    /// Returns the error for records the layout cannot hold.
+    fn unsupported(&self, records: &'static str) -> FormatError {
+        let format = match self.layout {
+            MtLayout::Mt4 => "mt4",
+            MtLayout::Mt5 => "mt5",
+            MtLayout::Hst => "hst",
+        };
+        FormatError::UnsupportedRecords {
+            format: format.to_string(),
+            records,
+        }
+    }
+
+    /// Writes bars as CSV rows of the configured layout.
+    fn write_csv<W: Write>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        if self.layout == MtLayout::Mt5 {
+            writeln!(
+                writer,
+                "<DATE>\t<TIME>\t<OPEN>\t<HIGH>\t<LOW>\t<CLOSE>\t<TICKVOL>\t<VOL>\t<SPREAD>"
+            )?;
+        }
+        for bar in bars {
+            let time = self.server_time.convert(bar.timestamp);
+            let (open, high, low, close) = (
+                self.price(bar.open),
+                self.price(bar.high),
+                self.price(bar.low),
+                self.price(bar.close),
+            );
+            if self.layout == MtLayout::Mt5 {
+                writeln!(
+                    writer,
+                    "{}\t{open}\t{high}\t{low}\t{close}\t{}\t0\t{}",
+                    time.format("%Y.%m.%d\t%H:%M:%S"),
+                    bar.tick_count,
+                    self.spread_points(bar),
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "{},{open},{high},{low},{close},{}",
+                    time.format("%Y.%m.%d,%H:%M"),
+                    bar.tick_count,
+                )?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes bars as a `.hst` history file: a 148-byte header followed by
+    /// one 60-byte little-endian record per bar.
+    fn write_hst<W: Write>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        let seconds = |bar: &Ohlcv| {
+            self.server_time
+                .convert(bar.timestamp)
+                .and_utc()
+                .timestamp()
+        };
+        let period = self.period_minutes.unwrap_or_else(|| match bars {
+            [first, second, ..] => {
+                u32::try_from((seconds(second) - seconds(first)) / 60).unwrap_or(0)
+            }
+            _ => 0,
+        });
+        let symbol = self.symbol.as_deref().unwrap_or_default().to_uppercase();
+
+        writer.write_all(&HST_VERSION.to_le_bytes())?;
+        writer.write_all(&fixed::<64>(HST_COPYRIGHT))?;
+        writer.write_all(&fixed::<12>(&symbol))?;
+        writer.write_all(&(period as i32).to_le_bytes())?;
+        writer.write_all(&(self.digits.unwrap_or(0) as i32).to_le_bytes())?;
+        // The creation time is that of the last bar, so that identical bars
+        // give identical files
+        let created = bars.last().map_or(0, seconds) as i32;
+        writer.write_all(&created.to_le_bytes())?;
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&[0u8; 52])?;
+
+        for bar in bars {
+            writer.write_all(&seconds(bar).to_le_bytes())?;
+            for price in [bar.open, bar.high, bar.low, bar.close] {
+                writer.write_all(&price.to_le_bytes())?;
+            }
+            writer.write_all(&i64::from(bar.tick_count).to_le_bytes())?;
+            writer.write_all(&self.spread_points(bar).to_le_bytes())?;
+            writer.write_all(&0i64.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Returns `text` as a NUL-padded field of `N` bytes, truncated to leave a
+/// terminating NUL.
+fn fixed<const N: usize>(text: &str) -> [u8; N] {
+    let mut field = [0u8; N];
+    let len = text.len().min(N - 1);
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+    field
+}
+
+impl Formatter for MtCsvFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        if self.layout != MtLayout::Mt5 {
+            return Err(self.unsupported("ticks"));
+        }
+        let mut writer = io::BufWriter::new(writer);
+        writeln!(
+            writer,
+            "<DATE>\t<TIME>\t<BID>\t<ASK>\t<LAST>\t<VOLUME>\t<FLAGS>"
+        )?;
+        for tick in ticks {
+            // Flags 6 mark a change of both bid and ask
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t\t\t6",
+                self.server_time
+                    .convert(tick.timestamp)
+                    .format("%Y.%m.%d\t%H:%M:%S%.3f"),
+                self.price(tick.bid),
+                self.price(tick.ask),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        match self.layout {
+            MtLayout::Mt4 | MtLayout::Mt5 => self.write_csv(bars, writer),
+            MtLayout::Hst => self.write_hst(bars, writer),
+        }
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        _returns: &[PriceReturn],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(self.unsupported("returns"))
+    }
+
+    fn extension(&self) -> &str {
+        match self.layout {
+            MtLayout::Mt4 | MtLayout::Mt5 => "csv",
+            MtLayout::Hst => "hst",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn bars() -> Vec<Ohlcv> {
+        let first = Utc.with_ymd_and_hms(2024, 7, 1, 21, 0, 0).unwrap();
+        vec![
+            Ohlcv::new(first, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_price_stats(1.12, 0.00002, 0.00003),
+            Ohlcv::new(first + TimeDelta::hours(1), 1.15, 1.16, 1.14, 1.15, 2.0, 1),
+        ]
+    }
+
+    #[test]
+    fn test_mt4_csv() {
+        let mut output = Vec::new();
+        MtCsvFormatter::new()
+            .with_digits(5)
+            .with_server_time(MtServerTime::NewYorkClose)
+            .write_ohlcv(&bars(), &mut output)
+            .unwrap();
+
+        // 21:00 UTC is 17:00 in New York during daylight saving time
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("2024.07.02,00:00,1.10000,1.20000,1.00000,1.15000,2")
+        );
+        assert_eq!(lines.next().unwrap().split(',').nth(1), Some("01:00"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_mt5_csv() {
+        let mut output = Vec::new();
+        MtCsvFormatter::new()
+            .with_layout(MtLayout::Mt5)
+            .with_digits(5)
+            .with_server_time("Europe/London".parse().unwrap())
+            .write_ohlcv(&bars(), &mut output)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("<DATE>\t<TIME>\t<OPEN>\t<HIGH>\t<LOW>\t<CLOSE>\t<TICKVOL>\t<VOL>\t<SPREAD>")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("2024.07.01\t22:00:00\t1.10000\t1.20000\t1.00000\t1.15000\t2\t0\t2")
+        );
+
+        let err = MtCsvFormatter::new()
+            .write_ticks(&[], Vec::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "mt4 output cannot hold ticks");
+    }
+
+    #[test]
+    fn test_hst_records() {
+        let mut output = Vec::new();
+        MtCsvFormatter::new()
+            .with_layout(MtLayout::Hst)
+            .with_symbol("eurusd")
+            .with_digits(5)
+            .write_ohlcv(&bars(), &mut output)
+            .unwrap();
+
+        assert_eq!(output.len(), 148 + 2 * 60);
+        assert_eq!(&output[..4], &HST_VERSION.to_le_bytes());
+        assert_eq!(&output[68..75], b"EURUSD\0");
+        // Period in minutes, taken from the bars, and digits
+        assert_eq!(&output[80..84], &60i32.to_le_bytes());
+        assert_eq!(&output[84..88], &5i32.to_le_bytes());
+
+        let record = &output[148..208];
+        let time = bars()[0].timestamp.timestamp();
+        assert_eq!(&record[..8], &time.to_le_bytes());
+        assert_eq!(&record[8..16], &1.1f64.to_le_bytes());
+        assert_eq!(&record[40..48], &2i64.to_le_bytes());
+        assert_eq!(&record[48..52], &2i32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_server_time_parsing() {
+        assert_eq!("UTC".parse::<MtServerTime>().unwrap(), MtServerTime::Utc);
+        assert_eq!(
+            "ny-close".parse::<MtServerTime>().unwrap(),
+            MtServerTime::NewYorkClose
+        );
+        let winter = Utc.with_ymd_and_hms(2024, 1, 2, 22, 0, 0).unwrap();
+        assert_eq!(
+            MtServerTime::NewYorkClose.convert(winter).to_string(),
+            "2024-01-03 00:00:00"
+        );
+        assert!(matches!(
+            "Mars/Olympus".parse::<MtServerTime>(),
+            Err(FormatError::UnknownTimezone(_))
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+//! MessagePack output format.
+//!
+//! Records are written as a stream of MessagePack maps, one per record,
+//! with the field names of the JSON output. Timestamps are integers since
+//! the Unix epoch, so records are smaller than JSON and need no date or
+//! number parsing.
+
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::{Tick, TimestampPrecision};
+use rmp::encode;
+use std::io::{self, Write};
+
+use crate::formatter::check_indicators;
+use crate::{FormatError, Formatter};
+
+/// MessagePack formatter.
+///
+/// Like NDJSON, the output is a concatenation of records rather than one
+/// array, so it can be decoded incrementally and appended to. Bar fields
+/// that are absent are omitted, as in JSON; missing indicator values are
+/// `nil`.
+#[derive(Debug, Clone, Default)]
+pub struct MsgpackFormatter {
+    /// Instrument written as the `symbol` field of every record, if any.
+    symbol: Option<String>,
+    /// Resolution of the integer timestamps.
+    timestamp_precision: TimestampPrecision,
+}
+
+/// A field value of a record.
+#[derive(Debug, Clone, Copy)]
+enum Field<'a> {
+    /// A string.
+    Str(&'a str),
+    /// A signed integer.
+    Int(i64),
+    /// An unsigned integer.
+    Uint(u64),
+    /// A 32-bit float.
+    Float(f32),
+    /// A 64-bit float.
+    Double(f64),
+    /// A boolean.
+    Bool(bool),
+    /// A missing value.
+    Nil,
+}
+
+impl MsgpackFormatter {
+    /// Creates a new MessagePack formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            symbol: None,
+            timestamp_precision: TimestampPrecision::Millisecond,
+        }
+    }
+
+    /// Adds a leading `symbol` field holding `symbol` to every record.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Sets the resolution of the integer timestamps (default:
+    /// milliseconds).
+    #[must_use]
+    pub const fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Returns a timestamp field at the configured precision.
+    fn timestamp(&self, timestamp: DateTime<Utc>) -> Field<'static> {
+        Field::Int(self.timestamp_precision.to_epoch(timestamp))
+    }
+
+    /// Writes one map per record of `fields`, after the symbol field.
+    fn write_records<'a, W: Write>(
+        &self,
+        records: impl Iterator<Item = Vec<(&'a str, Field<'a>)>>,
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        for fields in records {
+            let symbol = self.symbol.as_deref().map(|s| ("symbol", Field::Str(s)));
+            let len = fields.len() + usize::from(symbol.is_some());
+            encode::write_map_len(&mut writer, u32::try_from(len).unwrap_or(u32::MAX))
+                .map_err(io::Error::from)?;
+            for (name, value) in symbol.into_iter().chain(fields) {
+                write_field(&mut writer, name, value)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes a key and its value.
+fn write_field<W: Write>(writer: &mut W, name: &str, value: Field<'_>) -> io::Result<()> {
+    encode::write_str(writer, name)?;
+    match value {
+        Field::Str(s) => encode::write_str(writer, s)?,
+        Field::Int(v) => {
+            encode::write_sint(writer, v)?;
+        }
+        Field::Uint(v) => {
+            encode::write_uint(writer, v)?;
+        }
+        Field::Float(v) => encode::write_f32(writer, v)?,
+        Field::Double(v) => encode::write_f64(writer, v)?,
+        Field::Bool(v) => encode::write_bool(writer, v)?,
+        Field::Nil => encode::write_nil(writer)?,
+    }
+    Ok(())
+}
+
+impl Formatter for MsgpackFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let records = ticks.iter().map(|tick| {
+            vec![
+                ("timestamp", self.timestamp(tick.timestamp)),
+                ("ask", Field::Double(tick.ask)),
+                ("bid", Field::Double(tick.bid)),
+                ("ask_volume", Field::Float(tick.ask_volume)),
+                ("bid_volume", Field::Float(tick.bid_volume)),
+            ]
+        });
+        self.write_records(records, writer)
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let records = bars.iter().enumerate().map(|(row, bar)| {
+            let mut fields = vec![
+                ("timestamp", self.timestamp(bar.timestamp)),
+                ("open", Field::Double(bar.open)),
+                ("high", Field::Double(bar.high)),
+                ("low", Field::Double(bar.low)),
+                ("close", Field::Double(bar.close)),
+                ("volume", Field::Double(bar.volume)),
+                ("tick_count", Field::Uint(bar.tick_count.into())),
+            ];
+            let doubles = [
+                ("ask_volume", bar.ask_volume),
+                ("bid_volume", bar.bid_volume),
+                ("imbalance", bar.imbalance),
+            ];
+            fields.extend(
+                doubles
+                    .into_iter()
+                    .filter_map(|(name, v)| Some((name, Field::Double(v?)))),
+            );
+            let times = [
+                ("first_tick_ts", bar.first_tick_ts),
+                ("last_tick_ts", bar.last_tick_ts),
+            ];
+            fields.extend(
+                times
+                    .into_iter()
+                    .filter_map(|(name, ts)| Some((name, self.timestamp(ts?)))),
+            );
+            let doubles = [
+                ("vwap", bar.vwap),
+                ("avg_spread", bar.avg_spread),
+                ("max_spread", bar.max_spread),
+                ("tick_rate", bar.tick_rate),
+            ];
+            fields.extend(
+                doubles
+                    .into_iter()
+                    .filter_map(|(name, v)| Some((name, Field::Double(v?)))),
+            );
+            let counts = [
+                ("bid_changes", bar.bid_changes),
+                ("ask_changes", bar.ask_changes),
+            ];
+            fields.extend(
+                counts
+                    .into_iter()
+                    .filter_map(|(name, v)| Some((name, Field::Uint(v?.into())))),
+            );
+            if let Some(spread) = bar.twa_spread {
+                fields.push(("twa_spread", Field::Double(spread)));
+            }
+            if bar.is_partial {
+                fields.push(("is_partial", Field::Bool(true)));
+            }
+            fields.extend(indicators.iter().map(|c| {
+                let value = c.values[row].map_or(Field::Nil, Field::Double);
+                (c.name.as_str(), value)
+            }));
+            fields
+        });
+        self.write_records(records, writer)
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let records = returns.iter().map(|r| {
+            vec![
+                ("timestamp", self.timestamp(r.timestamp)),
+                ("return", Field::Double(r.value)),
+                ("tick_count", Field::Uint(r.tick_count.into())),
+            ]
+        });
+        self.write_records(records, writer)
+    }
+
+    fn extension(&self) -> &str {
+        "msgpack"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::{Value, json};
+
+    fn decode(bytes: &[u8]) -> Vec<Value> {
+        let mut reader = bytes;
+        let mut records = Vec::new();
+        while !reader.is_empty() {
+            records.push(rmp_serde::from_read(&mut reader).unwrap());
+        }
+        records
+    }
+
+    #[test]
+    fn test_msgpack_ticks() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap();
+        let ticks = [
+            Tick::new(timestamp, 1.5, 1.25, 100.0, 200.0),
+            Tick::new(timestamp, 1.5, 1.25, 1.0, 2.0),
+        ];
+
+        let mut output = Vec::new();
+        MsgpackFormatter::new()
+            .with_symbol("eurusd")
+            .write_ticks(&ticks, &mut output)
+            .unwrap();
+
+        let mut json = Vec::new();
+        crate::JsonFormatter::ndjson()
+            .with_symbol("eurusd")
+            .write_ticks(&ticks, &mut json)
+            .unwrap();
+        assert!(output.len() < json.len());
+
+        let records = decode(&output);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0],
+            json!({
+                "symbol": "eurusd",
+                "timestamp": timestamp.timestamp_millis(),
+                "ask": 1.5,
+                "bid": 1.25,
+                "ask_volume": 100.0,
+                "bid_volume": 200.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_msgpack_bars_omit_absent_fields() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0);
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None],
+        };
+
+        let mut output = Vec::new();
+        MsgpackFormatter::new()
+            .write_ohlcv_with_indicators(&[bar], &[sma], &mut output)
+            .unwrap();
+
+        let record = &decode(&output)[0];
+        assert_eq!(record["tick_count"], 2);
+        assert_eq!(record["bid_volume"], 3.0);
+        assert_eq!(record["sma_2"], Value::Null);
+        assert!(record.get("vwap").is_none());
+        assert!(record.get("is_partial").is_none());
+    }
+}
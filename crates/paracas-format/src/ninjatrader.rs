@@ -0,0 +1,172 @@
+//! NinjaTrader import format.
+//!
+//! NinjaTrader imports semicolon-separated text files without a header, one
+//! for ticks and one per bar type, with times in a compact
+//! `yyyyMMdd HHmmss` form. Minute bars are stamped with their close time.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use paracas_aggregate::{Ohlcv, PriceReturn};
+use paracas_types::{Tick, Timeframe};
+use std::io::{self, Write};
+
+use crate::formatter::format_price;
+use crate::{FormatError, Formatter};
+
+/// NinjaTrader formatter.
+///
+/// Ticks are written as `yyyyMMdd HHmmss fffffff;last;bid;ask;volume`, with
+/// the mid price as last price, and bars as
+/// `yyyyMMdd HHmmss;open;high;low;close;volume`, or `yyyyMMdd;...` for daily
+/// bars. Volumes are tick volumes: one per tick, the tick count per bar.
+/// Times are UTC; pick UTC as the time zone when importing.
+#[derive(Debug, Clone, Default)]
+pub struct NinjaTraderFormatter {
+    /// Timeframe of bars, which are stamped with their close time.
+    timeframe: Option<Timeframe>,
+    /// Decimal places of prices, if fixed.
+    digits: Option<u32>,
+}
+
+impl NinjaTraderFormatter {
+    /// Creates a new NinjaTrader formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            timeframe: None,
+            digits: None,
+        }
+    }
+
+    /// Sets the timeframe of bars. Bars are then stamped with their close
+    /// time, as NinjaTrader expects, and daily bars with their date only.
+    ///
+    /// Without a timeframe, bars are stamped with their open time.
+    #[must_use]
+    pub const fn with_timeframe(mut self, timeframe: Timeframe) -> Self {
+        self.timeframe = Some(timeframe);
+        self
+    }
+
+    /// Writes prices with `digits` decimal places.
+    #[must_use]
+    pub const fn with_digits(mut self, digits: u32) -> Self {
+        self.digits = Some(digits);
+        self
+    }
+
+    /// Formats a price with the configured digits.
+    fn price(&self, price: f64) -> String {
+        format_price(price, self.digits)
+    }
+
+    /// Returns the time a bar opening at `timestamp` is stamped with.
+    fn bar_time(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        self.timeframe
+            .and_then(|timeframe| timeframe.seconds())
+            .and_then(|seconds| TimeDelta::try_seconds(i64::try_from(seconds).ok()?))
+            .map_or(timestamp, |duration| timestamp + duration)
+    }
+}
+
+impl Formatter for NinjaTraderFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        for tick in ticks {
+            writeln!(
+                writer,
+                "{} {:07};{};{};{};1",
+                tick.timestamp.format("%Y%m%d %H%M%S"),
+                tick.timestamp.timestamp_subsec_nanos() / 100,
+                self.price(tick.mid()),
+                self.price(tick.bid),
+                self.price(tick.ask),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        let daily = self.timeframe == Some(Timeframe::Day1);
+        let mut writer = io::BufWriter::new(writer);
+        for bar in bars {
+            let time = if daily {
+                bar.timestamp.format("%Y%m%d")
+            } else {
+                self.bar_time(bar.timestamp).format("%Y%m%d %H%M%S")
+            };
+            writeln!(
+                writer,
+                "{time};{};{};{};{};{}",
+                self.price(bar.open),
+                self.price(bar.high),
+                self.price(bar.low),
+                self.price(bar.close),
+                bar.tick_count,
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        _returns: &[PriceReturn],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(FormatError::UnsupportedRecords {
+            format: "ninjatrader".to_string(),
+            records: "returns",
+        })
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_ninjatrader_ticks() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap()
+            + TimeDelta::microseconds(123_456);
+        let ticks = [Tick::new(timestamp, 1.10002, 1.1, 1.0, 2.0)];
+
+        let mut output = Vec::new();
+        NinjaTraderFormatter::new()
+            .with_digits(5)
+            .write_ticks(&ticks, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "20240115 123045 1234560;1.10001;1.10000;1.10002;1\n"
+        );
+    }
+
+    #[test]
+    fn test_ninjatrader_bars_close_time() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+
+        let mut output = Vec::new();
+        NinjaTraderFormatter::new()
+            .with_timeframe(Timeframe::Minute1)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "20240115 120100;1.1;1.2;1;1.15;2\n"
+        );
+
+        let mut output = Vec::new();
+        NinjaTraderFormatter::new()
+            .with_timeframe(Timeframe::Day1)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert!(output.starts_with(b"20240115;"));
+    }
+}
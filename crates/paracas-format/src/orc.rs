@@ -0,0 +1,433 @@
+//! Apache ORC output.
+//!
+//! Files are ORC files of a single stripe per 64 MiB of data, readable by
+//! Hive, Trino, Spark and other ORC-based warehouses without a conversion
+//! from Parquet. ORC has no unsigned types, so counts are `bigint` columns;
+//! timestamps are `timestamp` columns of UTC instants.
+
+use chrono::{DateTime, Utc};
+use orc_arrow::array::{
+    ArrayRef, BooleanArray, Float32Array, Float64Array, Int64Array, StringArray,
+    TimestampMicrosecondArray,
+};
+use orc_arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use orc_arrow::record_batch::RecordBatch;
+use orc_rust::ArrowWriterBuilder;
+use orc_rust::compression::CompressionType;
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{FormatError, Formatter};
+
+/// Compression codec of ORC streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OrcCompression {
+    /// Uncompressed streams.
+    None,
+    /// Zlib, the codec Hive writes by default.
+    #[default]
+    Zlib,
+    /// Snappy, faster but larger.
+    Snappy,
+    /// LZ4.
+    Lz4,
+    /// Zstandard, smaller, read by ORC 1.6 (Hive 4, Trino) and later.
+    Zstd,
+}
+
+impl OrcCompression {
+    /// Returns the ORC codec, if compressed.
+    const fn codec(self) -> Option<CompressionType> {
+        match self {
+            Self::None => None,
+            Self::Zlib => Some(CompressionType::Zlib),
+            Self::Snappy => Some(CompressionType::Snappy),
+            Self::Lz4 => Some(CompressionType::Lz4),
+            Self::Zstd => Some(CompressionType::Zstd),
+        }
+    }
+}
+
+/// Apache ORC formatter.
+///
+/// Writes ticks, bars and returns as flat ORC tables. Optional bar columns
+/// and indicators are nullable columns; volume profiles, which are nested,
+/// are not supported. ORC files carry no dataset metadata, so it is written
+/// as a JSON sidecar.
+#[derive(Debug, Clone, Default)]
+pub struct OrcFormatter {
+    /// Instrument written in a leading `symbol` column, if any.
+    symbol: Option<String>,
+    /// Compression codec of the streams.
+    compression: OrcCompression,
+}
+
+impl OrcFormatter {
+    /// Creates a new ORC formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            symbol: None,
+            compression: OrcCompression::Zlib,
+        }
+    }
+
+    /// Adds a leading `symbol` column holding `symbol` on every row.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Sets the compression codec (default: [`OrcCompression::Zlib`]).
+    #[must_use]
+    pub const fn with_compression(mut self, compression: OrcCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Writes an ORC file of the `timestamps` column followed by `fields`
+    /// holding `columns`, after the symbol column if any.
+    fn write_table<W: Write>(
+        &self,
+        timestamps: ArrayRef,
+        fields: Vec<Field>,
+        columns: Vec<ArrayRef>,
+        mut writer: W,
+    ) -> Result<(), FormatError> {
+        let rows = timestamps.len();
+        let symbol = self.symbol.as_ref().map(|symbol| {
+            (
+                Field::new("symbol", DataType::Utf8, false),
+                Arc::new(StringArray::from(vec![symbol.as_str(); rows])) as ArrayRef,
+            )
+        });
+        let (symbol_field, symbol_column) = symbol.unzip();
+        let schema = Arc::new(Schema::new(
+            symbol_field
+                .into_iter()
+                .chain([Field::new("timestamp", timestamp_type(), false)])
+                .chain(fields)
+                .collect::<Vec<_>>(),
+        ));
+        let columns = symbol_column
+            .into_iter()
+            .chain([timestamps])
+            .chain(columns)
+            .collect();
+        let batch = RecordBatch::try_new(Arc::clone(&schema), columns).map_err(orc)?;
+
+        let mut builder = ArrowWriterBuilder::new(&mut writer, schema);
+        if let Some(codec) = self.compression.codec() {
+            builder = builder.with_compression(codec);
+        }
+        let mut orc_writer = builder.try_build().map_err(orc)?;
+        orc_writer.write(&batch).map_err(orc)?;
+        orc_writer.close().map_err(orc)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Formatter for OrcFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let fields = vec![
+            Field::new("ask", DataType::Float64, false),
+            Field::new("bid", DataType::Float64, false),
+            Field::new("ask_volume", DataType::Float32, false),
+            Field::new("bid_volume", DataType::Float32, false),
+        ];
+        let columns: Vec<ArrayRef> = vec![
+            doubles(ticks.iter().map(|t| Some(t.ask))),
+            doubles(ticks.iter().map(|t| Some(t.bid))),
+            Arc::new(Float32Array::from_iter_values(
+                ticks.iter().map(|t| t.ask_volume),
+            )),
+            Arc::new(Float32Array::from_iter_values(
+                ticks.iter().map(|t| t.bid_volume),
+            )),
+        ];
+        self.write_table(
+            timestamps(ticks.iter().map(|t| Some(t.timestamp))),
+            fields,
+            columns,
+            writer,
+        )
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let optional = OhlcvColumns::of(bars);
+        let mut fields: Vec<_> = ["open", "high", "low", "close", "volume"]
+            .into_iter()
+            .map(|name| Field::new(name, DataType::Float64, false))
+            .chain([Field::new("tick_count", DataType::Int64, false)])
+            .collect();
+        let mut columns = vec![
+            doubles(bars.iter().map(|b| Some(b.open))),
+            doubles(bars.iter().map(|b| Some(b.high))),
+            doubles(bars.iter().map(|b| Some(b.low))),
+            doubles(bars.iter().map(|b| Some(b.close))),
+            doubles(bars.iter().map(|b| Some(b.volume))),
+            counts(bars.iter().map(|b| Some(b.tick_count))),
+        ];
+        let mut add = |name: &str, data_type: DataType, nullable: bool, column: ArrayRef| {
+            fields.push(Field::new(name, data_type, nullable));
+            columns.push(column);
+        };
+        if optional.side_volumes {
+            add(
+                "ask_volume",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.ask_volume)),
+            );
+            add(
+                "bid_volume",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.bid_volume)),
+            );
+            add(
+                "imbalance",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.imbalance)),
+            );
+        }
+        if optional.tick_times {
+            let partial = BooleanArray::from_iter(bars.iter().map(|b| Some(b.is_partial)));
+            add(
+                "first_tick_ts",
+                timestamp_type(),
+                true,
+                timestamps(bars.iter().map(|b| b.first_tick_ts)),
+            );
+            add(
+                "last_tick_ts",
+                timestamp_type(),
+                true,
+                timestamps(bars.iter().map(|b| b.last_tick_ts)),
+            );
+            add("is_partial", DataType::Boolean, false, Arc::new(partial));
+        }
+        if optional.price_stats {
+            add(
+                "vwap",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.vwap)),
+            );
+            add(
+                "avg_spread",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.avg_spread)),
+            );
+            add(
+                "max_spread",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.max_spread)),
+            );
+        }
+        if optional.microstructure {
+            add(
+                "tick_rate",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.tick_rate)),
+            );
+            add(
+                "bid_changes",
+                DataType::Int64,
+                true,
+                counts(bars.iter().map(|b| b.bid_changes)),
+            );
+            add(
+                "ask_changes",
+                DataType::Int64,
+                true,
+                counts(bars.iter().map(|b| b.ask_changes)),
+            );
+            add(
+                "twa_spread",
+                DataType::Float64,
+                true,
+                doubles(bars.iter().map(|b| b.twa_spread)),
+            );
+        }
+        for column in indicators {
+            add(
+                &column.name,
+                DataType::Float64,
+                true,
+                doubles(column.values.iter().copied()),
+            );
+        }
+        self.write_table(
+            timestamps(bars.iter().map(|b| Some(b.timestamp))),
+            fields,
+            columns,
+            writer,
+        )
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let fields = vec![
+            Field::new("return", DataType::Float64, false),
+            Field::new("tick_count", DataType::Int64, false),
+        ];
+        let columns = vec![
+            doubles(returns.iter().map(|r| Some(r.value))),
+            counts(returns.iter().map(|r| Some(r.tick_count))),
+        ];
+        self.write_table(
+            timestamps(returns.iter().map(|r| Some(r.timestamp))),
+            fields,
+            columns,
+            writer,
+        )
+    }
+
+    fn extension(&self) -> &str {
+        "orc"
+    }
+}
+
+/// Returns the Arrow type of timestamp columns, written as ORC `timestamp`
+/// columns in UTC.
+const fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Microsecond, None)
+}
+
+/// Returns a timestamp column of microseconds.
+fn timestamps(values: impl Iterator<Item = Option<DateTime<Utc>>>) -> ArrayRef {
+    Arc::new(TimestampMicrosecondArray::from_iter(
+        values.map(|value| value.map(|ts| ts.timestamp_micros())),
+    ))
+}
+
+/// Returns a nullable double column.
+fn doubles(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(Float64Array::from_iter(values))
+}
+
+/// Returns a nullable `bigint` column of counts.
+fn counts(values: impl Iterator<Item = Option<u32>>) -> ArrayRef {
+    Arc::new(Int64Array::from_iter(
+        values.map(|value| value.map(i64::from)),
+    ))
+}
+
+/// Converts an Arrow or ORC error.
+fn orc(e: impl std::fmt::Display) -> FormatError {
+    FormatError::Orc(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chrono::TimeZone;
+    use orc_arrow::array::{Array, AsArray};
+    use orc_arrow::datatypes::Float64Type;
+    use orc_rust::ArrowReaderBuilder;
+
+    /// Reads back the single batch of an ORC file.
+    fn read(output: Vec<u8>) -> RecordBatch {
+        let reader = ArrowReaderBuilder::try_new(Bytes::from(output))
+            .unwrap()
+            .build();
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 1);
+        batches.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_orc_ticks_round_trip() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let ticks = [Tick::new(timestamp, 1.10002, 1.1, 1.0, 2.0)];
+
+        let mut output = Vec::new();
+        OrcFormatter::new()
+            .with_symbol("eurusd")
+            .write_ticks(&ticks, &mut output)
+            .unwrap();
+        assert!(output.starts_with(b"ORC"));
+
+        let batch = read(output);
+        let names: Vec<_> = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "symbol",
+                "timestamp",
+                "ask",
+                "bid",
+                "ask_volume",
+                "bid_volume"
+            ]
+        );
+        assert_eq!(batch.column(0).as_string::<i32>().value(0), "eurusd");
+        assert_eq!(
+            batch.column(2).as_primitive::<Float64Type>().value(0),
+            1.10002
+        );
+    }
+
+    #[test]
+    fn test_orc_bars_nullable_columns() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2),
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0),
+        ];
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None, Some(1.15)],
+        };
+
+        let mut output = Vec::new();
+        OrcFormatter::new()
+            .with_compression(OrcCompression::Zstd)
+            .write_ohlcv_with_indicators(&bars, &[sma], &mut output)
+            .unwrap();
+
+        let batch = read(output);
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        let column = |name: &str| batch.column(schema.index_of(name).unwrap());
+        assert!(column("bid_volume").is_null(0));
+        assert_eq!(
+            column("bid_volume").as_primitive::<Float64Type>().value(1),
+            3.0
+        );
+        assert!(column("sma_2").is_null(0));
+        assert_eq!(
+            schema.field_with_name("tick_count").unwrap().data_type(),
+            &DataType::Int64
+        );
+    }
+}
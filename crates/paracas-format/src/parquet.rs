@@ -1,17 +1,36 @@
 //! Apache Parquet output format.
 
-use arrow::array::{Float32Array, Float64Array, TimestampMicrosecondArray, UInt32Array};
-use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::array::{
+    ArrayRef, BooleanArray, Decimal128Array, Float32Array, Float64Array, Int64Array, ListArray,
+    StringArray, StructArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+    TimestampNanosecondArray, UInt32Array,
+};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{DataType, Field, Fields, Schema, TimeUnit};
 use arrow::record_batch::RecordBatch;
-use paracas_aggregate::Ohlcv;
-use paracas_types::Tick;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use paracas_aggregate::{IndicatorColumn, Ohlcv, OhlcvBidAsk, PriceReturn, VolumeProfile};
+use paracas_types::{
+    ParquetCodec, ParquetSettings, ParquetStatistics, PriceEncoding, Tick, TimestampPrecision,
+};
 use parquet::arrow::ArrowWriter;
-use parquet::basic::Compression;
-use parquet::file::properties::WriterProperties;
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::{EnabledStatistics, WriterProperties};
+use parquet::format::SortingColumn;
 use std::io::Write;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
-use crate::{FormatError, Formatter};
+use crate::formatter::{OhlcvColumns, check_indicators, check_profiles};
+use crate::{
+    BatchWriter, DerivedColumns, FormatError, Formatter, METADATA_KEY_PREFIX, OutputMetadata,
+    StreamingFormatter,
+};
+
+/// Precision of decimal price columns, enough for any price of the feed.
+const DECIMAL_PRECISION: u8 = 18;
 
 /// Parquet formatter.
 #[derive(Debug, Clone)]
@@ -20,6 +39,26 @@ pub struct ParquetFormatter {
     row_group_size: usize,
     /// Compression codec.
     compression: Compression,
+    /// Whether columns are dictionary-encoded.
+    dictionary: bool,
+    /// Target data page size in bytes, if not the writer default.
+    data_page_size: Option<usize>,
+    /// Column statistics written.
+    statistics: EnabledStatistics,
+    /// Whether to declare the timestamp sort order of sorted rows.
+    sorting_columns: bool,
+    /// Storage of the price columns.
+    price_encoding: PriceEncoding,
+    /// Resolution of the timestamp column.
+    timestamp_precision: TimestampPrecision,
+    /// Time zone timestamps are displayed in.
+    timezone: Tz,
+    /// Dataset metadata embedded in the file footer.
+    metadata: Option<OutputMetadata>,
+    /// Instrument written in a leading `symbol` column, if any.
+    symbol: Option<String>,
+    /// Tick columns derived from the quotes.
+    derived: DerivedColumns,
 }
 
 impl Default for ParquetFormatter {
@@ -27,6 +66,16 @@ impl Default for ParquetFormatter {
         Self {
             row_group_size: 100_000,
             compression: Compression::SNAPPY,
+            dictionary: true,
+            data_page_size: None,
+            statistics: EnabledStatistics::Page,
+            sorting_columns: false,
+            price_encoding: PriceEncoding::Float,
+            timestamp_precision: TimestampPrecision::Microsecond,
+            timezone: Tz::UTC,
+            metadata: None,
+            symbol: None,
+            derived: DerivedColumns::new(),
         }
     }
 }
@@ -45,112 +94,688 @@ impl ParquetFormatter {
         self
     }
 
-    /// Sets the compression codec.
+    /// Sets the compression codec, e.g. `Compression::ZSTD(level)` for
+    /// files typically 2-3x smaller than the default Snappy.
     #[must_use]
     pub const fn with_compression(mut self, compression: Compression) -> Self {
         self.compression = compression;
         self
     }
 
-    /// Creates the Arrow schema for tick data.
-    fn tick_schema() -> Schema {
-        Schema::new(vec![
-            Field::new(
-                "timestamp",
-                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
-                false,
+    /// Enables or disables dictionary encoding (default: enabled).
+    #[must_use]
+    pub const fn with_dictionary(mut self, dictionary: bool) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    /// Sets the target size of data pages in bytes (default: 1 MiB).
+    #[must_use]
+    pub const fn with_data_page_size(mut self, bytes: usize) -> Self {
+        self.data_page_size = Some(bytes);
+        self
+    }
+
+    /// Sets the column statistics written (default: per page).
+    #[must_use]
+    pub const fn with_statistics(mut self, statistics: EnabledStatistics) -> Self {
+        self.statistics = statistics;
+        self
+    }
+
+    /// Declares the timestamp column as the sort order of each row group
+    /// (default: off).
+    ///
+    /// The declaration is only written by the [`Formatter`] methods, when
+    /// the rows are in timestamp order; streaming writers cannot know the
+    /// order up front and never declare it.
+    #[must_use]
+    pub const fn with_sorting_columns(mut self, sorting_columns: bool) -> Self {
+        self.sorting_columns = sorting_columns;
+        self
+    }
+
+    /// Sets how tick and bar prices are stored (default: floats).
+    ///
+    /// The integer and decimal encodings store the ask and bid of ticks and
+    /// the open, high, low and close of bars exactly, in nullable columns
+    /// where NaN prices become nulls. They need the decimal factor from
+    /// the [metadata](Self::with_metadata), which is written along with
+    /// `paracas.price_encoding`. Derived prices, such as Heikin-Ashi
+    /// candles, are rounded to the price increment; VWAPs, spreads and
+    /// volume profiles stay floats.
+    #[must_use]
+    pub const fn with_price_encoding(mut self, encoding: PriceEncoding) -> Self {
+        self.price_encoding = encoding;
+        self
+    }
+
+    /// Applies writer settings, such as those chosen on the command line.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Parquet`] if the level is out of range for
+    /// the codec, or given for a codec without levels.
+    pub fn with_settings(self, settings: &ParquetSettings) -> Result<Self, FormatError> {
+        let level_error = |e: parquet::errors::ParquetError| FormatError::Parquet(e.to_string());
+        let compression = match (settings.codec, settings.level) {
+            (codec, Some(_)) if !codec.has_levels() => {
+                return Err(FormatError::Parquet(format!(
+                    "{codec} compression takes no level"
+                )));
+            }
+            (ParquetCodec::Uncompressed, _) => Compression::UNCOMPRESSED,
+            (ParquetCodec::Snappy, _) => Compression::SNAPPY,
+            (ParquetCodec::Lz4, _) => Compression::LZ4_RAW,
+            (ParquetCodec::Gzip, level) => Compression::GZIP(
+                level
+                    .map_or_else(|| Ok(GzipLevel::default()), GzipLevel::try_new)
+                    .map_err(level_error)?,
+            ),
+            (ParquetCodec::Zstd, level) => Compression::ZSTD(
+                level
+                    .map_or_else(
+                        || Ok(ZstdLevel::default()),
+                        |level| ZstdLevel::try_new(i32::try_from(level).unwrap_or(i32::MAX)),
+                    )
+                    .map_err(level_error)?,
+            ),
+            (ParquetCodec::Brotli, level) => Compression::BROTLI(
+                level
+                    .map_or_else(|| Ok(BrotliLevel::default()), BrotliLevel::try_new)
+                    .map_err(level_error)?,
             ),
-            Field::new("ask", DataType::Float64, false),
-            Field::new("bid", DataType::Float64, false),
+        };
+        let statistics = match settings.statistics {
+            ParquetStatistics::None => EnabledStatistics::None,
+            ParquetStatistics::Chunk => EnabledStatistics::Chunk,
+            ParquetStatistics::Page => EnabledStatistics::Page,
+        };
+        Ok(Self {
+            compression,
+            dictionary: settings.dictionary,
+            data_page_size: settings.data_page_size,
+            statistics,
+            sorting_columns: settings.sorting_columns,
+            price_encoding: settings.price_encoding,
+            timestamp_precision: settings.timestamp_precision,
+            timezone: settings.timezone,
+            ..self
+        })
+    }
+
+    /// Sets the resolution of the timestamp column (default: microseconds).
+    ///
+    /// Nanosecond resolution preserves sub-microsecond timestamps, at the cost
+    /// of limiting the representable range to roughly 1677-2262.
+    #[must_use]
+    pub const fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Sets the time zone of timestamp columns (default: UTC).
+    ///
+    /// The zone is part of the Arrow type, so readers display timestamps in
+    /// it; the stored values are the same UTC instants whatever the zone.
+    #[must_use]
+    pub const fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Embeds dataset metadata in the file's key-value metadata.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Adds a leading `symbol` column holding `symbol` on every row, so
+    /// files of several instruments can be concatenated or loaded into one
+    /// table. Dictionary encoding stores the repeated value once per page.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Adds tick columns derived from the quotes after the volumes, stored
+    /// as floats whatever the price encoding, as the mid may fall halfway
+    /// between two price increments.
+    #[must_use]
+    pub const fn with_derived_columns(mut self, derived: DerivedColumns) -> Self {
+        self.derived = derived;
+        self
+    }
+
+    /// Builds the writer properties for this formatter, declaring the
+    /// timestamp sort order if enabled and the rows are `sorted`.
+    fn writer_properties(&self, sorted: bool) -> WriterProperties {
+        let key_value_metadata = self.metadata.as_ref().map(|metadata| {
+            let mut pairs = metadata.key_value_pairs();
+            if self.price_encoding != PriceEncoding::Float {
+                pairs.push((
+                    format!("{METADATA_KEY_PREFIX}price_encoding"),
+                    self.price_encoding.to_string(),
+                ));
+            }
+            pairs
+                .into_iter()
+                .map(|(key, value)| KeyValue::new(key, value))
+                .collect()
+        });
+
+        // The timestamp is the first column of every schema, after the
+        // symbol if any
+        let sorting_columns = (self.sorting_columns && sorted).then(|| {
+            vec![SortingColumn {
+                column_idx: i32::from(self.symbol.is_some()),
+                descending: false,
+                nulls_first: false,
+            }]
+        });
+
+        let builder = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_dictionary_enabled(self.dictionary)
+            .set_statistics_enabled(self.statistics)
+            .set_sorting_columns(sorting_columns)
+            .set_max_row_group_size(self.row_group_size)
+            .set_key_value_metadata(key_value_metadata);
+        match self.data_page_size {
+            Some(bytes) => builder.set_data_page_size_limit(bytes).build(),
+            None => builder.build(),
+        }
+    }
+
+    /// Returns the Arrow time unit for the configured precision.
+    const fn time_unit(&self) -> TimeUnit {
+        match self.timestamp_precision {
+            TimestampPrecision::Millisecond => TimeUnit::Millisecond,
+            TimestampPrecision::Microsecond => TimeUnit::Microsecond,
+            TimestampPrecision::Nanosecond => TimeUnit::Nanosecond,
+        }
+    }
+
+    /// Returns the Arrow type of timestamp columns.
+    fn timestamp_type(&self) -> DataType {
+        DataType::Timestamp(self.time_unit(), Some(self.timezone.name().into()))
+    }
+
+    /// Creates the Arrow field for the timestamp column.
+    fn timestamp_field(&self) -> Field {
+        Field::new("timestamp", self.timestamp_type(), false)
+    }
+
+    /// Builds the timestamp column at the configured precision.
+    fn timestamp_array(&self, timestamps: impl Iterator<Item = DateTime<Utc>>) -> ArrayRef {
+        self.optional_timestamp_array(timestamps.map(Some))
+    }
+
+    /// Builds a nullable timestamp column at the configured precision.
+    fn optional_timestamp_array(
+        &self,
+        timestamps: impl Iterator<Item = Option<DateTime<Utc>>>,
+    ) -> ArrayRef {
+        let precision = self.timestamp_precision;
+        let timezone = self.timezone.name();
+        let values: Vec<_> = timestamps
+            .map(|ts| ts.map(|ts| precision.to_epoch(ts)))
+            .collect();
+        match precision {
+            TimestampPrecision::Millisecond => {
+                Arc::new(TimestampMillisecondArray::from(values).with_timezone(timezone))
+            }
+            TimestampPrecision::Microsecond => {
+                Arc::new(TimestampMicrosecondArray::from(values).with_timezone(timezone))
+            }
+            TimestampPrecision::Nanosecond => {
+                Arc::new(TimestampNanosecondArray::from(values).with_timezone(timezone))
+            }
+        }
+    }
+
+    /// Returns the decimal factor and its number of digits, which the exact
+    /// price encodings need.
+    fn price_scale(&self) -> Result<(u32, i8), FormatError> {
+        let factor = self
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.decimal_factor)
+            .ok_or(FormatError::MissingDecimalFactor(self.price_encoding))?;
+        let digits = factor.ilog10();
+        if self.price_encoding == PriceEncoding::Decimal && 10_u32.pow(digits) != factor {
+            return Err(FormatError::Parquet(format!(
+                "decimal prices need a power of ten as decimal factor, not {factor}"
+            )));
+        }
+        Ok((factor, digits as i8))
+    }
+
+    /// Checks that prices can be written with the configured encoding.
+    fn check_price_encoding(&self) -> Result<(), FormatError> {
+        match self.price_encoding {
+            PriceEncoding::Float => Ok(()),
+            PriceEncoding::Integer | PriceEncoding::Decimal => self.price_scale().map(drop),
+        }
+    }
+
+    /// Creates the Arrow field for a price column.
+    fn price_field(&self, name: &str) -> Field {
+        let scale = self.price_scale().map_or(0, |(_, scale)| scale);
+        match self.price_encoding {
+            PriceEncoding::Float => Field::new(name, DataType::Float64, false),
+            PriceEncoding::Integer => Field::new(name, DataType::Int64, true),
+            PriceEncoding::Decimal => {
+                Field::new(name, DataType::Decimal128(DECIMAL_PRECISION, scale), true)
+            }
+        }
+    }
+
+    /// Builds a price column with the configured encoding.
+    fn price_array(&self, prices: impl Iterator<Item = f64>) -> Result<ArrayRef, FormatError> {
+        let (factor, scale) = self.price_scale().unwrap_or((1, 0));
+        let raw =
+            |price: f64| (!price.is_nan()).then(|| (price * f64::from(factor)).round() as i64);
+        Ok(match self.price_encoding {
+            PriceEncoding::Float => Arc::new(Float64Array::from_iter_values(prices)),
+            PriceEncoding::Integer => Arc::new(prices.map(raw).collect::<Int64Array>()),
+            PriceEncoding::Decimal => Arc::new(
+                prices
+                    .map(|price| raw(price).map(i128::from))
+                    .collect::<Decimal128Array>()
+                    .with_precision_and_scale(DECIMAL_PRECISION, scale)
+                    .map_err(|e| FormatError::Parquet(e.to_string()))?,
+            ),
+        })
+    }
+
+    /// Creates a schema of `fields`, after the symbol column if any.
+    fn schema(&self, fields: Vec<Field>) -> Schema {
+        let symbol = self
+            .symbol
+            .as_ref()
+            .map(|_| Field::new("symbol", DataType::Utf8, false));
+        Schema::new(symbol.into_iter().chain(fields).collect::<Vec<_>>())
+    }
+
+    /// Creates a record batch of `columns`, after the symbol column if any.
+    fn record_batch(
+        &self,
+        schema: Arc<Schema>,
+        columns: Vec<ArrayRef>,
+    ) -> Result<RecordBatch, FormatError> {
+        let rows = columns.first().map_or(0, |column| column.len());
+        let symbol = self.symbol.as_deref().map(|symbol| {
+            Arc::new(StringArray::from_iter_values(std::iter::repeat_n(
+                symbol, rows,
+            ))) as ArrayRef
+        });
+        RecordBatch::try_new(schema, symbol.into_iter().chain(columns).collect())
+            .map_err(|e| FormatError::Parquet(e.to_string()))
+    }
+
+    /// Creates the Arrow schema for tick data.
+    fn tick_schema(&self) -> Schema {
+        let mut fields = vec![
+            self.timestamp_field(),
+            self.price_field("ask"),
+            self.price_field("bid"),
             Field::new("ask_volume", DataType::Float32, false),
             Field::new("bid_volume", DataType::Float32, false),
-        ])
+        ];
+        if self.derived.spread {
+            fields.push(Field::new("spread", DataType::Float64, false));
+        }
+        if self.derived.mid {
+            fields.push(Field::new("mid", DataType::Float64, false));
+        }
+        self.schema(fields)
     }
 
     /// Creates the Arrow schema for OHLCV data.
-    fn ohlcv_schema() -> Schema {
-        Schema::new(vec![
-            Field::new(
-                "timestamp",
-                DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
-                false,
-            ),
-            Field::new("open", DataType::Float64, false),
-            Field::new("high", DataType::Float64, false),
-            Field::new("low", DataType::Float64, false),
-            Field::new("close", DataType::Float64, false),
+    ///
+    /// Optional columns are nullable and only present when requested, and
+    /// are followed by a nullable column per indicator and the volume
+    /// profile column if `profiles` is set.
+    fn ohlcv_schema(
+        &self,
+        columns: OhlcvColumns,
+        indicators: &[IndicatorColumn],
+        profiles: bool,
+    ) -> Schema {
+        let mut fields = vec![
+            self.timestamp_field(),
+            self.price_field("open"),
+            self.price_field("high"),
+            self.price_field("low"),
+            self.price_field("close"),
             Field::new("volume", DataType::Float64, false),
             Field::new("tick_count", DataType::UInt32, false),
+        ];
+        if columns.side_volumes {
+            fields.extend([
+                Field::new("ask_volume", DataType::Float64, true),
+                Field::new("bid_volume", DataType::Float64, true),
+                Field::new("imbalance", DataType::Float64, true),
+            ]);
+        }
+        if columns.tick_times {
+            let tick_ts = self.timestamp_type();
+            fields.extend([
+                Field::new("first_tick_ts", tick_ts.clone(), true),
+                Field::new("last_tick_ts", tick_ts, true),
+                Field::new("is_partial", DataType::Boolean, false),
+            ]);
+        }
+        if columns.price_stats {
+            fields.extend([
+                Field::new("vwap", DataType::Float64, true),
+                Field::new("avg_spread", DataType::Float64, true),
+                Field::new("max_spread", DataType::Float64, true),
+            ]);
+        }
+        if columns.microstructure {
+            fields.extend([
+                Field::new("tick_rate", DataType::Float64, true),
+                Field::new("bid_changes", DataType::UInt32, true),
+                Field::new("ask_changes", DataType::UInt32, true),
+                Field::new("twa_spread", DataType::Float64, true),
+            ]);
+        }
+        fields.extend(
+            indicators
+                .iter()
+                .map(|column| Field::new(&column.name, DataType::Float64, true)),
+        );
+        if profiles {
+            fields.push(Field::new(
+                "volume_profile",
+                DataType::List(Arc::new(price_level_field())),
+                false,
+            ));
+        }
+        self.schema(fields)
+    }
+
+    /// Creates the Arrow schema for return series.
+    fn returns_schema(&self) -> Schema {
+        self.schema(vec![
+            self.timestamp_field(),
+            Field::new("return", DataType::Float64, false),
+            Field::new("tick_count", DataType::UInt32, false),
         ])
     }
 
+    /// Creates the Arrow schema for bid/ask bars, with `is_partial` if
+    /// `partial`.
+    fn bid_ask_schema(&self, partial: bool) -> Schema {
+        let mut fields = vec![self.timestamp_field()];
+        fields.extend(
+            [
+                "bid_open",
+                "bid_high",
+                "bid_low",
+                "bid_close",
+                "ask_open",
+                "ask_high",
+                "ask_low",
+                "ask_close",
+            ]
+            .map(|name| self.price_field(name)),
+        );
+        fields.extend([
+            Field::new("bid_volume", DataType::Float64, false),
+            Field::new("ask_volume", DataType::Float64, false),
+            Field::new("tick_count", DataType::UInt32, false),
+        ]);
+        if partial {
+            fields.push(Field::new("is_partial", DataType::Boolean, false));
+        }
+        self.schema(fields)
+    }
+
     /// Converts ticks to Arrow RecordBatch.
-    fn ticks_to_batch(ticks: &[Tick]) -> Result<RecordBatch, FormatError> {
-        let timestamps: Vec<_> = ticks
-            .iter()
-            .map(|t| t.timestamp.timestamp_micros())
-            .collect();
-        let asks: Vec<_> = ticks.iter().map(|t| t.ask).collect();
-        let bids: Vec<_> = ticks.iter().map(|t| t.bid).collect();
+    fn ticks_to_batch(&self, ticks: &[Tick]) -> Result<RecordBatch, FormatError> {
+        let timestamps = self.timestamp_array(ticks.iter().map(|t| t.timestamp));
         let ask_vols: Vec<_> = ticks.iter().map(|t| t.ask_volume).collect();
         let bid_vols: Vec<_> = ticks.iter().map(|t| t.bid_volume).collect();
 
-        RecordBatch::try_new(
-            Arc::new(Self::tick_schema()),
-            vec![
-                Arc::new(TimestampMicrosecondArray::from(timestamps).with_timezone("UTC")),
-                Arc::new(Float64Array::from(asks)),
-                Arc::new(Float64Array::from(bids)),
-                Arc::new(Float32Array::from(ask_vols)),
-                Arc::new(Float32Array::from(bid_vols)),
-            ],
-        )
-        .map_err(|e| FormatError::Parquet(e.to_string()))
+        let mut columns = vec![
+            timestamps,
+            self.price_array(ticks.iter().map(|t| t.ask))?,
+            self.price_array(ticks.iter().map(|t| t.bid))?,
+            Arc::new(Float32Array::from(ask_vols)) as ArrayRef,
+            Arc::new(Float32Array::from(bid_vols)),
+        ];
+        if self.derived.spread {
+            columns.push(Arc::new(Float64Array::from_iter_values(
+                ticks.iter().map(Tick::spread),
+            )));
+        }
+        if self.derived.mid {
+            columns.push(Arc::new(Float64Array::from_iter_values(
+                ticks.iter().map(Tick::mid),
+            )));
+        }
+        self.record_batch(Arc::new(self.tick_schema()), columns)
     }
 
-    /// Converts OHLCV bars to Arrow RecordBatch.
-    fn ohlcv_to_batch(bars: &[Ohlcv]) -> Result<RecordBatch, FormatError> {
-        let timestamps: Vec<_> = bars
-            .iter()
-            .map(|b| b.timestamp.timestamp_micros())
-            .collect();
-        let opens: Vec<_> = bars.iter().map(|b| b.open).collect();
-        let highs: Vec<_> = bars.iter().map(|b| b.high).collect();
-        let lows: Vec<_> = bars.iter().map(|b| b.low).collect();
-        let closes: Vec<_> = bars.iter().map(|b| b.close).collect();
+    /// Converts OHLCV bars to Arrow RecordBatch, taking indicator values and
+    /// any volume profiles from `offset` on.
+    fn ohlcv_to_batch(
+        &self,
+        bars: &[Ohlcv],
+        schema: &Arc<Schema>,
+        optional: OhlcvColumns,
+        indicators: &[IndicatorColumn],
+        profiles: Option<&[VolumeProfile]>,
+        offset: usize,
+    ) -> Result<RecordBatch, FormatError> {
+        let timestamps = self.timestamp_array(bars.iter().map(|b| b.timestamp));
         let volumes: Vec<_> = bars.iter().map(|b| b.volume).collect();
         let tick_counts: Vec<_> = bars.iter().map(|b| b.tick_count).collect();
 
-        RecordBatch::try_new(
-            Arc::new(Self::ohlcv_schema()),
+        let mut columns: Vec<ArrayRef> = vec![
+            timestamps,
+            self.price_array(bars.iter().map(|b| b.open))?,
+            self.price_array(bars.iter().map(|b| b.high))?,
+            self.price_array(bars.iter().map(|b| b.low))?,
+            self.price_array(bars.iter().map(|b| b.close))?,
+            Arc::new(Float64Array::from(volumes)),
+            Arc::new(UInt32Array::from(tick_counts)),
+        ];
+        if optional.side_volumes {
+            let ask_vols: Vec<_> = bars.iter().map(|b| b.ask_volume).collect();
+            let bid_vols: Vec<_> = bars.iter().map(|b| b.bid_volume).collect();
+            let imbalances: Vec<_> = bars.iter().map(|b| b.imbalance).collect();
+            columns.extend([
+                Arc::new(Float64Array::from(ask_vols)) as ArrayRef,
+                Arc::new(Float64Array::from(bid_vols)),
+                Arc::new(Float64Array::from(imbalances)),
+            ]);
+        }
+        if optional.tick_times {
+            let partial: Vec<_> = bars.iter().map(|b| b.is_partial).collect();
+            columns.extend([
+                self.optional_timestamp_array(bars.iter().map(|b| b.first_tick_ts)),
+                self.optional_timestamp_array(bars.iter().map(|b| b.last_tick_ts)),
+                Arc::new(BooleanArray::from(partial)),
+            ]);
+        }
+        if optional.price_stats {
+            let vwaps: Vec<_> = bars.iter().map(|b| b.vwap).collect();
+            let avg_spreads: Vec<_> = bars.iter().map(|b| b.avg_spread).collect();
+            let max_spreads: Vec<_> = bars.iter().map(|b| b.max_spread).collect();
+            columns.extend([
+                Arc::new(Float64Array::from(vwaps)) as ArrayRef,
+                Arc::new(Float64Array::from(avg_spreads)),
+                Arc::new(Float64Array::from(max_spreads)),
+            ]);
+        }
+        if optional.microstructure {
+            let tick_rates: Vec<_> = bars.iter().map(|b| b.tick_rate).collect();
+            let bid_changes: Vec<_> = bars.iter().map(|b| b.bid_changes).collect();
+            let ask_changes: Vec<_> = bars.iter().map(|b| b.ask_changes).collect();
+            let twa_spreads: Vec<_> = bars.iter().map(|b| b.twa_spread).collect();
+            columns.extend([
+                Arc::new(Float64Array::from(tick_rates)) as ArrayRef,
+                Arc::new(UInt32Array::from(bid_changes)),
+                Arc::new(UInt32Array::from(ask_changes)),
+                Arc::new(Float64Array::from(twa_spreads)),
+            ]);
+        }
+        for column in indicators {
+            let values = &column.values[offset..offset + bars.len()];
+            columns.push(Arc::new(Float64Array::from(values.to_vec())));
+        }
+        if let Some(profiles) = profiles {
+            columns.push(profile_array(&profiles[offset..offset + bars.len()])?);
+        }
+
+        self.record_batch(Arc::clone(schema), columns)
+    }
+
+    /// Converts bid/ask bars to Arrow RecordBatch, with `is_partial` if
+    /// `partial`.
+    fn bid_ask_to_batch(
+        &self,
+        bars: &[OhlcvBidAsk],
+        schema: &Arc<Schema>,
+        partial: bool,
+    ) -> Result<RecordBatch, FormatError> {
+        let prices = |price: fn(&OhlcvBidAsk) -> f64| self.price_array(bars.iter().map(price));
+        let mut columns = vec![
+            self.timestamp_array(bars.iter().map(|b| b.timestamp)),
+            prices(|b| b.bid_open)?,
+            prices(|b| b.bid_high)?,
+            prices(|b| b.bid_low)?,
+            prices(|b| b.bid_close)?,
+            prices(|b| b.ask_open)?,
+            prices(|b| b.ask_high)?,
+            prices(|b| b.ask_low)?,
+            prices(|b| b.ask_close)?,
+            Arc::new(Float64Array::from_iter_values(
+                bars.iter().map(|b| b.bid_volume),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                bars.iter().map(|b| b.ask_volume),
+            )),
+            Arc::new(UInt32Array::from_iter_values(
+                bars.iter().map(|b| b.tick_count),
+            )),
+        ];
+        if partial {
+            columns.push(Arc::new(BooleanArray::from_iter(
+                bars.iter().map(|b| Some(b.is_partial)),
+            )));
+        }
+        self.record_batch(Arc::clone(schema), columns)
+    }
+
+    /// Converts a return series to Arrow RecordBatch.
+    fn returns_to_batch(
+        &self,
+        returns: &[PriceReturn],
+        schema: &Arc<Schema>,
+    ) -> Result<RecordBatch, FormatError> {
+        let timestamps = self.timestamp_array(returns.iter().map(|r| r.timestamp));
+        let values: Vec<_> = returns.iter().map(|r| r.value).collect();
+        let tick_counts: Vec<_> = returns.iter().map(|r| r.tick_count).collect();
+
+        self.record_batch(
+            Arc::clone(schema),
             vec![
-                Arc::new(TimestampMicrosecondArray::from(timestamps).with_timezone("UTC")),
-                Arc::new(Float64Array::from(opens)),
-                Arc::new(Float64Array::from(highs)),
-                Arc::new(Float64Array::from(lows)),
-                Arc::new(Float64Array::from(closes)),
-                Arc::new(Float64Array::from(volumes)),
+                timestamps,
+                Arc::new(Float64Array::from(values)),
                 Arc::new(UInt32Array::from(tick_counts)),
             ],
         )
-        .map_err(|e| FormatError::Parquet(e.to_string()))
+    }
+}
+
+/// In-memory Arrow conversion.
+///
+/// These build the record batches the formatter writes, with the same
+/// schema, symbol column, price encoding and timestamp precision, for
+/// Arrow-based engines such as DataFusion or Polars to use without a file
+/// round trip.
+impl ParquetFormatter {
+    /// Converts ticks to one Arrow record batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::MissingDecimalFactor`] if an exact price
+    /// encoding has no decimal factor, or [`FormatError::Parquet`] if the
+    /// batch cannot be built.
+    pub fn ticks_to_record_batch(&self, ticks: &[Tick]) -> Result<RecordBatch, FormatError> {
+        self.check_price_encoding()?;
+        self.ticks_to_batch(ticks)
+    }
+
+    /// Converts bars to one Arrow record batch, with a nullable column per
+    /// indicator after the bar columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, [`FormatError::MissingDecimalFactor`] if an exact
+    /// price encoding has no decimal factor, or [`FormatError::Parquet`] if
+    /// the batch cannot be built.
+    pub fn ohlcv_to_record_batch(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+    ) -> Result<RecordBatch, FormatError> {
+        check_indicators(bars, indicators)?;
+        self.check_price_encoding()?;
+        let columns = OhlcvColumns::of(bars);
+        let schema = Arc::new(self.ohlcv_schema(columns, indicators, false));
+        self.ohlcv_to_batch(bars, &schema, columns, indicators, None, 0)
+    }
+
+    /// Converts bid/ask bars to one Arrow record batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::MissingDecimalFactor`] if an exact price
+    /// encoding has no decimal factor, or [`FormatError::Parquet`] if the
+    /// batch cannot be built.
+    pub fn bid_ask_to_record_batch(
+        &self,
+        bars: &[OhlcvBidAsk],
+    ) -> Result<RecordBatch, FormatError> {
+        self.check_price_encoding()?;
+        let partial = bars.iter().any(|bar| bar.is_partial);
+        self.bid_ask_to_batch(bars, &Arc::new(self.bid_ask_schema(partial)), partial)
+    }
+
+    /// Converts a return series to one Arrow record batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Parquet`] if the batch cannot be built.
+    pub fn returns_to_record_batch(
+        &self,
+        returns: &[PriceReturn],
+    ) -> Result<RecordBatch, FormatError> {
+        self.returns_to_batch(returns, &Arc::new(self.returns_schema()))
     }
 }
 
 impl Formatter for ParquetFormatter {
     fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
-        let schema = Arc::new(Self::tick_schema());
-        let props = WriterProperties::builder()
-            .set_compression(self.compression)
-            .set_max_row_group_size(self.row_group_size)
-            .build();
+        self.check_price_encoding()?;
+        let schema = Arc::new(self.tick_schema());
+        let props = self.writer_properties(ticks.is_sorted_by_key(|tick| tick.timestamp));
 
         let mut arrow_writer = ArrowWriter::try_new(writer, schema, Some(props))
             .map_err(|e| FormatError::Parquet(e.to_string()))?;
 
         // Write in batches
         for chunk in ticks.chunks(self.row_group_size) {
-            let batch = Self::ticks_to_batch(chunk)?;
+            let batch = self.ticks_to_batch(chunk)?;
             arrow_writer
                 .write(&batch)
                 .map_err(|e| FormatError::Parquet(e.to_string()))?;
@@ -164,18 +789,70 @@ impl Formatter for ParquetFormatter {
     }
 
     fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
-        let schema = Arc::new(Self::ohlcv_schema());
-        let props = WriterProperties::builder()
-            .set_compression(self.compression)
-            .set_max_row_group_size(self.row_group_size)
-            .build();
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
 
-        let mut arrow_writer = ArrowWriter::try_new(writer, schema, Some(props))
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        self.write_bars(bars, indicators, None, writer)
+    }
+
+    fn write_ohlcv_with_profiles<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        profiles: &[VolumeProfile],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_profiles(bars, profiles)?;
+        self.write_bars(bars, &[], Some(profiles), writer)
+    }
+
+    fn write_bid_ask_bars<W: Write + Send>(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        self.check_price_encoding()?;
+        let partial = bars.iter().any(|bar| bar.is_partial);
+        let schema = Arc::new(self.bid_ask_schema(partial));
+        let props = self.writer_properties(bars.is_sorted_by_key(|bar| bar.timestamp));
+
+        let mut arrow_writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(props))
             .map_err(|e| FormatError::Parquet(e.to_string()))?;
 
-        // Write in batches
         for chunk in bars.chunks(self.row_group_size) {
-            let batch = Self::ohlcv_to_batch(chunk)?;
+            let batch = self.bid_ask_to_batch(chunk, &schema, partial)?;
+            arrow_writer
+                .write(&batch)
+                .map_err(|e| FormatError::Parquet(e.to_string()))?;
+        }
+
+        arrow_writer
+            .close()
+            .map_err(|e| FormatError::Parquet(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let schema = Arc::new(self.returns_schema());
+        let props = self.writer_properties(returns.is_sorted_by_key(|r| r.timestamp));
+
+        let mut arrow_writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(props))
+            .map_err(|e| FormatError::Parquet(e.to_string()))?;
+
+        // Write in batches
+        for chunk in returns.chunks(self.row_group_size) {
+            let batch = self.returns_to_batch(chunk, &schema)?;
             arrow_writer
                 .write(&batch)
                 .map_err(|e| FormatError::Parquet(e.to_string()))?;
@@ -193,6 +870,219 @@ impl Formatter for ParquetFormatter {
     }
 }
 
+impl ParquetFormatter {
+    /// Writes bars with extra columns already checked to have one value per
+    /// bar.
+    fn write_bars<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        profiles: Option<&[VolumeProfile]>,
+        writer: W,
+    ) -> Result<(), FormatError> {
+        self.check_price_encoding()?;
+        let columns = OhlcvColumns::of(bars);
+        let schema = Arc::new(self.ohlcv_schema(columns, indicators, profiles.is_some()));
+        let props = self.writer_properties(bars.is_sorted_by_key(|bar| bar.timestamp));
+
+        let mut arrow_writer = ArrowWriter::try_new(writer, Arc::clone(&schema), Some(props))
+            .map_err(|e| FormatError::Parquet(e.to_string()))?;
+
+        // Write in batches
+        for (i, chunk) in bars.chunks(self.row_group_size).enumerate() {
+            let offset = i * self.row_group_size;
+            let batch =
+                self.ohlcv_to_batch(chunk, &schema, columns, indicators, profiles, offset)?;
+            arrow_writer
+                .write(&batch)
+                .map_err(|e| FormatError::Parquet(e.to_string()))?;
+        }
+
+        arrow_writer
+            .close()
+            .map_err(|e| FormatError::Parquet(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+impl StreamingFormatter for ParquetFormatter {
+    type TickWriter<W: Write + Send> = ParquetBatchWriter<W, Tick>;
+    type OhlcvWriter<W: Write + Send> = ParquetBatchWriter<W, Ohlcv>;
+
+    fn tick_writer<W: Write + Send>(&self, writer: W) -> Result<Self::TickWriter<W>, FormatError> {
+        self.check_price_encoding()?;
+        let mut batch_writer = ParquetBatchWriter::new(self.clone(), writer);
+        batch_writer.open(Arc::new(self.tick_schema()))?;
+        Ok(batch_writer)
+    }
+
+    fn ohlcv_writer<W: Write + Send>(
+        &self,
+        writer: W,
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        self.check_price_encoding()?;
+        Ok(ParquetBatchWriter::new(self.clone(), writer))
+    }
+
+    fn ohlcv_writer_for<W: Write + Send>(
+        &self,
+        writer: W,
+        bars: &[Ohlcv],
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        Ok(self
+            .ohlcv_writer(writer)?
+            .with_columns(OhlcvColumns::of(bars)))
+    }
+}
+
+/// Incremental Parquet writer created by [`ParquetFormatter`].
+///
+/// Rows are buffered into row groups of the configured size; the footer is
+/// written by [`finish`](BatchWriter::finish).
+#[derive(Debug)]
+pub struct ParquetBatchWriter<W: Write + Send, T> {
+    formatter: ParquetFormatter,
+    /// Output until the Arrow writer is opened.
+    sink: Option<W>,
+    /// Arrow writer, opened once the schema is known.
+    arrow_writer: Option<ArrowWriter<W>>,
+    /// Bar columns, fixed by the first non-empty batch of bars.
+    columns: Option<OhlcvColumns>,
+    records: PhantomData<fn(&T)>,
+}
+
+impl<W: Write + Send, T> ParquetBatchWriter<W, T> {
+    /// Wraps `writer` without opening it.
+    fn new(formatter: ParquetFormatter, writer: W) -> Self {
+        Self {
+            formatter,
+            sink: Some(writer),
+            arrow_writer: None,
+            columns: None,
+            records: PhantomData,
+        }
+    }
+
+    /// Returns the Arrow writer, opening it with `schema` on first use.
+    fn open(&mut self, schema: Arc<Schema>) -> Result<&mut ArrowWriter<W>, FormatError> {
+        if let Some(sink) = self.sink.take() {
+            let props = self.formatter.writer_properties(false);
+            let arrow_writer = ArrowWriter::try_new(sink, schema, Some(props))
+                .map_err(|e| FormatError::Parquet(e.to_string()))?;
+            self.arrow_writer = Some(arrow_writer);
+        }
+        self.arrow_writer
+            .as_mut()
+            .ok_or_else(|| FormatError::Parquet("writer failed to open".to_string()))
+    }
+
+    /// Writes a record batch, opening the writer with its schema on first
+    /// use.
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), FormatError> {
+        self.open(batch.schema())?
+            .write(batch)
+            .map_err(|e| FormatError::Parquet(e.to_string()))
+    }
+
+    /// Writes the footer.
+    fn close(self) -> Result<(), FormatError> {
+        if let Some(arrow_writer) = self.arrow_writer {
+            arrow_writer
+                .close()
+                .map_err(|e| FormatError::Parquet(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> ParquetBatchWriter<W, Ohlcv> {
+    /// Fixes the bar columns up front instead of taking those of the
+    /// first batch.
+    pub(crate) const fn with_columns(mut self, columns: OhlcvColumns) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Returns the bar columns and schema, fixing the columns to those of
+    /// `bars` on first use.
+    fn bar_schema(&mut self, bars: &[Ohlcv]) -> (OhlcvColumns, Arc<Schema>) {
+        let columns = *self.columns.get_or_insert_with(|| OhlcvColumns::of(bars));
+        let schema = Arc::new(self.formatter.ohlcv_schema(columns, &[], false));
+        (columns, schema)
+    }
+}
+
+impl<W: Write + Send> BatchWriter<Tick> for ParquetBatchWriter<W, Tick> {
+    fn write_batch(&mut self, ticks: &[Tick]) -> Result<(), FormatError> {
+        if ticks.is_empty() {
+            return Ok(());
+        }
+        let batch = self.formatter.ticks_to_batch(ticks)?;
+        self.write(&batch)
+    }
+
+    fn finish(self) -> Result<(), FormatError> {
+        self.close()
+    }
+}
+
+impl<W: Write + Send> BatchWriter<Ohlcv> for ParquetBatchWriter<W, Ohlcv> {
+    fn write_batch(&mut self, bars: &[Ohlcv]) -> Result<(), FormatError> {
+        if bars.is_empty() {
+            return Ok(());
+        }
+        let (columns, schema) = self.bar_schema(bars);
+        let batch = self
+            .formatter
+            .ohlcv_to_batch(bars, &schema, columns, &[], None, 0)?;
+        self.write(&batch)
+    }
+
+    fn finish(mut self) -> Result<(), FormatError> {
+        let (_, schema) = self.bar_schema(&[]);
+        self.open(schema)?;
+        self.close()
+    }
+}
+
+/// Returns the fields of one price level of a volume profile.
+fn price_level_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("price", DataType::Float64, false),
+        Field::new("volume", DataType::Float64, false),
+        Field::new("tick_count", DataType::UInt32, false),
+    ])
+}
+
+/// Returns the item field of the `volume_profile` list column.
+fn price_level_field() -> Field {
+    Field::new("item", DataType::Struct(price_level_fields()), false)
+}
+
+/// Converts volume profiles to a list-of-struct column.
+fn profile_array(profiles: &[VolumeProfile]) -> Result<ArrayRef, FormatError> {
+    let levels = || profiles.iter().flat_map(|profile| &profile.levels);
+    let prices: Float64Array = levels().map(|level| Some(level.price)).collect();
+    let volumes: Float64Array = levels().map(|level| Some(level.volume)).collect();
+    let tick_counts: UInt32Array = levels().map(|level| Some(level.tick_count)).collect();
+    let values = StructArray::try_new(
+        price_level_fields(),
+        vec![Arc::new(prices), Arc::new(volumes), Arc::new(tick_counts)],
+        None,
+    )
+    .map_err(|e| FormatError::Parquet(e.to_string()))?;
+    let offsets = OffsetBuffer::from_lengths(profiles.iter().map(|profile| profile.levels.len()));
+    let list = ListArray::try_new(
+        Arc::new(price_level_field()),
+        offsets,
+        Arc::new(values),
+        None,
+    )
+    .map_err(|e| FormatError::Parquet(e.to_string()))?;
+    Ok(Arc::new(list))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,19 +1108,495 @@ mod tests {
         assert_eq!(&data[0..4], b"PAR1");
     }
 
+    #[test]
+    fn test_record_batches() {
+        use arrow::array::Array;
+
+        let formatter = ParquetFormatter::new().with_symbol("eurusd");
+        let batch = formatter
+            .ticks_to_record_batch(&[create_test_tick(), create_test_tick()])
+            .unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().field(0).name(), "symbol");
+        let asks = batch
+            .column_by_name("ask")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(asks.value(1), 1.1001);
+
+        let bar = Ohlcv::new(create_test_tick().timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None],
+        };
+        let batch = formatter
+            .ohlcv_to_record_batch(&[bar], std::slice::from_ref(&sma))
+            .unwrap();
+        assert_eq!(batch.num_columns(), 9);
+        assert!(batch.column_by_name("sma_2").unwrap().is_null(0));
+
+        let err = formatter.ohlcv_to_record_batch(&[bar, bar], &[sma]);
+        assert!(matches!(err, Err(FormatError::ColumnLength { .. })));
+    }
+
     #[test]
     fn test_tick_schema() {
-        let schema = ParquetFormatter::tick_schema();
+        let schema = ParquetFormatter::new().tick_schema();
         assert_eq!(schema.fields().len(), 5);
         assert!(schema.field_with_name("timestamp").is_ok());
         assert!(schema.field_with_name("ask").is_ok());
     }
 
+    #[test]
+    fn test_derived_tick_columns() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let ticks = [Tick::new(timestamp, 1.5, 1.25, 1.0, 1.0)];
+
+        let batch = ParquetFormatter::new()
+            .with_derived_columns(DerivedColumns::new().with_spread().with_mid())
+            .ticks_to_record_batch(&ticks)
+            .unwrap();
+
+        let schema = batch.schema();
+        assert_eq!(schema.fields().len(), 7);
+        let column = |name: &str| {
+            let index = schema.index_of(name).unwrap();
+            batch
+                .column(index)
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap()
+                .value(0)
+        };
+        assert_eq!(column("spread"), 0.25);
+        assert_eq!(column("mid"), 1.375);
+    }
+
     #[test]
     fn test_ohlcv_schema() {
-        let schema = ParquetFormatter::ohlcv_schema();
+        let schema = ParquetFormatter::new().ohlcv_schema(OhlcvColumns::default(), &[], false);
         assert_eq!(schema.fields().len(), 7);
         assert!(schema.field_with_name("open").is_ok());
         assert!(schema.field_with_name("close").is_ok());
+
+        let schema = ParquetFormatter::new().ohlcv_schema(
+            OhlcvColumns {
+                side_volumes: true,
+                ..Default::default()
+            },
+            &[],
+            false,
+        );
+        assert_eq!(schema.fields().len(), 10);
+        assert!(schema.field_with_name("imbalance").unwrap().is_nullable());
+
+        let schema = ParquetFormatter::new().ohlcv_schema(
+            OhlcvColumns {
+                side_volumes: true,
+                tick_times: true,
+                price_stats: true,
+                microstructure: true,
+                subsecond: false,
+            },
+            &[],
+            false,
+        );
+        assert_eq!(schema.fields().len(), 20);
+        assert!(schema.field_with_name("vwap").unwrap().is_nullable());
+        assert_eq!(
+            schema.field_with_name("bid_changes").unwrap().data_type(),
+            &DataType::UInt32
+        );
+        assert!(
+            schema
+                .field_with_name("first_tick_ts")
+                .unwrap()
+                .is_nullable()
+        );
+        assert!(!schema.field_with_name("is_partial").unwrap().is_nullable());
+
+        let atr = IndicatorColumn {
+            name: "atr_14".to_string(),
+            values: Vec::new(),
+        };
+        let schema = ParquetFormatter::new().ohlcv_schema(OhlcvColumns::default(), &[atr], false);
+        assert_eq!(schema.fields().len(), 8);
+        assert!(schema.field_with_name("atr_14").unwrap().is_nullable());
+    }
+
+    #[test]
+    fn test_parquet_indicators_across_row_groups() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2); 5];
+        let sma = IndicatorColumn {
+            name: "sma_3".to_string(),
+            values: vec![None, None, Some(1.0), Some(2.0), Some(3.0)],
+        };
+        let mut output = Vec::new();
+        ParquetFormatter::new()
+            .with_row_group_size(2)
+            .write_ohlcv_with_indicators(&bars, &[sma], &mut output)
+            .unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output))
+            .unwrap()
+            .build()
+            .unwrap();
+        let values: Vec<_> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("sma_3").unwrap();
+                let column = column.as_any().downcast_ref::<Float64Array>().unwrap();
+                column.iter().collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(values, [None, None, Some(1.0), Some(2.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn test_parquet_streaming_bars() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars: Vec<_> = (0..5)
+            .map(|i| Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, i).with_side_volumes(1.0, 3.0))
+            .collect();
+        let formatter = ParquetFormatter::new().with_row_group_size(2);
+        let mut output = Vec::new();
+        let mut writer = formatter.ohlcv_writer(&mut output).unwrap();
+        writer.write_batch(&bars[..2]).unwrap();
+        writer.write_batch(&[]).unwrap();
+        writer.write_batch(&bars[2..]).unwrap();
+        writer.finish().unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output)).unwrap();
+        assert_eq!(builder.metadata().num_row_groups(), 3);
+        assert!(builder.schema().field_with_name("ask_volume").is_ok());
+        let tick_counts: Vec<_> = builder
+            .build()
+            .unwrap()
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("tick_count").unwrap();
+                let column = column.as_any().downcast_ref::<UInt32Array>().unwrap();
+                column.values().to_vec()
+            })
+            .collect();
+        assert_eq!(tick_counts, [0, 1, 2, 3, 4]);
+
+        let mut output = Vec::new();
+        formatter
+            .tick_writer(&mut output)
+            .unwrap()
+            .finish()
+            .unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output)).unwrap();
+        assert_eq!(builder.schema().as_ref(), &formatter.tick_schema());
+        assert_eq!(builder.metadata().file_metadata().num_rows(), 0);
+    }
+
+    #[test]
+    fn test_parquet_volume_profiles() {
+        use arrow::array::Array;
+        use paracas_aggregate::PriceLevel;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = vec![Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2); 3];
+        let level = |price, volume| PriceLevel {
+            price,
+            volume,
+            tick_count: 1,
+        };
+        let profiles = vec![
+            VolumeProfile {
+                levels: vec![level(1.1, 1.0), level(1.2, 3.0)],
+            },
+            VolumeProfile::default(),
+            VolumeProfile {
+                levels: vec![level(1.0, 2.0)],
+            },
+        ];
+        let mut output = Vec::new();
+        ParquetFormatter::new()
+            .with_row_group_size(2)
+            .write_ohlcv_with_profiles(&bars, &profiles, &mut output)
+            .unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output))
+            .unwrap()
+            .build()
+            .unwrap();
+        let lengths: Vec<_> = reader
+            .flat_map(|batch| {
+                let batch = batch.unwrap();
+                let column = batch.column_by_name("volume_profile").unwrap();
+                let list = column.as_any().downcast_ref::<ListArray>().unwrap();
+                (0..list.len())
+                    .map(|row| list.value(row).len())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(lengths, [2, 0, 1]);
+    }
+
+    #[test]
+    fn test_parquet_returns() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap();
+        let returns = vec![PriceReturn {
+            timestamp,
+            value: 0.001,
+            tick_count: 7,
+        }];
+        let mut output = Cursor::new(Vec::new());
+
+        ParquetFormatter::new()
+            .write_returns(&returns, &mut output)
+            .unwrap();
+        assert!(!output.into_inner().is_empty());
+
+        let schema = ParquetFormatter::new().returns_schema();
+        assert_eq!(schema.fields().len(), 3);
+        assert!(schema.field_with_name("return").is_ok());
+    }
+
+    #[test]
+    fn test_parquet_bid_ask_bars() {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let bar = OhlcvBidAsk {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 15, 13, 0, 0).unwrap(),
+            bid_open: 1.1,
+            bid_high: 1.2,
+            bid_low: 1.0,
+            bid_close: 1.15,
+            ask_open: 1.1001,
+            ask_high: 1.2001,
+            ask_low: 1.0001,
+            ask_close: 1.1501,
+            bid_volume: 3.0,
+            ask_volume: 1.5,
+            tick_count: 2,
+            is_partial: true,
+        };
+        let mut output = Vec::new();
+
+        ParquetFormatter::new()
+            .write_bid_ask_bars(&[bar], &mut output)
+            .unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output))
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(Result::unwrap).collect();
+        let schema = batches[0].schema();
+        assert_eq!(schema.fields().len(), 13);
+        assert_eq!(schema.field(5).name(), "ask_open");
+        assert_eq!(schema.field(12).name(), "is_partial");
+        let ask_close = batches[0]
+            .column(8)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(ask_close.value(0), 1.1501);
+    }
+
+    #[test]
+    fn test_parquet_nanosecond_round_trip() {
+        use arrow::array::Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let nanos = 1_705_321_845_123_456_789;
+        let tick = Tick::from_epoch_nanos(nanos, 1.1001, 1.1000, 100.0, 200.0);
+        let formatter =
+            ParquetFormatter::new().with_timestamp_precision(TimestampPrecision::Nanosecond);
+        let mut output = Cursor::new(Vec::new());
+
+        formatter.write_ticks(&[tick], &mut output).unwrap();
+
+        let data = bytes::Bytes::from(output.into_inner());
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(data)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        let column = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .unwrap();
+        assert_eq!(column.len(), 1);
+        assert_eq!(column.value(0), nanos);
+    }
+
+    #[test]
+    fn test_parquet_millisecond_timezone_settings() {
+        let settings = ParquetSettings {
+            timestamp_precision: TimestampPrecision::Millisecond,
+            timezone: Tz::America__New_York,
+            ..ParquetSettings::default()
+        };
+        let formatter = ParquetFormatter::new().with_settings(&settings).unwrap();
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)
+            .with_tick_times(timestamp, timestamp)];
+
+        let batch = formatter.ohlcv_to_record_batch(&bars, &[]).unwrap();
+        let expected = DataType::Timestamp(TimeUnit::Millisecond, Some("America/New_York".into()));
+        let schema = batch.schema();
+        assert_eq!(schema.field(0).data_type(), &expected);
+        assert_eq!(
+            schema.field_with_name("first_tick_ts").unwrap().data_type(),
+            &expected
+        );
+        let column = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<TimestampMillisecondArray>()
+            .unwrap();
+        assert_eq!(column.value(0), timestamp.timestamp_millis());
+    }
+
+    #[test]
+    fn test_parquet_key_value_metadata() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let metadata = OutputMetadata::new()
+            .with_price_source("bid/ask")
+            .with_parameter("start", "2024-01-15");
+        let formatter = ParquetFormatter::new().with_metadata(metadata);
+        let mut output = Cursor::new(Vec::new());
+
+        formatter
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(output.into_inner())).unwrap();
+        let kv = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        let get = |k: &str| {
+            kv.iter()
+                .find(|entry| entry.key == k)
+                .and_then(|entry| entry.value.as_deref())
+        };
+        assert_eq!(get("paracas.price_source"), Some("bid/ask"));
+        assert_eq!(get("paracas.timezone"), Some("UTC"));
+        assert_eq!(get("paracas.param.start"), Some("2024-01-15"));
+    }
+
+    #[test]
+    fn test_parquet_settings() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let settings = ParquetSettings {
+            codec: ParquetCodec::Zstd,
+            level: Some(9),
+            sorting_columns: true,
+            ..ParquetSettings::default()
+        };
+        let formatter = ParquetFormatter::new().with_settings(&settings).unwrap();
+        let mut output = Cursor::new(Vec::new());
+        formatter
+            .write_ticks(&[create_test_tick(); 2], &mut output)
+            .unwrap();
+
+        let reader = SerializedFileReader::new(bytes::Bytes::from(output.into_inner())).unwrap();
+        let row_group = reader.metadata().row_group(0);
+        assert!(matches!(
+            row_group.column(0).compression(),
+            Compression::ZSTD(_)
+        ));
+        assert_eq!(row_group.sorting_columns().map(Vec::len), Some(1));
+
+        let invalid = ParquetSettings {
+            codec: ParquetCodec::Snappy,
+            level: Some(3),
+            ..ParquetSettings::default()
+        };
+        assert!(ParquetFormatter::new().with_settings(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parquet_symbol() {
+        use arrow::array::AsArray;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let formatter = ParquetFormatter::new()
+            .with_symbol("eurusd")
+            .with_sorting_columns(true);
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2); 2];
+        let mut output = Vec::new();
+        formatter.write_ohlcv(&bars, &mut output).unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(output)).unwrap();
+        let sorting = builder.metadata().row_group(0).sorting_columns().unwrap();
+        assert_eq!(sorting[0].column_idx, 1);
+        let batch = builder.build().unwrap().next().unwrap().unwrap();
+        assert_eq!(batch.schema().field(0).name(), "symbol");
+        assert_eq!(batch.schema().field(1).name(), "timestamp");
+        let symbols = batch.column(0).as_string::<i32>();
+        assert!(symbols.iter().all(|symbol| symbol == Some("eurusd")));
+    }
+
+    #[test]
+    fn test_parquet_exact_prices() {
+        use arrow::array::{Array, AsArray};
+        use arrow::datatypes::{Decimal128Type, Int64Type};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let read = |bytes: Vec<u8>| {
+            let builder =
+                ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(bytes)).unwrap();
+            builder.build().unwrap().next().unwrap().unwrap()
+        };
+        let metadata = OutputMetadata {
+            decimal_factor: Some(100_000),
+            ..OutputMetadata::new()
+        };
+
+        let mut output = Vec::new();
+        ParquetFormatter::new()
+            .with_metadata(metadata.clone())
+            .with_price_encoding(PriceEncoding::Integer)
+            .write_ticks(&[create_test_tick()], &mut output)
+            .unwrap();
+        let batch = read(output);
+        let asks = batch.column(1).as_primitive::<Int64Type>();
+        assert_eq!(asks.value(0), 110_010);
+
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bars = [
+            Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2),
+            Ohlcv::new(timestamp, f64::NAN, f64::NAN, f64::NAN, f64::NAN, 0.0, 0),
+        ];
+        let mut output = Vec::new();
+        ParquetFormatter::new()
+            .with_metadata(metadata)
+            .with_price_encoding(PriceEncoding::Decimal)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        let batch = read(output);
+        assert_eq!(
+            batch.schema().field(4).data_type(),
+            &DataType::Decimal128(18, 5)
+        );
+        let closes = batch.column(4).as_primitive::<Decimal128Type>();
+        assert_eq!(closes.value_as_string(0), "1.15000");
+        assert!(closes.is_null(1));
+
+        let err = ParquetFormatter::new()
+            .with_price_encoding(PriceEncoding::Integer)
+            .write_ticks(&[create_test_tick()], Vec::new())
+            .unwrap_err();
+        assert!(matches!(err, FormatError::MissingDecimalFactor(_)));
     }
 }
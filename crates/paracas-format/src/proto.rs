@@ -0,0 +1,124 @@
+//! Protobuf messages of [`ProtobufFormatter`](crate::ProtobufFormatter)
+//! output.
+//!
+//! The messages mirror [`PROTO_SCHEMA`], the `paracas.v1` schema shipped
+//! in the crate as `proto/paracas.proto`, so Rust consumers can decode
+//! output with prost directly and other languages can generate code from
+//! the schema.
+
+use prost::Message;
+use std::collections::BTreeMap;
+
+/// Protobuf schema of the messages, in `.proto` syntax.
+pub const PROTO_SCHEMA: &str = include_str!("../proto/paracas.proto");
+
+/// A bid/ask quote (`paracas.v1.Tick`).
+#[derive(Clone, PartialEq, Message)]
+pub struct Tick {
+    /// Instrument, if requested.
+    #[prost(string, optional, tag = "1")]
+    pub symbol: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+    /// Ask price.
+    #[prost(double, tag = "3")]
+    pub ask: f64,
+    /// Bid price.
+    #[prost(double, tag = "4")]
+    pub bid: f64,
+    /// Ask volume.
+    #[prost(float, tag = "5")]
+    pub ask_volume: f32,
+    /// Bid volume.
+    #[prost(float, tag = "6")]
+    pub bid_volume: f32,
+}
+
+/// An OHLCV bar (`paracas.v1.Ohlcv`).
+#[derive(Clone, PartialEq, Message)]
+pub struct Ohlcv {
+    /// Instrument, if requested.
+    #[prost(string, optional, tag = "1")]
+    pub symbol: Option<String>,
+    /// Start of the bar, in milliseconds since the Unix epoch.
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+    /// Open price.
+    #[prost(double, tag = "3")]
+    pub open: f64,
+    /// High price.
+    #[prost(double, tag = "4")]
+    pub high: f64,
+    /// Low price.
+    #[prost(double, tag = "5")]
+    pub low: f64,
+    /// Close price.
+    #[prost(double, tag = "6")]
+    pub close: f64,
+    /// Total volume.
+    #[prost(double, tag = "7")]
+    pub volume: f64,
+    /// Number of ticks in the bar.
+    #[prost(uint32, tag = "8")]
+    pub tick_count: u32,
+    /// Ask-side volume.
+    #[prost(double, optional, tag = "9")]
+    pub ask_volume: Option<f64>,
+    /// Bid-side volume.
+    #[prost(double, optional, tag = "10")]
+    pub bid_volume: Option<f64>,
+    /// Side volume imbalance.
+    #[prost(double, optional, tag = "11")]
+    pub imbalance: Option<f64>,
+    /// Time of the first tick, in milliseconds since the Unix epoch.
+    #[prost(int64, optional, tag = "12")]
+    pub first_tick_ts: Option<i64>,
+    /// Time of the last tick, in milliseconds since the Unix epoch.
+    #[prost(int64, optional, tag = "13")]
+    pub last_tick_ts: Option<i64>,
+    /// Whether more ticks for the bar's period may follow in later data.
+    #[prost(bool, tag = "14")]
+    pub is_partial: bool,
+    /// Volume-weighted average price.
+    #[prost(double, optional, tag = "15")]
+    pub vwap: Option<f64>,
+    /// Average bid-ask spread.
+    #[prost(double, optional, tag = "16")]
+    pub avg_spread: Option<f64>,
+    /// Largest bid-ask spread.
+    #[prost(double, optional, tag = "17")]
+    pub max_spread: Option<f64>,
+    /// Ticks per second.
+    #[prost(double, optional, tag = "18")]
+    pub tick_rate: Option<f64>,
+    /// Number of bid price changes.
+    #[prost(uint32, optional, tag = "19")]
+    pub bid_changes: Option<u32>,
+    /// Number of ask price changes.
+    #[prost(uint32, optional, tag = "20")]
+    pub ask_changes: Option<u32>,
+    /// Time-weighted average spread.
+    #[prost(double, optional, tag = "21")]
+    pub twa_spread: Option<f64>,
+    /// Indicator values by column name; missing values are absent.
+    #[prost(btree_map = "string, double", tag = "22")]
+    pub indicators: BTreeMap<String, f64>,
+}
+
+/// Change in close price over one bar (`paracas.v1.Return`).
+#[derive(Clone, PartialEq, Message)]
+pub struct Return {
+    /// Instrument, if requested.
+    #[prost(string, optional, tag = "1")]
+    pub symbol: Option<String>,
+    /// Start of the bar, in milliseconds since the Unix epoch.
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+    /// Change from the previous bar's close to this bar's close.
+    #[prost(double, tag = "3")]
+    pub value: f64,
+    /// Number of ticks in the bar.
+    #[prost(uint32, tag = "4")]
+    pub tick_count: u32,
+}
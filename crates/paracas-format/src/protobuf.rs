@@ -0,0 +1,210 @@
+//! Protobuf output.
+//!
+//! Records are written as length-delimited [`proto`](crate::proto)
+//! messages, each preceded by its size as a varint, so any protobuf
+//! runtime can decode them one by one against the published schema.
+
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use prost::Message;
+use std::io::{self, Write};
+
+use crate::formatter::check_indicators;
+use crate::{FormatError, Formatter, proto};
+
+/// Protobuf formatter.
+///
+/// Ticks, bars and returns are written as `paracas.v1.Tick`, `Ohlcv` and
+/// `Return` messages of [`PROTO_SCHEMA`](crate::proto::PROTO_SCHEMA).
+/// Absent bar fields and missing indicator values are left unset.
+#[derive(Debug, Clone, Default)]
+pub struct ProtobufFormatter {
+    /// Instrument set as the `symbol` field of every message, if any.
+    symbol: Option<String>,
+}
+
+impl ProtobufFormatter {
+    /// Creates a new protobuf formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { symbol: None }
+    }
+
+    /// Sets the `symbol` field of every message to `symbol`.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+}
+
+/// Writes each message with a varint length prefix.
+fn write_messages<M: Message, W: Write>(
+    messages: impl Iterator<Item = M>,
+    writer: W,
+) -> Result<(), FormatError> {
+    let mut writer = io::BufWriter::new(writer);
+    let mut buf = Vec::new();
+    for message in messages {
+        buf.clear();
+        message
+            .encode_length_delimited(&mut buf)
+            .map_err(io::Error::other)?;
+        writer.write_all(&buf)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+impl Formatter for ProtobufFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let messages = ticks.iter().map(|tick| proto::Tick {
+            symbol: self.symbol.clone(),
+            timestamp: tick.timestamp.timestamp_millis(),
+            ask: tick.ask,
+            bid: tick.bid,
+            ask_volume: tick.ask_volume,
+            bid_volume: tick.bid_volume,
+        });
+        write_messages(messages, writer)
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let messages = bars.iter().enumerate().map(|(row, bar)| proto::Ohlcv {
+            symbol: self.symbol.clone(),
+            timestamp: bar.timestamp.timestamp_millis(),
+            open: bar.open,
+            high: bar.high,
+            low: bar.low,
+            close: bar.close,
+            volume: bar.volume,
+            tick_count: bar.tick_count,
+            ask_volume: bar.ask_volume,
+            bid_volume: bar.bid_volume,
+            imbalance: bar.imbalance,
+            first_tick_ts: bar.first_tick_ts.map(|ts| ts.timestamp_millis()),
+            last_tick_ts: bar.last_tick_ts.map(|ts| ts.timestamp_millis()),
+            is_partial: bar.is_partial,
+            vwap: bar.vwap,
+            avg_spread: bar.avg_spread,
+            max_spread: bar.max_spread,
+            tick_rate: bar.tick_rate,
+            bid_changes: bar.bid_changes,
+            ask_changes: bar.ask_changes,
+            twa_spread: bar.twa_spread,
+            indicators: indicators
+                .iter()
+                .filter_map(|c| Some((c.name.clone(), c.values[row]?)))
+                .collect(),
+        });
+        write_messages(messages, writer)
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let messages = returns.iter().map(|r| proto::Return {
+            symbol: self.symbol.clone(),
+            timestamp: r.timestamp.timestamp_millis(),
+            value: r.value,
+            tick_count: r.tick_count,
+        });
+        write_messages(messages, writer)
+    }
+
+    fn extension(&self) -> &str {
+        "pb"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn decode<M: Message + Default>(bytes: &[u8]) -> Vec<M> {
+        let mut reader = bytes;
+        let mut messages = Vec::new();
+        while !reader.is_empty() {
+            messages.push(M::decode_length_delimited(&mut reader).unwrap());
+        }
+        messages
+    }
+
+    #[test]
+    fn test_protobuf_ticks() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap();
+        let ticks = [
+            Tick::new(timestamp, 1.5, 1.25, 100.0, 200.0),
+            Tick::new(timestamp, 1.5, 1.25, 1.0, 2.0),
+        ];
+
+        let mut output = Vec::new();
+        ProtobufFormatter::new()
+            .with_symbol("eurusd")
+            .write_ticks(&ticks, &mut output)
+            .unwrap();
+
+        let messages: Vec<proto::Tick> = decode(&output);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0],
+            proto::Tick {
+                symbol: Some("eurusd".to_string()),
+                timestamp: timestamp.timestamp_millis(),
+                ask: 1.5,
+                bid: 1.25,
+                ask_volume: 100.0,
+                bid_volume: 200.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_protobuf_bars_leave_absent_fields_unset() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0);
+        let indicators = [
+            IndicatorColumn {
+                name: "sma_2".to_string(),
+                values: vec![None],
+            },
+            IndicatorColumn {
+                name: "ema_2".to_string(),
+                values: vec![Some(1.1)],
+            },
+        ];
+
+        let mut output = Vec::new();
+        ProtobufFormatter::new()
+            .write_ohlcv_with_indicators(&[bar], &indicators, &mut output)
+            .unwrap();
+
+        let message = &decode::<proto::Ohlcv>(&output)[0];
+        assert_eq!(message.symbol, None);
+        assert_eq!(message.tick_count, 2);
+        assert_eq!(message.bid_volume, Some(3.0));
+        assert_eq!(message.vwap, None);
+        assert_eq!(message.indicators.len(), 1);
+        assert_eq!(message.indicators["ema_2"], 1.1);
+    }
+
+    #[test]
+    fn test_schema_declares_messages() {
+        for message in ["message Tick {", "message Ohlcv {", "message Return {"] {
+            assert!(proto::PROTO_SCHEMA.contains(message));
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Runtime dispatch of formatters by name.
+//!
+//! [`Formatter`] is generic over its writer, so it cannot be used as a
+//! trait object. [`DynFormatter`] is its object-safe counterpart, which
+//! every formatter implements, and [`FormatterRegistry`] maps format names
+//! to boxed formatters, so applications can add their own formats and pick
+//! one from a string at runtime. [`FileFormatter`] does the same for the
+//! formats written to a path rather than a stream.
+
+use paracas_aggregate::{IndicatorColumn, Ohlcv, OhlcvBidAsk, PriceReturn, VolumeProfile};
+use paracas_types::Tick;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::{
+    CsvFormatter, FormatError, Formatter, JsonFormatter, LeanFormatter, MtCsvFormatter, MtLayout,
+    NinjaTraderFormatter, TradingViewFormatter,
+};
+
+/// Object-safe variant of [`Formatter`], writing to a `dyn Write`.
+///
+/// Implemented for every [`Formatter`]; implement [`Formatter`] rather than
+/// this trait for custom formats.
+pub trait DynFormatter: Send + Sync {
+    /// Writes tick data to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_ticks_dyn(
+        &self,
+        ticks: &[Tick],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError>;
+
+    /// Writes OHLCV data with indicator columns after the bar columns to
+    /// `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, or an error if writing fails.
+    fn write_ohlcv_dyn(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError>;
+
+    /// Writes OHLCV data with the volume profile of each bar to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::NestedColumns`] if the format has no nested
+    /// columns, [`FormatError::ColumnLength`] if there is not one profile
+    /// per bar, or an error if writing fails.
+    fn write_ohlcv_with_profiles_dyn(
+        &self,
+        bars: &[Ohlcv],
+        profiles: &[VolumeProfile],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError>;
+
+    /// Writes bars carrying both sides of the quote to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::UnsupportedRecords`] if the format has no
+    /// layout for them, or an error if writing fails.
+    fn write_bid_ask_bars_dyn(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError>;
+
+    /// Writes a return series to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_returns_dyn(
+        &self,
+        returns: &[PriceReturn],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError>;
+
+    /// Returns the file extension for this format.
+    fn extension_dyn(&self) -> &str;
+}
+
+impl<F: Formatter> DynFormatter for F {
+    fn write_ticks_dyn(
+        &self,
+        ticks: &[Tick],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError> {
+        self.write_ticks(ticks, writer)
+    }
+
+    fn write_ohlcv_dyn(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, indicators, writer)
+    }
+
+    fn write_ohlcv_with_profiles_dyn(
+        &self,
+        bars: &[Ohlcv],
+        profiles: &[VolumeProfile],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError> {
+        self.write_ohlcv_with_profiles(bars, profiles, writer)
+    }
+
+    fn write_bid_ask_bars_dyn(
+        &self,
+        bars: &[OhlcvBidAsk],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError> {
+        self.write_bid_ask_bars(bars, writer)
+    }
+
+    fn write_returns_dyn(
+        &self,
+        returns: &[PriceReturn],
+        writer: &mut (dyn Write + Send),
+    ) -> Result<(), FormatError> {
+        self.write_returns(returns, writer)
+    }
+
+    fn extension_dyn(&self) -> &str {
+        self.extension()
+    }
+}
+
+/// Object-safe formatter of a format written to a path rather than a
+/// stream: the databases records are inserted into, and HDF5 files.
+pub trait FileFormatter: Send + Sync {
+    /// Writes tick data to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_ticks_to(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError>;
+
+    /// Writes OHLCV data with indicator columns to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, or an error if writing fails.
+    fn write_ohlcv_to(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError>;
+
+    /// Writes a return series to the file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_returns_to(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError>;
+}
+
+/// Formatters by case-insensitive format name.
+#[derive(Default)]
+pub struct FormatterRegistry {
+    formatters: BTreeMap<String, Box<dyn DynFormatter>>,
+}
+
+impl FormatterRegistry {
+    /// Creates an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry of the built-in stream formats compiled in, with
+    /// default settings, under their [`OutputFormat`](crate::OutputFormat)
+    /// names.
+    ///
+    /// Database and HDF5 output is written to a path rather than a stream,
+    /// so those formats are not included.
+    #[must_use]
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register("csv", CsvFormatter::new());
+        registry.register("json", JsonFormatter::new());
+        registry.register("ndjson", JsonFormatter::ndjson());
+        registry.register("mt4", MtCsvFormatter::new());
+        registry.register("mt5", MtCsvFormatter::new().with_layout(MtLayout::Mt5));
+        registry.register("hst", MtCsvFormatter::new().with_layout(MtLayout::Hst));
+        registry.register("ninjatrader", NinjaTraderFormatter::new());
+        registry.register("tradingview", TradingViewFormatter::new());
+        registry.register("lean", LeanFormatter::new());
+        #[cfg(feature = "parquet")]
+        registry.register("parquet", crate::ParquetFormatter::new());
+        #[cfg(feature = "avro")]
+        registry.register("avro", crate::AvroFormatter::new());
+        #[cfg(feature = "msgpack")]
+        registry.register("msgpack", crate::MsgpackFormatter::new());
+        #[cfg(feature = "xlsx")]
+        registry.register("xlsx", crate::XlsxFormatter::new());
+        #[cfg(feature = "protobuf")]
+        registry.register("protobuf", crate::ProtobufFormatter::new());
+        #[cfg(feature = "orc")]
+        registry.register("orc", crate::OrcFormatter::new());
+        registry
+    }
+
+    /// Registers `formatter` under `name`, returning the formatter it
+    /// replaces, if any.
+    pub fn register(
+        &mut self,
+        name: &str,
+        formatter: impl DynFormatter + 'static,
+    ) -> Option<Box<dyn DynFormatter>> {
+        self.formatters
+            .insert(name.to_lowercase(), Box::new(formatter))
+    }
+
+    /// Returns the formatter registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::UnknownFormat`] if no formatter has the name.
+    pub fn get(&self, name: &str) -> Result<&dyn DynFormatter, FormatError> {
+        self.formatters
+            .get(&name.to_lowercase())
+            .map(AsRef::as_ref)
+            .ok_or_else(|| FormatError::UnknownFormat(name.to_string()))
+    }
+
+    /// Returns the registered names in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formatters.keys().map(String::as_str)
+    }
+}
+
+impl std::fmt::Debug for FormatterRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    /// Writes one line per record with its timestamp.
+    struct Timestamps;
+
+    impl Formatter for Timestamps {
+        fn write_ticks<W: Write + Send>(
+            &self,
+            ticks: &[Tick],
+            mut writer: W,
+        ) -> Result<(), FormatError> {
+            for tick in ticks {
+                writeln!(writer, "{}", tick.timestamp.timestamp_millis())?;
+            }
+            Ok(())
+        }
+
+        fn write_ohlcv<W: Write + Send>(
+            &self,
+            _bars: &[Ohlcv],
+            _writer: W,
+        ) -> Result<(), FormatError> {
+            Ok(())
+        }
+
+        fn extension(&self) -> &str {
+            "ts"
+        }
+    }
+
+    #[test]
+    fn test_registry_dispatch() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let ticks = [Tick::new(timestamp, 1.5, 1.25, 1.0, 2.0)];
+        let mut registry = FormatterRegistry::builtin();
+        assert!(registry.register("Timestamps", Timestamps).is_none());
+        assert!(registry.names().any(|name| name == "csv"));
+
+        let formatter = registry.get("TIMESTAMPS").unwrap();
+        let mut output = Vec::new();
+        formatter.write_ticks_dyn(&ticks, &mut output).unwrap();
+        assert_eq!(
+            output,
+            format!("{}\n", timestamp.timestamp_millis()).as_bytes()
+        );
+        assert_eq!(formatter.extension_dyn(), "ts");
+        assert!(matches!(
+            formatter.write_returns_dyn(&[], &mut Vec::new()),
+            Err(FormatError::UnsupportedRecords {
+                records: "returns",
+                ..
+            })
+        ));
+
+        let mut output = Vec::new();
+        let csv = registry.get("csv").unwrap();
+        csv.write_ticks_dyn(&ticks, &mut output).unwrap();
+        assert!(output.starts_with(b"timestamp,"));
+
+        assert!(matches!(
+            registry.get("proprietary"),
+            Err(FormatError::UnknownFormat(name)) if name == "proprietary"
+        ));
+    }
+}
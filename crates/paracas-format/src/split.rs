@@ -0,0 +1,294 @@
+//! Output split into numbered files.
+//!
+//! Single files of tens of gigabytes are unwieldy for downstream tools.
+//! [`SplitLimits`] caps the rows or bytes of each file, rolling output over
+//! to `eurusd-0001.parquet`, `eurusd-0002.parquet` and so on, each a
+//! complete file with its own header or schema.
+
+use paracas_aggregate::Ohlcv;
+use paracas_types::Tick;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{BatchWriter, FormatError, StreamingFormatter};
+
+/// Most rows written between checks of the file size.
+const BATCH_ROWS: usize = 8192;
+
+/// Limits of each file of a split output.
+///
+/// A file is closed once it holds `max_rows` rows or `max_bytes` bytes,
+/// whichever comes first. Sizes are counted after any compression and
+/// checked between batches of rows, sized from the bytes per row so far, so
+/// files may exceed `max_bytes` by a few rows and the trailer. Compressors
+/// and Parquet row groups emit bytes in blocks, so their files may exceed
+/// it by up to a block or row group. Every file holds at least one row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SplitLimits {
+    /// Maximum rows per file, if limited.
+    pub max_rows: Option<usize>,
+    /// Maximum bytes per file, if limited.
+    pub max_bytes: Option<u64>,
+}
+
+impl SplitLimits {
+    /// Creates limits that never split.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_rows: None,
+            max_bytes: None,
+        }
+    }
+
+    /// Limits the rows of each file.
+    #[must_use]
+    pub const fn with_max_rows(mut self, rows: usize) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+
+    /// Limits the bytes of each file.
+    #[must_use]
+    pub const fn with_max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Returns whether any limit is set.
+    #[must_use]
+    pub const fn is_split(&self) -> bool {
+        self.max_rows.is_some() || self.max_bytes.is_some()
+    }
+
+    /// Returns the file numbered `index` of `output`, inserting the number
+    /// before `extension` if the name ends with it, e.g. `eurusd-0001.csv.gz`
+    /// for `eurusd.csv.gz` and extension `csv.gz`, or before the last
+    /// extension otherwise.
+    #[must_use]
+    pub fn part_path(output: &Path, extension: &str, index: usize) -> PathBuf {
+        let name = output.file_name().unwrap_or_default().to_string_lossy();
+        let (stem, extension) = name
+            .strip_suffix(&format!(".{extension}"))
+            .map(|stem| (stem.to_string(), extension.to_string()))
+            .or_else(|| {
+                let (stem, extension) = name.rsplit_once('.')?;
+                Some((stem.to_string(), extension.to_string()))
+            })
+            .unwrap_or_else(|| (name.to_string(), String::new()));
+        let part = if extension.is_empty() {
+            format!("{stem}-{index:04}")
+        } else {
+            format!("{stem}-{index:04}.{extension}")
+        };
+        output.with_file_name(part)
+    }
+
+    /// Writes ticks to numbered files of `output` (see
+    /// [`part_path`](Self::part_path)), returning the files written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be created or writing fails.
+    pub fn write_ticks<F: StreamingFormatter>(
+        &self,
+        formatter: &F,
+        ticks: &[Tick],
+        output: &Path,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        self.write_parts(ticks, output, formatter.extension(), |file| {
+            formatter.tick_writer(file)
+        })
+    }
+
+    /// Writes bars to numbered files of `output` (see
+    /// [`part_path`](Self::part_path)), returning the files written.
+    ///
+    /// Every file has the optional columns carried by any of the bars, so
+    /// the files share one header or schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be created or writing fails.
+    pub fn write_ohlcv<F: StreamingFormatter>(
+        &self,
+        formatter: &F,
+        bars: &[Ohlcv],
+        output: &Path,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        self.write_parts(bars, output, formatter.extension(), |file| {
+            formatter.ohlcv_writer_for(file, bars)
+        })
+    }
+
+    /// Writes `records` to as many files as the limits need, at least one.
+    fn write_parts<T, B: BatchWriter<T>>(
+        &self,
+        records: &[T],
+        output: &Path,
+        extension: &str,
+        open: impl Fn(CountingWriter) -> Result<B, FormatError>,
+    ) -> Result<Vec<PathBuf>, FormatError> {
+        let mut files = Vec::new();
+        let mut rest = records;
+        loop {
+            let path = Self::part_path(output, extension, files.len() + 1);
+            let written = Arc::new(AtomicU64::new(0));
+            let mut writer = open(CountingWriter {
+                inner: BufWriter::new(File::create(&path)?),
+                written: Arc::clone(&written),
+            })?;
+            let mut rows = 0;
+            while let Some(count) =
+                self.next_batch(rest.len(), rows, written.load(Ordering::Relaxed))
+            {
+                let (batch, tail) = rest.split_at(count);
+                writer.write_batch(batch)?;
+                rows += count;
+                rest = tail;
+            }
+            writer.finish()?;
+            files.push(path);
+            if rest.is_empty() {
+                return Ok(files);
+            }
+        }
+    }
+
+    /// Returns the number of rows to write next to a file already holding
+    /// `rows` rows and `bytes` bytes, with `remaining` rows left to write,
+    /// or `None` if the file is complete.
+    fn next_batch(&self, remaining: usize, rows: usize, bytes: u64) -> Option<usize> {
+        let full = self.max_rows.is_some_and(|max| rows >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max);
+        if remaining == 0 || (rows > 0 && full) {
+            return None;
+        }
+        let room = self
+            .max_rows
+            .map_or(usize::MAX, |max| max.saturating_sub(rows))
+            .max(1);
+        // Rows fitting in the bytes left, at the bytes per row so far
+        let fitting = match self.max_bytes {
+            Some(_) if rows == 0 => 1,
+            Some(max) if bytes > 0 => {
+                let per_row = bytes.div_ceil(rows as u64);
+                usize::try_from((max - bytes) / per_row).map_or(usize::MAX, |rows| rows.max(1))
+            }
+            _ => usize::MAX,
+        };
+        Some(remaining.min(BATCH_ROWS).min(room).min(fitting))
+    }
+}
+
+/// File writer counting the bytes written through it.
+#[derive(Debug)]
+struct CountingWriter {
+    inner: BufWriter<File>,
+    written: Arc<AtomicU64>,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.inner.write(buf)?;
+        self.written.fetch_add(count as u64, Ordering::Relaxed);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CsvFormatter;
+    use chrono::{TimeDelta, TimeZone, Utc};
+
+    #[test]
+    fn test_part_path() {
+        let part = |name: &str, extension: &str| {
+            SplitLimits::part_path(Path::new(name), extension, 1)
+                .to_string_lossy()
+                .into_owned()
+        };
+        assert_eq!(
+            part("out/eurusd.parquet", "parquet"),
+            "out/eurusd-0001.parquet"
+        );
+        assert_eq!(part("eurusd.csv.gz", "csv.gz"), "eurusd-0001.csv.gz");
+        assert_eq!(part("eurusd.2024.txt", "csv"), "eurusd.2024-0001.txt");
+        assert_eq!(part("eurusd", "csv"), "eurusd-0001");
+    }
+
+    #[test]
+    fn test_split_by_rows_repeats_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
+        let bars: Vec<_> = (0..5)
+            .map(|i| {
+                let bar = Ohlcv::new(start + TimeDelta::hours(i), 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+                // Only the last bar carries side volumes
+                if i == 4 {
+                    bar.with_side_volumes(1.0, 3.0)
+                } else {
+                    bar
+                }
+            })
+            .collect();
+
+        let files = SplitLimits::new()
+            .with_max_rows(2)
+            .write_ohlcv(&CsvFormatter::new(), &bars, &dir.path().join("eurusd.csv"))
+            .unwrap();
+
+        assert_eq!(files.len(), 3);
+        assert!(files[2].ends_with("eurusd-0003.csv"));
+        let contents: Vec<_> = files
+            .iter()
+            .map(|file| std::fs::read_to_string(file).unwrap())
+            .collect();
+        let header = contents[0].lines().next().unwrap();
+        assert!(header.ends_with(",ask_volume,bid_volume,imbalance"));
+        for content in &contents {
+            assert_eq!(content.lines().next(), Some(header));
+        }
+        assert_eq!(contents[0].lines().count(), 3);
+        assert_eq!(contents[2].lines().count(), 2);
+    }
+
+    #[test]
+    fn test_split_by_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
+        let ticks: Vec<_> = (0..20_000)
+            .map(|i| Tick::new(start + TimeDelta::milliseconds(i), 1.1001, 1.1, 1.0, 1.0))
+            .collect();
+
+        let limits = SplitLimits::new().with_max_bytes(100_000);
+        let files = limits
+            .write_ticks(&CsvFormatter::new(), &ticks, &dir.path().join("eurusd.csv"))
+            .unwrap();
+
+        assert!(files.len() > 1);
+        for file in &files {
+            assert!(std::fs::metadata(file).unwrap().len() < 100_100);
+        }
+        let rows: usize = files
+            .iter()
+            .map(|file| std::fs::read_to_string(file).unwrap().lines().count() - 1)
+            .sum();
+        assert_eq!(rows, ticks.len());
+
+        // Empty input still writes one file, with its header
+        let files = limits
+            .write_ticks(&CsvFormatter::new(), &[], &dir.path().join("empty.csv"))
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(!std::fs::read_to_string(&files[0]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+//! SQLite output.
+//!
+//! Unlike the stream formats, records are inserted into a table of a
+//! database file, created along with its indices if missing, so repeated
+//! writes of several instruments accumulate in one queryable store.
+
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::{Tick, TimestampPrecision};
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use std::path::Path;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{BARS_TABLE, FileFormatter, FormatError, RETURNS_TABLE, TICKS_TABLE};
+
+/// SQLite formatter.
+///
+/// Every table has a `symbol` column, indexed together with the
+/// `timestamp` column, and an index on `timestamp` alone. Timestamps are
+/// integers since the Unix epoch at the configured precision.
+#[derive(Debug, Clone, Default)]
+pub struct SqliteFormatter {
+    /// Table written, or the default table of the records if unset.
+    table: Option<String>,
+    /// Instrument stored in the `symbol` column, if any.
+    symbol: Option<String>,
+    /// Resolution of the timestamp columns.
+    timestamp_precision: TimestampPrecision,
+}
+
+/// A column of a table: its name and SQL definition.
+type ColumnDef = (String, &'static str);
+
+impl SqliteFormatter {
+    /// Creates a new SQLite formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            table: None,
+            symbol: None,
+            timestamp_precision: TimestampPrecision::Millisecond,
+        }
+    }
+
+    /// Writes into `table` instead of [`TICKS_TABLE`], [`BARS_TABLE`] or
+    /// [`RETURNS_TABLE`].
+    #[must_use]
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = Some(table.into());
+        self
+    }
+
+    /// Stores `symbol` in the `symbol` column of every row (default: NULL).
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Sets the resolution of the integer timestamps (default:
+    /// milliseconds).
+    #[must_use]
+    pub const fn with_timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Inserts ticks into the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table has other columns, or an insert fails.
+    pub fn write_ticks(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        let columns = [
+            ("ask", "REAL NOT NULL"),
+            ("bid", "REAL NOT NULL"),
+            ("ask_volume", "REAL NOT NULL"),
+            ("bid_volume", "REAL NOT NULL"),
+        ];
+        let rows = ticks.iter().map(|tick| {
+            vec![
+                self.timestamp(tick.timestamp),
+                Value::Real(tick.ask),
+                Value::Real(tick.bid),
+                Value::Real(f64::from(tick.ask_volume)),
+                Value::Real(f64::from(tick.bid_volume)),
+            ]
+        });
+        self.insert(path, TICKS_TABLE, &named(&columns), rows)
+    }
+
+    /// Inserts bars into the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table has other columns, or an insert fails.
+    pub fn write_ohlcv(&self, bars: &[Ohlcv], path: &Path) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], path)
+    }
+
+    /// Inserts bars with a nullable column per indicator into the database
+    /// at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::ColumnLength`] if a column does not have one
+    /// value per bar, or [`FormatError::Database`] if the database cannot
+    /// be opened, the table has other columns, or an insert fails.
+    pub fn write_ohlcv_with_indicators(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let optional = OhlcvColumns::of(bars);
+        let mut columns = named(&[
+            ("open", "REAL NOT NULL"),
+            ("high", "REAL NOT NULL"),
+            ("low", "REAL NOT NULL"),
+            ("close", "REAL NOT NULL"),
+            ("volume", "REAL NOT NULL"),
+            ("tick_count", "INTEGER NOT NULL"),
+        ]);
+        if optional.side_volumes {
+            columns.extend(named(&[
+                ("ask_volume", "REAL"),
+                ("bid_volume", "REAL"),
+                ("imbalance", "REAL"),
+            ]));
+        }
+        if optional.tick_times {
+            columns.extend(named(&[
+                ("first_tick_ts", "INTEGER"),
+                ("last_tick_ts", "INTEGER"),
+                ("is_partial", "INTEGER NOT NULL"),
+            ]));
+        }
+        if optional.price_stats {
+            columns.extend(named(&[
+                ("vwap", "REAL"),
+                ("avg_spread", "REAL"),
+                ("max_spread", "REAL"),
+            ]));
+        }
+        if optional.microstructure {
+            columns.extend(named(&[
+                ("tick_rate", "REAL"),
+                ("bid_changes", "INTEGER"),
+                ("ask_changes", "INTEGER"),
+                ("twa_spread", "REAL"),
+            ]));
+        }
+        columns.extend(indicators.iter().map(|c| (c.name.clone(), "REAL")));
+
+        let real = |value: Option<f64>| value.map_or(Value::Null, Value::Real);
+        let integer = |value: Option<u32>| value.map_or(Value::Null, |v| Value::Integer(v.into()));
+        let rows = bars.iter().enumerate().map(|(row, bar)| {
+            let mut values = vec![
+                self.timestamp(bar.timestamp),
+                Value::Real(bar.open),
+                Value::Real(bar.high),
+                Value::Real(bar.low),
+                Value::Real(bar.close),
+                Value::Real(bar.volume),
+                Value::Integer(bar.tick_count.into()),
+            ];
+            if optional.side_volumes {
+                values.extend([
+                    real(bar.ask_volume),
+                    real(bar.bid_volume),
+                    real(bar.imbalance),
+                ]);
+            }
+            if optional.tick_times {
+                values.extend([
+                    bar.first_tick_ts
+                        .map_or(Value::Null, |ts| self.timestamp(ts)),
+                    bar.last_tick_ts
+                        .map_or(Value::Null, |ts| self.timestamp(ts)),
+                    Value::Integer(bar.is_partial.into()),
+                ]);
+            }
+            if optional.price_stats {
+                values.extend([real(bar.vwap), real(bar.avg_spread), real(bar.max_spread)]);
+            }
+            if optional.microstructure {
+                values.extend([
+                    real(bar.tick_rate),
+                    integer(bar.bid_changes),
+                    integer(bar.ask_changes),
+                    real(bar.twa_spread),
+                ]);
+            }
+            values.extend(indicators.iter().map(|c| real(c.values[row])));
+            values
+        });
+        self.insert(path, BARS_TABLE, &columns, rows)
+    }
+
+    /// Inserts a return series into the database at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FormatError::Database`] if the database cannot be opened,
+    /// the table has other columns, or an insert fails.
+    pub fn write_returns(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        let columns = [
+            ("return", "REAL NOT NULL"),
+            ("tick_count", "INTEGER NOT NULL"),
+        ];
+        let rows = returns.iter().map(|r| {
+            vec![
+                self.timestamp(r.timestamp),
+                Value::Real(r.value),
+                Value::Integer(r.tick_count.into()),
+            ]
+        });
+        self.insert(path, RETURNS_TABLE, &named(&columns), rows)
+    }
+
+    /// Returns a timestamp column value at the configured precision.
+    fn timestamp(&self, timestamp: DateTime<Utc>) -> Value {
+        Value::Integer(self.timestamp_precision.to_epoch(timestamp))
+    }
+
+    /// Creates the table and its indices if missing and inserts `rows` of
+    /// the timestamp and `columns`, in one transaction.
+    fn insert(
+        &self,
+        path: &Path,
+        default_table: &str,
+        columns: &[ColumnDef],
+        rows: impl Iterator<Item = Vec<Value>>,
+    ) -> Result<(), FormatError> {
+        let table = self.table.as_deref().unwrap_or(default_table);
+        let definitions: Vec<_> = [("symbol".to_string(), "TEXT")]
+            .into_iter()
+            .chain([("timestamp".to_string(), "INTEGER NOT NULL")])
+            .chain(columns.iter().cloned())
+            .collect();
+        let names: Vec<_> = definitions.iter().map(|(name, _)| quote(name)).collect();
+        let placeholders: Vec<_> = (1..=names.len()).map(|i| format!("?{i}")).collect();
+
+        let mut conn = Connection::open(path).map_err(database)?;
+        let tx = conn.transaction().map_err(database)?;
+        tx.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {table_name} ({columns});
+             CREATE INDEX IF NOT EXISTS {timestamp_index} ON {table_name} (timestamp);
+             CREATE INDEX IF NOT EXISTS {symbol_index} ON {table_name} (symbol, timestamp);",
+            table_name = quote(table),
+            columns = definitions
+                .iter()
+                .map(|(name, definition)| format!("{} {definition}", quote(name)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            timestamp_index = quote(&format!("{table}_timestamp")),
+            symbol_index = quote(&format!("{table}_symbol_timestamp")),
+        ))
+        .map_err(database)?;
+        {
+            let mut statement = tx
+                .prepare(&format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    quote(table),
+                    names.join(", "),
+                    placeholders.join(", ")
+                ))
+                .map_err(database)?;
+            let symbol = self.symbol.clone().map_or(Value::Null, Value::Text);
+            for row in rows {
+                let values = std::iter::once(symbol.clone()).chain(row);
+                statement
+                    .execute(rusqlite::params_from_iter(values))
+                    .map_err(database)?;
+            }
+        }
+        tx.commit().map_err(database)
+    }
+}
+
+impl FileFormatter for SqliteFormatter {
+    fn write_ticks_to(&self, ticks: &[Tick], path: &Path) -> Result<(), FormatError> {
+        self.write_ticks(ticks, path)
+    }
+
+    fn write_ohlcv_to(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        path: &Path,
+    ) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, indicators, path)
+    }
+
+    fn write_returns_to(&self, returns: &[PriceReturn], path: &Path) -> Result<(), FormatError> {
+        self.write_returns(returns, path)
+    }
+}
+
+/// Names the given column definitions.
+fn named(columns: &[(&str, &'static str)]) -> Vec<ColumnDef> {
+    columns
+        .iter()
+        .map(|&(name, definition)| (name.to_string(), definition))
+        .collect()
+}
+
+/// Quotes an SQL identifier.
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Converts a SQLite error.
+fn database(e: rusqlite::Error) -> FormatError {
+    FormatError::Database(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_sqlite_ticks_accumulate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ticks.sqlite");
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap();
+        let ticks = [Tick::new(timestamp, 1.1001, 1.1000, 100.0, 200.0)];
+
+        let formatter = SqliteFormatter::new();
+        formatter
+            .clone()
+            .with_symbol("eurusd")
+            .write_ticks(&ticks, &path)
+            .unwrap();
+        formatter
+            .with_symbol("gbpusd")
+            .write_ticks(&ticks, &path)
+            .unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM ticks WHERE symbol = 'eurusd' AND timestamp = ?1",
+                [timestamp.timestamp_millis()],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+        let indices: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND tbl_name = 'ticks'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indices, 2);
+    }
+
+    #[test]
+    fn test_sqlite_bars_schema_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bars.sqlite");
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2);
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None],
+        };
+
+        let formatter = SqliteFormatter::new().with_table("m1");
+        formatter
+            .write_ohlcv_with_indicators(&[bar], &[sma], &path)
+            .unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let sma: Option<f64> = conn
+            .query_row("SELECT sma_2 FROM m1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sma, None);
+
+        let err = formatter
+            .write_ohlcv(&[bar.with_side_volumes(1.0, 3.0)], &path)
+            .unwrap_err();
+        assert!(matches!(err, FormatError::Database(_)));
+    }
+}
@@ -0,0 +1,77 @@
+//! Incremental writing of records as batches arrive.
+
+use paracas_aggregate::Ohlcv;
+use paracas_types::Tick;
+use std::io::Write;
+
+use crate::{FormatError, Formatter};
+
+/// Writer appending batches of records to an open output.
+///
+/// Created by a [`StreamingFormatter`], which writes any header up front;
+/// [`finish`](Self::finish) writes the trailer and flushes. Dropping the
+/// writer without finishing leaves the output incomplete.
+pub trait BatchWriter<T> {
+    /// Writes the next batch of records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn write_batch(&mut self, records: &[T]) -> Result<(), FormatError>;
+
+    /// Completes the output after the last batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    fn finish(self) -> Result<(), FormatError>;
+}
+
+/// Formatter able to write ticks and bars incrementally.
+///
+/// Unlike [`Formatter::write_ticks`] and [`Formatter::write_ohlcv`], which
+/// need the whole dataset in memory, the writers returned here take one
+/// batch at a time, such as the ticks of each downloaded hour, and produce
+/// the same output.
+pub trait StreamingFormatter: Formatter {
+    /// Incremental tick writer.
+    type TickWriter<W: Write + Send>: BatchWriter<Tick>;
+
+    /// Incremental bar writer.
+    type OhlcvWriter<W: Write + Send>: BatchWriter<Ohlcv>;
+
+    /// Begins writing ticks to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the header fails.
+    fn tick_writer<W: Write + Send>(&self, writer: W) -> Result<Self::TickWriter<W>, FormatError>;
+
+    /// Begins writing bars to `writer`.
+    ///
+    /// The optional column groups (side volumes, tick times and so on) are
+    /// those of the first non-empty batch; later bars are written with the
+    /// same columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the header fails.
+    fn ohlcv_writer<W: Write + Send>(&self, writer: W)
+    -> Result<Self::OhlcvWriter<W>, FormatError>;
+
+    /// Begins writing some of `bars` to `writer`, with the optional column
+    /// groups of all of `bars` rather than of the first batch, so that
+    /// outputs holding different parts of `bars` share one header or schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the header fails.
+    fn ohlcv_writer_for<W: Write + Send>(
+        &self,
+        writer: W,
+        bars: &[Ohlcv],
+    ) -> Result<Self::OhlcvWriter<W>, FormatError> {
+        let _ = bars;
+        self.ohlcv_writer(writer)
+    }
+}
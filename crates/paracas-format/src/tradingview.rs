@@ -0,0 +1,153 @@
+//! TradingView import format.
+//!
+//! TradingView exports chart data as CSV with a `time` column of Unix
+//! seconds, which its tooling and charting libraries read back, and takes
+//! custom daily data from Pine Seeds repositories as headerless CSV with
+//! `YYYYMMDDT` dates.
+
+use paracas_aggregate::{Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use std::io::{self, Write};
+
+use crate::formatter::format_price;
+use crate::{FormatError, Formatter};
+
+/// Layout of TradingView CSV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TradingViewStyle {
+    /// `time,open,high,low,close,Volume` with a header and Unix-second
+    /// times, as TradingView exports chart data.
+    #[default]
+    Chart,
+    /// Headerless `YYYYMMDDT,open,high,low,close,volume` rows of daily bars,
+    /// as Pine Seeds repositories hold them.
+    PineSeeds,
+}
+
+/// TradingView formatter.
+///
+/// Writes bars in a [`TradingViewStyle`]. Volumes are tick volumes, the tick
+/// count of each bar, as TradingView shows for forex symbols. TradingView
+/// imports bars only, so ticks, indicator columns and returns are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct TradingViewFormatter {
+    /// Layout of the output.
+    style: TradingViewStyle,
+    /// Decimal places of prices, if fixed.
+    digits: Option<u32>,
+}
+
+impl TradingViewFormatter {
+    /// Creates a new TradingView formatter of the chart layout.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            style: TradingViewStyle::Chart,
+            digits: None,
+        }
+    }
+
+    /// Sets the layout of the output (default: [`TradingViewStyle::Chart`]).
+    #[must_use]
+    pub const fn with_style(mut self, style: TradingViewStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Writes prices with `digits` decimal places.
+    #[must_use]
+    pub const fn with_digits(mut self, digits: u32) -> Self {
+        self.digits = Some(digits);
+        self
+    }
+}
+
+/// Returns the error for records TradingView does not import.
+fn unsupported(records: &'static str) -> FormatError {
+    FormatError::UnsupportedRecords {
+        format: "tradingview".to_string(),
+        records,
+    }
+}
+
+impl Formatter for TradingViewFormatter {
+    fn write_ticks<W: Write + Send>(&self, _ticks: &[Tick], _writer: W) -> Result<(), FormatError> {
+        Err(unsupported("ticks"))
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        let mut writer = io::BufWriter::new(writer);
+        if self.style == TradingViewStyle::Chart {
+            writeln!(writer, "time,open,high,low,close,Volume")?;
+        }
+        for bar in bars {
+            let time = match self.style {
+                TradingViewStyle::Chart => bar.timestamp.timestamp().to_string(),
+                TradingViewStyle::PineSeeds => bar.timestamp.format("%Y%m%dT").to_string(),
+            };
+            writeln!(
+                writer,
+                "{time},{},{},{},{},{}",
+                format_price(bar.open, self.digits),
+                format_price(bar.high, self.digits),
+                format_price(bar.low, self.digits),
+                format_price(bar.close, self.digits),
+                bar.tick_count,
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        _returns: &[PriceReturn],
+        _writer: W,
+    ) -> Result<(), FormatError> {
+        Err(unsupported("returns"))
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_tradingview_styles() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
+        let bars = [Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2)];
+
+        let mut output = Vec::new();
+        TradingViewFormatter::new()
+            .with_digits(5)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "time,open,high,low,close,Volume\n1705276800,1.10000,1.20000,1.00000,1.15000,2\n"
+        );
+
+        let mut output = Vec::new();
+        TradingViewFormatter::new()
+            .with_style(TradingViewStyle::PineSeeds)
+            .write_ohlcv(&bars, &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "20240115T,1.1,1.2,1,1.15,2\n"
+        );
+
+        assert!(matches!(
+            TradingViewFormatter::new().write_ticks(&[], Vec::new()),
+            Err(FormatError::UnsupportedRecords {
+                records: "ticks",
+                ..
+            })
+        ));
+    }
+}
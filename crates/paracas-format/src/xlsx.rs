@@ -0,0 +1,361 @@
+//! Excel (XLSX) output.
+//!
+//! Records are written to one worksheet with typed cells: timestamps are
+//! Excel date-times, prices and volumes numbers, so the sheet sorts, filters
+//! and charts without conversion. Worksheets are limited in rows, so this is
+//! meant for bars and small tick sets.
+
+use chrono::{DateTime, Utc};
+use paracas_aggregate::{IndicatorColumn, Ohlcv, PriceReturn};
+use paracas_types::Tick;
+use rust_xlsxwriter::{DocProperties, Format, Workbook, XlsxError};
+use std::io::Write;
+
+use crate::formatter::{OhlcvColumns, check_indicators};
+use crate::{FormatError, Formatter, OutputMetadata};
+
+/// Rows of records a worksheet holds below its header row.
+pub const XLSX_MAX_ROWS: usize = 1_048_575;
+
+/// Number format of timestamp cells, which hold UTC times.
+const TIMESTAMP_FORMAT: &str = "yyyy-mm-dd hh:mm:ss.000";
+
+/// Excel formatter.
+///
+/// Ticks, bars and returns are written to a `Ticks`, `Bars` or `Returns`
+/// worksheet with a bold, frozen and filterable header row. Missing bar
+/// values and indicator values are blank cells.
+#[derive(Debug, Clone)]
+pub struct XlsxFormatter {
+    /// Instrument written in a leading `symbol` column, if any.
+    symbol: Option<String>,
+    /// Dataset metadata stored as custom document properties.
+    metadata: Option<OutputMetadata>,
+    /// Most records written before failing.
+    max_rows: usize,
+}
+
+/// A typed cell value.
+#[derive(Debug, Clone, Copy)]
+enum Cell {
+    /// A UTC date-time.
+    Time(DateTime<Utc>),
+    /// A number.
+    Number(f64),
+    /// A boolean.
+    Bool(bool),
+    /// An empty cell.
+    Blank,
+}
+
+impl Default for XlsxFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XlsxFormatter {
+    /// Creates a new Excel formatter with default settings.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            symbol: None,
+            metadata: None,
+            max_rows: XLSX_MAX_ROWS,
+        }
+    }
+
+    /// Adds a leading `symbol` column holding `symbol` to every row.
+    #[must_use]
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    /// Stores dataset metadata as custom document properties, named with
+    /// the [`METADATA_KEY_PREFIX`](crate::METADATA_KEY_PREFIX).
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: OutputMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Fails writes of more than `max_rows` records (default and maximum:
+    /// [`XLSX_MAX_ROWS`]), rather than producing workbooks too large to
+    /// open comfortably.
+    #[must_use]
+    pub const fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    /// Writes a workbook with a `sheet` of `columns` after the symbol
+    /// column, one row per row of cells.
+    fn write_sheet<W: Write>(
+        &self,
+        sheet: &str,
+        columns: &[String],
+        rows: impl ExactSizeIterator<Item = Vec<Cell>>,
+        mut writer: W,
+    ) -> Result<(), FormatError> {
+        let count = rows.len();
+        let limit = self.max_rows.min(XLSX_MAX_ROWS);
+        if count > limit {
+            return Err(FormatError::TooManyRows { rows: count, limit });
+        }
+
+        let mut workbook = Workbook::new();
+        if let Some(metadata) = &self.metadata {
+            let properties = metadata
+                .key_value_pairs()
+                .into_iter()
+                .fold(DocProperties::new(), |properties, (key, value)| {
+                    properties.set_custom_property(key, value)
+                });
+            workbook.set_properties(&properties);
+        }
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet).map_err(xlsx)?;
+
+        let header = Format::new().set_bold();
+        let time = Format::new().set_num_format(TIMESTAMP_FORMAT);
+        let names: Vec<_> = self
+            .symbol
+            .iter()
+            .map(|_| "symbol")
+            .chain(columns.iter().map(String::as_str))
+            .collect();
+        for (col, name) in names.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, column(col), *name, &header)
+                .map_err(xlsx)?;
+        }
+        let first = usize::from(self.symbol.is_some());
+        for (index, cells) in rows.enumerate() {
+            let row = u32::try_from(index + 1).unwrap_or(u32::MAX);
+            if let Some(symbol) = &self.symbol {
+                worksheet.write_string(row, 0, symbol).map_err(xlsx)?;
+            }
+            for (offset, cell) in cells.into_iter().enumerate() {
+                let col = column(first + offset);
+                match cell {
+                    Cell::Time(ts) => {
+                        worksheet.write_datetime_with_format(row, col, ts.naive_utc(), &time)
+                    }
+                    Cell::Number(v) => worksheet.write_number(row, col, v),
+                    Cell::Bool(v) => worksheet.write_boolean(row, col, v),
+                    Cell::Blank => continue,
+                }
+                .map_err(xlsx)?;
+            }
+        }
+
+        for (col, name) in names.iter().enumerate() {
+            let width = if *name == "timestamp" || name.ends_with("_ts") {
+                23
+            } else {
+                name.len().max(10) + 2
+            };
+            worksheet
+                .set_column_width(column(col), u32::try_from(width).unwrap_or(u32::MAX))
+                .map_err(xlsx)?;
+        }
+        worksheet.set_freeze_panes(1, 0).map_err(xlsx)?;
+        let last_row = u32::try_from(count).unwrap_or(u32::MAX);
+        worksheet
+            .autofilter(0, 0, last_row, column(names.len().saturating_sub(1)))
+            .map_err(xlsx)?;
+
+        writer.write_all(&workbook.save_to_buffer().map_err(xlsx)?)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Formatter for XlsxFormatter {
+    fn write_ticks<W: Write + Send>(&self, ticks: &[Tick], writer: W) -> Result<(), FormatError> {
+        let columns = names(&["timestamp", "ask", "bid", "ask_volume", "bid_volume"]);
+        let rows = ticks.iter().map(|tick| {
+            vec![
+                Cell::Time(tick.timestamp),
+                Cell::Number(tick.ask),
+                Cell::Number(tick.bid),
+                Cell::Number(f64::from(tick.ask_volume)),
+                Cell::Number(f64::from(tick.bid_volume)),
+            ]
+        });
+        self.write_sheet("Ticks", &columns, rows, writer)
+    }
+
+    fn write_ohlcv<W: Write + Send>(&self, bars: &[Ohlcv], writer: W) -> Result<(), FormatError> {
+        self.write_ohlcv_with_indicators(bars, &[], writer)
+    }
+
+    fn write_ohlcv_with_indicators<W: Write + Send>(
+        &self,
+        bars: &[Ohlcv],
+        indicators: &[IndicatorColumn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        check_indicators(bars, indicators)?;
+        let optional = OhlcvColumns::of(bars);
+        let mut columns = names(&[
+            "timestamp",
+            "open",
+            "high",
+            "low",
+            "close",
+            "volume",
+            "tick_count",
+        ]);
+        if optional.side_volumes {
+            columns.extend(names(&["ask_volume", "bid_volume", "imbalance"]));
+        }
+        if optional.tick_times {
+            columns.extend(names(&["first_tick_ts", "last_tick_ts", "is_partial"]));
+        }
+        if optional.price_stats {
+            columns.extend(names(&["vwap", "avg_spread", "max_spread"]));
+        }
+        if optional.microstructure {
+            columns.extend(names(&[
+                "tick_rate",
+                "bid_changes",
+                "ask_changes",
+                "twa_spread",
+            ]));
+        }
+        columns.extend(indicators.iter().map(|c| c.name.clone()));
+
+        let number = |value: Option<f64>| value.map_or(Cell::Blank, Cell::Number);
+        let count = |value: Option<u32>| value.map_or(Cell::Blank, |v| Cell::Number(v.into()));
+        let time = |value: Option<DateTime<Utc>>| value.map_or(Cell::Blank, Cell::Time);
+        let rows = bars.iter().enumerate().map(|(row, bar)| {
+            let mut cells = vec![
+                Cell::Time(bar.timestamp),
+                Cell::Number(bar.open),
+                Cell::Number(bar.high),
+                Cell::Number(bar.low),
+                Cell::Number(bar.close),
+                Cell::Number(bar.volume),
+                Cell::Number(bar.tick_count.into()),
+            ];
+            if optional.side_volumes {
+                cells.extend([
+                    number(bar.ask_volume),
+                    number(bar.bid_volume),
+                    number(bar.imbalance),
+                ]);
+            }
+            if optional.tick_times {
+                cells.extend([
+                    time(bar.first_tick_ts),
+                    time(bar.last_tick_ts),
+                    Cell::Bool(bar.is_partial),
+                ]);
+            }
+            if optional.price_stats {
+                cells.extend([
+                    number(bar.vwap),
+                    number(bar.avg_spread),
+                    number(bar.max_spread),
+                ]);
+            }
+            if optional.microstructure {
+                cells.extend([
+                    number(bar.tick_rate),
+                    count(bar.bid_changes),
+                    count(bar.ask_changes),
+                    number(bar.twa_spread),
+                ]);
+            }
+            cells.extend(indicators.iter().map(|c| number(c.values[row])));
+            cells
+        });
+        self.write_sheet("Bars", &columns, rows, writer)
+    }
+
+    fn write_returns<W: Write + Send>(
+        &self,
+        returns: &[PriceReturn],
+        writer: W,
+    ) -> Result<(), FormatError> {
+        let columns = names(&["timestamp", "return", "tick_count"]);
+        let rows = returns.iter().map(|r| {
+            vec![
+                Cell::Time(r.timestamp),
+                Cell::Number(r.value),
+                Cell::Number(r.tick_count.into()),
+            ]
+        });
+        self.write_sheet("Returns", &columns, rows, writer)
+    }
+
+    fn extension(&self) -> &str {
+        "xlsx"
+    }
+}
+
+/// Returns the worksheet column number of `index`.
+fn column(index: usize) -> u16 {
+    u16::try_from(index).unwrap_or(u16::MAX)
+}
+
+/// Returns owned column names.
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+/// Converts an XLSX writer error.
+fn xlsx(e: XlsxError) -> FormatError {
+    FormatError::Xlsx(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, Reader, Xlsx, open_workbook_from_rs};
+    use chrono::TimeZone;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_xlsx_typed_cells() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let bar = Ohlcv::new(timestamp, 1.1, 1.2, 1.0, 1.15, 4.0, 2).with_side_volumes(1.0, 3.0);
+        let sma = IndicatorColumn {
+            name: "sma_2".to_string(),
+            values: vec![None],
+        };
+
+        let mut output = Vec::new();
+        XlsxFormatter::new()
+            .with_symbol("eurusd")
+            .write_ohlcv_with_indicators(&[bar], &[sma], &mut output)
+            .unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook_from_rs(Cursor::new(output)).unwrap();
+        let range = workbook.worksheet_range("Bars").unwrap();
+        assert_eq!(range.get((0, 0)), Some(&Data::String("symbol".to_string())));
+        assert_eq!(range.get((0, 11)), Some(&Data::String("sma_2".to_string())));
+        assert_eq!(range.get((1, 0)), Some(&Data::String("eurusd".to_string())));
+        assert!(matches!(range.get((1, 1)), Some(Data::DateTime(_))));
+        assert_eq!(range.get((1, 2)), Some(&Data::Float(1.1)));
+        assert_eq!(range.get((1, 9)), Some(&Data::Float(3.0)));
+        assert!(matches!(range.get((1, 11)), None | Some(Data::Empty)));
+    }
+
+    #[test]
+    fn test_xlsx_row_limit() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap();
+        let ticks = vec![Tick::new(timestamp, 1.5, 1.25, 1.0, 2.0); 3];
+
+        let err = XlsxFormatter::new()
+            .with_max_rows(2)
+            .write_ticks(&ticks, Vec::new())
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FormatError::TooManyRows { rows: 3, limit: 2 }
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+//! Description of what this build of paracas supports.
+//!
+//! [`capabilities`] lists the output formats, timeframes, instrument
+//! categories and crate features compiled in, so frontends can render option
+//! lists without hard-coding them.
+
+use paracas_types::{Category, PriceSource, Timeframe};
+use serde::Serialize;
+
+/// Crate features of `paracas-lib`, in the order they are reported.
+const FEATURES: &[(&str, bool)] = &[
+    ("fetch", cfg!(feature = "fetch")),
+    ("aggregate", cfg!(feature = "aggregate")),
+    ("parallel", cfg!(feature = "parallel")),
+    ("format", cfg!(feature = "format")),
+    ("parquet", cfg!(feature = "parquet")),
+    ("gzip", cfg!(feature = "gzip")),
+    ("zstd", cfg!(feature = "zstd")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("duckdb", cfg!(feature = "duckdb")),
+    ("avro", cfg!(feature = "avro")),
+    ("msgpack", cfg!(feature = "msgpack")),
+    ("hdf5", cfg!(feature = "hdf5")),
+    ("xlsx", cfg!(feature = "xlsx")),
+    ("protobuf", cfg!(feature = "protobuf")),
+    ("orc", cfg!(feature = "orc")),
+    ("tracing", cfg!(feature = "tracing")),
+    ("rustls-tls", cfg!(feature = "rustls-tls")),
+    (
+        "rustls-tls-native-roots",
+        cfg!(feature = "rustls-tls-native-roots"),
+    ),
+    ("native-tls", cfg!(feature = "native-tls")),
+];
+
+/// What this build supports, as returned by [`capabilities`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// Version of paracas.
+    pub version: &'static str,
+    /// Minimum supported Rust version.
+    pub rust_version: &'static str,
+    /// Output formats, including those not compiled in. Empty without the
+    /// `format` feature.
+    pub formats: Vec<FormatCapability>,
+    /// Aggregation timeframes.
+    pub timeframes: Vec<Timeframe>,
+    /// Tick prices bars can be built from.
+    pub price_sources: Vec<PriceSource>,
+    /// Instrument categories.
+    pub categories: Vec<Category>,
+    /// TLS backends compiled in. Empty without the `fetch` feature.
+    pub tls_backends: Vec<&'static str>,
+    /// Crate features and whether each is enabled.
+    pub features: Vec<FeatureFlag>,
+}
+
+/// An output format and whether it can be used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FormatCapability {
+    /// Format name, as accepted when parsing a format.
+    pub name: &'static str,
+    /// File extension.
+    pub extension: &'static str,
+    /// Whether the format is compiled in.
+    pub available: bool,
+    /// Crate feature that provides the format, if it is optional.
+    pub required_feature: Option<&'static str>,
+}
+
+/// A crate feature and whether it is enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeatureFlag {
+    /// Feature name.
+    pub name: &'static str,
+    /// Whether the feature is enabled in this build.
+    pub enabled: bool,
+}
+
+/// Returns what this build of paracas supports.
+///
+/// The result serializes to JSON for frontends and servers:
+///
+/// ```
+/// let capabilities = paracas_lib::capabilities();
+/// assert!(capabilities.timeframes.contains(&paracas_lib::Timeframe::Minute1));
+/// ```
+#[must_use]
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        rust_version: env!("CARGO_PKG_RUST_VERSION"),
+        formats: formats(),
+        timeframes: Timeframe::all().to_vec(),
+        price_sources: PriceSource::all().to_vec(),
+        categories: Category::all().to_vec(),
+        tls_backends: tls_backends(),
+        features: FEATURES
+            .iter()
+            .map(|&(name, enabled)| FeatureFlag { name, enabled })
+            .collect(),
+    }
+}
+
+#[cfg(feature = "format")]
+fn formats() -> Vec<FormatCapability> {
+    paracas_format::OutputFormat::all()
+        .iter()
+        .map(|format| FormatCapability {
+            name: format.name(),
+            extension: format.extension(),
+            available: format.is_available(),
+            required_feature: format.required_feature(),
+        })
+        .collect()
+}
+
+#[cfg(not(feature = "format"))]
+const fn formats() -> Vec<FormatCapability> {
+    Vec::new()
+}
+
+#[cfg(feature = "fetch")]
+fn tls_backends() -> Vec<&'static str> {
+    paracas_fetch::TlsBackend::available()
+        .iter()
+        .map(paracas_fetch::TlsBackend::as_str)
+        .collect()
+}
+
+#[cfg(not(feature = "fetch"))]
+const fn tls_backends() -> Vec<&'static str> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_serialize() {
+        let json = serde_json::to_value(capabilities()).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["timeframes"][2], "m1");
+        assert_eq!(json["categories"][0], "forex");
+        assert_eq!(json["features"].as_array().unwrap().len(), FEATURES.len());
+        #[cfg(feature = "parquet")]
+        assert_eq!(
+            json["formats"][3],
+            serde_json::json!({
+                "name": "parquet",
+                "extension": "parquet",
+                "available": true,
+                "required_feature": "parquet",
+            })
+        );
+    }
+}
@@ -35,6 +35,14 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod capabilities;
+#[cfg(all(feature = "fetch", feature = "aggregate"))]
+mod pipeline;
+
+pub use capabilities::{Capabilities, FeatureFlag, FormatCapability, capabilities};
+#[cfg(all(feature = "fetch", feature = "aggregate"))]
+pub use pipeline::{ohlcv_stream, ohlcv_stream_with_options};
+
 // Re-export core types
 pub use paracas_types::*;
 
@@ -44,20 +52,74 @@ pub use paracas_instruments::InstrumentRegistry;
 // Re-export fetch functionality
 #[cfg(feature = "fetch")]
 pub use paracas_fetch::{
-    ClientConfig, DecompressError, DownloadClient, DownloadError, ParseError, TickBatch,
-    tick_stream, tick_stream_resilient,
+    Addrs, Backoff, BasicAuth, CacheConfig, CancellationToken, Certificate, CertificateError,
+    ClientConfig, ConditionalDownload, Coverage, CoverageManifest, DEFAULT_MAX_MEDIAN_RATIO,
+    DEFAULT_MAX_RETRY_AFTER, DataSource, DecompressError, DiskCache, DnsResolver, DownloadClient,
+    DownloadError, DownloadStats, ExpiryPolicy, FailureInjection, FailureKind, HeaderHook,
+    HeaderMap, HeaderName, HeaderValue, HourError, InjectedFailure, IntegrityIssue,
+    IntegrityProblem, InvalidTick, InvalidTickAction, Jitter, LateDataRetry, MANIFEST_FILE,
+    ManifestEntry, Name, ParseError, PriceProblem, PriceValidation, ProgressObserver, RateLimit,
+    RateLimiter, Resolve, Resolving, RetryPolicy, Revision, SkipReason, SlowRequest,
+    SlowRequestHook, StatsSnapshot, StreamOptions, StreamProgress, TickBatch, TickColumns,
+    TlsBackend, VERIFIED_FILE, VerifyOptions, VerifyReport, chunk_ticks, fetch_hour,
+    load_certificates, multi_tick_stream, multi_tick_stream_with_options, parse_ticks_columnar,
+    parse_ticks_into, sha256_file_hex, sha256_hex, tick_stream, tick_stream_chunks,
+    tick_stream_resilient, tick_stream_resilient_split, tick_stream_resilient_with_options,
+    tick_stream_with_options,
 };
 
+#[cfg(feature = "fetch")]
+pub use paracas_fetch::url;
+
 // Re-export aggregation
 #[cfg(feature = "aggregate")]
-pub use paracas_aggregate::{Ohlcv, TickAggregator};
+pub use paracas_aggregate::{
+    BarMeasure, BarResampler, BidAskAggregator, DEFAULT_SPIKE_WINDOW, FilterReason, GapFiller,
+    HeikinAshi, IndicatorColumn, IndicatorSet, MultiTimeframeAggregator, Ohlcv, OhlcvBidAsk,
+    PriceBarAggregator, PriceBarKind, PriceLevel, PriceReturn, ReturnCalculator, ReturnKind,
+    TickAggregator, TickFilter, VolumeBarAggregator, VolumeProfile, VolumeProfileAggregator,
+};
+
+#[cfg(feature = "parallel")]
+pub use paracas_aggregate::ParallelAggregator;
 
 // Re-export formatters
 #[cfg(feature = "format")]
-pub use paracas_format::{CsvFormatter, FormatError, Formatter, JsonFormatter, OutputFormat};
+pub use paracas_format::{
+    BatchWriter, CompressedWriter, CsvFormatter, DATA_LICENSE, DerivedColumns, DynFormatter,
+    FileFormatter, FormatError, Formatter, FormatterRegistry, InstrumentAliases, JsonFieldNaming,
+    JsonFormatter, LeanFormatter, LeanResolution, LeanTickType, MtCsvFormatter, MtLayout,
+    MtServerTime, NinjaTraderFormatter, OutputCompression, OutputFormat, OutputMetadata,
+    SplitLimits, StreamingFormatter, TimestampStyle, TradingViewFormatter, TradingViewStyle,
+    read_first_line,
+};
 
 #[cfg(all(feature = "format", feature = "parquet"))]
-pub use paracas_format::ParquetFormatter;
+pub use paracas_format::{ParquetFormatter, arrow};
+
+#[cfg(all(feature = "format", feature = "sqlite"))]
+pub use paracas_format::SqliteFormatter;
+
+#[cfg(all(feature = "format", feature = "duckdb"))]
+pub use paracas_format::DuckDbFormatter;
+
+#[cfg(all(feature = "format", feature = "avro"))]
+pub use paracas_format::AvroFormatter;
+
+#[cfg(all(feature = "format", feature = "msgpack"))]
+pub use paracas_format::MsgpackFormatter;
+
+#[cfg(all(feature = "format", feature = "hdf5"))]
+pub use paracas_format::Hdf5Formatter;
+
+#[cfg(all(feature = "format", feature = "xlsx"))]
+pub use paracas_format::XlsxFormatter;
+
+#[cfg(all(feature = "format", feature = "protobuf"))]
+pub use paracas_format::{ProtobufFormatter, proto};
+
+#[cfg(all(feature = "format", feature = "orc"))]
+pub use paracas_format::{OrcCompression, OrcFormatter};
 
 /// Prelude module for convenient imports.
 ///
@@ -67,22 +129,53 @@ pub use paracas_format::ParquetFormatter;
 pub mod prelude {
     pub use paracas_types::{
         Category, DateRange, DateRangeError, Instrument, ParacasError, RawTick, Result, Tick,
-        Timeframe,
+        Timeframe, TimestampPrecision, TradingCalendar,
     };
 
     pub use paracas_instruments::InstrumentRegistry;
 
     #[cfg(feature = "fetch")]
     pub use paracas_fetch::{
-        ClientConfig, DownloadClient, TickBatch, tick_stream, tick_stream_resilient,
+        ClientConfig, DownloadClient, StreamOptions, TickBatch, tick_stream, tick_stream_chunks,
+        tick_stream_resilient,
     };
 
     #[cfg(feature = "aggregate")]
     pub use paracas_aggregate::{Ohlcv, TickAggregator};
 
+    #[cfg(all(feature = "fetch", feature = "aggregate"))]
+    pub use crate::ohlcv_stream;
+
     #[cfg(feature = "format")]
-    pub use paracas_format::{CsvFormatter, Formatter, JsonFormatter, OutputFormat};
+    pub use paracas_format::{
+        BatchWriter, CsvFormatter, Formatter, JsonFormatter, OutputFormat, OutputMetadata,
+        StreamingFormatter,
+    };
 
     #[cfg(all(feature = "format", feature = "parquet"))]
     pub use paracas_format::ParquetFormatter;
+
+    #[cfg(all(feature = "format", feature = "sqlite"))]
+    pub use paracas_format::SqliteFormatter;
+
+    #[cfg(all(feature = "format", feature = "duckdb"))]
+    pub use paracas_format::DuckDbFormatter;
+
+    #[cfg(all(feature = "format", feature = "avro"))]
+    pub use paracas_format::AvroFormatter;
+
+    #[cfg(all(feature = "format", feature = "msgpack"))]
+    pub use paracas_format::MsgpackFormatter;
+
+    #[cfg(all(feature = "format", feature = "hdf5"))]
+    pub use paracas_format::Hdf5Formatter;
+
+    #[cfg(all(feature = "format", feature = "xlsx"))]
+    pub use paracas_format::XlsxFormatter;
+
+    #[cfg(all(feature = "format", feature = "protobuf"))]
+    pub use paracas_format::ProtobufFormatter;
+
+    #[cfg(all(feature = "format", feature = "orc"))]
+    pub use paracas_format::OrcFormatter;
 }
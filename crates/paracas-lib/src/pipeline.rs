@@ -0,0 +1,148 @@
+//! Streaming tick-to-bar pipeline.
+
+use futures::stream::{self, Stream, StreamExt};
+use paracas_aggregate::{Ohlcv, TickAggregator};
+use paracas_fetch::{DownloadClient, StreamOptions, TickBatch, tick_stream_with_options};
+use paracas_types::{DateRange, Instrument, ParacasError, Timeframe};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// Creates a stream of OHLCV bars for an instrument and range.
+///
+/// Hours are downloaded in order and fed straight into a
+/// [`TickAggregator`], and each bar is yielded as soon as it completes, so
+/// memory use stays flat however long the range. The trailing bar is
+/// flagged as partial, as by [`TickAggregator::finish`]. Weekend hours are
+/// skipped for forex instruments, as by [`StreamOptions::for_instrument`].
+///
+/// The stream ends after the first error, since bars after a failed hour
+/// would silently miss its ticks.
+pub fn ohlcv_stream<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    timeframe: Timeframe,
+) -> impl Stream<Item = Result<Ohlcv, ParacasError>> + 'a {
+    ohlcv_stream_with_options(
+        client,
+        instrument,
+        range,
+        TickAggregator::new(timeframe),
+        StreamOptions::for_instrument(instrument),
+    )
+}
+
+/// Creates a bar stream, as [`ohlcv_stream`], aggregating with a configured
+/// aggregator and downloading with the given stream options.
+///
+/// Hours are always downloaded in order, whatever
+/// [`StreamOptions::ordered`] is set to.
+pub fn ohlcv_stream_with_options<'a>(
+    client: &'a DownloadClient,
+    instrument: &'a Instrument,
+    range: DateRange,
+    aggregator: TickAggregator,
+    options: StreamOptions,
+) -> impl Stream<Item = Result<Ohlcv, ParacasError>> + 'a {
+    let batches = tick_stream_with_options(client, instrument, range, options.with_ordered(true));
+    let state = Pipeline {
+        batches: Box::pin(batches),
+        aggregator: Some(aggregator),
+        pending: VecDeque::new(),
+    };
+    stream::unfold(state, Pipeline::next)
+}
+
+/// State of a bar stream between polls.
+struct Pipeline<'a> {
+    batches: Pin<Box<dyn Stream<Item = Result<TickBatch, ParacasError>> + 'a>>,
+    /// The aggregator, until the tick stream ends or fails.
+    aggregator: Option<TickAggregator>,
+    /// Bars completed but not yet yielded.
+    pending: VecDeque<Ohlcv>,
+}
+
+impl Pipeline<'_> {
+    /// Yields the next bar, pulling hours until one completes.
+    async fn next(mut self) -> Option<(Result<Ohlcv, ParacasError>, Self)> {
+        loop {
+            if let Some(bar) = self.pending.pop_front() {
+                return Some((Ok(bar), self));
+            }
+            let aggregator = self.aggregator.as_mut()?;
+            match self.batches.next().await {
+                Some(Ok(batch)) => self.pending.extend(
+                    batch
+                        .ticks
+                        .iter()
+                        .filter_map(|tick| aggregator.process(*tick)),
+                ),
+                Some(Err(e)) => {
+                    self.aggregator = None;
+                    return Some((Err(e), self));
+                }
+                None => {
+                    let last = self.aggregator.take().and_then(TickAggregator::finish);
+                    self.pending.extend(last);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use paracas_fetch::url::{relative_path, tick_url};
+    use paracas_fetch::{ClientConfig, DataSource};
+    use paracas_types::Category;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_stream_matches_collected_aggregation() {
+        // Lay the fixture hours out as a local mirror of the data feed
+        let fixtures =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../integration/fixtures/eurusd");
+        let dir = tempfile::tempdir().unwrap();
+        for hour in 10..=12 {
+            let url = tick_url(
+                "eurusd",
+                Utc.with_ymd_and_hms(2024, 1, 2, hour, 0, 0).unwrap(),
+            );
+            let path = dir.path().join(relative_path(&url).unwrap());
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::copy(fixtures.join(format!("2024-01-02T{hour}.bi5")), path).unwrap();
+        }
+        let config = ClientConfig {
+            source: DataSource::local(dir.path()),
+            ..Default::default()
+        };
+        let client = DownloadClient::new(config).unwrap();
+        let instrument = Instrument::new("eurusd", "EUR/USD", "", Category::Forex, 100_000, None);
+        let range = DateRange::single_day(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap())
+            .with_hours(10, 12)
+            .unwrap();
+
+        let bars: Vec<_> = ohlcv_stream(&client, &instrument, range, Timeframe::Minute15)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let options = StreamOptions::default().with_ordered(true);
+        let ticks: Vec<_> = tick_stream_with_options(&client, &instrument, range, options)
+            .map(|batch| batch.unwrap().ticks)
+            .concat()
+            .await;
+        let mut aggregator = TickAggregator::new(Timeframe::Minute15);
+        let mut expected: Vec<_> = ticks
+            .iter()
+            .filter_map(|t| aggregator.process(*t))
+            .collect();
+        expected.extend(aggregator.finish());
+
+        assert_eq!(bars.len(), 12);
+        assert_eq!(bars, expected);
+        assert!(bars.last().unwrap().is_partial);
+    }
+}
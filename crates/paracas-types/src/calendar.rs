@@ -0,0 +1,147 @@
+//! Trading calendars describing which hours an instrument's feed covers.
+
+use chrono::{DateTime, Datelike, TimeDelta, Timelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{Category, is_forex_weekend};
+
+/// The hours in which an instrument's feed publishes data.
+///
+/// Used to count the hours of a range that can hold ticks, so progress and
+/// size estimates match what is actually downloaded. Calendars are
+/// approximations in UTC that err on the side of including an hour, e.g.
+/// sessions cover both the summer and winter opening times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum TradingCalendar {
+    /// Every hour of every day, as for cryptocurrencies.
+    Continuous,
+    /// Around the clock from Sunday evening to Friday evening, skipping the
+    /// forex weekend (see [`is_forex_weekend`]).
+    Weekdays,
+    /// A daily session on weekdays, from the `open` hour through the `close`
+    /// hour (UTC, inclusive).
+    ///
+    /// When `open` is after `close` the session wraps midnight and belongs
+    /// to the day on which it closes, so Sunday evening hours are included
+    /// and Friday evening hours are not.
+    Session {
+        /// First hour of the session (UTC, 0-23).
+        open: u32,
+        /// Last hour of the session (UTC, 0-23).
+        close: u32,
+    },
+}
+
+impl TradingCalendar {
+    /// Returns the default calendar for instruments of `category`.
+    ///
+    /// Stocks and ETFs follow the US cash session; everything else but
+    /// cryptocurrencies trades around the clock on weekdays.
+    #[must_use]
+    pub const fn for_category(category: Category) -> Self {
+        match category {
+            Category::Crypto => Self::Continuous,
+            Category::Stock | Category::Etf => Self::Session {
+                open: 13,
+                close: 20,
+            },
+            Category::Forex | Category::Index | Category::Commodity | Category::Bond => {
+                Self::Weekdays
+            }
+        }
+    }
+
+    /// Returns true if the hour starting at `hour` may hold ticks.
+    #[must_use]
+    pub fn is_trading_hour(&self, hour: DateTime<Utc>) -> bool {
+        match *self {
+            Self::Continuous => true,
+            Self::Weekdays => !is_forex_weekend(hour),
+            Self::Session { open, close } => {
+                let h = hour.hour();
+                let (in_session, session_day) = if open <= close {
+                    (open <= h && h <= close, hour)
+                } else if h >= open {
+                    (true, hour + TimeDelta::days(1))
+                } else {
+                    (h <= close, hour)
+                };
+                in_session && !matches!(session_day.weekday(), Weekday::Sat | Weekday::Sun)
+            }
+        }
+    }
+
+    /// Returns the number of trading hours in a week.
+    #[must_use]
+    pub const fn hours_per_week(&self) -> u32 {
+        match *self {
+            Self::Continuous => 7 * 24,
+            // Sunday from 20:00 through Friday
+            Self::Weekdays => 4 + 5 * 24,
+            Self::Session { open, close } => {
+                let per_day = if open <= close {
+                    close - open + 1
+                } else {
+                    24 - open + close + 1
+                };
+                5 * per_day
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DateRange;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn week() -> DateRange {
+        // Monday 2024-01-08 through Sunday 2024-01-14
+        DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 14).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hours_per_week_matches_iteration() {
+        let calendars = [
+            TradingCalendar::Continuous,
+            TradingCalendar::Weekdays,
+            TradingCalendar::Session {
+                open: 13,
+                close: 20,
+            },
+            TradingCalendar::Session { open: 23, close: 5 },
+        ];
+        for calendar in calendars {
+            let counted = week().trading_hours(calendar).count();
+            assert_eq!(counted as u32, calendar.hours_per_week(), "{calendar:?}");
+            assert_eq!(week().total_trading_hours(calendar), counted);
+        }
+    }
+
+    #[test]
+    fn test_wrapping_session_belongs_to_closing_day() {
+        let calendar = TradingCalendar::Session { open: 23, close: 5 };
+        // Sunday 23:00 opens Monday's session, Friday 23:00 opens Saturday's
+        let sunday = Utc.with_ymd_and_hms(2024, 1, 14, 23, 0, 0).unwrap();
+        let friday = Utc.with_ymd_and_hms(2024, 1, 12, 23, 0, 0).unwrap();
+        assert!(calendar.is_trading_hour(sunday));
+        assert!(!calendar.is_trading_hour(friday));
+    }
+
+    #[test]
+    fn test_serde() {
+        let calendar: TradingCalendar =
+            serde_json::from_str(r#"{"kind": "session", "open": 7, "close": 15}"#).unwrap();
+        assert_eq!(calendar, TradingCalendar::Session { open: 7, close: 15 });
+        assert_eq!(
+            TradingCalendar::for_category(Category::Crypto),
+            TradingCalendar::Continuous
+        );
+    }
+}
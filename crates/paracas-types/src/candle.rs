@@ -0,0 +1,66 @@
+//! Candle styles for bar output.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How bars are drawn from the aggregated prices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CandleStyle {
+    /// Plain OHLC bars.
+    #[default]
+    Standard,
+    /// Heikin-Ashi candles, averaging each bar with the previous candle to
+    /// smooth out noise.
+    HeikinAshi,
+}
+
+impl CandleStyle {
+    /// Returns the style as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::HeikinAshi => "heikin-ashi",
+        }
+    }
+}
+
+impl std::fmt::Display for CandleStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for CandleStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "standard" | "ohlc" => Ok(Self::Standard),
+            "heikin-ashi" | "heikinashi" | "ha" => Ok(Self::HeikinAshi),
+            _ => Err(format!(
+                "invalid candle style '{s}', expected one of: standard, heikin-ashi"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candle_style_round_trip() {
+        assert_eq!("HA".parse(), Ok(CandleStyle::HeikinAshi));
+        assert_eq!(
+            CandleStyle::HeikinAshi.to_string().parse(),
+            Ok(CandleStyle::HeikinAshi)
+        );
+        assert_eq!(
+            serde_json::to_string(&CandleStyle::HeikinAshi).unwrap(),
+            "\"heikin-ashi\""
+        );
+        assert!("renko".parse::<CandleStyle>().is_err());
+    }
+}
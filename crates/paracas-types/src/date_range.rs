@@ -1,16 +1,24 @@
 //! Date range and hour iteration.
 
-use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeZone, Timelike, Utc, Weekday};
 
-use crate::DateRangeError;
+use crate::{DateRangeError, TradingCalendar};
 
 /// A range of dates for data retrieval.
+///
+/// By default the range covers whole days. Use [`DateRange::with_hours`] to
+/// start at a specific hour on the first day and stop at a specific hour on
+/// the last day.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DateRange {
     /// Start date (inclusive).
     pub start: NaiveDate,
     /// End date (inclusive).
     pub end: NaiveDate,
+    /// First hour (UTC, 0-23) on the start date (inclusive).
+    pub start_hour: u32,
+    /// Last hour (UTC, 0-23) on the end date (inclusive).
+    pub end_hour: u32,
 }
 
 impl DateRange {
@@ -23,7 +31,12 @@ impl DateRange {
         if start > end {
             return Err(DateRangeError::InvalidRange { start, end });
         }
-        Ok(Self { start, end })
+        Ok(Self {
+            start,
+            end,
+            start_hour: 0,
+            end_hour: 23,
+        })
     }
 
     /// Creates a date range for a single day.
@@ -32,19 +45,87 @@ impl DateRange {
         Self {
             start: date,
             end: date,
+            start_hour: 0,
+            end_hour: 23,
+        }
+    }
+
+    /// Restricts the range to start at `start_hour` on the start date and
+    /// stop after `end_hour` on the end date (both UTC, inclusive).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either hour is above 23, or if the range is a
+    /// single day and `start_hour > end_hour`.
+    pub fn with_hours(mut self, start_hour: u32, end_hour: u32) -> Result<Self, DateRangeError> {
+        for hour in [start_hour, end_hour] {
+            if hour > 23 {
+                return Err(DateRangeError::InvalidHour(hour));
+            }
         }
+        if self.start == self.end && start_hour > end_hour {
+            return Err(DateRangeError::InvalidHourRange {
+                start_hour,
+                end_hour,
+            });
+        }
+        self.start_hour = start_hour;
+        self.end_hour = end_hour;
+        Ok(self)
+    }
+
+    /// Returns true if the range covers whole days.
+    #[must_use]
+    pub const fn is_whole_days(&self) -> bool {
+        self.start_hour == 0 && self.end_hour == 23
+    }
+
+    /// Returns the start of the first hour in the range.
+    #[must_use]
+    pub fn start_datetime(&self) -> DateTime<Utc> {
+        hour_on(self.start, self.start_hour)
+    }
+
+    /// Returns the start of the last hour in the range.
+    #[must_use]
+    pub fn end_datetime(&self) -> DateTime<Utc> {
+        hour_on(self.end, self.end_hour)
     }
 
     /// Returns an iterator over all hours in the date range.
     pub fn hours(&self) -> HourIterator {
-        HourIterator::new(self.start, self.end)
+        HourIterator {
+            current: self.start_datetime(),
+            end: self.end_datetime(),
+        }
     }
 
     /// Returns the total number of hours in the range.
     #[must_use]
     pub fn total_hours(&self) -> usize {
-        let days = (self.end - self.start).num_days() + 1;
-        (days * 24) as usize
+        ((self.end_datetime() - self.start_datetime()).num_hours() + 1) as usize
+    }
+
+    /// Returns an iterator over the hours of the range in which `calendar`
+    /// trades.
+    pub fn trading_hours(
+        &self,
+        calendar: TradingCalendar,
+    ) -> impl Iterator<Item = DateTime<Utc>> + use<> {
+        self.hours()
+            .filter(move |hour| calendar.is_trading_hour(*hour))
+    }
+
+    /// Returns the number of hours of the range in which `calendar` trades.
+    ///
+    /// Unlike [`total_hours`](Self::total_hours), which counts every hour,
+    /// this skips weekends and closed sessions.
+    #[must_use]
+    pub fn total_trading_hours(&self, calendar: TradingCalendar) -> usize {
+        match calendar {
+            TradingCalendar::Continuous => self.total_hours(),
+            _ => self.trading_hours(calendar).count(),
+        }
     }
 
     /// Returns the total number of days in the range.
@@ -62,10 +143,23 @@ impl DateRange {
 
 impl std::fmt::Display for DateRange {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} to {}", self.start, self.end)
+        if self.is_whole_days() {
+            write!(f, "{} to {}", self.start, self.end)
+        } else {
+            write!(
+                f,
+                "{} {:02}:00 to {} {:02}:00",
+                self.start, self.start_hour, self.end, self.end_hour
+            )
+        }
     }
 }
 
+/// Returns the UTC timestamp of `hour` on `date`.
+fn hour_on(date: NaiveDate, hour: u32) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap()))
+}
+
 /// Iterator over all hours in a date range.
 #[derive(Debug, Clone)]
 pub struct HourIterator {
@@ -73,22 +167,6 @@ pub struct HourIterator {
     end: DateTime<Utc>,
 }
 
-impl HourIterator {
-    /// Creates a new hour iterator for the given date range.
-    fn new(start: NaiveDate, end: NaiveDate) -> Self {
-        let start_dt =
-            Utc.from_utc_datetime(&start.and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap()));
-        // End at 23:00 of the end date (last hour of the day)
-        let end_dt =
-            Utc.from_utc_datetime(&end.and_time(NaiveTime::from_hms_opt(23, 0, 0).unwrap()));
-
-        Self {
-            current: start_dt,
-            end: end_dt,
-        }
-    }
-}
-
 impl Iterator for HourIterator {
     type Item = DateTime<Utc>;
 
@@ -113,6 +191,26 @@ impl Iterator for HourIterator {
 
 impl ExactSizeIterator for HourIterator {}
 
+/// First hour (UTC) on Sunday from which forex data may exist.
+///
+/// The market reopens at 21:00 UTC in summer and 22:00 UTC in winter; one
+/// extra hour of margin is kept.
+const FOREX_SUNDAY_OPEN_HOUR: u32 = 20;
+
+/// Returns true if the hour falls inside the forex weekend closure.
+///
+/// Covers all of Saturday and Sunday before 20:00 UTC, hours for which
+/// Dukascopy never has forex data. Friday evening hours are kept since the
+/// close shifts with daylight saving time.
+#[must_use]
+pub fn is_forex_weekend(hour: DateTime<Utc>) -> bool {
+    match hour.weekday() {
+        Weekday::Sat => true,
+        Weekday::Sun => hour.hour() < FOREX_SUNDAY_OPEN_HOUR,
+        _ => false,
+    }
+}
+
 /// Extracts the hour start timestamp from a Dukascopy URL.
 ///
 /// URL format: `https://datafeed.dukascopy.com/datafeed/{INSTRUMENT}/{YEAR}/{MONTH}/{DAY}/{HOUR}h_ticks.bi5`
@@ -178,6 +276,54 @@ mod tests {
         assert_eq!(hours[23].hour(), 23);
     }
 
+    #[test]
+    fn test_intraday_hours() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let range = DateRange::new(start, end)
+            .unwrap()
+            .with_hours(7, 16)
+            .unwrap();
+        let hours: Vec<_> = range.hours().collect();
+
+        assert_eq!(range.total_hours(), 17 + 17);
+        assert_eq!(hours.len(), range.total_hours());
+        assert_eq!(hours[0].hour(), 7);
+        assert_eq!(hours.last().unwrap().hour(), 16);
+        assert_eq!(range.to_string(), "2024-01-01 07:00 to 2024-01-02 16:00");
+    }
+
+    #[test]
+    fn test_invalid_hours() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let range = DateRange::single_day(day);
+        assert!(range.with_hours(0, 24).is_err());
+        assert!(range.with_hours(16, 7).is_err());
+        assert_eq!(range.with_hours(7, 16).unwrap().total_hours(), 10);
+    }
+
+    #[test]
+    fn test_forex_weekend() {
+        // 2024-01-13 is a Saturday.
+        let sat = Utc.with_ymd_and_hms(2024, 1, 13, 12, 0, 0).unwrap();
+        let sun_early = Utc.with_ymd_and_hms(2024, 1, 14, 19, 0, 0).unwrap();
+        let sun_open = Utc.with_ymd_and_hms(2024, 1, 14, 20, 0, 0).unwrap();
+        let fri_late = Utc.with_ymd_and_hms(2024, 1, 12, 23, 0, 0).unwrap();
+
+        assert!(is_forex_weekend(sat));
+        assert!(is_forex_weekend(sun_early));
+        assert!(!is_forex_weekend(sun_open));
+        assert!(!is_forex_weekend(fri_late));
+
+        let week = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 1, 8).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 14).unwrap(),
+        )
+        .unwrap();
+        let open = week.hours().filter(|h| !is_forex_weekend(*h)).count();
+        assert_eq!(open, 7 * 24 - 24 - 20);
+    }
+
     #[test]
     fn test_hour_from_url() {
         let url = "https://datafeed.dukascopy.com/datafeed/EURUSD/2024/00/15/12h_ticks.bi5";
@@ -60,4 +60,17 @@ pub enum DateRangeError {
         /// The end date.
         end: NaiveDate,
     },
+
+    /// Hour is outside 0-23.
+    #[error("Invalid hour: {0} (expected 0-23)")]
+    InvalidHour(u32),
+
+    /// Start hour is after end hour on a single-day range.
+    #[error("Invalid hour range: {start_hour:02}:00 > {end_hour:02}:00 on the same day")]
+    InvalidHourRange {
+        /// The start hour.
+        start_hour: u32,
+        /// The end hour.
+        end_hour: u32,
+    },
 }
@@ -0,0 +1,59 @@
+//! Policies for bar periods without ticks.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// What to emit for bar periods in which no tick arrived.
+///
+/// Filling gaps gives a regular index with one bar per period between the
+/// first and last bar, as many time-series libraries expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GapFill {
+    /// Emit nothing, leaving gaps in the index.
+    #[default]
+    Skip,
+    /// Emit a flat bar at the previous close with zero volume and ticks.
+    Flat,
+    /// Emit a marker bar with missing (NaN) prices and zero volume and ticks.
+    Null,
+}
+
+impl GapFill {
+    /// Returns the policy as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Flat => "flat",
+            Self::Null => "null",
+        }
+    }
+
+    /// Returns true if gaps are filled.
+    #[must_use]
+    pub const fn fills(&self) -> bool {
+        !matches!(self, Self::Skip)
+    }
+}
+
+impl std::fmt::Display for GapFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for GapFill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" | "none" => Ok(Self::Skip),
+            "flat" | "ffill" | "previous" => Ok(Self::Flat),
+            "null" | "nan" => Ok(Self::Null),
+            _ => Err(format!(
+                "invalid gap fill '{s}', expected one of: skip, flat, null"
+            )),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Rolling indicators computed over bars.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A rolling indicator added as an extra column next to each bar.
+///
+/// Written as the indicator name and its period, e.g. `sma_20` or `atr_14`;
+/// parsing also accepts `sma20` and `sma:20`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum Indicator {
+    /// Simple moving average of the close over the period.
+    Sma(usize),
+    /// Exponential moving average of the close, seeded with the simple
+    /// average of the first period.
+    Ema(usize),
+    /// Average true range, with Wilder's smoothing.
+    Atr(usize),
+    /// Standard deviation of the log returns of the close over the period,
+    /// not annualized.
+    Volatility(usize),
+}
+
+impl Indicator {
+    /// Returns the indicator name without the period.
+    #[must_use]
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Sma(_) => "sma",
+            Self::Ema(_) => "ema",
+            Self::Atr(_) => "atr",
+            Self::Volatility(_) => "volatility",
+        }
+    }
+
+    /// Returns the number of bars the indicator looks back over.
+    #[must_use]
+    pub const fn period(&self) -> usize {
+        match self {
+            Self::Sma(period)
+            | Self::Ema(period)
+            | Self::Atr(period)
+            | Self::Volatility(period) => *period,
+        }
+    }
+}
+
+impl std::fmt::Display for Indicator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}_{}", self.kind(), self.period())
+    }
+}
+
+impl FromStr for Indicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let split = lower
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(|| format!("indicator '{s}' has no period, e.g. sma_20"))?;
+        let (name, period) = lower.split_at(split);
+        let period: usize = period
+            .parse()
+            .ok()
+            .filter(|period| *period > 0)
+            .ok_or_else(|| format!("invalid period in indicator '{s}'"))?;
+        match name.trim_end_matches(['_', ':']) {
+            "sma" => Ok(Self::Sma(period)),
+            "ema" => Ok(Self::Ema(period)),
+            "atr" => Ok(Self::Atr(period)),
+            "volatility" | "vol" => Ok(Self::Volatility(period)),
+            _ => Err(format!(
+                "invalid indicator '{s}', expected one of: sma, ema, atr, volatility"
+            )),
+        }
+    }
+}
+
+impl From<Indicator> for String {
+    fn from(indicator: Indicator) -> Self {
+        indicator.to_string()
+    }
+}
+
+impl TryFrom<String> for Indicator {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indicator_round_trip() {
+        assert_eq!("SMA20".parse(), Ok(Indicator::Sma(20)));
+        assert_eq!("ema:50".parse(), Ok(Indicator::Ema(50)));
+        assert_eq!("vol_10".parse(), Ok(Indicator::Volatility(10)));
+        assert_eq!(Indicator::Atr(14).to_string(), "atr_14");
+        assert_eq!(
+            serde_json::from_str::<Indicator>("\"atr_14\"").unwrap(),
+            Indicator::Atr(14)
+        );
+        assert!("sma".parse::<Indicator>().is_err());
+        assert!("sma_0".parse::<Indicator>().is_err());
+        assert!("rsi_14".parse::<Indicator>().is_err());
+    }
+}
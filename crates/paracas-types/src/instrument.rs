@@ -3,6 +3,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::TradingCalendar;
+
 /// Instrument category.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -37,6 +39,20 @@ impl Category {
             Self::Bond => "bond",
         }
     }
+
+    /// Returns all categories.
+    #[must_use]
+    pub const fn all() -> &'static [Self] {
+        &[
+            Self::Forex,
+            Self::Crypto,
+            Self::Index,
+            Self::Stock,
+            Self::Commodity,
+            Self::Etf,
+            Self::Bond,
+        ]
+    }
 }
 
 impl std::fmt::Display for Category {
@@ -60,6 +76,9 @@ pub struct Instrument {
     decimal_factor: u32,
     /// Earliest available tick data timestamp.
     start_tick_date: Option<DateTime<Utc>>,
+    /// Trading calendar, overriding the category default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    calendar: Option<TradingCalendar>,
 }
 
 impl Instrument {
@@ -80,9 +99,17 @@ impl Instrument {
             category,
             decimal_factor,
             start_tick_date,
+            calendar: None,
         }
     }
 
+    /// Sets the trading calendar, overriding the category default.
+    #[must_use]
+    pub const fn with_calendar(mut self, calendar: TradingCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
     /// Returns the instrument identifier.
     #[must_use]
     pub fn id(&self) -> &str {
@@ -107,6 +134,18 @@ impl Instrument {
         self.category
     }
 
+    /// Returns the trading calendar of the instrument.
+    ///
+    /// Falls back to [`TradingCalendar::for_category`] unless a calendar was
+    /// set explicitly.
+    #[must_use]
+    pub const fn calendar(&self) -> TradingCalendar {
+        match self.calendar {
+            Some(calendar) => calendar,
+            None => TradingCalendar::for_category(self.category),
+        }
+    }
+
     /// Returns the decimal factor for price normalization.
     #[must_use]
     pub const fn decimal_factor(&self) -> u32 {
@@ -119,6 +158,41 @@ impl Instrument {
         f64::from(self.decimal_factor)
     }
 
+    /// Returns the size of one pip in price units.
+    ///
+    /// See [`Self::pip_size_for`].
+    #[must_use]
+    pub fn pip_size(&self) -> f64 {
+        Self::pip_size_for(self.decimal_factor)
+    }
+
+    /// Returns the size of one pip for prices with the given decimal factor.
+    ///
+    /// A pip is ten price increments of `1 / decimal_factor`, e.g. 0.0001 for
+    /// EUR/USD quoted to five decimals and 0.01 for USD/JPY quoted to three.
+    #[must_use]
+    pub fn pip_size_for(decimal_factor: u32) -> f64 {
+        10.0 / f64::from(decimal_factor)
+    }
+
+    /// Returns the number of decimal places of quoted prices.
+    ///
+    /// See [`Self::price_digits_for`].
+    #[must_use]
+    pub const fn price_digits(&self) -> u32 {
+        Self::price_digits_for(self.decimal_factor)
+    }
+
+    /// Returns the number of decimal places of prices with the given decimal
+    /// factor, e.g. 5 for EUR/USD (100000) and 3 for USD/JPY (1000).
+    #[must_use]
+    pub const fn price_digits_for(decimal_factor: u32) -> u32 {
+        match decimal_factor.checked_ilog10() {
+            Some(digits) => digits,
+            None => 0,
+        }
+    }
+
     /// Returns the earliest available tick data timestamp.
     #[must_use]
     pub const fn start_tick_date(&self) -> Option<DateTime<Utc>> {
@@ -188,6 +262,8 @@ mod tests {
         assert_eq!(instrument.id(), "eurusd");
         assert_eq!(instrument.name(), "EUR/USD");
         assert_eq!(instrument.decimal_factor(), 100_000);
+        assert!((instrument.pip_size() - 0.0001).abs() < 1e-12);
+        assert_eq!(instrument.price_digits(), 5);
         assert!(instrument.is_forex());
         assert!(!instrument.is_crypto());
     }
@@ -6,7 +6,14 @@
 //! - [`RawTick`] - Raw tick from bi5 binary format before price normalization
 //! - [`Instrument`] - Financial instrument with metadata
 //! - [`Timeframe`] - OHLCV aggregation timeframe
+//! - [`PriceSource`] - Bid, ask or mid prices for bars
+//! - [`GapFill`] - What to emit for bar periods without ticks
+//! - [`CandleStyle`] - Plain or Heikin-Ashi candles
+//! - [`Indicator`] - Rolling indicator written next to each bar
 //! - [`DateRange`] - Date range for data retrieval
+//! - [`TradingCalendar`] - Hours in which an instrument trades
+//! - [`TimestampPrecision`] - Sub-second resolution for timestamp output
+//! - [`ParquetSettings`] - Compression and encoding of Parquet output
 
 #![doc = include_str!("../README.md")]
 #![doc(issue_tracker_base_url = "https://github.com/factordynamics/paracas/issues/")]
@@ -14,14 +21,28 @@
 #![warn(missing_docs)]
 #![forbid(unsafe_code)]
 
+mod calendar;
+mod candle;
 mod date_range;
 mod error;
+mod gap;
+mod indicator;
 mod instrument;
+mod parquet;
+mod price;
 mod tick;
 mod timeframe;
+mod timestamp;
 
-pub use date_range::{DateRange, HourIterator, hour_from_url};
+pub use calendar::TradingCalendar;
+pub use candle::CandleStyle;
+pub use date_range::{DateRange, HourIterator, hour_from_url, is_forex_weekend};
 pub use error::{DateRangeError, ParacasError, Result};
+pub use gap::GapFill;
+pub use indicator::Indicator;
 pub use instrument::{Category, Instrument};
+pub use parquet::{ParquetCodec, ParquetSettings, ParquetStatistics, PriceEncoding};
+pub use price::PriceSource;
 pub use tick::{RawTick, Tick};
-pub use timeframe::{Timeframe, TimeframeParseError};
+pub use timeframe::{CustomDuration, Timeframe, TimeframeParseError};
+pub use timestamp::TimestampPrecision;
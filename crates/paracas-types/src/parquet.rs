@@ -0,0 +1,261 @@
+//! Parquet writer tuning.
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::TimestampPrecision;
+
+/// Compression codec of Parquet data pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetCodec {
+    /// No compression.
+    Uncompressed,
+    /// Snappy: fast, widely supported, larger files.
+    #[default]
+    Snappy,
+    /// Gzip, levels 0-9.
+    Gzip,
+    /// LZ4 (raw), very fast decompression.
+    Lz4,
+    /// Zstandard, levels 1-22: typically 2-3x smaller than Snappy.
+    Zstd,
+    /// Brotli, levels 0-11.
+    Brotli,
+}
+
+impl ParquetCodec {
+    /// Returns the codec as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Uncompressed => "uncompressed",
+            Self::Snappy => "snappy",
+            Self::Gzip => "gzip",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+            Self::Brotli => "brotli",
+        }
+    }
+
+    /// Returns true if the codec takes a compression level.
+    #[must_use]
+    pub const fn has_levels(&self) -> bool {
+        matches!(self, Self::Gzip | Self::Zstd | Self::Brotli)
+    }
+}
+
+impl std::fmt::Display for ParquetCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ParquetCodec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uncompressed" | "none" => Ok(Self::Uncompressed),
+            "snappy" => Ok(Self::Snappy),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "lz4" => Ok(Self::Lz4),
+            "zstd" | "zst" => Ok(Self::Zstd),
+            "brotli" => Ok(Self::Brotli),
+            _ => Err(format!(
+                "invalid parquet codec '{s}', expected one of: uncompressed, snappy, gzip, lz4, zstd, brotli"
+            )),
+        }
+    }
+}
+
+/// Column statistics written to Parquet files, which readers use to skip
+/// row groups and pages outside a filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ParquetStatistics {
+    /// No statistics.
+    None,
+    /// Statistics per column chunk (row group).
+    Chunk,
+    /// Statistics per column chunk and per data page.
+    #[default]
+    Page,
+}
+
+impl ParquetStatistics {
+    /// Returns the level as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Chunk => "chunk",
+            Self::Page => "page",
+        }
+    }
+}
+
+impl std::fmt::Display for ParquetStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ParquetStatistics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "chunk" => Ok(Self::Chunk),
+            "page" => Ok(Self::Page),
+            _ => Err(format!(
+                "invalid parquet statistics '{s}', expected one of: none, chunk, page"
+            )),
+        }
+    }
+}
+
+/// How price columns are stored in Parquet files.
+///
+/// Prices are multiples of `1 / decimal_factor`, so the exact encodings
+/// avoid float rounding and compress far better than floats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceEncoding {
+    /// 64-bit floats.
+    #[default]
+    Float,
+    /// The raw 64-bit integers of the source feed, prices times the
+    /// decimal factor.
+    Integer,
+    /// Arrow `Decimal128` with the decimal factor's number of digits as
+    /// scale.
+    Decimal,
+}
+
+impl PriceEncoding {
+    /// Returns the encoding as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Float => "float",
+            Self::Integer => "integer",
+            Self::Decimal => "decimal",
+        }
+    }
+}
+
+impl std::fmt::Display for PriceEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PriceEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "float" | "f64" => Ok(Self::Float),
+            "integer" | "int" | "raw" => Ok(Self::Integer),
+            "decimal" => Ok(Self::Decimal),
+            _ => Err(format!(
+                "invalid price encoding '{s}', expected one of: float, integer, decimal"
+            )),
+        }
+    }
+}
+
+/// Writer options for Parquet output.
+///
+/// The defaults match the Parquet writer's own: Snappy pages, dictionary
+/// encoding, page statistics and 1 MiB pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParquetSettings {
+    /// Compression codec of data pages.
+    pub codec: ParquetCodec,
+    /// Codec level, or the codec's default level if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+    /// Dictionary-encode columns, which shrinks columns with few distinct
+    /// values.
+    pub dictionary: bool,
+    /// Target size of data pages in bytes, or the writer default if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_page_size: Option<usize>,
+    /// Column statistics written.
+    pub statistics: ParquetStatistics,
+    /// Declare the timestamp column as the sort order of each row group
+    /// when the rows are in timestamp order.
+    pub sorting_columns: bool,
+    /// Storage of the price columns.
+    pub price_encoding: PriceEncoding,
+    /// Resolution of timestamp columns. Some engines, such as older Spark,
+    /// only read milliseconds; others join on nanoseconds.
+    pub timestamp_precision: TimestampPrecision,
+    /// Time zone readers display timestamps in. Values are UTC instants
+    /// whatever the zone.
+    pub timezone: Tz,
+}
+
+impl Default for ParquetSettings {
+    fn default() -> Self {
+        Self {
+            codec: ParquetCodec::default(),
+            level: None,
+            dictionary: true,
+            data_page_size: None,
+            statistics: ParquetStatistics::default(),
+            sorting_columns: false,
+            price_encoding: PriceEncoding::default(),
+            timestamp_precision: TimestampPrecision::Microsecond,
+            timezone: Tz::UTC,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("ZSTD".parse::<ParquetCodec>(), Ok(ParquetCodec::Zstd));
+        assert_eq!(
+            "none".parse::<ParquetCodec>(),
+            Ok(ParquetCodec::Uncompressed)
+        );
+        assert!("lzo".parse::<ParquetCodec>().is_err());
+        assert!(ParquetCodec::Zstd.has_levels());
+        assert!(!ParquetCodec::Snappy.has_levels());
+        assert_eq!(
+            "chunk".parse::<ParquetStatistics>(),
+            Ok(ParquetStatistics::Chunk)
+        );
+        assert_eq!("raw".parse::<PriceEncoding>(), Ok(PriceEncoding::Integer));
+    }
+
+    #[test]
+    fn test_settings_serde_defaults() {
+        let settings: ParquetSettings = serde_json::from_str(r#"{"codec":"zstd"}"#).unwrap();
+        assert_eq!(settings.codec, ParquetCodec::Zstd);
+        assert!(settings.dictionary);
+        assert_eq!(settings.statistics, ParquetStatistics::Page);
+        assert_eq!(
+            settings.timestamp_precision,
+            TimestampPrecision::Microsecond
+        );
+
+        let settings: ParquetSettings =
+            serde_json::from_str(r#"{"timestamp_precision":"ms","timezone":"Europe/Berlin"}"#)
+                .unwrap();
+        assert_eq!(
+            settings.timestamp_precision,
+            TimestampPrecision::Millisecond
+        );
+        assert_eq!(settings.timezone, Tz::Europe__Berlin);
+    }
+}
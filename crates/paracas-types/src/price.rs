@@ -0,0 +1,84 @@
+//! Choice of tick price used to build bars.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Which side of a tick's quote prices a bar.
+///
+/// Dukascopy's own candles and most backtesting platforms use bid prices;
+/// mid prices are the average of bid and ask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    /// The bid price.
+    Bid,
+    /// The ask price.
+    Ask,
+    /// The average of bid and ask.
+    #[default]
+    Mid,
+}
+
+impl PriceSource {
+    /// Returns the price source as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bid => "bid",
+            Self::Ask => "ask",
+            Self::Mid => "mid",
+        }
+    }
+
+    /// Returns all price sources.
+    #[must_use]
+    pub const fn all() -> &'static [Self] {
+        &[Self::Bid, Self::Ask, Self::Mid]
+    }
+}
+
+impl std::fmt::Display for PriceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PriceSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bid" => Ok(Self::Bid),
+            "ask" => Ok(Self::Ask),
+            "mid" | "middle" => Ok(Self::Mid),
+            _ => Err(format!(
+                "invalid price source '{s}', expected one of: bid, ask, mid"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tick;
+    use chrono::Utc;
+
+    #[test]
+    fn test_tick_price() {
+        let tick = Tick::new(Utc::now(), 1.1002, 1.1000, 1.0, 1.0);
+        assert!((tick.price(PriceSource::Bid) - 1.1000).abs() < 1e-12);
+        assert!((tick.price(PriceSource::Ask) - 1.1002).abs() < 1e-12);
+        assert!((tick.price(PriceSource::Mid) - 1.1001).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_parse_and_serde() {
+        assert_eq!("BID".parse(), Ok(PriceSource::Bid));
+        assert!("last".parse::<PriceSource>().is_err());
+        assert_eq!(serde_json::to_string(&PriceSource::Ask).unwrap(), "\"ask\"");
+        for source in PriceSource::all() {
+            assert_eq!(source.as_str().parse(), Ok(*source));
+        }
+    }
+}
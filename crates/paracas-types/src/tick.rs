@@ -3,7 +3,12 @@
 use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::{PriceSource, TimestampPrecision};
+
 /// A single tick representing a price update.
+///
+/// Timestamps keep full nanosecond resolution even though Dukascopy data is
+/// only millisecond-precise, so finer-grained sources round-trip unchanged.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Tick {
     /// Timestamp of the tick (UTC).
@@ -43,6 +48,16 @@ impl Tick {
         (self.ask + self.bid) / 2.0
     }
 
+    /// Returns the bid, ask or mid price.
+    #[must_use]
+    pub fn price(&self, source: PriceSource) -> f64 {
+        match source {
+            PriceSource::Bid => self.bid,
+            PriceSource::Ask => self.ask,
+            PriceSource::Mid => self.mid(),
+        }
+    }
+
     /// Returns the spread (ask - bid).
     #[must_use]
     pub fn spread(&self) -> f64 {
@@ -54,6 +69,30 @@ impl Tick {
     pub fn total_volume(&self) -> f32 {
         self.ask_volume + self.bid_volume
     }
+
+    /// Returns the timestamp as nanoseconds since the Unix epoch.
+    #[must_use]
+    pub fn timestamp_nanos(&self) -> i64 {
+        TimestampPrecision::Nanosecond.to_epoch(self.timestamp)
+    }
+
+    /// Creates a tick from a timestamp in nanoseconds since the Unix epoch.
+    #[must_use]
+    pub const fn from_epoch_nanos(
+        nanos: i64,
+        ask: f64,
+        bid: f64,
+        ask_volume: f32,
+        bid_volume: f32,
+    ) -> Self {
+        Self::new(
+            DateTime::from_timestamp_nanos(nanos),
+            ask,
+            bid,
+            ask_volume,
+            bid_volume,
+        )
+    }
 }
 
 /// Raw tick as read from bi5 file (before price normalization).
@@ -135,6 +174,17 @@ mod tests {
         assert!((tick.spread() - 0.0001).abs() < 1e-10);
     }
 
+    #[test]
+    fn test_tick_nanosecond_round_trip() {
+        let nanos = 1_705_321_845_123_456_789;
+        let tick = Tick::from_epoch_nanos(nanos, 1.1001, 1.1000, 100.0, 200.0);
+        assert_eq!(tick.timestamp_nanos(), nanos);
+
+        let json = serde_json::to_string(&tick).unwrap();
+        let decoded: Tick = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.timestamp_nanos(), nanos);
+    }
+
     #[test]
     fn test_raw_tick_normalize() {
         let hour_start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
@@ -1,42 +1,94 @@
 //! OHLCV aggregation timeframe definitions.
 
 use serde::{Deserialize, Serialize};
+use std::num::NonZeroU64;
 use std::str::FromStr;
+use std::time::Duration;
+
+/// Seconds per unit suffix of custom timeframes, largest first.
+const UNITS: [(char, u64); 4] = [('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)];
 
 /// OHLCV aggregation timeframe.
+///
+/// Serializes as its string identifier, e.g. `"m5"` or `"90s"`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
-#[serde(rename_all = "lowercase")]
+#[serde(into = "String", try_from = "String")]
 pub enum Timeframe {
     /// Tick-by-tick (no aggregation).
     #[default]
     Tick,
     /// 1-second bars.
-    #[serde(rename = "s1")]
     Second1,
     /// 1-minute bars.
-    #[serde(rename = "m1")]
     Minute1,
     /// 5-minute bars.
-    #[serde(rename = "m5")]
     Minute5,
     /// 15-minute bars.
-    #[serde(rename = "m15")]
     Minute15,
     /// 30-minute bars.
-    #[serde(rename = "m30")]
     Minute30,
     /// 1-hour bars.
-    #[serde(rename = "h1")]
     Hour1,
     /// 4-hour bars.
-    #[serde(rename = "h4")]
     Hour4,
     /// Daily bars.
-    #[serde(rename = "d1")]
     Day1,
+    /// Bars of any whole number of seconds, e.g. 90 seconds or 6 hours.
+    ///
+    /// Bars are aligned to the Unix epoch, which puts bar boundaries at
+    /// midnight UTC for durations that divide a day. Built with
+    /// [`Timeframe::from_duration`] or by parsing, which map durations of
+    /// the fixed timeframes to their variants.
+    Custom(CustomDuration),
+}
+
+/// Duration of [`Timeframe::Custom`] bars: a whole number of seconds, at
+/// least one, other than the duration of a fixed timeframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomDuration(NonZeroU64);
+
+impl CustomDuration {
+    /// Returns the duration in seconds.
+    #[must_use]
+    pub const fn seconds(&self) -> u64 {
+        self.0.get()
+    }
+
+    /// Returns the duration.
+    #[must_use]
+    pub const fn duration(&self) -> Duration {
+        Duration::from_secs(self.0.get())
+    }
 }
 
 impl Timeframe {
+    /// Returns the timeframe of bars lasting `duration`.
+    ///
+    /// Durations of the fixed timeframes map to their variants, others to
+    /// [`Timeframe::Custom`]. Returns `None` for durations shorter than a
+    /// second or with a fractional second.
+    #[must_use]
+    pub fn from_duration(duration: Duration) -> Option<Self> {
+        if duration.subsec_nanos() != 0 {
+            return None;
+        }
+        let seconds = NonZeroU64::new(duration.as_secs())?;
+        let fixed = Self::all()
+            .iter()
+            .find(|timeframe| timeframe.seconds() == Some(seconds.get()));
+        Some(
+            fixed
+                .copied()
+                .unwrap_or(Self::Custom(CustomDuration(seconds))),
+        )
+    }
+
+    /// Returns the duration of a bar, or None for tick data.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        self.seconds().map(Duration::from_secs)
+    }
+
     /// Returns the duration in seconds, or None for tick data.
     #[must_use]
     pub const fn seconds(&self) -> Option<u64> {
@@ -50,6 +102,7 @@ impl Timeframe {
             Self::Hour1 => Some(3600),
             Self::Hour4 => Some(14400),
             Self::Day1 => Some(86400),
+            Self::Custom(duration) => Some(duration.seconds()),
         }
     }
 
@@ -69,6 +122,9 @@ impl Timeframe {
     }
 
     /// Returns the timeframe as a string identifier.
+    ///
+    /// Custom timeframes return `"custom"`; their [`Display`](std::fmt::Display)
+    /// output is the full identifier, e.g. `"90s"`.
     #[must_use]
     pub const fn as_str(&self) -> &'static str {
         match self {
@@ -81,10 +137,13 @@ impl Timeframe {
             Self::Hour1 => "h1",
             Self::Hour4 => "h4",
             Self::Day1 => "d1",
+            Self::Custom(_) => "custom",
         }
     }
 
-    /// Returns all available timeframes.
+    /// Returns all fixed timeframes.
+    ///
+    /// [`Timeframe::Custom`] durations are not listed.
     #[must_use]
     pub const fn all() -> &'static [Self] {
         &[
@@ -103,7 +162,16 @@ impl Timeframe {
 
 impl std::fmt::Display for Timeframe {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.as_str())
+        let Self::Custom(duration) = self else {
+            return write!(f, "{}", self.as_str());
+        };
+        let seconds = duration.seconds();
+        // Largest unit dividing the duration, so 7200 seconds display as 2h
+        let (unit, size) = UNITS
+            .into_iter()
+            .find(|(_, size)| seconds % size == 0)
+            .unwrap_or(('s', 1));
+        write!(f, "{}{unit}", seconds / size)
     }
 }
 
@@ -121,11 +189,40 @@ impl FromStr for Timeframe {
             "h1" | "1h" | "hour" | "hour1" => Ok(Self::Hour1),
             "h4" | "4h" | "hour4" => Ok(Self::Hour4),
             "d1" | "1d" | "day" | "day1" | "daily" => Ok(Self::Day1),
-            _ => Err(TimeframeParseError(s.to_string())),
+            other => parse_duration(other)
+                .and_then(Self::from_duration)
+                .ok_or_else(|| TimeframeParseError(s.to_string())),
         }
     }
 }
 
+impl From<Timeframe> for String {
+    fn from(timeframe: Timeframe) -> Self {
+        timeframe.to_string()
+    }
+}
+
+impl TryFrom<String> for Timeframe {
+    type Error = TimeframeParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// Parses a duration written as a count and a unit (`s`, `m`, `h` or `d`)
+/// in either order, e.g. `90s`, `2h` or `h6`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let (count, unit) = match s.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((0, unit)) => (&s[unit.len_utf8()..], unit),
+        Some((index, unit)) if index + unit.len_utf8() == s.len() => (&s[..index], unit),
+        _ => return None,
+    };
+    let count: u64 = count.parse().ok()?;
+    let (_, size) = UNITS.into_iter().find(|(name, _)| *name == unit)?;
+    count.checked_mul(size).map(Duration::from_secs)
+}
+
 /// Error returned when parsing an invalid timeframe string.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TimeframeParseError(String);
@@ -134,7 +231,8 @@ impl std::fmt::Display for TimeframeParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "invalid timeframe '{}', expected one of: tick, s1, m1, m5, m15, m30, h1, h4, d1",
+            "invalid timeframe '{}', expected one of: tick, s1, m1, m5, m15, m30, h1, h4, d1, \
+             or a duration such as 90s or 2h",
             self.0
         )
     }
@@ -161,4 +259,32 @@ mod tests {
         assert_eq!("H4".parse::<Timeframe>().unwrap(), Timeframe::Hour4);
         assert!("invalid".parse::<Timeframe>().is_err());
     }
+
+    #[test]
+    fn test_custom_timeframe() {
+        let custom = |seconds| Timeframe::from_duration(Duration::from_secs(seconds)).unwrap();
+        assert!(matches!(custom(90), Timeframe::Custom(_)));
+        assert_eq!("90s".parse(), Ok(custom(90)));
+        assert_eq!("2h".parse(), Ok(custom(7200)));
+        assert_eq!("H6".parse(), Ok(custom(21_600)));
+        assert_eq!("60m".parse(), Ok(Timeframe::Hour1));
+        assert!("0s".parse::<Timeframe>().is_err());
+        assert!("2w".parse::<Timeframe>().is_err());
+        assert!("2h30m".parse::<Timeframe>().is_err());
+
+        assert_eq!(custom(90).to_string(), "90s");
+        assert_eq!(custom(7200).to_string(), "2h");
+        assert_eq!(custom(7200).seconds(), Some(7200));
+        assert_eq!(serde_json::to_string(&custom(120)).unwrap(), "\"2m\"");
+        assert_eq!(
+            serde_json::from_str::<Timeframe>("\"m15\"").unwrap(),
+            Timeframe::Minute15
+        );
+        assert_eq!(Timeframe::from_duration(Duration::from_millis(1500)), None);
+        assert_eq!(Timeframe::from_duration(Duration::from_millis(500)), None);
+        assert_eq!(
+            Timeframe::from_duration(Duration::from_secs(60)),
+            Some(Timeframe::Minute1)
+        );
+    }
 }
@@ -0,0 +1,165 @@
+//! Timestamp precision handling.
+
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Sub-second resolution used when rendering or storing timestamps.
+///
+/// Dukascopy ticks carry millisecond offsets, but ticks from other sources
+/// (or synthetic data) may be finer. [`Tick`](crate::Tick) timestamps are
+/// always stored with nanosecond resolution; this type controls how much of
+/// that resolution survives serialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum TimestampPrecision {
+    /// Millisecond resolution (Dukascopy source precision).
+    #[default]
+    #[serde(rename = "ms")]
+    Millisecond,
+    /// Microsecond resolution.
+    #[serde(rename = "us")]
+    Microsecond,
+    /// Nanosecond resolution.
+    #[serde(rename = "ns")]
+    Nanosecond,
+}
+
+impl TimestampPrecision {
+    /// Returns the number of fractional second digits.
+    #[must_use]
+    pub const fn digits(&self) -> u16 {
+        match self {
+            Self::Millisecond => 3,
+            Self::Microsecond => 6,
+            Self::Nanosecond => 9,
+        }
+    }
+
+    /// Returns the `strftime` pattern for an ISO 8601 UTC timestamp.
+    #[must_use]
+    pub const fn iso_format(&self) -> &'static str {
+        match self {
+            Self::Millisecond => "%Y-%m-%dT%H:%M:%S%.3fZ",
+            Self::Microsecond => "%Y-%m-%dT%H:%M:%S%.6fZ",
+            Self::Nanosecond => "%Y-%m-%dT%H:%M:%S%.9fZ",
+        }
+    }
+
+    /// Returns the timestamp as an integer offset from the Unix epoch.
+    ///
+    /// Nanosecond values saturate outside the range representable by `i64`
+    /// (roughly years 1677 to 2262).
+    #[must_use]
+    pub fn to_epoch(&self, timestamp: DateTime<Utc>) -> i64 {
+        match self {
+            Self::Millisecond => timestamp.timestamp_millis(),
+            Self::Microsecond => timestamp.timestamp_micros(),
+            Self::Nanosecond => timestamp.timestamp_nanos_opt().unwrap_or_else(|| {
+                if timestamp.timestamp() < 0 {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                }
+            }),
+        }
+    }
+
+    /// Truncates the timestamp to this precision.
+    #[must_use]
+    pub fn truncate(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        timestamp.trunc_subsecs(self.digits())
+    }
+
+    /// Returns the precision as a string identifier.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Millisecond => "ms",
+            Self::Microsecond => "us",
+            Self::Nanosecond => "ns",
+        }
+    }
+}
+
+impl std::fmt::Display for TimestampPrecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TimestampPrecision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ms" | "milli" | "millis" | "millisecond" => Ok(Self::Millisecond),
+            "us" | "micro" | "micros" | "microsecond" => Ok(Self::Microsecond),
+            "ns" | "nano" | "nanos" | "nanosecond" => Ok(Self::Nanosecond),
+            _ => Err(format!(
+                "invalid timestamp precision '{s}', expected one of: ms, us, ns"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeDelta, TimeZone};
+
+    fn sample() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 15, 12, 30, 45).unwrap() + TimeDelta::nanoseconds(123_456_789)
+    }
+
+    #[test]
+    fn test_iso_format() {
+        let ts = sample();
+        assert_eq!(
+            ts.format(TimestampPrecision::Millisecond.iso_format())
+                .to_string(),
+            "2024-01-15T12:30:45.123Z"
+        );
+        assert_eq!(
+            ts.format(TimestampPrecision::Nanosecond.iso_format())
+                .to_string(),
+            "2024-01-15T12:30:45.123456789Z"
+        );
+    }
+
+    #[test]
+    fn test_to_epoch() {
+        let ts = sample();
+        let secs = ts.timestamp();
+        assert_eq!(
+            TimestampPrecision::Millisecond.to_epoch(ts),
+            secs * 1_000 + 123
+        );
+        assert_eq!(
+            TimestampPrecision::Microsecond.to_epoch(ts),
+            secs * 1_000_000 + 123_456
+        );
+        assert_eq!(
+            TimestampPrecision::Nanosecond.to_epoch(ts),
+            secs * 1_000_000_000 + 123_456_789
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "ns".parse::<TimestampPrecision>().unwrap(),
+            TimestampPrecision::Nanosecond
+        );
+        assert!("seconds".parse::<TimestampPrecision>().is_err());
+    }
+
+    #[test]
+    fn test_serde_names() {
+        let json = serde_json::to_string(&TimestampPrecision::Microsecond).unwrap();
+        assert_eq!(json, "\"us\"");
+        assert_eq!(
+            serde_json::from_str::<TimestampPrecision>(&json).unwrap(),
+            TimestampPrecision::Microsecond
+        );
+    }
+}
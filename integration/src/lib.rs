@@ -0,0 +1,125 @@
+//! Fixture-driven integration tests for paracas.
+//!
+//! `fixtures/<instrument>/<YYYY-MM-DDTHH>.bi5` holds small bi5 archives, one
+//! per hour, in the Dukascopy wire format. An empty file is an hour without
+//! ticks, as served by the data feed. The tests in `tests/` drive them through
+//! the whole pipeline (decompress, parse, aggregate, format) and compare the
+//! output with golden files under `golden/`.
+//!
+//! After an intended change to the output, regenerate the golden files with
+//! `PARACAS_UPDATE_GOLDEN=1 cargo test -p paracas-integration` and review the
+//! diff.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use paracas_aggregate::{Ohlcv, TickAggregator};
+use paracas_fetch::{decompress_bi5, parse_ticks_into};
+use paracas_instruments::InstrumentRegistry;
+use paracas_types::{Tick, Timeframe};
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes [`assert_golden`] rewrite golden files.
+pub const UPDATE_GOLDEN_ENV: &str = "PARACAS_UPDATE_GOLDEN";
+
+/// A bi5 archive of one instrument hour.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    /// Start of the hour.
+    pub hour: DateTime<Utc>,
+    /// Path of the archive.
+    pub path: PathBuf,
+}
+
+/// Returns the root directory of the crate.
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// Returns the fixtures of `instrument`, ordered by hour.
+///
+/// # Panics
+///
+/// Panics if the fixture directory cannot be read or contains a file not
+/// named after its hour.
+#[must_use]
+pub fn fixtures(instrument: &str) -> Vec<Fixture> {
+    let dir = crate_dir().join("fixtures").join(instrument);
+    let entries =
+        std::fs::read_dir(&dir).unwrap_or_else(|e| panic!("Failed to read {}: {e}", dir.display()));
+    let mut fixtures: Vec<_> = entries
+        .map(|entry| {
+            let path = entry.expect("Failed to read fixture entry").path();
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+            let hour = NaiveDateTime::parse_from_str(&format!("{stem}:00"), "%Y-%m-%dT%H:%M")
+                .unwrap_or_else(|e| panic!("Bad fixture name {}: {e}", path.display()))
+                .and_utc();
+            Fixture { hour, path }
+        })
+        .collect();
+    fixtures.sort_by_key(|fixture| fixture.hour);
+    fixtures
+}
+
+/// Decompresses and parses every fixture of `instrument` into ticks.
+///
+/// # Panics
+///
+/// Panics if the instrument is unknown or a fixture fails to decode.
+#[must_use]
+pub fn load_ticks(instrument: &str) -> Vec<Tick> {
+    let decimal_factor = InstrumentRegistry::global()
+        .get(instrument)
+        .unwrap_or_else(|| panic!("Unknown instrument: {instrument}"))
+        .decimal_factor_f64();
+    let mut ticks = Vec::new();
+    for fixture in fixtures(instrument) {
+        let compressed = std::fs::read(&fixture.path).expect("Failed to read fixture");
+        if compressed.is_empty() {
+            continue;
+        }
+        let data = decompress_bi5(&compressed).expect("Failed to decompress fixture");
+        parse_ticks_into(&data, fixture.hour, decimal_factor, &mut ticks)
+            .expect("Failed to parse fixture");
+    }
+    ticks
+}
+
+/// Aggregates `ticks` into bars of `timeframe`.
+#[must_use]
+pub fn aggregate(ticks: &[Tick], timeframe: Timeframe) -> Vec<Ohlcv> {
+    let mut aggregator = TickAggregator::new(timeframe);
+    let mut bars: Vec<_> = ticks
+        .iter()
+        .filter_map(|tick| aggregator.process(*tick))
+        .collect();
+    bars.extend(aggregator.finish());
+    bars
+}
+
+/// Compares `actual` with the golden file `golden/<name>`.
+///
+/// With [`UPDATE_GOLDEN_ENV`] set, the golden file is rewritten instead.
+///
+/// # Panics
+///
+/// Panics if the output differs from the golden file or the file cannot be
+/// read or written.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = crate_dir().join("golden").join(name);
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        std::fs::create_dir_all(path.parent().expect("Golden file has a parent"))
+            .expect("Failed to create golden directory");
+        std::fs::write(&path, actual).expect("Failed to write golden file");
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {}: {e} (set {UPDATE_GOLDEN_ENV}=1 to create it)",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "Output differs from {} (set {UPDATE_GOLDEN_ENV}=1 to update)",
+        path.display()
+    );
+}
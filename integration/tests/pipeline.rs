@@ -0,0 +1,110 @@
+//! Golden-output tests over the bi5 fixtures.
+
+use arrow::csv::WriterBuilder;
+use bytes::Bytes;
+use paracas_format::{CsvFormatter, Formatter, JsonFormatter, ParquetFormatter};
+use paracas_integration::{aggregate, assert_golden, fixtures, load_ticks};
+use paracas_types::Timeframe;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+/// Instruments with fixtures.
+const INSTRUMENTS: &[&str] = &["eurusd", "btcusd"];
+
+/// Runs a fixture through the pipeline and renders it with `formatter`.
+fn render(formatter: &impl Formatter, instrument: &str, timeframe: Timeframe) -> Vec<u8> {
+    let ticks = load_ticks(instrument);
+    let mut out = Vec::new();
+    if timeframe.is_tick() {
+        formatter.write_ticks(&ticks, &mut out).unwrap();
+    } else {
+        formatter
+            .write_ohlcv(&aggregate(&ticks, timeframe), &mut out)
+            .unwrap();
+    }
+    out
+}
+
+/// Checks every instrument and timeframe against `golden/<instrument>/<timeframe>.<extension>`.
+fn check_all(extension: &str, render: impl Fn(&str, Timeframe) -> String) {
+    for instrument in INSTRUMENTS {
+        for timeframe in Timeframe::all() {
+            let name = format!("{instrument}/{timeframe}.{extension}");
+            assert_golden(&name, &render(instrument, *timeframe));
+        }
+    }
+}
+
+#[test]
+fn test_fixtures_decode() {
+    let eurusd = load_ticks("eurusd");
+    assert_eq!(fixtures("eurusd").len(), 3);
+    assert_eq!(eurusd.len(), 354);
+    assert!(eurusd.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert!(eurusd.iter().all(|tick| tick.ask > tick.bid));
+
+    // The trailing empty hour contributes no ticks
+    let btcusd = load_ticks("btcusd");
+    assert_eq!(fixtures("btcusd").len(), 2);
+    assert_eq!(btcusd.len(), 175);
+    assert!(btcusd.iter().all(|tick| tick.bid > 60_000.0));
+}
+
+#[test]
+fn test_streaming_decoder_matches() {
+    for instrument in INSTRUMENTS {
+        for fixture in fixtures(instrument) {
+            let compressed = std::fs::read(&fixture.path).unwrap();
+            if compressed.is_empty() {
+                continue;
+            }
+            let buffered: Vec<_> =
+                paracas_fetch::parse_ticks(&paracas_fetch::decompress_bi5(&compressed).unwrap())
+                    .unwrap()
+                    .collect();
+            let mut streamed = Vec::new();
+            paracas_fetch::decompress_ticks(&compressed, None, |tick| streamed.push(tick)).unwrap();
+            assert_eq!(buffered, streamed, "{}", fixture.path.display());
+        }
+    }
+}
+
+#[test]
+fn test_csv_golden() {
+    check_all("csv", |instrument, timeframe| {
+        String::from_utf8(render(&CsvFormatter::new(), instrument, timeframe)).unwrap()
+    });
+}
+
+#[test]
+fn test_json_golden() {
+    check_all("json", |instrument, timeframe| {
+        String::from_utf8(render(&JsonFormatter::new(), instrument, timeframe)).unwrap()
+    });
+}
+
+#[test]
+fn test_ndjson_golden() {
+    check_all("ndjson", |instrument, timeframe| {
+        String::from_utf8(render(&JsonFormatter::ndjson(), instrument, timeframe)).unwrap()
+    });
+}
+
+/// Parquet files are decoded and compared as CSV, so the golden files do
+/// not depend on the writer version embedded in the file.
+#[test]
+fn test_parquet_golden() {
+    check_all("parquet.csv", |instrument, timeframe| {
+        let parquet = render(&ParquetFormatter::new(), instrument, timeframe);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(parquet))
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        let mut writer = WriterBuilder::new().build(&mut out);
+        for batch in reader {
+            writer.write(&batch.unwrap()).unwrap();
+        }
+        drop(writer);
+        String::from_utf8(out).unwrap()
+    });
+}